@@ -1,10 +1,10 @@
 pub mod paths {
-    use std::{env, fs, path::Path, collections::HashMap};
+    use std::{env, fs, path::{Path, PathBuf}, collections::HashMap};
     use glob::{glob, glob_with, MatchOptions};
     use inquire::Text;
     use regex::Regex;
     use rayon::prelude::*;
-    use crate::{configs::config::{self, Artefacts}, ops::{get_files, file_ops::{self, log_msg}}};
+    use crate::{configs::config::{self, Artefacts}, ops::{get_files, file_ops::{self, log_msg}, source_ops::{self, DataSourceKind}}};
 
     pub fn check_art(artefacts: Vec<Artefacts>, data_source: &String, silent: bool, main_args: &config::MainArgs) -> HashMap<String, String> {
         let mut art_paths = HashMap::new();
@@ -218,7 +218,7 @@ pub mod paths {
         data_paths_clone    
     }
 
-    /// check if the collection has a base path that is the root
+    /// check if the collection has a base path that is the root of a Windows volume
     /// serves as a good way to check if the data source is a collection or a mounted drive
     /// 
     /// Args:
@@ -234,9 +234,63 @@ pub mod paths {
             },
             None => (),
         };
+        // a drive of a collection or another OS, which isn't read as a volume
+        if source_ops::detect(base_path).kind != DataSourceKind::WindowsVolume {
+            return Err(data_paths_clone);
+        }
         Ok(data_paths_clone)
     }
-    
+
+    /// Select the processing profile of the evidence, returning the configs and data source
+    /// to process with. The kind of data source is detected, so a collection is processed
+    /// from the folder holding its evidence, such as the `C` folder of KAPE or CyLR or the
+    /// `[root]` folder of UAC. A macOS volume, a Linux root or a folder of artefacts use the
+    /// configs of `config/profiles/<profile>` instead of the defaults, which target Windows
+    /// evidence, with the default main config kept when the profile has none. Configs the
+    /// user gave are kept, as is the data source given to them.
+    pub fn select_profile(config: &Path, artefacts_config: &Path, data_source: &String, main_args: &config::MainArgs) -> (PathBuf, PathBuf, String) {
+        let unchanged = (config.to_path_buf(), artefacts_config.to_path_buf(), data_source.to_string());
+        let detection = source_ops::detect(Path::new(data_source));
+        let (kind, root, evidence) = (detection.kind, detection.root.display(), &detection.evidence);
+        match kind {
+            DataSourceKind::Unknown => {
+                file_ops::log_msg(&main_args.out_log, format!("[-] The kind of data source wasn't recognised at {data_source}, having {evidence}"));
+                return unchanged;
+            },
+            DataSourceKind::DiskImage => {
+                file_ops::log_msg(&main_args.out_log, format!("[!] {kind} found at {root}, having {evidence}. Mount it to process its volume, or use whipped"));
+                return unchanged;
+            },
+            _ if detection.archived => {
+                file_ops::log_msg(&main_args.out_log, format!("[!] {kind} found at {root}, having {evidence}. Extract it to process it, or use whipped"));
+                return unchanged;
+            },
+            _ => file_ops::log_msg(&main_args.out_log, format!("[+] {kind} found at {root}, having {evidence}")),
+        }
+        let config_dir = main_args.tool_path.parent().unwrap_or(Path::new("")).join("config");
+        let is_default = |path: &Path| ["windows", "linux"].iter().any(|os| path.parent() == Some(config_dir.join(os).as_path()));
+        if !is_default(config) || !is_default(artefacts_config) {
+            file_ops::log_msg(&main_args.out_log, format!("[-] Keeping the configs given, processing the {kind} from {data_source}"));
+            return unchanged;
+        }
+        let Some(name) = detection.profile else {
+            file_ops::log_msg(&main_args.out_log, format!("[+] Processing with the default configs from {root}"));
+            return (config.to_path_buf(), artefacts_config.to_path_buf(), root.to_string());
+        };
+        let profile = config_dir.join("profiles").join(name);
+        let (profile_config, profile_artefacts) = (profile.join("main.yaml"), profile.join("artefacts.yaml"));
+        if !profile_artefacts.is_file() {
+            file_ops::log_msg(&main_args.out_log, format!("[!] The {name} profile wasn't found in {}", profile.display()));
+            return unchanged;
+        }
+        let profile_config = match profile_config.is_file() {
+            true => profile_config,
+            false => config.to_path_buf(),
+        };
+        file_ops::log_msg(&main_args.out_log, format!("[+] Processing with the {name} profile: {}", profile.display()));
+        (profile_config, profile_artefacts, root.to_string())
+    }
+
     fn check_art_access(filepath: &String, out_log: &Path) -> bool {
         match file_ops::check_access(&filepath) {
             Ok(_message) => {
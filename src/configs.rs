@@ -79,7 +79,42 @@ use struct_iterable::Iterable;
       pub silent: bool,
       pub out_log: PathBuf,
       pub multi_pb: MultiProgress,
-      pub collect: bool
+      pub collect: bool,
+      pub out_format: OutFormat,
+      /// YARA rule folders or files, comma separated, scanned with those of the config
+      pub yara_rules: String,
+      /// keep the timeline JSON lines with Parquet output, for the case steps run after it
+      pub keep_timeline: bool
+  }
+
+  /// Output format of the timeline and builtin parsers
+  #[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+  #[serde(rename_all = "lowercase")]
+  pub enum OutFormat {
+    /// JSONL for the timeline and CSV for the builtin parsers
+    #[default]
+    Native,
+    /// Parquet instead of the JSONL and CSV, which are removed at the end of the run
+    Parquet,
+    /// Parquet alongside the JSONL and CSV
+    Both,
+  }
+
+  impl OutFormat {
+    /// check if Parquet is written
+    pub fn parquet(&self) -> bool {
+      *self != OutFormat::Native
+    }
+  }
+
+  impl std::fmt::Display for OutFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      match self {
+        OutFormat::Native => write!(f, "native"),
+        OutFormat::Parquet => write!(f, "parquet"),
+        OutFormat::Both => write!(f, "both"),
+      }
+    }
   }
 
   // Set struct for setup args
@@ -103,7 +138,15 @@ use struct_iterable::Iterable;
     pub in_link: String,
     pub out_link: String,
     pub update: bool,
-    pub keep_evidence: bool
+    pub keep_evidence: bool,
+    #[serde(default)]
+    pub out_format: OutFormat,
+    #[serde(default)]
+    pub merge_timelines: bool,
+    #[serde(default)]
+    pub ioc_report: bool,
+    #[serde(default)]
+    pub yara_rules: String
   }
 
   // Set struct for whipped image args
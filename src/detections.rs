@@ -0,0 +1,2 @@
+pub mod sigma;
+pub mod sigma_rules;
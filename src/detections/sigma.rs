@@ -0,0 +1,530 @@
+/*
+Builtin Sigma engine. The Sigma rules of the folders given by `--rules` are evaluated
+over the events of the timeline and the records of the event logs parsed by EvtxECmd,
+so one rule set covers the registry, execution and filesystem artefacts as well as the
+event logs. The logsources and fields of the rules are mapped onto these records by
+config/sigma.yaml. The detections are written as JSONL with the rule and matched record,
+with a summary of the detections of each rule.
+*/
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use super::sigma_rules::{self, Level, LogSource, SigmaRecord, SigmaRule};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+use crate::timeline::builder::{self as timeline, arg_value, DateRange, TimelineEvent};
+use crate::timeline::mapping;
+
+/// The default mapping, used when config/sigma.yaml isn't found beside the tools folder
+pub const DEFAULT_MAPPING: &str = include_str!("../../config/sigma.yaml");
+const RULE_EXTENSIONS: &[&str] = &["yml", "yaml"];
+/// The statuses of the rules that are skipped unless given to `--status`
+const SKIPPED_STATUSES: &[&str] = &["deprecated", "unsupported"];
+/// The detections written of each rule by default, all are counted in the summary
+const DEFAULT_MAX_PER_RULE: usize = 1000;
+/// The records evaluated in parallel at a time
+const BATCH_RECORDS: usize = 10_000;
+/// The folder and name of the EvtxECmd output of the event logs
+const EVENT_LOG_FOLDER: &str = "EventLogs";
+const EVENT_LOG_FILE: &str = r"evtxecmd.*\.csv$";
+const SUMMARY_HEADER: [&str; 7] = ["Level", "RuleId", "Title", "Detections", "FirstSeen", "LastSeen", "RuleFile"];
+
+/// The mapping of the Sigma logsources onto the timeline and event logs
+#[derive(Debug, Clone, Deserialize)]
+pub struct SigmaMapping {
+    pub logsources: Vec<LogSourceMapping>,
+}
+
+/// The records of a Sigma logsource, with how its fields are read
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogSourceMapping {
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub product: String,
+    #[serde(default)]
+    pub service: String,
+    /// the timeline sources evaluated
+    #[serde(default)]
+    pub timeline: Vec<String>,
+    /// the event log channels evaluated
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// the event ids of the channels evaluated, all when empty
+    #[serde(default)]
+    pub event_ids: Vec<u32>,
+    /// the Sigma fields with the record fields tried for each
+    #[serde(default)]
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl SigmaMapping {
+    /// read the mapping from a YAML file
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Unable to open Sigma mapping {}", path.display()))?;
+        serde_yaml::from_reader(file)
+            .with_context(|| format!("Unable to parse Sigma mapping {}", path.display()))
+    }
+
+    /// the mapping that is built into wiskess
+    pub fn default_mapping() -> Self {
+        serde_yaml::from_str(DEFAULT_MAPPING).expect("The builtin Sigma mapping is valid")
+    }
+}
+
+impl LogSourceMapping {
+    /// check the rule's logsource is this one, where the rule gives its category,
+    /// product or service
+    pub fn applies(&self, logsource: &LogSource) -> bool {
+        let keys = [
+            (&logsource.category, &self.category),
+            (&logsource.product, &self.product),
+            (&logsource.service, &self.service),
+        ];
+        keys.iter().any(|(rule, _)| rule.is_some())
+            && keys.iter().all(|(rule, mapped)| rule.as_ref().is_none_or(|r| r.eq_ignore_ascii_case(mapped)))
+    }
+}
+
+/// Where a record is from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Timeline,
+    EventLog,
+}
+
+/// A record of the timeline or the event logs, with its fields in order
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub kind: RecordKind,
+    /// the timeline source or event log channel
+    pub source: String,
+    pub event_id: Option<u32>,
+    pub datetime: String,
+    pub host: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Record {
+    /// the record of a timeline event
+    pub fn from_event(event: TimelineEvent) -> Self {
+        let fields = vec![
+            ("datetime".to_string(), event.datetime.clone()),
+            ("timestamp_desc".to_string(), event.timestamp_desc),
+            ("source".to_string(), event.source.clone()),
+            ("message".to_string(), event.message),
+            ("host".to_string(), event.host.clone()),
+            ("user".to_string(), event.user),
+            ("path".to_string(), event.path),
+        ];
+        Record {
+            kind: RecordKind::Timeline,
+            source: event.source,
+            event_id: None,
+            datetime: event.datetime,
+            host: event.host,
+            fields: fields.into_iter().filter(|(_, v)| !v.is_empty()).collect(),
+        }
+    }
+
+    /// get a field, case-insensitive
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+/// A record read with the fields of a logsource's mapping
+struct MappedRecord<'a> {
+    record: &'a Record,
+    fields: &'a HashMap<String, Vec<String>>,
+}
+
+impl SigmaRecord for MappedRecord<'_> {
+    fn field(&self, name: &str) -> Option<&str> {
+        match self.fields.get(&name.to_lowercase()) {
+            Some(aliases) => aliases.iter().find_map(|a| self.record.get(a)),
+            None => self.record.get(name),
+        }
+    }
+
+    fn values(&self) -> Vec<&str> {
+        self.record.fields.iter().map(|(_, v)| v.as_str()).collect()
+    }
+}
+
+/// the named values of the event data in the Payload of EvtxECmd, which is the event's
+/// XML as JSON, i.e. `{"EventData":{"Data":[{"@Name":"Image","#text":"C:\\a.exe"}]}}`
+pub fn payload_fields(payload: &str) -> Vec<(String, String)> {
+    fn walk(value: &serde_json::Value, key: &str, fields: &mut Vec<(String, String)>) {
+        match value {
+            serde_json::Value::Object(object) => {
+                match (object.get("@Name").and_then(|n| n.as_str()), object.get("#text")) {
+                    (Some(name), Some(text)) => walk(text, name, fields),
+                    (Some(_), None) => (),
+                    _ => object.iter()
+                        .filter(|(k, _)| !k.starts_with('@') && !k.starts_with('#'))
+                        .for_each(|(k, v)| walk(v, k, fields)),
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, key, fields)),
+            serde_json::Value::String(text) => fields.push((key.to_string(), text.clone())),
+            serde_json::Value::Null => (),
+            other => fields.push((key.to_string(), other.to_string())),
+        }
+    }
+    let mut fields = Vec::new();
+    if let Ok(value) = serde_json::from_str(payload) {
+        walk(&value, "", &mut fields);
+    }
+    fields.retain(|(k, v)| !k.is_empty() && !v.is_empty());
+    fields
+}
+
+/// read the records of an EvtxECmd CSV in the case's date range, calling `each` for
+/// every record
+pub fn read_event_logs(file: &Path, range: &DateRange, mut each: impl FnMut(Record) -> Result<()>) -> Result<()> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true)
+        .from_path(file)
+        .with_context(|| format!("Unable to open {}", file.display()))?;
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim_start_matches('\u{feff}').to_string()).collect();
+    for row in reader.records().map_while(|r| r.ok()) {
+        let mut fields: Vec<(String, String)> = Vec::new();
+        for (header, value) in headers.iter().zip(row.iter()) {
+            match header.as_str() {
+                "Payload" => fields.extend(payload_fields(value)),
+                _ if !value.is_empty() => fields.push((header.clone(), value.to_string())),
+                _ => (),
+            }
+        }
+        let record = Record {
+            kind: RecordKind::EventLog,
+            source: String::new(),
+            event_id: None,
+            datetime: String::new(),
+            host: String::new(),
+            fields,
+        };
+        let dt = record.get("TimeCreated").and_then(|t| mapping::parse_time(t, ""));
+        if dt.is_some_and(|dt| !range.contains(&dt)) {
+            continue;
+        }
+        let record = Record {
+            source: record.get("Channel").unwrap_or_default().to_string(),
+            event_id: record.get("EventId").and_then(|id| id.trim().parse().ok()),
+            datetime: common::fmt_dt(dt),
+            host: record.get("Computer").unwrap_or_default().to_string(),
+            ..record
+        };
+        each(record)?;
+    }
+    Ok(())
+}
+
+/// A logsource of the mapping with the rules evaluated over its records
+struct Entry {
+    fields: HashMap<String, Vec<String>>,
+    event_ids: HashSet<u32>,
+    rules: Vec<usize>,
+}
+
+/// The rules with the logsources they are evaluated with, by timeline source and channel
+pub struct SigmaEngine {
+    pub rules: Vec<SigmaRule>,
+    entries: Vec<Entry>,
+    by_timeline: HashMap<String, Vec<usize>>,
+    by_channel: HashMap<String, Vec<usize>>,
+}
+
+impl SigmaEngine {
+    /// map the rules onto the logsources, returning the engine and the rules of
+    /// logsources that aren't mapped, which are left out
+    pub fn new(rules: Vec<SigmaRule>, mapping: &SigmaMapping) -> (Self, Vec<SigmaRule>) {
+        let (rules, unmapped): (Vec<SigmaRule>, Vec<SigmaRule>) = rules.into_iter()
+            .partition(|r| mapping.logsources.iter().any(|l| l.applies(&r.logsource)));
+        let mut engine = SigmaEngine { rules, entries: Vec::new(), by_timeline: HashMap::new(), by_channel: HashMap::new() };
+        for logsource in &mapping.logsources {
+            let rules: Vec<usize> = engine.rules.iter().enumerate()
+                .filter(|(_, r)| logsource.applies(&r.logsource))
+                .map(|(i, _)| i)
+                .collect();
+            if rules.is_empty() {
+                continue;
+            }
+            let index = engine.entries.len();
+            engine.entries.push(Entry {
+                fields: logsource.fields.iter().map(|(k, v)| (k.to_lowercase(), v.clone())).collect(),
+                event_ids: logsource.event_ids.iter().copied().collect(),
+                rules,
+            });
+            for source in &logsource.timeline {
+                engine.by_timeline.entry(source.to_lowercase()).or_default().push(index);
+            }
+            for channel in &logsource.channels {
+                engine.by_channel.entry(channel.to_lowercase()).or_default().push(index);
+            }
+        }
+        (engine, unmapped)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// the indexes of the rules matching the record
+    pub fn detect(&self, record: &Record) -> Vec<usize> {
+        let entries = match record.kind {
+            RecordKind::Timeline => self.by_timeline.get(&record.source.to_lowercase()),
+            RecordKind::EventLog => self.by_channel.get(&record.source.to_lowercase()),
+        };
+        let mut matched = Vec::new();
+        for entry in entries.into_iter().flatten().map(|e| &self.entries[*e]) {
+            if !entry.event_ids.is_empty() && !record.event_id.is_some_and(|id| entry.event_ids.contains(&id)) {
+                continue;
+            }
+            let mapped = MappedRecord { record, fields: &entry.fields };
+            for rule in &entry.rules {
+                if !matched.contains(rule) && self.rules[*rule].matches(&mapped) {
+                    matched.push(*rule);
+                }
+            }
+        }
+        matched
+    }
+}
+
+/// A record matching a rule
+#[derive(Debug, Clone, Serialize)]
+pub struct Detection {
+    pub datetime: String,
+    pub rule_id: String,
+    pub title: String,
+    pub level: Level,
+    pub status: String,
+    pub tags: Vec<String>,
+    pub rule_file: String,
+    pub source: String,
+    pub host: String,
+    pub record: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The detections of a rule
+#[derive(Debug, Clone, Default)]
+struct RuleSummary {
+    detections: usize,
+    first: String,
+    last: String,
+}
+
+/// The detections of the records, with those beyond the limit of each rule only counted
+struct Detections {
+    max_per_rule: usize,
+    written: Vec<Detection>,
+    summaries: Vec<RuleSummary>,
+}
+
+impl Detections {
+    fn add_batch(&mut self, engine: &SigmaEngine, batch: &mut Vec<Record>) {
+        let matched: Vec<(usize, Vec<usize>)> = batch.par_iter()
+            .map(|record| engine.detect(record))
+            .enumerate()
+            .filter(|(_, rules)| !rules.is_empty())
+            .collect();
+        for (index, rules) in matched {
+            let record = &batch[index];
+            for r in rules {
+                let summary = &mut self.summaries[r];
+                summary.detections += 1;
+                if summary.first.is_empty() || (!record.datetime.is_empty() && record.datetime < summary.first) {
+                    summary.first = record.datetime.clone();
+                }
+                if record.datetime > summary.last {
+                    summary.last = record.datetime.clone();
+                }
+                if summary.detections > self.max_per_rule {
+                    continue;
+                }
+                let rule = &engine.rules[r];
+                self.written.push(Detection {
+                    datetime: record.datetime.clone(),
+                    rule_id: rule.id.clone(),
+                    title: rule.title.clone(),
+                    level: rule.level,
+                    status: rule.status.clone(),
+                    tags: rule.tags.clone(),
+                    rule_file: rule.file.clone(),
+                    source: record.source.clone(),
+                    host: record.host.clone(),
+                    record: record.fields.iter().map(|(k, v)| (k.clone(), serde_json::Value::from(v.as_str()))).collect(),
+                });
+            }
+        }
+        batch.clear();
+    }
+}
+
+/// load the Sigma rules of the rule files in the folders, returning the rules, the errors
+/// of the rules skipped and the number of files
+pub fn load_rules(paths: &[PathBuf]) -> (Vec<SigmaRule>, Vec<String>, usize) {
+    let mut files: Vec<PathBuf> = paths.iter()
+        .flat_map(|p| WalkDir::new(p).into_iter().flatten())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| p.extension().is_some_and(|e| RULE_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str())))
+        .collect();
+    files.sort();
+    let parsed: Vec<(Vec<SigmaRule>, Vec<String>)> = files.par_iter()
+        .map(|file| {
+            let name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
+            match fs::read_to_string(file) {
+                Ok(source) => sigma_rules::parse_rules(&source, &name),
+                Err(e) => (Vec::new(), vec![format!("{name}: {e}")]),
+            }
+        })
+        .collect();
+    let (rules, errors): (Vec<Vec<SigmaRule>>, Vec<Vec<String>>) = parsed.into_iter().unzip();
+    (rules.into_iter().flatten().collect(), errors.into_iter().flatten().collect(), files.len())
+}
+
+/// get the mapping from `--mapping <file>` in the args, otherwise config/sigma.yaml beside
+/// the tools folder or the builtin mapping
+fn load_mapping(args: &BuiltinArgs) -> Result<SigmaMapping> {
+    if let Some(path) = arg_value(&args.args, "--mapping") {
+        return SigmaMapping::open(Path::new(&path));
+    }
+    let config = args.main_args.tool_path
+        .parent()
+        .map(|p| p.join("config").join("sigma.yaml"))
+        .unwrap_or_else(|| PathBuf::from("sigma.yaml"));
+    match config.is_file() {
+        true => SigmaMapping::open(&config),
+        false => Ok(SigmaMapping::default_mapping()),
+    }
+}
+
+/// the EvtxECmd output of the event logs in the wiskess output
+fn event_log_files(out_path: &Path) -> Vec<PathBuf> {
+    let Some(folder) = common::join_case_insensitive(out_path, EVENT_LOG_FOLDER) else { return Vec::new() };
+    let Ok(re) = RegexBuilder::new(EVENT_LOG_FILE).case_insensitive(true).build() else { return Vec::new() };
+    let mut files: Vec<PathBuf> = fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.file_name().is_some_and(|n| re.is_match(&n.to_string_lossy())))
+        .collect();
+    files.sort();
+    files
+}
+
+/// run the builtin Sigma engine with the rules of `--rules <folders>`, over the timeline,
+/// or `--timeline <file>` such as the case timeline, and the EvtxECmd output of the event
+/// logs. `--min-level <level>` skips the rules below the level, `--status <statuses>`
+/// includes the deprecated or unsupported rules, `--max-per-rule <n>` caps the detections
+/// written of each rule and `--mapping <file>` sets the Sigma mapping. The detections are
+/// written as JSONL to the outfile, with the detections of each rule in `{stem}_summary.csv`
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let mapping = load_mapping(args)?;
+    let rule_paths: Vec<PathBuf> = arg_value(&args.args, "--rules").unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .filter(|p| p.exists())
+        .collect();
+    let (rules, mut errors, files) = load_rules(&rule_paths);
+    let min_level = match arg_value(&args.args, "--min-level") {
+        Some(level) => Level::from_name(&level).with_context(|| format!("Unknown level {level}"))?,
+        None => Level::Informational,
+    };
+    let statuses = arg_value(&args.args, "--status").unwrap_or_default().to_lowercase();
+    let included = |rule: &SigmaRule| rule.level >= min_level
+        && (!SKIPPED_STATUSES.contains(&rule.status.as_str()) || statuses.split(',').any(|s| s.trim() == rule.status));
+    let rules: Vec<SigmaRule> = rules.into_iter().filter(included).collect();
+    let (engine, unmapped) = SigmaEngine::new(rules, &mapping);
+    errors.extend(unmapped.iter().map(|r| format!("{}: {} has a logsource that isn't mapped", r.file, r.title)));
+    let max_per_rule = match arg_value(&args.args, "--max-per-rule") {
+        Some(max) => max.parse().with_context(|| format!("Invalid --max-per-rule {max}"))?,
+        None => DEFAULT_MAX_PER_RULE,
+    };
+
+    fs::create_dir_all(&args.outfolder)?;
+    let stem = Path::new(&args.outfile).file_stem().unwrap_or_default().to_string_lossy().to_string();
+    if !errors.is_empty() {
+        fs::write(args.outfolder.join(format!("{stem}_rule_errors.txt")), errors.join("\n") + "\n")?;
+    }
+    let mut detections = Detections { max_per_rule, written: Vec::new(), summaries: vec![RuleSummary::default(); engine.rules.len()] };
+    let mut records = 0;
+    let mut missing_timeline = None;
+    if !engine.is_empty() {
+        let out_path = Path::new(&args.main_args.out_path);
+        let timeline_file = arg_value(&args.args, "--timeline")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| out_path.join("Timeline").join(timeline::TIMELINE_FILE));
+        let mut batch = Vec::with_capacity(BATCH_RECORDS);
+        if timeline_file.is_file() {
+            timeline::read_events(&timeline_file, |event| {
+                records += 1;
+                batch.push(Record::from_event(event));
+                if batch.len() == BATCH_RECORDS {
+                    detections.add_batch(&engine, &mut batch);
+                }
+                Ok(())
+            })?;
+        } else {
+            file_ops::log_msg(&args.main_args.out_log, format!("[!] Sigma rules aren't run over the timeline, as {} wasn't found", timeline_file.display()));
+            missing_timeline = Some(timeline_file);
+        }
+        let range = DateRange::new(&args.main_args.start_date, &args.main_args.end_date);
+        for file in event_log_files(out_path) {
+            read_event_logs(&file, &range, |record| {
+                records += 1;
+                batch.push(record);
+                if batch.len() == BATCH_RECORDS {
+                    detections.add_batch(&engine, &mut batch);
+                }
+                Ok(())
+            })?;
+        }
+        detections.add_batch(&engine, &mut batch);
+    }
+
+    let mut written = detections.written;
+    written.sort_by(|a, b| a.datetime.cmp(&b.datetime).then(b.level.cmp(&a.level)).then(a.title.cmp(&b.title)));
+    let mut writer = std::io::BufWriter::new(File::create(args.outfolder.join(&args.outfile))?);
+    for detection in &written {
+        serde_json::to_writer(&mut writer, detection)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    let mut summary: Vec<(&SigmaRule, &RuleSummary)> = engine.rules.iter().zip(&detections.summaries)
+        .filter(|(_, s)| s.detections > 0)
+        .collect();
+    summary.sort_by(|a, b| b.0.level.cmp(&a.0.level).then(b.1.detections.cmp(&a.1.detections)).then(a.0.title.cmp(&b.0.title)));
+    let mut summary_writer = common::csv_writer(&args.outfolder.join(format!("{stem}_summary.csv")))?;
+    summary_writer.write_record(SUMMARY_HEADER)?;
+    for (rule, found) in &summary {
+        summary_writer.write_record([
+            rule.level.name(), &rule.id, &rule.title, &found.detections.to_string(), &found.first, &found.last, &rule.file,
+        ])?;
+    }
+    summary_writer.flush()?;
+    let total: usize = detections.summaries.iter().map(|s| s.detections).sum();
+    let mut msg = format!(
+        "Sigma detections: {total} from {} of {} rules in {files} rule files ({} skipped), over {records} records",
+        summary.len(), engine.rules.len(), errors.len()
+    );
+    if let Some(timeline_file) = missing_timeline {
+        msg.push_str(&format!(", without the timeline as {} wasn't found", timeline_file.display()));
+    }
+    Ok(msg)
+}
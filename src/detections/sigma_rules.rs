@@ -0,0 +1,641 @@
+/*
+Parser of Sigma rules for the builtin Sigma engine. Each rule's detection is compiled to
+matchers of the field values, with the value modifiers of the Sigma specification:
+contains, startswith, endswith, all, re (with i, m and s), cidr, cased, exists, windash,
+lt, lte, gt and gte, and base64 and base64offset with the utf16 encodings. Conditions
+of and, or, not, brackets and `1 of` or `all of` the searches are supported. Rules with
+aggregations, correlations or the expand and fieldref modifiers are skipped with an error.
+*/
+
+use std::net::IpAddr;
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::iocs::loader::IpNetwork;
+
+/// The level of a rule, in order of severity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Informational,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "informational" | "info" => Some(Level::Informational),
+            "low" => Some(Level::Low),
+            "medium" => Some(Level::Medium),
+            "high" => Some(Level::High),
+            "critical" => Some(Level::Critical),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Informational => "informational",
+            Level::Low => "low",
+            Level::Medium => "medium",
+            Level::High => "high",
+            Level::Critical => "critical",
+        }
+    }
+}
+
+/// The logsource of a rule, in lowercase
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogSource {
+    pub category: Option<String>,
+    pub product: Option<String>,
+    pub service: Option<String>,
+}
+
+/// How a value of a field is matched. The strings are lowercase unless the field's
+/// match is cased
+#[derive(Debug, Clone)]
+pub enum ValueMatcher {
+    Equals(String),
+    Contains(String),
+    StartsWith(String),
+    EndsWith(String),
+    Regex(Regex),
+    Cidr(IpNetwork),
+    Lt(f64),
+    Lte(f64),
+    Gt(f64),
+    Gte(f64),
+    Exists(bool),
+    /// the field is missing or empty, from a null value
+    Null,
+}
+
+/// A field of a search with the values it matches, any of them unless `all` is set
+#[derive(Debug, Clone)]
+pub struct FieldMatch {
+    pub field: String,
+    pub matchers: Vec<ValueMatcher>,
+    pub all: bool,
+    pub cased: bool,
+}
+
+/// A search of the detection, either groups of fields, where a group matches when all
+/// of its fields do and the search when any group does, or keywords found in any value
+#[derive(Debug, Clone)]
+pub enum Search {
+    Fields(Vec<Vec<FieldMatch>>),
+    Keywords(Vec<ValueMatcher>),
+}
+
+/// The condition of the detection, over the indexes of its searches
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Search(usize),
+    Not(Box<Condition>),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+}
+
+/// A compiled Sigma rule
+#[derive(Debug, Clone)]
+pub struct SigmaRule {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+    pub level: Level,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub logsource: LogSource,
+    pub searches: Vec<(String, Search)>,
+    pub condition: Condition,
+    /// the rule file's name
+    pub file: String,
+}
+
+/// A record that rules are evaluated over, with its fields read by the Sigma field names
+pub trait SigmaRecord {
+    fn field(&self, name: &str) -> Option<&str>;
+    /// every value of the record, for the keywords
+    fn values(&self) -> Vec<&str>;
+}
+
+impl ValueMatcher {
+    /// check the value, which is lowercase unless the match is cased
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            ValueMatcher::Equals(s) => value == s,
+            ValueMatcher::Contains(s) => value.contains(s.as_str()),
+            ValueMatcher::StartsWith(s) => value.starts_with(s.as_str()),
+            ValueMatcher::EndsWith(s) => value.ends_with(s.as_str()),
+            ValueMatcher::Regex(re) => re.is_match(value),
+            ValueMatcher::Cidr(net) => IpAddr::from_str(value.trim()).is_ok_and(|ip| net.contains(&ip)),
+            ValueMatcher::Lt(n) => number(value).is_some_and(|v| v < *n),
+            ValueMatcher::Lte(n) => number(value).is_some_and(|v| v <= *n),
+            ValueMatcher::Gt(n) => number(value).is_some_and(|v| v > *n),
+            ValueMatcher::Gte(n) => number(value).is_some_and(|v| v >= *n),
+            ValueMatcher::Exists(exists) => *exists,
+            ValueMatcher::Null => value.is_empty(),
+        }
+    }
+
+    /// check a field that the record doesn't have
+    fn matches_missing(&self) -> bool {
+        matches!(self, ValueMatcher::Exists(false) | ValueMatcher::Null)
+    }
+}
+
+fn number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    match value.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+        None => value.parse().ok(),
+    }
+}
+
+impl FieldMatch {
+    fn matches(&self, record: &dyn SigmaRecord) -> bool {
+        let Some(value) = record.field(&self.field) else {
+            return self.matchers.iter().any(ValueMatcher::matches_missing);
+        };
+        let lower;
+        let value = match self.cased {
+            true => value,
+            false => {
+                lower = value.to_lowercase();
+                lower.as_str()
+            }
+        };
+        match self.all {
+            true => self.matchers.iter().all(|m| m.matches(value)),
+            false => self.matchers.iter().any(|m| m.matches(value)),
+        }
+    }
+}
+
+impl Search {
+    fn matches(&self, record: &dyn SigmaRecord) -> bool {
+        match self {
+            Search::Fields(groups) => groups.iter().any(|fields| fields.iter().all(|f| f.matches(record))),
+            Search::Keywords(keywords) => record.values().iter().any(|v| {
+                let v = v.to_lowercase();
+                keywords.iter().any(|k| k.matches(&v))
+            }),
+        }
+    }
+}
+
+impl SigmaRule {
+    /// check the rule's condition over the record
+    pub fn matches(&self, record: &dyn SigmaRecord) -> bool {
+        self.eval(&self.condition, record)
+    }
+
+    fn eval(&self, condition: &Condition, record: &dyn SigmaRecord) -> bool {
+        match condition {
+            Condition::Search(i) => self.searches[*i].1.matches(record),
+            Condition::Not(c) => !self.eval(c, record),
+            Condition::And(terms) => terms.iter().all(|c| self.eval(c, record)),
+            Condition::Or(terms) => terms.iter().any(|c| self.eval(c, record)),
+            Condition::AllOf(searches) => searches.iter().all(|i| self.searches[*i].1.matches(record)),
+            Condition::AnyOf(searches) => searches.iter().any(|i| self.searches[*i].1.matches(record)),
+        }
+    }
+
+    /// the fields the rule's searches read
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields: Vec<&str> = self.searches.iter()
+            .flat_map(|(_, s)| match s {
+                Search::Fields(groups) => groups.iter().flatten().map(|f| f.field.as_str()).collect(),
+                Search::Keywords(_) => Vec::new(),
+            })
+            .collect();
+        fields.sort();
+        fields.dedup();
+        fields
+    }
+}
+
+/// A part of a value with Sigma's wildcards
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Glob {
+    Char(char),
+    Any,
+    One,
+}
+
+/// split a value into its characters and wildcards, where `\*`, `\?` and `\\` escape them
+fn glob(value: &str) -> Vec<Glob> {
+    let mut parts = Vec::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => parts.push(Glob::Any),
+            '?' => parts.push(Glob::One),
+            '\\' if matches!(chars.peek(), Some('*' | '?' | '\\')) => parts.push(Glob::Char(chars.next().unwrap_or('\\'))),
+            c => parts.push(Glob::Char(c)),
+        }
+    }
+    parts
+}
+
+/// the matcher of a value with wildcards, as a plain comparison where it can be
+fn glob_matcher(parts: &[Glob], cased: bool) -> Result<ValueMatcher> {
+    let leading = parts.first() == Some(&Glob::Any);
+    let trailing = parts.len() > 1 && parts.last() == Some(&Glob::Any);
+    let inner = &parts[leading as usize..parts.len() - trailing as usize];
+    if inner.iter().all(|p| matches!(p, Glob::Char(_))) {
+        let text: String = inner.iter().map(|p| match p { Glob::Char(c) => *c, _ => ' ' }).collect();
+        let text = if cased { text } else { text.to_lowercase() };
+        return Ok(match (leading, trailing) {
+            (false, false) => ValueMatcher::Equals(text),
+            (true, true) => ValueMatcher::Contains(text),
+            (false, true) => ValueMatcher::StartsWith(text),
+            (true, false) => ValueMatcher::EndsWith(text),
+        });
+    }
+    let pattern: String = parts.iter().map(|p| match p {
+        Glob::Char(c) => regex::escape(&c.to_string()),
+        Glob::Any => ".*".to_string(),
+        Glob::One => ".".to_string(),
+    }).collect();
+    Ok(ValueMatcher::Regex(RegexBuilder::new(&format!("^{pattern}$"))
+        .case_insensitive(!cased)
+        .dot_matches_new_line(true)
+        .build()?))
+}
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3F) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+/// the three base64 encodings of the data at each offset in a longer string, without the
+/// characters that depend on the data around it
+fn base64_offsets(data: &[u8]) -> Vec<String> {
+    const STARTS: [usize; 3] = [0, 2, 3];
+    const END_TRIMS: [usize; 3] = [0, 3, 2];
+    (0..3).map(|i| {
+        let mut shifted = vec![b' '; i];
+        shifted.extend(data);
+        let encoded = base64(&shifted);
+        let end = encoded.len() - END_TRIMS[(data.len() + i) % 3];
+        encoded.get(STARTS[i]..end).unwrap_or_default().to_string()
+    }).collect()
+}
+
+/// the text of a YAML scalar, or None for null
+fn scalar(value: &Value) -> Result<Option<String>> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(s) => Ok(Some(s.clone())),
+        Value::Number(n) => Ok(Some(n.to_string())),
+        Value::Bool(b) => Ok(Some(b.to_string())),
+        other => bail!("Unsupported value {other:?}"),
+    }
+}
+
+/// replace the dashes starting the words of the value with each of the dashes Windows
+/// accepts for the options of a command
+fn windash(value: &str) -> Vec<String> {
+    let mut variants = vec![value.to_string()];
+    for dash in ['/', '–', '—', '―'] {
+        let mut variant = String::new();
+        let mut previous = ' ';
+        for c in value.chars() {
+            variant.push(if c == '-' && previous.is_whitespace() { dash } else { c });
+            previous = c;
+        }
+        if variant != value {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+/// compile the field of a search, as `Field|modifier|modifier: values`
+fn field_match(key: &str, values: &Value) -> Result<FieldMatch> {
+    let mut parts = key.split('|');
+    let field = parts.next().unwrap_or_default().to_string();
+    let modifiers: Vec<String> = parts.map(|m| m.to_lowercase()).collect();
+    let has = |m: &str| modifiers.iter().any(|x| x == m);
+    for modifier in &modifiers {
+        if !["contains", "startswith", "endswith", "all", "re", "i", "m", "s", "cidr", "cased", "exists",
+            "windash", "lt", "lte", "gt", "gte", "base64", "base64offset", "wide", "utf16le", "utf16be", "utf16"]
+            .contains(&modifier.as_str()) {
+            bail!("The {modifier} modifier of {field} isn't supported");
+        }
+    }
+    let values: Vec<Option<String>> = match values {
+        Value::Sequence(list) => list.iter().map(scalar).collect::<Result<_>>()?,
+        value => vec![scalar(value)?],
+    };
+    let encoded = has("base64") || has("base64offset");
+    // regexes are case-sensitive unless `|i` is given, so the values aren't lowercased
+    let cased = has("cased") || has("re") || encoded;
+    let mut matchers = Vec::new();
+    for value in values {
+        let Some(value) = value else {
+            matchers.push(ValueMatcher::Null);
+            continue;
+        };
+        if has("exists") {
+            matchers.push(ValueMatcher::Exists(value.eq_ignore_ascii_case("true")));
+            continue;
+        }
+        let number = || value.trim().parse::<f64>().with_context(|| format!("{field} needs a number, not {value}"));
+        if has("lt") || has("lte") || has("gt") || has("gte") {
+            matchers.push(match modifiers.iter().find(|m| ["lt", "lte", "gt", "gte"].contains(&m.as_str())).map(String::as_str) {
+                Some("lt") => ValueMatcher::Lt(number()?),
+                Some("lte") => ValueMatcher::Lte(number()?),
+                Some("gt") => ValueMatcher::Gt(number()?),
+                _ => ValueMatcher::Gte(number()?),
+            });
+            continue;
+        }
+        if has("cidr") {
+            matchers.push(ValueMatcher::Cidr(IpNetwork::parse(&value)?));
+            continue;
+        }
+        if has("re") {
+            let re = RegexBuilder::new(&value)
+                .case_insensitive(has("i"))
+                .multi_line(has("m"))
+                .dot_matches_new_line(has("s"))
+                .build()
+                .with_context(|| format!("Invalid regex of {field}"))?;
+            matchers.push(ValueMatcher::Regex(re));
+            continue;
+        }
+        let texts: Vec<String> = match encoded {
+            true => {
+                let bytes: Vec<u8> = match () {
+                    _ if has("wide") || has("utf16le") => value.encode_utf16().flat_map(|c| c.to_le_bytes()).collect(),
+                    _ if has("utf16be") => value.encode_utf16().flat_map(|c| c.to_be_bytes()).collect(),
+                    _ if has("utf16") => [0xFF, 0xFE].into_iter().chain(value.encode_utf16().flat_map(|c| c.to_le_bytes())).collect(),
+                    _ => value.into_bytes(),
+                };
+                match has("base64offset") {
+                    true => base64_offsets(&bytes),
+                    false => vec![base64(&bytes)],
+                }
+            }
+            false if has("wide") || has("utf16le") || has("utf16be") || has("utf16") => {
+                bail!("The utf16 modifiers of {field} are only supported with base64");
+            }
+            false if has("windash") => windash(&value),
+            false => vec![value],
+        };
+        for text in texts {
+            let mut parts = match encoded {
+                true => text.chars().map(Glob::Char).collect(),
+                false => glob(&text),
+            };
+            if has("contains") || has("endswith") {
+                parts.insert(0, Glob::Any);
+            }
+            if has("contains") || has("startswith") {
+                parts.push(Glob::Any);
+            }
+            matchers.push(glob_matcher(&parts, cased)?);
+        }
+    }
+    if matchers.is_empty() {
+        bail!("{field} has no values");
+    }
+    Ok(FieldMatch { field, matchers, all: has("all"), cased })
+}
+
+/// compile the fields of a search's map, which all must match
+fn field_group(map: &Mapping) -> Result<Vec<FieldMatch>> {
+    map.iter()
+        .map(|(key, values)| {
+            let key = key.as_str().ok_or_else(|| anyhow!("Invalid field {key:?}"))?;
+            field_match(key, values)
+        })
+        .collect()
+}
+
+fn parse_search(value: &Value) -> Result<Search> {
+    match value {
+        Value::Mapping(map) => Ok(Search::Fields(vec![field_group(map)?])),
+        Value::Sequence(list) if list.iter().all(|v| v.is_mapping()) => {
+            let groups = list.iter().filter_map(Value::as_mapping).map(field_group).collect::<Result<_>>()?;
+            Ok(Search::Fields(groups))
+        }
+        Value::Sequence(list) => {
+            let keywords = list.iter()
+                .map(|v| {
+                    let text = scalar(v)?.ok_or_else(|| anyhow!("A null keyword"))?;
+                    let parts: Vec<Glob> = [Glob::Any].into_iter().chain(glob(&text)).chain([Glob::Any]).collect();
+                    glob_matcher(&parts, false)
+                })
+                .collect::<Result<_>>()?;
+            Ok(Search::Keywords(keywords))
+        }
+        value => bail!("Invalid search {value:?}"),
+    }
+}
+
+/// the indexes of the searches named by the pattern, which may end in `*`, or all of
+/// them for `them` except those starting with `_`
+fn search_set(searches: &[(String, Search)], pattern: &str) -> Result<Vec<usize>> {
+    let found: Vec<usize> = searches.iter().enumerate()
+        .filter(|(_, (name, _))| match pattern {
+            "them" => !name.starts_with('_'),
+            pattern => match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            },
+        })
+        .map(|(i, _)| i)
+        .collect();
+    match found.is_empty() {
+        true => bail!("No search matches {pattern}"),
+        false => Ok(found),
+    }
+}
+
+/// The words of a condition being parsed
+struct ConditionParser<'a> {
+    tokens: Vec<String>,
+    pos: usize,
+    searches: &'a [(String, Search)],
+}
+
+impl ConditionParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn eat(&mut self, word: &str) -> bool {
+        let found = self.peek().is_some_and(|t| t.eq_ignore_ascii_case(word));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn next(&mut self) -> Result<String> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| anyhow!("The condition ends early"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn parse_or(&mut self) -> Result<Condition> {
+        let mut terms = vec![self.parse_and()?];
+        while self.eat("or") {
+            terms.push(self.parse_and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Condition::Or(terms) })
+    }
+
+    fn parse_and(&mut self) -> Result<Condition> {
+        let mut terms = vec![self.parse_not()?];
+        while self.eat("and") {
+            terms.push(self.parse_not()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Condition::And(terms) })
+    }
+
+    fn parse_not(&mut self) -> Result<Condition> {
+        match self.eat("not") {
+            true => Ok(Condition::Not(Box::new(self.parse_not()?))),
+            false => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Condition> {
+        let token = self.next()?;
+        match token.to_lowercase().as_str() {
+            "(" => {
+                let condition = self.parse_or()?;
+                match self.eat(")") {
+                    true => Ok(condition),
+                    false => bail!("Unclosed bracket in the condition"),
+                }
+            }
+            "|" => bail!("Aggregations aren't supported"),
+            quantifier @ ("1" | "any" | "all") => {
+                if !self.eat("of") {
+                    bail!("Expected of after {quantifier}");
+                }
+                let set = search_set(self.searches, &self.next()?)?;
+                Ok(match quantifier {
+                    "all" => Condition::AllOf(set),
+                    _ => Condition::AnyOf(set),
+                })
+            }
+            _ => {
+                let index = self.searches.iter().position(|(name, _)| *name == token)
+                    .ok_or_else(|| anyhow!("Unknown search {token} in the condition"))?;
+                Ok(Condition::Search(index))
+            }
+        }
+    }
+}
+
+/// parse a condition over the searches
+pub fn parse_condition(condition: &str, searches: &[(String, Search)]) -> Result<Condition> {
+    let tokens = condition.replace('(', " ( ").replace(')', " ) ").replace('|', " | ")
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    let mut parser = ConditionParser { tokens, pos: 0, searches };
+    let parsed = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(parsed),
+        Some("|") => bail!("Aggregations aren't supported"),
+        Some(token) => bail!("Unexpected {token} in the condition"),
+    }
+}
+
+fn text(map: &Mapping, key: &str) -> String {
+    map.get(key).and_then(|v| scalar(v).ok().flatten()).unwrap_or_default()
+}
+
+/// parse a rule from a YAML document of a rule file
+pub fn parse_rule(doc: &Value, file: &str) -> Result<SigmaRule> {
+    let map = doc.as_mapping().ok_or_else(|| anyhow!("The rule isn't a mapping"))?;
+    if map.contains_key("correlation") || map.contains_key("action") {
+        bail!("Correlations and rule collections aren't supported");
+    }
+    let detection = map.get("detection").and_then(Value::as_mapping).ok_or_else(|| anyhow!("The rule has no detection"))?;
+    let logsource = map.get("logsource").and_then(Value::as_mapping);
+    let logsource_key = |key: &str| logsource.map(|l| text(l, key).to_lowercase()).filter(|v| !v.is_empty());
+    let mut searches = Vec::new();
+    for (name, search) in detection {
+        let name = name.as_str().unwrap_or_default();
+        if name == "condition" || name == "timeframe" {
+            continue;
+        }
+        let search = parse_search(search).with_context(|| format!("In search {name}"))?;
+        searches.push((name.to_string(), search));
+    }
+    let conditions: Vec<String> = match detection.get("condition") {
+        Some(Value::Sequence(list)) => list.iter().filter_map(|c| c.as_str().map(String::from)).collect(),
+        Some(Value::String(condition)) => vec![condition.clone()],
+        _ => bail!("The rule has no condition"),
+    };
+    let mut conditions: Vec<Condition> = conditions.iter().map(|c| parse_condition(c, &searches)).collect::<Result<_>>()?;
+    let condition = match conditions.len() {
+        1 => conditions.remove(0),
+        _ => Condition::Or(conditions),
+    };
+    let level = text(map, "level");
+    Ok(SigmaRule {
+        id: text(map, "id"),
+        title: text(map, "title"),
+        status: text(map, "status").to_lowercase(),
+        level: Level::from_name(&level).unwrap_or_default(),
+        description: text(map, "description"),
+        tags: map.get("tags").and_then(Value::as_sequence)
+            .map(|tags| tags.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+            .unwrap_or_default(),
+        logsource: LogSource {
+            category: logsource_key("category"),
+            product: logsource_key("product"),
+            service: logsource_key("service"),
+        },
+        searches,
+        condition,
+        file: file.to_string(),
+    })
+}
+
+/// parse the rules of a rule file, returning the rules and the errors of those skipped
+pub fn parse_rules(source: &str, file: &str) -> (Vec<SigmaRule>, Vec<String>) {
+    let mut rules = Vec::new();
+    let mut errors = Vec::new();
+    for doc in serde_yaml::Deserializer::from_str(source) {
+        let parsed = Value::deserialize(doc)
+            .map_err(anyhow::Error::from)
+            .and_then(|doc| parse_rule(&doc, file));
+        match parsed {
+            Ok(rule) => rules.push(rule),
+            Err(e) => errors.push(format!("{file}: {e:#}")),
+        }
+    }
+    (rules, errors)
+}
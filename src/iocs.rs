@@ -0,0 +1,8 @@
+pub mod allowlist;
+pub mod hash_match;
+pub mod intel;
+pub mod loader;
+pub mod report;
+pub mod scanner;
+pub mod yara;
+pub mod yara_rules;
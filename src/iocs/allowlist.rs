@@ -0,0 +1,175 @@
+/*
+The allowlist of the IOC enrichers, suppressing the hits that are known to be benign.
+Hits are suppressed in the paths matching a glob, for an IOC in the paths of its globs,
+or in a file with a known good hash. The suppressed hits are still counted, so analysts
+know what was filtered. The data source scans also skip the wiskess outputs found in
+the top levels of the data source, i.e. of an earlier run stored beside the collection.
+*/
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::parsers::hashes::FileHashes;
+use crate::timeline::builder::arg_value;
+
+/// The allowlist used when `--allowlist` isn't given, in the config folder beside the tools
+const DEFAULT_ALLOWLIST: &str = "allowlist.yaml";
+/// The depth of the folders below the data source checked for a wiskess output, as they
+/// are stored beside the collection rather than deep within it
+const WISKESS_OUTPUT_DEPTH: usize = 2;
+
+/// An IOC with the paths it is suppressed in, all paths when there are none
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AllowedIoc {
+    pub ioc: String,
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+/// The allowlist as it is written in YAML
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AllowlistFile {
+    /// globs of the paths whose hits are suppressed
+    #[serde(default)]
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub iocs: Vec<AllowedIoc>,
+    /// known good MD5, SHA1 or SHA256 hashes
+    #[serde(default)]
+    pub hashes: Vec<String>,
+    /// files of known good hashes, one on each line, relative to the allowlist
+    #[serde(default)]
+    pub hash_files: Vec<String>,
+}
+
+/// The compiled allowlist
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    paths: Vec<Regex>,
+    iocs: Vec<(Regex, Vec<Regex>)>,
+    hashes: HashSet<String>,
+}
+
+/// the regex of a glob, where `*` matches any characters, `?` one character and either
+/// separator matches both, ignoring case
+pub fn glob_regex(glob: &str) -> Result<Regex> {
+    let mut pattern = String::from("^");
+    for c in glob.trim().chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '\\' | '/' => pattern.push_str(r"[\\/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .with_context(|| format!("Invalid glob {glob}"))
+}
+
+/// the hash at the start of a line of a hash set, such as the output of sha256sum or an
+/// NSRL CSV
+fn line_hash(line: &str) -> Option<String> {
+    let value = line.split([',', ' ', '\t']).next()?.trim().trim_matches('"');
+    let is_hash = matches!(value.len(), 32 | 40 | 64) && value.chars().all(|c| c.is_ascii_hexdigit());
+    is_hash.then(|| value.to_lowercase())
+}
+
+impl Allowlist {
+    pub fn new(file: &AllowlistFile, folder: &Path) -> Result<Self> {
+        let globs = |globs: &[String]| globs.iter().map(|g| glob_regex(g)).collect::<Result<Vec<_>>>();
+        let mut hashes: HashSet<String> = file.hashes.iter().filter_map(|h| line_hash(h)).collect();
+        for hash_file in &file.hash_files {
+            let path = folder.join(hash_file);
+            let data = fs::read(&path).with_context(|| format!("Unable to read the hash set {}", path.display()))?;
+            hashes.extend(String::from_utf8_lossy(&data).lines().filter_map(line_hash));
+        }
+        Ok(Allowlist {
+            paths: globs(&file.paths)?,
+            iocs: file.iocs.iter()
+                .map(|i| Ok((glob_regex(&i.ioc)?, globs(&i.paths)?)))
+                .collect::<Result<_>>()?,
+            hashes,
+        })
+    }
+
+    /// read the allowlist from a YAML file
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Unable to open the allowlist {}", path.display()))?;
+        let allowlist: AllowlistFile = serde_yaml::from_reader(file)
+            .with_context(|| format!("Unable to parse the allowlist {}", path.display()))?;
+        Allowlist::new(&allowlist, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// get the allowlist of `--allowlist <file>` in the args, otherwise config/allowlist.yaml
+    /// beside the tools folder, or an empty allowlist
+    pub fn load(args: &BuiltinArgs) -> Result<Self> {
+        if let Some(path) = arg_value(&args.args, "--allowlist") {
+            return Allowlist::open(Path::new(&path));
+        }
+        let config = args.main_args.tool_path
+            .parent()
+            .map(|p| p.join("config").join(DEFAULT_ALLOWLIST))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_ALLOWLIST));
+        match config.is_file() {
+            true => Allowlist::open(&config),
+            false => Ok(Allowlist::default()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.iocs.is_empty() && self.hashes.is_empty()
+    }
+
+    pub fn has_hashes(&self) -> bool {
+        !self.hashes.is_empty()
+    }
+
+    /// check if every hit in the path is suppressed
+    pub fn allows_path(&self, path: &str) -> bool {
+        self.paths.iter().any(|re| re.is_match(path))
+    }
+
+    /// check if a hit of the IOC, or YARA rule, in the path is suppressed
+    pub fn allows_hit(&self, ioc: &str, path: &str) -> bool {
+        self.allows_path(path) || self.iocs.iter().any(|(re, paths)| {
+            re.is_match(ioc) && (paths.is_empty() || paths.iter().any(|p| p.is_match(path)))
+        })
+    }
+
+    /// check if a hash is known good
+    pub fn allows_hash(&self, hash: &str) -> bool {
+        self.hashes.contains(&hash.to_lowercase())
+    }
+
+    /// check if any hash of a file is known good
+    pub fn allows_hashes(&self, hashes: &FileHashes) -> bool {
+        [&hashes.md5, &hashes.sha1, &hashes.sha256].iter().any(|h| self.hashes.contains(h.as_str()))
+    }
+}
+
+/// check if an entry of a walk of the data source is a wiskess output to skip. Only the
+/// folders in the top levels are checked, so each folder of the walk isn't listed again
+pub fn skips_wiskess_output(entry: &walkdir::DirEntry) -> bool {
+    (1..=WISKESS_OUTPUT_DEPTH).contains(&entry.depth())
+        && entry.file_type().is_dir()
+        && is_wiskess_output(entry.path())
+}
+
+/// check if a folder is the output of wiskess, having the log of a run
+pub fn is_wiskess_output(folder: &Path) -> bool {
+    fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .any(|e| {
+            let name = e.file_name().to_string_lossy().to_lowercase();
+            name.starts_with("wiskess_") && name.ends_with(".log") && e.path().is_file()
+        })
+}
\ No newline at end of file
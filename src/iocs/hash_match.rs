@@ -0,0 +1,322 @@
+/*
+Hash IOC matching. The executables and scripts of the data source, the deleted $R files
+of the Recycle Bin and the files collected to Artefacts are hashed with MD5, SHA1 and
+SHA256 and compared with the hash IOCs, along with the SHA1 of each program recorded in
+the Amcache output. The matches are written with the path, hash type and IOC of each,
+apart from those of the allowlist, such as files with a known good hash.
+*/
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use super::allowlist::{self, Allowlist};
+use super::loader::{self, Ioc, IocType};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::parsers::common;
+use crate::parsers::hashes::{self, FileHashes};
+use crate::timeline::builder::{arg_value, split_args};
+
+/// The extensions of the executables and scripts hashed in the data source
+const HASH_EXTENSIONS: &[&str] = &[
+    "bat", "cmd", "com", "cpl", "dll", "drv", "efi", "exe", "hta", "jar", "js", "jse", "msi",
+    "ocx", "ps1", "psd1", "psm1", "py", "scr", "sh", "sys", "vbe", "vbs", "wsf", "wsh",
+];
+/// The files larger than this are not hashed by default, in MB
+const DEFAULT_MAX_SIZE_MB: u64 = 100;
+/// The folder of the wiskess output that collected files are copied to
+const ARTEFACTS_FOLDER: &str = "Artefacts";
+const MATCH_HEADER: [&str; 10] = ["Path", "Source", "Size", "HashType", "Ioc", "List", "Description", "Event", "Tags", "Confidence"];
+
+/// Where the hashed file was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashSource {
+    DataSource,
+    RecycleBin,
+    Artefacts,
+    Amcache,
+}
+
+/// A file with a hash of an IOC
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HashMatch {
+    pub path: String,
+    pub source: HashSource,
+    pub size: u64,
+    pub hash_type: IocType,
+    pub ioc: String,
+    pub list: String,
+    pub description: String,
+    pub event: String,
+    pub tags: String,
+    pub confidence: Option<u8>,
+}
+
+/// The files that are hashed, set by the wisker's args
+#[derive(Debug, Clone)]
+pub struct HashFilter {
+    pub max_size: u64,
+    /// the extensions hashed in the data source, all when None
+    pub extensions: Option<Vec<String>>,
+    /// the paths must contain one of these, when there are any
+    pub include: Vec<String>,
+    /// the paths must not contain any of these
+    pub exclude: Vec<String>,
+}
+
+impl Default for HashFilter {
+    fn default() -> Self {
+        HashFilter {
+            max_size: DEFAULT_MAX_SIZE_MB * 1024 * 1024,
+            extensions: Some(HASH_EXTENSIONS.iter().map(|e| e.to_string()).collect()),
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl HashFilter {
+    /// read the filter from `--max-size <MB>`, `--extensions <exe,dll>` or `--all-files`,
+    /// and `--include` or `--exclude` with comma separated parts of the paths
+    pub fn from_args(args: &str) -> Result<Self> {
+        let list = |flag: &str| -> Vec<String> {
+            arg_value(args, flag)
+                .map(|v| v.split(',').map(|p| p.trim().to_lowercase()).filter(|p| !p.is_empty()).collect())
+                .unwrap_or_default()
+        };
+        let mut filter = HashFilter { include: list("--include"), exclude: list("--exclude"), ..Default::default() };
+        if let Some(size) = arg_value(args, "--max-size") {
+            filter.max_size = size.parse::<u64>().with_context(|| format!("Invalid --max-size {size}"))? * 1024 * 1024;
+        }
+        if split_args(args).iter().any(|a| a == "--all-files") {
+            filter.extensions = None;
+        } else if arg_value(args, "--extensions").is_some() {
+            filter.extensions = Some(list("--extensions").into_iter().map(|e| e.trim_start_matches('.').to_string()).collect());
+        }
+        Ok(filter)
+    }
+
+    /// check the path is included and not excluded
+    pub fn path_allowed(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy().to_lowercase();
+        (self.include.is_empty() || self.include.iter().any(|p| path.contains(p.as_str())))
+            && !self.exclude.iter().any(|p| path.contains(p.as_str()))
+    }
+
+    /// check the extension of the file is one that is hashed
+    pub fn extension_allowed(&self, path: &Path) -> bool {
+        match &self.extensions {
+            None => true,
+            Some(extensions) => path.extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .is_some_and(|e| extensions.contains(&e)),
+        }
+    }
+}
+
+/// check the file was deleted to the Recycle Bin, as a $R file or in a deleted $R folder
+pub fn is_recycled(path: &Path) -> bool {
+    let lower = path.to_string_lossy().to_lowercase();
+    (lower.contains("$recycle.bin") || lower.contains("recycler"))
+        && path.components().any(|c| {
+            let name = c.as_os_str().to_string_lossy();
+            name.starts_with("$R") && !name.eq_ignore_ascii_case("$Recycle.Bin")
+        })
+}
+
+/// list the files under the root that pass the filter and the size limit, skipping the
+/// folder given and the outputs of wiskess in the top levels of the root
+pub fn walk(root: &Path, skip: &Path, filter: &HashFilter, keep: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    if !root.exists() {
+        return Vec::new();
+    }
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.path() != skip && !allowlist::skips_wiskess_output(e))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| filter.path_allowed(e.path()) && keep(e.path()))
+        .filter(|e| e.metadata().is_ok_and(|m| m.len() <= filter.max_size))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// the files to hash, being the executables and scripts of the data source, the $R files
+/// of the Recycle Bin and everything collected to Artefacts
+pub fn find_files(data_source: Option<&Path>, recycle_bin: Option<&Path>, out_path: &Path, filter: &HashFilter) -> Vec<(HashSource, PathBuf)> {
+    let mut files = Vec::new();
+    if let Some(root) = data_source {
+        for path in walk(root, out_path, filter, |p| filter.extension_allowed(p) || is_recycled(p)) {
+            let source = if is_recycled(&path) { HashSource::RecycleBin } else { HashSource::DataSource };
+            files.push((source, path));
+        }
+    }
+    if let Some(root) = recycle_bin {
+        files.extend(walk(root, out_path, filter, is_recycled).into_iter().map(|p| (HashSource::RecycleBin, p)));
+    }
+    let artefacts = out_path.join(ARTEFACTS_FOLDER);
+    files.extend(walk(&artefacts, Path::new(""), filter, |_| true).into_iter().map(|p| (HashSource::Artefacts, p)));
+    let mut seen = BTreeSet::new();
+    files.retain(|(_, p)| seen.insert(p.clone()));
+    files
+}
+
+/// The hash IOCs, by their type and value
+pub struct HashIocs {
+    iocs: HashMap<(IocType, String), Ioc>,
+}
+
+impl HashIocs {
+    pub fn new(iocs: Vec<Ioc>) -> Self {
+        let iocs = iocs.into_iter()
+            .filter(|ioc| ioc.ioc_type.is_hash())
+            .map(|ioc| ((ioc.ioc_type, ioc.value.clone()), ioc))
+            .collect();
+        HashIocs { iocs }
+    }
+
+    pub fn len(&self) -> usize {
+        self.iocs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iocs.is_empty()
+    }
+
+    /// get the IOC of the hash, which is lowercase hex
+    pub fn get(&self, hash_type: IocType, hash: &str) -> Option<&Ioc> {
+        self.iocs.get(&(hash_type, hash.to_string()))
+    }
+
+    /// the IOCs of any of the file's hashes
+    pub fn matches(&self, hashes: &FileHashes) -> Vec<&Ioc> {
+        [(IocType::Md5, &hashes.md5), (IocType::Sha1, &hashes.sha1), (IocType::Sha256, &hashes.sha256)]
+            .into_iter()
+            .filter_map(|(hash_type, hash)| self.get(hash_type, hash))
+            .collect()
+    }
+}
+
+/// the SHA1 of an Amcache entry, which the registry stores with four leading zeros
+fn amcache_sha1(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    let value = match value.len() {
+        44 if value.starts_with("0000") => value[4..].to_string(),
+        _ => value,
+    };
+    (value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit())).then_some(value)
+}
+
+/// check the SHA1 of the programs in the Amcache CSVs of the output, returning the entries
+/// checked and the matches
+pub fn amcache_matches(out_path: &Path, iocs: &HashIocs) -> Result<(usize, Vec<HashMatch>)> {
+    let mut checked = 0;
+    let mut matches = Vec::new();
+    let csvs = WalkDir::new(out_path)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            let name = p.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+            name.contains("amcache") && name.ends_with(".csv")
+        });
+    for csv in csvs {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(&csv)?;
+        let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim().to_lowercase()).collect();
+        let column = |names: &[&str]| names.iter().find_map(|n| headers.iter().position(|h| h == n));
+        let Some(sha1_col) = column(&["sha1"]) else { continue };
+        let path_col = column(&["fullpath", "path", "lowercaselongpath", "name"]);
+        let size_col = column(&["size", "filesize"]);
+        for record in reader.records().flatten() {
+            let Some(sha1) = record.get(sha1_col).and_then(amcache_sha1) else { continue };
+            checked += 1;
+            let Some(ioc) = iocs.get(IocType::Sha1, &sha1) else { continue };
+            let field = |col: Option<usize>| col.and_then(|c| record.get(c)).unwrap_or_default();
+            matches.push(HashMatch {
+                path: field(path_col).to_string(),
+                source: HashSource::Amcache,
+                size: field(size_col).parse().unwrap_or(0),
+                hash_type: IocType::Sha1,
+                ioc: ioc.value.clone(),
+                list: ioc.list.clone(),
+                description: ioc.description.clone(),
+                event: ioc.event.clone(),
+                tags: ioc.tags_text(),
+                confidence: ioc.confidence,
+            });
+        }
+    }
+    Ok((checked, matches))
+}
+
+/// run the builtin hash matching of the hash IOCs in the case's IOC file, or
+/// `--ioc-file <path>`, over the wisker's input, the recycle_bin artefact, the Artefacts
+/// folder and the Amcache output. The matches are written to the outfile, apart from
+/// those of the allowlist, or `--allowlist <file>`
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let ioc_file = arg_value(&args.args, "--ioc-file").unwrap_or_else(|| args.main_args.ioc_file.clone());
+    let iocs = HashIocs::new(loader::load_iocs(Path::new(&ioc_file), &args.main_args.out_log)?);
+    let filter = HashFilter::from_args(&args.args)?;
+    let allowlist = Allowlist::load(args)?;
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    if iocs.is_empty() {
+        writer.write_record(MATCH_HEADER)?;
+        writer.flush()?;
+        return Ok("No hash IOCs to match".to_string());
+    }
+
+    let out_path = Path::new(&args.main_args.out_path);
+    let data_source = Some(Path::new(&args.input)).filter(|_| !args.input.is_empty() && args.input != "wiskess_none");
+    let recycle_bin = args.data_path("recycle_bin");
+    let files = find_files(data_source, recycle_bin.as_deref(), out_path, &filter);
+    let mut matches: Vec<(HashMatch, bool)> = files.par_iter()
+        .filter_map(|(source, path)| {
+            let hashes = hashes::hash_file(path).ok()?;
+            let known_good = allowlist.allows_hashes(&hashes);
+            let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+            let found: Vec<(HashMatch, bool)> = iocs.matches(&hashes).into_iter().map(|ioc| (HashMatch {
+                path: path.to_string_lossy().to_string(),
+                source: *source,
+                size,
+                hash_type: ioc.ioc_type,
+                ioc: ioc.value.clone(),
+                list: ioc.list.clone(),
+                description: ioc.description.clone(),
+                event: ioc.event.clone(),
+                tags: ioc.tags_text(),
+                confidence: ioc.confidence,
+            }, known_good)).collect();
+            Some(found)
+        })
+        .flatten()
+        .collect();
+    let (amcache_checked, amcache) = amcache_matches(out_path, &iocs)?;
+    matches.extend(amcache.into_iter().map(|m| {
+        let known_good = allowlist.allows_hash(&m.ioc);
+        (m, known_good)
+    }));
+    let found = matches.len();
+    let mut matches: Vec<HashMatch> = matches.into_iter()
+        .filter(|(m, known_good)| !known_good && !allowlist.allows_hit(&m.ioc, &m.path))
+        .map(|(m, _)| m)
+        .collect();
+    let suppressed = found - matches.len();
+    matches.sort_by(|a, b| a.ioc.cmp(&b.ioc).then(a.source.cmp(&b.source)).then(a.path.cmp(&b.path)));
+    if matches.is_empty() {
+        writer.write_record(MATCH_HEADER)?;
+    }
+    for found in &matches {
+        writer.serialize(found)?;
+    }
+    writer.flush()?;
+    Ok(format!(
+        "Hash IOC matches: {} from {} hash IOCs, with {} files hashed and {amcache_checked} Amcache entries checked, and {suppressed} matches suppressed by the allowlist",
+        matches.len(), iocs.len(), files.len()
+    ))
+}
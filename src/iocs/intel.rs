@@ -0,0 +1,238 @@
+/*
+Offline import of threat intel. The JSON of MISP events and STIX 2.1 bundles exported
+from a threat intel platform is read from disk, so the IOCs of a case can come from
+intel without a live MISP instance, i.e. on an air-gapped case box. Each indicator is
+added to the typed IOCs with its event or report, tags and confidence, which are then
+written with its findings.
+*/
+
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde_json::Value;
+
+use super::loader::{Ioc, IocType};
+
+/// The confidence of the tags of the MISP confidence-level taxonomy
+const MISP_CONFIDENCE: &[(&str, u8)] = &[
+    ("completely-confident", 100),
+    ("usually-confident", 75),
+    ("fairly-confident", 50),
+    ("rarely-confident", 25),
+    ("unconfident", 0),
+];
+
+/// the IOC type of a MISP attribute type, or of a part of a composite type such as
+/// `filename|sha256`. None is recognised from the value, and types that can't be matched
+/// in files, such as ports and YARA rules, are skipped
+fn misp_type(attribute_type: &str) -> Option<Option<IocType>> {
+    let ioc_type = match attribute_type {
+        "ip-src" | "ip-dst" | "ip" => None,
+        "domain" | "hostname" => Some(IocType::Domain),
+        "url" | "uri" | "link" => Some(IocType::Url),
+        "md5" => Some(IocType::Md5),
+        "sha1" => Some(IocType::Sha1),
+        "sha256" => Some(IocType::Sha256),
+        "filename" => Some(IocType::Filename),
+        "regkey" => Some(IocType::RegistryKey),
+        "mutex" | "named pipe" | "user-agent" | "email" | "email-src" | "email-dst" | "email-subject"
+            | "pattern-in-file" | "pattern-in-memory" | "windows-service-name" | "windows-scheduled-task" => Some(IocType::String),
+        _ => return None,
+    };
+    Some(ioc_type)
+}
+
+/// the IOCs of a MISP attribute, splitting the values of a composite type
+fn misp_attribute(attribute: &Value, event: &str, event_tags: &[String], list: &str, iocs: &mut Vec<Ioc>) -> Result<()> {
+    if attribute.get("to_ids").is_some_and(|ids| ids == false) || attribute.get("deleted").is_some_and(|d| d == true) {
+        return Ok(());
+    }
+    let text = |key: &str| attribute.get(key).and_then(Value::as_str).unwrap_or_default();
+    let attribute_type = match text("type") {
+        "malware-sample" => "filename|md5",
+        other => other,
+    };
+    let mut tags = event_tags.to_vec();
+    for tag in misp_tags(attribute) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    let confidence = misp_confidence(&tags);
+    let types: Vec<&str> = attribute_type.split('|').collect();
+    let values: Vec<&str> = match types.len() {
+        1 => vec![text("value")],
+        n => text("value").splitn(n, '|').collect(),
+    };
+    for (part_type, value) in types.iter().zip(values) {
+        let Some(mut ioc_type) = misp_type(part_type) else { continue };
+        // a filename with its folders is a path
+        if ioc_type == Some(IocType::Filename) && (value.contains('\\') || value.contains('/')) {
+            ioc_type = Some(IocType::FilePath);
+        }
+        if value.trim().is_empty() {
+            continue;
+        }
+        let mut ioc = Ioc::new(value, ioc_type, list, text("comment"))
+            .with_context(|| format!("The {attribute_type} attribute {} of the MISP event {event}", text("value")))?;
+        ioc.event = event.to_string();
+        ioc.tags = tags.clone();
+        ioc.confidence = confidence;
+        iocs.push(ioc);
+    }
+    Ok(())
+}
+
+/// the names of the tags of a MISP event or attribute
+fn misp_tags(value: &Value) -> Vec<String> {
+    value.get("Tag").and_then(Value::as_array).into_iter()
+        .flatten()
+        .filter_map(|tag| tag.get("name").and_then(Value::as_str))
+        .map(String::from)
+        .collect()
+}
+
+/// the confidence of a `misp:confidence-level` tag, the last when there are more
+fn misp_confidence(tags: &[String]) -> Option<u8> {
+    tags.iter().rev().find_map(|tag| {
+        let level = tag.strip_prefix("misp:confidence-level=")?.trim_matches('"');
+        MISP_CONFIDENCE.iter().find(|(name, _)| *name == level).map(|(_, confidence)| *confidence)
+    })
+}
+
+/// read the IOCs of a MISP event, from its attributes and the attributes of its objects
+fn read_misp_event(event: &Value, list: &str, iocs: &mut Vec<Ioc>) -> Result<()> {
+    let info = event.get("info").and_then(Value::as_str).unwrap_or_default();
+    let name = match event.get("id").and_then(|id| id.as_str().map(String::from).or_else(|| id.as_u64().map(|i| i.to_string()))) {
+        Some(id) if !info.is_empty() => format!("{id}: {info}"),
+        _ => info.to_string(),
+    };
+    let tags = misp_tags(event);
+    let attributes = event.get("Attribute").and_then(Value::as_array).into_iter().flatten();
+    let object_attributes = event.get("Object").and_then(Value::as_array).into_iter()
+        .flatten()
+        .filter(|object| !object.get("deleted").is_some_and(|d| d == true))
+        .flat_map(|object| object.get("Attribute").and_then(Value::as_array).into_iter().flatten());
+    for attribute in attributes.chain(object_attributes) {
+        misp_attribute(attribute, &name, &tags, list, iocs)?;
+    }
+    Ok(())
+}
+
+/// the IOC types of the STIX object paths
+fn stix_type(object: &str, path: &str) -> Option<Option<IocType>> {
+    let path = path.to_lowercase().replace(['\'', '"'], "");
+    let ioc_type = match (object, path.as_str()) {
+        ("ipv4-addr" | "ipv6-addr", "value") => None,
+        ("domain-name", "value") => Some(IocType::Domain),
+        ("url", "value") => Some(IocType::Url),
+        ("file", "hashes.md5") => Some(IocType::Md5),
+        ("file", "hashes.sha-1" | "hashes.sha1") => Some(IocType::Sha1),
+        ("file", "hashes.sha-256" | "hashes.sha256") => Some(IocType::Sha256),
+        ("file", "name") => Some(IocType::Filename),
+        ("file", "parent_directory_ref.path") | ("directory", "path") => Some(IocType::FilePath),
+        ("windows-registry-key", "key") => Some(IocType::RegistryKey),
+        ("email-addr", "value") | ("mutex", "name") | ("process", "command_line") => Some(IocType::String),
+        _ => return None,
+    };
+    Some(ioc_type)
+}
+
+/// the values of the comparisons of a STIX pattern, i.e.
+/// `[file:hashes.'SHA-256' = '...'] OR [domain-name:value = 'evil.com']`. LIKE and
+/// MATCHES are read as regexes, and other comparisons are skipped
+fn stix_pattern(pattern: &str) -> Vec<(Option<IocType>, String)> {
+    let re = Regex::new(r"(?i)([a-z0-9-]+):([a-z0-9_.'\-\[\]\*]+)\s*(=|LIKE|MATCHES)\s*'((?:[^'\\]|\\.)*)'")
+        .expect("The STIX comparison regex is valid");
+    re.captures_iter(pattern)
+        .filter_map(|c| {
+            let ioc_type = stix_type(&c[1].to_lowercase(), &c[2])?;
+            let value = c[4].replace("\\'", "'").replace("\\\\", "\\");
+            match c[3].to_uppercase().as_str() {
+                "=" => Some((ioc_type, value)),
+                "MATCHES" => Some((Some(IocType::Regex), value)),
+                _ => {
+                    let like = regex::escape(&value).replace('%', ".*").replace('_', ".");
+                    Some((Some(IocType::Regex), format!("(?i){like}")))
+                }
+            }
+        })
+        .collect()
+}
+
+/// read the IOCs of the indicators of a STIX bundle, with the names of the reports and
+/// groupings that refer to them as their event
+fn read_stix(bundle: &Value, list: &str, iocs: &mut Vec<Ioc>) -> Result<()> {
+    let objects = bundle.get("objects").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+    let mut events: HashMap<&str, Vec<&str>> = HashMap::new();
+    for object in objects {
+        let kind = object.get("type").and_then(Value::as_str).unwrap_or_default();
+        if kind != "report" && kind != "grouping" {
+            continue;
+        }
+        let name = object.get("name").and_then(Value::as_str).unwrap_or_default();
+        for reference in object.get("object_refs").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            events.entry(reference).or_default().push(name);
+        }
+    }
+    for indicator in objects {
+        let text = |key: &str| indicator.get(key).and_then(Value::as_str).unwrap_or_default();
+        if text("type") != "indicator" || indicator.get("revoked").is_some_and(|r| r == true) {
+            continue;
+        }
+        if !matches!(text("pattern_type"), "" | "stix") {
+            continue;
+        }
+        let mut tags: Vec<String> = Vec::new();
+        for tag in ["labels", "indicator_types"].iter()
+            .flat_map(|key| indicator.get(*key).and_then(Value::as_array).into_iter().flatten())
+            .filter_map(Value::as_str)
+        {
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        let event = events.get(text("id")).map(|names| names.join("; ")).unwrap_or_default();
+        let description = match text("description") {
+            "" => text("name"),
+            description => description,
+        };
+        let confidence = indicator.get("confidence").and_then(Value::as_u64).map(|c| c.min(100) as u8);
+        for (ioc_type, value) in stix_pattern(text("pattern")) {
+            let mut ioc = Ioc::new(&value, ioc_type, list, description)
+                .with_context(|| format!("The pattern {} of the STIX indicator {}", text("pattern"), text("id")))?;
+            ioc.event = event.clone();
+            ioc.tags = tags.clone();
+            ioc.confidence = confidence;
+            iocs.push(ioc);
+        }
+    }
+    Ok(())
+}
+
+/// read the IOCs of the JSON exported from MISP, being an event, a list of events or the
+/// response of a search, or of a STIX 2.1 bundle
+pub fn read_intel(path: &Path, iocs: &mut Vec<Ioc>) -> Result<()> {
+    let json: Value = serde_json::from_slice(&std::fs::read(path)?)
+        .with_context(|| format!("Unable to read the JSON of {}", path.display()))?;
+    let list = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    if json.get("type").is_some_and(|t| t == "bundle") {
+        return read_stix(&json, &list, iocs);
+    }
+    let events: Vec<&Value> = match &json {
+        Value::Object(map) if map.contains_key("Event") => vec![&json],
+        Value::Object(map) => match map.get("response") {
+            Some(Value::Array(response)) => response.iter().collect(),
+            Some(response) => response.get("Event").into_iter().flat_map(|e| e.as_array().into_iter().flatten()).collect(),
+            None => bail!("{} isn't a MISP event or STIX bundle", path.display()),
+        },
+        Value::Array(events) => events.iter().collect(),
+        _ => bail!("{} isn't a MISP event or STIX bundle", path.display()),
+    };
+    for event in events {
+        let event = event.get("Event").unwrap_or(event);
+        read_misp_event(event, &list, iocs).with_context(|| format!("In {}", path.display()))?;
+    }
+    Ok(())
+}
\ No newline at end of file
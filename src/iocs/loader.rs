@@ -0,0 +1,516 @@
+/*
+Typed IOCs. Each IOC is given a type, either from the list or recognised from its value,
+so it can be matched the way that suits it: CIDR ranges contain the addresses found,
+domains match their subdomains, and hashes are compared with the hashes of the scanned
+files. Lists are plain text with an optional `type:` prefix on each line, CSV with a
+value and type column, YAML, or the JSON of MISP events and STIX bundles exported from
+threat intel platforms. Defanged values, such as `hxxp://evil[.]com`, are refanged as
+they are read.
+*/
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::str::FromStr;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_yaml::Value;
+
+use super::intel;
+use crate::ops::file_ops;
+
+/// The extensions of a value read as a filename rather than a domain, i.e. `evil.exe`.
+/// Use the `domain:` prefix for a domain with one of these top level domains
+const FILE_EXTENSIONS: &[&str] = &[
+    "7z", "bat", "bin", "cab", "cmd", "cpl", "csv", "dat", "db", "dll", "doc", "docm", "docx",
+    "exe", "gz", "hta", "img", "ini", "iso", "jar", "js", "jse", "json", "lnk", "log", "msi",
+    "pdf", "ps1", "psm1", "py", "rar", "scr", "sh", "sys", "tar", "tmp", "txt", "vbe", "vbs",
+    "xls", "xlsm", "xlsx", "xml", "zip",
+];
+/// The roots of registry keys, removed so the key matches however the hive was parsed
+const REGISTRY_ROOTS: &[&str] = &[
+    "HKEY_LOCAL_MACHINE", "HKLM", "HKEY_CURRENT_USER", "HKCU", "HKEY_USERS", "HKU",
+    "HKEY_CLASSES_ROOT", "HKCR",
+];
+
+/// The type of an IOC, deciding how it is matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IocType {
+    Md5,
+    Sha1,
+    Sha256,
+    Ipv4,
+    Ipv6,
+    Cidr,
+    Domain,
+    Url,
+    FilePath,
+    RegistryKey,
+    Regex,
+    Filename,
+    String,
+}
+
+impl IocType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IocType::Md5 => "md5",
+            IocType::Sha1 => "sha1",
+            IocType::Sha256 => "sha256",
+            IocType::Ipv4 => "ipv4",
+            IocType::Ipv6 => "ipv6",
+            IocType::Cidr => "cidr",
+            IocType::Domain => "domain",
+            IocType::Url => "url",
+            IocType::FilePath => "file_path",
+            IocType::RegistryKey => "registry_key",
+            IocType::Regex => "regex",
+            IocType::Filename => "filename",
+            IocType::String => "string",
+        }
+    }
+
+    /// get the type from its name in a list, allowing the common alternatives
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase().replace(['-', ' '], "_");
+        let ioc_type = match name.as_str() {
+            "md5" => IocType::Md5,
+            "sha1" => IocType::Sha1,
+            "sha256" => IocType::Sha256,
+            "ip" | "ipv4" | "ip_address" => IocType::Ipv4,
+            "ipv6" => IocType::Ipv6,
+            "cidr" | "ip_range" | "subnet" => IocType::Cidr,
+            "domain" | "hostname" | "fqdn" => IocType::Domain,
+            "url" | "uri" => IocType::Url,
+            "path" | "file_path" | "filepath" => IocType::FilePath,
+            "registry" | "registry_key" | "regkey" => IocType::RegistryKey,
+            "regex" | "regexp" => IocType::Regex,
+            "filename" | "file_name" | "file" => IocType::Filename,
+            "string" | "text" | "keyword" => IocType::String,
+            _ => return None,
+        };
+        Some(ioc_type)
+    }
+
+    pub fn is_hash(&self) -> bool {
+        matches!(self, IocType::Md5 | IocType::Sha1 | IocType::Sha256)
+    }
+}
+
+/// An IOC with the list it came from, and the intel of those imported from MISP or STIX
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ioc {
+    pub value: String,
+    pub ioc_type: IocType,
+    pub list: String,
+    pub description: String,
+    /// the MISP event or STIX report of the IOC
+    pub event: String,
+    pub tags: Vec<String>,
+    /// from 0 to 100, when the intel gives it
+    pub confidence: Option<u8>,
+}
+
+impl Ioc {
+    /// refang and normalise the value, recognising its type when it isn't given
+    pub fn new(value: &str, ioc_type: Option<IocType>, list: &str, description: &str) -> Result<Self> {
+        let mut value = value.trim().to_string();
+        let ioc_type = match ioc_type {
+            Some(IocType::Regex) => IocType::Regex,
+            Some(ioc_type) => {
+                value = refang(&value);
+                ioc_type
+            }
+            None => {
+                value = refang(&value);
+                detect_type(&value)
+            }
+        };
+        if value.is_empty() {
+            bail!("The IOC is empty");
+        }
+        match ioc_type {
+            IocType::Md5 | IocType::Sha1 | IocType::Sha256 => {
+                let len = match ioc_type {
+                    IocType::Md5 => 32,
+                    IocType::Sha1 => 40,
+                    _ => 64,
+                };
+                if value.len() != len || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                    bail!("{value} isn't a {} hash", ioc_type.name());
+                }
+                value = value.to_lowercase();
+            }
+            IocType::Ipv4 => {
+                value = Ipv4Addr::from_str(&value).with_context(|| format!("{value} isn't an IPv4 address"))?.to_string();
+            }
+            IocType::Ipv6 => {
+                value = Ipv6Addr::from_str(&value).with_context(|| format!("{value} isn't an IPv6 address"))?.to_string();
+            }
+            IocType::Cidr => {
+                IpNetwork::parse(&value)?;
+            }
+            IocType::Regex => {
+                regex::bytes::Regex::new(&value).with_context(|| format!("{value} isn't a valid regex"))?;
+            }
+            IocType::Domain => value = value.trim_end_matches('.').to_lowercase(),
+            _ => (),
+        }
+        Ok(Ioc {
+            value,
+            ioc_type,
+            list: list.to_string(),
+            description: description.to_string(),
+            event: String::new(),
+            tags: Vec::new(),
+            confidence: None,
+        })
+    }
+
+    /// add the intel of an IOC of the same type and value, from another list or event
+    pub fn merge(&mut self, other: &Ioc) {
+        if self.event.is_empty() {
+            self.event = other.event.clone();
+        }
+        if self.description.is_empty() {
+            self.description = other.description.clone();
+        }
+        for tag in &other.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        self.confidence = self.confidence.max(other.confidence);
+    }
+
+    /// the tags as one field of the findings
+    pub fn tags_text(&self) -> String {
+        self.tags.join("; ")
+    }
+
+    /// the strings matched in the files for the IOC. Paths and registry keys are also
+    /// matched with the other separator and JSON escaped, paths without their drive and
+    /// registry keys without their root, so they match however they were parsed
+    pub fn patterns(&self) -> Vec<String> {
+        let mut patterns = match self.ioc_type {
+            IocType::Cidr | IocType::Regex => Vec::new(),
+            IocType::FilePath => {
+                // the drive letter isn't in the paths of the MFT or a mounted image
+                let bytes = self.value.as_bytes();
+                let path = match bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+                    true => &self.value[2..],
+                    false => self.value.as_str(),
+                };
+                let windows = path.replace('/', "\\");
+                vec![windows.clone(), path.replace('\\', "/"), windows.replace('\\', "\\\\")]
+            }
+            IocType::RegistryKey => {
+                let key = self.value.replace('/', "\\");
+                let key = REGISTRY_ROOTS.iter()
+                    .find_map(|root| strip_prefix_ignore_case(&key, &format!("{root}\\")))
+                    .unwrap_or(&key)
+                    .to_string();
+                vec![key.clone(), key.replace('\\', "\\\\")]
+            }
+            _ => vec![self.value.clone()],
+        };
+        let mut seen = HashSet::new();
+        patterns.retain(|p| !p.is_empty() && seen.insert(p.to_lowercase()));
+        patterns
+    }
+}
+
+/// An IPv4 or IPv6 network in CIDR notation, i.e. `10.0.0.0/8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn parse(value: &str) -> Result<Self> {
+        let (addr, prefix) = value.split_once('/').unwrap_or((value, ""));
+        let addr = IpAddr::from_str(addr.trim()).with_context(|| format!("{value} isn't a CIDR range"))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix.trim() {
+            "" => max,
+            prefix => prefix.parse().ok().filter(|p| *p <= max).with_context(|| format!("{value} has an invalid prefix"))?,
+        };
+        Ok(IpNetwork { addr, prefix })
+    }
+
+    /// check if the address is in the network
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+/// refang a defanged IOC, i.e. `hxxps://evil[.]com` to `https://evil.com`
+pub fn refang(value: &str) -> String {
+    let mut value = value.trim().to_string();
+    for (defanged, fanged) in [
+        ("[.]", "."), ("(.)", "."), ("{.}", "."), ("[dot]", "."), ("(dot)", "."),
+        ("[:]", ":"), ("[://]", "://"), ("[@]", "@"), ("[at]", "@"), ("[/]", "/"),
+    ] {
+        value = value.replace(defanged, fanged);
+    }
+    for (defanged, fanged) in [("hxxps", "https"), ("hxxp", "http"), ("fxp", "ftp")] {
+        if let Some(rest) = strip_prefix_ignore_case(&value, &format!("{defanged}:")) {
+            value = format!("{fanged}:{rest}");
+        }
+    }
+    value
+}
+
+/// recognise the type of an IOC from its value, falling back to a string
+pub fn detect_type(value: &str) -> IocType {
+    let is_hex = !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit());
+    match value.len() {
+        32 if is_hex => return IocType::Md5,
+        40 if is_hex => return IocType::Sha1,
+        64 if is_hex => return IocType::Sha256,
+        _ => (),
+    }
+    if Ipv4Addr::from_str(value).is_ok() {
+        return IocType::Ipv4;
+    }
+    if Ipv6Addr::from_str(value).is_ok() {
+        return IocType::Ipv6;
+    }
+    if value.contains('/') && IpNetwork::parse(value).is_ok() {
+        return IocType::Cidr;
+    }
+    if let Some((scheme, _)) = value.split_once("://") {
+        if !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)) {
+            return IocType::Url;
+        }
+    }
+    if REGISTRY_ROOTS.iter().any(|root| strip_prefix_ignore_case(value, &format!("{root}\\")).is_some()) {
+        return IocType::RegistryKey;
+    }
+    let bytes = value.as_bytes();
+    let drive = bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && (bytes[2] == b'\\' || bytes[2] == b'/');
+    if drive || value.starts_with("\\\\") || value.contains('\\') || (value.starts_with('/') && value.len() > 1) {
+        return IocType::FilePath;
+    }
+    let valid_label = |label: &str| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if let Some((name, extension)) = value.rsplit_once('.') {
+        let extension = extension.to_lowercase();
+        if !name.is_empty() && !value.contains(' ') && FILE_EXTENSIONS.contains(&extension.as_str()) {
+            return IocType::Filename;
+        }
+        if value.split('.').all(valid_label) && extension.len() >= 2 && extension.chars().all(|c| c.is_ascii_alphabetic()) {
+            return IocType::Domain;
+        }
+    }
+    IocType::String
+}
+
+/// split a line of a text list into its type and value, when it has a known `type:` prefix
+fn typed_line(line: &str) -> (Option<IocType>, &str) {
+    match line.split_once(':') {
+        Some((name, value)) if !value.starts_with("//") => match IocType::from_name(name) {
+            Some(ioc_type) => (Some(ioc_type), value.trim()),
+            None => (None, line),
+        },
+        _ => (None, line),
+    }
+}
+
+/// the name of a list from its file
+fn list_name(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// log an IOC that isn't valid, which is skipped so the rest of its list is still read
+fn log_skipped(out_log: &Path, path: &Path, line: u64, e: &anyhow::Error) {
+    file_ops::log_msg(out_log, format!("[-] Skipped line {line} of the IOC file {}: {e:#}", path.display()));
+}
+
+/// read a text list, one IOC on each line with an optional `type:` prefix. Empty lines
+/// and `#` comments are skipped, as are lines that aren't valid, which are logged
+fn read_text(path: &Path, iocs: &mut Vec<Ioc>, out_log: &Path) -> Result<()> {
+    let data = std::fs::read(path)?;
+    let list = list_name(path);
+    for (i, line) in String::from_utf8_lossy(&data).lines().enumerate() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (ioc_type, value) = typed_line(line);
+        match Ioc::new(value, ioc_type, &list, "") {
+            Ok(ioc) => iocs.push(ioc),
+            Err(e) => log_skipped(out_log, path, i as u64 + 1, &e),
+        }
+    }
+    Ok(())
+}
+
+/// read a CSV list, with a value column (`value`, `ioc` or `indicator`) and optional
+/// `type`, `list` and `description` columns. Rows that aren't valid are skipped and logged
+fn read_csv(path: &Path, iocs: &mut Vec<Ioc>, out_log: &Path) -> Result<()> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim().trim_start_matches('\u{feff}').to_lowercase()).collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let Some(value_col) = column(&["value", "ioc", "indicator"]) else {
+        bail!("{} has no value, ioc or indicator column", path.display());
+    };
+    let type_col = column(&["type", "ioc_type"]);
+    let list_col = column(&["list", "source"]);
+    let desc_col = column(&["description", "comment"]);
+    let default_list = list_name(path);
+    let mut record = csv::StringRecord::new();
+    loop {
+        let line = reader.position().line();
+        match reader.read_record(&mut record) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => {
+                log_skipped(out_log, path, e.position().map_or(line, |p| p.line()), &e.into());
+                continue;
+            }
+        }
+        let line = record.position().map_or(line, |p| p.line());
+        let field = |col: Option<usize>| col.and_then(|c| record.get(c)).map(str::trim).unwrap_or("");
+        let value = field(Some(value_col));
+        if value.is_empty() {
+            continue;
+        }
+        let ioc_type = match field(type_col) {
+            "" => None,
+            name => match IocType::from_name(name) {
+                Some(ioc_type) => Some(ioc_type),
+                None => {
+                    log_skipped(out_log, path, line, &anyhow::anyhow!("Unknown IOC type {name}"));
+                    continue;
+                }
+            },
+        };
+        let list = match field(list_col) {
+            "" => default_list.as_str(),
+            list => list,
+        };
+        match Ioc::new(value, ioc_type, list, field(desc_col)) {
+            Ok(ioc) => iocs.push(ioc),
+            Err(e) => log_skipped(out_log, path, line, &e),
+        }
+    }
+    Ok(())
+}
+
+/// read an entry of a YAML list, either the value or a mapping of the value, type and
+/// description
+fn yaml_entry(entry: &Value, list: &str, iocs: &mut Vec<Ioc>) -> Result<()> {
+    let text = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    match entry {
+        Value::Mapping(map) => {
+            let get = |key: &str| map.get(key).and_then(text).unwrap_or_default();
+            let ioc_type = match get("type").as_str() {
+                "" => None,
+                name => Some(IocType::from_name(name).with_context(|| format!("Unknown IOC type {name}"))?),
+            };
+            let entry_list = get("list");
+            let list = if entry_list.is_empty() { list } else { &entry_list };
+            iocs.push(Ioc::new(&get("value"), ioc_type, list, &get("description"))?);
+        }
+        other => match text(other) {
+            Some(value) => {
+                let (ioc_type, value) = typed_line(&value);
+                iocs.push(Ioc::new(value, ioc_type, list, "")?);
+            }
+            None => bail!("Unexpected IOC entry {other:?}"),
+        },
+    }
+    Ok(())
+}
+
+/// read a YAML list, either a sequence of entries or a mapping of list names to entries
+fn read_yaml(path: &Path, iocs: &mut Vec<Ioc>) -> Result<()> {
+    let yaml: Value = serde_yaml::from_str(&std::fs::read_to_string(path)?)
+        .with_context(|| format!("Unable to read the YAML of {}", path.display()))?;
+    let default_list = list_name(path);
+    let in_file = |e: anyhow::Error| e.context(format!("In {}", path.display()));
+    match yaml {
+        Value::Sequence(entries) => {
+            for entry in &entries {
+                yaml_entry(entry, &default_list, iocs).map_err(in_file)?;
+            }
+        }
+        Value::Mapping(lists) => {
+            for (list, entries) in &lists {
+                let list = list.as_str().unwrap_or(&default_list);
+                let Value::Sequence(entries) = entries else { bail!("The list {list} of {} isn't a sequence", path.display()) };
+                for entry in entries {
+                    yaml_entry(entry, list, iocs).map_err(in_file)?;
+                }
+            }
+        }
+        _ => bail!("{} isn't a list of IOCs", path.display()),
+    }
+    Ok(())
+}
+
+/// read the file by its extension
+fn read_list(path: &Path, iocs: &mut Vec<Ioc>, out_log: &Path) -> Result<()> {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "csv" => read_csv(path, iocs, out_log),
+        "yaml" | "yml" => read_yaml(path, iocs),
+        "json" => intel::read_intel(path, iocs),
+        _ => read_text(path, iocs, out_log),
+    }
+}
+
+/// load the IOCs of a list, or of each list in a folder. The list of each IOC is the
+/// name of its file unless the list sets it, and an IOC of the same type is kept once,
+/// with the intel of each list it is in. IOCs that aren't valid are logged to out_log
+pub fn load_iocs(path: &Path, out_log: &Path) -> Result<Vec<Ioc>> {
+    let mut iocs = Vec::new();
+    if path.is_dir() {
+        let mut files: Vec<_> = std::fs::read_dir(path)?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && p.extension().is_some_and(|e| ["txt", "csv", "yaml", "yml", "json"].contains(&e.to_string_lossy().to_lowercase().as_str())))
+            .collect();
+        files.sort();
+        for file in files {
+            read_list(&file, &mut iocs, out_log)?;
+        }
+    } else {
+        read_list(path, &mut iocs, out_log).with_context(|| format!("Unable to read the IOC file {}", path.display()))?;
+    }
+    let mut seen: HashMap<(IocType, String), usize> = HashMap::new();
+    let mut unique: Vec<Ioc> = Vec::with_capacity(iocs.len());
+    for ioc in iocs {
+        match seen.entry((ioc.ioc_type, ioc.value.to_lowercase())) {
+            Entry::Occupied(first) => unique[*first.get()].merge(&ioc),
+            Entry::Vacant(entry) => {
+                entry.insert(unique.len());
+                unique.push(ioc);
+            }
+        }
+    }
+    Ok(unique)
+}
@@ -0,0 +1,489 @@
+/*
+Case-wide report of the IOC findings. The IOC findings, hash matches and YARA matches of
+each host's wiskess output, such as those processed by whipped, are aggregated into one
+summary of each IOC: the hosts it was seen on, the first and last times of the timeline
+events that contain it, the artefacts it was found in and example lines. The report is
+written as CSV and as HTML to send for scoping, with the IOCs found on each host.
+*/
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use aho_corasick::AhoCorasickBuilder;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use super::loader::{Ioc, IocType};
+use crate::parsers::common;
+use crate::timeline::builder::{self, TIMELINE_FILE, TimelineEvent};
+use crate::timeline::merge;
+
+pub const CASE_IOC_FOLDER: &str = "Case-IOC-Report";
+pub const CASE_IOC_CSV: &str = "case_ioc_report.csv";
+pub const CASE_IOC_HOSTS_CSV: &str = "case_ioc_hosts.csv";
+pub const CASE_IOC_HTML: &str = "case_ioc_report.html";
+/// The folder of each host's wiskess output with the IOC findings
+const FINDINGS_FOLDER: &str = "IOC_Findings";
+/// The example lines kept of each IOC
+const EXAMPLES: usize = 5;
+/// The example lines are cut to this many characters
+const EXAMPLE_CHARS: usize = 200;
+
+/// A finding of an IOC on a host
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Finding {
+    pub host: String,
+    pub ioc: String,
+    /// the IOC type, or yara for a YARA rule
+    pub ioc_type: String,
+    pub list: String,
+    pub event: String,
+    /// the artefact it was found in
+    pub source: String,
+    pub path: String,
+    pub line: String,
+    /// the hits the finding counts for, as the IOC scanner writes up to `--max-hits` rows
+    /// of an IOC with its hits in the summary
+    pub hits: usize,
+}
+
+/// An example line of an IOC
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    pub host: String,
+    pub path: String,
+    pub line: String,
+}
+
+/// The findings of an IOC across the hosts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IocAggregate {
+    pub ioc: String,
+    pub ioc_type: String,
+    pub list: String,
+    pub event: String,
+    pub hits: usize,
+    /// the hosts with their hits
+    pub hosts: BTreeMap<String, usize>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub sources: BTreeSet<String>,
+    pub examples: Vec<Example>,
+}
+
+impl IocAggregate {
+    /// widen the first and last times with the time of an event
+    fn seen(&mut self, datetime: &str) {
+        if datetime.is_empty() {
+            return;
+        }
+        if self.first_seen.is_empty() || datetime < self.first_seen.as_str() {
+            self.first_seen = datetime.to_string();
+        }
+        if datetime > self.last_seen.as_str() {
+            self.last_seen = datetime.to_string();
+        }
+    }
+}
+
+/// The row of an IOC in the CSV report
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct IocRow<'a> {
+    ioc: &'a str,
+    #[serde(rename = "Type")]
+    ioc_type: &'a str,
+    list: &'a str,
+    event: &'a str,
+    hosts: usize,
+    host_names: String,
+    hits: usize,
+    first_seen: &'a str,
+    last_seen: &'a str,
+    sources: String,
+    examples: String,
+}
+
+/// The IOCs found on a host
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HostIocs {
+    pub host: String,
+    pub iocs: usize,
+    pub hits: usize,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// The case-wide IOC report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IocReport {
+    pub outputs: usize,
+    pub iocs: Vec<IocAggregate>,
+    pub hosts: Vec<HostIocs>,
+}
+
+impl IocReport {
+    /// format the IOCs found on each host as a table for the terminal
+    pub fn table(&self) -> String {
+        let width = self.hosts.iter().map(|h| h.host.len()).max().unwrap_or(0).max(4);
+        let mut table = format!("{:<width$}  {:>6}  {:>10}  {:<24}  {:<24}\n", "Host", "IOCs", "Hits", "First seen", "Last seen");
+        for host in &self.hosts {
+            table.push_str(&format!(
+                "{:<width$}  {:>6}  {:>10}  {:<24}  {:<24}\n",
+                host.host, host.iocs, host.hits, host.first_seen, host.last_seen
+            ));
+        }
+        table
+    }
+}
+
+/// find each host's wiskess output with IOC findings in the folder, such as the local
+/// storage of whipped, or a single wiskess output folder
+pub fn find_outputs(input: &Path) -> Vec<PathBuf> {
+    let mut outputs: Vec<PathBuf> = WalkDir::new(input)
+        .max_depth(2)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.into_path())
+        .filter(|p| common::join_case_insensitive(p, FINDINGS_FOLDER).is_some_and(|f| f.is_dir()))
+        .collect();
+    outputs.sort();
+    outputs
+}
+
+/// the artefact a finding is from, being the output file of the tool for the findings
+/// in the wiskess output, or the file of the data source
+fn artefact(path: &str, in_output: bool) -> String {
+    let path = Path::new(path);
+    let name = match in_output {
+        true => path.file_stem(),
+        false => path.file_name(),
+    };
+    name.map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// read the findings of the IOC scanner, hash matching and YARA in an IOC_Findings folder
+pub fn read_findings(folder: &Path, host: &str) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let mut files: Vec<PathBuf> = fs::read_dir(folder)?.flatten().map(|e| e.path()).filter(|p| p.is_file()).collect();
+    files.sort();
+    for file in files {
+        let extension = file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        match extension.as_str() {
+            "csv" => read_csv_findings(&file, host, &summary_hits(&file), &mut findings)?,
+            "jsonl" => read_yara_findings(&file, host, &mut findings)?,
+            _ => (),
+        }
+    }
+    Ok(findings)
+}
+
+/// the hits of each IOC, by its type and lowercase value, from the `_summary.csv` of the
+/// IOC scanner beside its findings, if there is one
+fn summary_hits(file: &Path) -> HashMap<(String, String), usize> {
+    let mut hits = HashMap::new();
+    let stem = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let Ok(mut reader) = csv::Reader::from_path(file.with_file_name(format!("{stem}_summary.csv"))) else { return hits };
+    let Ok(headers) = reader.headers().cloned() else { return hits };
+    let column = |name: &str| headers.iter().position(|h| h.trim_start_matches('\u{feff}') == name);
+    let (Some(ioc_col), Some(type_col), Some(hits_col)) = (column("Ioc"), column("Type"), column("Hits")) else { return hits };
+    for record in reader.records().flatten() {
+        let (Some(ioc), Some(ioc_type)) = (record.get(ioc_col), record.get(type_col)) else { continue };
+        if let Some(count) = record.get(hits_col).and_then(|h| h.parse().ok()) {
+            hits.insert((ioc_type.to_string(), ioc.to_lowercase()), count);
+        }
+    }
+    hits
+}
+
+/// read the hits of the IOC scanner or the hash matches, by the columns of the CSV. The
+/// hits of the IOC scanner are those of its summary, counted on the first row of each
+/// IOC, and each other row is a hit. The summaries and other CSVs are skipped
+fn read_csv_findings(file: &Path, host: &str, summary: &HashMap<(String, String), usize>, findings: &mut Vec<Finding>) -> Result<()> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(file)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim_start_matches('\u{feff}').to_string()).collect();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (Some(ioc_col), Some(path_col)) = (column("Ioc"), column("Path")) else { return Ok(()) };
+    let scanner = [column("Scope"), column("Context"), column("Type")];
+    let hashes = [column("Source"), column("HashType")];
+    let (list_col, event_col) = (column("List"), column("Event"));
+    if scanner.iter().any(Option::is_none) && hashes.iter().any(Option::is_none) {
+        return Ok(());
+    }
+    let mut counted = HashSet::new();
+    for record in reader.records().flatten() {
+        let field = |col: Option<usize>| col.and_then(|c| record.get(c)).unwrap_or_default().to_string();
+        let path = field(Some(path_col));
+        let finding = match scanner {
+            [Some(_), Some(_), Some(_)] => {
+                let key = (field(scanner[2]), field(Some(ioc_col)).to_lowercase());
+                let hits = match summary.get(&key) {
+                    Some(hits) if counted.insert(key) => *hits,
+                    Some(_) => 0,
+                    None => 1,
+                };
+                Finding {
+                    ioc_type: field(scanner[2]),
+                    source: artefact(&path, field(scanner[0]) == "output"),
+                    line: field(scanner[1]),
+                    hits,
+                    ..Default::default()
+                }
+            }
+            _ => {
+                let source = field(hashes[0]);
+                Finding {
+                    ioc_type: field(hashes[1]),
+                    source: match source.as_str() {
+                        "data_source" => artefact(&path, false),
+                        _ => source.clone(),
+                    },
+                    line: format!("{} of {path} from {source}", field(hashes[1])),
+                    hits: 1,
+                    ..Default::default()
+                }
+            }
+        };
+        findings.push(Finding {
+            host: host.to_string(),
+            ioc: field(Some(ioc_col)),
+            list: field(list_col),
+            event: field(event_col),
+            path,
+            ..finding
+        });
+    }
+    Ok(())
+}
+
+/// read the YARA matches, with the rule as the IOC and its matched strings as the line
+fn read_yara_findings(file: &Path, host: &str, findings: &mut Vec<Finding>) -> Result<()> {
+    for line in fs::read_to_string(file)?.lines() {
+        let Ok(found) = serde_json::from_str::<serde_json::Value>(line) else { continue };
+        let text = |key: &str| found.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let Some(rule) = found.get("rule").and_then(|r| r.as_str()) else { continue };
+        let path = text("path");
+        let strings: Vec<String> = found.get("strings").and_then(|s| s.as_array()).into_iter()
+            .flatten()
+            .filter_map(|s| Some(format!("{} {}", s.get("identifier")?.as_str()?, s.get("data")?.as_str()?)))
+            .collect();
+        findings.push(Finding {
+            host: host.to_string(),
+            ioc: rule.to_string(),
+            ioc_type: "yara".to_string(),
+            list: text("namespace"),
+            event: String::new(),
+            source: match text("source").as_str() {
+                "data_source" => artefact(&path, false),
+                source => source.to_string(),
+            },
+            path,
+            line: strings.join("; "),
+            hits: 1,
+        });
+    }
+    Ok(())
+}
+
+/// the times of the first and last events of the host's timeline that contain each IOC,
+/// by the IOC's index in the aggregates
+fn timeline_times(output: &Path, iocs: &[&IocAggregate]) -> Result<HashMap<usize, (String, String)>> {
+    let mut times = HashMap::new();
+    let Some(timeline) = common::join_case_insensitive(output, "Timeline").map(|t| t.join(TIMELINE_FILE)) else { return Ok(times) };
+    if !timeline.is_file() {
+        return Ok(times);
+    }
+    let mut patterns = Vec::new();
+    let mut owners = Vec::new();
+    for (i, aggregate) in iocs.iter().enumerate() {
+        let Some(ioc_type) = IocType::from_name(&aggregate.ioc_type) else { continue };
+        let Ok(ioc) = Ioc::new(&aggregate.ioc, Some(ioc_type), "", "") else { continue };
+        for pattern in ioc.patterns() {
+            patterns.push(pattern);
+            owners.push(i);
+        }
+    }
+    if patterns.is_empty() {
+        return Ok(times);
+    }
+    let searcher = AhoCorasickBuilder::new().ascii_case_insensitive(true).build(&patterns)?;
+    builder::read_events(&timeline, |event: TimelineEvent| {
+        let text = format!("{} {} {}", event.message, event.path, event.user);
+        for found in searcher.find_overlapping_iter(&text) {
+            let (first, last) = times.entry(owners[found.pattern().as_usize()]).or_insert_with(|| (event.datetime.clone(), event.datetime.clone()));
+            if event.datetime < *first {
+                *first = event.datetime.clone();
+            }
+            if event.datetime > *last {
+                *last = event.datetime.clone();
+            }
+        }
+        Ok(())
+    })?;
+    Ok(times)
+}
+
+/// aggregate the IOC findings of the hosts' wiskess outputs into the case report
+pub fn aggregate(outputs: &[PathBuf]) -> Result<IocReport> {
+    if outputs.is_empty() {
+        bail!("No IOC findings were found to report, run the IOC enrichers for each host first");
+    }
+    let mut aggregates: Vec<IocAggregate> = Vec::new();
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+    // the first and last times of each host, from the one read of its timeline
+    let mut host_times: HashMap<String, (String, String)> = HashMap::new();
+    for output in outputs {
+        let host = merge::output_host(output);
+        let Some(folder) = common::join_case_insensitive(output, FINDINGS_FOLDER) else { continue };
+        let mut on_host = BTreeSet::new();
+        for finding in read_findings(&folder, &host)? {
+            let key = (finding.ioc_type.clone(), finding.ioc.to_lowercase());
+            let i = *index.entry(key).or_insert_with(|| {
+                aggregates.push(IocAggregate {
+                    ioc: finding.ioc.clone(),
+                    ioc_type: finding.ioc_type.clone(),
+                    list: finding.list.clone(),
+                    event: finding.event.clone(),
+                    ..Default::default()
+                });
+                aggregates.len() - 1
+            });
+            let aggregate = &mut aggregates[i];
+            aggregate.hits += finding.hits;
+            *aggregate.hosts.entry(host.clone()).or_insert(0) += finding.hits;
+            if !finding.source.is_empty() {
+                aggregate.sources.insert(finding.source);
+            }
+            if aggregate.examples.len() < EXAMPLES && !aggregate.examples.iter().any(|e| e.line == finding.line) {
+                aggregate.examples.push(Example {
+                    host: host.clone(),
+                    path: finding.path,
+                    line: finding.line.chars().take(EXAMPLE_CHARS).collect(),
+                });
+            }
+            on_host.insert(i);
+        }
+        let on_host: Vec<usize> = on_host.into_iter().collect();
+        let found: Vec<&IocAggregate> = on_host.iter().map(|i| &aggregates[*i]).collect();
+        for (i, (first, last)) in timeline_times(output, &found)? {
+            let (host_first, host_last) = host_times.entry(host.clone()).or_insert_with(|| (first.clone(), last.clone()));
+            if first < *host_first {
+                *host_first = first.clone();
+            }
+            if last > *host_last {
+                *host_last = last.clone();
+            }
+            let aggregate = &mut aggregates[on_host[i]];
+            aggregate.seen(&first);
+            aggregate.seen(&last);
+        }
+    }
+
+    let mut hosts: BTreeMap<String, HostIocs> = BTreeMap::new();
+    for aggregate in &aggregates {
+        for (host, hits) in &aggregate.hosts {
+            let summary = hosts.entry(host.clone()).or_insert_with(|| {
+                let (first_seen, last_seen) = host_times.get(host).cloned().unwrap_or_default();
+                HostIocs { host: host.clone(), first_seen, last_seen, ..Default::default() }
+            });
+            summary.iocs += 1;
+            summary.hits += hits;
+        }
+    }
+    aggregates.sort_by(|a, b| b.hosts.len().cmp(&a.hosts.len()).then(b.hits.cmp(&a.hits)).then(a.ioc.cmp(&b.ioc)));
+    Ok(IocReport { outputs: outputs.len(), iocs: aggregates, hosts: hosts.into_values().collect() })
+}
+
+/// escape text for HTML
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// the report as a standalone HTML page
+pub fn to_html(report: &IocReport) -> String {
+    let mut html = String::from(concat!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Case IOC Report</title>\n<style>\n",
+        "body { font-family: Segoe UI, Arial, sans-serif; margin: 2em; color: #222; }\n",
+        "table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }\n",
+        "th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }\n",
+        "th { background: #2b3e50; color: #fff; }\n",
+        "tr:nth-child(even) { background: #f4f6f8; }\n",
+        "code { font-size: 0.9em; word-break: break-all; }\n",
+        "</style>\n</head>\n<body>\n<h1>Case IOC Report</h1>\n",
+    ));
+    let hits: usize = report.iocs.iter().map(|i| i.hits).sum();
+    html.push_str(&format!(
+        "<p>{} IOCs found with {hits} hits on {} hosts, from {} wiskess outputs.</p>\n",
+        report.iocs.len(), report.hosts.len(), report.outputs
+    ));
+    html.push_str("<h2>Hosts</h2>\n<table>\n<tr><th>Host</th><th>IOCs</th><th>Hits</th><th>First seen</th><th>Last seen</th></tr>\n");
+    for host in &report.hosts {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(&host.host), host.iocs, host.hits, html_escape(&host.first_seen), html_escape(&host.last_seen)
+        ));
+    }
+    html.push_str("</table>\n<h2>IOCs</h2>\n<table>\n<tr><th>IOC</th><th>Type</th><th>List</th><th>Hosts</th><th>Hits</th><th>First seen</th><th>Last seen</th><th>Sources</th><th>Examples</th></tr>\n");
+    for ioc in &report.iocs {
+        let hosts: Vec<String> = ioc.hosts.iter().map(|(h, n)| format!("{} ({n})", html_escape(h))).collect();
+        let sources: Vec<String> = ioc.sources.iter().map(|s| html_escape(s)).collect();
+        let examples: Vec<String> = ioc.examples.iter()
+            .map(|e| format!("<li>{}: <code>{}</code><br>{}</li>", html_escape(&e.host), html_escape(&e.path), html_escape(&e.line)))
+            .collect();
+        let list = match ioc.event.is_empty() {
+            true => html_escape(&ioc.list),
+            false => format!("{}<br>{}", html_escape(&ioc.list), html_escape(&ioc.event)),
+        };
+        html.push_str(&format!(
+            "<tr><td><code>{}</code></td><td>{}</td><td>{list}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><ul>{}</ul></td></tr>\n",
+            html_escape(&ioc.ioc), html_escape(&ioc.ioc_type), hosts.join("<br>"), ioc.hits,
+            html_escape(&ioc.first_seen), html_escape(&ioc.last_seen), sources.join("<br>"), examples.join("")
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// aggregate the IOC findings of the hosts' wiskess outputs, writing the report of each IOC
+/// as CSV and HTML and the IOCs of each host as CSV to the out_dir
+pub fn write_report(outputs: &[PathBuf], out_dir: &Path) -> Result<IocReport> {
+    let report = aggregate(outputs)?;
+    let mut writer = common::csv_writer(&out_dir.join(CASE_IOC_CSV))?;
+    for ioc in &report.iocs {
+        writer.serialize(IocRow {
+            ioc: &ioc.ioc,
+            ioc_type: &ioc.ioc_type,
+            list: &ioc.list,
+            event: &ioc.event,
+            hosts: ioc.hosts.len(),
+            host_names: ioc.hosts.keys().cloned().collect::<Vec<_>>().join("; "),
+            hits: ioc.hits,
+            first_seen: &ioc.first_seen,
+            last_seen: &ioc.last_seen,
+            sources: ioc.sources.iter().cloned().collect::<Vec<_>>().join("; "),
+            examples: ioc.examples.iter().map(|e| format!("{}: {}", e.host, e.line)).collect::<Vec<_>>().join(" | "),
+        })?;
+    }
+    writer.flush()?;
+    let mut writer = common::csv_writer(&out_dir.join(CASE_IOC_HOSTS_CSV))?;
+    for host in &report.hosts {
+        writer.serialize(host)?;
+    }
+    writer.flush()?;
+    fs::write(out_dir.join(CASE_IOC_HTML), to_html(&report))?;
+    Ok(report)
+}
\ No newline at end of file
@@ -0,0 +1,552 @@
+/*
+Builtin IOC scanner, replacing the ripgrep and jq enrichers so the IOC matching is the
+same on Windows and Linux. The IOCs are matched case-insensitive with Aho-Corasick, as
+ASCII and as UTF-16LE so the strings of binary artefacts are found, over the wiskess
+output and the data source in parallel. Each hit is written with its context to
+IOC_Findings, with a summary of the hits of each IOC as CSV and JSON. The IOCs are typed
+by the loader, so each is matched as its type suits, and the hits record the type and
+list of the IOC. Hits of the allowlist are suppressed and counted in the summary.
+*/
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+use regex::bytes::{Regex, RegexBuilder};
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use super::allowlist::{self, Allowlist};
+use super::loader::{self, Ioc, IocType, IpNetwork};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+use crate::parsers::hashes::{self, HashingReader};
+use crate::timeline::builder::{arg_value, split_args};
+
+/// The bytes of a file searched at a time
+pub const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// The bytes either side of a hit kept as its context
+const CONTEXT_BYTES: usize = 64;
+/// The bytes at the end of a chunk held back, so the boundary after a hit is known
+const GUARD_BYTES: usize = 4;
+/// The bytes kept between chunks when there are regex IOCs, the longest regex hit that
+/// is found across chunks
+const REGEX_SPAN: usize = 4096;
+/// The hits of an IOC written for each file by default, all are counted
+const DEFAULT_MAX_HITS: usize = 100;
+/// The contexts and paths of each IOC kept in the JSON summary
+const SUMMARY_EXAMPLES: usize = 20;
+
+/// Where the file that was scanned is from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Output,
+    DataSource,
+}
+
+/// How the IOC was encoded where it was found, or the file hash it matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    Ascii,
+    Utf16le,
+    FileHash,
+}
+
+/// A hit of an IOC in a file, with the text around it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct IocHit {
+    pub ioc: String,
+    #[serde(rename = "Type")]
+    pub ioc_type: IocType,
+    pub list: String,
+    pub scope: Scope,
+    pub path: String,
+    pub offset: u64,
+    pub encoding: Encoding,
+    pub context: String,
+    /// the intel of the IOC, from MISP or STIX
+    pub event: String,
+    pub tags: String,
+    pub confidence: Option<u8>,
+}
+
+/// The hits of the IOCs in a file, with every hit counted but only the first of each
+/// IOC kept
+#[derive(Debug, Clone, Default)]
+pub struct FileHits {
+    pub hits: Vec<IocHit>,
+    pub counts: BTreeMap<usize, usize>,
+    pub bytes: u64,
+}
+
+/// The hits of an IOC across the files scanned
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IocSummary {
+    pub ioc: String,
+    pub ioc_type: Option<IocType>,
+    pub list: String,
+    pub event: String,
+    pub tags: Vec<String>,
+    pub confidence: Option<u8>,
+    pub hits: usize,
+    pub files: usize,
+    pub output_hits: usize,
+    pub data_source_hits: usize,
+    /// the hits suppressed by the allowlist
+    pub suppressed: usize,
+    pub paths: Vec<String>,
+    pub contexts: Vec<String>,
+}
+
+/// The summary of a scan, written as JSON
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanReport {
+    pub iocs: usize,
+    pub files_scanned: usize,
+    pub files_skipped: usize,
+    pub suppressed: usize,
+    pub bytes_scanned: u64,
+    pub summary: Vec<IocSummary>,
+}
+
+/// How a hit must be separated from the text around it, by the type of its IOC
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Boundary {
+    None,
+    Word,
+    /// IPv4 addresses, not part of a longer address or number
+    Dotted,
+    /// domains, which may be a subdomain but not part of a longer name
+    Domain,
+    /// filenames, which may follow a path but not be part of a longer name
+    Filename,
+    /// IPv6 addresses, not part of a longer address
+    Colon,
+}
+
+impl Boundary {
+    fn for_ioc(ioc_type: IocType, word: bool) -> Self {
+        match ioc_type {
+            IocType::Md5 | IocType::Sha1 | IocType::Sha256 => Boundary::Word,
+            IocType::Ipv4 | IocType::Cidr => Boundary::Dotted,
+            IocType::Ipv6 => Boundary::Colon,
+            IocType::Domain => Boundary::Domain,
+            IocType::Filename => Boundary::Filename,
+            _ if word => Boundary::Word,
+            _ => Boundary::None,
+        }
+    }
+
+    /// check the hit is separate from the characters before and after it, nearest first
+    fn separate(&self, before: [Option<u8>; 2], after: [Option<u8>; 2]) -> bool {
+        let word = |c: Option<u8>| c.is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_');
+        let joins = |c: [Option<u8>; 2], seps: &[u8]| word(c[0]) || (c[0].is_some_and(|c| seps.contains(&c)) && word(c[1]));
+        match self {
+            Boundary::None => true,
+            Boundary::Word => !word(before[0]) && !word(after[0]),
+            Boundary::Dotted => !joins(before, b".-") && !joins(after, b".-"),
+            Boundary::Domain => !word(before[0]) && before[0] != Some(b'-') && !joins(after, b".-"),
+            Boundary::Filename => !word(before[0]) && !matches!(before[0], Some(b'-' | b'.')) && !joins(after, b".-"),
+            Boundary::Colon => !joins(before, b".:") && !joins(after, b".:"),
+        }
+    }
+}
+
+/// A string matched by the automata, for the IOC at the index
+struct Pattern {
+    ioc: usize,
+    boundary: Boundary,
+}
+
+/// A match in the buffer, by the index of its IOC
+#[derive(Clone, Copy)]
+struct RawMatch {
+    ioc: usize,
+    start: usize,
+    end: usize,
+    encoding: Encoding,
+}
+
+/// Matches the IOCs as ASCII and UTF-16LE, ignoring the case of ASCII letters. Regexes
+/// and CIDR ranges are matched in ASCII text, and hashes are also compared with the
+/// hashes of each file
+pub struct IocScanner {
+    pub iocs: Vec<Ioc>,
+    patterns: Vec<Pattern>,
+    ascii: AhoCorasick,
+    utf16: AhoCorasick,
+    regexes: Vec<(usize, Regex)>,
+    networks: Vec<(usize, IpNetwork)>,
+    ipv4: Option<Regex>,
+    ipv6: Option<Regex>,
+    hashes: bool,
+    keep: usize,
+}
+
+impl IocScanner {
+    /// build the automata of the IOCs. With `word`, strings, paths and URLs must not be
+    /// inside a longer word, as `rg -w` would match
+    pub fn new(iocs: Vec<Ioc>, word: bool) -> Result<Self> {
+        if iocs.is_empty() {
+            bail!("There are no IOCs to scan for");
+        }
+        let mut patterns = Vec::new();
+        let mut strings = Vec::new();
+        let mut regexes = Vec::new();
+        let mut networks = Vec::new();
+        for (i, ioc) in iocs.iter().enumerate() {
+            for pattern in ioc.patterns() {
+                patterns.push(Pattern { ioc: i, boundary: Boundary::for_ioc(ioc.ioc_type, word) });
+                strings.push(pattern);
+            }
+            match ioc.ioc_type {
+                IocType::Regex => regexes.push((i, RegexBuilder::new(&ioc.value).case_insensitive(true).build()?)),
+                IocType::Cidr => networks.push((i, IpNetwork::parse(&ioc.value)?)),
+                _ => (),
+            }
+        }
+        let build = |patterns: Vec<Vec<u8>>| AhoCorasickBuilder::new()
+            .ascii_case_insensitive(true)
+            .match_kind(MatchKind::Standard)
+            .build(patterns);
+        let utf16: Vec<Vec<u8>> = strings.iter()
+            .map(|p| p.encode_utf16().flat_map(|c| c.to_le_bytes()).collect())
+            .collect();
+        let max_len = utf16.iter().map(Vec::len).max().unwrap_or(0);
+        let span = if regexes.is_empty() { max_len } else { max_len.max(REGEX_SPAN) };
+        let has_network = |v4: bool| networks.iter().any(|(_, n): &(usize, IpNetwork)| n.addr.is_ipv4() == v4);
+        Ok(IocScanner {
+            ascii: build(strings.iter().map(|p| p.as_bytes().to_vec()).collect())?,
+            utf16: build(utf16)?,
+            ipv4: has_network(true).then(|| Regex::new(r"\d{1,3}(?:\.\d{1,3}){3}")).transpose()?,
+            ipv6: has_network(false).then(|| Regex::new(r"(?i)[0-9a-f]{0,4}(?::[0-9a-f]{0,4}){2,7}")).transpose()?,
+            hashes: iocs.iter().any(|ioc| ioc.ioc_type.is_hash()),
+            keep: span + GUARD_BYTES + CONTEXT_BYTES * 2,
+            iocs,
+            patterns,
+            regexes,
+            networks,
+        })
+    }
+
+    /// check the hit is separate from the text around it, as its boundary needs. A side
+    /// of the IOC that isn't a letter or number, i.e. a path's `\`, is always separate
+    fn is_separate(&self, data: &[u8], m: &RawMatch, boundary: Boundary) -> bool {
+        if boundary == Boundary::None {
+            return true;
+        }
+        let width = match m.encoding {
+            Encoding::Utf16le => 2,
+            _ => 1,
+        };
+        let char_at = |i: Option<usize>| i.and_then(|i| data.get(i).copied().filter(|_| width == 1 || data.get(i + 1) == Some(&0)));
+        let before = [char_at(m.start.checked_sub(width)), char_at(m.start.checked_sub(width * 2))];
+        let after = [char_at(Some(m.end)), char_at(Some(m.end + width))];
+        let first = char_at(Some(m.start)).is_some_and(|c| c.is_ascii_alphanumeric());
+        let last = char_at(m.end.checked_sub(width)).is_some_and(|c| c.is_ascii_alphanumeric());
+        boundary.separate(
+            if first { before } else { [None, None] },
+            if last { after } else { [None, None] },
+        )
+    }
+
+    /// find the matches ending after `from` and up to `to` in the buffer
+    fn search(&self, data: &[u8], from: usize, to: usize, mut each: impl FnMut(RawMatch)) {
+        let in_window = |end: usize| end > from && end <= to;
+        for (automaton, encoding) in [(&self.ascii, Encoding::Ascii), (&self.utf16, Encoding::Utf16le)] {
+            for m in automaton.find_overlapping_iter(data) {
+                if !in_window(m.end()) {
+                    continue;
+                }
+                let pattern = &self.patterns[m.pattern().as_usize()];
+                let raw = RawMatch { ioc: pattern.ioc, start: m.start(), end: m.end(), encoding };
+                if self.is_separate(data, &raw, pattern.boundary) {
+                    each(raw);
+                }
+            }
+        }
+        for (ioc, regex) in &self.regexes {
+            for m in regex.find_iter(data).filter(|m| in_window(m.end())) {
+                each(RawMatch { ioc: *ioc, start: m.start(), end: m.end(), encoding: Encoding::Ascii });
+            }
+        }
+        for (regex, boundary) in [(&self.ipv4, Boundary::Dotted), (&self.ipv6, Boundary::Colon)] {
+            let Some(regex) = regex else { continue };
+            for m in regex.find_iter(data).filter(|m| in_window(m.end())) {
+                let Some(ip) = std::str::from_utf8(m.as_bytes()).ok().and_then(|ip| IpAddr::from_str(ip).ok()) else { continue };
+                let found = RawMatch { ioc: 0, start: m.start(), end: m.end(), encoding: Encoding::Ascii };
+                if !self.is_separate(data, &found, boundary) {
+                    continue;
+                }
+                for (ioc, _) in self.networks.iter().filter(|(_, n)| n.contains(&ip)) {
+                    each(RawMatch { ioc: *ioc, ..found });
+                }
+            }
+        }
+    }
+
+    /// scan a reader in chunks, keeping the end of each chunk so hits across chunks are
+    /// found. `each` is called with the IOC's index, the offset, encoding and context of
+    /// each hit, and the number of bytes read is returned
+    pub fn scan_reader(&self, mut reader: impl Read, mut each: impl FnMut(usize, u64, Encoding, String)) -> Result<u64> {
+        let keep = self.keep;
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut buffer_start = 0u64;
+        let mut reported = 0;
+        loop {
+            let read = reader.by_ref().take(CHUNK_SIZE as u64).read_to_end(&mut buffer)?;
+            let last = read < CHUNK_SIZE;
+            let limit = match last {
+                true => buffer.len(),
+                false => buffer.len().saturating_sub(GUARD_BYTES),
+            };
+            self.search(&buffer, reported, limit, |m| {
+                each(m.ioc, buffer_start + m.start as u64, m.encoding, context(&buffer, &m));
+            });
+            if last {
+                return Ok(buffer_start + buffer.len() as u64);
+            }
+            let drop = buffer.len().saturating_sub(keep);
+            buffer.drain(..drop);
+            buffer_start += drop as u64;
+            reported = limit - drop;
+        }
+    }
+
+    /// scan a file, keeping up to `max_hits` hits of each IOC. When there are hash IOCs
+    /// the file is hashed as it is scanned, and a matching hash is a hit at offset 0
+    pub fn scan_file(&self, path: &Path, scope: Scope, max_hits: usize) -> Result<FileHits> {
+        let file = File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+        let mut found = FileHits::default();
+        let path_str = path.to_string_lossy().to_string();
+        let mut add = |ioc: usize, offset: u64, encoding: Encoding, context: String| {
+            let count = found.counts.entry(ioc).or_insert(0);
+            *count += 1;
+            if *count <= max_hits {
+                found.hits.push(IocHit {
+                    ioc: self.iocs[ioc].value.clone(),
+                    ioc_type: self.iocs[ioc].ioc_type,
+                    list: self.iocs[ioc].list.clone(),
+                    scope,
+                    path: path_str.clone(),
+                    offset,
+                    encoding,
+                    context,
+                    event: self.iocs[ioc].event.clone(),
+                    tags: self.iocs[ioc].tags_text(),
+                    confidence: self.iocs[ioc].confidence,
+                });
+            }
+        };
+        let mut reader = HashingReader::new(BufReader::new(file));
+        let bytes = match self.hashes {
+            true => self.scan_reader(&mut reader, &mut add)?,
+            false => self.scan_reader(&mut reader.inner, &mut add)?,
+        };
+        if self.hashes {
+            let hashes = reader.hasher.finish();
+            for (i, ioc) in self.iocs.iter().enumerate() {
+                let hash = match ioc.ioc_type {
+                    IocType::Md5 => &hashes.md5,
+                    IocType::Sha1 => &hashes.sha1,
+                    IocType::Sha256 => &hashes.sha256,
+                    _ => continue,
+                };
+                if *hash == ioc.value {
+                    add(i, 0, Encoding::FileHash, format!("{} of the file", ioc.ioc_type.name()));
+                }
+            }
+        }
+        found.bytes = bytes;
+        Ok(found)
+    }
+}
+
+/// the printable text around a hit, with other characters as spaces
+fn context(data: &[u8], m: &RawMatch) -> String {
+    let printable = |c: char| match c.is_control() || c == char::REPLACEMENT_CHARACTER {
+        true => ' ',
+        false => c,
+    };
+    let text: String = match m.encoding {
+        Encoding::Ascii | Encoding::FileHash => {
+            let start = m.start.saturating_sub(CONTEXT_BYTES);
+            let end = (m.end + CONTEXT_BYTES).min(data.len());
+            String::from_utf8_lossy(&data[start..end]).chars().map(printable).collect()
+        }
+        Encoding::Utf16le => {
+            // keep the alignment of the hit's characters
+            let start = m.start - (m.start.min(CONTEXT_BYTES * 2) & !1);
+            let end = (m.end + CONTEXT_BYTES * 2).min(data.len());
+            let units: Vec<u16> = data[start..end].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units).chars().map(printable).collect()
+        }
+    };
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// list the files to scan in the root, skipping the folders given and, in the top levels
+/// of the data source, the outputs of wiskess
+fn list_files(root: &Path, scope: Scope, skip: &[PathBuf]) -> Vec<(Scope, PathBuf)> {
+    if root.is_file() {
+        return vec![(scope, root.to_path_buf())];
+    }
+    let wiskess_output = |e: &walkdir::DirEntry| scope == Scope::DataSource && allowlist::skips_wiskess_output(e);
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| !skip.iter().any(|s| e.path() == s) && !wiskess_output(e))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| (scope, e.into_path()))
+        .collect()
+}
+
+/// scan the files in parallel, returning the hits sorted by IOC, path and offset, and
+/// the summary of each IOC. The hits of the allowlist are only counted as suppressed
+pub fn scan_files(scanner: &IocScanner, files: &[(Scope, PathBuf)], max_hits: usize, allowlist: &Allowlist) -> (Vec<IocHit>, ScanReport, Vec<String>) {
+    let results: Vec<(Scope, &PathBuf, Result<FileHits>, bool)> = files.par_iter()
+        .map(|(scope, path)| {
+            let found = scanner.scan_file(path, *scope, max_hits);
+            // only the files with hits are hashed to check for a known good file
+            let known_good = allowlist.has_hashes()
+                && found.as_ref().is_ok_and(|f| !f.counts.is_empty())
+                && hashes::hash_file(path).is_ok_and(|h| allowlist.allows_hashes(&h));
+            (*scope, path, found, known_good)
+        })
+        .collect();
+    let mut report = ScanReport { iocs: scanner.iocs.len(), ..Default::default() };
+    let mut summary: Vec<IocSummary> = scanner.iocs.iter()
+        .map(|ioc| IocSummary {
+            ioc: ioc.value.clone(),
+            ioc_type: Some(ioc.ioc_type),
+            list: ioc.list.clone(),
+            event: ioc.event.clone(),
+            tags: ioc.tags.clone(),
+            confidence: ioc.confidence,
+            ..Default::default()
+        })
+        .collect();
+    let mut hits = Vec::new();
+    let mut errors = Vec::new();
+    for (scope, path, result, known_good) in results {
+        let mut found = match result {
+            Ok(found) => found,
+            Err(e) => {
+                report.files_skipped += 1;
+                errors.push(format!("{:#}", e));
+                continue;
+            }
+        };
+        report.files_scanned += 1;
+        report.bytes_scanned += found.bytes;
+        let path_str = path.to_string_lossy();
+        let suppressed = |ioc: &str| known_good || allowlist.allows_hit(ioc, &path_str);
+        found.hits.retain(|hit| !suppressed(&hit.ioc));
+        for (pattern, count) in found.counts {
+            let ioc = &mut summary[pattern];
+            if suppressed(&ioc.ioc) {
+                ioc.suppressed += count;
+                report.suppressed += count;
+                continue;
+            }
+            ioc.hits += count;
+            ioc.files += 1;
+            match scope {
+                Scope::Output => ioc.output_hits += count,
+                Scope::DataSource => ioc.data_source_hits += count,
+            }
+            if ioc.paths.len() < SUMMARY_EXAMPLES {
+                ioc.paths.push(path.to_string_lossy().to_string());
+            }
+        }
+        for hit in &found.hits {
+            let ioc = summary.iter_mut()
+                .find(|s| s.ioc == hit.ioc && s.ioc_type == Some(hit.ioc_type))
+                .expect("hits are of the IOCs scanned");
+            if ioc.contexts.len() < SUMMARY_EXAMPLES && !ioc.contexts.contains(&hit.context) {
+                ioc.contexts.push(hit.context.clone());
+            }
+        }
+        hits.extend(found.hits);
+    }
+    hits.sort_by(|a, b| a.ioc.cmp(&b.ioc)
+        .then(a.ioc_type.cmp(&b.ioc_type))
+        .then(a.path.cmp(&b.path))
+        .then(a.offset.cmp(&b.offset)));
+    for ioc in &mut summary {
+        ioc.paths.sort();
+    }
+    summary.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.ioc.cmp(&b.ioc)));
+    report.summary = summary;
+    (hits, report, errors)
+}
+
+/// run the builtin IOC scanner over the wiskess output, skipping its own outfolder, and
+/// the wisker's input. The IOCs are loaded from the case's IOC file, or a folder of
+/// lists, or `--ioc-file <path>`.
+/// `--output-only` or `--input-only` scan one of them, `--word` only matches whole
+/// words, `--max-hits <n>` sets the hits of each IOC written for a file (100 by
+/// default) and `--allowlist <file>` sets the allowlist. The hits are written to the
+/// outfile, with `_summary.csv` and `_summary.json` of the IOCs beside it
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let flags = split_args(&args.args);
+    let flag = |name: &str| flags.iter().any(|f| f == name);
+    let ioc_file = arg_value(&args.args, "--ioc-file").unwrap_or_else(|| args.main_args.ioc_file.clone());
+    let max_hits = match arg_value(&args.args, "--max-hits") {
+        Some(n) => n.parse().with_context(|| format!("Invalid --max-hits {n}"))?,
+        None => DEFAULT_MAX_HITS,
+    };
+    let scanner = IocScanner::new(loader::load_iocs(Path::new(&ioc_file), &args.main_args.out_log)?, flag("--word"))?;
+
+    let mut files = Vec::new();
+    if !flag("--input-only") {
+        files.extend(list_files(Path::new(&args.main_args.out_path), Scope::Output, std::slice::from_ref(&args.outfolder)));
+    }
+    let input = Path::new(&args.input);
+    if !flag("--output-only") && !args.input.is_empty() && args.input != "wiskess_none" && input.exists() {
+        files.extend(list_files(input, Scope::DataSource, &[args.outfolder.clone(), PathBuf::from(&args.main_args.out_path)]));
+    }
+    let allowlist = Allowlist::load(args)?;
+    let (hits, report, errors) = scan_files(&scanner, &files, max_hits, &allowlist);
+    for error in errors.iter().take(SUMMARY_EXAMPLES) {
+        file_ops::log_msg(&args.main_args.out_log, format!("[!] IOC scan skipped a file: {error}"));
+    }
+
+    let out_file = args.outfolder.join(&args.outfile);
+    let stem = out_file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "iocs".to_string());
+    let mut writer = common::csv_writer(&out_file)?;
+    if hits.is_empty() {
+        writer.write_record(["Ioc", "Type", "List", "Scope", "Path", "Offset", "Encoding", "Context", "Event", "Tags", "Confidence"])?;
+    }
+    for hit in &hits {
+        writer.serialize(hit)?;
+    }
+    writer.flush()?;
+    let mut writer = common::csv_writer(&args.outfolder.join(format!("{stem}_summary.csv")))?;
+    writer.write_record(["Ioc", "Type", "List", "Hits", "Files", "OutputHits", "DataSourceHits", "Suppressed", "Event", "Tags", "Confidence"])?;
+    for ioc in &report.summary {
+        let ioc_type = ioc.ioc_type.map(|t| t.name()).unwrap_or_default();
+        writer.write_record([
+            ioc.ioc.clone(), ioc_type.to_string(), ioc.list.clone(), ioc.hits.to_string(), ioc.files.to_string(),
+            ioc.output_hits.to_string(), ioc.data_source_hits.to_string(), ioc.suppressed.to_string(), ioc.event.clone(), ioc.tags.join("; "),
+            ioc.confidence.map(|c| c.to_string()).unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    std::fs::write(args.outfolder.join(format!("{stem}_summary.json")), serde_json::to_string_pretty(&report)?)?;
+
+    let matched = report.summary.iter().filter(|s| s.hits > 0).count();
+    Ok(format!(
+        "IOCs matched: {matched} of {}, with {} hits in {} files scanned ({} skipped) and {} hits suppressed by the allowlist",
+        report.iocs, report.summary.iter().map(|s| s.hits).sum::<usize>(), report.files_scanned, report.files_skipped, report.suppressed
+    ))
+}
@@ -0,0 +1,677 @@
+/*
+Builtin YARA scanning. The rule files of the folders given by the wisker's `--rules` and
+the `--yara-rules` of the command line are parsed, with the parsed rules cached by the
+SHA256 of each file so unchanged rule sets are not parsed again. This is not libyara or
+yara-x, only the subset of YARA described in yara_rules is supported, and each rule
+outside it is skipped with an error that is logged, or fails the scan with `--strict`. The text strings of all
+rules are searched in one pass with Aho-Corasick, and the hex strings and regexes are
+prefiltered with a regex set, before the conditions are evaluated. The files of the data
+source and Artefacts are scanned in parallel and the matching rules written as JSONL.
+*/
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use aho_corasick::AhoCorasick;
+use anyhow::{anyhow, bail, Context, Result};
+use rayon::prelude::*;
+use regex::bytes::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use super::allowlist::Allowlist;
+use super::hash_match::{self, HashFilter};
+use super::yara_rules::{self, BinOp, Expr, MetaValue, Pattern, Quantifier, StringSet, YaraRule, YaraString};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::{common, hashes};
+use crate::timeline::builder::{arg_value, split_args};
+
+/// The version of the parsed rules, to ignore the cache of an older parser
+const CACHE_VERSION: u32 = 3;
+/// The folder of the tool path that the parsed rules are cached in, by default
+const CACHE_FOLDER: &str = "yara_cache";
+const RULE_EXTENSIONS: &[&str] = &["yar", "yara"];
+/// The depth of includes followed, in case a rule file includes itself
+const MAX_INCLUDE_DEPTH: usize = 8;
+/// The files larger than this are not scanned by default, in MB
+const DEFAULT_MAX_SIZE_MB: u64 = 64;
+/// The offsets kept of each string, for `at`, `in` and the output
+const MAX_STRING_MATCHES: usize = 1000;
+/// The matches of a regex or hex string counted in a file, as YARA stops at too many
+const MAX_REGEX_MATCHES: usize = 1_000_000;
+/// The offsets of each string written to the output
+const REPORTED_MATCHES: usize = 10;
+/// The bytes of a match written to the output
+const MATCH_DATA_BYTES: usize = 64;
+/// The size limit of the compiled regexes of the hex strings and regexes
+const REGEX_SIZE_LIMIT: usize = 256 * 1024 * 1024;
+const ARTEFACTS_FOLDER: &str = "Artefacts";
+
+/// Where the scanned file was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YaraSource {
+    DataSource,
+    Artefacts,
+}
+
+/// A match of a rule's string
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StringMatch {
+    pub identifier: String,
+    pub offset: usize,
+    pub length: usize,
+    /// the matched bytes, with those not printable escaped as \xHH
+    pub data: String,
+}
+
+/// A file matching a rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct YaraMatch {
+    pub rule: String,
+    pub namespace: String,
+    pub tags: Vec<String>,
+    pub meta: serde_json::Map<String, serde_json::Value>,
+    pub path: String,
+    pub source: YaraSource,
+    pub size: u64,
+    pub strings: Vec<StringMatch>,
+}
+
+/// The parsed rules of a file, as cached
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRules {
+    version: u32,
+    rules: Vec<YaraRule>,
+    errors: Vec<String>,
+}
+
+/// The rules of the rule files, with the errors of the rules that were skipped and of the
+/// files that couldn't be read
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    pub rules: Vec<YaraRule>,
+    pub errors: Vec<String>,
+    /// the rules that couldn't be parsed
+    pub skipped: usize,
+    pub files: usize,
+    pub cached: usize,
+}
+
+/// the rule files of the folders or files, sorted so the rules are in the same order
+pub fn rule_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = paths.iter()
+        .flat_map(|p| WalkDir::new(p).into_iter().flatten())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| p.extension().is_some_and(|e| RULE_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str())))
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// read the rule file with its includes put in place, as YARA does
+fn read_with_includes(path: &Path, depth: usize) -> Result<String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        bail!("Too many nested includes in {}", path.display());
+    }
+    let source = fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    let mut expanded = String::with_capacity(source.len());
+    for line in source.lines() {
+        let included = line.trim().strip_prefix("include")
+            .map(str::trim)
+            .and_then(|v| v.strip_prefix('"'))
+            .and_then(|v| v.strip_suffix('"'));
+        match included {
+            Some(included) => {
+                let included = path.parent().unwrap_or(Path::new("")).join(included);
+                expanded.push_str(&read_with_includes(&included, depth + 1)?);
+            }
+            None => expanded.push_str(line),
+        }
+        expanded.push('\n');
+    }
+    Ok(expanded)
+}
+
+/// load the rules of a file from the cache, or parse and cache them, returning if they
+/// were cached
+fn load_rule_file(path: &Path, cache_dir: Option<&Path>) -> Result<(CachedRules, bool)> {
+    let source = read_with_includes(path, 0)?;
+    let key = common::to_hex(&Sha256::digest(source.as_bytes()));
+    let cache_file = cache_dir.map(|d| d.join(format!("{key}.json")));
+    if let Some(cached) = cache_file.as_ref()
+        .and_then(|f| fs::read(f).ok())
+        .and_then(|b| serde_json::from_slice::<CachedRules>(&b).ok())
+        .filter(|c| c.version == CACHE_VERSION) {
+        return Ok((cached, true));
+    }
+    let namespace = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let (rules, errors) = yara_rules::parse_rules(&source, &namespace);
+    let parsed = CachedRules { version: CACHE_VERSION, rules, errors };
+    if let Some(cache_file) = cache_file {
+        // the cache is only to save time, so the rules are still used if it can't be written
+        let _ = serde_json::to_vec(&parsed).map(|b| fs::write(cache_file, b));
+    }
+    Ok((parsed, false))
+}
+
+/// load the rules of the folders or files, using the cache folder when given
+pub fn load_rules(paths: &[PathBuf], cache_dir: Option<&Path>) -> RuleSet {
+    let cache_dir = cache_dir.filter(|d| fs::create_dir_all(d).is_ok());
+    let mut set = RuleSet::default();
+    for file in rule_files(paths) {
+        set.files += 1;
+        let name = file.display();
+        match load_rule_file(&file, cache_dir) {
+            Ok((parsed, cached)) => {
+                set.cached += cached as usize;
+                set.rules.extend(parsed.rules);
+                set.skipped += parsed.errors.len();
+                set.errors.extend(parsed.errors.into_iter().map(|e| format!("{name}: {e}")));
+            }
+            Err(e) => set.errors.push(format!("{name}: {e}")),
+        }
+    }
+    set
+}
+
+/// The string of a rule that a pattern of the automata or regexes is for
+#[derive(Debug, Clone, Copy)]
+struct Target {
+    rule: usize,
+    string: usize,
+    wide: bool,
+    fullword: bool,
+}
+
+/// The matches of a string in a file, with the offsets and lengths of up to
+/// MAX_STRING_MATCHES of them
+#[derive(Debug, Clone, Default)]
+struct StringHits {
+    count: usize,
+    hits: Vec<(usize, usize)>,
+}
+
+impl StringHits {
+    fn add(&mut self, offset: usize, length: usize) {
+        self.count += 1;
+        if self.hits.len() < MAX_STRING_MATCHES {
+            self.hits.push((offset, length));
+        }
+    }
+}
+
+/// The rules compiled for scanning
+pub struct YaraScanner {
+    rules: Vec<YaraRule>,
+    exact: Option<AhoCorasick>,
+    exact_targets: Vec<Target>,
+    nocase: Option<AhoCorasick>,
+    nocase_targets: Vec<Target>,
+    regexes: Vec<(Target, Regex)>,
+    /// the regexes in one set, to skip those not matching, unless too large to compile
+    regex_set: Option<RegexSet>,
+}
+
+/// the bytes of the text as UTF-16LE, as the `wide` modifier matches
+fn widen(text: &[u8]) -> Vec<u8> {
+    text.iter().flat_map(|b| [*b, 0]).collect()
+}
+
+/// check the match is not inside a longer word, for the `fullword` modifier
+fn is_fullword(data: &[u8], start: usize, end: usize, wide: bool) -> bool {
+    let word_at = |i: usize| data.get(i).is_some_and(|b| b.is_ascii_alphanumeric())
+        && (!wide || data.get(i + 1) == Some(&0));
+    let before = match wide {
+        true => start >= 2 && word_at(start - 2),
+        false => start >= 1 && word_at(start - 1),
+    };
+    !before && !word_at(end)
+}
+
+impl YaraScanner {
+    /// compile the rules, returning the errors of those with regexes that don't compile,
+    /// which are left out
+    pub fn new(rules: Vec<YaraRule>) -> Result<(Self, Vec<String>)> {
+        let mut errors = Vec::new();
+        let mut kept = Vec::new();
+        let mut compiled = Vec::new();
+        for rule in rules {
+            let regexes: Result<Vec<Option<Regex>>> = rule.strings.iter()
+                .map(|s| match &s.pattern {
+                    Pattern::Literal(_) => Ok(None),
+                    Pattern::Regex(pattern) => RegexBuilder::new(pattern)
+                        .size_limit(REGEX_SIZE_LIMIT)
+                        .build()
+                        .map(Some)
+                        .map_err(|e| anyhow!("{}: {e}", s.id)),
+                })
+                .collect();
+            match regexes {
+                Ok(regexes) => {
+                    compiled.push(regexes);
+                    kept.push(rule);
+                }
+                Err(e) => errors.push(format!("{}: {e}", rule.name)),
+            }
+        }
+
+        let mut exact = (Vec::new(), Vec::new());
+        let mut nocase = (Vec::new(), Vec::new());
+        let mut regexes = Vec::new();
+        for (r, (rule, rule_regexes)) in kept.iter().zip(compiled).enumerate() {
+            for (s, (string, regex)) in rule.strings.iter().zip(rule_regexes).enumerate() {
+                let target = |wide| Target { rule: r, string: s, wide, fullword: string.fullword };
+                match (&string.pattern, regex) {
+                    (Pattern::Literal(text), _) => {
+                        let (patterns, targets) = if string.nocase { &mut nocase } else { &mut exact };
+                        if string.ascii {
+                            patterns.push(text.clone());
+                            targets.push(target(false));
+                        }
+                        if string.wide {
+                            patterns.push(widen(text));
+                            targets.push(target(true));
+                        }
+                    }
+                    (Pattern::Regex(_), Some(regex)) => regexes.push((target(false), regex)),
+                    (Pattern::Regex(_), None) => (),
+                }
+            }
+        }
+        let automaton = |patterns: &[Vec<u8>], nocase: bool| -> Result<Option<AhoCorasick>> {
+            if patterns.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(AhoCorasick::builder().ascii_case_insensitive(nocase).build(patterns)?))
+        };
+        let regex_set = RegexSetBuilder::new(regexes.iter().map(|(_, r)| r.as_str()))
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .ok()
+            .filter(|_| !regexes.is_empty());
+        let scanner = YaraScanner {
+            exact: automaton(&exact.0, false)?,
+            exact_targets: exact.1,
+            nocase: automaton(&nocase.0, true)?,
+            nocase_targets: nocase.1,
+            regexes,
+            regex_set,
+            rules: kept,
+        };
+        Ok((scanner, errors))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// find the matches of every string of the rules in the data
+    fn find_strings(&self, data: &[u8]) -> Vec<Vec<StringHits>> {
+        let mut found: Vec<Vec<StringHits>> = self.rules.iter()
+            .map(|r| vec![StringHits::default(); r.strings.len()])
+            .collect();
+        let mut record = |target: &Target, start: usize, end: usize| {
+            if !target.fullword || is_fullword(data, start, end, target.wide) {
+                found[target.rule][target.string].add(start, end - start);
+            }
+        };
+        for (automaton, targets) in [(&self.exact, &self.exact_targets), (&self.nocase, &self.nocase_targets)] {
+            let Some(automaton) = automaton else { continue };
+            for m in automaton.find_overlapping_iter(data) {
+                record(&targets[m.pattern().as_usize()], m.start(), m.end());
+            }
+        }
+        let candidates: Vec<usize> = match &self.regex_set {
+            Some(set) => set.matches(data).into_iter().collect(),
+            None => (0..self.regexes.len()).collect(),
+        };
+        // as in YARA, a regex matches at each offset it can, so its matches may overlap
+        for i in candidates {
+            let (target, regex) = &self.regexes[i];
+            let mut start = 0;
+            for _ in 0..MAX_REGEX_MATCHES {
+                let Some(m) = regex.find_at(data, start) else { break };
+                record(target, m.start(), m.end());
+                start = m.start() + 1;
+                if start > data.len() {
+                    break;
+                }
+            }
+        }
+        found
+    }
+
+    /// scan the data, returning the index of each matching rule that isn't private, with
+    /// the matches of its strings
+    fn scan(&self, data: &[u8]) -> Vec<(usize, Vec<StringHits>)> {
+        let mut found = self.find_strings(data);
+        let mut results: HashMap<(&str, &str), bool> = HashMap::new();
+        let mut failed_globals: HashSet<&str> = HashSet::new();
+        let mut matched = Vec::new();
+        for (r, rule) in self.rules.iter().enumerate() {
+            let eval = Eval { rule, data, strings: &found[r], results: &results };
+            let result = eval.value(&rule.condition).truth();
+            if rule.global && !result {
+                failed_globals.insert(&rule.namespace);
+            }
+            results.insert((&rule.namespace, &rule.name), result);
+            if result {
+                matched.push(r);
+            }
+        }
+        matched.into_iter()
+            .filter(|r| !self.rules[*r].private && !failed_globals.contains(self.rules[*r].namespace.as_str()))
+            .map(|r| (r, std::mem::take(&mut found[r])))
+            .collect()
+    }
+
+    /// scan a file, returning the rules it matches
+    pub fn scan_file(&self, path: &Path, source: YaraSource) -> Result<Vec<YaraMatch>> {
+        let data = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let data = data.as_slice();
+        let matches = self.scan(data).into_iter().map(|(r, hits)| {
+            let rule = &self.rules[r];
+            let strings = rule.strings.iter().zip(hits)
+                .filter(|(s, _)| !s.private)
+                .flat_map(|(s, h)| h.hits.into_iter().take(REPORTED_MATCHES).map(move |(offset, length)| StringMatch {
+                    identifier: s.id.clone(),
+                    offset,
+                    length,
+                    data: escape_bytes(&data[offset..offset + length.min(MATCH_DATA_BYTES)]),
+                }))
+                .collect();
+            YaraMatch {
+                rule: rule.name.clone(),
+                namespace: rule.namespace.clone(),
+                tags: rule.tags.clone(),
+                meta: rule.meta.iter().map(|(k, v)| (k.clone(), meta_json(v))).collect(),
+                path: path.to_string_lossy().to_string(),
+                source,
+                size: data.len() as u64,
+                strings,
+            }
+        }).collect();
+        Ok(matches)
+    }
+}
+
+fn meta_json(value: &MetaValue) -> serde_json::Value {
+    match value {
+        MetaValue::Text(text) => serde_json::Value::from(text.as_str()),
+        MetaValue::Int(value) => serde_json::Value::from(*value),
+        MetaValue::Bool(value) => serde_json::Value::from(*value),
+    }
+}
+
+/// the bytes as text, escaping those not printable as \xHH
+fn escape_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| match b {
+        b' '..=b'~' if *b != b'\\' => (*b as char).to_string(),
+        b => format!("\\x{b:02x}"),
+    }).collect()
+}
+
+/// The value of an expression, which is undefined when reading outside of the file or
+/// dividing by zero, as in YARA
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Bool(bool),
+    Int(i64),
+    Undefined,
+}
+
+impl Value {
+    fn truth(self) -> bool {
+        match self {
+            Value::Bool(b) => b,
+            Value::Int(i) => i != 0,
+            Value::Undefined => false,
+        }
+    }
+
+    fn int(self) -> Option<i64> {
+        match self {
+            Value::Bool(b) => Some(b as i64),
+            Value::Int(i) => Some(i),
+            Value::Undefined => None,
+        }
+    }
+}
+
+/// The evaluation of a rule's condition over a file
+struct Eval<'a> {
+    rule: &'a YaraRule,
+    data: &'a [u8],
+    strings: &'a [StringHits],
+    results: &'a HashMap<(&'a str, &'a str), bool>,
+}
+
+impl Eval<'_> {
+    fn hits(&self, id: &str) -> &StringHits {
+        let i = self.rule.strings.iter().position(|s| s.id == id).unwrap_or(0);
+        &self.strings[i]
+    }
+
+    /// the strings of the set
+    fn set(&self, set: &StringSet) -> Vec<&StringHits> {
+        let in_set = |s: &YaraString| match set {
+            StringSet::Them => true,
+            StringSet::Ids(ids) => ids.iter().any(|id| match id.strip_suffix('*') {
+                Some(prefix) => s.id.starts_with(prefix),
+                None => &s.id == id,
+            }),
+        };
+        self.rule.strings.iter().zip(self.strings).filter(|(s, _)| in_set(s)).map(|(_, h)| h).collect()
+    }
+
+    fn int(&self, expr: &Expr) -> Option<i64> {
+        self.value(expr).int()
+    }
+
+    fn range(&self, lo: &Expr, hi: &Expr) -> Option<(i64, i64)> {
+        Some((self.int(lo)?, self.int(hi)?))
+    }
+
+    fn value(&self, expr: &Expr) -> Value {
+        let int = |v: Option<i64>| v.map(Value::Int).unwrap_or(Value::Undefined);
+        match expr {
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Int(i) => Value::Int(*i),
+            Expr::Filesize => Value::Int(self.data.len() as i64),
+            Expr::Matched(id) => Value::Bool(self.hits(id).count > 0),
+            Expr::Count(id) => Value::Int(self.hits(id).count as i64),
+            Expr::CountIn(id, lo, hi) => int(self.range(lo, hi).map(|(lo, hi)| {
+                self.hits(id).hits.iter().filter(|(o, _)| (lo..=hi).contains(&(*o as i64))).count() as i64
+            })),
+            Expr::Offset(id, index) | Expr::Length(id, index) => int(self.int(index)
+                .filter(|i| *i >= 1)
+                .and_then(|i| self.hits(id).hits.get(i as usize - 1))
+                .map(|(offset, length)| match expr {
+                    Expr::Offset(..) => *offset as i64,
+                    _ => *length as i64,
+                })),
+            Expr::At(id, at) => match self.int(at) {
+                Some(at) => Value::Bool(self.hits(id).hits.iter().any(|(o, _)| *o as i64 == at)),
+                None => Value::Undefined,
+            },
+            Expr::In(id, lo, hi) => match self.range(lo, hi) {
+                Some((lo, hi)) => Value::Bool(self.hits(id).hits.iter().any(|(o, _)| (lo..=hi).contains(&(*o as i64)))),
+                None => Value::Undefined,
+            },
+            Expr::Of(quantifier, set) => {
+                let strings = self.set(set);
+                let matched = strings.iter().filter(|h| h.count > 0).count();
+                Value::Bool(match quantifier {
+                    Quantifier::All => matched == strings.len(),
+                    Quantifier::Any => matched > 0,
+                    Quantifier::None => matched == 0,
+                })
+            }
+            Expr::OfCount(n, set) | Expr::OfPercent(n, set) => {
+                let Some(n) = self.int(n) else { return Value::Undefined };
+                let strings = self.set(set);
+                let matched = strings.iter().filter(|h| h.count > 0).count() as i64;
+                Value::Bool(match expr {
+                    Expr::OfCount(..) => matched >= n,
+                    _ => matched * 100 >= n * strings.len() as i64,
+                })
+            }
+            Expr::ReadInt(read, offset) => {
+                let Some(offset) = self.int(offset).and_then(|o| usize::try_from(o).ok()) else { return Value::Undefined };
+                let Some(bytes) = offset.checked_add(read.size).and_then(|end| self.data.get(offset..end)) else {
+                    return Value::Undefined;
+                };
+                let mut buf = [0u8; 8];
+                match read.big_endian {
+                    true => bytes.iter().rev().enumerate().for_each(|(i, b)| buf[i] = *b),
+                    false => buf[..read.size].copy_from_slice(bytes),
+                }
+                let value = u64::from_le_bytes(buf);
+                let bits = read.size as u32 * 8;
+                Value::Int(match read.signed {
+                    true => ((value << (64 - bits)) as i64) >> (64 - bits),
+                    false => value as i64,
+                })
+            }
+            Expr::RuleRef(name) => Value::Bool(self.results.get(&(self.rule.namespace.as_str(), name.as_str())).copied().unwrap_or(false)),
+            Expr::Not(e) => match self.value(e) {
+                Value::Undefined => Value::Undefined,
+                v => Value::Bool(!v.truth()),
+            },
+            Expr::And(terms) => Value::Bool(terms.iter().all(|t| self.value(t).truth())),
+            Expr::Or(terms) => Value::Bool(terms.iter().any(|t| self.value(t).truth())),
+            Expr::Neg(e) => int(self.int(e).map(i64::wrapping_neg)),
+            Expr::BitNot(e) => int(self.int(e).map(|v| !v)),
+            Expr::Binary(op, a, b) => {
+                let (Some(a), Some(b)) = (self.int(a), self.int(b)) else { return Value::Undefined };
+                match op {
+                    BinOp::Eq => Value::Bool(a == b),
+                    BinOp::Ne => Value::Bool(a != b),
+                    BinOp::Lt => Value::Bool(a < b),
+                    BinOp::Le => Value::Bool(a <= b),
+                    BinOp::Gt => Value::Bool(a > b),
+                    BinOp::Ge => Value::Bool(a >= b),
+                    BinOp::Add => Value::Int(a.wrapping_add(b)),
+                    BinOp::Sub => Value::Int(a.wrapping_sub(b)),
+                    BinOp::Mul => Value::Int(a.wrapping_mul(b)),
+                    BinOp::Div => int(a.checked_div(b)),
+                    BinOp::Mod => int(a.checked_rem(b)),
+                    BinOp::BitAnd => Value::Int(a & b),
+                    BinOp::BitOr => Value::Int(a | b),
+                    BinOp::BitXor => Value::Int(a ^ b),
+                    BinOp::Shl | BinOp::Shr if b < 0 => Value::Undefined,
+                    BinOp::Shl => Value::Int(if b >= 64 { 0 } else { a << b }),
+                    BinOp::Shr => Value::Int(if b >= 64 { 0 } else { a >> b }),
+                }
+            }
+        }
+    }
+}
+
+/// the rule folders or files of the comma separated list
+fn split_paths(paths: &str) -> Vec<PathBuf> {
+    paths.split(',').map(str::trim).filter(|p| !p.is_empty()).map(PathBuf::from).collect()
+}
+
+/// run the builtin YARA scan with the rules of `--rules <folders>` and the command line's
+/// `--yara-rules`, over the wisker's input and the Artefacts folder. Rules that are skipped
+/// are logged, or fail the scan with `--strict`. The parsed rules are
+/// cached in `--cache <folder>`, or yara_cache of the tool path, and the files filtered
+/// with `--max-size <MB>`, `--extensions`, `--include` and `--exclude` as the hash
+/// matching is. The matches are written as JSONL to the outfile, apart from those of the
+/// allowlist, or `--allowlist <file>`, where a rule is suppressed as an IOC is
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let mut rule_paths = split_paths(&arg_value(&args.args, "--rules").unwrap_or_default());
+    rule_paths.extend(split_paths(&args.main_args.yara_rules));
+    rule_paths.retain(|p| p.exists());
+    let cache_dir = arg_value(&args.args, "--cache")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| args.main_args.tool_path.join(CACHE_FOLDER));
+    let rule_set = load_rules(&rule_paths, Some(&cache_dir));
+    let (scanner, compile_errors) = YaraScanner::new(rule_set.rules)?;
+    let skipped = rule_set.skipped + compile_errors.len();
+    let errors: Vec<String> = rule_set.errors.into_iter().chain(compile_errors).collect();
+
+    fs::create_dir_all(&args.outfolder)?;
+    let outfile = args.outfolder.join(&args.outfile);
+    if !errors.is_empty() {
+        let stem = Path::new(&args.outfile).file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let errors_file = args.outfolder.join(format!("{stem}_rule_errors.txt"));
+        fs::write(&errors_file, errors.join("\n") + "\n")?;
+        for e in &errors {
+            file_ops::log_msg(&args.main_args.out_log, format!("[!] YARA rule skipped, {e}"));
+        }
+        let msg = format!(
+            "{skipped} YARA rules were skipped as they aren't supported by the builtin scanner or have errors, listed in {}",
+            errors_file.display()
+        );
+        if split_args(&args.args).iter().any(|a| a == "--strict") {
+            bail!("{msg}");
+        }
+        file_ops::log_msg(&args.main_args.out_log, format!("[!] {msg}"));
+    }
+    if scanner.is_empty() {
+        fs::File::create(&outfile)?;
+        return Ok(format!("No YARA rules to scan with, from {} rule files ({skipped} rules skipped)", rule_set.files));
+    }
+
+    let mut filter = HashFilter::from_args(&args.args)?;
+    if arg_value(&args.args, "--extensions").is_none() {
+        filter.extensions = None;
+    }
+    if arg_value(&args.args, "--max-size").is_none() {
+        filter.max_size = DEFAULT_MAX_SIZE_MB * 1024 * 1024;
+    }
+    let out_path = Path::new(&args.main_args.out_path);
+    let mut files: Vec<(YaraSource, PathBuf)> = Vec::new();
+    if !args.input.is_empty() && args.input != "wiskess_none" {
+        let found = hash_match::walk(Path::new(&args.input), out_path, &filter, |_| true);
+        files.extend(found.into_iter().map(|p| (YaraSource::DataSource, p)));
+    }
+    let found = hash_match::walk(&out_path.join(ARTEFACTS_FOLDER), Path::new(""), &filter, |_| true);
+    files.extend(found.into_iter().map(|p| (YaraSource::Artefacts, p)));
+    let mut seen = BTreeSet::new();
+    files.retain(|(_, p)| seen.insert(p.clone()));
+
+    let allowlist = Allowlist::load(args)?;
+    let scanned: Vec<(Vec<YaraMatch>, bool)> = files.par_iter()
+        .filter_map(|(source, path)| {
+            let found = scanner.scan_file(path, *source).ok()?;
+            let known_good = allowlist.has_hashes()
+                && !found.is_empty()
+                && hashes::hash_file(path).is_ok_and(|h| allowlist.allows_hashes(&h));
+            Some((found, known_good))
+        })
+        .collect();
+    let mut suppressed = 0;
+    let mut matches: Vec<YaraMatch> = Vec::new();
+    for (found, known_good) in scanned {
+        for m in found {
+            match known_good || allowlist.allows_hit(&m.rule, &m.path) {
+                true => suppressed += 1,
+                false => matches.push(m),
+            }
+        }
+    }
+    matches.sort_by(|a, b| a.path.cmp(&b.path).then(a.namespace.cmp(&b.namespace)).then(a.rule.cmp(&b.rule)));
+    let mut writer = std::io::BufWriter::new(fs::File::create(&outfile)?);
+    for found in &matches {
+        serde_json::to_writer(&mut writer, found)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(format!(
+        "YARA matches: {} from {} rules of {} rule files ({} cached, {skipped} rules skipped), with {} files scanned and {suppressed} matches suppressed by the allowlist",
+        matches.len(), scanner.len(), rule_set.files, rule_set.cached, files.len()
+    ))
+}
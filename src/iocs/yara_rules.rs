@@ -0,0 +1,975 @@
+/*
+Parser of YARA rules for the builtin YARA scanner. The rules are parsed to an AST that
+can be cached, with each string compiled to a literal, for the Aho-Corasick automata, or
+to a regex, for the hex strings and regexes. This is a subset of YARA, not libyara or
+yara-x. Supported are text, hex and regex strings with the nocase, wide, ascii, fullword
+and private modifiers, and conditions of booleans, counts, offsets, `of` sets, filesize,
+the integer functions and references to other rules. Not supported are modules (`pe`,
+`math` and the like), `for` loops, `entrypoint`, the xor, base64 and base64wide
+modifiers, and the string operators such as `contains`, `icontains` and `matches`. Each
+rule that can't be parsed is skipped with an error while the rest of its file is kept.
+*/
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// A value of a rule's meta
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetaValue {
+    Text(String),
+    Int(i64),
+    Bool(bool),
+}
+
+/// What a string matches, either literal bytes or a regex of bytes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Pattern {
+    Literal(Vec<u8>),
+    Regex(String),
+}
+
+/// A string of a rule with its modifiers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YaraString {
+    /// the identifier, i.e. `$a`, or `$` for an anonymous string
+    pub id: String,
+    pub pattern: Pattern,
+    pub nocase: bool,
+    pub ascii: bool,
+    pub wide: bool,
+    pub fullword: bool,
+    pub private: bool,
+}
+
+/// The sets of strings of `of`, either `them` or the identifiers, which may end in `*`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StringSet {
+    Them,
+    Ids(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Quantifier {
+    All,
+    Any,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BinOp {
+    Add, Sub, Mul, Div, Mod,
+    BitAnd, BitOr, BitXor, Shl, Shr,
+    Eq, Ne, Lt, Le, Gt, Ge,
+}
+
+/// The integer read from the data by `uint16(offset)` and the like
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IntRead {
+    pub size: usize,
+    pub signed: bool,
+    pub big_endian: bool,
+}
+
+/// An expression of a rule's condition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Expr {
+    Bool(bool),
+    Int(i64),
+    Filesize,
+    /// `$a`, true when the string matched
+    Matched(String),
+    /// `#a`, the number of matches
+    Count(String),
+    /// `#a in (lo..hi)`, the number of matches in the range
+    CountIn(String, Box<Expr>, Box<Expr>),
+    /// `@a[i]`, the offset of the ith match
+    Offset(String, Box<Expr>),
+    /// `!a[i]`, the length of the ith match
+    Length(String, Box<Expr>),
+    At(String, Box<Expr>),
+    In(String, Box<Expr>, Box<Expr>),
+    Of(Quantifier, StringSet),
+    OfCount(Box<Expr>, StringSet),
+    OfPercent(Box<Expr>, StringSet),
+    ReadInt(IntRead, Box<Expr>),
+    RuleRef(String),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Neg(Box<Expr>),
+    BitNot(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+/// A parsed rule, with its namespace being the rule file's name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YaraRule {
+    pub name: String,
+    pub namespace: String,
+    pub tags: Vec<String>,
+    pub meta: Vec<(String, MetaValue)>,
+    pub strings: Vec<YaraString>,
+    pub condition: Expr,
+    pub private: bool,
+    pub global: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    /// `$a`, `$` or `$a*`
+    StrId(String),
+    CountId(String),
+    OffsetId(String),
+    LengthId(String),
+    Int(i64),
+    Text(Vec<u8>),
+    Hex(String),
+    Regex(String, String),
+    Punct(&'static str),
+    /// what couldn't be lexed, with the error, to skip the rule it is in
+    Invalid(String),
+}
+
+const PUNCTS: &[&str] = &[
+    "..", "==", "!=", "<=", ">=", "<<", ">>", "<", ">", "=", "{", "}", "(", ")", "[", "]", ":",
+    ",", "+", "-", "*", "\\", "%", "&", "|", "^", "~", ".",
+];
+
+/// the words that start a rule
+const RULE_WORDS: &[&str] = &["rule", "private", "global"];
+
+fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// split the source into tokens, with the line of each. What can't be lexed is an
+/// `Invalid` token, to the end of its line or of the source when it is unclosed
+fn lex(source: &str) -> Vec<(Token, usize)> {
+    let src = source.as_bytes();
+    let mut tokens: Vec<(Token, usize)> = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    while i < src.len() {
+        let c = src[i];
+        match c {
+            b'\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            b'/' if src.get(i + 1) == Some(&b'/') => {
+                while i < src.len() && src[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if src.get(i + 1) == Some(&b'*') => {
+                let Some(end) = source[i + 2..].find("*/") else {
+                    tokens.push((Token::Invalid(format!("Unclosed comment on line {line}")), line));
+                    break;
+                };
+                line += source[i..i + 2 + end].matches('\n').count();
+                i += end + 4;
+            }
+            b'"' => {
+                match lex_text(src, i + 1) {
+                    Some((text, end)) => {
+                        tokens.push((Token::Text(text), line));
+                        i = end;
+                    }
+                    None => {
+                        tokens.push((Token::Invalid(format!("Invalid string on line {line}")), line));
+                        i = line_end(src, i);
+                    }
+                }
+            }
+            b'{' if matches!(tokens.last(), Some((Token::Punct("="), _))) => {
+                let Some(end) = source[i..].find('}') else {
+                    tokens.push((Token::Invalid(format!("Unclosed hex string on line {line}")), line));
+                    break;
+                };
+                let hex = &source[i + 1..i + end];
+                line += hex.matches('\n').count();
+                tokens.push((Token::Hex(hex.to_string()), line));
+                i += end + 1;
+            }
+            b'/' => {
+                let mut pattern = Vec::new();
+                let mut j = i + 1;
+                let closed = loop {
+                    match src.get(j) {
+                        None | Some(b'\n') => break false,
+                        Some(b'\\') if src.get(j + 1) == Some(&b'/') => {
+                            pattern.push(b'/');
+                            j += 2;
+                        }
+                        Some(b'\\') => {
+                            pattern.extend(&src[j..(j + 2).min(src.len())]);
+                            j += 2;
+                        }
+                        Some(b'/') => break true,
+                        Some(c) => {
+                            pattern.push(*c);
+                            j += 1;
+                        }
+                    }
+                };
+                if !closed {
+                    tokens.push((Token::Invalid(format!("Unclosed regex on line {line}")), line));
+                    i = j;
+                    continue;
+                }
+                j += 1;
+                let flags_start = j;
+                while j < src.len() && (src[j] == b'i' || src[j] == b's') {
+                    j += 1;
+                }
+                tokens.push((Token::Regex(String::from_utf8_lossy(&pattern).to_string(), source[flags_start..j].to_string()), line));
+                i = j;
+            }
+            b'$' | b'#' | b'@' | b'!' if src.get(i + 1).is_some_and(|c| is_ident(*c)) || c == b'$' => {
+                let mut j = i + 1;
+                while j < src.len() && is_ident(src[j]) {
+                    j += 1;
+                }
+                let name = &source[i + 1..j];
+                let token = match c {
+                    b'$' => {
+                        let wildcard = src.get(j) == Some(&b'*');
+                        if wildcard {
+                            j += 1;
+                        }
+                        Token::StrId(format!("${name}{}", if wildcard { "*" } else { "" }))
+                    }
+                    b'#' => Token::CountId(format!("${name}")),
+                    b'@' => Token::OffsetId(format!("${name}")),
+                    _ => Token::LengthId(format!("${name}")),
+                };
+                tokens.push((token, line));
+                i = j;
+            }
+            c if c.is_ascii_digit() => {
+                let mut j = i;
+                while j < src.len() && (src[j].is_ascii_alphanumeric()) {
+                    j += 1;
+                }
+                let word = &source[i..j];
+                let (number, multiplier) = match word.len() > 2 {
+                    true if word.ends_with("KB") => (&word[..word.len() - 2], 1024),
+                    true if word.ends_with("MB") => (&word[..word.len() - 2], 1024 * 1024),
+                    _ => (word, 1),
+                };
+                let value = match number.get(..2) {
+                    Some("0x") => i64::from_str_radix(&number[2..], 16),
+                    Some("0o") => i64::from_str_radix(&number[2..], 8),
+                    _ => number.parse(),
+                };
+                let token = match value.map(|v| v.checked_mul(multiplier)) {
+                    Ok(Some(value)) => Token::Int(value),
+                    Ok(None) => Token::Invalid(format!("Number {word} is too large on line {line}")),
+                    Err(_) => Token::Invalid(format!("Invalid number {word} on line {line}")),
+                };
+                tokens.push((token, line));
+                i = j;
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let mut j = i;
+                while j < src.len() && is_ident(src[j]) {
+                    j += 1;
+                }
+                tokens.push((Token::Ident(source[i..j].to_string()), line));
+                i = j;
+            }
+            _ => {
+                match PUNCTS.iter().find(|p| src[i..].starts_with(p.as_bytes())) {
+                    Some(punct) => {
+                        tokens.push((Token::Punct(punct), line));
+                        i += punct.len();
+                    }
+                    None => {
+                        let c = source[i..].chars().next().unwrap_or_default();
+                        tokens.push((Token::Invalid(format!("Unexpected character {c} on line {line}")), line));
+                        i += c.len_utf8().max(1);
+                    }
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// the index of the end of the line of i
+fn line_end(src: &[u8], i: usize) -> usize {
+    src[i..].iter().position(|c| *c == b'\n').map_or(src.len(), |p| i + p)
+}
+
+/// read a quoted string from after its opening quote, returning its bytes and the index
+/// after its closing quote
+fn lex_text(src: &[u8], start: usize) -> Option<(Vec<u8>, usize)> {
+    let mut text = Vec::new();
+    let mut i = start;
+    loop {
+        match *src.get(i)? {
+            b'"' => return Some((text, i + 1)),
+            b'\n' => return None,
+            b'\\' => {
+                let escaped = match *src.get(i + 1)? {
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    b'r' => b'\r',
+                    b'\\' => b'\\',
+                    b'"' => b'"',
+                    b'x' => {
+                        let hex = std::str::from_utf8(src.get(i + 2..i + 4)?).ok()?;
+                        i += 2;
+                        u8::from_str_radix(hex, 16).ok()?
+                    }
+                    _ => return None,
+                };
+                text.push(escaped);
+                i += 2;
+            }
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// escape a byte for a regex of bytes
+fn regex_byte(b: u8) -> String {
+    format!("\\x{b:02X}")
+}
+
+/// the regex class of the bytes matching a value under a mask, i.e. `4?`
+fn masked_class(value: u8, mask: u8, negated: bool) -> String {
+    let bytes: String = (0..=255u8).filter(|b| b & mask == value & mask).map(regex_byte).collect();
+    format!("[{}{bytes}]", if negated { "^" } else { "" })
+}
+
+/// convert a hex string, such as `{ 4D 5A ?? [2-4] ( 90 | 91 ) }`, to a regex of bytes
+pub fn hex_to_regex(hex: &str) -> Result<String> {
+    let chars: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut regex = String::new();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '(' => {
+                depth += 1;
+                regex.push_str("(?:");
+                i += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                regex.push(')');
+                i += 1;
+            }
+            '|' if depth > 0 => {
+                regex.push('|');
+                i += 1;
+            }
+            '[' => {
+                let end = chars[i..].iter().position(|c| *c == ']').ok_or_else(|| anyhow!("Unclosed jump in hex string"))?;
+                let jump: String = chars[i + 1..i + end].iter().collect();
+                let bound = |v: &str| -> Result<Option<u32>> {
+                    match v {
+                        "" => Ok(None),
+                        v => Ok(Some(v.parse().map_err(|_| anyhow!("Invalid jump [{jump}] in hex string"))?)),
+                    }
+                };
+                let repeat = match jump.split_once('-') {
+                    None => format!("{{{}}}", bound(&jump)?.ok_or_else(|| anyhow!("Empty jump in hex string"))?),
+                    Some((lo, hi)) => match (bound(lo)?.unwrap_or(0), bound(hi)?) {
+                        (lo, Some(hi)) if hi < lo => bail!("Invalid jump [{jump}] in hex string"),
+                        (lo, Some(hi)) => format!("{{{lo},{hi}}}?"),
+                        (lo, None) => format!("{{{lo},}}?"),
+                    },
+                };
+                regex.push('.');
+                regex.push_str(&repeat);
+                i += end + 1;
+            }
+            c => {
+                let negated = c == '~';
+                let start = if negated { i + 1 } else { i };
+                let (Some(high), Some(low)) = (chars.get(start), chars.get(start + 1)) else {
+                    bail!("Invalid byte in hex string");
+                };
+                let nibble = |c: &char| -> Result<(u8, u8)> {
+                    match c {
+                        '?' => Ok((0, 0)),
+                        c => Ok((c.to_digit(16).ok_or_else(|| anyhow!("Invalid byte {high}{low} in hex string"))? as u8, 0xF)),
+                    }
+                };
+                let ((high, high_mask), (low, low_mask)) = (nibble(high)?, nibble(low)?);
+                let (value, mask) = ((high << 4) | low, (high_mask << 4) | low_mask);
+                let atom = match (mask, negated) {
+                    (0, true) => bail!("A negated wildcard in hex string"),
+                    (0, false) => ".".to_string(),
+                    (0xFF, false) => regex_byte(value),
+                    (mask, negated) => masked_class(value, mask, negated),
+                };
+                regex.push_str(&atom);
+                i = start + 2;
+            }
+        }
+    }
+    if depth != 0 {
+        bail!("Unclosed alternative in hex string");
+    }
+    Ok(format!("(?s-u){regex}"))
+}
+
+/// The tokens of a file being parsed into rules
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    pos: usize,
+    namespace: String,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + ahead).map(|(t, _)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map(|(_, l)| *l).unwrap_or(0)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _)| t.clone()).ok_or_else(|| anyhow!("Unexpected end of the rules"))?;
+        self.pos += 1;
+        match token {
+            Token::Invalid(e) => Err(anyhow!(e)),
+            token => Ok(token),
+        }
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn is_ident(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(w)) if w == word)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.is_punct(punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_ident(&mut self, word: &str) -> bool {
+        let found = self.is_ident(word);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<()> {
+        match self.eat_punct(punct) {
+            true => Ok(()),
+            false => bail!("Expected {punct} on line {}", self.line()),
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(word) => Ok(word),
+            other => bail!("Expected a name on line {}, found {other:?}", self.line()),
+        }
+    }
+
+    /// read the `private` and `global` before a rule's `rule`
+    fn parse_rule_header(&mut self) -> Result<(bool, bool)> {
+        let mut private = false;
+        let mut global = false;
+        loop {
+            match self.expect_ident()?.as_str() {
+                "private" => private = true,
+                "global" => global = true,
+                "rule" => return Ok((private, global)),
+                other => bail!("Expected rule, found {other} on line {}", self.line()),
+            }
+        }
+    }
+
+    /// skip to the next rule or import at the top level, after what couldn't be parsed
+    fn skip_to_rule(&mut self) {
+        self.pos += 1;
+        while let Some(token) = self.peek() {
+            if matches!(token, Token::Ident(w) if RULE_WORDS.contains(&w.as_str()) || w == "import" || w == "include") {
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// skip past the `}` closing the rule's body, from its `{`
+    fn skip_rule(&mut self, open: usize) {
+        let mut depth = 0;
+        self.pos = open;
+        while let Some(token) = self.peek().cloned() {
+            self.pos += 1;
+            match token {
+                Token::Punct("{") => depth += 1,
+                Token::Punct("}") => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        return;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn parse_rule(&mut self, private: bool, global: bool) -> Result<YaraRule> {
+        let name = self.expect_ident()?;
+        let mut tags = Vec::new();
+        if self.eat_punct(":") {
+            while let Some(Token::Ident(tag)) = self.peek() {
+                tags.push(tag.clone());
+                self.pos += 1;
+            }
+        }
+        self.expect_punct("{")?;
+        let mut rule = YaraRule {
+            name, namespace: self.namespace.clone(), tags, meta: Vec::new(), strings: Vec::new(),
+            condition: Expr::Bool(false), private, global,
+        };
+        if self.is_ident("meta") && matches!(self.peek_at(1), Some(Token::Punct(":"))) {
+            self.pos += 2;
+            while matches!(self.peek_at(1), Some(Token::Punct("="))) {
+                let key = self.expect_ident()?;
+                self.expect_punct("=")?;
+                let value = match self.next()? {
+                    Token::Text(text) => MetaValue::Text(String::from_utf8_lossy(&text).to_string()),
+                    Token::Int(value) => MetaValue::Int(value),
+                    Token::Punct("-") => match self.next()? {
+                        Token::Int(value) => MetaValue::Int(-value),
+                        _ => bail!("Invalid meta {key} on line {}", self.line()),
+                    },
+                    Token::Ident(word) if word == "true" || word == "false" => MetaValue::Bool(word == "true"),
+                    _ => bail!("Invalid meta {key} on line {}", self.line()),
+                };
+                rule.meta.push((key, value));
+            }
+        }
+        if self.is_ident("strings") && matches!(self.peek_at(1), Some(Token::Punct(":"))) {
+            self.pos += 2;
+            while let Some(Token::StrId(id)) = self.peek() {
+                let id = id.clone();
+                self.pos += 1;
+                self.expect_punct("=")?;
+                let string = self.parse_string(id)?;
+                rule.strings.push(string);
+            }
+        }
+        if !(self.eat_ident("condition") && self.eat_punct(":")) {
+            bail!("Expected the condition of rule {} on line {}", rule.name, self.line());
+        }
+        rule.condition = self.parse_or(&rule)?;
+        self.expect_punct("}")?;
+        Ok(rule)
+    }
+
+    fn parse_string(&mut self, id: String) -> Result<YaraString> {
+        if id.ends_with('*') {
+            bail!("Invalid string name {id}");
+        }
+        let line = self.line();
+        let token = self.next()?;
+        let mut string = YaraString {
+            id, pattern: Pattern::Literal(Vec::new()), nocase: false, ascii: false, wide: false,
+            fullword: false, private: false,
+        };
+        loop {
+            match self.peek() {
+                Some(Token::Ident(word)) if !matches!(self.peek_at(1), Some(Token::Punct("="))) => {
+                    match word.as_str() {
+                        "nocase" => string.nocase = true,
+                        "ascii" => string.ascii = true,
+                        "wide" => string.wide = true,
+                        "fullword" => string.fullword = true,
+                        "private" => string.private = true,
+                        "condition" => break,
+                        other => bail!("The {other} modifier of {} isn't supported", string.id),
+                    }
+                    self.pos += 1;
+                }
+                _ => break,
+            }
+        }
+        if !string.wide {
+            string.ascii = true;
+        }
+        string.pattern = match token {
+            Token::Text(text) if !text.is_empty() => Pattern::Literal(text),
+            Token::Hex(hex) => {
+                if string.nocase || string.wide || string.fullword {
+                    bail!("Hex string {} has text modifiers on line {line}", string.id);
+                }
+                Pattern::Regex(hex_to_regex(&hex)?)
+            }
+            Token::Regex(pattern, flags) => {
+                if string.wide {
+                    bail!("The wide regex {} isn't supported", string.id);
+                }
+                let mut prefix = "(?-u".to_string();
+                if flags.contains('i') || string.nocase {
+                    prefix.push('i');
+                }
+                if flags.contains('s') {
+                    prefix.push('s');
+                }
+                Pattern::Regex(format!("{prefix}){pattern}"))
+            }
+            other => bail!("Invalid string {} on line {line}, found {other:?}", string.id),
+        };
+        Ok(string)
+    }
+
+    /// check the strings of the set are in the rule
+    fn check_set(&self, rule: &YaraRule, set: &StringSet) -> Result<()> {
+        if rule.strings.is_empty() {
+            bail!("Rule {} uses strings but has none", rule.name);
+        }
+        if let StringSet::Ids(ids) = set {
+            for id in ids {
+                let found = match id.strip_suffix('*') {
+                    Some(prefix) => rule.strings.iter().any(|s| s.id.starts_with(prefix)),
+                    None => rule.strings.iter().any(|s| &s.id == id),
+                };
+                if !found {
+                    bail!("Rule {} has no string {id}", rule.name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_string(&self, rule: &YaraRule, id: &str) -> Result<()> {
+        match rule.strings.iter().any(|s| s.id == id) {
+            true => Ok(()),
+            false => bail!("Rule {} has no string {id}", rule.name),
+        }
+    }
+
+    fn parse_or(&mut self, rule: &YaraRule) -> Result<Expr> {
+        let mut terms = vec![self.parse_and(rule)?];
+        while self.eat_ident("or") {
+            terms.push(self.parse_and(rule)?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Or(terms) })
+    }
+
+    fn parse_and(&mut self, rule: &YaraRule) -> Result<Expr> {
+        let mut terms = vec![self.parse_not(rule)?];
+        while self.eat_ident("and") {
+            terms.push(self.parse_not(rule)?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::And(terms) })
+    }
+
+    fn parse_not(&mut self, rule: &YaraRule) -> Result<Expr> {
+        match self.eat_ident("not") {
+            true => Ok(Expr::Not(Box::new(self.parse_not(rule)?))),
+            false => self.parse_compare(rule),
+        }
+    }
+
+    fn parse_compare(&mut self, rule: &YaraRule) -> Result<Expr> {
+        let left = self.parse_binary(rule, 0)?;
+        let op = match self.peek() {
+            Some(Token::Punct("==")) => BinOp::Eq,
+            Some(Token::Punct("!=")) => BinOp::Ne,
+            Some(Token::Punct("<")) => BinOp::Lt,
+            Some(Token::Punct("<=")) => BinOp::Le,
+            Some(Token::Punct(">")) => BinOp::Gt,
+            Some(Token::Punct(">=")) => BinOp::Ge,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.parse_binary(rule, 0)?;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    /// parse the binary operators by their precedence, lowest first
+    fn parse_binary(&mut self, rule: &YaraRule, level: usize) -> Result<Expr> {
+        const LEVELS: &[&[(&str, BinOp)]] = &[
+            &[("|", BinOp::BitOr)],
+            &[("^", BinOp::BitXor)],
+            &[("&", BinOp::BitAnd)],
+            &[("<<", BinOp::Shl), (">>", BinOp::Shr)],
+            &[("+", BinOp::Add), ("-", BinOp::Sub)],
+            &[("*", BinOp::Mul), ("\\", BinOp::Div), ("%", BinOp::Mod)],
+        ];
+        if level == LEVELS.len() {
+            return self.parse_unary(rule);
+        }
+        let mut left = self.parse_binary(rule, level + 1)?;
+        while let Some((_, op)) = LEVELS[level].iter().find(|(p, _)| self.is_punct(p)) {
+            self.pos += 1;
+            let right = self.parse_binary(rule, level + 1)?;
+            left = Expr::Binary(*op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self, rule: &YaraRule) -> Result<Expr> {
+        if self.eat_punct("-") {
+            return Ok(Expr::Neg(Box::new(self.parse_unary(rule)?)));
+        }
+        if self.eat_punct("~") {
+            return Ok(Expr::BitNot(Box::new(self.parse_unary(rule)?)));
+        }
+        self.parse_primary(rule)
+    }
+
+    fn parse_range(&mut self, rule: &YaraRule) -> Result<(Expr, Expr)> {
+        self.expect_punct("(")?;
+        let lo = self.parse_binary(rule, 0)?;
+        self.expect_punct("..")?;
+        let hi = self.parse_binary(rule, 0)?;
+        self.expect_punct(")")?;
+        Ok((lo, hi))
+    }
+
+    fn parse_set(&mut self, rule: &YaraRule) -> Result<StringSet> {
+        let set = if self.eat_ident("them") {
+            StringSet::Them
+        } else {
+            self.expect_punct("(")?;
+            let mut ids = Vec::new();
+            loop {
+                match self.next()? {
+                    Token::StrId(id) => ids.push(id),
+                    other => bail!("Only sets of strings are supported, found {other:?} on line {}", self.line()),
+                }
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+            self.expect_punct(")")?;
+            StringSet::Ids(ids)
+        };
+        self.check_set(rule, &set)?;
+        Ok(set)
+    }
+
+    /// the index of a match, i.e. the `[2]` of `@a[2]`, being the first without one
+    fn parse_index(&mut self, rule: &YaraRule) -> Result<Expr> {
+        match self.eat_punct("[") {
+            true => {
+                let index = self.parse_binary(rule, 0)?;
+                self.expect_punct("]")?;
+                Ok(index)
+            }
+            false => Ok(Expr::Int(1)),
+        }
+    }
+
+    fn parse_primary(&mut self, rule: &YaraRule) -> Result<Expr> {
+        let line = self.line();
+        match self.next()? {
+            Token::Punct("(") => {
+                let expr = self.parse_or(rule)?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Token::Int(value) => {
+                if self.eat_punct("%") {
+                    self.expect_of()?;
+                    return Ok(Expr::OfPercent(Box::new(Expr::Int(value)), self.parse_set(rule)?));
+                }
+                if self.eat_ident("of") {
+                    return Ok(Expr::OfCount(Box::new(Expr::Int(value)), self.parse_set(rule)?));
+                }
+                Ok(Expr::Int(value))
+            }
+            Token::StrId(id) => {
+                if id == "$" || id.ends_with('*') {
+                    bail!("{id} is only supported in sets, on line {line}");
+                }
+                self.check_string(rule, &id)?;
+                if self.eat_ident("at") {
+                    return Ok(Expr::At(id, Box::new(self.parse_binary(rule, 0)?)));
+                }
+                if self.eat_ident("in") {
+                    let (lo, hi) = self.parse_range(rule)?;
+                    return Ok(Expr::In(id, Box::new(lo), Box::new(hi)));
+                }
+                Ok(Expr::Matched(id))
+            }
+            Token::CountId(id) => {
+                self.check_string(rule, &id)?;
+                if self.eat_ident("in") {
+                    let (lo, hi) = self.parse_range(rule)?;
+                    return Ok(Expr::CountIn(id, Box::new(lo), Box::new(hi)));
+                }
+                Ok(Expr::Count(id))
+            }
+            Token::OffsetId(id) => {
+                self.check_string(rule, &id)?;
+                Ok(Expr::Offset(id, Box::new(self.parse_index(rule)?)))
+            }
+            Token::LengthId(id) => {
+                self.check_string(rule, &id)?;
+                Ok(Expr::Length(id, Box::new(self.parse_index(rule)?)))
+            }
+            Token::Ident(word) => self.parse_word(rule, word, line),
+            other => bail!("Unexpected {other:?} in the condition on line {line}"),
+        }
+    }
+
+    fn expect_of(&mut self) -> Result<()> {
+        match self.eat_ident("of") {
+            true => Ok(()),
+            false => bail!("Expected of on line {}", self.line()),
+        }
+    }
+
+    fn parse_word(&mut self, rule: &YaraRule, word: String, line: usize) -> Result<Expr> {
+        match word.as_str() {
+            "true" => Ok(Expr::Bool(true)),
+            "false" => Ok(Expr::Bool(false)),
+            "filesize" => Ok(Expr::Filesize),
+            "all" | "any" | "none" => {
+                self.expect_of()?;
+                let quantifier = match word.as_str() {
+                    "all" => Quantifier::All,
+                    "any" => Quantifier::Any,
+                    _ => Quantifier::None,
+                };
+                Ok(Expr::Of(quantifier, self.parse_set(rule)?))
+            }
+            "for" => bail!("for loops aren't supported, on line {line}"),
+            "entrypoint" => bail!("entrypoint isn't supported, on line {line}"),
+            _ if self.is_punct(".") => bail!("The module {word} isn't supported, on line {line}"),
+            _ => {
+                if let Some(read) = int_read(&word) {
+                    self.expect_punct("(")?;
+                    let offset = self.parse_binary(rule, 0)?;
+                    self.expect_punct(")")?;
+                    return Ok(Expr::ReadInt(read, Box::new(offset)));
+                }
+                Ok(Expr::RuleRef(word))
+            }
+        }
+    }
+}
+
+/// the integer function of the name, i.e. `uint32be`
+fn int_read(name: &str) -> Option<IntRead> {
+    let (signed, rest) = match name.strip_prefix("uint") {
+        Some(rest) => (false, rest),
+        None => (true, name.strip_prefix("int")?),
+    };
+    let (bits, big_endian) = match rest.strip_suffix("be") {
+        Some(bits) => (bits, true),
+        None => (rest, false),
+    };
+    let size = match bits {
+        "8" => 1,
+        "16" => 2,
+        "32" => 4,
+        _ => return None,
+    };
+    Some(IntRead { size, signed, big_endian })
+}
+
+/// parse the rules of a file's source, in the namespace. Rules that can't be parsed are
+/// skipped and their errors returned, so a file with an unsupported rule still has the
+/// rest of its rules
+pub fn parse_rules(source: &str, namespace: &str) -> (Vec<YaraRule>, Vec<String>) {
+    let tokens = lex(source);
+    let mut parser = Parser { tokens: &tokens, pos: 0, namespace: namespace.to_string() };
+    let mut rules: Vec<YaraRule> = Vec::new();
+    let mut errors = Vec::new();
+    while let Some(token) = parser.peek().cloned() {
+        let start = parser.pos;
+        match token {
+            Token::Ident(word) if word == "import" || word == "include" => {
+                parser.pos += 1;
+                match parser.peek() {
+                    Some(Token::Text(_)) => parser.pos += 1,
+                    _ => errors.push(format!("Expected a name after {word} on line {}", parser.line())),
+                }
+            }
+            Token::Ident(word) if RULE_WORDS.contains(&word.as_str()) => {
+                match parser.parse_rule_header().and_then(|(private, global)| parser.parse_rule(private, global)) {
+                    Ok(rule) => {
+                        let known = |name: &str| rules.iter().any(|r| r.name == name);
+                        match rule_refs(&rule.condition).into_iter().find(|r| !known(r)) {
+                            Some(missing) => errors.push(format!("{}: unknown rule or identifier {missing}", rule.name)),
+                            None => rules.push(rule),
+                        }
+                    }
+                    Err(e) => {
+                        let name = tokens[start..].iter()
+                            .find_map(|(t, _)| match t {
+                                Token::Ident(name) if !RULE_WORDS.contains(&name.as_str()) => Some(name.clone()),
+                                _ => None,
+                            })
+                            .unwrap_or_else(|| "unnamed".to_string());
+                        // the error of what couldn't be lexed is clearer than what the parser expected
+                        let invalid = tokens[start..(parser.pos + 1).min(tokens.len())].iter().find_map(|(t, _)| match t {
+                            Token::Invalid(invalid) => Some(invalid.clone()),
+                            _ => None,
+                        });
+                        errors.push(format!("{name}: {}", invalid.unwrap_or_else(|| e.to_string())));
+                        let open = tokens[start..].iter().position(|(t, _)| *t == Token::Punct("{")).map(|p| start + p);
+                        match open {
+                            Some(open) => parser.skip_rule(open),
+                            None => break,
+                        }
+                    }
+                }
+            }
+            Token::Invalid(e) => {
+                errors.push(e);
+                parser.skip_to_rule();
+            }
+            other => {
+                errors.push(format!("Unexpected {other:?} on line {}", parser.line()));
+                parser.skip_to_rule();
+            }
+        }
+        // always move on, so an error can't stop the rest of the rules being parsed
+        if parser.pos == start {
+            parser.pos += 1;
+        }
+    }
+    (rules, errors)
+}
+
+/// the names of the rules referenced by the condition
+fn rule_refs(expr: &Expr) -> Vec<String> {
+    let mut refs = Vec::new();
+    let mut stack = vec![expr];
+    while let Some(expr) = stack.pop() {
+        match expr {
+            Expr::RuleRef(name) => refs.push(name.clone()),
+            Expr::Not(e) | Expr::Neg(e) | Expr::BitNot(e) | Expr::ReadInt(_, e) | Expr::At(_, e)
+                | Expr::Offset(_, e) | Expr::Length(_, e) | Expr::OfCount(e, _) | Expr::OfPercent(e, _) => stack.push(e),
+            Expr::In(_, a, b) | Expr::CountIn(_, a, b) | Expr::Binary(_, a, b) => {
+                stack.push(a);
+                stack.push(b);
+            }
+            Expr::And(terms) | Expr::Or(terms) => stack.extend(terms),
+            _ => (),
+        }
+    }
+    refs
+}
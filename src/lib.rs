@@ -7,6 +7,12 @@ pub mod init;
 pub mod webs;
 pub mod whipped;
 pub mod utils;
+pub mod parsers;
+pub mod timeline;
+pub mod iocs;
+pub mod linux;
+pub mod macos;
+pub mod detections;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,7 @@
+pub mod common;
+pub mod history;
+pub mod journal;
+pub mod logins;
+pub mod persistence;
+pub mod syslog;
+pub mod weblogs;
@@ -0,0 +1,189 @@
+/*
+Shared reading of Linux evidence, from a mounted root or the `[root]` folder of a UAC
+collection. Logs are read whether plain or gzip compressed by logrotate, with their
+rotations found beside them. The accounts come from etc/passwd and the timezone from
+etc/timezone or the target of etc/localtime, so the local times of syslog can be
+converted to UTC.
+*/
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use flate2::read::MultiGzDecoder;
+
+/// The folder of the file system in a UAC collection
+pub const UAC_ROOT: &str = "[root]";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// An account of etc/passwd
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Account {
+    pub name: String,
+    pub uid: u32,
+    pub home: String,
+    pub shell: String,
+}
+
+/// check if the folder is the root of a Linux file system, having etc and var/log
+pub fn is_linux_root(path: &Path) -> bool {
+    path.join("etc").is_dir() && path.join("var").join("log").is_dir()
+}
+
+/// the root of the Linux file system in the data source, being the data source itself or
+/// the `[root]` folder of a UAC collection
+pub fn linux_root(data_source: &Path) -> Option<PathBuf> {
+    [data_source.to_path_buf(), data_source.join(UAC_ROOT)]
+        .into_iter()
+        .find(|p| is_linux_root(p))
+}
+
+/// open a log, decompressing it when it is gzipped
+pub fn open_log(path: &Path) -> Result<Box<dyn BufRead>> {
+    let mut file = File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+    let mut magic = [0u8; 2];
+    let gzipped = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    let file = File::open(path)?;
+    Ok(match gzipped {
+        true => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        false => Box::new(BufReader::new(file)),
+    })
+}
+
+/// read the lines of a log, with the bytes that aren't UTF-8 replaced
+pub fn read_lines(path: &Path, mut each: impl FnMut(&str)) -> Result<()> {
+    let mut reader = open_log(path)?;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&line);
+        each(text.trim_end_matches(['\n', '\r']));
+    }
+}
+
+/// the logs in the folder with one of the names, and their rotations such as
+/// `auth.log.1`, `auth.log.2.gz` or `secure-20240301`, oldest first by modified time
+pub fn rotated_logs(folder: &Path, names: &[&str]) -> Vec<PathBuf> {
+    let mut logs: Vec<(Option<std::time::SystemTime>, PathBuf)> = fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_file())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            names.iter().any(|n| name == *n || name.starts_with(&format!("{n}.")) || name.starts_with(&format!("{n}-")))
+        })
+        .map(|e| (e.metadata().and_then(|m| m.modified()).ok(), e.path()))
+        .collect();
+    logs.sort();
+    logs.into_iter().map(|(_, path)| path).collect()
+}
+
+/// the modified time of a file
+pub fn modified(path: &Path) -> Option<DateTime<Utc>> {
+    fs::symlink_metadata(path).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from)
+}
+
+/// the accounts of etc/passwd, by their uid
+pub fn read_passwd(root: &Path) -> HashMap<u32, Account> {
+    let Ok(data) = fs::read(root.join("etc").join("passwd")) else { return HashMap::new() };
+    String::from_utf8_lossy(&data).lines()
+        .filter(|l| !l.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            let account = Account {
+                name: fields.first()?.to_string(),
+                uid: fields.get(2)?.parse().ok()?,
+                home: fields.get(5).unwrap_or(&"").to_string(),
+                shell: fields.get(6).unwrap_or(&"").to_string(),
+            };
+            Some((account.uid, account))
+        })
+        .collect()
+}
+
+/// the home folders of the users, from etc/passwd and the folders of home, with root's.
+/// Each is the user and the folder in the root
+pub fn user_homes(root: &Path) -> Vec<(String, PathBuf)> {
+    let mut homes: Vec<(String, PathBuf)> = Vec::new();
+    let mut add = |user: String, home: PathBuf| {
+        if home.is_dir() && !homes.iter().any(|(_, h)| *h == home) {
+            homes.push((user, home));
+        }
+    };
+    let mut accounts: Vec<Account> = read_passwd(root).into_values().collect();
+    accounts.sort_by_key(|a| a.uid);
+    for account in accounts {
+        let home = account.home.trim_start_matches('/');
+        if !home.is_empty() && home != "nonexistent" {
+            add(account.name, root.join(home));
+        }
+    }
+    add("root".to_string(), root.join("root"));
+    for entry in fs::read_dir(root.join("home")).into_iter().flatten().flatten() {
+        add(entry.file_name().to_string_lossy().to_string(), entry.path());
+    }
+    homes
+}
+
+/// the timezone of the host, from etc/timezone, the zoneinfo file etc/localtime links to
+/// or the ZONE of etc/sysconfig/clock
+pub fn host_timezone(root: &Path) -> Option<Tz> {
+    let etc = root.join("etc");
+    let from_file = fs::read_to_string(etc.join("timezone")).ok()
+        .and_then(|zone| Tz::from_str(zone.trim()).ok());
+    let from_link = || fs::read_link(etc.join("localtime")).ok().and_then(|target| {
+        let target = target.to_string_lossy().to_string();
+        let zone = target.split("zoneinfo/").nth(1)?;
+        Tz::from_str(zone.trim_start_matches("posix/")).ok()
+    });
+    let from_clock = || fs::read_to_string(etc.join("sysconfig").join("clock")).ok().and_then(|clock| {
+        clock.lines()
+            .find_map(|l| l.trim().strip_prefix("ZONE="))
+            .and_then(|zone| Tz::from_str(zone.trim_matches('"')).ok())
+    });
+    from_file.or_else(from_link).or_else(from_clock)
+}
+
+/// convert a local time of the host to UTC, taking the earlier of ambiguous times
+pub fn local_to_utc(local: &NaiveDateTime, timezone: Option<&Tz>) -> Option<DateTime<Utc>> {
+    let Some(timezone) = timezone else { return Some(local.and_utc()) };
+    match timezone.from_local_datetime(local) {
+        LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => Some(dt.with_timezone(&Utc)),
+        // in the gap of a daylight saving change, the time is an hour ahead of the gap
+        LocalResult::None => timezone.from_local_datetime(&(*local - Duration::hours(1))).earliest()
+            .map(|dt| dt.with_timezone(&Utc) + Duration::hours(1)),
+    }
+}
+
+/// Dates syslog times without a year, such as `Mar  1 10:00:00`, from the year the log was
+/// last modified. A time after the log was modified is from the year before
+pub struct SyslogYear {
+    modified: Option<DateTime<Utc>>,
+}
+
+impl SyslogYear {
+    pub fn new(log: &Path) -> Self {
+        SyslogYear { modified: modified(log) }
+    }
+
+    /// the local time of a syslog time without a year
+    pub fn date(&self, time: &str) -> Option<NaiveDateTime> {
+        let time = time.split_whitespace().collect::<Vec<_>>().join(" ");
+        let year = self.modified.map(|m| m.year()).unwrap_or_else(|| Utc::now().year());
+        let dated = NaiveDateTime::parse_from_str(&format!("{year} {time}"), "%Y %b %d %H:%M:%S").ok()?;
+        match self.modified {
+            // allow for the log being a day ahead in its local time
+            Some(modified) if dated.and_utc() > modified + Duration::days(1) => {
+                NaiveDateTime::parse_from_str(&format!("{} {time}", year - 1), "%Y %b %d %H:%M:%S").ok()
+            }
+            _ => Some(dated),
+        }
+    }
+}
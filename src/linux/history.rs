@@ -0,0 +1,136 @@
+/*
+Builtin parser of the shell histories in the home folders of a Linux host: bash, zsh,
+sh, ash and fish. Times are read where the shell records them, being the `#<epoch>`
+comments bash writes with HISTTIMEFORMAT, the extended history of zsh and the `when` of
+fish. Commands without a time keep their line number so their order is kept.
+*/
+
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use super::common as linux;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+
+const HISTORY_HEADER: [&str; 6] = ["Timestamp", "User", "Shell", "Line", "Command", "SourceFile"];
+/// The history files of a home folder, with their shell
+const HISTORIES: [(&str, &str); 6] = [
+    (".bash_history", "bash"),
+    (".zsh_history", "zsh"),
+    (".history", "sh"),
+    (".sh_history", "sh"),
+    (".ash_history", "ash"),
+    (".local/share/fish/fish_history", "fish"),
+];
+
+/// A command of a shell history
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub line: usize,
+    pub command: String,
+}
+
+/// parse the lines of a history in the format of the shell
+pub fn parse_history(shell: &str, lines: &[String]) -> Vec<HistoryEntry> {
+    match shell {
+        "fish" => parse_fish(lines),
+        _ => parse_lines(lines),
+    }
+}
+
+/// parse a history of a command per line, with the times of bash and zsh
+fn parse_lines(lines: &[String]) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    let mut timestamp = None;
+    let mut continued = false;
+    for (i, line) in lines.iter().enumerate() {
+        // a multiline command of zsh ends each line but the last with a backslash
+        if continued {
+            if let Some(last) = entries.last_mut() {
+                last.command.push('\n');
+                last.command.push_str(line.trim_end_matches('\\'));
+            }
+            continued = line.ends_with('\\');
+            continue;
+        }
+        if let Some(epoch) = line.strip_prefix('#').and_then(|e| e.trim().parse::<i64>().ok()) {
+            timestamp = common::unix_to_dt(epoch);
+            continue;
+        }
+        // the extended history of zsh, `: <epoch>:<duration>;<command>`
+        let (time, command) = match line.strip_prefix(": ").and_then(|l| l.split_once(';')) {
+            Some((meta, command)) => (meta.split(':').next().and_then(|e| e.trim().parse().ok()).and_then(common::unix_to_dt), command),
+            None => (timestamp.take(), line.as_str()),
+        };
+        if command.trim().is_empty() {
+            continue;
+        }
+        continued = command.ends_with('\\');
+        entries.push(HistoryEntry { timestamp: time, line: i + 1, command: command.trim_end_matches('\\').to_string() });
+    }
+    entries
+}
+
+/// parse the YAML like history of fish, of `- cmd:` items with their `when:`
+fn parse_fish(lines: &[String]) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            entries.push(HistoryEntry { timestamp: None, line: i + 1, command: command.replace("\\n", "\n") });
+        } else if let (Some(epoch), Some(last)) = (line.trim().strip_prefix("when: "), entries.last_mut()) {
+            last.timestamp = epoch.trim().parse().ok().and_then(common::unix_to_dt);
+        }
+    }
+    entries
+}
+
+/// the history files of the users on the host, with the user and shell of each
+pub fn find_histories(root: &Path) -> Vec<(String, &'static str, PathBuf)> {
+    histories_of(linux::user_homes(root))
+}
+
+/// the history files in the home folders, each being the user and the folder
+pub fn histories_of(homes: Vec<(String, PathBuf)>) -> Vec<(String, &'static str, PathBuf)> {
+    homes.into_iter()
+        .flat_map(|(user, home)| HISTORIES.iter()
+            .map(|(file, shell)| (user.clone(), *shell, home.join(file)))
+            .filter(|(_, _, path)| path.is_file())
+            .collect::<Vec<_>>())
+        .collect()
+}
+
+/// run the builtin parser of the shell histories, with the input as the Linux root. The
+/// commands are written to the outfile
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let histories = find_histories(Path::new(&args.input));
+    let (commands, files) = write_histories(args, histories)?;
+    Ok(format!("Linux shell history: {commands} commands from {files} histories"))
+}
+
+/// write the commands of the histories to the outfile, returning the number of commands
+/// and histories read
+pub fn write_histories(args: &BuiltinArgs, histories: Vec<(String, &str, PathBuf)>) -> Result<(usize, usize)> {
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(HISTORY_HEADER)?;
+    let (mut commands, mut files) = (0, 0);
+    for (user, shell, path) in histories {
+        let mut lines = Vec::new();
+        if let Err(e) = linux::read_lines(&path, |line| lines.push(line.to_string())) {
+            file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read the history {}: {:#}", path.display(), e));
+            continue;
+        }
+        files += 1;
+        for entry in parse_history(shell, &lines) {
+            writer.write_record([
+                common::fmt_dt(entry.timestamp), user.clone(), shell.to_string(), entry.line.to_string(),
+                entry.command, path.display().to_string(),
+            ])?;
+            commands += 1;
+        }
+    }
+    writer.flush()?;
+    Ok((commands, files))
+}
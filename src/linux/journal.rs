@@ -0,0 +1,358 @@
+/*
+Builtin reader of the systemd journal files of var/log/journal and run/log/journal, in
+their binary format. The entries are found by the chain of entry arrays from the header,
+each entry listing the offsets of its data objects, which are the `FIELD=value` pairs
+shared between entries. Data objects larger than journald's threshold are compressed with
+XZ, LZ4 or ZSTD, and compact journals, the default from systemd 252, use 32 bit offsets.
+Journals that weren't closed cleanly, such as those of a live collection or `.journal~`
+files, are read up to the last entry that can be parsed.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use walkdir::WalkDir;
+
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common::{le_u32, le_u64, to_hex};
+
+const SIGNATURE: &[u8; 8] = b"LPKSHHRH";
+/// The folders of the persistent and volatile journals, relative to the root
+const JOURNAL_FOLDERS: [&str; 2] = ["var/log/journal", "run/log/journal"];
+const HEADER_MIN_SIZE: usize = 208;
+const OBJECT_HEADER_SIZE: usize = 16;
+
+const INCOMPATIBLE_XZ: u32 = 1;
+const INCOMPATIBLE_LZ4: u32 = 2;
+const INCOMPATIBLE_KEYED_HASH: u32 = 4;
+const INCOMPATIBLE_ZSTD: u32 = 8;
+const INCOMPATIBLE_COMPACT: u32 = 16;
+const INCOMPATIBLE_SUPPORTED: u32 = INCOMPATIBLE_XZ | INCOMPATIBLE_LZ4 | INCOMPATIBLE_KEYED_HASH | INCOMPATIBLE_ZSTD | INCOMPATIBLE_COMPACT;
+
+const OBJECT_DATA: u8 = 1;
+const OBJECT_ENTRY: u8 = 3;
+const OBJECT_ENTRY_ARRAY: u8 = 6;
+const COMPRESSED_XZ: u8 = 1;
+const COMPRESSED_LZ4: u8 = 2;
+const COMPRESSED_ZSTD: u8 = 4;
+/// The largest data of an object, as journald's DATA_SIZE_MAX, so the size claimed by a
+/// corrupt object isn't allocated
+const DATA_SIZE_MAX: usize = 768 * 1024 * 1024;
+/// The most an LZ4 block can expand, as each byte of a match length adds 255 bytes
+const LZ4_MAX_RATIO: usize = 255;
+
+/// The data objects kept decoded, as most are shared by many entries
+const DATA_CACHE_SIZE: usize = 65536;
+/// The fields written as their own keys of each line, by the key
+const SUMMARY_FIELDS: [(&str, &str); 8] = [
+    ("hostname", "_HOSTNAME"),
+    ("unit", "_SYSTEMD_UNIT"),
+    ("identifier", "SYSLOG_IDENTIFIER"),
+    ("pid", "_PID"),
+    ("uid", "_UID"),
+    ("comm", "_COMM"),
+    ("priority", "PRIORITY"),
+    ("message", "MESSAGE"),
+];
+
+/// The header of a journal file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JournalHeader {
+    pub incompatible_flags: u32,
+    pub state: u8,
+    pub machine_id: String,
+    pub header_size: u64,
+    pub n_entries: u64,
+    pub entry_array_offset: u64,
+}
+
+impl JournalHeader {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_MIN_SIZE || &data[..8] != SIGNATURE {
+            bail!("not a journal file");
+        }
+        let header = JournalHeader {
+            incompatible_flags: le_u32(data, 12).unwrap_or_default(),
+            state: data[16],
+            machine_id: to_hex(&data[40..56]),
+            header_size: le_u64(data, 88).unwrap_or_default(),
+            n_entries: le_u64(data, 152).unwrap_or_default(),
+            entry_array_offset: le_u64(data, 176).unwrap_or_default(),
+        };
+        let unsupported = header.incompatible_flags & !INCOMPATIBLE_SUPPORTED;
+        if unsupported != 0 {
+            bail!("unsupported incompatible flags {unsupported:#x}");
+        }
+        Ok(header)
+    }
+
+    /// check if the journal uses the 32 bit offsets of compact mode
+    pub fn compact(&self) -> bool {
+        self.incompatible_flags & INCOMPATIBLE_COMPACT != 0
+    }
+}
+
+/// An entry of a journal, with its fields in the order they are listed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JournalEntry {
+    pub seqnum: u64,
+    /// microseconds since the unix epoch
+    pub realtime: u64,
+    /// microseconds since the boot
+    pub monotonic: u64,
+    pub boot_id: String,
+    pub fields: Vec<Rc<(String, Vec<u8>)>>,
+}
+
+impl JournalEntry {
+    /// the first value of a field, with the bytes that aren't UTF-8 replaced
+    pub fn field(&self, name: &str) -> Option<String> {
+        self.fields.iter()
+            .find(|f| f.0 == name)
+            .map(|f| String::from_utf8_lossy(&f.1).to_string())
+    }
+
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_micros(i64::try_from(self.realtime).ok()?)
+    }
+
+    /// the entry as a line of JSON, with its times, the fields of the summary and all the
+    /// fields. Like `journalctl -o json`, a field with several values is an array and a
+    /// value that isn't UTF-8 is an array of its bytes
+    pub fn to_json(&self, journal: &str) -> Value {
+        let mut fields = Map::new();
+        for field in &self.fields {
+            let value = match std::str::from_utf8(&field.1) {
+                Ok(text) => Value::String(text.to_string()),
+                Err(_) => json!(field.1),
+            };
+            match fields.get_mut(&field.0) {
+                Some(Value::Array(values)) if !values.is_empty() && !values[0].is_number() => values.push(value),
+                Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+                None => { fields.insert(field.0.clone(), value); }
+            }
+        }
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), json!(self.timestamp().map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Micros, true))));
+        line.insert("realtime".to_string(), json!(self.realtime));
+        line.insert("monotonic".to_string(), json!(self.monotonic));
+        line.insert("seqnum".to_string(), json!(self.seqnum));
+        line.insert("boot_id".to_string(), json!(self.boot_id));
+        for (key, name) in SUMMARY_FIELDS {
+            line.insert(key.to_string(), json!(self.field(name).unwrap_or_default()));
+        }
+        line.insert("journal".to_string(), json!(journal));
+        line.insert("fields".to_string(), Value::Object(fields));
+        Value::Object(line)
+    }
+}
+
+/// A writer of decompressed data that fails past a limit
+struct LimitedWriter {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.data.len() + buf.len() > self.limit {
+            return Err(std::io::Error::other(format!("the data is over {} bytes", self.limit)));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// decompress the payload of a data object by its flags, up to DATA_SIZE_MAX bytes
+pub fn decompress(flags: u8, payload: &[u8]) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    match flags & (COMPRESSED_XZ | COMPRESSED_LZ4 | COMPRESSED_ZSTD) {
+        0 => data.extend_from_slice(payload),
+        COMPRESSED_XZ => {
+            let mut writer = LimitedWriter { data, limit: DATA_SIZE_MAX };
+            lzma_rs::xz_decompress(&mut Cursor::new(payload), &mut writer)
+                .map_err(|e| anyhow::anyhow!("XZ: {e:?}"))?;
+            data = writer.data;
+        }
+        COMPRESSED_LZ4 => {
+            // the size of the data comes before the LZ4 block
+            let size = le_u64(payload, 0).unwrap_or_default();
+            let block = payload.get(8..).unwrap_or_default();
+            let limit = DATA_SIZE_MAX.min(block.len().saturating_mul(LZ4_MAX_RATIO));
+            if size > limit as u64 {
+                bail!("LZ4: the size {size} is over the {limit} bytes the block can hold");
+            }
+            data = lz4_flex::block::decompress(block, size as usize)?;
+        }
+        COMPRESSED_ZSTD => {
+            zstd::stream::read::Decoder::new(payload)?.take(DATA_SIZE_MAX as u64 + 1).read_to_end(&mut data)?;
+            if data.len() > DATA_SIZE_MAX {
+                bail!("ZSTD: the data is over {DATA_SIZE_MAX} bytes");
+            }
+        }
+        other => bail!("unknown compression {other:#x}"),
+    }
+    Ok(data)
+}
+
+/// A journal file, read into memory
+pub struct JournalFile {
+    data: Vec<u8>,
+    pub header: JournalHeader,
+    cache: HashMap<u64, Rc<(String, Vec<u8>)>>,
+    /// the data objects that couldn't be read, such as those cut off by a crash
+    pub bad_data: usize,
+}
+
+impl JournalFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let header = JournalHeader::parse(&data)?;
+        Ok(JournalFile { data, header, cache: HashMap::new(), bad_data: 0 })
+    }
+
+    /// the type, flags and contents of the object at the offset, within its size
+    fn object(&self, offset: u64) -> Option<(u8, u8, &[u8])> {
+        let offset = usize::try_from(offset).ok()?;
+        if offset < self.header.header_size as usize || offset % 8 != 0 {
+            return None;
+        }
+        let size = usize::try_from(le_u64(&self.data, offset + 8)?).ok()?;
+        if size < OBJECT_HEADER_SIZE {
+            return None;
+        }
+        let object = self.data.get(offset..offset.checked_add(size)?)?;
+        Some((object[0], object[1], object))
+    }
+
+    /// the offsets of the entries, following the chain of entry arrays
+    pub fn entry_offsets(&self) -> Vec<u64> {
+        let item_size = if self.header.compact() { 4 } else { 8 };
+        let mut offsets = Vec::new();
+        let mut visited = HashSet::new();
+        let mut array = self.header.entry_array_offset;
+        while array != 0 && visited.insert(array) && (offsets.len() as u64) < self.header.n_entries {
+            let Some((OBJECT_ENTRY_ARRAY, _, object)) = self.object(array) else { break };
+            offsets.extend(object[24..].chunks_exact(item_size)
+                .map(|item| match item_size {
+                    4 => le_u32(item, 0).unwrap_or_default() as u64,
+                    _ => le_u64(item, 0).unwrap_or_default(),
+                })
+                .take_while(|offset| *offset != 0));
+            array = le_u64(object, 16).unwrap_or_default();
+        }
+        offsets.truncate(self.header.n_entries as usize);
+        offsets
+    }
+
+    /// the field of the data object at the offset
+    fn data_field(&mut self, offset: u64) -> Option<Rc<(String, Vec<u8>)>> {
+        if let Some(field) = self.cache.get(&offset) {
+            return Some(field.clone());
+        }
+        let payload_at = if self.header.compact() { 72 } else { 64 };
+        let field = match self.object(offset) {
+            Some((OBJECT_DATA, flags, object)) if object.len() >= payload_at => decompress(flags, &object[payload_at..]).ok(),
+            _ => None,
+        };
+        let Some(field) = field else {
+            self.bad_data += 1;
+            return None;
+        };
+        let split = field.iter().position(|b| *b == b'=').unwrap_or(field.len());
+        let field = Rc::new((
+            String::from_utf8_lossy(&field[..split]).to_string(),
+            field.get(split + 1..).unwrap_or_default().to_vec(),
+        ));
+        if self.cache.len() >= DATA_CACHE_SIZE {
+            self.cache.clear();
+        }
+        self.cache.insert(offset, field.clone());
+        Some(field)
+    }
+
+    /// the entry at the offset, or None when it isn't an entry object
+    pub fn entry(&mut self, offset: u64) -> Option<JournalEntry> {
+        let (item_size, compact) = if self.header.compact() { (4, true) } else { (16, false) };
+        let (kind, _, object) = self.object(offset)?;
+        if kind != OBJECT_ENTRY || object.len() < 64 {
+            return None;
+        }
+        let items: Vec<u64> = object[64..].chunks_exact(item_size)
+            .filter_map(|item| match compact {
+                true => le_u32(item, 0).map(u64::from),
+                false => le_u64(item, 0),
+            })
+            .collect();
+        let mut entry = JournalEntry {
+            seqnum: le_u64(object, 16)?,
+            realtime: le_u64(object, 24)?,
+            monotonic: le_u64(object, 32)?,
+            boot_id: to_hex(&object[40..56]),
+            fields: Vec::with_capacity(items.len()),
+        };
+        entry.fields = items.into_iter().filter_map(|item| self.data_field(item)).collect();
+        Some(entry)
+    }
+}
+
+/// the journal files of the root, being the `.journal` files and the `.journal~` files of
+/// journals that weren't closed cleanly
+pub fn find_journals(root: &Path) -> Vec<PathBuf> {
+    let mut journals: Vec<PathBuf> = JOURNAL_FOLDERS.iter()
+        .flat_map(|folder| WalkDir::new(root.join(folder)).max_depth(3).into_iter().flatten())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy();
+            name.ends_with(".journal") || name.ends_with(".journal~")
+        })
+        .map(|e| e.into_path())
+        .collect();
+    journals.sort();
+    journals
+}
+
+/// run the builtin journal reader, with the input as the Linux root. Each entry is written
+/// as a line of JSON to the outfile, journal by journal
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let root = Path::new(&args.input);
+    fs::create_dir_all(&args.outfolder)?;
+    let mut writer = std::io::BufWriter::new(fs::File::create(args.outfolder.join(&args.outfile))?);
+    let (mut entries, mut journals, mut bad_entries, mut bad_data) = (0, 0, 0, 0);
+    for path in find_journals(root) {
+        let mut journal = match JournalFile::open(&path) {
+            Ok(journal) => journal,
+            Err(e) => {
+                file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read the journal {}: {:#}", path.display(), e));
+                continue;
+            }
+        };
+        let source = path.display().to_string();
+        for offset in journal.entry_offsets() {
+            let Some(entry) = journal.entry(offset) else {
+                bad_entries += 1;
+                continue;
+            };
+            serde_json::to_writer(&mut writer, &entry.to_json(&source))?;
+            writer.write_all(b"\n")?;
+            entries += 1;
+        }
+        bad_data += journal.bad_data;
+        journals += 1;
+    }
+    writer.flush()?;
+    Ok(format!("Linux journal: {entries} entries from {journals} journals ({bad_entries} entries and {bad_data} fields unreadable)"))
+}
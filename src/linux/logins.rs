@@ -0,0 +1,206 @@
+/*
+Builtin parser of the Linux login records: the binary utmp records of var/log/wtmp, of
+the logins, logouts and boots, and var/log/btmp, of the failed logins, with their
+rotations, and var/log/lastlog, of the last login of each uid. These are the records
+`last`, `lastb` and `lastlog` read, in the 384 byte utmp and 292 byte lastlog formats of
+64 bit and 32 bit x86 and ARM hosts. The files are read a record at a time, as lastlog
+is a sparse file indexed by uid that can be far larger than the data in it.
+*/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+use super::common::{self as linux, Account};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common::{self, le_u32};
+
+const LOGINS_HEADER: [&str; 10] = [
+    "Timestamp", "Record", "Type", "User", "Terminal", "Host", "Ip", "Pid", "Session", "SourceFile",
+];
+pub const UTMP_SIZE: usize = 384;
+pub const LASTLOG_SIZE: usize = 292;
+/// The uids of lastlog read in order. The records of the accounts with higher uids, such
+/// as nobody, are read at their offset rather than reading the sparse file up to them
+pub const LASTLOG_MAX_UID: u32 = 65536;
+
+/// A utmp record of wtmp or btmp
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UtmpRecord {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub record_type: &'static str,
+    pub pid: u32,
+    pub terminal: String,
+    pub user: String,
+    pub host: String,
+    pub ip: String,
+    pub session: u32,
+}
+
+/// the name of a utmp record type
+fn utmp_type(value: u32) -> &'static str {
+    match value {
+        1 => "RUN_LVL",
+        2 => "BOOT_TIME",
+        3 => "NEW_TIME",
+        4 => "OLD_TIME",
+        5 => "INIT_PROCESS",
+        6 => "LOGIN_PROCESS",
+        7 => "USER_PROCESS",
+        8 => "DEAD_PROCESS",
+        9 => "ACCOUNTING",
+        _ => "",
+    }
+}
+
+/// read a null terminated string of a fixed length field
+fn fixed_string(data: &[u8]) -> String {
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+/// the IP of a utmp record, IPv4 when only the first dword is set
+fn utmp_ip(data: &[u8]) -> String {
+    if data.iter().all(|b| *b == 0) {
+        return String::new();
+    }
+    match data[4..].iter().all(|b| *b == 0) {
+        true => Ipv4Addr::new(data[0], data[1], data[2], data[3]).to_string(),
+        false => <[u8; 16]>::try_from(data).map(|a| Ipv6Addr::from(a).to_string()).unwrap_or_default(),
+    }
+}
+
+/// parse a utmp record, returning None for empty records
+pub fn parse_utmp(data: &[u8]) -> Option<UtmpRecord> {
+    let record_type = utmp_type(le_u32(data, 0)?);
+    if record_type.is_empty() || data.len() < UTMP_SIZE {
+        return None;
+    }
+    let seconds = le_u32(data, 340)?;
+    let micros = le_u32(data, 344)?;
+    Some(UtmpRecord {
+        timestamp: common::unix_to_dt(seconds as i64).map(|dt| dt + Duration::microseconds(micros as i64 % 1_000_000)),
+        record_type,
+        pid: le_u32(data, 4)?,
+        terminal: fixed_string(&data[8..40]),
+        user: fixed_string(&data[44..76]),
+        host: fixed_string(&data[76..332]),
+        ip: utmp_ip(&data[348..364]),
+        session: le_u32(data, 336)?,
+    })
+}
+
+/// read a record of a fixed size, returning false at the end of the data, where a
+/// partial record is dropped
+fn read_record(reader: &mut impl Read, record: &mut [u8]) -> Result<bool> {
+    match reader.read_exact(record) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// parse the lastlog record of a uid, returning None if it never logged in
+fn parse_lastlog_record(uid: u32, record: &[u8], accounts: &HashMap<u32, Account>) -> Option<UtmpRecord> {
+    let seconds = le_u32(record, 0)?;
+    if seconds == 0 || record.len() < LASTLOG_SIZE {
+        return None;
+    }
+    Some(UtmpRecord {
+        timestamp: common::unix_to_dt(seconds as i64),
+        record_type: "LAST_LOGIN",
+        terminal: fixed_string(&record[4..36]),
+        user: accounts.get(&uid).map(|a| a.name.clone()).unwrap_or_else(|| format!("uid {uid}")),
+        host: fixed_string(&record[36..292]),
+        ..Default::default()
+    })
+}
+
+/// read the lastlog records, being the last login of each uid by its index. The records
+/// up to LASTLOG_MAX_UID are read in order, and those of the accounts above it by uid
+pub fn read_lastlog<R: Read + Seek>(mut reader: R, accounts: &HashMap<u32, Account>) -> Result<Vec<UtmpRecord>> {
+    let mut records = Vec::new();
+    let mut record = [0u8; LASTLOG_SIZE];
+    let mut buffered = BufReader::new(&mut reader);
+    for uid in 0..LASTLOG_MAX_UID {
+        if !read_record(&mut buffered, &mut record)? {
+            break;
+        }
+        records.extend(parse_lastlog_record(uid, &record, accounts));
+    }
+    drop(buffered);
+    let mut high: Vec<u32> = accounts.keys().copied().filter(|uid| *uid >= LASTLOG_MAX_UID).collect();
+    high.sort();
+    for uid in high {
+        reader.seek(SeekFrom::Start(uid as u64 * LASTLOG_SIZE as u64))?;
+        if read_record(&mut reader, &mut record)? {
+            records.extend(parse_lastlog_record(uid, &record, accounts));
+        }
+    }
+    Ok(records)
+}
+
+/// run the builtin parser of the login records, with the input as the Linux root. The
+/// records are written to the outfile
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let root = Path::new(&args.input);
+    let log_folder = root.join("var").join("log");
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(LOGINS_HEADER)?;
+    let mut write = |record: &UtmpRecord, kind: &str, source: &str| writer.write_record([
+        common::fmt_dt(record.timestamp), kind.to_string(), record.record_type.to_string(), record.user.clone(),
+        record.terminal.clone(), record.host.clone(), record.ip.clone(),
+        match record.pid { 0 => String::new(), pid => pid.to_string() },
+        match record.session { 0 => String::new(), session => session.to_string() },
+        source.to_string(),
+    ]);
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for kind in ["wtmp", "btmp"] {
+        for log in linux::rotated_logs(&log_folder, &[kind]) {
+            let source = log.display().to_string();
+            let mut reader = match linux::open_log(&log) {
+                Ok(reader) => reader,
+                Err(e) => {
+                    file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read {source}: {:#}", e));
+                    continue;
+                }
+            };
+            let mut record = [0u8; UTMP_SIZE];
+            loop {
+                match read_record(&mut reader, &mut record) {
+                    Ok(true) => (),
+                    Ok(false) => break,
+                    Err(e) => {
+                        file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read all of {source}: {:#}", e));
+                        break;
+                    }
+                }
+                if let Some(record) = parse_utmp(&record) {
+                    write(&record, kind, &source)?;
+                    *counts.entry(kind).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    let lastlog = log_folder.join("lastlog");
+    if let Ok(file) = File::open(&lastlog) {
+        match read_lastlog(file, &linux::read_passwd(root)) {
+            Ok(records) => {
+                for record in records {
+                    write(&record, "lastlog", &lastlog.display().to_string())?;
+                    *counts.entry("lastlog").or_insert(0) += 1;
+                }
+            }
+            Err(e) => file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read {}: {:#}", lastlog.display(), e)),
+        }
+    }
+    writer.flush()?;
+    let count = |kind: &str| counts.get(kind).copied().unwrap_or(0);
+    Ok(format!("Linux logins: {} wtmp records, {} failed logins of btmp and {} last logins", count("wtmp"), count("btmp"), count("lastlog")))
+}
@@ -0,0 +1,299 @@
+/*
+Builtin parser of the persistence of a Linux host, in the columns of the Windows
+persistence report. Reads the system crontab and cron.d, the crontabs of each user in
+var/spool/cron, the scripts of the cron.hourly to cron.monthly folders, anacrontab,
+the systemd services, timers, sockets and paths of the system and of each user, and
+rc.local. Units shipped with the OS in usr/lib/systemd are only reported when enabled,
+being linked from a `.wants` or `.requires` folder, unless all units are asked for.
+*/
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+use super::common as linux;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::parsers::common;
+use crate::parsers::persistence::{PersistenceEntry, PERSISTENCE_HEADER};
+use crate::timeline::builder::split_args;
+
+/// The crontabs with a user field, relative to the root
+const SYSTEM_CRONTABS: [&str; 2] = ["etc/crontab", "etc/cron.d"];
+/// The folders of the crontabs of each user, named by the user
+const USER_CRONTABS: [&str; 3] = ["var/spool/cron/crontabs", "var/spool/cron/tabs", "var/spool/cron"];
+const CRON_PERIODS: [&str; 4] = ["hourly", "daily", "weekly", "monthly"];
+/// The folders of the systemd units of the system and users, with whether the units are
+/// shipped with the OS
+const UNIT_FOLDERS: [(&str, bool); 6] = [
+    ("etc/systemd/system", false),
+    ("etc/systemd/user", false),
+    ("run/systemd/system", false),
+    ("usr/lib/systemd/system", true),
+    ("usr/lib/systemd/user", true),
+    ("lib/systemd/system", true),
+];
+const USER_UNIT_FOLDER: &str = ".config/systemd/user";
+const UNIT_TYPES: [&str; 4] = ["service", "timer", "socket", "path"];
+/// The settings of a unit reported in its details
+const UNIT_DETAILS: [&str; 6] = ["Description", "WantedBy", "OnCalendar", "OnBootSec", "ListenStream", "PathChanged"];
+
+/// parse the lines of a crontab, with a user field for the system crontabs. Each is the
+/// schedule, user and command
+pub fn parse_crontab(text: &str, user_field: bool) -> Vec<(String, String, String)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // environment settings such as `SHELL=/bin/sh` or `MAILTO = root`
+            if fields[0].contains('=') || fields.get(1) == Some(&"=") {
+                return None;
+            }
+            let schedule_len = if fields[0].starts_with('@') { 1 } else { 5 };
+            let command_at = schedule_len + usize::from(user_field);
+            if fields.len() <= command_at {
+                return None;
+            }
+            let user = if user_field { fields[schedule_len].to_string() } else { String::new() };
+            Some((fields[..schedule_len].join(" "), user, skip_fields(line, command_at).to_string()))
+        })
+        .collect()
+}
+
+/// the rest of a line after a number of whitespace separated fields, keeping the
+/// spacing of a command
+fn skip_fields(line: &str, count: usize) -> &str {
+    let mut rest = line.trim_start();
+    for _ in 0..count {
+        rest = rest.split_once(char::is_whitespace).map(|(_, r)| r.trim_start()).unwrap_or("");
+    }
+    rest
+}
+
+/// parse the jobs of anacrontab, each being the period, job ID and command
+pub fn parse_anacrontab(text: &str) -> Vec<(String, String, String)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields[0].contains('=') || fields.len() < 4 {
+                return None;
+            }
+            Some((fields[0].to_string(), fields[2].to_string(), skip_fields(line, 3).to_string()))
+        })
+        .collect()
+}
+
+/// parse the settings of a systemd unit, as the key and value of each line. Keys that are
+/// set more than once, such as ExecStart, are kept in order
+pub fn parse_unit(text: &str) -> Vec<(String, String)> {
+    let mut settings = Vec::new();
+    let mut continued: Option<(String, String)> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some((key, mut value)) = continued.take() {
+            value.push(' ');
+            value.push_str(line.trim_end_matches('\\').trim());
+            match line.ends_with('\\') {
+                true => continued = Some((key, value)),
+                false => settings.push((key, value)),
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with(['#', ';', '[']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let setting = (key.trim().to_string(), value.trim_end_matches('\\').trim().to_string());
+        match line.ends_with('\\') {
+            true => continued = Some(setting),
+            false => settings.push(setting),
+        }
+    }
+    settings.extend(continued);
+    settings
+}
+
+/// the units that are enabled, by the links of the `.wants` and `.requires` folders
+fn enabled_units(unit_folders: &[PathBuf]) -> HashSet<String> {
+    unit_folders.iter()
+        .flat_map(|folder| fs::read_dir(folder).into_iter().flatten().flatten())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.ends_with(".wants") || name.ends_with(".requires")
+        })
+        .flat_map(|e| fs::read_dir(e.path()).into_iter().flatten().flatten())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+fn file_entry(path: &Path, mechanism: &str, location: &str) -> PersistenceEntry {
+    PersistenceEntry {
+        timestamp: linux::modified(path),
+        timestamp_type: "Modified".to_string(),
+        mechanism: mechanism.to_string(),
+        location: location.to_string(),
+        source: path.display().to_string(),
+        ..Default::default()
+    }
+}
+
+fn files_in(folder: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// the cron jobs of the system, users, periodic folders and anacrontab
+pub fn cron_entries(root: &Path) -> Vec<PersistenceEntry> {
+    let mut entries = Vec::new();
+    let mut crontab = |path: &Path, location: &str, user: Option<String>| {
+        let Ok(data) = fs::read(path) else { return };
+        for (schedule, field_user, command) in parse_crontab(&String::from_utf8_lossy(&data), user.is_none()) {
+            entries.push(PersistenceEntry {
+                name: file_name(path),
+                command,
+                user: user.clone().unwrap_or(field_user),
+                details: format!("Schedule: {schedule}"),
+                ..file_entry(path, "Cron", location)
+            });
+        }
+    };
+    for system in SYSTEM_CRONTABS {
+        let path = root.join(system);
+        let tabs = if path.is_dir() { files_in(&path) } else { vec![path] };
+        for tab in tabs {
+            crontab(&tab, system, None);
+        }
+    }
+    let mut seen = HashSet::new();
+    for folder in USER_CRONTABS {
+        for tab in files_in(&root.join(folder)) {
+            if seen.insert(tab.clone()) {
+                crontab(&tab, folder, Some(file_name(&tab)));
+            }
+        }
+    }
+    for period in CRON_PERIODS {
+        let location = format!("etc/cron.{period}");
+        for script in files_in(&root.join(&location)) {
+            entries.push(PersistenceEntry {
+                name: file_name(&script),
+                command: format!("/{location}/{}", file_name(&script)),
+                user: "root".to_string(),
+                details: format!("Schedule: {period}"),
+                ..file_entry(&script, "Cron", &location)
+            });
+        }
+    }
+    let anacrontab = root.join("etc").join("anacrontab");
+    if let Ok(data) = fs::read(&anacrontab) {
+        for (period, job, command) in parse_anacrontab(&String::from_utf8_lossy(&data)) {
+            entries.push(PersistenceEntry {
+                name: job,
+                command,
+                user: "root".to_string(),
+                details: format!("Period: {period}"),
+                ..file_entry(&anacrontab, "Anacron", "etc/anacrontab")
+            });
+        }
+    }
+    entries
+}
+
+/// the systemd units of the system and users, with the units of the OS only when they
+/// are enabled or all units are asked for
+pub fn systemd_entries(root: &Path, all_units: bool) -> Vec<PersistenceEntry> {
+    let mut folders: Vec<(PathBuf, String, bool, String)> = UNIT_FOLDERS.iter()
+        .map(|(folder, vendor)| (root.join(folder), folder.to_string(), *vendor, String::new()))
+        .collect();
+    for (user, home) in linux::user_homes(root) {
+        let location = home.strip_prefix(root).unwrap_or(&home).join(USER_UNIT_FOLDER);
+        folders.push((root.join(&location), location.display().to_string(), false, user));
+    }
+    let enabled = enabled_units(&folders.iter().map(|(f, ..)| f.clone()).collect::<Vec<_>>());
+
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    for (folder, location, vendor, user) in folders {
+        for unit in files_in(&folder) {
+            let name = file_name(&unit);
+            let is_unit = name.rsplit_once('.').is_some_and(|(_, ext)| UNIT_TYPES.contains(&ext));
+            let is_enabled = enabled.contains(&name);
+            if !is_unit || (vendor && !is_enabled && !all_units) || !seen.insert(fs::canonicalize(&unit).unwrap_or(unit.clone())) {
+                continue;
+            }
+            let Ok(data) = fs::read(&unit) else { continue };
+            let settings = parse_unit(&String::from_utf8_lossy(&data));
+            let values = |key: &str| settings.iter()
+                .filter(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            let mut details: Vec<String> = UNIT_DETAILS.iter()
+                .flat_map(|key| values(key).into_iter().map(move |v| format!("{key}: {v}")))
+                .collect();
+            details.push(format!("Enabled: {is_enabled}"));
+            let run_as = values("User").first().map(|u| u.to_string());
+            entries.push(PersistenceEntry {
+                name,
+                command: [values("ExecStartPre"), values("ExecStart")].concat().join("; "),
+                user: run_as.unwrap_or_else(|| user.clone()),
+                details: details.join(", "),
+                ..file_entry(&unit, "Systemd", &location)
+            });
+        }
+    }
+    entries
+}
+
+/// the commands of rc.local
+pub fn rc_local_entries(root: &Path) -> Vec<PersistenceEntry> {
+    ["etc/rc.local", "etc/rc.d/rc.local"].iter()
+        .map(|location| (root.join(location), location))
+        .filter_map(|(path, location)| {
+            let data = fs::read(&path).ok()?;
+            let commands: Vec<String> = String::from_utf8_lossy(&data).lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#') && *l != "exit 0")
+                .map(str::to_string)
+                .collect();
+            Some(commands.into_iter().map(|command| PersistenceEntry {
+                name: "rc.local".to_string(),
+                command,
+                user: "root".to_string(),
+                ..file_entry(&path, "RcLocal", location)
+            }).collect::<Vec<_>>())
+        })
+        .flatten()
+        .collect()
+}
+
+/// run the builtin persistence wisker of a Linux host, with the input as the Linux root.
+/// `--all-units` includes the systemd units of the OS that aren't enabled
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let root = Path::new(&args.input);
+    let all_units = split_args(&args.args).iter().any(|a| a == "--all-units");
+    let mut entries = cron_entries(root);
+    entries.extend(systemd_entries(root, all_units));
+    entries.extend(rc_local_entries(root));
+
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(PERSISTENCE_HEADER)?;
+    for entry in &entries {
+        writer.write_record(entry.to_record())?;
+    }
+    writer.flush()?;
+    Ok(format!("Linux persistence entries written: {}", entries.len()))
+}
@@ -0,0 +1,174 @@
+/*
+Builtin parser of the Linux system logs: auth.log or secure for authentication, and
+syslog, messages and kern.log, with their rotations. Each line is split into its time,
+host, program, PID and message, in the traditional BSD format or the RFC 3339 times of
+rsyslog. The SSH logins, failures, sessions, sudo commands and account changes of the
+auth logs are named as events with their user and source IP.
+*/
+
+use std::path::Path;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+
+use super::common::{self as linux, SyslogYear};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+
+const SYSLOG_HEADER: [&str; 10] = [
+    "Timestamp", "Host", "Program", "Pid", "Event", "User", "SourceIp", "Message", "Log", "SourceFile",
+];
+/// The logs read from var/log, with the kind of each
+const LOGS: [(&str, &str); 5] = [
+    ("auth.log", "auth"),
+    ("secure", "auth"),
+    ("syslog", "syslog"),
+    ("messages", "syslog"),
+    ("kern.log", "kern"),
+];
+
+/// A line of a system log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyslogLine {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub host: String,
+    pub program: String,
+    pub pid: String,
+    pub message: String,
+}
+
+/// An authentication event of a message, with its user and source IP
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthEvent {
+    pub event: &'static str,
+    pub user: String,
+    pub source_ip: String,
+}
+
+/// Parses the lines of the system logs, and the events of the auth logs
+pub struct SyslogParser {
+    line: Regex,
+    events: Vec<(&'static str, Option<&'static str>, Regex)>,
+    timezone: Option<Tz>,
+}
+
+impl SyslogParser {
+    pub fn new(timezone: Option<Tz>) -> Result<Self> {
+        // the events with the program they are logged by, in the order they are tried
+        let events = [
+            ("ssh_login", Some("sshd"), r"^Accepted (?P<method>\S+) for (?P<user>\S+) from (?P<ip>\S+)"),
+            ("ssh_failed", Some("sshd"), r"^Failed (?P<method>\S+) for (?:invalid user )?(?P<user>\S+) from (?P<ip>\S+)"),
+            ("ssh_invalid_user", Some("sshd"), r"^Invalid user (?P<user>\S*) from (?P<ip>\S+)"),
+            ("ssh_disconnect", Some("sshd"), r"^Disconnected from (?:authenticating |invalid )?user (?P<user>\S+) (?P<ip>\S+)"),
+            ("sudo", Some("sudo"), r"^\s*(?P<user>\S+) : .*COMMAND="),
+            ("su", Some("su"), r"session opened for user (?P<user>[^\s(]+)"),
+            ("session_opened", None, r"session opened for user (?P<user>[^\s(]+)"),
+            ("session_closed", None, r"session closed for user (?P<user>[^\s(]+)"),
+            ("auth_failure", None, r"authentication failure;(?:.*?\brhost=(?P<ip>\S+))?(?:.*?\buser=(?P<user>\S+))?"),
+            ("user_added", None, r"^new user: name=(?P<user>[^,\s]+)"),
+            ("user_deleted", None, r"^delete user '(?P<user>[^']+)'"),
+            ("password_changed", None, r"password changed for (?P<user>\S+)"),
+            ("group_changed", None, r"^(?:add '(?P<user>[^']+)' to (?:shadow )?group|new group: name=)"),
+        ];
+        Ok(SyslogParser {
+            line: Regex::new(concat!(
+                r"^(?:(?P<iso>\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?)|",
+                r"(?P<bsd>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}))\s+(?P<host>\S+)\s+",
+                r"(?P<program>[^\s\[:]+)(?:\[(?P<pid>\d+)\])?:\s?(?P<message>.*)$",
+            ))?,
+            events: events.into_iter()
+                .map(|(event, program, pattern)| Ok((event, program, Regex::new(pattern)?)))
+                .collect::<Result<_>>()?,
+            timezone,
+        })
+    }
+
+    /// parse a line, returning None for lines that aren't a syslog message
+    pub fn parse(&self, line: &str, year: &SyslogYear) -> Option<SyslogLine> {
+        let caps = self.line.captures(line)?;
+        let field = |name: &str| caps.name(name).map(|m| m.as_str().to_string()).unwrap_or_default();
+        let timestamp = match (caps.name("iso"), caps.name("bsd")) {
+            (Some(iso), _) => DateTime::parse_from_rfc3339(iso.as_str()).ok()
+                .map(|dt| dt.with_timezone(&Utc))
+                .or_else(|| {
+                    let local = chrono::NaiveDateTime::parse_from_str(iso.as_str(), "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+                    linux::local_to_utc(&local, self.timezone.as_ref())
+                }),
+            (_, Some(bsd)) => year.date(bsd.as_str()).and_then(|local| linux::local_to_utc(&local, self.timezone.as_ref())),
+            _ => None,
+        };
+        Some(SyslogLine {
+            timestamp,
+            host: field("host"),
+            program: field("program"),
+            pid: field("pid"),
+            message: field("message"),
+        })
+    }
+
+    /// the authentication event of a message, if it is one
+    pub fn event(&self, line: &SyslogLine) -> Option<AuthEvent> {
+        let program = line.program.rsplit('/').next().unwrap_or_default();
+        self.events.iter()
+            .filter(|(_, by, _)| by.is_none_or(|p| program == p))
+            .find_map(|(event, _, regex)| {
+                let caps = regex.captures(&line.message)?;
+                let field = |name: &str| caps.name(name).map(|m| m.as_str().to_string());
+                Some(AuthEvent {
+                    event,
+                    user: field("user").unwrap_or_default(),
+                    source_ip: field("ip").unwrap_or_default(),
+                })
+            })
+    }
+}
+
+/// run the builtin parser of the system logs, with the input as the Linux root. The lines
+/// are written to the outfile, with the local times of syslog converted to UTC with the
+/// host's timezone
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let root = Path::new(&args.input);
+    let log_folder = root.join("var").join("log");
+    let timezone = linux::host_timezone(root);
+    if timezone.is_none() {
+        file_ops::log_msg(&args.main_args.out_log, "[-] The timezone of the Linux host wasn't found, syslog times are read as UTC".to_string());
+    }
+    let parser = SyslogParser::new(timezone)?;
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(SYSLOG_HEADER)?;
+
+    let (mut lines, mut events, mut logs, mut skipped) = (0, 0, 0, 0);
+    for (name, kind) in LOGS {
+        for log in linux::rotated_logs(&log_folder, &[name]) {
+            let year = SyslogYear::new(&log);
+            let source = log.display().to_string();
+            let mut error = None;
+            let read = linux::read_lines(&log, |text| {
+                let Some(line) = parser.parse(text, &year) else {
+                    skipped += usize::from(!text.trim().is_empty());
+                    return;
+                };
+                let event = parser.event(&line).unwrap_or_default();
+                events += usize::from(!event.event.is_empty());
+                lines += 1;
+                if let Err(e) = writer.write_record([
+                    common::fmt_dt(line.timestamp), line.host, line.program, line.pid, event.event.to_string(),
+                    event.user, event.source_ip, line.message, kind.to_string(), source.clone(),
+                ]) {
+                    error.get_or_insert(e);
+                }
+            });
+            if let Some(e) = error {
+                return Err(e.into());
+            }
+            match read {
+                Ok(()) => logs += 1,
+                Err(e) => file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read the log {source}: {:#}", e)),
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(format!("Linux logs: {lines} lines from {logs} logs, with {events} authentication events ({skipped} lines skipped)"))
+}
@@ -0,0 +1,140 @@
+/*
+Builtin parser of the access logs of Apache, nginx and lighttpd in var/log, with their
+rotations. Lines in the common and combined log formats are split into the client,
+request, status, size, referer and user agent, with the time converted to UTC from the
+offset it is logged with.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use super::common as linux;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+
+const ACCESS_HEADER: [&str; 12] = [
+    "Timestamp", "ClientIp", "User", "Method", "Url", "Protocol", "Status", "Bytes", "Referer", "UserAgent",
+    "Server", "SourceFile",
+];
+/// The log folders of the web servers, in var/log
+const SERVERS: [&str; 4] = ["apache2", "httpd", "nginx", "lighttpd"];
+
+/// A request of an access log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessLine {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub client_ip: String,
+    pub user: String,
+    pub method: String,
+    pub url: String,
+    pub protocol: String,
+    pub status: String,
+    pub bytes: String,
+    pub referer: String,
+    pub user_agent: String,
+}
+
+/// Parses the lines of the common and combined log formats
+pub struct AccessParser {
+    line: Regex,
+}
+
+impl AccessParser {
+    pub fn new() -> Result<Self> {
+        Ok(AccessParser {
+            line: Regex::new(concat!(
+                r#"^(?P<ip>\S+) \S+ (?P<user>\S+) \[(?P<time>[^\]]+)\] "(?P<request>(?:[^"\\]|\\.)*)" "#,
+                r#"(?P<status>\d{3}) (?P<bytes>\S+)(?: "(?P<referer>(?:[^"\\]|\\.)*)" "(?P<agent>(?:[^"\\]|\\.)*)")?"#,
+            ))?,
+        })
+    }
+
+    /// parse a line, returning None for lines not in the common or combined format
+    pub fn parse(&self, line: &str) -> Option<AccessLine> {
+        let caps = self.line.captures(line)?;
+        let field = |name: &str| match caps.name(name).map(|m| m.as_str()) {
+            Some("-") | None => String::new(),
+            Some(value) => value.to_string(),
+        };
+        let request = field("request");
+        let mut parts = request.splitn(3, ' ');
+        let (method, url, protocol) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(url), protocol) => (method.to_string(), url.to_string(), protocol.unwrap_or_default().to_string()),
+            // requests that aren't HTTP, such as a TLS handshake to a plain port
+            _ => (String::new(), request.clone(), String::new()),
+        };
+        Some(AccessLine {
+            timestamp: DateTime::parse_from_str(&field("time"), "%d/%b/%Y:%H:%M:%S %z").ok().map(|dt| dt.with_timezone(&Utc)),
+            client_ip: field("ip"),
+            user: field("user"),
+            method,
+            url,
+            protocol,
+            status: field("status"),
+            bytes: field("bytes"),
+            referer: field("referer"),
+            user_agent: field("agent"),
+        })
+    }
+}
+
+/// the access logs of the web servers, with the server of each
+pub fn find_access_logs(root: &Path) -> Vec<(&'static str, PathBuf)> {
+    let log_folder = root.join("var").join("log");
+    SERVERS.iter()
+        .flat_map(|server| {
+            let mut names: Vec<String> = fs::read_dir(log_folder.join(server)).into_iter()
+                .flatten()
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|n| n.contains("access"))
+                .collect();
+            names.sort();
+            names.dedup();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            linux::rotated_logs(&log_folder.join(server), &names).into_iter()
+                .map(|log| (*server, log))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// run the builtin parser of the web server access logs, with the input as the Linux root.
+/// The requests are written to the outfile
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let root = Path::new(&args.input);
+    let parser = AccessParser::new()?;
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(ACCESS_HEADER)?;
+    let (mut requests, mut logs, mut skipped) = (0, 0, 0);
+    for (server, log) in find_access_logs(root) {
+        let source = log.display().to_string();
+        let mut error = None;
+        let read = linux::read_lines(&log, |text| {
+            let Some(line) = parser.parse(text) else {
+                skipped += usize::from(!text.trim().is_empty());
+                return;
+            };
+            requests += 1;
+            if let Err(e) = writer.write_record([
+                common::fmt_dt(line.timestamp), line.client_ip, line.user, line.method, line.url, line.protocol,
+                line.status, line.bytes, line.referer, line.user_agent, server.to_string(), source.clone(),
+            ]) {
+                error.get_or_insert(e);
+            }
+        });
+        if let Some(e) = error {
+            return Err(e.into());
+        }
+        match read {
+            Ok(()) => logs += 1,
+            Err(e) => file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read the log {source}: {:#}", e)),
+        }
+    }
+    writer.flush()?;
+    Ok(format!("Web access logs: {requests} requests from {logs} logs ({skipped} lines skipped)"))
+}
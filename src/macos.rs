@@ -0,0 +1,6 @@
+pub mod common;
+pub mod history;
+pub mod install_log;
+pub mod knowledgec;
+pub mod launchd;
+pub mod quarantine;
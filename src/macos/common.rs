@@ -0,0 +1,70 @@
+/*
+Shared reading of macOS evidence, from a mounted volume or the `[root]` folder of a UAC
+collection. The system's files are under private, as etc and var are links to it, and
+the users' homes are in Users, with root's in private/var/root. Plists are read whether
+they are binary, as most on the system are, or XML.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use plist::Value;
+
+use crate::linux::common::UAC_ROOT;
+
+/// The folders of the users' homes that aren't a user
+const SHARED_HOMES: [&str; 2] = ["Shared", "Guest"];
+
+/// check if the folder is the root of a macOS volume, having private/var and Library
+pub fn is_macos_root(path: &Path) -> bool {
+    path.join("private").join("var").is_dir() && path.join("Library").is_dir()
+}
+
+/// the root of the macOS volume in the data source, being the data source itself or the
+/// `[root]` folder of a UAC collection
+pub fn macos_root(data_source: &Path) -> Option<PathBuf> {
+    [data_source.to_path_buf(), data_source.join(UAC_ROOT)]
+        .into_iter()
+        .find(|p| is_macos_root(p))
+}
+
+/// the home folders of the users in Users, with root's. Each is the user and the folder
+pub fn user_homes(root: &Path) -> Vec<(String, PathBuf)> {
+    let mut homes: Vec<(String, PathBuf)> = fs::read_dir(root.join("Users")).into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+        .filter(|(user, _)| !user.starts_with('.') && !SHARED_HOMES.contains(&user.as_str()))
+        .collect();
+    homes.sort();
+    let root_home = root.join("private").join("var").join("root");
+    if root_home.is_dir() {
+        homes.push(("root".to_string(), root_home));
+    }
+    homes
+}
+
+/// read a binary or XML plist
+pub fn read_plist(path: &Path) -> Result<Value> {
+    Value::from_file(path).with_context(|| format!("Unable to read the plist {}", path.display()))
+}
+
+/// format a plist value as text, with arrays joined by spaces and dictionaries as their
+/// keys and values
+pub fn plist_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Date(d) => d.to_xml_format(),
+        Value::Array(values) => values.iter().map(plist_text).collect::<Vec<_>>().join(" "),
+        Value::Dictionary(dict) => dict.iter()
+            .map(|(k, v)| format!("{k}={}", plist_text(v)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        Value::Data(data) => crate::parsers::common::to_hex(data),
+        _ => String::new(),
+    }
+}
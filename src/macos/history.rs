@@ -0,0 +1,32 @@
+/*
+Builtin parser of the shell histories of a macOS volume, being zsh, the default shell
+from Catalina, and bash with the per session histories of .bash_sessions, read by the
+parsers of the Linux shell histories.
+*/
+
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+
+use super::common as macos;
+use crate::linux::history;
+use crate::ops::builtin_ops::BuiltinArgs;
+
+/// run the builtin parser of the shell histories, with the input as the macOS root. The
+/// commands are written to the outfile
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let homes = macos::user_homes(Path::new(&args.input));
+    let mut histories = history::histories_of(homes.clone());
+    for (user, home) in homes {
+        let mut sessions: Vec<_> = fs::read_dir(home.join(".bash_sessions")).into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "history" || e == "historynew"))
+            .collect();
+        sessions.sort();
+        histories.extend(sessions.into_iter().map(|path| (user.clone(), "bash", path)));
+    }
+    let (commands, files) = history::write_histories(args, histories)?;
+    Ok(format!("macOS shell history: {commands} commands from {files} histories"))
+}
@@ -0,0 +1,99 @@
+/*
+Builtin parser of the software installs of a macOS volume, from private/var/log/install.log
+and its rotations. Each line has its local time with the offset from UTC, the host and
+the process, such as installd, softwareupdated or Installer. The packages PackageKit
+reports as installed are named with their version, so installs stand out from the rest
+of the log.
+*/
+
+use std::path::Path;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::linux::common as linux;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+
+const INSTALL_HEADER: [&str; 8] = ["Timestamp", "Host", "Process", "Pid", "Package", "Version", "Message", "SourceFile"];
+const LOG_FOLDER: &str = "private/var/log";
+
+/// A line of install.log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstallLine {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub host: String,
+    pub process: String,
+    pub pid: String,
+    pub package: String,
+    pub version: String,
+    pub message: String,
+}
+
+/// Parses the lines of install.log, and the packages installed
+pub struct InstallLogParser {
+    line: Regex,
+    installed: Regex,
+}
+
+impl InstallLogParser {
+    pub fn new() -> Result<Self> {
+        Ok(InstallLogParser {
+            line: Regex::new(r"^(?P<time>\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}[+-]\d{2}(?::?\d{2})?) (?P<host>\S+) (?P<process>[^\[:]+?)(?:\[(?P<pid>\d+)\])?: (?P<message>.*)$")?,
+            installed: Regex::new(r#"Installed "(?P<package>[^"]+)" \((?P<version>[^)]*)\)"#)?,
+        })
+    }
+
+    /// parse a line, returning None for the lines of a message spread over several lines
+    pub fn parse(&self, line: &str) -> Option<InstallLine> {
+        let caps = self.line.captures(line)?;
+        let field = |name: &str| caps.name(name).map(|m| m.as_str().to_string()).unwrap_or_default();
+        let message = field("message");
+        let (package, version) = self.installed.captures(&message)
+            .map(|c| (c["package"].to_string(), c["version"].to_string()))
+            .unwrap_or_default();
+        Some(InstallLine {
+            timestamp: DateTime::parse_from_str(&field("time"), "%Y-%m-%d %H:%M:%S%#z").ok().map(|dt| dt.with_timezone(&Utc)),
+            host: field("host"),
+            process: field("process"),
+            pid: field("pid"),
+            package,
+            version,
+            message,
+        })
+    }
+}
+
+/// run the builtin parser of install.log, with the input as the macOS root. The lines are
+/// written to the outfile
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let parser = InstallLogParser::new()?;
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(INSTALL_HEADER)?;
+    let (mut lines, mut installs, mut logs) = (0, 0, 0);
+    for log in linux::rotated_logs(&Path::new(&args.input).join(LOG_FOLDER), &["install.log"]) {
+        let source = log.display().to_string();
+        let mut error = None;
+        let read = linux::read_lines(&log, |text| {
+            let Some(line) = parser.parse(text) else { return };
+            lines += 1;
+            installs += usize::from(!line.package.is_empty());
+            if let Err(e) = writer.write_record([
+                common::fmt_dt(line.timestamp), line.host, line.process, line.pid, line.package, line.version,
+                line.message, source.clone(),
+            ]) {
+                error.get_or_insert(e);
+            }
+        });
+        if let Some(e) = error {
+            return Err(e.into());
+        }
+        match read {
+            Ok(()) => logs += 1,
+            Err(e) => file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read the log {source}: {:#}", e)),
+        }
+    }
+    writer.flush()?;
+    Ok(format!("macOS install log: {lines} lines from {logs} logs, with {installs} packages installed"))
+}
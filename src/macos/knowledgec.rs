@@ -0,0 +1,84 @@
+/*
+Builtin parser of the KnowledgeC databases of a macOS volume, the CoreDuet store of the
+user's activity. Each event of ZOBJECT is a stream, such as the app in focus, web usage,
+the display being on or the device being locked, with its start and end in Cocoa time
+and the value it recorded, such as the bundle ID of the app. The system database is in
+private/var/db/CoreDuet/Knowledge and each user's in their Library.
+*/
+
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+use super::common as macos;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+use crate::parsers::sqlite::{self, SqliteCopy, SqlRow};
+
+const KNOWLEDGEC_HEADER: [&str; 9] = ["Start", "End", "DurationSeconds", "Stream", "Value", "Bundle", "Created", "User", "SourceFile"];
+const SYSTEM_DB: &str = "private/var/db/CoreDuet/Knowledge/knowledgeC.db";
+const USER_DB: &str = "Library/Application Support/Knowledge/knowledgeC.db";
+
+/// the KnowledgeC databases of the volume, with the user of each
+pub fn find_databases(root: &Path) -> Vec<(String, PathBuf)> {
+    let mut databases = vec![(String::new(), root.join(SYSTEM_DB))];
+    databases.extend(macos::user_homes(root).into_iter().map(|(user, home)| (user, home.join(USER_DB))));
+    databases.retain(|(_, db)| db.is_file());
+    databases
+}
+
+fn cocoa(row: &SqlRow, column: &str) -> String {
+    common::fmt_dt(sqlite::real(row, column).and_then(common::cocoa_to_dt))
+}
+
+/// read the events of a KnowledgeC database, as the rows of the output
+pub fn read_events(db: &SqliteCopy) -> Result<Vec<[String; 7]>> {
+    let bundle = match db.has_table("ZSOURCE") {
+        true => "ZSOURCE.ZBUNDLEID",
+        false => "''",
+    };
+    let join = match db.has_table("ZSOURCE") {
+        true => "LEFT JOIN ZSOURCE ON ZOBJECT.ZSOURCE = ZSOURCE.Z_PK",
+        false => "",
+    };
+    let rows = db.query(&format!(
+        "SELECT ZOBJECT.ZSTREAMNAME AS stream, COALESCE(ZOBJECT.ZVALUESTRING, ZOBJECT.ZVALUEINTEGER, ZOBJECT.ZVALUEDOUBLE) AS value,
+         ZOBJECT.ZSTARTDATE AS start, ZOBJECT.ZENDDATE AS end, ZOBJECT.ZCREATIONDATE AS created, {bundle} AS bundle
+         FROM ZOBJECT {join} ORDER BY ZOBJECT.ZSTARTDATE"
+    ))?;
+    Ok(rows.iter().map(|row| {
+        let duration = match (sqlite::int(row, "start"), sqlite::int(row, "end")) {
+            (Some(start), Some(end)) if end >= start => (end - start).to_string(),
+            _ => String::new(),
+        };
+        [
+            cocoa(row, "start"), cocoa(row, "end"), duration, sqlite::text(row, "stream"),
+            sqlite::text(row, "value"), sqlite::text(row, "bundle"), cocoa(row, "created"),
+        ]
+    }).collect())
+}
+
+/// run the builtin parser of the KnowledgeC databases, with the input as the macOS root.
+/// The events are written to the outfile
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(KNOWLEDGEC_HEADER)?;
+    let (mut events, mut databases) = (0, 0);
+    for (user, path) in find_databases(Path::new(&args.input)) {
+        let read = SqliteCopy::open(&path).and_then(|db| read_events(&db));
+        let rows = match read {
+            Ok(rows) => rows,
+            Err(e) => {
+                file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read KnowledgeC {}: {:#}", path.display(), e));
+                continue;
+            }
+        };
+        for row in rows {
+            writer.write_record(row.iter().chain([&user, &path.display().to_string()]))?;
+            events += 1;
+        }
+        databases += 1;
+    }
+    writer.flush()?;
+    Ok(format!("KnowledgeC: {events} events from {databases} databases"))
+}
@@ -0,0 +1,132 @@
+/*
+Builtin parser of the persistence of a macOS volume, in the columns of the Windows
+persistence report. Reads the plists of the launch agents and daemons in Library and
+each user's Library, and the crontabs of private/var/at/tabs. The agents and daemons of
+System/Library ship with macOS on its sealed system volume, so are only reported when
+asked for. Each plist gives the label, the program and its arguments, the account it
+runs as and when it is started, such as at load, on an interval or when paths change.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use plist::{Dictionary, Value};
+
+use super::common as macos;
+use crate::linux::common as linux;
+use crate::linux::persistence::parse_crontab;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+use crate::parsers::persistence::{PersistenceEntry, PERSISTENCE_HEADER};
+use crate::timeline::builder::split_args;
+
+/// The folders of the launch agents and daemons, with whether they ship with macOS
+const LAUNCHD_FOLDERS: [(&str, &str, bool); 4] = [
+    ("Library/LaunchAgents", "LaunchAgent", false),
+    ("Library/LaunchDaemons", "LaunchDaemon", false),
+    ("System/Library/LaunchAgents", "LaunchAgent", true),
+    ("System/Library/LaunchDaemons", "LaunchDaemon", true),
+];
+const USER_AGENTS: &str = "Library/LaunchAgents";
+const CRONTABS: &str = "private/var/at/tabs";
+/// The keys of when a job is started, reported in its details
+const START_KEYS: [&str; 7] = ["RunAtLoad", "KeepAlive", "StartInterval", "StartCalendarInterval", "WatchPaths", "QueueDirectories", "StartOnMount"];
+
+/// the entry of a launch agent or daemon from its plist
+pub fn launchd_entry(plist: &Dictionary, mechanism: &str, location: &str, user: &str) -> PersistenceEntry {
+    let text = |key: &str| plist.get(key).map(macos::plist_text).unwrap_or_default();
+    let command = match plist.get("ProgramArguments") {
+        Some(Value::Array(args)) if !plist.contains_key("Program") => args.iter().map(macos::plist_text).collect::<Vec<_>>().join(" "),
+        Some(Value::Array(args)) => format!("{} {}", text("Program"), args.iter().skip(1).map(macos::plist_text).collect::<Vec<_>>().join(" ")).trim().to_string(),
+        _ => text("Program"),
+    };
+    let mut details: Vec<String> = START_KEYS.iter()
+        .filter(|key| plist.contains_key(key))
+        .map(|key| format!("{key}: {}", text(key)))
+        .collect();
+    if plist.get("Disabled").and_then(Value::as_boolean) == Some(true) {
+        details.push("Disabled: true".to_string());
+    }
+    let run_as = text("UserName");
+    PersistenceEntry {
+        mechanism: mechanism.to_string(),
+        location: location.to_string(),
+        name: text("Label"),
+        command,
+        user: if run_as.is_empty() { user.to_string() } else { run_as },
+        details: details.join(", "),
+        ..Default::default()
+    }
+}
+
+fn plists_in(folder: &Path) -> Vec<PathBuf> {
+    let mut plists: Vec<PathBuf> = fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "plist"))
+        .collect();
+    plists.sort();
+    plists
+}
+
+/// run the builtin persistence wisker of a macOS volume, with the input as the macOS
+/// root. `--all-items` includes the agents and daemons of System/Library
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let root = Path::new(&args.input);
+    let all_items = split_args(&args.args).iter().any(|a| a == "--all-items");
+    let mut folders: Vec<(PathBuf, String, &str, String)> = LAUNCHD_FOLDERS.iter()
+        .filter(|(_, _, system)| all_items || !system)
+        .map(|(folder, mechanism, _)| (root.join(folder), folder.to_string(), *mechanism, String::new()))
+        .collect();
+    for (user, home) in macos::user_homes(root) {
+        let location = home.strip_prefix(root).unwrap_or(&home).join(USER_AGENTS);
+        folders.push((root.join(&location), location.display().to_string(), "LaunchAgent", user));
+    }
+
+    let mut entries = Vec::new();
+    for (folder, location, mechanism, user) in folders {
+        for path in plists_in(&folder) {
+            let plist = match macos::read_plist(&path) {
+                Ok(Value::Dictionary(plist)) => plist,
+                Ok(_) => continue,
+                Err(e) => {
+                    file_ops::log_msg(&args.main_args.out_log, format!("[!] {:#}", e));
+                    continue;
+                }
+            };
+            entries.push(PersistenceEntry {
+                timestamp: linux::modified(&path),
+                timestamp_type: "Modified".to_string(),
+                source: path.display().to_string(),
+                ..launchd_entry(&plist, mechanism, &location, &user)
+            });
+        }
+    }
+    for tab in fs::read_dir(root.join(CRONTABS)).into_iter().flatten().flatten().map(|e| e.path()).filter(|p| p.is_file()) {
+        let Ok(data) = fs::read(&tab) else { continue };
+        let user = tab.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        for (schedule, _, command) in parse_crontab(&String::from_utf8_lossy(&data), false) {
+            entries.push(PersistenceEntry {
+                timestamp: linux::modified(&tab),
+                timestamp_type: "Modified".to_string(),
+                mechanism: "Cron".to_string(),
+                location: CRONTABS.to_string(),
+                name: user.clone(),
+                command,
+                user: user.clone(),
+                details: format!("Schedule: {schedule}"),
+                source: tab.display().to_string(),
+            });
+        }
+    }
+
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(PERSISTENCE_HEADER)?;
+    for entry in &entries {
+        writer.write_record(entry.to_record())?;
+    }
+    writer.flush()?;
+    Ok(format!("macOS persistence entries written: {}", entries.len()))
+}
@@ -0,0 +1,83 @@
+/*
+Builtin parser of the quarantine events of a macOS volume, from the LaunchServices
+QuarantineEventsV2 database in each user's Library/Preferences. Gatekeeper records a
+file downloaded or received by an app that sets the quarantine attribute, with the app,
+the URL of the file, the page it was linked from and the sender of attachments.
+*/
+
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+use super::common as macos;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+use crate::parsers::sqlite::{self, SqliteCopy};
+
+const QUARANTINE_HEADER: [&str; 11] = [
+    "Timestamp", "User", "Agent", "AgentBundle", "DataUrl", "OriginUrl", "SenderName", "SenderAddress", "Type",
+    "EventId", "SourceFile",
+];
+const QUARANTINE_DB: &str = "Library/Preferences/com.apple.LaunchServices.QuarantineEventsV2";
+
+/// the kind of a quarantine event from its LSQuarantineTypeNumber
+fn quarantine_type(value: i64) -> &'static str {
+    match value {
+        0 => "WebDownload",
+        1 => "OtherDownload",
+        2 => "EmailAttachment",
+        3 => "MessageAttachment",
+        4 => "CalendarAttachment",
+        5 => "OtherAttachment",
+        _ => "",
+    }
+}
+
+/// the quarantine databases of the users, with the user of each
+pub fn find_databases(root: &Path) -> Vec<(String, PathBuf)> {
+    macos::user_homes(root).into_iter()
+        .map(|(user, home)| (user, home.join(QUARANTINE_DB)))
+        .filter(|(_, db)| db.is_file())
+        .collect()
+}
+
+/// read the events of a quarantine database, as the rows of the output after the time
+pub fn read_events(db: &SqliteCopy) -> Result<Vec<[String; 9]>> {
+    let rows = db.query(
+        "SELECT LSQuarantineTimeStamp AS time, LSQuarantineAgentName AS agent, LSQuarantineAgentBundleIdentifier AS bundle,
+         LSQuarantineDataURLString AS data_url, LSQuarantineOriginURLString AS origin_url, LSQuarantineSenderName AS sender,
+         LSQuarantineSenderAddress AS address, LSQuarantineTypeNumber AS type, LSQuarantineEventIdentifier AS id
+         FROM LSQuarantineEvent ORDER BY LSQuarantineTimeStamp",
+    )?;
+    Ok(rows.iter().map(|row| {
+        [
+            common::fmt_dt(sqlite::real(row, "time").and_then(common::cocoa_to_dt)), sqlite::text(row, "agent"), sqlite::text(row, "bundle"),
+            sqlite::text(row, "data_url"), sqlite::text(row, "origin_url"), sqlite::text(row, "sender"),
+            sqlite::text(row, "address"), quarantine_type(sqlite::int(row, "type").unwrap_or(-1)).to_string(),
+            sqlite::text(row, "id"),
+        ]
+    }).collect())
+}
+
+/// run the builtin parser of the quarantine events, with the input as the macOS root. The
+/// events are written to the outfile
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let mut writer = common::csv_writer(&args.outfolder.join(&args.outfile))?;
+    writer.write_record(QUARANTINE_HEADER)?;
+    let mut events = 0;
+    for (user, path) in find_databases(Path::new(&args.input)) {
+        let rows = match SqliteCopy::open(&path).and_then(|db| read_events(&db)) {
+            Ok(rows) => rows,
+            Err(e) => {
+                file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read quarantine events {}: {:#}", path.display(), e));
+                continue;
+            }
+        };
+        for [time, rest @ ..] in rows {
+            writer.write_record([time, user.clone()].into_iter().chain(rest).chain([path.display().to_string()]))?;
+            events += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(format!("macOS quarantine events written: {events}"))
+}
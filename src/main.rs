@@ -1,6 +1,8 @@
 use wiskess_rust::configs::config;
 use wiskess_rust::ops::{file_ops, wiskess};
 use wiskess_rust::init::{scripts, setup};
+use wiskess_rust::iocs::report;
+use wiskess_rust::timeline::merge;
 use wiskess_rust::webs::web;
 use wiskess_rust::whipped::whip_main;
 use wiskess_rust::utils;
@@ -70,7 +72,7 @@ enum Commands {
         /// End date - the current date or end of the incident timeframe
         #[arg(long)]
         end_date: String,
-        /// IOC list file
+        /// IOC list file, or a folder of lists, as text, CSV or YAML
         #[arg(short, long, default_value = "iocs.txt")]
         ioc_file: String,
         /// The link that the data is stored on, i.e https://myaccount.file.core.windows.net/myclient/?sp=rl&st=...VWjgWTY8uc%3D&sr=s
@@ -87,6 +89,36 @@ enum Commands {
         /// Caution: make sure you have enough disk space for all the data source list.
         #[arg(short, long)]
         keep_evidence: bool,
+        /// Output format of the timeline and builtin parsers; native is JSONL and CSV
+        #[arg(long, value_enum, default_value_t = config::OutFormat::Native)]
+        out_format: config::OutFormat,
+        /// YARA rule folders or files, comma separated, scanned along with those of the config
+        #[arg(long, default_value = "")]
+        yara_rules: String,
+        /// Set this flag to merge the timelines of the hosts into one case timeline once all are processed
+        #[arg(long)]
+        merge_timelines: bool,
+        /// Set this flag to report the IOC findings of the hosts in one case IOC report once all are processed
+        #[arg(long)]
+        ioc_report: bool,
+    },
+    /// merge the timelines of the hosts processed by wiskess into one case timeline
+    MergeTimelines {
+        /// folder with the wiskess output of each host, i.e. the local storage of whipped
+        #[arg(short, long)]
+        input: String,
+        /// output folder of the case timeline, default is Case-Timeline in the input folder
+        #[arg(short, long, default_value = "")]
+        out_path: String,
+    },
+    /// report the IOC findings of the hosts processed by wiskess, with the hosts each IOC was seen on
+    IocReport {
+        /// folder with the wiskess output of each host, i.e. the local storage of whipped
+        #[arg(short, long)]
+        input: String,
+        /// output folder of the case IOC report, default is Case-IOC-Report in the input folder
+        #[arg(short, long, default_value = "")]
+        out_path: String,
     },
     /// process the data with wiskess
     Wiskess {
@@ -108,9 +140,15 @@ enum Commands {
         /// End date - the current date or end of the incident timeframe
         #[arg(long)]
         end_date: String,
-        /// IOC list file
+        /// IOC list file, or a folder of lists, as text, CSV or YAML
         #[arg(short, long)]
         ioc_file: String,
+        /// Output format of the timeline and builtin parsers; native is JSONL and CSV
+        #[arg(long, value_enum, default_value_t = config::OutFormat::Native)]
+        out_format: config::OutFormat,
+        /// YARA rule folders or files, comma separated, scanned along with those of the config
+        #[arg(long, default_value = "")]
+        yara_rules: String,
     },
     OldWhip {
         /// config file of the binaries to run as processors
@@ -131,7 +169,7 @@ enum Commands {
         /// End date - the current date or end of the incident timeframe
         #[arg(long)]
         end_date: String,
-        /// IOC list file
+        /// IOC list file, or a folder of lists, as text, CSV or YAML
         #[arg(short, long, default_value = "iocs.txt")]
         ioc_file: String,
         /// The link that the data is stored on, i.e https://myaccount.file.core.windows.net/myclient/?sp=rl&st=...VWjgWTY8uc%3D&sr=s
@@ -244,6 +282,10 @@ fn main() {
             out_link,
             update,
             keep_evidence,
+            out_format,
+            yara_rules,
+            merge_timelines,
+            ioc_report,
         } => {            
             // Confirm date is valid
             let start_date = file_ops::check_date(start_date, &"start date".to_string());
@@ -264,6 +306,10 @@ fn main() {
                 out_link,
                 update,
                 keep_evidence,
+                out_format,
+                merge_timelines,
+                ioc_report,
+                yara_rules,
             };
 
             match whip_main::whip_main(args, &tool_path) {
@@ -271,6 +317,42 @@ fn main() {
                 Err(e) => println!("[!] There was an issue getting the data whipped. Error: {e}")
             }
         },
+        Commands::MergeTimelines {
+            input,
+            out_path,
+        } => {
+            let out_path = match out_path.as_str() {
+                "" => Path::new(&input).join(merge::CASE_TIMELINE_FOLDER),
+                _ => PathBuf::from(out_path),
+            };
+            let timelines = merge::find_timelines(Path::new(&input));
+            match merge::merge(&timelines, &out_path) {
+                std::result::Result::Ok(report) => {
+                    println!("[+] Merged {} timelines into {}: {} events, {} duplicates removed",
+                        report.timelines, out_path.join(merge::CASE_TIMELINE_FILE).display(), report.events, report.duplicates);
+                    println!("{}", report.table());
+                },
+                Err(e) => println!("[!] Unable to merge the timelines. Error: {e}"),
+            }
+        },
+        Commands::IocReport {
+            input,
+            out_path,
+        } => {
+            let out_path = match out_path.as_str() {
+                "" => Path::new(&input).join(report::CASE_IOC_FOLDER),
+                _ => PathBuf::from(out_path),
+            };
+            let outputs = report::find_outputs(Path::new(&input));
+            match report::write_report(&outputs, &out_path) {
+                std::result::Result::Ok(report) => {
+                    println!("[+] Reported {} IOCs from {} wiskess outputs to {}",
+                        report.iocs.len(), report.outputs, out_path.join(report::CASE_IOC_HTML).display());
+                    println!("{}", report.table());
+                },
+                Err(e) => println!("[!] Unable to report the IOC findings. Error: {e}"),
+            }
+        },
         Commands::Wiskess {
             config,
             artefacts_config,
@@ -278,7 +360,9 @@ fn main() {
             out_path,
             start_date,
             end_date,
-            ioc_file
+            ioc_file,
+            out_format,
+            yara_rules
         } => {
             let (config, artefacts_config) = utils::check_configs(config, &tool_path, artefacts_config);
             
@@ -290,6 +374,9 @@ fn main() {
                 ioc_file,
                 silent: args.silent,
                 collect,
+                out_format,
+                yara_rules,
+                keep_timeline: false,
                 out_log: PathBuf::new(),
                 multi_pb: MultiProgress::new()
             };
@@ -327,6 +414,10 @@ fn main() {
                 out_link,
                 update,
                 keep_evidence,
+                out_format: Default::default(),
+                merge_timelines: false,
+                ioc_report: false,
+                yara_rules: String::new(),
             };
 
             scripts::run_whipped(&tool_path, args)
@@ -3,4 +3,7 @@ pub mod exe_ops;
 pub mod valid_ops;
 pub mod get_files;
 pub mod sector_reader;
-pub mod wiskess;
\ No newline at end of file
+pub mod wiskess;
+pub mod builtin_ops;
+pub mod parquet_ops;
+pub mod source_ops;
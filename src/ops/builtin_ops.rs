@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Result};
+
+use crate::configs::config::{self, Wiskers};
+use crate::detections::sigma;
+use crate::iocs::{hash_match, scanner as iocs, yara};
+use crate::linux::{history, journal, logins, persistence as linux_persistence, syslog, weblogs};
+use crate::macos::{history as macos_history, install_log, knowledgec, launchd, quarantine};
+use crate::parsers::{browsers, hostinfo, memory, persistence, recycle, srum, sum};
+use crate::timeline::builder as timeline;
+use super::{exe_ops, parquet_ops};
+
+/// Wiskers with a binary starting with this prefix are run natively by wiskess,
+/// i.e. `binary: 'builtin:srum'`, rather than as an external tool
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+/// check if the wisker binary is a builtin parser
+pub fn is_builtin(binary: &str) -> bool {
+    binary.starts_with(BUILTIN_PREFIX)
+}
+
+/// The arguments passed to a builtin, with the placeholders already replaced
+#[derive(Debug, Clone)]
+pub struct BuiltinArgs {
+    /// the name of the builtin, i.e. srum from `builtin:srum`
+    pub name: String,
+    /// the path to the artefact from the wisker's input
+    pub input: String,
+    /// the path to the artefact from the wisker's input_other, empty if not set
+    pub input_other: String,
+    /// the output folder, made from the out_path and the wisker's outfolder
+    pub outfolder: PathBuf,
+    /// the wisker's outfile
+    pub outfile: String,
+    /// the wisker's args
+    pub args: String,
+    pub data_paths: HashMap<String, String>,
+    pub main_args: config::MainArgs,
+}
+
+impl BuiltinArgs {
+    /// check if the input_other artefact was found
+    pub fn has_input_other(&self) -> bool {
+        !self.input_other.is_empty() && self.input_other != "wiskess_none"
+    }
+
+    /// get the path of another artefact by its name in the artefacts config, i.e. sam,
+    /// if it was found
+    pub fn data_path(&self, name: &str) -> Option<PathBuf> {
+        self.data_paths.get(name)
+            .filter(|p| !p.is_empty() && *p != "wiskess_none")
+            .map(PathBuf::from)
+    }
+}
+
+/// run a builtin wisker, returning a message of what was done
+///
+/// Args:
+/// * `wisker`: the wisker from the config with a `builtin:` binary
+/// * `data_paths`: a hash map of the file paths that the data is sourced, i.e. srum:'C:\Windows\System32\sru\SRUDB.dat'
+/// * `main_args`: the arguments specified from the main.rs, i.e. out_path
+pub fn run_builtin(wisker: &Wiskers, data_paths: &HashMap<String, String>, main_args: &config::MainArgs) -> Result<String> {
+    let outfolder = Path::new(&main_args.out_path).join(&wisker.outfolder);
+    let outfolder_str = outfolder.to_string_lossy().to_string();
+    let input_other = match wisker.input_other.as_str() {
+        "" => String::new(),
+        other => exe_ops::get_wisker_art(data_paths, &other.to_string(), main_args),
+    };
+    let args = BuiltinArgs {
+        name: wisker.binary.trim_start_matches(BUILTIN_PREFIX).to_string(),
+        input: exe_ops::get_wisker_art(data_paths, &wisker.input, main_args),
+        input_other,
+        outfolder,
+        outfile: wisker.outfile.clone(),
+        args: exe_ops::set_placeholder(&wisker.args, wisker, data_paths, &outfolder_str, main_args),
+        data_paths: data_paths.clone(),
+        main_args: main_args.clone(),
+    };
+    let msg = match args.name.as_str() {
+        "browsers" => browsers::run(&args),
+        "hash_match" => hash_match::run(&args),
+        "hostinfo" => hostinfo::run(&args),
+        "iocs" => iocs::run(&args),
+        "linux_history" => history::run(&args),
+        "linux_journal" => journal::run(&args),
+        "linux_logins" => logins::run(&args),
+        "linux_logs" => syslog::run(&args),
+        "linux_persistence" => linux_persistence::run(&args),
+        "macos_history" => macos_history::run(&args),
+        "macos_install_log" => install_log::run(&args),
+        "macos_knowledgec" => knowledgec::run(&args),
+        "macos_persistence" => launchd::run(&args),
+        "macos_quarantine" => quarantine::run(&args),
+        "memory_strings" => memory::run(&args),
+        "persistence" => persistence::run(&args),
+        "recycle_bin" => recycle::run(&args),
+        "sigma" => sigma::run(&args),
+        "srum" => srum::run(&args),
+        "sum" => sum::run(&args),
+        "timeline" => timeline::run(&args),
+        "web_logs" => weblogs::run(&args),
+        "yara" => yara::run(&args),
+        other => bail!("Unknown builtin wisker: {other}"),
+    }?;
+    // the timeline writes its own Parquet, partitioned by source and day
+    if main_args.out_format.parquet() && args.name != "timeline" {
+        let converted = parquet_ops::convert_builtin_outputs(wisker, main_args)?;
+        return Ok(format!("{msg}. Parquet files written: {}", converted.len()));
+    }
+    Ok(msg)
+}
@@ -5,7 +5,7 @@ use rayon::ThreadPoolBuilder;
 use std::fs::{canonicalize, OpenOptions};
 use crate::configs::config::{self, Wiskers};
 use crate::init::setup;
-use super::file_ops;
+use super::{builtin_ops, file_ops};
 
 pub fn run_whipped_script(script: &String, args: config::WhippedArgs) {
     let mut pwsh = "pwsh".to_string();
@@ -186,7 +186,7 @@ fn set_wisker(wisker: &config::Wiskers, data_paths: &HashMap<String, String>, fo
     }
 }
 
-fn set_placeholder(wisker_field: &String, wisker: &Wiskers, data_paths: &HashMap<String, String>, folder_path: &String, main_args: &config::MainArgs) -> String {
+pub(crate) fn set_placeholder(wisker_field: &String, wisker: &Wiskers, data_paths: &HashMap<String, String>, folder_path: &String, main_args: &config::MainArgs) -> String {
     let input_path = get_wisker_art(data_paths, &wisker.input, main_args);
     let mut input_other_path = String::new();
     if wisker.input_other != "" {
@@ -206,7 +206,7 @@ fn set_placeholder(wisker_field: &String, wisker: &Wiskers, data_paths: &HashMap
     wisker_arg
 }
 
-fn get_wisker_art(data_paths: &HashMap<String, String>, input: &String, _main_args: &config::MainArgs) -> String {
+pub(crate) fn get_wisker_art(data_paths: &HashMap<String, String>, input: &String, _main_args: &config::MainArgs) -> String {
     let input_path = data_paths[input].clone();
     
     if input_path != "" {
@@ -250,7 +250,9 @@ pub fn load_wisker(main_args_c: &config::MainArgs, wisker: &config::Wiskers, dat
 
 pub fn installed_binary_check(chk_exists: bool, binary: &String) -> String {
     let mut installed = false;
-    if chk_exists {
+    if builtin_ops::is_builtin(binary) {
+        installed = true;
+    } else if chk_exists {
         for test_arg in ["-h", "help", "--version", "-v", "-V", "-c print('wiskess')"] {
             if binary.starts_with("py") {
                 let python_check = run_wisker(binary, &"-V 2>&1".to_string(), Path::new(""));
@@ -330,8 +332,9 @@ pub fn run_commands(func: &Vec<Wiskers>, main_args: &config::MainArgs, data_path
         pool.spawn(move || {
             let input_file = data_paths_c[&wisker.input].as_str();
             if input_file != "wiskess_none" {
+                let builtin_paths = data_paths_c.clone();
                 // Build the variables needed to run the binary
-                let (wisker_arg, wisker_binary, wisker_script, overwrite_file, err_msg) = load_wisker(
+                let (wisker_arg, wisker_binary, wisker_script, overwrite_file, mut err_msg) = load_wisker(
                     &main_args_c, 
                     &wisker, 
                     data_paths_c);
@@ -348,16 +351,31 @@ pub fn run_commands(func: &Vec<Wiskers>, main_args: &config::MainArgs, data_path
                         _ = run_posh("-c", &wisker_script, &main_args_c.out_log, &"".to_string(), true);
                     }
 
-                    let output = run_wisker(&wisker_binary, &wisker_arg, &main_args_c.out_log);
-                    
-                    // run the binary with the args
-                    file_ops::log_msg(&main_args_c.out_log, format!("[+] Done {} with command: {} {}", 
-                        &wisker.name, 
-                        &wisker_binary,
-                        &wisker_arg));
+                    if builtin_ops::is_builtin(&wisker_binary) {
+                        // builtins are parsed natively, rather than running an external binary
+                        file_ops::log_msg(&main_args_c.out_log, format!("[ ] Running: {}", &wisker_binary));
+                        match builtin_ops::run_builtin(&wisker, &builtin_paths, &main_args_c) {
+                            Ok(msg) => file_ops::log_msg(&main_args_c.out_log, format!("[+] Done {} with builtin: {}. {}", 
+                                &wisker.name,
+                                &wisker_binary,
+                                msg)),
+                            Err(e) => {
+                                err_msg = format!("[!] The builtin `{}` failed: {:#}", &wisker_binary, e);
+                                file_ops::log_msg(&main_args_c.out_log, err_msg.clone());
+                            }
+                        }
+                    } else {
+                        let output = run_wisker(&wisker_binary, &wisker_arg, &main_args_c.out_log);
                         
-                    tx.send(output.stdout).unwrap();
-                    tx.send(output.stderr).unwrap();
+                        // run the binary with the args
+                        file_ops::log_msg(&main_args_c.out_log, format!("[+] Done {} with command: {} {}", 
+                            &wisker.name, 
+                            &wisker_binary,
+                            &wisker_arg));
+                            
+                        tx.send(output.stdout).unwrap();
+                        tx.send(output.stderr).unwrap();
+                    }
                             
                 } else {    
                     let folder_path = format!("{}/{}", &main_args_c.out_path, &wisker.outfolder);
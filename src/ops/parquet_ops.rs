@@ -0,0 +1,142 @@
+/*
+Parquet output of the builtin parsers, so the results can be queried with DuckDB or
+polars directly. Each CSV written by a builtin is converted to a Parquet file beside
+it, with every column as a string, and with `--out-format parquet` the CSVs and the
+timeline JSON lines are removed at the end of the run once the reporters have read them.
+*/
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use arrow_array::{ArrayRef, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use walkdir::WalkDir;
+
+use crate::configs::config::{MainArgs, Wiskers};
+use crate::parsers::common;
+use crate::timeline::builder::TIMELINE_FILE;
+use crate::timeline::parquet::PARQUET_FOLDER;
+use super::file_ops;
+
+/// The number of rows written to each row group
+pub const BATCH_ROWS: usize = 65_536;
+
+/// make a Parquet writer for the schema, with snappy compression
+pub fn writer(out_file: &Path, schema: SchemaRef) -> Result<ArrowWriter<File>> {
+    if let Some(parent) = out_file.parent() {
+        file_ops::make_folders(parent);
+    }
+    let file = File::create(out_file).with_context(|| format!("Unable to create {}", out_file.display()))?;
+    let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    Ok(ArrowWriter::try_new(file, schema, Some(props))?)
+}
+
+/// convert a CSV file to Parquet, with every column as a nullable string, returning the
+/// number of rows. Empty values are written as null
+pub fn csv_to_parquet(csv_file: &Path, parquet_file: &Path) -> Result<usize> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(csv_file)
+        .with_context(|| format!("Unable to open {}", csv_file.display()))?;
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim_start_matches('\u{feff}').to_string()).collect();
+    let schema: SchemaRef = Arc::new(Schema::new(
+        unique_names(&headers).into_iter().map(|h| Field::new(h, DataType::Utf8, true)).collect::<Vec<_>>()
+    ));
+    let mut writer = writer(parquet_file, schema.clone())?;
+    let mut columns: Vec<Vec<Option<String>>> = vec![Vec::new(); headers.len()];
+    let mut rows = 0;
+    for record in reader.records() {
+        let record = record?;
+        for (i, column) in columns.iter_mut().enumerate() {
+            column.push(record.get(i).filter(|v| !v.is_empty()).map(String::from));
+        }
+        rows += 1;
+        if rows % BATCH_ROWS == 0 {
+            write_strings(&mut writer, &schema, &mut columns)?;
+        }
+    }
+    write_strings(&mut writer, &schema, &mut columns)?;
+    writer.close()?;
+    Ok(rows)
+}
+
+/// write the buffered string columns as a batch, leaving the columns empty
+fn write_strings(writer: &mut ArrowWriter<File>, schema: &SchemaRef, columns: &mut [Vec<Option<String>>]) -> Result<()> {
+    if columns.first().is_none_or(|c| c.is_empty()) {
+        return Ok(());
+    }
+    let arrays: Vec<ArrayRef> = columns.iter_mut()
+        .map(|c| Arc::new(StringArray::from(std::mem::take(c))) as ArrayRef)
+        .collect();
+    writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+    Ok(())
+}
+
+/// make the column names unique, as Parquet needs, by adding a number to repeated names
+fn unique_names(headers: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (i, header) in headers.iter().enumerate() {
+        let mut name = match header.is_empty() {
+            true => format!("column_{}", i + 1),
+            false => header.clone(),
+        };
+        let mut n = 1;
+        while names.contains(&name) {
+            n += 1;
+            name = format!("{header}_{n}");
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// convert the CSVs a builtin wrote, found by the wisker's outfile in its outfolder,
+/// returning the Parquet files written
+pub fn convert_builtin_outputs(wisker: &Wiskers, main_args: &MainArgs) -> Result<Vec<PathBuf>> {
+    let outfolder = Path::new(&main_args.out_path).join(&wisker.outfolder);
+    let pattern = outfolder.join(&wisker.outfile).to_string_lossy().to_string();
+    let mut converted = Vec::new();
+    for csv_file in glob::glob(&pattern)?.flatten() {
+        if !csv_file.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+            continue;
+        }
+        let parquet_file = csv_file.with_extension("parquet");
+        csv_to_parquet(&csv_file, &parquet_file)?;
+        converted.push(parquet_file);
+    }
+    Ok(converted)
+}
+
+/// remove the CSVs that were converted to Parquet, which have a Parquet file of the same
+/// name beside them, returning how many were removed
+pub fn remove_converted(out_path: &Path) -> Result<usize> {
+    let mut removed = 0;
+    for entry in WalkDir::new(out_path).into_iter().flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|e| e != "parquet") {
+            continue;
+        }
+        let csv_file = path.with_extension("csv");
+        if csv_file.is_file() {
+            std::fs::remove_file(&csv_file).with_context(|| format!("Unable to remove {}", csv_file.display()))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// remove the timeline JSON lines of the wiskess output once exported to Parquet, after
+/// the reporters such as sigma have read them, returning if it was removed
+pub fn remove_timeline(out_path: &Path) -> Result<bool> {
+    let Some(timeline_dir) = common::join_case_insensitive(out_path, "Timeline") else { return Ok(false) };
+    let timeline_file = timeline_dir.join(TIMELINE_FILE);
+    if !timeline_file.is_file() || !timeline_dir.join(PARQUET_FOLDER).is_dir() {
+        return Ok(false);
+    }
+    std::fs::remove_file(&timeline_file).with_context(|| format!("Unable to remove {}", timeline_file.display()))?;
+    Ok(true)
+}
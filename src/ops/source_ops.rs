@@ -0,0 +1,550 @@
+/*
+Detection of the kind of data source, so it is processed with the configs of its evidence
+from the folder holding it. The kinds are a mounted volume of Windows, Linux or macOS,
+the output of a triage tool, being KAPE targets, a Velociraptor, CyLR or UAC collection,
+whether extracted or still in its archive, a disk image or a folder of artefacts. Each is
+recognised by the layout its tool writes, or the signature of the image, and the evidence
+found is kept so the choice can be logged.
+*/
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::linux::common::{self as linux, UAC_ROOT};
+use crate::macos::common as macos;
+use crate::parsers::common::{le_u16, le_u32, le_u64};
+
+/// The folder of the files uploaded to a Velociraptor collection
+pub const VELOCIRAPTOR_UPLOADS: &str = "uploads";
+/// The extensions of the disk images that can be mounted
+pub const IMAGE_EXTENSIONS: [&str; 11] = ["vmdk", "vhdx", "vhd", "e01", "ex01", "vdi", "qcow2", "raw", "dd", "img", "001"];
+/// The entries of a tarball read to find the `[root]` of UAC, as it may be after the
+/// live response
+const TAR_ENTRIES: usize = 10_000;
+/// The largest GNU long name or pax header read for the name of the next entry, with
+/// those larger skipped
+const TAR_LONG_NAME_MAX: u64 = 8 * 1024;
+/// The size of the end of a zip searched for its end of central directory, being the
+/// record and the longest comment
+const ZIP_TAIL: u64 = 22 + 65_535;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+const SEVEN_ZIP_MAGIC: [u8; 6] = [0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c];
+
+/// The name of a type of artefact and the check of a lowercase file name being one
+type ArtefactType = (&'static str, fn(&str) -> bool);
+
+/// The kinds of data source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataSourceKind {
+    WindowsVolume,
+    KapeTarget,
+    Velociraptor,
+    Cylr,
+    Uac,
+    LinuxRoot,
+    MacosRoot,
+    DiskImage,
+    Artefacts,
+    Archive,
+    Unknown,
+}
+
+impl fmt::Display for DataSourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DataSourceKind::WindowsVolume => "Windows volume",
+            DataSourceKind::KapeTarget => "KAPE target output",
+            DataSourceKind::Velociraptor => "Velociraptor collection",
+            DataSourceKind::Cylr => "CyLR collection",
+            DataSourceKind::Uac => "UAC collection",
+            DataSourceKind::LinuxRoot => "Linux root",
+            DataSourceKind::MacosRoot => "macOS volume",
+            DataSourceKind::DiskImage => "Disk image",
+            DataSourceKind::Artefacts => "Folder of artefacts",
+            DataSourceKind::Archive => "Archive",
+            DataSourceKind::Unknown => "Unknown data source",
+        })
+    }
+}
+
+/// The kind of a data source and where its evidence is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub kind: DataSourceKind,
+    /// the folder to process, such as the OS drive of a collection, or the file of an
+    /// archive or image
+    pub root: PathBuf,
+    /// the profile of `config/profiles` for the evidence, None for the default configs
+    pub profile: Option<&'static str>,
+    /// the collection is still in its archive, so needs extracting
+    pub archived: bool,
+    /// what was found that recognised the kind
+    pub evidence: String,
+}
+
+impl Detection {
+    fn new(kind: DataSourceKind, root: &Path, evidence: String) -> Detection {
+        Detection { kind, root: root.to_path_buf(), profile: None, archived: false, evidence }
+    }
+
+    fn with_profile(mut self, profile: &'static str) -> Detection {
+        self.profile = Some(profile);
+        self
+    }
+
+    fn archived(mut self) -> Detection {
+        self.archived = true;
+        self
+    }
+}
+
+/// detect the kind of the data source, being a folder or the file of an image or archive
+pub fn detect(data_source: &Path) -> Detection {
+    if data_source.is_file() {
+        detect_file(data_source)
+    } else if data_source.is_dir() {
+        detect_folder(data_source)
+    } else {
+        Detection::new(DataSourceKind::Unknown, data_source, "nothing, as it wasn't found".to_string())
+    }
+}
+
+/// detect the kind of a folder, checking the layouts of a volume before those of the
+/// collections that hold one
+fn detect_folder(folder: &Path) -> Detection {
+    if has_windows_child(folder) {
+        let found: Vec<String> = ["$MFT", "Users", "pagefile.sys"].into_iter()
+            .filter_map(|name| child_named(folder, name))
+            .collect();
+        return Detection::new(DataSourceKind::WindowsVolume, folder, join_and(&[vec!["Windows".to_string()], found].concat()));
+    }
+    let uac_root = folder.join(UAC_ROOT);
+    if uac_root.is_dir() {
+        let uac_log = match folder.join("uac.log").is_file() {
+            true => " and uac.log",
+            false => "",
+        };
+        return match volume_root(&uac_root) {
+            Some((profile, evidence)) => Detection::new(DataSourceKind::Uac, &uac_root, format!("{evidence} in {UAC_ROOT}{uac_log}"))
+                .with_profile(profile),
+            None => Detection::new(DataSourceKind::Uac, &uac_root, format!("{UAC_ROOT}{uac_log}")),
+        };
+    }
+    if let Some((profile, evidence)) = volume_root(folder) {
+        let kind = match profile {
+            "macos" => DataSourceKind::MacosRoot,
+            _ => DataSourceKind::LinuxRoot,
+        };
+        return Detection::new(kind, folder, evidence.to_string()).with_profile(profile);
+    }
+    let uploads = folder.join(VELOCIRAPTOR_UPLOADS);
+    if is_velociraptor(folder, &uploads) {
+        return velociraptor_root(&uploads);
+    }
+    if let Some(copy_log) = files_of(folder).into_iter().find(|name| name.ends_with("_CopyLog.csv")) {
+        let (root, evidence) = match drive_folder(folder) {
+            Some(drive) => (drive.clone(), format!("{copy_log}, with Windows in {}", file_name(&drive))),
+            None => (folder.to_path_buf(), copy_log),
+        };
+        return Detection::new(DataSourceKind::KapeTarget, &root, evidence);
+    }
+    if let Some(drive) = drive_folder(folder) {
+        return Detection::new(DataSourceKind::Cylr, &drive, format!("the drive folder {} having Windows", file_name(&drive)));
+    }
+    if let Some(volume) = subfolders(folder).into_iter().find(|path| has_windows_child(path)) {
+        return Detection::new(DataSourceKind::WindowsVolume, &volume, format!("Windows in {}", file_name(&volume)));
+    }
+    let artefacts = artefact_counts(folder);
+    if !artefacts.is_empty() {
+        return Detection::new(DataSourceKind::Artefacts, folder, join_and(&artefacts)).with_profile("artefacts");
+    }
+    Detection::new(DataSourceKind::Unknown, folder, "none of the layouts known".to_string())
+}
+
+/// detect the kind of a file, being a disk image, or an archive of a collection
+fn detect_file(path: &Path) -> Detection {
+    let mut header = vec![0u8; 1024];
+    let read = File::open(path).and_then(|mut f| f.read(&mut header)).unwrap_or(0);
+    header.truncate(read);
+    if let Some(evidence) = image_signature(path, &header) {
+        return Detection::new(DataSourceKind::DiskImage, path, evidence);
+    }
+    let extension = extension(path);
+    if header.starts_with(&ZIP_MAGIC) || extension == "zip" {
+        let names = zip_names(path).unwrap_or_default();
+        return collection_in(path, &names, "zip");
+    }
+    if header.starts_with(&GZIP_MAGIC) || header.get(257..262) == Some(b"ustar") {
+        let name = file_name(path);
+        if name.starts_with("uac-") && (name.ends_with(".tar.gz") || name.ends_with(".tgz") || name.ends_with(".tar")) {
+            return Detection::new(DataSourceKind::Uac, path, format!("the name {name} of a UAC archive")).archived();
+        }
+        let names = tar_names(path, TAR_ENTRIES).unwrap_or_default();
+        return collection_in(path, &names, "tarball");
+    }
+    if header.starts_with(&SEVEN_ZIP_MAGIC) || extension == "7z" {
+        return Detection::new(DataSourceKind::Archive, path, "a 7z archive, with its contents found once extracted".to_string()).archived();
+    }
+    match IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        true => Detection::new(DataSourceKind::DiskImage, path, format!("the .{extension} extension")),
+        false => Detection::new(DataSourceKind::Unknown, path, "none of the signatures known".to_string()),
+    }
+}
+
+/// the kind of collection in an archive from the names of its files
+fn collection_in(path: &Path, names: &[String], archive: &str) -> Detection {
+    let detection = |kind, evidence: &str| Detection::new(kind, path, format!("{evidence} in the {archive}")).archived();
+    let first_two = |name: &str| {
+        let mut parts = name.split('/');
+        (parts.next().unwrap_or("").to_string(), parts.next().unwrap_or("").to_string())
+    };
+    if names.iter().any(|n| n.starts_with(&format!("{UAC_ROOT}/")) || n == "uac.log") {
+        return detection(DataSourceKind::Uac, UAC_ROOT);
+    }
+    if names.iter().any(|n| n.starts_with(&format!("{VELOCIRAPTOR_UPLOADS}/"))) {
+        return detection(DataSourceKind::Velociraptor, VELOCIRAPTOR_UPLOADS);
+    }
+    if let Some(copy_log) = names.iter().find(|n| n.ends_with("_CopyLog.csv")) {
+        return detection(DataSourceKind::KapeTarget, copy_log);
+    }
+    let drive = names.iter().map(|n| first_two(n)).find(|(drive, child)| is_drive_name(drive) && child.eq_ignore_ascii_case("windows"));
+    if let Some((drive, child)) = drive {
+        return detection(DataSourceKind::Cylr, &format!("{drive}/{child}"));
+    }
+    Detection::new(DataSourceKind::Archive, path, format!("a {archive} of {} files, with its contents found once extracted", names.len())).archived()
+}
+
+/// the evidence of the signature of a disk image, from the header of the file
+fn image_signature(path: &Path, header: &[u8]) -> Option<String> {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+    let signature = if at(0, b"EVF\x09\x0d\x0a\xff\x00") {
+        "the EWF signature of an E01"
+    } else if at(0, b"EVF2\x0d\x0a\x81\x00") {
+        "the EWF2 signature of an Ex01"
+    } else if at(0, b"vhdxfile") {
+        "the signature of a VHDX"
+    } else if at(0, b"conectix") || vhd_footer(path) {
+        "the signature of a VHD"
+    } else if at(0, b"KDMV") || at(0, b"# Disk DescriptorFile") {
+        "the signature of a VMDK"
+    } else if le_u32(header, 0x40) == Some(0xbeda_107f) {
+        "the signature of a VDI"
+    } else if at(0, b"QFI\xfb") {
+        "the signature of a QCOW2"
+    } else if at(512, b"EFI PART") {
+        "a GPT partition table"
+    } else if at(3, b"NTFS    ") {
+        "an NTFS boot sector"
+    } else if has_partitions(header) {
+        "an MBR partition table"
+    } else {
+        return None;
+    };
+    Some(signature.to_string())
+}
+
+/// check if the file ends with the footer of a fixed VHD
+fn vhd_footer(path: &Path) -> bool {
+    let mut footer = [0u8; 8];
+    File::open(path)
+        .and_then(|mut f| {
+            f.seek(SeekFrom::End(-512))?;
+            f.read_exact(&mut footer)
+        })
+        .is_ok_and(|_| &footer == b"conectix")
+}
+
+/// check if the first sector is an MBR with a partition, as a boot signature alone is
+/// found in other files
+fn has_partitions(header: &[u8]) -> bool {
+    if le_u16(header, 510) != Some(0xaa55) {
+        return false;
+    }
+    (0..4).map(|i| 446 + i * 16).any(|entry| {
+        matches!(header.get(entry), Some(0x00 | 0x80)) && header.get(entry + 4).is_some_and(|kind| *kind != 0)
+    })
+}
+
+/// the names of the files in a zip, read from its central directory, including a zip64
+pub fn zip_names(path: &Path) -> Option<Vec<String>> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let tail_start = len.saturating_sub(ZIP_TAIL);
+    file.seek(SeekFrom::Start(tail_start)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    let eocd = (0..tail.len().saturating_sub(21)).rev().find(|i| le_u32(&tail, *i) == Some(0x0605_4b50))?;
+    let mut entries = le_u16(&tail, eocd + 10)? as u64;
+    let mut cd_size = le_u32(&tail, eocd + 12)? as u64;
+    let mut cd_offset = le_u32(&tail, eocd + 16)? as u64;
+    if cd_offset == 0xffff_ffff && eocd >= 20 && le_u32(&tail, eocd - 20) == Some(0x0706_4b50) {
+        let zip64_offset = le_u64(&tail, eocd - 12)?;
+        let mut zip64 = [0u8; 56];
+        file.seek(SeekFrom::Start(zip64_offset)).ok()?;
+        file.read_exact(&mut zip64).ok()?;
+        if le_u32(&zip64, 0) != Some(0x0606_4b50) {
+            return None;
+        }
+        entries = le_u64(&zip64, 32)?;
+        cd_size = le_u64(&zip64, 40)?;
+        cd_offset = le_u64(&zip64, 48)?;
+    }
+    if cd_offset.checked_add(cd_size)? > len {
+        return None;
+    }
+    let mut cd = vec![0u8; cd_size as usize];
+    file.seek(SeekFrom::Start(cd_offset)).ok()?;
+    file.read_exact(&mut cd).ok()?;
+    let mut names = Vec::new();
+    let mut offset = 0;
+    while (names.len() as u64) < entries && le_u32(&cd, offset) == Some(0x0201_4b50) {
+        let name_len = le_u16(&cd, offset + 28)? as usize;
+        let extra_len = le_u16(&cd, offset + 30)? as usize;
+        let comment_len = le_u16(&cd, offset + 32)? as usize;
+        let name = cd.get(offset + 46..offset + 46 + name_len)?;
+        names.push(String::from_utf8_lossy(name).replace('\\', "/"));
+        offset += 46 + name_len + extra_len + comment_len;
+    }
+    Some(names)
+}
+
+/// the names of the first entries of a tar, plain or gzipped, as far as the limit.
+/// The long names of GNU and pax entries are read, up to `TAR_LONG_NAME_MAX` bytes
+pub fn tar_names(path: &Path, limit: usize) -> Option<Vec<String>> {
+    let mut reader = linux::open_log(path).ok()?;
+    let mut names = Vec::new();
+    let mut long_name: Option<String> = None;
+    let mut header = [0u8; 512];
+    while names.len() < limit && reader.read_exact(&mut header).is_ok() {
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let size = tar_size(&header[124..136])?;
+        let data_len = size.div_ceil(512) * 512;
+        match header[156] {
+            b'L' | b'x' if size > TAR_LONG_NAME_MAX => skip(&mut reader, data_len)?,
+            b'L' | b'x' => {
+                let mut data = vec![0u8; size as usize];
+                reader.read_exact(&mut data).ok()?;
+                skip(&mut reader, data_len - size)?;
+                long_name = match header[156] {
+                    b'L' => Some(text(&data)),
+                    _ => String::from_utf8_lossy(&data).lines()
+                        .find_map(|record| record.split_once(" path=").map(|(_, p)| p.to_string()))
+                        .or(long_name),
+                };
+            }
+            _ => {
+                let name = long_name.take().unwrap_or_else(|| match &header[257..262] == b"ustar" && header[345] != 0 {
+                    true => format!("{}/{}", text(&header[345..500]), text(&header[..100])),
+                    false => text(&header[..100]),
+                });
+                names.push(name.trim_start_matches("./").to_string());
+                skip(&mut reader, data_len)?;
+            }
+        }
+    }
+    Some(names)
+}
+
+/// the size of a tar entry, in octal or the base-256 of large files
+fn tar_size(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 != 0 {
+        return Some(field[1..].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64));
+    }
+    let octal = text(field);
+    match octal.trim() {
+        "" => Some(0),
+        octal => u64::from_str_radix(octal, 8).ok(),
+    }
+}
+
+fn skip(reader: &mut Box<dyn BufRead>, len: u64) -> Option<()> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink()).ok()?;
+    (skipped == len).then_some(())
+}
+
+/// the text of a field up to its first null
+fn text(data: &[u8]) -> String {
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+/// check if the file is a tarball, gzipped or not
+pub fn is_tarball(path: &Path) -> bool {
+    let mut header = [0u8; 262];
+    let read = File::open(path).and_then(|mut f| f.read(&mut header)).unwrap_or(0);
+    header[..read].starts_with(&GZIP_MAGIC) || header.get(257..262) == Some(b"ustar")
+}
+
+/// the profile and evidence of the root of a macOS or Linux volume. A macOS volume is
+/// checked first, as its etc and var links would pass as a Linux root
+fn volume_root(path: &Path) -> Option<(&'static str, &'static str)> {
+    if macos::is_macos_root(path) {
+        Some(("macos", "private/var and Library"))
+    } else if linux::is_linux_root(path) {
+        Some(("linux", "etc and var/log"))
+    } else {
+        None
+    }
+}
+
+/// check if the folder is a Velociraptor collection, with uploads of the auto or ntfs
+/// accessors, or the context Velociraptor writes beside them
+fn is_velociraptor(folder: &Path, uploads: &Path) -> bool {
+    uploads.is_dir() && (
+        ["auto", "ntfs"].iter().any(|accessor| uploads.join(accessor).is_dir())
+        || ["collection_context.json", "client_info.json"].iter().any(|context| folder.join(context).is_file())
+    )
+}
+
+/// the OS drive of a Velociraptor collection, being the files already moved out of the
+/// accessors by whipped, or the first drive having Windows
+fn velociraptor_root(uploads: &Path) -> Detection {
+    let files = uploads.join("files");
+    if files.is_dir() {
+        return Detection::new(DataSourceKind::Velociraptor, &files, format!("{VELOCIRAPTOR_UPLOADS} with its files moved"));
+    }
+    let drives = find_os_drive_letters(uploads);
+    let drive = ["auto", "ntfs"].iter()
+        .flat_map(|accessor| subfolders(&uploads.join(accessor)))
+        .find(|path| extract_drive_letter(&file_name(path)).is_some() && has_windows_child(path));
+    match drive {
+        Some(drive) => {
+            let letters: Vec<String> = drives.iter().map(|d| format!("{d}:")).collect();
+            Detection::new(DataSourceKind::Velociraptor, &drive, format!("{VELOCIRAPTOR_UPLOADS}, with Windows on {}", join_and(&letters)))
+        }
+        None => Detection::new(DataSourceKind::Velociraptor, uploads, format!("{VELOCIRAPTOR_UPLOADS}, without a drive having Windows")),
+    }
+}
+
+/// the first folder named as a drive letter, such as `C` of CyLR or KAPE, having Windows
+fn drive_folder(folder: &Path) -> Option<PathBuf> {
+    subfolders(folder).into_iter().find(|path| is_drive_name(&file_name(path)) && has_windows_child(path))
+}
+
+fn is_drive_name(name: &str) -> bool {
+    name.len() == 1 && name.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// the counts of the artefacts in the folder, found by their names. Each is the count and
+/// the type, such as `3 event logs`
+fn artefact_counts(folder: &Path) -> Vec<String> {
+    const HIVES: [&str; 8] = ["system", "software", "sam", "security", "default", "ntuser.dat", "usrclass.dat", "amcache.hve"];
+    let types: [ArtefactType; 7] = [
+        ("event logs", |name| name.ends_with(".evtx")),
+        ("prefetch files", |name| name.ends_with(".pf")),
+        ("registry hives", |name| HIVES.contains(&name)),
+        ("MFTs", |name| name == "$mft"),
+        ("USN journals", |name| name == "$j" || name.starts_with("$usnjrnl")),
+        ("SRUM databases", |name| name == "srudb.dat"),
+        ("LNK files and jump lists", |name| name.ends_with(".lnk") || name.ends_with("destinations-ms")),
+    ];
+    let names: Vec<String> = files_of(folder).iter().map(|n| n.to_lowercase()).collect();
+    types.iter()
+        .map(|(artefact, is_type)| (names.iter().filter(|n| is_type(n)).count(), artefact))
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, artefact)| format!("{count} {artefact}"))
+        .collect()
+}
+
+/// the names of the files in the folder
+fn files_of(folder: &Path) -> Vec<String> {
+    fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_file())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+/// the folders in the folder, sorted so the detection is the same on each OS
+fn subfolders(folder: &Path) -> Vec<PathBuf> {
+    let mut folders: Vec<PathBuf> = fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    folders.sort();
+    folders
+}
+
+/// the name of the child of the folder, matched case-insensitively
+fn child_named(folder: &Path, name: &str) -> Option<String> {
+    fs::read_dir(folder).ok()?
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .find(|child| child.eq_ignore_ascii_case(name))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+/// join the items as a list, i.e. `a, b and c`
+fn join_and(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+/// Extracts the drive letter (uppercased) from a Velociraptor percent-encoded
+/// path component, matching both forms Velociraptor produces: `<LETTER>%3A`
+/// (e.g. `F%3A` for `F:`) and `%5C%5C.%5C<LETTER>%3A` (e.g. `%5C%5C.%5CF%3A`
+/// for `\\.\F:`). Case-insensitive. Returns `None` if it matches neither.
+pub fn extract_drive_letter(component_str: &str) -> Option<char> {
+    let re = regex::Regex::new(r"(?i)^(?:%5C%5C\.%5C)?([A-Za-z])%3A$").unwrap();
+    re.captures(component_str)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().chars().next())
+        .map(|c| c.to_ascii_uppercase())
+}
+
+/// Returns true if `dir` has an immediate child directory named `Windows`
+/// (case-insensitive), so this works the same on Windows and Linux.
+pub fn has_windows_child(dir: &Path) -> bool {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .any(|entry| {
+            entry.file_type().is_dir()
+                && entry.file_name().to_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case("windows"))
+        })
+}
+
+/// Walks a Velociraptor `uploads` folder's `auto`/`ntfs` subtrees at depth 2
+/// (i.e. `uploads/<auto|ntfs>/<drive-component>`) and returns the distinct,
+/// sorted drive letters that have an immediate `Windows` child - i.e. are
+/// confirmed to be the OS drive. Either encoded form of the same letter, in
+/// either subtree, confirms that letter.
+pub fn find_os_drive_letters(uploads_dir: &Path) -> Vec<char> {
+    let mut confirmed: Vec<char> = WalkDir::new(uploads_dir)
+        .min_depth(2)
+        .max_depth(2)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_dir())
+        .filter_map(|entry| {
+            let comp_str = entry.file_name().to_str()?;
+            let letter = extract_drive_letter(comp_str)?;
+            has_windows_child(entry.path()).then_some(letter)
+        })
+        .collect();
+    confirmed.sort_unstable();
+    confirmed.dedup();
+    confirmed
+}
@@ -1,6 +1,6 @@
 use indicatif::MultiProgress;
 
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use chrono::Utc;
 
@@ -8,9 +8,9 @@ use std::fs::OpenOptions;
 
 use crate::{art::paths, configs::config, init::setup};
 
-use super::{exe_ops, valid_ops, file_ops};
+use super::{exe_ops, valid_ops, file_ops, parquet_ops};
 
-pub fn start_wiskess(args: config::MainArgs, config: &PathBuf, artefacts_config: &PathBuf, data_source: &String) {
+pub fn start_wiskess(args: config::MainArgs, config: &Path, artefacts_config: &Path, data_source: &String) {
     let (date_time_fmt, wiskess_start, main_args) = init_wiskess(args);
     
     let (config, data_paths) = config_wiskess(
@@ -37,22 +37,46 @@ pub fn start_wiskess(args: config::MainArgs, config: &PathBuf, artefacts_config:
     // Validate wiskess has processed all input files into output files
     valid_ops::valid_process(&config.wiskers, &main_args, &data_paths, &data_source, &main_args.out_log);
 
+    // Remove the CSVs of the builtins and the timeline once converted, when only Parquet output is wanted
+    if main_args.out_format == config::OutFormat::Parquet {
+        match parquet_ops::remove_converted(Path::new(&main_args.out_path)) {
+            Ok(removed) => file_ops::log_msg(&main_args.out_log, format!("[+] Removed {} CSVs converted to Parquet", removed)),
+            Err(e) => file_ops::log_msg(&main_args.out_log, format!("[!] Unable to remove the CSVs converted to Parquet: {:#}", e)),
+        }
+        if !main_args.keep_timeline {
+            remove_timeline(Path::new(&main_args.out_path), &main_args.out_log);
+        }
+    }
+
     // Set end time
     end_wiskess(wiskess_start, main_args, &date_time_fmt);
 }
 
-pub(crate) fn config_wiskess(config: &PathBuf, artefacts_config: &PathBuf, data_source: &String, silent: bool, main_args: &config::MainArgs) -> (config::Config, std::collections::HashMap<String, String>) {
+/// remove the timeline JSON lines exported to Parquet, logging the outcome
+pub(crate) fn remove_timeline(out_path: &Path, out_log: &Path) {
+    match parquet_ops::remove_timeline(out_path) {
+        Ok(true) => file_ops::log_msg(out_log, "[+] Removed the timeline JSON lines exported to Parquet".to_string()),
+        Ok(false) => {},
+        Err(e) => file_ops::log_msg(out_log, format!("[!] Unable to remove the timeline exported to Parquet: {:#}", e)),
+    }
+}
+
+pub(crate) fn config_wiskess(config: &Path, artefacts_config: &Path, data_source: &String, silent: bool, main_args: &config::MainArgs) -> (config::Config, std::collections::HashMap<String, String>) {
+    // Use the profile and root of the kind of data source detected
+    let (config, artefacts_config, data_source) = paths::select_profile(config, artefacts_config, data_source, main_args);
+    let data_source = &data_source;
+
     // Read the config
     let f: std::fs::File = OpenOptions::new()
         .read(true)
-        .open(config)
+        .open(&config)
         .expect("Unable to open config file.");
     let config: config::Config = serde_yaml::from_reader(f).expect("Could not read values.");
 
     // Read the artefacts config
     let f: std::fs::File = OpenOptions::new()
         .read(true)
-        .open(artefacts_config)
+        .open(&artefacts_config)
         .expect("Unable to open artefacts config file.");
     let config_artefacts: config::ConfigArt = serde_yaml::from_reader(f).expect("Could not read values of artefacts config.");
                 
@@ -101,6 +125,7 @@ pub(crate) fn init_wiskess(args: config::MainArgs) -> (String, chrono::prelude::
         
     // Write start time to log
     file_ops::log_msg(&out_log, format!("Starting wiskess at: {}", wiskess_start_str));
+    file_ops::log_msg(&out_log, format!("Output format: {}", args.out_format));
 
     // Confirm date is valid
     let start_date = file_ops::check_date(args.start_date, &"start date".to_string());
@@ -114,6 +139,9 @@ pub(crate) fn init_wiskess(args: config::MainArgs) -> (String, chrono::prelude::
         ioc_file: args.ioc_file,
         silent: args.silent,
         collect: args.collect,
+        out_format: args.out_format,
+        yara_rules: args.yara_rules,
+        keep_timeline: args.keep_timeline,
         out_log,
         multi_pb: MultiProgress::new()
     };
@@ -130,9 +158,10 @@ pub(crate) fn end_wiskess(wiskess_start: chrono::prelude::DateTime<Utc>, main_ar
     file_ops::log_msg(
         &main_args.out_log, 
         format!(
-            "Wiskess finished at: {}, which took: {} [H:M:S]", 
+            "Wiskess finished at: {}, which took: {} [H:M:S], with output format: {}", 
             wiskess_stop.format(date_time_fmt).to_string(), 
-            duration
+            duration,
+            main_args.out_format
         )
     );
 }
@@ -0,0 +1,13 @@
+pub mod browsers;
+pub mod common;
+pub mod ese;
+pub mod hashes;
+pub mod hive;
+pub mod hostinfo;
+pub mod memory;
+pub mod persistence;
+pub mod recycle;
+pub mod sqlite;
+pub mod srum;
+pub mod sum;
+pub mod xpress;
@@ -0,0 +1,508 @@
+/*
+Browser history parser for Chromium based browsers (Chrome, Edge, Brave, Opera, Vivaldi)
+and Firefox. Walks each user profile in the Users folder, reading the History, Web Data
+and Login Data databases of Chromium, and places.sqlite, downloads.sqlite and
+formhistory.sqlite of Firefox. Downloads are enriched with the Zone.Identifier of the
+downloaded file, which holds the host URL that it was downloaded from.
+*/
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use csv::Writer;
+
+use super::common;
+use super::sqlite::{self, SqlRow, SqliteCopy};
+use crate::art::paths::find_case_insensitive;
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+
+/// Chromium browsers and the path of their user data folder, relative to the user's profile
+const CHROMIUM_PATHS: [(&str, &str); 7] = [
+    ("Chrome", r"AppData\Local\Google\Chrome\User Data"),
+    ("Edge", r"AppData\Local\Microsoft\Edge\User Data"),
+    ("Brave", r"AppData\Local\BraveSoftware\Brave-Browser\User Data"),
+    ("Chromium", r"AppData\Local\Chromium\User Data"),
+    ("Vivaldi", r"AppData\Local\Vivaldi\User Data"),
+    ("Opera", r"AppData\Roaming\Opera Software\Opera Stable"),
+    ("Chrome", r"Local Settings\Application Data\Google\Chrome\User Data"),
+];
+/// Firefox profiles folders, relative to the user's profile
+const FIREFOX_PATHS: [&str; 2] = [
+    r"AppData\Roaming\Mozilla\Firefox\Profiles",
+    r"Application Data\Mozilla\Firefox\Profiles",
+];
+
+const VISITS_HEADER: [&str; 10] = [
+    "VisitTime", "User", "Browser", "Profile", "Url", "Title", "VisitCount", "VisitType", "FromUrl", "SourceFile",
+];
+const DOWNLOADS_HEADER: [&str; 18] = [
+    "StartTime", "EndTime", "User", "Browser", "Profile", "Url", "UrlChain", "Referrer", "TabUrl", "TargetPath",
+    "TotalBytes", "State", "DangerType", "MimeType", "ZoneId", "ZoneHostUrl", "ZoneReferrerUrl", "SourceFile",
+];
+const SEARCHES_HEADER: [&str; 7] = ["Time", "User", "Browser", "Profile", "Term", "Url", "SourceFile"];
+const LOGINS_HEADER: [&str; 10] = [
+    "Created", "LastUsed", "User", "Browser", "Profile", "OriginUrl", "ActionUrl", "Username", "TimesUsed", "SourceFile",
+];
+const AUTOFILL_HEADER: [&str; 9] = [
+    "Created", "LastUsed", "User", "Browser", "Profile", "Field", "Value", "Count", "SourceFile",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowserKind {
+    Chromium,
+    Firefox,
+}
+
+/// A browser profile found in a user's profile folder
+#[derive(Debug, Clone)]
+pub struct BrowserProfile {
+    pub user: String,
+    pub browser: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: BrowserKind,
+}
+
+/// The Zone.Identifier alternate data stream of a downloaded file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ZoneIdentifier {
+    pub zone_id: String,
+    pub host_url: String,
+    pub referrer_url: String,
+}
+
+/// The output CSV files in the Browsers folder, with the number of rows written to each
+struct BrowserWriters {
+    visits: Writer<File>,
+    downloads: Writer<File>,
+    searches: Writer<File>,
+    logins: Writer<File>,
+    autofill: Writer<File>,
+    counts: [usize; 5],
+}
+
+impl BrowserWriters {
+    fn new(outfolder: &Path) -> Result<Self> {
+        let writer = |name: &str, header: &[&str]| -> Result<Writer<File>> {
+            let mut w = common::csv_writer(&outfolder.join(name))?;
+            w.write_record(header)?;
+            Ok(w)
+        };
+        Ok(BrowserWriters {
+            visits: writer("browser_visits.csv", &VISITS_HEADER)?,
+            downloads: writer("browser_downloads.csv", &DOWNLOADS_HEADER)?,
+            searches: writer("browser_searches.csv", &SEARCHES_HEADER)?,
+            logins: writer("browser_logins.csv", &LOGINS_HEADER)?,
+            autofill: writer("browser_autofill.csv", &AUTOFILL_HEADER)?,
+            counts: [0; 5],
+        })
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        for w in [&mut self.visits, &mut self.downloads, &mut self.searches, &mut self.logins, &mut self.autofill] {
+            w.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// run the builtin browsers wisker, with the input as the Users folder
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let user_dir = Path::new(&args.input);
+    let root = user_dir.parent().unwrap_or(user_dir);
+    let profiles = find_profiles(user_dir);
+    let mut out = BrowserWriters::new(&args.outfolder)?;
+    for profile in &profiles {
+        let result = match profile.kind {
+            BrowserKind::Chromium => parse_chromium(profile, root, &mut out),
+            BrowserKind::Firefox => parse_firefox(profile, &mut out),
+        };
+        if let Err(e) = result {
+            file_ops::log_msg(&args.main_args.out_log, format!(
+                "[!] Unable to parse {} profile {}: {:#}", profile.browser, profile.path.display(), e
+            ));
+        }
+    }
+    out.flush()?;
+    let [visits, downloads, searches, logins, autofill] = out.counts;
+    Ok(format!(
+        "Browser records written from {} profiles - visits: {visits}, downloads: {downloads}, searches: {searches}, logins: {logins}, autofill: {autofill}",
+        profiles.len()
+    ))
+}
+
+/// find the Chromium and Firefox profiles in each user's profile folder
+pub fn find_profiles(user_dir: &Path) -> Vec<BrowserProfile> {
+    let mut profiles = Vec::new();
+    for user_path in sorted_dirs(user_dir) {
+        let user = dir_name(&user_path);
+        for (browser, relative) in CHROMIUM_PATHS {
+            let Some(data_dir) = common::join_case_insensitive(&user_path, relative) else { continue };
+            // Opera keeps its history in the user data folder, the others in a folder per profile
+            let candidates = std::iter::once(data_dir.clone()).chain(sorted_dirs(&data_dir));
+            for path in candidates.filter(|p| common::join_case_insensitive(p, "History").is_some()) {
+                profiles.push(BrowserProfile {
+                    user: user.clone(),
+                    browser: browser.to_string(),
+                    name: dir_name(&path),
+                    path,
+                    kind: BrowserKind::Chromium,
+                });
+            }
+        }
+        for relative in FIREFOX_PATHS {
+            let Some(profiles_dir) = common::join_case_insensitive(&user_path, relative) else { continue };
+            for path in sorted_dirs(&profiles_dir) {
+                if common::join_case_insensitive(&path, "places.sqlite").is_some() {
+                    profiles.push(BrowserProfile {
+                        user: user.clone(),
+                        browser: "Firefox".to_string(),
+                        name: dir_name(&path),
+                        path,
+                        kind: BrowserKind::Firefox,
+                    });
+                }
+            }
+        }
+    }
+    profiles
+}
+
+fn sorted_dirs(path: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// open a database in the profile folder, None if it doesn't exist
+fn open_db(profile: &BrowserProfile, name: &str) -> Result<Option<(SqliteCopy, String)>> {
+    let Some(path) = common::join_case_insensitive(&profile.path, name) else { return Ok(None) };
+    Ok(Some((SqliteCopy::open(&path)?, path.display().to_string())))
+}
+
+fn parse_chromium(profile: &BrowserProfile, root: &Path, out: &mut BrowserWriters) -> Result<()> {
+    let owner = [profile.user.clone(), profile.browser.clone(), profile.name.clone()];
+    if let Some((db, source)) = open_db(profile, "History")? {
+        let visits = db.query(
+            "SELECT v.visit_time, u.url, u.title, u.visit_count, v.transition, fu.url AS from_url
+             FROM visits v JOIN urls u ON v.url = u.id
+             LEFT JOIN visits fv ON v.from_visit = fv.id LEFT JOIN urls fu ON fv.url = fu.id
+             ORDER BY v.visit_time",
+        )?;
+        for row in &visits {
+            let transition = sqlite::int(row, "transition").unwrap_or(0);
+            write_row(&mut out.visits, &mut out.counts[0], &[
+                &webkit(row, "visit_time"), &owner[0], &owner[1], &owner[2],
+                &sqlite::text(row, "url"), &sqlite::text(row, "title"), &sqlite::text(row, "visit_count"),
+                chromium_transition(transition), &sqlite::text(row, "from_url"), &source,
+            ])?;
+        }
+
+        if db.has_table("downloads") {
+            let mut chains: HashMap<i64, Vec<String>> = HashMap::new();
+            if db.has_table("downloads_url_chains") {
+                for row in db.query("SELECT id, url FROM downloads_url_chains ORDER BY id, chain_index")? {
+                    if let Some(id) = sqlite::int(&row, "id") {
+                        chains.entry(id).or_default().push(sqlite::text(&row, "url"));
+                    }
+                }
+            }
+            for row in db.query("SELECT * FROM downloads ORDER BY start_time")? {
+                let chain = sqlite::int(&row, "id").and_then(|id| chains.get(&id)).cloned().unwrap_or_default();
+                let url = chain.last().cloned().unwrap_or_else(|| sqlite::text(&row, "url"));
+                let referrer = first_non_empty(&row, &["referrer", "tab_referrer_url"]);
+                let target = first_non_empty(&row, &["target_path", "current_path", "full_path"]);
+                let zone = zone_identifier(root, &target).unwrap_or_default();
+                let state = chromium_download_state(sqlite::int(&row, "state").unwrap_or(-1));
+                write_row(&mut out.downloads, &mut out.counts[1], &[
+                    &webkit(&row, "start_time"), &webkit(&row, "end_time"), &owner[0], &owner[1], &owner[2],
+                    &url, &chain.join(" -> "), &referrer, &sqlite::text(&row, "tab_url"), &target,
+                    &sqlite::text(&row, "total_bytes"), state, &sqlite::text(&row, "danger_type"),
+                    &sqlite::text(&row, "mime_type"), &zone.zone_id, &zone.host_url, &zone.referrer_url, &source,
+                ])?;
+            }
+        }
+
+        if db.has_table("keyword_search_terms") {
+            let searches = db.query(
+                "SELECT k.term, u.url, u.last_visit_time FROM keyword_search_terms k JOIN urls u ON k.url_id = u.id
+                 ORDER BY u.last_visit_time",
+            )?;
+            for row in &searches {
+                write_row(&mut out.searches, &mut out.counts[2], &[
+                    &webkit(row, "last_visit_time"), &owner[0], &owner[1], &owner[2],
+                    &sqlite::text(row, "term"), &sqlite::text(row, "url"), &source,
+                ])?;
+            }
+        }
+    }
+
+    // only the metadata of saved logins is written, not the encrypted passwords
+    if let Some((db, source)) = open_db(profile, "Login Data")? {
+        if db.has_table("logins") {
+            for row in db.query("SELECT * FROM logins ORDER BY date_created")? {
+                write_row(&mut out.logins, &mut out.counts[3], &[
+                    &webkit(&row, "date_created"), &webkit(&row, "date_last_used"), &owner[0], &owner[1], &owner[2],
+                    &sqlite::text(&row, "origin_url"), &sqlite::text(&row, "action_url"),
+                    &sqlite::text(&row, "username_value"), &sqlite::text(&row, "times_used"), &source,
+                ])?;
+            }
+        }
+    }
+
+    if let Some((db, source)) = open_db(profile, "Web Data")? {
+        if db.has_table("autofill") {
+            for row in db.query("SELECT * FROM autofill ORDER BY date_created")? {
+                write_row(&mut out.autofill, &mut out.counts[4], &[
+                    &unix(&row, "date_created"), &unix(&row, "date_last_used"), &owner[0], &owner[1], &owner[2],
+                    &sqlite::text(&row, "name"), &sqlite::text(&row, "value"), &sqlite::text(&row, "count"), &source,
+                ])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_firefox(profile: &BrowserProfile, out: &mut BrowserWriters) -> Result<()> {
+    let owner = [profile.user.clone(), profile.browser.clone(), profile.name.clone()];
+    if let Some((db, source)) = open_db(profile, "places.sqlite")? {
+        let visits = db.query(
+            "SELECT v.visit_date, p.url, p.title, p.visit_count, v.visit_type, fp.url AS from_url
+             FROM moz_historyvisits v JOIN moz_places p ON v.place_id = p.id
+             LEFT JOIN moz_historyvisits fv ON v.from_visit = fv.id LEFT JOIN moz_places fp ON fv.place_id = fp.id
+             ORDER BY v.visit_date",
+        )?;
+        // the referrer of a download is the page the download visit came from
+        let mut referrers: HashMap<String, String> = HashMap::new();
+        for row in &visits {
+            let visit_type = sqlite::int(row, "visit_type").unwrap_or(0);
+            if visit_type == 7 {
+                referrers.insert(sqlite::text(row, "url"), sqlite::text(row, "from_url"));
+            }
+            write_row(&mut out.visits, &mut out.counts[0], &[
+                &prtime(row, "visit_date"), &owner[0], &owner[1], &owner[2],
+                &sqlite::text(row, "url"), &sqlite::text(row, "title"), &sqlite::text(row, "visit_count"),
+                firefox_visit_type(visit_type), &sqlite::text(row, "from_url"), &source,
+            ])?;
+        }
+
+        if db.has_table("moz_annos") {
+            let annos = db.query(
+                "SELECT p.id AS place_id, p.url, n.name, a.content, a.dateAdded FROM moz_annos a
+                 JOIN moz_anno_attributes n ON a.anno_attribute_id = n.id JOIN moz_places p ON a.place_id = p.id
+                 WHERE n.name IN ('downloads/destinationFileURI', 'downloads/metaData')
+                 ORDER BY a.dateAdded",
+            )?;
+            let mut downloads: Vec<(String, String, String, serde_json::Value)> = Vec::new();
+            let mut index: HashMap<i64, usize> = HashMap::new();
+            for row in &annos {
+                let place = sqlite::int(row, "place_id").unwrap_or(0);
+                let i = *index.entry(place).or_insert_with(|| {
+                    downloads.push((sqlite::text(row, "url"), prtime(row, "dateAdded"), String::new(), serde_json::Value::Null));
+                    downloads.len() - 1
+                });
+                let content = sqlite::text(row, "content");
+                match sqlite::text(row, "name").as_str() {
+                    "downloads/destinationFileURI" => downloads[i].2 = file_uri_to_path(&content),
+                    _ => downloads[i].3 = serde_json::from_str(&content).unwrap_or_default(),
+                }
+            }
+            for (url, start, target, meta) in downloads {
+                let end = meta.get("endTime")
+                    .and_then(|t| t.as_i64())
+                    .map(|ms| common::fmt_dt(common::unix_micros_to_dt(ms * 1000)))
+                    .unwrap_or_default();
+                let size = meta.get("fileSize").map(|s| s.to_string()).unwrap_or_default();
+                let state = meta.get("state").and_then(|s| s.as_i64()).map(firefox_download_state).unwrap_or("");
+                let referrer = referrers.get(&url).cloned().unwrap_or_default();
+                write_row(&mut out.downloads, &mut out.counts[1], &[
+                    &start, &end, &owner[0], &owner[1], &owner[2], &url, &url,
+                    &referrer, "", &target, &size, state, "", "", "", "", "", &source,
+                ])?;
+            }
+        }
+    }
+
+    // downloads before Firefox 21 are in their own database
+    if let Some((db, source)) = open_db(profile, "downloads.sqlite")? {
+        if db.has_table("moz_downloads") {
+            for row in db.query("SELECT * FROM moz_downloads ORDER BY startTime")? {
+                let url = sqlite::text(&row, "source");
+                write_row(&mut out.downloads, &mut out.counts[1], &[
+                    &prtime(&row, "startTime"), &prtime(&row, "endTime"), &owner[0], &owner[1], &owner[2],
+                    &url, &url, &sqlite::text(&row, "referrer"), "",
+                    &file_uri_to_path(&sqlite::text(&row, "target")), &sqlite::text(&row, "maxBytes"),
+                    firefox_download_state(sqlite::int(&row, "state").unwrap_or(-1)), "",
+                    &sqlite::text(&row, "mimeType"), "", "", "", &source,
+                ])?;
+            }
+        }
+    }
+
+    if let Some((db, source)) = open_db(profile, "formhistory.sqlite")? {
+        if db.has_table("moz_formhistory") {
+            let searches = db.query(
+                "SELECT value, lastUsed FROM moz_formhistory WHERE fieldname = 'searchbar-history' ORDER BY lastUsed",
+            )?;
+            for row in &searches {
+                write_row(&mut out.searches, &mut out.counts[2], &[
+                    &prtime(row, "lastUsed"), &owner[0], &owner[1], &owner[2],
+                    &sqlite::text(row, "value"), "", &source,
+                ])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_row(writer: &mut Writer<File>, count: &mut usize, row: &[&str]) -> Result<()> {
+    writer.write_record(row)?;
+    *count += 1;
+    Ok(())
+}
+
+fn webkit(row: &SqlRow, column: &str) -> String {
+    common::fmt_dt(sqlite::int(row, column).and_then(common::webkit_to_dt))
+}
+
+fn prtime(row: &SqlRow, column: &str) -> String {
+    common::fmt_dt(sqlite::int(row, column).and_then(common::unix_micros_to_dt))
+}
+
+fn unix(row: &SqlRow, column: &str) -> String {
+    common::fmt_dt(sqlite::int(row, column).and_then(common::unix_to_dt))
+}
+
+fn first_non_empty(row: &SqlRow, columns: &[&str]) -> String {
+    columns.iter().map(|c| sqlite::text(row, c)).find(|v| !v.is_empty()).unwrap_or_default()
+}
+
+/// the core type of a Chromium page transition, from the lowest byte
+fn chromium_transition(transition: i64) -> &'static str {
+    match transition & 0xff {
+        0 => "LINK",
+        1 => "TYPED",
+        2 => "AUTO_BOOKMARK",
+        3 => "AUTO_SUBFRAME",
+        4 => "MANUAL_SUBFRAME",
+        5 => "GENERATED",
+        6 => "AUTO_TOPLEVEL",
+        7 => "FORM_SUBMIT",
+        8 => "RELOAD",
+        9 => "KEYWORD",
+        10 => "KEYWORD_GENERATED",
+        _ => "",
+    }
+}
+
+fn chromium_download_state(state: i64) -> &'static str {
+    match state {
+        0 => "IN_PROGRESS",
+        1 => "COMPLETE",
+        2 => "CANCELLED",
+        3 | 4 => "INTERRUPTED",
+        _ => "",
+    }
+}
+
+fn firefox_visit_type(visit_type: i64) -> &'static str {
+    match visit_type {
+        1 => "LINK",
+        2 => "TYPED",
+        3 => "BOOKMARK",
+        4 => "EMBED",
+        5 => "REDIRECT_PERMANENT",
+        6 => "REDIRECT_TEMPORARY",
+        7 => "DOWNLOAD",
+        8 => "FRAMED_LINK",
+        9 => "RELOAD",
+        _ => "",
+    }
+}
+
+fn firefox_download_state(state: i64) -> &'static str {
+    match state {
+        0 => "IN_PROGRESS",
+        1 => "COMPLETE",
+        2 => "FAILED",
+        3 => "CANCELLED",
+        4 => "PAUSED",
+        6 => "BLOCKED_PARENTAL",
+        7 => "SCANNING",
+        8 => "BLOCKED_POLICY",
+        9 => "BLOCKED_DIRTY",
+        _ => "",
+    }
+}
+
+/// convert a file:/// URI to a path, decoding the percent encoded characters
+fn file_uri_to_path(uri: &str) -> String {
+    let Some(path) = uri.strip_prefix("file:///") else { return uri.to_string() };
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8_lossy(&decoded).to_string();
+    // windows paths are file:///C:/Users/..., otherwise keep the leading slash
+    match path.as_bytes().get(1) {
+        Some(b':') => path.replace('/', "\\"),
+        _ => format!("/{path}"),
+    }
+}
+
+/// read the Zone.Identifier of the downloaded file, from the path it was saved to.
+/// The drive letter is replaced with the root of the data source. The stream is read
+/// as an alternate data stream, or as a file named `<file>:Zone.Identifier` or its
+/// URL encoded form `<file>%3AZone.Identifier`, as stored by collection tools.
+pub fn zone_identifier(root: &Path, target: &str) -> Option<ZoneIdentifier> {
+    let relative = match target.as_bytes().get(1) {
+        Some(b':') => &target[2..],
+        _ => target,
+    };
+    let (parent, name) = relative.rsplit_once(['\\', '/'])?;
+    let parent = common::join_case_insensitive(root, parent)?;
+    let content = [":Zone.Identifier", "%3AZone.Identifier"].iter().find_map(|suffix| {
+        let stream = parent.join(format!("{name}{suffix}"));
+        let stream = match stream.exists() {
+            true => stream,
+            false => find_case_insensitive(&stream)?,
+        };
+        fs::read(stream).ok()
+    })?;
+    let content = match content.starts_with(&[0xff, 0xfe]) {
+        true => common::utf16le_to_string(&content[2..]),
+        false => String::from_utf8_lossy(&content).to_string(),
+    };
+    let mut zone = ZoneIdentifier::default();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let value = value.trim().to_string();
+        match key.trim().to_lowercase().as_str() {
+            "zoneid" => zone.zone_id = value,
+            "hosturl" => zone.host_url = value,
+            "referrerurl" => zone.referrer_url = value,
+            _ => (),
+        }
+    }
+    Some(zone)
+}
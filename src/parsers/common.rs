@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, TimeZone, Utc};
+
+use crate::ops::file_ops;
+
+/// Number of 100ns intervals between the FILETIME epoch (1601-01-01) and the unix epoch
+const FILETIME_UNIX_DIFF: i64 = 116_444_736_000_000_000;
+/// Seconds between the unix epoch and the Cocoa epoch of 2001-01-01
+const COCOA_UNIX_DIFF: i64 = 978_307_200;
+
+/// read a little endian u16 from the slice at the offset, None if out of bounds
+pub fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+/// read a little endian u32 from the slice at the offset, None if out of bounds
+pub fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// read a little endian u64 from the slice at the offset, None if out of bounds
+pub fn le_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// convert a Windows FILETIME (100ns intervals since 1601-01-01) to UTC.
+/// Returns None for zero, or values that can't be represented
+pub fn filetime_to_dt(filetime: u64) -> Option<DateTime<Utc>> {
+    if filetime == 0 || filetime > i64::MAX as u64 {
+        return None;
+    }
+    let unix_100ns = filetime as i64 - FILETIME_UNIX_DIFF;
+    let secs = unix_100ns.div_euclid(10_000_000);
+    let nanos = (unix_100ns.rem_euclid(10_000_000) * 100) as u32;
+    Utc.timestamp_opt(secs, nanos).single()
+}
+
+/// convert a WebKit/Chromium timestamp (microseconds since 1601-01-01) to UTC
+pub fn webkit_to_dt(micros: i64) -> Option<DateTime<Utc>> {
+    filetime_to_dt((micros.max(0) as u64).checked_mul(10)?)
+}
+
+/// convert a unix timestamp in microseconds (PRTime, as used by Firefox) to UTC
+pub fn unix_micros_to_dt(micros: i64) -> Option<DateTime<Utc>> {
+    if micros <= 0 {
+        return None;
+    }
+    Utc.timestamp_micros(micros).single()
+}
+
+/// convert a unix timestamp in seconds to UTC
+pub fn unix_to_dt(secs: i64) -> Option<DateTime<Utc>> {
+    if secs <= 0 {
+        return None;
+    }
+    Utc.timestamp_opt(secs, 0).single()
+}
+
+/// convert an OLE automation date (fractional days since 1899-12-30), as used by
+/// ESE DateTime columns, to UTC. Returns None for zero or invalid values
+pub fn ole_to_dt(days: f64) -> Option<DateTime<Utc>> {
+    if !days.is_finite() || days == 0.0 {
+        return None;
+    }
+    let base = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?.and_utc();
+    let millis = (days * 86_400_000.0).round();
+    if millis.abs() > i64::MAX as f64 {
+        return None;
+    }
+    base.checked_add_signed(Duration::milliseconds(millis as i64))
+}
+
+/// convert a Cocoa timestamp (seconds since 2001-01-01), as used by the macOS databases
+/// and plists, to UTC. Returns None for zero or invalid values
+pub fn cocoa_to_dt(secs: f64) -> Option<DateTime<Utc>> {
+    if !secs.is_finite() || secs == 0.0 {
+        return None;
+    }
+    let millis = ((secs + COCOA_UNIX_DIFF as f64) * 1000.0).round();
+    if millis.abs() > i64::MAX as f64 {
+        return None;
+    }
+    Utc.timestamp_millis_opt(millis as i64).single()
+}
+
+/// convert a 16 byte Windows SYSTEMTIME (year, month, weekday, day, hour, minute,
+/// second, milliseconds) at the offset. Returns None when unset or invalid
+pub fn systemtime_to_dt(data: &[u8], offset: usize) -> Option<DateTime<Utc>> {
+    let field = |i: usize| le_u16(data, offset + i * 2).map(|v| v as u32);
+    let date = NaiveDate::from_ymd_opt(field(0)? as i32, field(1)?, field(3)?)?;
+    let time = date.and_hms_milli_opt(field(4)?, field(5)?, field(6)?, field(7)?)?;
+    Some(time.and_utc())
+}
+
+/// format a timestamp as ISO 8601 in UTC, i.e. 2024-01-31T13:45:01.123Z, or empty if missing
+pub fn fmt_dt(dt: Option<DateTime<Utc>>) -> String {
+    match dt {
+        Some(dt) => dt.to_rfc3339_opts(SecondsFormat::Millis, true),
+        None => String::new(),
+    }
+}
+
+/// decode UTF-16LE bytes to a string, stopping at the first null character
+pub fn utf16le_to_string(data: &[u8]) -> String {
+    let chars: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    String::from_utf16_lossy(&chars)
+}
+
+/// decode single byte (ASCII / Windows-1252-ish) text, stopping at the first null
+pub fn ascii_to_string(data: &[u8]) -> String {
+    data.iter()
+        .take_while(|b| **b != 0)
+        .map(|b| *b as char)
+        .collect()
+}
+
+/// convert a binary Windows SID to its string form, i.e. S-1-5-21-...-1001
+pub fn sid_to_string(data: &[u8]) -> Option<String> {
+    let revision = *data.first()?;
+    let sub_count = *data.get(1)? as usize;
+    let authority_bytes = data.get(2..8)?;
+    let authority = authority_bytes
+        .iter()
+        .fold(0u64, |acc, b| (acc << 8) | *b as u64);
+    let mut sid = format!("S-{revision}-{authority}");
+    for i in 0..sub_count {
+        sid.push_str(&format!("-{}", le_u32(data, 8 + i * 4)?));
+    }
+    Some(sid)
+}
+
+/// well known SIDs that won't be found in a profile list
+pub fn well_known_sid(sid: &str) -> Option<&'static str> {
+    match sid {
+        "S-1-5-18" => Some("SYSTEM"),
+        "S-1-5-19" => Some("LOCAL SERVICE"),
+        "S-1-5-20" => Some("NETWORK SERVICE"),
+        _ => None,
+    }
+}
+
+/// format a little endian (mixed-endian) binary GUID as {XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}
+pub fn guid_to_string(data: &[u8]) -> Option<String> {
+    let d = data.get(..16)?;
+    Some(format!(
+        "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+        le_u32(d, 0)?, le_u16(d, 4)?, le_u16(d, 6)?,
+        d[8], d[9], d[10], d[11], d[12], d[13], d[14], d[15]
+    ))
+}
+
+/// format bytes as a lowercase hex string
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// create a CSV writer for the output file, making the parent folder if needed
+pub fn csv_writer(out_file: &Path) -> anyhow::Result<csv::Writer<File>> {
+    if let Some(parent) = out_file.parent() {
+        file_ops::make_folders(parent);
+    }
+    Ok(csv::Writer::from_path(out_file)?)
+}
+
+/// join a relative path, split by `\` or `/`, onto the base path, matching each
+/// component case-insensitively as NTFS paths are when mounted or collected on Linux.
+/// Returns None if any component doesn't exist
+pub fn join_case_insensitive(base: &Path, relative: &str) -> Option<PathBuf> {
+    let mut path = base.to_path_buf();
+    for component in relative.split(['\\', '/']).filter(|c| !c.is_empty()) {
+        let joined = path.join(component);
+        path = match joined.exists() {
+            true => joined,
+            false => crate::art::paths::find_case_insensitive(&joined)?,
+        };
+    }
+    Some(path)
+}
@@ -0,0 +1,623 @@
+/*
+ESE (Extensible Storage Engine / JET Blue) database reader.
+Reads the catalog, tables and records of databases such as SRUDB.dat and the
+User Access Logging Current.mdb without the Windows esent libraries. The database
+is read as-is, so dirty databases (not replayed with esentutl) are parsed with the
+records that have been flushed to the database file.
+*/
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::common::{self, le_u16, le_u32};
+use super::xpress;
+
+const ESE_SIGNATURE: u32 = 0x89AB_CDEF;
+/// The catalog (MSysObjects) always has its father data page at page 4
+const CATALOG_PAGE: u32 = 4;
+const MAX_TREE_DEPTH: usize = 32;
+
+const PAGE_FLAG_ROOT: u32 = 0x0001;
+const PAGE_FLAG_LEAF: u32 = 0x0002;
+const PAGE_FLAG_SPACE_TREE: u32 = 0x0020;
+
+const TAG_FLAG_DEFUNCT: u16 = 0x0002;
+const TAG_FLAG_COMMON_KEY: u16 = 0x0004;
+
+const TAGGED_FLAG_COMPRESSED: u8 = 0x02;
+const TAGGED_FLAG_LONG_VALUE: u8 = 0x04;
+const TAGGED_FLAG_MULTI_VALUE: u8 = 0x08;
+
+const CATALOG_TYPE_TABLE: i64 = 1;
+const CATALOG_TYPE_COLUMN: i64 = 2;
+const CATALOG_TYPE_LONG_VALUE: i64 = 4;
+
+/// Codepage of UTF-16 little endian text columns
+pub const CODEPAGE_UNICODE: u32 = 1200;
+
+/// ESE column types (JET_coltyp)
+pub mod coltyp {
+    pub const BIT: u32 = 1;
+    pub const UNSIGNED_BYTE: u32 = 2;
+    pub const SHORT: u32 = 3;
+    pub const LONG: u32 = 4;
+    pub const CURRENCY: u32 = 5;
+    pub const IEEE_SINGLE: u32 = 6;
+    pub const IEEE_DOUBLE: u32 = 7;
+    pub const DATE_TIME: u32 = 8;
+    pub const BINARY: u32 = 9;
+    pub const TEXT: u32 = 10;
+    pub const LONG_BINARY: u32 = 11;
+    pub const LONG_TEXT: u32 = 12;
+    pub const UNSIGNED_LONG: u32 = 14;
+    pub const LONG_LONG: u32 = 15;
+    pub const GUID: u32 = 16;
+    pub const UNSIGNED_SHORT: u32 = 17;
+}
+
+/// A column definition from the catalog
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub id: u32,
+    pub name: String,
+    pub col_type: u32,
+    pub space_usage: u32,
+    pub codepage: u32,
+}
+
+/// A table definition from the catalog, with its columns ordered by identifier
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub object_id: u32,
+    pub fdp: u32,
+    pub long_value_fdp: Option<u32>,
+    pub columns: Vec<Column>,
+}
+
+/// A decoded column value
+#[derive(Debug, Clone, PartialEq)]
+pub enum EseValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// OLE automation date, as stored in DateTime columns
+    DateTime(f64),
+    Guid([u8; 16]),
+    Binary(Vec<u8>),
+    Text(String),
+}
+
+impl EseValue {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            EseValue::Int(v) => Some(*v),
+            EseValue::Bool(b) => Some(*b as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            EseValue::Binary(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            EseValue::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_datetime(&self) -> Option<DateTime<Utc>> {
+        match self {
+            EseValue::DateTime(d) => common::ole_to_dt(*d),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == EseValue::Null
+    }
+}
+
+impl fmt::Display for EseValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EseValue::Null => Ok(()),
+            EseValue::Bool(b) => write!(f, "{b}"),
+            EseValue::Int(v) => write!(f, "{v}"),
+            EseValue::Float(v) => write!(f, "{v}"),
+            EseValue::DateTime(d) => write!(f, "{}", common::fmt_dt(common::ole_to_dt(*d))),
+            EseValue::Guid(g) => write!(f, "{}", common::guid_to_string(g).unwrap_or_default()),
+            EseValue::Binary(b) => write!(f, "{}", common::to_hex(b)),
+            EseValue::Text(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// A table record, the values are in the order of the table's columns
+#[derive(Debug, Clone, Default)]
+pub struct Record {
+    pub values: Vec<(String, EseValue)>,
+}
+
+impl Record {
+    /// get a value by column name, case-insensitive
+    pub fn get(&self, name: &str) -> Option<&EseValue> {
+        self.values
+            .iter()
+            .find(|(col, _)| col.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+}
+
+struct Page<'a> {
+    data: &'a [u8],
+    flags: u32,
+    tag_count: usize,
+    header_size: usize,
+    large: bool,
+}
+
+/// An ESE database, read into memory
+pub struct EseDb {
+    data: Vec<u8>,
+    page_size: usize,
+    format_revision: u32,
+    tables: Vec<Table>,
+    long_values: RefCell<HashMap<u32, HashMap<u32, Vec<u8>>>>,
+}
+
+impl EseDb {
+    /// open and read the catalog of the ESE database at the file path
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = fs::read(path)
+            .with_context(|| format!("Unable to read ESE database {}", path.display()))?;
+        Self::from_bytes(data)
+            .with_context(|| format!("Unable to parse ESE database {}", path.display()))
+    }
+
+    /// parse the ESE database from bytes, reading the file header and catalog
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        if le_u32(&data, 4) != Some(ESE_SIGNATURE) {
+            bail!("Missing ESE file header signature");
+        }
+        let format_revision = le_u32(&data, 232).unwrap_or(0);
+        let page_size = le_u32(&data, 236).unwrap_or(0) as usize;
+        if !matches!(page_size, 2048 | 4096 | 8192 | 16384 | 32768) {
+            bail!("Unsupported ESE page size {page_size}");
+        }
+        let mut db = EseDb {
+            data,
+            page_size,
+            format_revision,
+            tables: Vec::new(),
+            long_values: RefCell::new(HashMap::new()),
+        };
+        db.tables = db.read_catalog();
+        Ok(db)
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    /// get a table by name, case-insensitive
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    /// read all records of the named table
+    pub fn table_records(&self, name: &str) -> Result<Vec<Record>> {
+        match self.table(name) {
+            Some(table) => Ok(self.records(table)),
+            None => bail!("Table {name} not found in ESE database"),
+        }
+    }
+
+    /// read all records of the table, walking its B+ tree from the father data page
+    pub fn records(&self, table: &Table) -> Vec<Record> {
+        self.leaf_entries(table.fdp)
+            .into_iter()
+            .map(|(_, data)| self.parse_record(&table.columns, data, table.long_value_fdp))
+            .collect()
+    }
+
+    fn large_pages(&self) -> bool {
+        self.page_size >= 16384 && self.format_revision >= 0x11
+    }
+
+    fn page(&self, page_number: u32) -> Option<Page<'_>> {
+        let offset = (page_number as usize + 1) * self.page_size;
+        let data = self.data.get(offset..offset + self.page_size)?;
+        let large = self.large_pages();
+        Some(Page {
+            data,
+            flags: le_u32(data, 36)?,
+            tag_count: le_u16(data, 34)? as usize,
+            header_size: if large { 80 } else { 40 },
+            large,
+        })
+    }
+
+    /// get the value and flags of a page tag, tags are stored backwards from the end of the page
+    fn page_tag<'a>(&self, page: &Page<'a>, index: usize) -> Option<(&'a [u8], u16)> {
+        let tag_offset = self.page_size.checked_sub(4 * (index + 1))?;
+        let raw_size = le_u16(page.data, tag_offset)?;
+        let raw_offset = le_u16(page.data, tag_offset + 2)?;
+        let (size, offset, mut flags) = if page.large {
+            (raw_size & 0x7fff, raw_offset & 0x7fff, 0)
+        } else {
+            (raw_size & 0x1fff, raw_offset & 0x1fff, raw_offset >> 13)
+        };
+        let start = page.header_size + offset as usize;
+        let value = page.data.get(start..start + size as usize)?;
+        if page.large && index > 0 {
+            // large pages keep the tag flags in the top bits of the value
+            flags = le_u16(value, 0).unwrap_or(0) >> 13;
+        }
+        Some((value, flags))
+    }
+
+    /// get the key and data of each entry on the page, skipping deleted entries
+    fn page_entries<'a>(&self, page: &Page<'a>) -> Vec<(Vec<u8>, &'a [u8])> {
+        let prefix: &[u8] = match page.flags & PAGE_FLAG_ROOT {
+            0 => self.page_tag(page, 0).map(|(v, _)| v).unwrap_or(&[]),
+            _ => &[],
+        };
+        let mut entries = Vec::new();
+        for i in 1..page.tag_count {
+            let Some((value, flags)) = self.page_tag(page, i) else { continue };
+            if flags & TAG_FLAG_DEFUNCT != 0 {
+                continue;
+            }
+            let mut pos = 0;
+            let mut common_size = 0;
+            if flags & TAG_FLAG_COMMON_KEY != 0 {
+                common_size = (le_u16(value, 0).unwrap_or(0) & 0x1fff) as usize;
+                pos = 2;
+            }
+            let Some(local_size) = le_u16(value, pos) else { continue };
+            let local_size = (local_size & 0x1fff) as usize;
+            pos += 2;
+            let Some(local_key) = value.get(pos..pos + local_size) else { continue };
+            let mut key = prefix[..common_size.min(prefix.len())].to_vec();
+            key.extend_from_slice(local_key);
+            entries.push((key, &value[pos + local_size..]));
+        }
+        entries
+    }
+
+    /// walk the B+ tree from the root page, returning the entries of all its leaf pages
+    fn leaf_entries(&self, root: u32) -> Vec<(Vec<u8>, &[u8])> {
+        let mut entries = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(root, 0)];
+        while let Some((page_number, depth)) = stack.pop() {
+            if depth > MAX_TREE_DEPTH || !visited.insert(page_number) {
+                continue;
+            }
+            let Some(page) = self.page(page_number) else { continue };
+            if page.flags & PAGE_FLAG_SPACE_TREE != 0 {
+                continue;
+            }
+            let page_entries = self.page_entries(&page);
+            if page.flags & PAGE_FLAG_LEAF != 0 {
+                entries.extend(page_entries);
+            } else {
+                // branch entries point to child pages, push in reverse to keep the key order
+                for (_, data) in page_entries.into_iter().rev() {
+                    if let Some(child) = le_u32(data, 0) {
+                        stack.push((child, depth + 1));
+                    }
+                }
+            }
+        }
+        entries
+    }
+
+    fn read_catalog(&self) -> Vec<Table> {
+        let catalog_columns = catalog_columns();
+        let mut tables: Vec<Table> = Vec::new();
+        let mut columns: HashMap<u32, Vec<Column>> = HashMap::new();
+        let mut long_values: HashMap<u32, u32> = HashMap::new();
+
+        for (_, data) in self.leaf_entries(CATALOG_PAGE) {
+            let record = self.parse_record(&catalog_columns, data, None);
+            let int = |name: &str| record.get(name).and_then(|v| v.as_i64()).unwrap_or(0);
+            let object_id = int("ObjidTable") as u32;
+            let name = record.get("Name").and_then(|v| v.as_str()).unwrap_or("").to_string();
+            match int("Type") {
+                CATALOG_TYPE_TABLE => tables.push(Table {
+                    name,
+                    object_id,
+                    fdp: int("ColtypOrPgnoFDP") as u32,
+                    long_value_fdp: None,
+                    columns: Vec::new(),
+                }),
+                CATALOG_TYPE_COLUMN => columns.entry(object_id).or_default().push(Column {
+                    id: int("Id") as u32,
+                    name,
+                    col_type: int("ColtypOrPgnoFDP") as u32,
+                    space_usage: int("SpaceUsage") as u32,
+                    codepage: int("PagesOrLocale") as u32,
+                }),
+                CATALOG_TYPE_LONG_VALUE => {
+                    long_values.insert(object_id, int("ColtypOrPgnoFDP") as u32);
+                }
+                _ => (),
+            }
+        }
+
+        for table in tables.iter_mut() {
+            let mut table_columns = columns.remove(&table.object_id).unwrap_or_default();
+            table_columns.sort_by_key(|c| c.id);
+            table.columns = table_columns;
+            table.long_value_fdp = long_values.get(&table.object_id).copied();
+        }
+        tables
+    }
+
+    /// parse a record's fixed, variable and tagged data into values for each column
+    fn parse_record(&self, columns: &[Column], data: &[u8], long_value_fdp: Option<u32>) -> Record {
+        let mut record = Record::default();
+        if data.len() < 4 {
+            return record;
+        }
+        let last_fixed = data[0] as u32;
+        let last_variable = data[1] as u32;
+        let variable_offset = le_u16(data, 2).unwrap_or(0) as usize;
+
+        // fixed size columns are stored one after another from offset 4
+        let mut fixed: HashMap<u32, &[u8]> = HashMap::new();
+        let mut fixed_pos = 4;
+        for column in columns.iter().filter(|c| c.id <= 127 && c.id <= last_fixed) {
+            let size = fixed_size(column);
+            if let Some(value) = data.get(fixed_pos..fixed_pos + size) {
+                fixed.insert(column.id, value);
+            }
+            fixed_pos += size;
+        }
+
+        // variable size columns have an array of end offsets, the high bit marks an empty value
+        let mut variable: HashMap<u32, &[u8]> = HashMap::new();
+        let variable_count = last_variable.saturating_sub(127) as usize;
+        let variable_start = variable_offset + variable_count * 2;
+        let mut previous_end = 0;
+        for i in 0..variable_count {
+            let Some(raw_end) = le_u16(data, variable_offset + i * 2) else { break };
+            if raw_end & 0x8000 != 0 {
+                continue;
+            }
+            let end = raw_end as usize;
+            if let Some(value) = data.get(variable_start + previous_end..variable_start + end) {
+                variable.insert(128 + i as u32, value);
+            }
+            previous_end = end;
+        }
+
+        let tagged = self.parse_tagged(data, variable_start + previous_end);
+
+        for column in columns {
+            let value = if column.id <= 127 {
+                fixed.get(&column.id).map(|v| decode_value(column, v)).unwrap_or(EseValue::Null)
+            } else if column.id <= 255 {
+                variable.get(&column.id).map(|v| decode_value(column, v)).unwrap_or(EseValue::Null)
+            } else {
+                match tagged.get(&column.id) {
+                    Some((value, flags)) => self.decode_tagged(column, value, *flags, long_value_fdp),
+                    None => EseValue::Null,
+                }
+            };
+            record.values.push((column.name.clone(), value));
+        }
+        record
+    }
+
+    /// parse the tagged data array, returning each column's data and tagged flags
+    fn parse_tagged<'a>(&self, data: &'a [u8], start: usize) -> HashMap<u32, (&'a [u8], u8)> {
+        let mut tagged = HashMap::new();
+        let Some(area) = data.get(start..) else { return tagged };
+        let (offset_mask, flags_always) = match self.page_size > 8192 && self.format_revision >= 0x11 {
+            true => (0x7fff, true),
+            false => (0x3fff, false),
+        };
+        let Some(first_offset) = le_u16(area, 2) else { return tagged };
+        let count = (first_offset & offset_mask) as usize / 4;
+        let mut items: Vec<(u32, usize, bool)> = Vec::with_capacity(count);
+        for i in 0..count {
+            let (Some(id), Some(raw_offset)) = (le_u16(area, i * 4), le_u16(area, i * 4 + 2)) else { break };
+            let has_flags = flags_always || raw_offset & 0x4000 != 0;
+            items.push((id as u32, (raw_offset & offset_mask) as usize, has_flags));
+        }
+        for (i, (id, offset, has_flags)) in items.iter().enumerate() {
+            let end = items.get(i + 1).map(|n| n.1).unwrap_or(area.len());
+            let Some(mut value) = area.get(*offset..end.max(*offset)) else { continue };
+            let mut flags = 0;
+            if *has_flags && !value.is_empty() {
+                flags = value[0];
+                value = &value[1..];
+            }
+            tagged.insert(*id, (value, flags));
+        }
+        tagged
+    }
+
+    fn decode_tagged(&self, column: &Column, value: &[u8], flags: u8, long_value_fdp: Option<u32>) -> EseValue {
+        let mut bytes = value.to_vec();
+        if flags & TAGGED_FLAG_LONG_VALUE != 0 {
+            let long_value = match (long_value_fdp, le_u32(value, 0)) {
+                (Some(fdp), Some(lid)) => self.long_value(fdp, lid),
+                _ => None,
+            };
+            match long_value {
+                Some(lv) => bytes = lv,
+                None => return EseValue::Null,
+            }
+        }
+        if flags & TAGGED_FLAG_MULTI_VALUE != 0 {
+            // multi-valued columns are returned raw
+            return EseValue::Binary(bytes);
+        }
+        if flags & TAGGED_FLAG_COMPRESSED != 0 {
+            let (decompressed, ascii) = decompress(&bytes);
+            if ascii && is_text(column) {
+                return EseValue::Text(common::ascii_to_string(&decompressed));
+            }
+            bytes = decompressed;
+        }
+        decode_value(column, &bytes)
+    }
+
+    /// get a long value by its identifier, loading the table's long value tree on first use
+    fn long_value(&self, long_value_fdp: u32, lid: u32) -> Option<Vec<u8>> {
+        let mut cache = self.long_values.borrow_mut();
+        let values = cache
+            .entry(long_value_fdp)
+            .or_insert_with(|| self.load_long_values(long_value_fdp));
+        values.get(&lid).cloned()
+    }
+
+    /// long value segments are keyed by the big endian identifier and offset
+    fn load_long_values(&self, long_value_fdp: u32) -> HashMap<u32, Vec<u8>> {
+        let mut segments: HashMap<u32, Vec<(u32, &[u8])>> = HashMap::new();
+        for (key, data) in self.leaf_entries(long_value_fdp) {
+            if key.len() != 8 {
+                // 4 byte keys are the long value root, holding the reference count and size
+                continue;
+            }
+            let lid = u32::from_be_bytes(key[0..4].try_into().unwrap());
+            let offset = u32::from_be_bytes(key[4..8].try_into().unwrap());
+            segments.entry(lid).or_default().push((offset, data));
+        }
+        segments
+            .into_iter()
+            .map(|(lid, mut parts)| {
+                parts.sort_by_key(|(offset, _)| *offset);
+                (lid, parts.into_iter().flat_map(|(_, d)| d.iter().copied()).collect())
+            })
+            .collect()
+    }
+}
+
+/// the fixed columns of the catalog table MSysObjects
+fn catalog_columns() -> Vec<Column> {
+    let column = |id, name: &str, col_type, space_usage| Column {
+        id,
+        name: name.to_string(),
+        col_type,
+        space_usage,
+        codepage: 0,
+    };
+    vec![
+        column(1, "ObjidTable", coltyp::LONG, 4),
+        column(2, "Type", coltyp::SHORT, 2),
+        column(3, "Id", coltyp::LONG, 4),
+        column(4, "ColtypOrPgnoFDP", coltyp::LONG, 4),
+        column(5, "SpaceUsage", coltyp::LONG, 4),
+        column(6, "Flags", coltyp::LONG, 4),
+        column(7, "PagesOrLocale", coltyp::LONG, 4),
+        column(8, "RootFlag", coltyp::BIT, 1),
+        column(9, "RecordOffset", coltyp::SHORT, 2),
+        column(10, "LCMapFlags", coltyp::LONG, 4),
+        column(11, "KeyMost", coltyp::UNSIGNED_SHORT, 2),
+        column(128, "Name", coltyp::TEXT, 255),
+    ]
+}
+
+/// the size of a fixed column, from its catalog space usage or its type
+fn fixed_size(column: &Column) -> usize {
+    if column.space_usage > 0 {
+        return column.space_usage as usize;
+    }
+    match column.col_type {
+        coltyp::BIT | coltyp::UNSIGNED_BYTE => 1,
+        coltyp::SHORT | coltyp::UNSIGNED_SHORT => 2,
+        coltyp::LONG | coltyp::UNSIGNED_LONG | coltyp::IEEE_SINGLE => 4,
+        coltyp::CURRENCY | coltyp::IEEE_DOUBLE | coltyp::DATE_TIME | coltyp::LONG_LONG => 8,
+        coltyp::GUID => 16,
+        _ => 0,
+    }
+}
+
+fn is_text(column: &Column) -> bool {
+    matches!(column.col_type, coltyp::TEXT | coltyp::LONG_TEXT)
+}
+
+/// decode the bytes of a column into a value based on its type
+fn decode_value(column: &Column, bytes: &[u8]) -> EseValue {
+    let int = |size: usize| -> Option<[u8; 8]> {
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes.get(..size)?);
+        Some(buf)
+    };
+    let value = match column.col_type {
+        coltyp::BIT => bytes.first().map(|b| EseValue::Bool(*b != 0)),
+        coltyp::UNSIGNED_BYTE => bytes.first().map(|b| EseValue::Int(*b as i64)),
+        coltyp::SHORT => int(2).map(|b| EseValue::Int(i16::from_le_bytes([b[0], b[1]]) as i64)),
+        coltyp::UNSIGNED_SHORT => int(2).map(|b| EseValue::Int(u16::from_le_bytes([b[0], b[1]]) as i64)),
+        coltyp::LONG => int(4).map(|b| EseValue::Int(i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as i64)),
+        coltyp::UNSIGNED_LONG => int(4).map(|b| EseValue::Int(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as i64)),
+        coltyp::CURRENCY | coltyp::LONG_LONG => int(8).map(|b| EseValue::Int(i64::from_le_bytes(b))),
+        coltyp::IEEE_SINGLE => int(4).map(|b| EseValue::Float(f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)),
+        coltyp::IEEE_DOUBLE => int(8).map(|b| EseValue::Float(f64::from_le_bytes(b))),
+        coltyp::DATE_TIME => int(8).map(|b| EseValue::DateTime(f64::from_le_bytes(b))),
+        coltyp::GUID => bytes.get(..16).map(|g| EseValue::Guid(g.try_into().unwrap())),
+        coltyp::TEXT | coltyp::LONG_TEXT => Some(EseValue::Text(match column.codepage {
+            CODEPAGE_UNICODE => common::utf16le_to_string(bytes),
+            _ => common::ascii_to_string(bytes),
+        })),
+        _ => Some(EseValue::Binary(bytes.to_vec())),
+    };
+    value.unwrap_or(EseValue::Null)
+}
+
+/// decompress a compressed column value, the compression type is in the top 5 bits of the
+/// first byte. Returns the bytes and whether they are single byte text from 7-bit ASCII
+fn decompress(data: &[u8]) -> (Vec<u8>, bool) {
+    match data.first().map(|b| b >> 3) {
+        Some(1) => (seven_bit_decompress(data, false), true),
+        Some(2) => (seven_bit_decompress(data, true), false),
+        Some(3) => {
+            let size = le_u16(data, 1).unwrap_or(0) as usize;
+            match xpress::lz77_decompress(data.get(3..).unwrap_or(&[]), size) {
+                Ok(out) => (out, false),
+                Err(_) => (data.to_vec(), false),
+            }
+        }
+        _ => (data.to_vec(), false),
+    }
+}
+
+/// unpack 7-bit characters packed least significant bit first, after the header byte
+fn seven_bit_decompress(data: &[u8], utf16: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * 8 / 7 * 2);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for b in data.iter().skip(1) {
+        bits |= (*b as u32) << bit_count;
+        bit_count += 8;
+        while bit_count >= 7 {
+            out.push((bits & 0x7f) as u8);
+            if utf16 {
+                out.push(0);
+            }
+            bits >>= 7;
+            bit_count -= 7;
+        }
+    }
+    out
+}
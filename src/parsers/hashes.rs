@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use anyhow::{Context, Result};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::common;
+
+/// The MD5, SHA1 and SHA256 of a file, as lowercase hex
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileHashes {
+    pub md5: String,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+/// Calculates the MD5, SHA1 and SHA256 together as the data is read
+#[derive(Default)]
+pub struct FileHasher {
+    md5: Md5,
+    sha1: Sha1,
+    sha256: Sha256,
+}
+
+impl FileHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        self.md5.update(data);
+        self.sha1.update(data);
+        self.sha256.update(data);
+    }
+
+    pub fn finish(self) -> FileHashes {
+        FileHashes {
+            md5: common::to_hex(&self.md5.finalize()),
+            sha1: common::to_hex(&self.sha1.finalize()),
+            sha256: common::to_hex(&self.sha256.finalize()),
+        }
+    }
+}
+
+/// A reader that hashes the data read through it, so a file can be hashed while it is
+/// being scanned
+pub struct HashingReader<R> {
+    pub inner: R,
+    pub hasher: FileHasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashingReader { inner, hasher: FileHasher::default() }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// hash the file in a single read, calculating MD5, SHA1 and SHA256 together
+pub fn hash_file(path: &Path) -> Result<FileHashes> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to open file to hash {}", path.display()))?;
+    let mut reader = BufReader::with_capacity(1 << 20, file);
+    let mut hasher = FileHasher::default();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finish())
+}
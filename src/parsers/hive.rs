@@ -0,0 +1,278 @@
+/*
+Registry hive (regf) reader.
+Reads keys, their last written time and values from offline hives such as SYSTEM,
+SOFTWARE and SAM. Transaction logs are not replayed, so the hive is read as it is on disk.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::common::{self, le_u16, le_u32, le_u64};
+
+/// Hive bins start after the 4096 byte base block, cell offsets are relative to this
+const HBIN_START: usize = 4096;
+/// Value data larger than this is stored in big data (db) segments
+const BIG_DATA_SEGMENT: usize = 16344;
+const MAX_LIST_DEPTH: usize = 8;
+
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_MULTI_SZ: u32 = 7;
+pub const REG_QWORD: u32 = 11;
+
+/// A registry hive, read into memory
+pub struct Hive {
+    data: Vec<u8>,
+    root_offset: u32,
+}
+
+impl Hive {
+    /// open and read the registry hive at the file path
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = fs::read(path)
+            .with_context(|| format!("Unable to read registry hive {}", path.display()))?;
+        Self::from_bytes(data)
+            .with_context(|| format!("Unable to parse registry hive {}", path.display()))
+    }
+
+    /// parse the registry hive from bytes, checking the base block
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        if data.get(0..4) != Some(b"regf") {
+            bail!("Missing regf signature");
+        }
+        let Some(root_offset) = le_u32(&data, 0x24) else { bail!("Truncated base block") };
+        let hive = Hive { data, root_offset };
+        if hive.root().is_none() {
+            bail!("Root key not found at offset {root_offset:#x}");
+        }
+        Ok(hive)
+    }
+
+    /// the root key of the hive
+    pub fn root(&self) -> Option<Key<'_>> {
+        Key::new(self, self.root_offset)
+    }
+
+    /// get a key by its path from the root, i.e. `Microsoft\Windows NT\CurrentVersion`,
+    /// the names are matched case-insensitive
+    pub fn key(&self, path: &str) -> Option<Key<'_>> {
+        let mut key = self.root()?;
+        for name in path.split('\\').filter(|n| !n.is_empty()) {
+            key = key.subkey(name)?;
+        }
+        Some(key)
+    }
+
+    /// get the data of an allocated cell, without its size header
+    fn cell(&self, offset: u32) -> Option<&[u8]> {
+        let start = HBIN_START.checked_add(offset as usize)?;
+        let size = le_u32(&self.data, start)? as i32;
+        let size = size.unsigned_abs() as usize;
+        if size < 4 {
+            return None;
+        }
+        self.data.get(start + 4..start + size)
+    }
+
+    /// get the key offsets of a subkey list, following index roots (ri) to their lists
+    fn subkey_offsets(&self, list_offset: u32, depth: usize, offsets: &mut Vec<u32>) {
+        if depth > MAX_LIST_DEPTH {
+            return;
+        }
+        let Some(list) = self.cell(list_offset) else { return };
+        let count = le_u16(list, 2).unwrap_or(0) as usize;
+        match list.get(0..2) {
+            Some(b"lf") | Some(b"lh") => {
+                offsets.extend((0..count).filter_map(|i| le_u32(list, 4 + i * 8)));
+            }
+            Some(b"li") => {
+                offsets.extend((0..count).filter_map(|i| le_u32(list, 4 + i * 4)));
+            }
+            Some(b"ri") => {
+                for sub_list in (0..count).filter_map(|i| le_u32(list, 4 + i * 4)) {
+                    self.subkey_offsets(sub_list, depth + 1, offsets);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// read value data, which is stored inline, in a cell or in big data segments
+    fn value_data(&self, size: u32, offset: u32) -> Vec<u8> {
+        if size & 0x8000_0000 != 0 {
+            let size = ((size & 0x7fff_ffff) as usize).min(4);
+            return offset.to_le_bytes()[..size].to_vec();
+        }
+        let size = size as usize;
+        let Some(cell) = self.cell(offset) else { return Vec::new() };
+        if size > BIG_DATA_SEGMENT && cell.get(0..2) == Some(b"db") {
+            let count = le_u16(cell, 2).unwrap_or(0) as usize;
+            let Some(list) = le_u32(cell, 4).and_then(|o| self.cell(o)) else { return Vec::new() };
+            let mut data = Vec::with_capacity(size);
+            for segment in (0..count).filter_map(|i| le_u32(list, i * 4)) {
+                if let Some(segment) = self.cell(segment) {
+                    let remaining = size - data.len();
+                    data.extend_from_slice(&segment[..segment.len().min(remaining).min(BIG_DATA_SEGMENT)]);
+                }
+            }
+            return data;
+        }
+        cell[..size.min(cell.len())].to_vec()
+    }
+}
+
+/// A registry key (nk cell)
+#[derive(Clone, Copy)]
+pub struct Key<'a> {
+    hive: &'a Hive,
+    cell: &'a [u8],
+}
+
+impl<'a> Key<'a> {
+    fn new(hive: &'a Hive, offset: u32) -> Option<Self> {
+        let cell = hive.cell(offset)?;
+        if cell.get(0..2) != Some(b"nk") || cell.len() < 76 {
+            return None;
+        }
+        Some(Key { hive, cell })
+    }
+
+    pub fn name(&self) -> String {
+        let flags = le_u16(self.cell, 2).unwrap_or(0);
+        let len = le_u16(self.cell, 72).unwrap_or(0) as usize;
+        let name = self.cell.get(76..76 + len).unwrap_or(&[]);
+        decode_name(name, flags & KEY_COMP_NAME != 0)
+    }
+
+    pub fn last_written(&self) -> Option<DateTime<Utc>> {
+        common::filetime_to_dt(le_u64(self.cell, 4)?)
+    }
+
+    pub fn subkeys(&self) -> Vec<Key<'a>> {
+        let count = le_u32(self.cell, 20).unwrap_or(0);
+        let mut offsets = Vec::new();
+        if count > 0 {
+            if let Some(list) = le_u32(self.cell, 28) {
+                self.hive.subkey_offsets(list, 0, &mut offsets);
+            }
+        }
+        offsets.into_iter().filter_map(|o| Key::new(self.hive, o)).collect()
+    }
+
+    /// get a direct subkey by name, case-insensitive
+    pub fn subkey(&self, name: &str) -> Option<Key<'a>> {
+        self.subkeys().into_iter().find(|k| k.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn values(&self) -> Vec<Value> {
+        let count = le_u32(self.cell, 36).unwrap_or(0) as usize;
+        let Some(list) = le_u32(self.cell, 40).and_then(|o| self.hive.cell(o)) else { return Vec::new() };
+        (0..count)
+            .filter_map(|i| le_u32(list, i * 4))
+            .filter_map(|o| self.read_value(o))
+            .collect()
+    }
+
+    /// get a value by name, case-insensitive. The default value has an empty name
+    pub fn value(&self, name: &str) -> Option<Value> {
+        self.values().into_iter().find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    fn read_value(&self, offset: u32) -> Option<Value> {
+        let cell = self.hive.cell(offset)?;
+        if cell.get(0..2) != Some(b"vk") {
+            return None;
+        }
+        let name_len = le_u16(cell, 2)? as usize;
+        let flags = le_u16(cell, 16)?;
+        Some(Value {
+            name: decode_name(cell.get(20..20 + name_len)?, flags & VALUE_COMP_NAME != 0),
+            data_type: le_u32(cell, 12)?,
+            data: self.hive.value_data(le_u32(cell, 4)?, le_u32(cell, 8)?),
+        })
+    }
+}
+
+/// A registry value (vk cell) with its data
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub name: String,
+    pub data_type: u32,
+    pub data: Vec<u8>,
+}
+
+impl Value {
+    /// the value as a string, numbers are formatted as decimal and binary as hex
+    pub fn as_string(&self) -> String {
+        match self.data_type {
+            REG_SZ | REG_EXPAND_SZ => common::utf16le_to_string(&self.data),
+            REG_MULTI_SZ => self.as_multi_string().join(";"),
+            REG_DWORD => self.as_u32().map(|v| v.to_string()).unwrap_or_default(),
+            REG_QWORD => self.as_u64().map(|v| v.to_string()).unwrap_or_default(),
+            _ => common::to_hex(&self.data),
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        le_u32(&self.data, 0)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        le_u64(&self.data, 0)
+    }
+
+    pub fn as_multi_string(&self) -> Vec<String> {
+        let chars: Vec<u16> = self.data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        chars
+            .split(|c| *c == 0)
+            .filter(|s| !s.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect()
+    }
+}
+
+/// map user SIDs to usernames from the ProfileImagePath in the SOFTWARE hive ProfileList
+pub fn profile_users(software: &Hive) -> HashMap<String, String> {
+    let mut users = HashMap::new();
+    let Some(profiles) = software.key(r"Microsoft\Windows NT\CurrentVersion\ProfileList") else { return users };
+    for profile in profiles.subkeys() {
+        let Some(path) = profile.value("ProfileImagePath") else { continue };
+        let path = path.as_string();
+        let name = path.rsplit(['\\', '/']).next().unwrap_or(&path).to_string();
+        users.insert(profile.name(), name);
+    }
+    users
+}
+
+/// get the name of the current control set from the SYSTEM hive's Select key, i.e. ControlSet001
+pub fn current_control_set(system: &Hive) -> String {
+    let current = system.key("Select")
+        .and_then(|k| k.value("Current"))
+        .and_then(|v| v.as_u32())
+        .unwrap_or(1);
+    format!("ControlSet{current:03}")
+}
+
+/// get the hostname from the ComputerName of the current control set in the SYSTEM hive
+pub fn computer_name(system: &Hive) -> Option<String> {
+    let key = system.key(&format!(r"{}\Control\ComputerName\ComputerName", current_control_set(system)))?;
+    Some(key.value("ComputerName")?.as_string()).filter(|n| !n.is_empty())
+}
+
+fn decode_name(name: &[u8], compressed: bool) -> String {
+    match compressed {
+        true => common::ascii_to_string(name),
+        false => common::utf16le_to_string(name),
+    }
+}
@@ -0,0 +1,569 @@
+/*
+Host information report, replacing polars_hostinfo.py.
+Reads the SYSTEM, SOFTWARE and SAM hives for the hostname, OS build and install date,
+timezone, last shutdown, network interfaces, domain, local users and groups, installed
+software and last logons. The report is written as Markdown for the analyst and as
+JSON, so the facts of each host in a case can be aggregated.
+*/
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::common::{self, le_u16, le_u32, le_u64};
+use super::hive::{self, Hive, Key, REG_BINARY};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::timeline::mapping::SourceMapping;
+
+pub const HOSTINFO_JSON: &str = "host_info.json";
+pub const HOSTINFO_MARKDOWN: &str = "Host_Information.md";
+const CURRENT_VERSION: &str = r"Microsoft\Windows NT\CurrentVersion";
+const UNINSTALL_PATHS: [&str; 2] = [
+    r"Microsoft\Windows\CurrentVersion\Uninstall",
+    r"WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
+];
+/// The class of the network adapters in the Control\Network key, with their connection names
+const NETWORK_CLASS: &str = "{4D36E972-E325-11CE-BFC1-08002BE10318}";
+/// The processed event logs, with their channel, time, event id and computer columns and
+/// the name of the Security channel in each
+const EVENT_LOG_SOURCES: [(&str, [&str; 4], &str); 2] = [
+    ("EventLogs/EvtxECmd-All.csv", ["Channel", "TimeCreated", "EventId", "Computer"], "Security"),
+    ("EventLogs/hayabusa.csv", ["Channel", "datetime", "EventID", "Computer"], "Sec"),
+];
+/// The data of the SAM V and C values starts after their header
+const SAM_V_DATA: usize = 0xCC;
+const SAM_C_DATA: usize = 0x34;
+const ACB_DISABLED: u16 = 0x0001;
+
+/// The facts of a host, as written to the JSON report
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostInfo {
+    pub hostname: String,
+    pub domain: String,
+    pub domain_joined: bool,
+    pub os: OsInfo,
+    pub timezone: TimezoneInfo,
+    pub last_shutdown: String,
+    pub interfaces: Vec<NetworkInterface>,
+    pub machine_sid: String,
+    pub users: Vec<LocalUser>,
+    pub groups: Vec<LocalGroup>,
+    pub software: Vec<InstalledSoftware>,
+    pub last_logged_on_user: String,
+    pub last_logons: Vec<ProfileLogon>,
+    pub event_logs: EventLogInfo,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OsInfo {
+    pub product_name: String,
+    pub edition: String,
+    pub display_version: String,
+    pub build: String,
+    pub build_lab: String,
+    pub install_date: String,
+    pub registered_owner: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimezoneInfo {
+    pub name: String,
+    pub standard_name: String,
+    /// minutes to add to the local time to get UTC
+    pub bias: i32,
+    pub active_bias: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInterface {
+    pub guid: String,
+    pub name: String,
+    pub dhcp: bool,
+    pub ip_addresses: Vec<String>,
+    pub subnet_masks: Vec<String>,
+    pub gateways: Vec<String>,
+    pub dns_servers: Vec<String>,
+    pub dhcp_server: String,
+    pub domain: String,
+    pub lease_obtained: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalUser {
+    pub name: String,
+    pub full_name: String,
+    pub comment: String,
+    pub rid: u32,
+    pub sid: String,
+    pub created: String,
+    pub last_logon: String,
+    pub password_last_set: String,
+    pub last_failed_logon: String,
+    pub logon_count: u16,
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalGroup {
+    pub name: String,
+    pub comment: String,
+    pub rid: u32,
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstalledSoftware {
+    pub name: String,
+    pub version: String,
+    pub publisher: String,
+    pub install_date: String,
+    pub install_location: String,
+    pub last_written: String,
+}
+
+/// The facts from the processed event logs, the hostname of the last logon event and
+/// the retention of the Security log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventLogInfo {
+    pub hostname: String,
+    pub security_first: String,
+    pub security_last: String,
+    pub source: String,
+}
+
+/// The last time a user's profile was loaded, which is when they last logged on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileLogon {
+    pub user: String,
+    pub sid: String,
+    pub last_logon: String,
+}
+
+/// run the builtin hostinfo reporter, with the input as the SYSTEM hive, input_other as
+/// the SOFTWARE hive and the SAM hive from the artefact paths
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let open = |path: &Path, name: &str| match Hive::open(path) {
+        Ok(hive) => Some(hive),
+        Err(e) => {
+            file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read the {name} hive: {:#}", e));
+            None
+        }
+    };
+    let system = open(Path::new(&args.input), "SYSTEM");
+    let software = match args.has_input_other() {
+        true => open(Path::new(&args.input_other), "SOFTWARE"),
+        false => None,
+    };
+    let sam = args.data_path("sam").and_then(|p| open(&p, "SAM"));
+    if system.is_none() && software.is_none() && sam.is_none() {
+        bail!("None of the SYSTEM, SOFTWARE or SAM hives could be read");
+    }
+    let mut info = host_info(system.as_ref(), software.as_ref(), sam.as_ref());
+    info.event_logs = event_log_info(Path::new(&args.main_args.out_path));
+
+    file_ops::make_folders(&args.outfolder);
+    std::fs::write(args.outfolder.join(HOSTINFO_JSON), serde_json::to_string_pretty(&info)?)?;
+    std::fs::write(args.outfolder.join(HOSTINFO_MARKDOWN), to_markdown(&info))?;
+    Ok(format!(
+        "Host info written for {}: {} interfaces, {} users, {} groups, {} software",
+        info.hostname, info.interfaces.len(), info.users.len(), info.groups.len(), info.software.len()
+    ))
+}
+
+/// get the facts of the host from the hives that were found
+pub fn host_info(system: Option<&Hive>, software: Option<&Hive>, sam: Option<&Hive>) -> HostInfo {
+    let mut info = HostInfo::default();
+    if let Some(system) = system {
+        let cs = hive::current_control_set(system);
+        info.hostname = hive::computer_name(system).unwrap_or_default();
+        info.timezone = timezone(system, &cs);
+        info.last_shutdown = system.key(&format!(r"{cs}\Control\Windows"))
+            .and_then(|k| k.value("ShutdownTime"))
+            .filter(|v| v.data_type == REG_BINARY)
+            .and_then(|v| le_u64(&v.data, 0))
+            .map(|ft| common::fmt_dt(common::filetime_to_dt(ft)))
+            .unwrap_or_default();
+        info.interfaces = interfaces(system, &cs);
+        if let Some(tcpip) = system.key(&format!(r"{cs}\Services\Tcpip\Parameters")) {
+            info.domain = ["Domain", "NV Domain"].iter()
+                .map(|n| string_value(&tcpip, n))
+                .find(|d| !d.is_empty())
+                .unwrap_or_default();
+        }
+    }
+    if let Some(software) = software {
+        info.os = os_info(software);
+        if info.domain.is_empty() {
+            info.domain = software.key(&format!(r"{CURRENT_VERSION}\Winlogon"))
+                .map(|k| string_value(&k, "DefaultDomainName"))
+                .filter(|d| !d.eq_ignore_ascii_case(&info.hostname))
+                .unwrap_or_default();
+        }
+        info.software = installed_software(software);
+        info.last_logged_on_user = software.key(r"Microsoft\Windows\CurrentVersion\Authentication\LogonUI")
+            .map(|k| string_value(&k, "LastLoggedOnUser"))
+            .unwrap_or_default();
+        info.last_logons = profile_logons(software);
+    }
+    info.domain_joined = !info.domain.is_empty();
+    if let Some(sam) = sam {
+        info.machine_sid = machine_sid(sam).unwrap_or_default();
+        info.users = local_users(sam, &info.machine_sid);
+        let mut names: HashMap<String, String> = info.users.iter().map(|u| (u.sid.clone(), u.name.clone())).collect();
+        names.extend(info.last_logons.iter().map(|l| (l.sid.clone(), l.user.clone())));
+        info.groups = local_groups(sam, &names);
+    }
+    info
+}
+
+fn string_value(key: &Key, name: &str) -> String {
+    key.value(name).map(|v| v.as_string().trim().to_string()).unwrap_or_default()
+}
+
+/// get the values of a REG_SZ or REG_MULTI_SZ, which may be split by spaces or commas
+fn list_value(key: &Key, name: &str) -> Vec<String> {
+    let Some(value) = key.value(name) else { return Vec::new() };
+    let values = match value.data_type {
+        hive::REG_MULTI_SZ => value.as_multi_string(),
+        _ => vec![value.as_string()],
+    };
+    values.iter()
+        .flat_map(|v| v.split([' ', ',']).map(str::trim).map(String::from).collect::<Vec<_>>())
+        .filter(|v| !v.is_empty() && v != "0.0.0.0")
+        .collect()
+}
+
+fn os_info(software: &Hive) -> OsInfo {
+    let Some(key) = software.key(CURRENT_VERSION) else { return OsInfo::default() };
+    let build = string_value(&key, "CurrentBuildNumber");
+    let build = match key.value("UBR").and_then(|v| v.as_u32()) {
+        Some(ubr) if !build.is_empty() => format!("{build}.{ubr}"),
+        _ => build,
+    };
+    let mut product_name = string_value(&key, "ProductName");
+    // Windows 11 kept the ProductName of Windows 10, so it's known by its build
+    let major_build: u32 = build.split('.').next().and_then(|b| b.parse().ok()).unwrap_or(0);
+    if major_build >= 22000 && product_name.starts_with("Windows 10") {
+        product_name = product_name.replacen("Windows 10", "Windows 11", 1);
+    }
+    let install_date = match key.value("InstallDate").and_then(|v| v.as_u32()) {
+        Some(secs) if secs > 0 => common::unix_to_dt(secs as i64),
+        _ => key.value("InstallTime").and_then(|v| v.as_u64()).and_then(common::filetime_to_dt),
+    };
+    OsInfo {
+        product_name,
+        edition: string_value(&key, "EditionID"),
+        display_version: ["DisplayVersion", "ReleaseId", "CSDVersion"].iter()
+            .map(|n| string_value(&key, n))
+            .find(|v| !v.is_empty())
+            .unwrap_or_default(),
+        build,
+        build_lab: ["BuildLabEx", "BuildLab"].iter()
+            .map(|n| string_value(&key, n))
+            .find(|v| !v.is_empty())
+            .unwrap_or_default(),
+        install_date: common::fmt_dt(install_date),
+        registered_owner: string_value(&key, "RegisteredOwner"),
+    }
+}
+
+fn timezone(system: &Hive, cs: &str) -> TimezoneInfo {
+    let Some(key) = system.key(&format!(r"{cs}\Control\TimeZoneInformation")) else { return TimezoneInfo::default() };
+    let bias = |name: &str| key.value(name).and_then(|v| v.as_u32()).map(|b| b as i32);
+    TimezoneInfo {
+        name: string_value(&key, "TimeZoneKeyName"),
+        standard_name: string_value(&key, "StandardName"),
+        bias: bias("Bias").unwrap_or(0),
+        active_bias: bias("ActiveTimeBias"),
+    }
+}
+
+fn interfaces(system: &Hive, cs: &str) -> Vec<NetworkInterface> {
+    let Some(key) = system.key(&format!(r"{cs}\Services\Tcpip\Parameters\Interfaces")) else { return Vec::new() };
+    let names = system.key(&format!(r"{cs}\Control\Network\{NETWORK_CLASS}"));
+    let mut interfaces = Vec::new();
+    for adapter in key.subkeys() {
+        let guid = adapter.name();
+        let dhcp = adapter.value("EnableDHCP").and_then(|v| v.as_u32()) == Some(1);
+        let (ip_addresses, subnet_masks, gateways, dns_servers) = match dhcp {
+            true => (
+                list_value(&adapter, "DhcpIPAddress"),
+                list_value(&adapter, "DhcpSubnetMask"),
+                list_value(&adapter, "DhcpDefaultGateway"),
+                list_value(&adapter, "DhcpNameServer"),
+            ),
+            false => (
+                list_value(&adapter, "IPAddress"),
+                list_value(&adapter, "SubnetMask"),
+                list_value(&adapter, "DefaultGateway"),
+                list_value(&adapter, "NameServer"),
+            ),
+        };
+        // skip the adapters that were never configured
+        if ip_addresses.is_empty() && dns_servers.is_empty() {
+            continue;
+        }
+        let name = names
+            .and_then(|n| n.subkey(&guid))
+            .and_then(|k| k.subkey("Connection"))
+            .map(|k| string_value(&k, "Name"))
+            .unwrap_or_default();
+        let lease_obtained = adapter.value("LeaseObtainedTime")
+            .and_then(|v| v.as_u32())
+            .and_then(|secs| common::unix_to_dt(secs as i64));
+        interfaces.push(NetworkInterface {
+            name,
+            dhcp,
+            ip_addresses,
+            subnet_masks,
+            gateways,
+            dns_servers,
+            dhcp_server: string_value(&adapter, "DhcpServer"),
+            domain: ["Domain", "DhcpDomain"].iter()
+                .map(|n| string_value(&adapter, n))
+                .find(|d| !d.is_empty())
+                .unwrap_or_default(),
+            lease_obtained: common::fmt_dt(lease_obtained),
+            guid,
+        });
+    }
+    interfaces
+}
+
+fn installed_software(software: &Hive) -> Vec<InstalledSoftware> {
+    let mut installed = Vec::new();
+    for path in UNINSTALL_PATHS {
+        let Some(key) = software.key(path) else { continue };
+        for app in key.subkeys() {
+            let name = string_value(&app, "DisplayName");
+            if name.is_empty() {
+                continue;
+            }
+            installed.push(InstalledSoftware {
+                name,
+                version: string_value(&app, "DisplayVersion"),
+                publisher: string_value(&app, "Publisher"),
+                install_date: install_date(&string_value(&app, "InstallDate")),
+                install_location: string_value(&app, "InstallLocation"),
+                last_written: common::fmt_dt(app.last_written()),
+            });
+        }
+    }
+    installed.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()).then(a.version.cmp(&b.version)));
+    installed.dedup();
+    installed
+}
+
+/// format the yyyymmdd InstallDate of an application as yyyy-mm-dd, keeping other formats
+fn install_date(date: &str) -> String {
+    match chrono::NaiveDate::parse_from_str(date, "%Y%m%d") {
+        Ok(date) => date.format("%Y-%m-%d").to_string(),
+        Err(_) => date.to_string(),
+    }
+}
+
+/// the users' last logons, from the last load time of their profile
+fn profile_logons(software: &Hive) -> Vec<ProfileLogon> {
+    let users = hive::profile_users(software);
+    let Some(profiles) = software.key(&format!(r"{CURRENT_VERSION}\ProfileList")) else { return Vec::new() };
+    let mut logons: Vec<ProfileLogon> = profiles.subkeys().iter().map(|profile| {
+        let sid = profile.name();
+        let time = |name: &str| profile.value(name).and_then(|v| v.as_u32());
+        let last_logon = match (time("LocalProfileLoadTimeHigh"), time("LocalProfileLoadTimeLow")) {
+            (Some(high), Some(low)) => common::filetime_to_dt(((high as u64) << 32) | low as u64),
+            _ => None,
+        };
+        ProfileLogon {
+            user: users.get(&sid).cloned()
+                .or_else(|| common::well_known_sid(&sid).map(String::from))
+                .unwrap_or_default(),
+            last_logon: common::fmt_dt(last_logon),
+            sid,
+        }
+    }).collect();
+    logons.sort_by(|a, b| b.last_logon.cmp(&a.last_logon));
+    logons
+}
+
+/// the SID of the machine, from the last 12 bytes of the V value of the SAM's Account key
+pub fn machine_sid(sam: &Hive) -> Option<String> {
+    let value = sam.key(r"SAM\Domains\Account")?.value("V")?;
+    let data = value.data.get(value.data.len().checked_sub(12)?..)?;
+    Some(format!("S-1-5-21-{}-{}-{}", le_u32(data, 0)?, le_u32(data, 4)?, le_u32(data, 8)?))
+}
+
+/// read a string from the SAM V or C value, by the offset and length at the header offset
+fn sam_string(data: &[u8], header: usize, data_start: usize) -> String {
+    let (Some(offset), Some(len)) = (le_u32(data, header), le_u32(data, header + 4)) else { return String::new() };
+    let start = data_start + offset as usize;
+    data.get(start..start + len as usize).map(common::utf16le_to_string).unwrap_or_default()
+}
+
+/// read the local users of the SAM, with their logon times from the F value
+pub fn local_users(sam: &Hive, machine_sid: &str) -> Vec<LocalUser> {
+    let Some(users_key) = sam.key(r"SAM\Domains\Account\Users") else { return Vec::new() };
+    let mut users = Vec::new();
+    for name_key in users_key.subkey("Names").map(|k| k.subkeys()).unwrap_or_default() {
+        // the RID of the user is the type of the default value of its name key
+        let Some(rid) = name_key.value("").map(|v| v.data_type) else { continue };
+        let mut user = LocalUser {
+            name: name_key.name(),
+            rid,
+            sid: match machine_sid.is_empty() {
+                true => String::new(),
+                false => format!("{machine_sid}-{rid}"),
+            },
+            created: common::fmt_dt(name_key.last_written()),
+            ..Default::default()
+        };
+        if let Some(key) = users_key.subkey(&format!("{rid:08X}")) {
+            if let Some(f) = key.value("F") {
+                let time = |offset| le_u64(&f.data, offset).and_then(common::filetime_to_dt);
+                user.last_logon = common::fmt_dt(time(0x08));
+                user.password_last_set = common::fmt_dt(time(0x18));
+                user.last_failed_logon = common::fmt_dt(time(0x28));
+                user.disabled = le_u16(&f.data, 0x38).is_some_and(|acb| acb & ACB_DISABLED != 0);
+                user.logon_count = le_u16(&f.data, 0x42).unwrap_or(0);
+            }
+            if let Some(v) = key.value("V") {
+                user.full_name = sam_string(&v.data, 0x18, SAM_V_DATA);
+                user.comment = sam_string(&v.data, 0x24, SAM_V_DATA);
+            }
+        }
+        users.push(user);
+    }
+    users.sort_by_key(|u| u.rid);
+    users
+}
+
+/// read the builtin groups of the SAM, such as Administrators, with their members'
+/// SIDs resolved to names where they are known
+pub fn local_groups(sam: &Hive, names: &HashMap<String, String>) -> Vec<LocalGroup> {
+    let Some(aliases) = sam.key(r"SAM\Domains\Builtin\Aliases") else { return Vec::new() };
+    let mut groups = Vec::new();
+    for alias in aliases.subkeys() {
+        let Ok(rid) = u32::from_str_radix(&alias.name(), 16) else { continue };
+        let Some(c) = alias.value("C") else { continue };
+        let data = &c.data;
+        let mut members = Vec::new();
+        let count = le_u32(data, 0x30).unwrap_or(0) as usize;
+        let mut offset = SAM_C_DATA + le_u32(data, 0x28).unwrap_or(0) as usize;
+        for _ in 0..count.min(1024) {
+            let Some(sid) = data.get(offset..).and_then(common::sid_to_string) else { break };
+            offset += 8 + 4 * data[offset + 1] as usize;
+            members.push(match names.get(&sid).filter(|n| !n.is_empty()) {
+                Some(name) => format!("{name} ({sid})"),
+                None => sid,
+            });
+        }
+        groups.push(LocalGroup {
+            name: sam_string(data, 0x10, SAM_C_DATA),
+            comment: sam_string(data, 0x1C, SAM_C_DATA),
+            rid,
+            members,
+        });
+    }
+    groups.sort_by_key(|g| g.rid);
+    groups
+}
+
+/// get the hostname and Security log retention from the first of the processed event
+/// logs found in the out_path
+pub fn event_log_info(out_path: &Path) -> EventLogInfo {
+    for (path, [channel, time, event_id, computer], security) in EVENT_LOG_SOURCES {
+        let Some(file) = common::join_case_insensitive(out_path, path) else { continue };
+        let source = SourceMapping { format: "csv".to_string(), ..Default::default() };
+        let mut info = EventLogInfo { source: path.to_string(), ..Default::default() };
+        let (mut first, mut last) = (None, None);
+        let read = source.read_rows(&file, |row| {
+            if row.get(channel) != Some(security) {
+                return;
+            }
+            if let Some(dt) = row.get(time).and_then(|t| source.parse_time(t)) {
+                first = first.min(Some(dt)).or(Some(dt));
+                last = last.max(Some(dt));
+            }
+            if row.get(event_id) == Some("4624") {
+                if let Some(name) = row.get(computer) {
+                    info.hostname = name.split('.').next().unwrap_or(name).to_string();
+                }
+            }
+        });
+        if read.is_ok() && last.is_some() {
+            info.security_first = common::fmt_dt(first);
+            info.security_last = common::fmt_dt(last);
+            return info;
+        }
+    }
+    EventLogInfo::default()
+}
+
+/// escape the pipes of a value in a Markdown table
+fn cell(value: &str) -> String {
+    value.replace('|', r"\|")
+}
+
+/// write the host info as a Markdown report
+pub fn to_markdown(info: &HostInfo) -> String {
+    let mut md = String::new();
+    let _ = writeln!(md, "# WISKESS Host Information: {}\n", info.hostname);
+    let rows = [
+        ("Hostname", info.hostname.clone()),
+        ("Domain", if info.domain_joined { info.domain.clone() } else { "Not joined (workgroup)".to_string() }),
+        ("Windows Version (Product Name)", info.os.product_name.clone()),
+        ("Edition", info.os.edition.clone()),
+        ("Windows Version (Display Version)", info.os.display_version.clone()),
+        ("Build", info.os.build.clone()),
+        ("Build Lab", info.os.build_lab.clone()),
+        ("Install Date", info.os.install_date.clone()),
+        ("Registered Owner", info.os.registered_owner.clone()),
+        ("Timezone", info.timezone.name.clone()),
+        ("Bias (minutes)", info.timezone.bias.to_string()),
+        ("ActiveTimeBias (minutes)", info.timezone.active_bias.map(|b| b.to_string()).unwrap_or_default()),
+        ("Shutdown Time", info.last_shutdown.clone()),
+        ("Last Logged On User", info.last_logged_on_user.clone()),
+        ("Hostname Event Logs", info.event_logs.hostname.clone()),
+        ("Security Log Retention", match info.event_logs.security_last.is_empty() {
+            true => String::new(),
+            false => format!("From: {} to: {}", info.event_logs.security_first, info.event_logs.security_last),
+        }),
+        ("Machine SID", info.machine_sid.clone()),
+    ];
+    let _ = writeln!(md, "| Property | Value |\n| --- | --- |");
+    for (name, value) in rows {
+        let _ = writeln!(md, "| {name} | {} |", cell(&value));
+    }
+
+    let _ = writeln!(md, "\n## Network Interfaces\n\n| Name | GUID | DHCP | IP Addresses | Subnet Masks | Gateways | DNS Servers | Domain | Lease Obtained |\n| --- | --- | --- | --- | --- | --- | --- | --- | --- |");
+    for i in &info.interfaces {
+        let _ = writeln!(md, "| {} | {} | {} | {} | {} | {} | {} | {} | {} |",
+            cell(&i.name), i.guid, i.dhcp, i.ip_addresses.join(", "), i.subnet_masks.join(", "),
+            i.gateways.join(", "), i.dns_servers.join(", "), cell(&i.domain), i.lease_obtained);
+    }
+
+    let _ = writeln!(md, "\n## Local Users\n\n| Name | Full Name | RID | Created | Last Logon | Password Last Set | Logon Count | Disabled |\n| --- | --- | --- | --- | --- | --- | --- | --- |");
+    for u in &info.users {
+        let _ = writeln!(md, "| {} | {} | {} | {} | {} | {} | {} | {} |",
+            cell(&u.name), cell(&u.full_name), u.rid, u.created, u.last_logon, u.password_last_set, u.logon_count, u.disabled);
+    }
+
+    let _ = writeln!(md, "\n## Local Groups\n\n| Name | Members |\n| --- | --- |");
+    for g in info.groups.iter().filter(|g| !g.members.is_empty()) {
+        let _ = writeln!(md, "| {} | {} |", cell(&g.name), cell(&g.members.join(", ")));
+    }
+
+    let _ = writeln!(md, "\n## Last Logons\n\n| User | SID | Last Logon |\n| --- | --- | --- |");
+    for l in &info.last_logons {
+        let _ = writeln!(md, "| {} | {} | {} |", cell(&l.user), l.sid, l.last_logon);
+    }
+
+    let _ = writeln!(md, "\n## Installed Software\n\n| Name | Version | Publisher | Install Date | Install Location |\n| --- | --- | --- | --- | --- |");
+    for s in &info.software {
+        let _ = writeln!(md, "| {} | {} | {} | {} | {} |",
+            cell(&s.name), cell(&s.version), cell(&s.publisher), s.install_date, cell(&s.install_location));
+    }
+    md
+}
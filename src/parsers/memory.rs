@@ -0,0 +1,695 @@
+/*
+Builtin carver of the strings in memory files: pagefile.sys, swapfile.sys and
+hiberfil.sys. ASCII and UTF-16LE strings above a minimum length are carved with their
+offsets, then the URLs, IPs, domains, email addresses and command lines in them are
+written to their own CSVs, and the IOCs of the case are matched over the strings.
+
+The Xpress blocks of a hibernation file, as written by Windows 7 and earlier, are
+decompressed with the plain LZ77 of MS-XCA and carved separately, with the rest of the
+file carved as raw bytes. The Huffman compressed sets of later hibernation files are
+only carved as raw bytes, with a warning logged for a hibernation file without Xpress
+blocks.
+*/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use aho_corasick::AhoCorasick;
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use super::{common, xpress};
+use crate::iocs::allowlist::Allowlist;
+use crate::iocs::loader::{self, IocType};
+use crate::iocs::scanner::{Encoding, IocHit, IocScanner, Scope, CHUNK_SIZE};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::timeline::builder::{arg_value, split_args};
+
+/// The memory files carved when the input is a folder, i.e. the root of the data source
+pub const MEMORY_FILES: [&str; 3] = ["pagefile.sys", "swapfile.sys", "hiberfil.sys"];
+/// The characters of the shortest string carved by default
+const DEFAULT_MIN_LENGTH: usize = 8;
+/// Longer strings are split into strings of this many characters
+const MAX_LENGTH: usize = 4096;
+/// The hits of an IOC written for each memory file by default
+const DEFAULT_MAX_HITS: usize = 100;
+/// The characters either side of an IOC hit kept as its context
+const CONTEXT_CHARS: usize = 64;
+/// The file of the IOC hits in the IOC findings folder, for the case IOC report
+pub const IOC_FILE: &str = "ioc_memory_strings.csv";
+/// The signature of an Xpress block in a hibernation file
+const XPRESS_SIGNATURE: &[u8] = b"\x81\x81xpress";
+/// The size of the header of an Xpress block, before its compressed data
+const XPRESS_HEADER_SIZE: u64 = 0x20;
+const PAGE_SIZE: usize = 4096;
+const STRINGS_HEADER: [&str; 5] = ["File", "Offset", "XpressBlock", "Encoding", "Value"];
+/// The header of the IOC hits, being that of the IOC findings with the Xpress block
+const IOC_HEADER: [&str; 12] = ["Ioc", "Type", "List", "Scope", "Path", "Offset", "XpressBlock", "Encoding", "Context", "Event", "Tags", "Confidence"];
+
+/// The kinds of values extracted from the carved strings, with the suffix of their CSV
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ValueKind {
+    Url,
+    Ip,
+    Domain,
+    Email,
+    Command,
+}
+
+impl ValueKind {
+    pub const ALL: [ValueKind; 5] = [ValueKind::Url, ValueKind::Ip, ValueKind::Domain, ValueKind::Email, ValueKind::Command];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueKind::Url => "urls",
+            ValueKind::Ip => "ips",
+            ValueKind::Domain => "domains",
+            ValueKind::Email => "emails",
+            ValueKind::Command => "commands",
+        }
+    }
+}
+
+/// The top level domains of the domains extracted, as most dotted words in memory are
+/// file names or code rather than domains
+const TLDS: &[&str] = &[
+    "com", "net", "org", "info", "biz", "io", "co", "us", "uk", "de", "ru", "cn", "jp", "fr", "nl", "eu", "gov",
+    "edu", "mil", "int", "xyz", "top", "online", "site", "club", "app", "dev", "cloud", "me", "tv", "cc", "ws",
+    "su", "in", "br", "au", "ca", "ch", "it", "es", "se", "no", "pl", "ua", "ir", "kz", "kp", "onion", "live",
+    "tech", "store", "shop", "pro", "link", "click", "pw", "tk", "ml", "ga", "cf", "gq", "local", "corp", "lan",
+];
+
+/// A string carved from a memory file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarvedString {
+    /// the offset of the string in the file, or in the decompressed data of its block
+    pub offset: u64,
+    /// the offset of the Xpress block in the file, for strings of decompressed data
+    pub block: Option<u64>,
+    pub encoding: Encoding,
+    pub text: String,
+}
+
+impl CarvedString {
+    /// the offset of a character of the string
+    fn char_offset(&self, index: usize) -> u64 {
+        match self.encoding {
+            Encoding::Utf16le => self.offset + index as u64 * 2,
+            _ => self.offset + index as u64,
+        }
+    }
+}
+
+/// A URL, IP, domain, email address or command line in a carved string
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarvedValue {
+    pub kind: ValueKind,
+    pub offset: u64,
+    pub value: String,
+}
+
+/// The row of a carved string or value
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct StringRow<'a> {
+    file: &'a str,
+    offset: u64,
+    xpress_block: Option<u64>,
+    encoding: Encoding,
+    value: &'a str,
+}
+
+impl<'a> StringRow<'a> {
+    fn new(file: &'a str, carved: &CarvedString, offset: u64, value: &'a str) -> Self {
+        StringRow { file, offset, xpress_block: carved.block, encoding: carved.encoding, value }
+    }
+}
+
+/// The row of an IOC hit, with the offset in the decompressed data of its Xpress block for
+/// hits in a block, as the strings are
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct IocHitRow<'a> {
+    ioc: &'a str,
+    #[serde(rename = "Type")]
+    ioc_type: IocType,
+    list: &'a str,
+    scope: Scope,
+    path: &'a str,
+    offset: u64,
+    xpress_block: Option<u64>,
+    encoding: Encoding,
+    context: &'a str,
+    event: &'a str,
+    tags: &'a str,
+    confidence: Option<u8>,
+}
+
+impl<'a> IocHitRow<'a> {
+    fn new(hit: &'a IocHit, xpress_block: Option<u64>) -> Self {
+        IocHitRow {
+            ioc: &hit.ioc,
+            ioc_type: hit.ioc_type,
+            list: &hit.list,
+            scope: hit.scope,
+            path: &hit.path,
+            offset: hit.offset,
+            xpress_block,
+            encoding: hit.encoding,
+            context: &hit.context,
+            event: &hit.event,
+            tags: &hit.tags,
+            confidence: hit.confidence,
+        }
+    }
+}
+
+/// A string being carved, from the offset of its first character
+#[derive(Debug, Default)]
+struct Run {
+    start: u64,
+    text: String,
+}
+
+/// Carves the printable ASCII and UTF-16LE strings from bytes fed in order, with the
+/// UTF-16LE strings found at even and odd offsets
+pub struct StringCarver {
+    min_length: usize,
+    block: Option<u64>,
+    position: u64,
+    ascii: Run,
+    utf16: [Run; 2],
+    /// the low byte of the next UTF-16LE character, by the parity of its offset
+    low: [Option<u8>; 2],
+}
+
+fn printable(b: u8) -> bool {
+    (0x20..0x7f).contains(&b) || b == b'\t'
+}
+
+impl StringCarver {
+    pub fn new(min_length: usize, block: Option<u64>, start: u64) -> Self {
+        StringCarver {
+            min_length,
+            block,
+            position: start,
+            ascii: Run::default(),
+            utf16: [Run::default(), Run::default()],
+            low: [None, None],
+        }
+    }
+
+    fn end_run(run: &mut Run, encoding: Encoding, min_length: usize, block: Option<u64>, each: &mut impl FnMut(CarvedString)) {
+        if run.text.len() >= min_length {
+            each(CarvedString { offset: run.start, block, encoding, text: std::mem::take(&mut run.text) });
+        }
+        run.text.clear();
+    }
+
+    fn push(run: &mut Run, offset: u64, c: u8, encoding: Encoding, min_length: usize, block: Option<u64>, each: &mut impl FnMut(CarvedString)) {
+        if run.text.is_empty() {
+            run.start = offset;
+        }
+        run.text.push(c as char);
+        if run.text.len() >= MAX_LENGTH {
+            Self::end_run(run, encoding, min_length, block, each);
+        }
+    }
+
+    /// carve the next bytes, calling `each` with the strings that end in them
+    pub fn feed(&mut self, data: &[u8], mut each: impl FnMut(CarvedString)) {
+        let (min_length, block) = (self.min_length, self.block);
+        for &b in data {
+            let pos = self.position;
+            match printable(b) {
+                true => Self::push(&mut self.ascii, pos, b, Encoding::Ascii, min_length, block, &mut each),
+                false => Self::end_run(&mut self.ascii, Encoding::Ascii, min_length, block, &mut each),
+            }
+            // this byte is the high byte of the character starting at the previous byte
+            let high = ((pos + 1) % 2) as usize;
+            if let Some(low) = self.low[high].take() {
+                match b {
+                    0 => Self::push(&mut self.utf16[high], pos - 1, low, Encoding::Utf16le, min_length, block, &mut each),
+                    _ => Self::end_run(&mut self.utf16[high], Encoding::Utf16le, min_length, block, &mut each),
+                }
+            }
+            // and the low byte of the character starting here
+            let low = (pos % 2) as usize;
+            match printable(b) {
+                true => self.low[low] = Some(b),
+                false => Self::end_run(&mut self.utf16[low], Encoding::Utf16le, min_length, block, &mut each),
+            }
+            self.position += 1;
+        }
+    }
+
+    /// end the strings being carved, such as at the end of the data or a gap in it
+    pub fn finish(&mut self, mut each: impl FnMut(CarvedString)) {
+        let (min_length, block) = (self.min_length, self.block);
+        Self::end_run(&mut self.ascii, Encoding::Ascii, min_length, block, &mut each);
+        for run in &mut self.utf16 {
+            Self::end_run(run, Encoding::Utf16le, min_length, block, &mut each);
+        }
+        self.low = [None, None];
+    }
+
+    /// carve from a new offset, ending the strings of the data before it
+    pub fn seek(&mut self, position: u64, each: impl FnMut(CarvedString)) {
+        self.finish(each);
+        self.position = position;
+    }
+}
+
+/// check an IPv4 address isn't part of a longer dotted number, such as a version
+fn is_separate_ip(text: &str, start: usize, end: usize) -> bool {
+    let bytes = text.as_bytes();
+    let dotted = |a: Option<&u8>, b: Option<&u8>| a == Some(&b'.') && b.is_some_and(|c| c.is_ascii_digit());
+    let before = start.checked_sub(1).and_then(|i| bytes.get(i));
+    let before_2 = start.checked_sub(2).and_then(|i| bytes.get(i));
+    !dotted(before, before_2) && !dotted(bytes.get(end), bytes.get(end + 1))
+}
+
+/// Extracts the URLs, IPs, domains, email addresses and command lines of carved strings
+pub struct ValueExtractor {
+    url: Regex,
+    email: Regex,
+    ipv4: Regex,
+    domain: Regex,
+    /// a program commonly run with arguments, followed by a switch or argument
+    command: Regex,
+}
+
+impl ValueExtractor {
+    pub fn new() -> Result<Self> {
+        let regex = |pattern: &str| RegexBuilder::new(pattern).case_insensitive(true).build();
+        Ok(ValueExtractor {
+            url: regex(r#"\b(?:https?|ftps?|wss?|smb)://[a-z0-9\-._~%:@\[\]]+(?:/[^\s"'<>`{}|\\^]*)?"#)?,
+            email: regex(r"\b[a-z0-9._%+\-]{1,64}@(?:[a-z0-9\-]{1,63}\.)+[a-z]{2,24}\b")?,
+            ipv4: regex(r"\b\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}\b")?,
+            domain: regex(r"\b(?:[a-z0-9](?:[a-z0-9\-]{0,61}[a-z0-9])?\.)+([a-z]{2,24})\b")?,
+            command: regex(concat!(
+                r"(?:^|[\s\\/\x22])(?:cmd|powershell|pwsh|wscript|cscript|mshta|rundll32|regsvr32|certutil|bitsadmin|wmic|schtasks|",
+                r"net1?|sc|reg|vssadmin|wevtutil|msiexec|bcdedit|netsh|bash|sh|curl|wget|python[23]?|perl|nc|ncat)",
+                r"(?:\.exe)?\x22?\s+(?:[-/]{1,2}[a-z]|[a-z]+\s)",
+            ))?,
+        })
+    }
+
+    /// extract the values of a carved string. A command line is the rest of the string,
+    /// from the path of the program run
+    pub fn extract(&self, carved: &CarvedString) -> Vec<CarvedValue> {
+        let text = &carved.text;
+        let mut values = Vec::new();
+        let mut add = |kind: ValueKind, start: usize, value: &str| {
+            values.push(CarvedValue { kind, offset: carved.char_offset(start), value: value.to_string() });
+        };
+        for m in self.url.find_iter(text) {
+            add(ValueKind::Url, m.start(), m.as_str().trim_end_matches(['.', ',', ')', ';']));
+        }
+        for m in self.email.find_iter(text) {
+            add(ValueKind::Email, m.start(), m.as_str());
+        }
+        for m in self.ipv4.find_iter(text) {
+            if Ipv4Addr::from_str(m.as_str()).is_ok() && is_separate_ip(text, m.start(), m.end()) {
+                add(ValueKind::Ip, m.start(), m.as_str());
+            }
+        }
+        for caps in self.domain.captures_iter(text) {
+            let (Some(m), Some(tld)) = (caps.get(0), caps.get(1)) else { continue };
+            let in_email = m.start() > 0 && text.as_bytes()[m.start() - 1] == b'@';
+            if !in_email && TLDS.contains(&tld.as_str().to_lowercase().as_str()) {
+                add(ValueKind::Domain, m.start(), m.as_str());
+            }
+        }
+        if let Some(m) = self.command.find(text) {
+            let program = m.start() + m.as_str().len() - m.as_str().trim_start_matches([' ', '\t', '"', '\\', '/']).len();
+            let start = text[..program].rfind([' ', '\t', '"']).map(|i| i + 1).unwrap_or(0);
+            add(ValueKind::Command, start, text[start..].trim());
+        }
+        values
+    }
+}
+
+/// An Xpress block of a hibernation file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XpressBlock {
+    pub offset: u64,
+    pub pages: usize,
+    pub compressed_size: u64,
+}
+
+impl XpressBlock {
+    /// parse the header of a block, with the number of pages less one in the low 10 bits
+    /// of the dword after the signature and the compressed size less one above them
+    pub fn parse(header: &[u8], offset: u64) -> Option<Self> {
+        if !header.starts_with(XPRESS_SIGNATURE) {
+            return None;
+        }
+        let info = common::le_u32(header, XPRESS_SIGNATURE.len())?;
+        let size = ((info >> 10) as u64) + 1;
+        Some(XpressBlock {
+            offset,
+            pages: (info & 0x3ff) as usize + 1,
+            // the blocks are 8 byte aligned
+            compressed_size: (size + 7) & !7,
+        })
+    }
+
+    /// the offset after the block's data
+    pub fn end(&self) -> u64 {
+        self.offset + XPRESS_HEADER_SIZE + self.compressed_size
+    }
+
+    /// decompress the pages of the block, which are stored when they didn't compress
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let size = self.pages * PAGE_SIZE;
+        match data.len() >= size {
+            true => Ok(data[..size].to_vec()),
+            false => xpress::lz77_decompress(data, size),
+        }
+    }
+}
+
+/// find the Xpress blocks of a hibernation file, skipping those that overlap the block
+/// before
+pub fn find_xpress_blocks(path: &Path) -> Result<Vec<XpressBlock>> {
+    let searcher = AhoCorasick::new([XPRESS_SIGNATURE])?;
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+    let mut header_reader = File::open(path)?;
+    let mut blocks: Vec<XpressBlock> = Vec::new();
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut buffer_start = 0u64;
+    loop {
+        let read = file.by_ref().take(CHUNK_SIZE as u64).read_to_end(&mut buffer)?;
+        for m in searcher.find_iter(&buffer) {
+            let offset = buffer_start + m.start() as u64;
+            if blocks.last().is_some_and(|b| offset < b.end()) {
+                continue;
+            }
+            let mut header = [0u8; XPRESS_HEADER_SIZE as usize];
+            header_reader.seek(SeekFrom::Start(offset))?;
+            if header_reader.read_exact(&mut header).is_err() {
+                continue;
+            }
+            if let Some(block) = XpressBlock::parse(&header, offset).filter(|b| b.end() <= length) {
+                blocks.push(block);
+            }
+        }
+        if read < CHUNK_SIZE {
+            return Ok(blocks);
+        }
+        // keep the bytes a signature could start in
+        let drop = buffer.len() - (XPRESS_SIGNATURE.len() - 1);
+        buffer.drain(..drop);
+        buffer_start += drop as u64;
+    }
+}
+
+/// The Xpress blocks of a carved memory file
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CarvedBlocks {
+    pub found: usize,
+    /// the blocks that couldn't be decompressed
+    pub failed: usize,
+}
+
+/// check if the file is a hibernation file
+pub fn is_hiberfil(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n.to_string_lossy().eq_ignore_ascii_case("hiberfil.sys"))
+}
+
+/// carve the strings of a memory file. The Xpress blocks of a hibernation file are
+/// decompressed and carved on their own, returning the blocks found and those that
+/// couldn't be decompressed
+pub fn carve_file(path: &Path, min_length: usize, mut each: impl FnMut(CarvedString)) -> Result<CarvedBlocks> {
+    let blocks = match is_hiberfil(path) {
+        true => find_xpress_blocks(path)?,
+        false => Vec::new(),
+    };
+    let mut file = File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+    let length = file.metadata()?.len();
+    let mut carver = StringCarver::new(min_length, None, 0);
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut carve_raw = |file: &mut File, carver: &mut StringCarver, start: u64, end: u64, each: &mut dyn FnMut(CarvedString)| -> Result<()> {
+        file.seek(SeekFrom::Start(start))?;
+        carver.seek(start, &mut *each);
+        let mut reader = BufReader::new(file.take(end - start));
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            carver.feed(&buffer[..read], &mut *each);
+        }
+    };
+    let mut position = 0;
+    let mut failed = 0;
+    for block in &blocks {
+        carve_raw(&mut file, &mut carver, position, block.offset, &mut each)?;
+        file.seek(SeekFrom::Start(block.offset + XPRESS_HEADER_SIZE))?;
+        let mut data = vec![0u8; block.compressed_size as usize];
+        file.read_exact(&mut data)?;
+        match block.decompress(&data) {
+            Ok(pages) => {
+                let mut block_carver = StringCarver::new(min_length, Some(block.offset), 0);
+                block_carver.feed(&pages, &mut each);
+                block_carver.finish(&mut each);
+            }
+            Err(_) => failed += 1,
+        }
+        position = block.end();
+    }
+    carve_raw(&mut file, &mut carver, position, length, &mut each)?;
+    carver.finish(&mut each);
+    Ok(CarvedBlocks { found: blocks.len(), failed })
+}
+
+/// the memory files of the input, being the input file or those at the root of the folder
+pub fn find_memory_files(input: &Path) -> Vec<PathBuf> {
+    if input.is_file() {
+        return vec![input.to_path_buf()];
+    }
+    MEMORY_FILES.iter()
+        .filter_map(|name| common::join_case_insensitive(input, name))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+/// Matches the IOCs over the carved strings of a memory file, in batches of strings
+struct StringIocs<'a> {
+    scanner: &'a IocScanner,
+    allowlist: &'a Allowlist,
+    path: String,
+    max_hits: usize,
+    batch: Vec<CarvedString>,
+    batch_bytes: usize,
+    counts: HashMap<usize, usize>,
+    /// the hits, with the Xpress block of those in decompressed data
+    hits: Vec<(IocHit, Option<u64>)>,
+    suppressed: usize,
+}
+
+impl StringIocs<'_> {
+    fn push(&mut self, carved: &CarvedString) -> Result<()> {
+        self.batch_bytes += carved.text.len() + 1;
+        self.batch.push(carved.clone());
+        if self.batch_bytes >= CHUNK_SIZE {
+            self.scan()?;
+        }
+        Ok(())
+    }
+
+    /// scan the strings joined by new lines, with each hit found in its string
+    fn scan(&mut self) -> Result<()> {
+        let mut starts = Vec::with_capacity(self.batch.len());
+        let mut joined = Vec::with_capacity(self.batch_bytes);
+        for carved in &self.batch {
+            starts.push(joined.len());
+            joined.extend_from_slice(carved.text.as_bytes());
+            joined.push(b'\n');
+        }
+        let mut found = Vec::new();
+        self.scanner.scan_reader(joined.as_slice(), |ioc, offset, _, _| found.push((ioc, offset as usize)))?;
+        for (ioc, offset) in found {
+            let index = starts.partition_point(|s| *s <= offset) - 1;
+            let carved = &self.batch[index];
+            let at = offset - starts[index];
+            let value = &self.scanner.iocs[ioc];
+            if self.allowlist.allows_hit(&value.value, &self.path) {
+                self.suppressed += 1;
+                continue;
+            }
+            let count = self.counts.entry(ioc).or_insert(0);
+            *count += 1;
+            if *count > self.max_hits {
+                continue;
+            }
+            let start = at.saturating_sub(CONTEXT_CHARS);
+            let end = (at + CONTEXT_CHARS * 2).min(carved.text.len());
+            let hit = IocHit {
+                ioc: value.value.clone(),
+                ioc_type: value.ioc_type,
+                list: value.list.clone(),
+                scope: Scope::DataSource,
+                path: self.path.clone(),
+                offset: carved.char_offset(at),
+                encoding: carved.encoding,
+                context: carved.text[start..end].trim().to_string(),
+                event: value.event.clone(),
+                tags: value.tags_text(),
+                confidence: value.confidence,
+            };
+            self.hits.push((hit, carved.block));
+        }
+        self.batch.clear();
+        self.batch_bytes = 0;
+        Ok(())
+    }
+}
+
+/// run the builtin carver of the memory files, with the input as a memory file or the
+/// root of the data source. The strings are written to the outfile, and the values in
+/// them to `<stem>_urls.csv`, `_ips.csv`, `_domains.csv`, `_emails.csv` and
+/// `_commands.csv` beside it. The IOCs of the case's IOC file, or `--ioc-file <path>`,
+/// are matched over the strings, with the hits written to IOC_Findings. Without memory
+/// files a note is logged and nothing is carved.
+/// `--min-length <n>` sets the characters of the shortest string (8 by default),
+/// `--values-only` skips writing every string, `--max-hits <n>` sets the hits of each
+/// IOC written for a file (100 by default) and `--allowlist <file>` sets the allowlist
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let flags = split_args(&args.args);
+    let values_only = flags.iter().any(|f| f == "--values-only");
+    let min_length = match arg_value(&args.args, "--min-length") {
+        Some(n) => n.parse::<usize>().with_context(|| format!("Invalid --min-length {n}"))?.max(1),
+        None => DEFAULT_MIN_LENGTH,
+    };
+    let max_hits = match arg_value(&args.args, "--max-hits") {
+        Some(n) => n.parse().with_context(|| format!("Invalid --max-hits {n}"))?,
+        None => DEFAULT_MAX_HITS,
+    };
+    let files = find_memory_files(Path::new(&args.input));
+    if files.is_empty() {
+        let msg = format!("No pagefile.sys, swapfile.sys or hiberfil.sys found in {}, so no memory strings were carved", args.input);
+        file_ops::log_msg(&args.main_args.out_log, format!("[ ] {msg}"));
+        return Ok(msg);
+    }
+    // the strings are still carved without IOCs to match
+    let ioc_file = arg_value(&args.args, "--ioc-file").unwrap_or_else(|| args.main_args.ioc_file.clone());
+    let scanner = match loader::load_iocs(Path::new(&ioc_file), &args.main_args.out_log).and_then(|iocs| IocScanner::new(iocs, false)) {
+        Ok(scanner) => Some(scanner),
+        Err(e) => {
+            file_ops::log_msg(&args.main_args.out_log, format!("[!] Memory strings are carved without IOCs: {e:#}"));
+            None
+        }
+    };
+    let allowlist = Allowlist::load(args)?;
+    let extractor = ValueExtractor::new()?;
+
+    let out_file = args.outfolder.join(&args.outfile);
+    let stem = out_file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "strings".to_string());
+    let mut strings_writer = match values_only {
+        true => None,
+        false => Some(common::csv_writer(&out_file)?),
+    };
+    let mut value_writers = HashMap::new();
+    for kind in ValueKind::ALL {
+        value_writers.insert(kind, common::csv_writer(&args.outfolder.join(format!("{stem}_{}.csv", kind.name())))?);
+    }
+
+    let mut strings = 0;
+    let mut values: HashMap<ValueKind, usize> = HashMap::new();
+    let mut failed_blocks = 0;
+    let mut hits = Vec::new();
+    let mut suppressed = 0;
+    for file in &files {
+        let path = file.to_string_lossy().to_string();
+        let mut iocs = scanner.as_ref().map(|scanner| StringIocs {
+            scanner,
+            allowlist: &allowlist,
+            path: path.clone(),
+            max_hits,
+            batch: Vec::new(),
+            batch_bytes: 0,
+            counts: HashMap::new(),
+            hits: Vec::new(),
+            suppressed: 0,
+        });
+        let mut error = None;
+        let carved = carve_file(file, min_length, |carved| {
+            if error.is_some() {
+                return;
+            }
+            let result = (|| -> Result<()> {
+                if let Some(writer) = &mut strings_writer {
+                    writer.serialize(StringRow::new(&path, &carved, carved.offset, &carved.text))?;
+                }
+                for value in extractor.extract(&carved) {
+                    *values.entry(value.kind).or_insert(0) += 1;
+                    if let Some(writer) = value_writers.get_mut(&value.kind) {
+                        writer.serialize(StringRow::new(&path, &carved, value.offset, &value.value))?;
+                    }
+                }
+                if let Some(iocs) = &mut iocs {
+                    iocs.push(&carved)?;
+                }
+                Ok(())
+            })();
+            strings += 1;
+            error = result.err();
+        });
+        match (carved, error) {
+            (Ok(blocks), None) => {
+                failed_blocks += blocks.failed;
+                if blocks.found == 0 && is_hiberfil(file) {
+                    file_ops::log_msg(&args.main_args.out_log, format!(
+                        "[!] No Xpress blocks found in {path}, so it is carved as raw bytes. The Huffman compressed sets \
+                        of the hibernation files of Windows 8 and later aren't decompressed"
+                    ));
+                }
+            }
+            (Err(e), _) | (_, Some(e)) => return Err(e.context(format!("Unable to carve {path}"))),
+        }
+        if let Some(mut iocs) = iocs {
+            iocs.scan()?;
+            suppressed += iocs.suppressed;
+            hits.extend(iocs.hits);
+        }
+    }
+    // the header is written with the first row, so the empty CSVs are given it
+    if let Some(writer) = &mut strings_writer {
+        if strings == 0 {
+            writer.write_record(STRINGS_HEADER)?;
+        }
+        writer.flush()?;
+    }
+    for (kind, writer) in value_writers.iter_mut() {
+        if !values.contains_key(kind) {
+            writer.write_record(STRINGS_HEADER)?;
+        }
+        writer.flush()?;
+    }
+
+    if scanner.is_some() {
+        let mut writer = common::csv_writer(&Path::new(&args.main_args.out_path).join("IOC_Findings").join(IOC_FILE))?;
+        if hits.is_empty() {
+            writer.write_record(IOC_HEADER)?;
+        }
+        for (hit, block) in &hits {
+            writer.serialize(IocHitRow::new(hit, *block))?;
+        }
+        writer.flush()?;
+    }
+    let count = |kind: ValueKind| values.get(&kind).copied().unwrap_or(0);
+    Ok(format!(
+        "Memory strings: {strings} from {} files, with {} URLs, {} IPs, {} domains, {} emails and {} command lines, \
+        {} IOC hits ({suppressed} suppressed by the allowlist) and {failed_blocks} Xpress blocks that failed to decompress",
+        files.len(), count(ValueKind::Url), count(ValueKind::Ip), count(ValueKind::Domain), count(ValueKind::Email),
+        count(ValueKind::Command), hits.len()
+    ))
+}
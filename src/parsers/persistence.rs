@@ -0,0 +1,587 @@
+/*
+Persistence parser, gathering the common autostart locations into one report.
+Reads the scheduled tasks in Windows\System32\Tasks (XML) and Windows\Tasks (.job),
+services from the SYSTEM hive, the Run/RunOnce, Winlogon and Image File Execution
+Options keys from the SOFTWARE hive and each user's NTUSER.DAT, the startup folders,
+and carves the WMI event consumers, filters and their bindings from OBJECTS.DATA.
+*/
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+
+use super::common::{self, le_u16, le_u32};
+use super::hive::{self, Hive, Key};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::timeline::timezone::SystemTimezone;
+
+pub(crate) const PERSISTENCE_HEADER: [&str; 9] = [
+    "Timestamp", "TimestampType", "Mechanism", "Location", "Name", "Command", "User", "Details", "SourceFile",
+];
+const TASKS_PATH: &str = r"Windows\System32\Tasks";
+const JOBS_PATH: &str = r"Windows\Tasks";
+const SYSTEM_PATH: &str = r"Windows\System32\config\SYSTEM";
+const SOFTWARE_PATH: &str = r"Windows\System32\config\SOFTWARE";
+const OBJECTS_PATH: &str = r"Windows\System32\wbem\Repository\OBJECTS.DATA";
+const USER_PATHS: [&str; 2] = ["Users", "Documents and Settings"];
+/// Startup folders for all users, relative to the root
+const STARTUP_PATHS: [&str; 2] = [
+    r"ProgramData\Microsoft\Windows\Start Menu\Programs\Startup",
+    r"Documents and Settings\All Users\Start Menu\Programs\Startup",
+];
+/// Startup folders relative to the user's profile
+const USER_STARTUP_PATHS: [&str; 2] = [
+    r"AppData\Roaming\Microsoft\Windows\Start Menu\Programs\Startup",
+    r"Start Menu\Programs\Startup",
+];
+/// Run keys, relative to the SOFTWARE hive or the Software key of NTUSER.DAT
+const RUN_KEYS: [&str; 5] = [
+    r"Microsoft\Windows\CurrentVersion\Run",
+    r"Microsoft\Windows\CurrentVersion\RunOnce",
+    r"Microsoft\Windows\CurrentVersion\Policies\Explorer\Run",
+    r"Wow6432Node\Microsoft\Windows\CurrentVersion\Run",
+    r"Wow6432Node\Microsoft\Windows\CurrentVersion\RunOnce",
+];
+const WINLOGON_KEY: &str = r"Microsoft\Windows NT\CurrentVersion\Winlogon";
+const WINLOGON_VALUES: [&str; 3] = ["Userinit", "Shell", "Taskman"];
+const IFEO_KEYS: [&str; 2] = [
+    r"Microsoft\Windows NT\CurrentVersion\Image File Execution Options",
+    r"Wow6432Node\Microsoft\Windows NT\CurrentVersion\Image File Execution Options",
+];
+const SILENT_EXIT_KEY: &str = r"Microsoft\Windows NT\CurrentVersion\SilentProcessExit";
+/// The job file's fixed length section, the variable length strings start after it
+const JOB_FIXED_SIZE: usize = 68;
+const JOB_DISABLED: u32 = 0x4;
+/// How many carved strings after a WMI instance's name are searched for its properties
+const WMI_SEARCH_STRINGS: usize = 8;
+
+/// A persistence mechanism found on the host, written as a row of persistence.csv
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PersistenceEntry {
+    pub timestamp: Option<DateTime<Utc>>,
+    /// what the timestamp is, i.e. LastWritten for a registry key
+    pub timestamp_type: String,
+    pub mechanism: String,
+    pub location: String,
+    pub name: String,
+    pub command: String,
+    pub user: String,
+    pub details: String,
+    pub source: String,
+}
+
+impl PersistenceEntry {
+    pub(crate) fn to_record(&self) -> [String; 9] {
+        [
+            common::fmt_dt(self.timestamp), self.timestamp_type.clone(), self.mechanism.clone(),
+            self.location.clone(), self.name.clone(), self.command.clone(), self.user.clone(),
+            self.details.clone(), self.source.clone(),
+        ]
+    }
+}
+
+/// A WMI event consumer bound to an event filter, carved from OBJECTS.DATA
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WmiBinding {
+    pub consumer_type: String,
+    pub consumer: String,
+    pub filter: String,
+    pub command: String,
+    pub query: String,
+}
+
+/// run the builtin persistence wisker, with the input as the root of the Windows volume
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let root = Path::new(&args.input);
+    let log = |msg: String| file_ops::log_msg(&args.main_args.out_log, msg);
+    let software = open_hive(root, SOFTWARE_PATH, &log);
+    let users = software.as_ref().map(hive::profile_users).unwrap_or_default();
+    let system = open_hive(root, SYSTEM_PATH, &log);
+    let timezone = system.as_ref().and_then(SystemTimezone::from_system);
+
+    let mut entries = Vec::new();
+    if let Some(tasks) = common::join_case_insensitive(root, TASKS_PATH) {
+        if timezone.is_none() {
+            log("[-] The host's timezone wasn't read from the SYSTEM hive, so the task dates without an offset are taken as UTC".to_string());
+        }
+        entries.extend(task_entries(&tasks, &users, timezone.as_ref()));
+    }
+    if let Some(jobs) = common::join_case_insensitive(root, JOBS_PATH) {
+        entries.extend(job_entries(&jobs));
+    }
+    if let Some(system) = &system {
+        entries.extend(service_entries(system, &source_path(root, SYSTEM_PATH)));
+    }
+    if let Some(software) = &software {
+        entries.extend(software_entries(software, &source_path(root, SOFTWARE_PATH)));
+    }
+    for (user, profile) in user_profiles(root) {
+        if let Some(ntuser) = common::join_case_insensitive(&profile, "NTUSER.DAT") {
+            match Hive::open(&ntuser) {
+                Ok(hive) => entries.extend(ntuser_entries(&hive, &user, &ntuser.display().to_string())),
+                Err(e) => log(format!("[!] Unable to read user hive: {:#}", e)),
+            }
+        }
+        if let Some(startup) = USER_STARTUP_PATHS.iter().find_map(|p| common::join_case_insensitive(&profile, p)) {
+            entries.extend(startup_entries(&startup, &user));
+        }
+    }
+    if let Some(startup) = STARTUP_PATHS.iter().find_map(|p| common::join_case_insensitive(root, p)) {
+        entries.extend(startup_entries(&startup, ""));
+    }
+    if let Some(objects) = common::join_case_insensitive(root, OBJECTS_PATH) {
+        match fs::read(&objects) {
+            Ok(data) => entries.extend(wmi_entries(&data, &objects.display().to_string())),
+            Err(e) => log(format!("[!] Unable to read WMI repository {}: {}", objects.display(), e)),
+        }
+    }
+
+    let mut writer = common::csv_writer(&args.outfolder.join("persistence.csv"))?;
+    writer.write_record(PERSISTENCE_HEADER)?;
+    for entry in &entries {
+        writer.write_record(entry.to_record())?;
+    }
+    writer.flush()?;
+    Ok(format!("Persistence entries written: {}", entries.len()))
+}
+
+fn open_hive(root: &Path, path: &str, log: &dyn Fn(String)) -> Option<Hive> {
+    let path = common::join_case_insensitive(root, path)?;
+    Hive::open(&path).map_err(|e| log(format!("[!] Unable to read registry hive: {:#}", e))).ok()
+}
+
+fn source_path(root: &Path, path: &str) -> String {
+    common::join_case_insensitive(root, path)
+        .map(|p| p.display().to_string())
+        .unwrap_or_default()
+}
+
+/// get the username and folder of each user's profile, from Users or the legacy
+/// Documents and Settings, which is a junction to Users on Vista+
+fn user_profiles(root: &Path) -> Vec<(String, PathBuf)> {
+    let Some(user_dir) = USER_PATHS.iter().find_map(|p| common::join_case_insensitive(root, p)) else { return Vec::new() };
+    let Ok(entries) = fs::read_dir(&user_dir) else { return Vec::new() };
+    let mut dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    dirs.sort();
+    dirs.into_iter().map(|p| (file_name(&p), p)).collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// resolve a SID to its username, leaving other account names as they are
+fn resolve_user(user: &str, users: &HashMap<String, String>) -> String {
+    users.get(user)
+        .map(String::as_str)
+        .or_else(|| common::well_known_sid(user))
+        .unwrap_or(user)
+        .to_string()
+}
+
+/// walk the Tasks folder, parsing each task's XML into an entry per action, with the dates
+/// without an offset converted from the host's timezone
+pub fn task_entries(tasks: &Path, users: &HashMap<String, String>, timezone: Option<&SystemTimezone>) -> Vec<PersistenceEntry> {
+    let mut entries = Vec::new();
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(tasks)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    files.sort();
+    for file in files {
+        let Ok(data) = fs::read(&file) else { continue };
+        let xml = decode_xml(&data);
+        if !xml.contains("<Task") {
+            continue;
+        }
+        let task_path = file.strip_prefix(tasks).unwrap_or(&file);
+        let folder = task_path.parent()
+            .map(|p| format!(r"\{}", p.to_string_lossy().replace('/', r"\")))
+            .unwrap_or_default();
+        let registered = xml_value(&xml, "Date").and_then(|d| parse_task_date(&d, timezone));
+        // triggers can have their own UserId and Enabled, so these are read from their parent
+        let principal = xml_block(&xml, "Principal").unwrap_or_default();
+        let user = xml_value(principal, "UserId")
+            .or_else(|| xml_value(principal, "GroupId"))
+            .map(|u| resolve_user(&u, users))
+            .unwrap_or_default();
+        let settings = xml_block(&xml, "Settings").unwrap_or_default();
+        let mut details = vec![
+            format!("Enabled={}", xml_value(settings, "Enabled").unwrap_or_else(|| "true".to_string())),
+            format!("Hidden={}", xml_value(settings, "Hidden").unwrap_or_else(|| "false".to_string())),
+        ];
+        if let Some(triggers) = xml_block(&xml, "Triggers") {
+            details.push(format!("Triggers={}", trigger_names(triggers).join(",")));
+        }
+        if let Some(author) = xml_value(&xml, "Author") {
+            details.push(format!("Author={author}"));
+        }
+        let mut commands: Vec<String> = xml_blocks(&xml, "Exec")
+            .into_iter()
+            .map(|exec| {
+                let command = xml_value(exec, "Command").unwrap_or_default();
+                match xml_value(exec, "Arguments") {
+                    Some(args) => format!("{command} {args}"),
+                    None => command,
+                }
+            })
+            .collect();
+        commands.extend(xml_blocks(&xml, "ComHandler").into_iter().map(|com| {
+            format!("ComHandler {}", xml_value(com, "ClassId").unwrap_or_default())
+        }));
+        if commands.is_empty() {
+            commands.push(String::new());
+        }
+        for command in commands {
+            entries.push(PersistenceEntry {
+                timestamp: registered,
+                timestamp_type: "Registered".to_string(),
+                mechanism: "ScheduledTask".to_string(),
+                location: folder.clone(),
+                name: file_name(&file),
+                command,
+                user: user.clone(),
+                details: details.join("; "),
+                source: file.display().to_string(),
+            });
+        }
+    }
+    entries
+}
+
+/// decode a task's XML, which is usually UTF-16LE with a BOM, otherwise UTF-8
+fn decode_xml(data: &[u8]) -> String {
+    match data {
+        [0xff, 0xfe, rest @ ..] => common::utf16le_to_string(rest),
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).to_string(),
+        _ => String::from_utf8_lossy(data).to_string(),
+    }
+}
+
+/// get the content of each element with the tag, i.e. `<Exec>...</Exec>`
+fn xml_blocks<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}"), format!("</{tag}>"));
+    let mut blocks = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // skip longer tags with the same prefix, i.e. <Date> when looking for <Data>
+        let Some(end_of_tag) = after.find('>') else { break };
+        if !after.starts_with(['>', ' ', '/', '\t', '\r', '\n']) || after[..end_of_tag].ends_with('/') {
+            rest = after;
+            continue;
+        }
+        let content = &after[end_of_tag + 1..];
+        let Some(end) = content.find(&close) else { break };
+        blocks.push(&content[..end]);
+        rest = &content[end + close.len()..];
+    }
+    blocks
+}
+
+fn xml_block<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    xml_blocks(xml, tag).into_iter().next()
+}
+
+/// get the text of the first element with the tag, with the XML entities decoded
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let value = xml_block(xml, tag)?.trim();
+    Some(
+        value.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// get the element names of the triggers, i.e. LogonTrigger and CalendarTrigger
+fn trigger_names(triggers: &str) -> Vec<String> {
+    let re = Regex::new(r"<(\w+Trigger)[\s>]").unwrap();
+    re.captures_iter(triggers).map(|c| c[1].to_string()).collect()
+}
+
+/// parse the task's registration date. Dates without an offset are in the host's local time,
+/// converted with its timezone, or taken as UTC when it isn't known
+pub fn parse_task_date(date: &str, timezone: Option<&SystemTimezone>) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(date) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+    Some(match timezone {
+        Some(timezone) => timezone.to_utc(&local),
+        None => local.and_utc(),
+    })
+}
+
+/// parse each .job file in the Tasks folder
+pub fn job_entries(jobs: &Path) -> Vec<PersistenceEntry> {
+    let Ok(dir) = fs::read_dir(jobs) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = dir.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("job")))
+        .collect();
+    files.sort();
+    files.into_iter()
+        .filter_map(|file| {
+            let mut entry = parse_job(&fs::read(&file).ok()?)?;
+            entry.name = file_name(&file);
+            entry.source = file.display().to_string();
+            Some(entry)
+        })
+        .collect()
+}
+
+/// parse a legacy .job file, with its last run time, application, parameters and author
+pub fn parse_job(data: &[u8]) -> Option<PersistenceEntry> {
+    if data.len() < JOB_FIXED_SIZE + 2 {
+        return None;
+    }
+    let status = le_u32(data, 44)?;
+    let flags = le_u32(data, 48)?;
+    let mut offset = JOB_FIXED_SIZE + 2;
+    let mut next = || -> Option<String> {
+        let chars = le_u16(data, offset)? as usize;
+        let value = common::utf16le_to_string(data.get(offset + 2..offset + 2 + chars * 2)?);
+        offset += 2 + chars * 2;
+        Some(value)
+    };
+    let application = next()?;
+    let parameters = next().unwrap_or_default();
+    let working_dir = next().unwrap_or_default();
+    let author = next().unwrap_or_default();
+    let command = match parameters.is_empty() {
+        true => application,
+        false => format!("{application} {parameters}"),
+    };
+    Some(PersistenceEntry {
+        timestamp: common::systemtime_to_dt(data, 52),
+        timestamp_type: "LastRun".to_string(),
+        mechanism: "ScheduledTaskJob".to_string(),
+        location: JOBS_PATH.to_string(),
+        command,
+        user: author.clone(),
+        details: format!(
+            "Enabled={}; Status={status:#x}; WorkingDirectory={working_dir}; Author={author}",
+            flags & JOB_DISABLED == 0
+        ),
+        ..Default::default()
+    })
+}
+
+/// get the services and drivers of the current control set, with their image path
+pub fn service_entries(system: &Hive, source: &str) -> Vec<PersistenceEntry> {
+    let control_set = hive::current_control_set(system);
+    let Some(services) = system.key(&format!(r"{control_set}\Services")) else { return Vec::new() };
+    let mut entries = Vec::new();
+    for service in services.subkeys() {
+        let Some(image_path) = service.value("ImagePath").map(|v| v.as_string()) else { continue };
+        let number = |name: &str| service.value(name).and_then(|v| v.as_u32());
+        let service_type = number("Type").unwrap_or(0);
+        let mut details = vec![
+            format!("Start={}", start_name(number("Start"))),
+            format!("Type={service_type:#x}"),
+        ];
+        if let Some(display) = service.value("DisplayName") {
+            details.push(format!("DisplayName={}", display.as_string()));
+        }
+        if let Some(dll) = service.subkey("Parameters").and_then(|p| p.value("ServiceDll")) {
+            details.push(format!("ServiceDll={}", dll.as_string()));
+        }
+        entries.push(PersistenceEntry {
+            timestamp: service.last_written(),
+            timestamp_type: "LastWritten".to_string(),
+            // kernel and file system drivers are types 0x1 and 0x2
+            mechanism: match service_type & 0x3 != 0 {
+                true => "Driver",
+                false => "Service",
+            }.to_string(),
+            location: format!(r"HKLM\SYSTEM\{control_set}\Services"),
+            name: service.name(),
+            command: image_path,
+            user: service.value("ObjectName").map(|v| v.as_string()).unwrap_or_default(),
+            details: details.join("; "),
+            source: source.to_string(),
+        });
+    }
+    entries
+}
+
+fn start_name(start: Option<u32>) -> String {
+    match start {
+        Some(0) => "Boot".to_string(),
+        Some(1) => "System".to_string(),
+        Some(2) => "Automatic".to_string(),
+        Some(3) => "Manual".to_string(),
+        Some(4) => "Disabled".to_string(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+/// get the Run, Winlogon and Image File Execution Options entries of the SOFTWARE hive
+pub fn software_entries(software: &Hive, source: &str) -> Vec<PersistenceEntry> {
+    let Some(root) = software.root() else { return Vec::new() };
+    let mut entries = registry_entries(root, r"HKLM\SOFTWARE", "", source);
+    for ifeo_path in IFEO_KEYS {
+        let Some(ifeo) = software.key(ifeo_path) else { continue };
+        for image in ifeo.subkeys() {
+            let Some(debugger) = image.value("Debugger") else { continue };
+            entries.push(value_entry(&image, &debugger.name, debugger.as_string(), "IFEO",
+                &format!(r"HKLM\SOFTWARE\{ifeo_path}\{}", image.name()), "", source));
+        }
+    }
+    if let Some(silent_exit) = software.key(SILENT_EXIT_KEY) {
+        for image in silent_exit.subkeys() {
+            let Some(monitor) = image.value("MonitorProcess") else { continue };
+            entries.push(value_entry(&image, &monitor.name, monitor.as_string(), "IFEO",
+                &format!(r"HKLM\SOFTWARE\{SILENT_EXIT_KEY}\{}", image.name()), "", source));
+        }
+    }
+    entries
+}
+
+/// get the Run and Winlogon entries of a user's NTUSER.DAT
+pub fn ntuser_entries(ntuser: &Hive, user: &str, source: &str) -> Vec<PersistenceEntry> {
+    match ntuser.key("Software") {
+        Some(software) => registry_entries(software, &format!(r"HKU\{user}\Software"), user, source),
+        None => Vec::new(),
+    }
+}
+
+/// get the values of the Run keys and Winlogon key below the Software key
+fn registry_entries(software: Key, location: &str, user: &str, source: &str) -> Vec<PersistenceEntry> {
+    let mut entries = Vec::new();
+    for run_path in RUN_KEYS {
+        let Some(run) = subkey_path(software, run_path) else { continue };
+        let mechanism = match run_path.ends_with("RunOnce") {
+            true => "RunOnce",
+            false => "Run",
+        };
+        for value in run.values() {
+            entries.push(value_entry(&run, &value.name, value.as_string(), mechanism,
+                &format!(r"{location}\{run_path}"), user, source));
+        }
+    }
+    if let Some(winlogon) = subkey_path(software, WINLOGON_KEY) {
+        for value in winlogon.values().iter().filter(|v| WINLOGON_VALUES.iter().any(|n| v.name.eq_ignore_ascii_case(n))) {
+            entries.push(value_entry(&winlogon, &value.name, value.as_string(), "Winlogon",
+                &format!(r"{location}\{WINLOGON_KEY}"), user, source));
+        }
+    }
+    entries
+}
+
+fn subkey_path<'a>(key: Key<'a>, path: &str) -> Option<Key<'a>> {
+    path.split('\\').try_fold(key, |k, name| k.subkey(name))
+}
+
+fn value_entry(key: &Key, name: &str, command: String, mechanism: &str, location: &str, user: &str, source: &str) -> PersistenceEntry {
+    PersistenceEntry {
+        timestamp: key.last_written(),
+        timestamp_type: "LastWritten".to_string(),
+        mechanism: mechanism.to_string(),
+        location: location.to_string(),
+        name: name.to_string(),
+        command,
+        user: user.to_string(),
+        source: source.to_string(),
+        ..Default::default()
+    }
+}
+
+/// list the files in a startup folder, with their modified time
+pub fn startup_entries(startup: &Path, user: &str) -> Vec<PersistenceEntry> {
+    let Ok(dir) = fs::read_dir(startup) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = dir.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && !file_name(p).eq_ignore_ascii_case("desktop.ini"))
+        .collect();
+    files.sort();
+    files.into_iter()
+        .map(|file| PersistenceEntry {
+            timestamp: fs::metadata(&file).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from),
+            timestamp_type: "Modified".to_string(),
+            mechanism: "StartupFolder".to_string(),
+            location: startup.display().to_string(),
+            name: file_name(&file),
+            command: file.display().to_string(),
+            user: user.to_string(),
+            source: file.display().to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// carve the WMI event consumers bound to event filters from OBJECTS.DATA, as a row per binding
+pub fn wmi_entries(data: &[u8], source: &str) -> Vec<PersistenceEntry> {
+    carve_wmi_bindings(data)
+        .into_iter()
+        .map(|binding| PersistenceEntry {
+            mechanism: "WmiEventConsumer".to_string(),
+            location: r"root\subscription".to_string(),
+            name: binding.consumer,
+            command: binding.command,
+            user: "SYSTEM".to_string(),
+            details: format!("ConsumerType={}; Filter={}; Query={}", binding.consumer_type, binding.filter, binding.query),
+            source: source.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// find the __FilterToConsumerBinding references in the carved strings, then look up the
+/// consumer's command and the filter's query from the strings that follow their names
+pub fn carve_wmi_bindings(data: &[u8]) -> Vec<WmiBinding> {
+    let consumer_re = Regex::new(r#"(\w*EventConsumer)\.Name="([^"]*)""#).unwrap();
+    let filter_re = Regex::new(r#"__EventFilter\.Name="([^"]*)""#).unwrap();
+    let strings = carve_strings(data);
+    let mut pairs = BTreeSet::new();
+    for (i, s) in strings.iter().enumerate() {
+        let Some(consumer) = consumer_re.captures(s) else { continue };
+        let filter = strings[i..(i + 4).min(strings.len())]
+            .iter()
+            .find_map(|s| filter_re.captures(s).map(|c| c[1].to_string()));
+        if let Some(filter) = filter {
+            pairs.insert((consumer[1].to_string(), consumer[2].to_string(), filter));
+        }
+    }
+    pairs.into_iter()
+        .map(|(consumer_type, consumer, filter)| {
+            let command = find_after(&strings, &consumer, |s| {
+                s != consumer && !s.starts_with("__") && !s.contains("EventConsumer")
+                    && !["VBScript", "JScript"].contains(&s)
+            });
+            let query = find_after(&strings, &filter, |s| s.to_lowercase().starts_with("select "));
+            WmiBinding { consumer_type, consumer, filter, command, query }
+        })
+        .collect()
+}
+
+/// find the first string matching the predicate within the strings that follow the name
+fn find_after(strings: &[String], name: &str, matches: impl Fn(&str) -> bool) -> String {
+    strings.iter()
+        .enumerate()
+        .filter(|(_, s)| *s == name)
+        .find_map(|(i, _)| {
+            strings[i + 1..(i + 1 + WMI_SEARCH_STRINGS).min(strings.len())]
+                .iter()
+                .find(|s| matches(s))
+        })
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// carve the runs of printable ASCII of at least 4 characters
+fn carve_strings(data: &[u8]) -> Vec<String> {
+    data.split(|b| !(0x20..0x7f).contains(b) && *b != b'\t')
+        .filter(|s| s.len() >= 4)
+        .map(|s| String::from_utf8_lossy(s).to_string())
+        .collect()
+}
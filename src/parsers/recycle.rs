@@ -0,0 +1,169 @@
+/*
+Recycle Bin parser for the Vista+ $I files (version 1 and 2) in $Recycle.Bin and the
+XP INFO2 file in RECYCLER. Each record is written with the SID of the folder it is in,
+the username resolved from the SOFTWARE hive, the original path, size, deletion time
+and whether the deleted file ($R or Dc) is still in the Recycle Bin. The deleted files
+can be hashed by giving `--hash` in the wisker args, to match them with IOC hashes.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use super::common::{self, le_u32, le_u64};
+use super::hashes;
+use super::hive::{self, Hive};
+use crate::ops::builtin_ops::BuiltinArgs;
+
+const RECYCLE_HEADER: [&str; 13] = [
+    "DeletedOn", "Sid", "UserName", "OriginalPath", "FileSize", "Format", "IndexFile", "DeletedFile",
+    "DeletedFileExists", "IsDirectory", "MD5", "SHA1", "SHA256",
+];
+/// The size of the fixed UTF-16 path in $I version 1 and INFO2 records
+const PATH_SIZE: usize = 520;
+const INFO2_HEADER_SIZE: usize = 20;
+
+/// A deleted file record from a $I file or INFO2 record
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecycleRecord {
+    pub deleted_on: Option<DateTime<Utc>>,
+    pub original_path: String,
+    pub file_size: u64,
+    pub format: String,
+    /// the name of the deleted file in the Recycle Bin, i.e. $R123ABC.exe or Dc1.exe
+    pub deleted_name: String,
+}
+
+/// run the builtin recycle bin wisker, with the input as $Recycle.Bin or RECYCLER and
+/// input_other as the SOFTWARE hive
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let users = match args.has_input_other() {
+        true => hive::profile_users(&Hive::open(Path::new(&args.input_other))?),
+        false => HashMap::new(),
+    };
+    let hash = args.args.split_whitespace().any(|a| a == "--hash");
+    let mut writer = common::csv_writer(&args.outfolder.join("recycle_bin.csv"))?;
+    writer.write_record(RECYCLE_HEADER)?;
+
+    let mut count = 0;
+    for (sid_dir, index_file) in find_index_files(Path::new(&args.input)) {
+        let sid = sid_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let user = users.get(&sid)
+            .cloned()
+            .or_else(|| common::well_known_sid(&sid).map(String::from))
+            .unwrap_or_default();
+        let Ok(data) = fs::read(&index_file) else { continue };
+        let records = match is_info2(&index_file) {
+            true => parse_info2(&data),
+            false => parse_index(&data, &index_file).into_iter().collect(),
+        };
+        for record in records {
+            let deleted = match common::join_case_insensitive(&sid_dir, &record.deleted_name) {
+                Some(path) => path,
+                None => sid_dir.join(&record.deleted_name),
+            };
+            let exists = deleted.exists();
+            let is_dir = deleted.is_dir();
+            let hashes = match hash && exists && !is_dir {
+                true => hashes::hash_file(&deleted).unwrap_or_default(),
+                false => hashes::FileHashes::default(),
+            };
+            writer.write_record([
+                common::fmt_dt(record.deleted_on), sid.clone(), user.clone(), record.original_path,
+                record.file_size.to_string(), record.format, index_file.display().to_string(),
+                deleted.display().to_string(), exists.to_string(), is_dir.to_string(),
+                hashes.md5, hashes.sha1, hashes.sha256,
+            ])?;
+            count += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(format!("Recycle Bin records written: {count}"))
+}
+
+fn is_info2(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n.eq_ignore_ascii_case("INFO2"))
+}
+
+/// find the $I files and INFO2 files in each SID folder, returning the folder and file
+fn find_index_files(recycle_bin: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(recycle_bin) else { return files };
+    let mut sid_dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    sid_dirs.sort();
+    for sid_dir in sid_dirs {
+        let Ok(entries) = fs::read_dir(&sid_dir) else { continue };
+        let mut index_files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .filter(|p| {
+                let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                name.starts_with("$I") || name.eq_ignore_ascii_case("INFO2")
+            })
+            .collect();
+        index_files.sort();
+        files.extend(index_files.into_iter().map(|f| (sid_dir.clone(), f)));
+    }
+    files
+}
+
+/// parse a $I file, version 1 has a fixed 520 byte path and version 2 a length prefixed path
+pub fn parse_index(data: &[u8], path: &Path) -> Option<RecycleRecord> {
+    let version = le_u64(data, 0)?;
+    let file_size = le_u64(data, 8)?;
+    let deleted_on = common::filetime_to_dt(le_u64(data, 16)?);
+    let original_path = match version {
+        1 => common::utf16le_to_string(data.get(24..24 + PATH_SIZE.min(data.len() - 24))?),
+        2 => {
+            let chars = le_u32(data, 24)? as usize;
+            common::utf16le_to_string(data.get(28..(28 + chars * 2).min(data.len()))?)
+        }
+        _ => return None,
+    };
+    let name = path.file_name()?.to_string_lossy().to_string();
+    Some(RecycleRecord {
+        deleted_on,
+        original_path,
+        file_size,
+        format: format!("$I v{version}"),
+        deleted_name: format!("$R{}", name.get(2..).unwrap_or_default()),
+    })
+}
+
+/// parse the records of an XP INFO2 file. The deleted file is named Dc<index><extension>
+pub fn parse_info2(data: &[u8]) -> Vec<RecycleRecord> {
+    let mut records = Vec::new();
+    let record_size = le_u32(data, 12).unwrap_or(0) as usize;
+    if record_size < 280 {
+        return records;
+    }
+    let mut offset = INFO2_HEADER_SIZE;
+    while let Some(record) = data.get(offset..offset + record_size) {
+        offset += record_size;
+        let ansi_path = common::ascii_to_string(&record[..260]);
+        let index = le_u32(record, 260).unwrap_or(0);
+        let drive = le_u32(record, 264).unwrap_or(0);
+        let unicode_path = record.get(280..280 + PATH_SIZE).map(common::utf16le_to_string).unwrap_or_default();
+        // the first byte of the ANSI path is cleared when the file is removed from the Recycle Bin
+        let original_path = match unicode_path.is_empty() {
+            true => ansi_path,
+            false => unicode_path,
+        };
+        let extension = original_path.rsplit_once('.')
+            .filter(|(_, ext)| !ext.contains(['\\', '/']))
+            .map(|(_, ext)| format!(".{ext}"))
+            .unwrap_or_default();
+        let drive_letter = (b'a' + (drive as u8).min(25)) as char;
+        records.push(RecycleRecord {
+            deleted_on: common::filetime_to_dt(le_u64(record, 268).unwrap_or(0)),
+            original_path,
+            file_size: le_u32(record, 276).unwrap_or(0) as u64,
+            format: "INFO2".to_string(),
+            deleted_name: format!("D{drive_letter}{index}{extension}"),
+        });
+    }
+    records
+}
@@ -0,0 +1,105 @@
+/*
+Helpers to read SQLite artefacts, i.e. browser history databases.
+The database is copied with its write-ahead log and journal to a temporary folder
+before being opened, so the evidence isn't modified or locked and records still
+in the WAL are included.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags};
+use tempfile::TempDir;
+
+/// The files SQLite keeps beside the database, that hold uncommitted records
+const SIDECARS: [&str; 3] = ["-wal", "-journal", "-shm"];
+
+/// A SQLite database opened from a temporary copy, which is removed when dropped
+pub struct SqliteCopy {
+    pub conn: Connection,
+    _temp_dir: TempDir,
+}
+
+/// A row of a query, with the values by column name
+pub type SqlRow = HashMap<String, Value>;
+
+impl SqliteCopy {
+    /// copy the database and any WAL or journal to a temporary folder and open it
+    pub fn open(path: &Path) -> Result<Self> {
+        let temp_dir = TempDir::new()?;
+        let file_name = path.file_name().context("Database path has no file name")?;
+        let copy = temp_dir.path().join(file_name);
+        fs::copy(path, &copy)
+            .with_context(|| format!("Unable to copy SQLite database {}", path.display()))?;
+        for sidecar in SIDECARS {
+            let mut name = file_name.to_os_string();
+            name.push(sidecar);
+            let source = path.with_file_name(&name);
+            if source.is_file() {
+                fs::copy(&source, temp_dir.path().join(&name))?;
+            }
+        }
+        let conn = Connection::open_with_flags(&copy, OpenFlags::SQLITE_OPEN_READ_WRITE)
+            .with_context(|| format!("Unable to open SQLite database {}", path.display()))?;
+        Ok(SqliteCopy { conn, _temp_dir: temp_dir })
+    }
+
+    /// check if the table exists in the database
+    pub fn has_table(&self, table: &str) -> bool {
+        self.conn
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table],
+                |r| r.get::<_, i64>(0),
+            )
+            .map(|n| n > 0)
+            .unwrap_or(false)
+    }
+
+    /// run the query and return every row, with the values by column name
+    pub fn query(&self, sql: &str) -> Result<Vec<SqlRow>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+        let rows = stmt.query_map([], |row| {
+            let mut values = HashMap::new();
+            for (i, name) in names.iter().enumerate() {
+                values.insert(name.clone(), row.get::<_, Value>(i)?);
+            }
+            Ok(values)
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<SqlRow>>>()?)
+    }
+}
+
+/// get a column of the row as text, numbers are formatted and blobs are lossy UTF-8
+pub fn text(row: &SqlRow, column: &str) -> String {
+    match row.get(column) {
+        Some(Value::Text(s)) => s.clone(),
+        Some(Value::Integer(i)) => i.to_string(),
+        Some(Value::Real(f)) => f.to_string(),
+        Some(Value::Blob(b)) => String::from_utf8_lossy(b).to_string(),
+        _ => String::new(),
+    }
+}
+
+/// get a column of the row as an integer
+pub fn int(row: &SqlRow, column: &str) -> Option<i64> {
+    match row.get(column) {
+        Some(Value::Integer(i)) => Some(*i),
+        Some(Value::Real(f)) => Some(*f as i64),
+        Some(Value::Text(s)) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// get a column of the row as a float, i.e. the Cocoa times of macOS databases
+pub fn real(row: &SqlRow, column: &str) -> Option<f64> {
+    match row.get(column) {
+        Some(Value::Real(f)) => Some(*f),
+        Some(Value::Integer(i)) => Some(*i as f64),
+        Some(Value::Text(s)) => s.parse().ok(),
+        _ => None,
+    }
+}
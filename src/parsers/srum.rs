@@ -0,0 +1,134 @@
+/*
+SRUM (System Resource Usage Monitor) parser for SRUDB.dat.
+Dumps the network usage, application resource usage, network connectivity, push
+notification and energy usage tables to CSV, resolving the AppId and UserId columns
+using the SruDbIdMapTable and usernames from the ProfileList of the SOFTWARE hive.
+*/
+
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::Result;
+
+use super::common;
+use super::ese::{EseDb, EseValue, Table};
+use super::hive::{self, Hive};
+use crate::ops::builtin_ops::BuiltinArgs;
+
+/// The SRUM extension tables, with the friendly name used for the output file
+pub const SRUM_TABLES: [(&str, &str); 6] = [
+    ("{973F5D5C-1D90-4944-BE8E-24B94231A174}", "network_usage"),
+    ("{D10CA2FE-6FCF-4F6D-848E-B2E99266FA89}", "app_resource_usage"),
+    ("{DD6636C4-8929-4683-974E-22C046A43763}", "network_connectivity"),
+    ("{D10CA2FE-6FCF-4F6D-848E-B2E99266FA86}", "push_notifications"),
+    ("{FEE4E14F-02A9-4550-B5CE-5FA2DA202E37}", "energy_usage"),
+    ("{FEE4E14F-02A9-4550-B5CE-5FA2DA202E37}LT", "energy_usage_lt"),
+];
+
+/// The table that maps the AppId and UserId columns to names and SIDs
+const ID_MAP_TABLE: &str = "SruDbIdMapTable";
+/// IdType of a SruDbIdMapTable entry where the blob is a binary SID
+const ID_TYPE_SID: i64 = 3;
+/// Columns holding a FILETIME as an integer, rather than an ESE DateTime
+const FILETIME_COLUMNS: [&str; 2] = ["ConnectStartTime", "EventTimestamp"];
+
+/// run the builtin srum wisker, with the input as SRUDB.dat and input_other as the SOFTWARE hive
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let db = EseDb::open(Path::new(&args.input))?;
+    let users = match args.has_input_other() {
+        true => hive::profile_users(&Hive::open(Path::new(&args.input_other))?),
+        false => HashMap::new(),
+    };
+    let counts = write_tables(&db, &users, &args.outfolder)?;
+    let summary: Vec<String> = counts.iter().map(|(name, n)| format!("{name}: {n}")).collect();
+    Ok(format!("SRUM records written - {}", summary.join(", ")))
+}
+
+/// write each SRUM table found in the database to `srum_<name>.csv` in the output folder,
+/// returning the number of records written for each table
+pub fn write_tables(db: &EseDb, users: &HashMap<String, String>, outfolder: &Path) -> Result<Vec<(String, usize)>> {
+    let id_map = id_map(db);
+    let mut counts = Vec::new();
+    for (guid, name) in SRUM_TABLES {
+        let Some(table) = db.table(guid) else { continue };
+        let out_file = outfolder.join(format!("srum_{name}.csv"));
+        let count = write_table(db, table, &id_map, users, &out_file)?;
+        counts.push((name.to_string(), count));
+    }
+    Ok(counts)
+}
+
+fn write_table(db: &EseDb, table: &Table, id_map: &HashMap<i64, String>, users: &HashMap<String, String>, out_file: &Path) -> Result<usize> {
+    let mut writer = common::csv_writer(out_file)?;
+    let mut header: Vec<String> = table.columns.iter().map(|c| c.name.clone()).collect();
+    header.extend(["AppName", "UserSid", "UserName", "InterfaceType"].map(String::from));
+    writer.write_record(&header)?;
+
+    let records = db.records(table);
+    for record in &records {
+        let mut row: Vec<String> = record.values
+            .iter()
+            .map(|(name, value)| format_value(name, value))
+            .collect();
+        let lookup = |col: &str| {
+            record.get(col)
+                .and_then(|v| v.as_i64())
+                .and_then(|id| id_map.get(&id))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let user_sid = lookup("UserId");
+        let user_name = users.get(&user_sid)
+            .cloned()
+            .or_else(|| common::well_known_sid(&user_sid).map(String::from))
+            .unwrap_or_default();
+        let interface = record.get("InterfaceLuid")
+            .and_then(|v| v.as_i64())
+            .map(interface_type)
+            .unwrap_or_default();
+        row.extend([lookup("AppId"), user_sid, user_name, interface]);
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(records.len())
+}
+
+fn format_value(column: &str, value: &EseValue) -> String {
+    if FILETIME_COLUMNS.iter().any(|c| c.eq_ignore_ascii_case(column)) {
+        if let Some(filetime) = value.as_i64() {
+            return common::fmt_dt(common::filetime_to_dt(filetime as u64));
+        }
+    }
+    value.to_string()
+}
+
+/// read the SruDbIdMapTable, mapping the IdIndex to the application name or user SID
+pub fn id_map(db: &EseDb) -> HashMap<i64, String> {
+    let mut map = HashMap::new();
+    let Ok(records) = db.table_records(ID_MAP_TABLE) else { return map };
+    for record in records {
+        let Some(index) = record.get("IdIndex").and_then(|v| v.as_i64()) else { continue };
+        let id_type = record.get("IdType").and_then(|v| v.as_i64()).unwrap_or(0);
+        let blob = record.get("IdBlob").and_then(|v| v.as_bytes()).unwrap_or(&[]);
+        let name = match id_type {
+            ID_TYPE_SID => common::sid_to_string(blob).unwrap_or_default(),
+            _ => common::utf16le_to_string(blob),
+        };
+        map.insert(index, name);
+    }
+    map
+}
+
+/// the interface type from the top 16 bits of the interface LUID (IANA ifType)
+fn interface_type(luid: i64) -> String {
+    let if_type = (luid as u64) >> 48;
+    match if_type {
+        6 => "Ethernet".to_string(),
+        71 => "Wireless".to_string(),
+        23 => "PPP".to_string(),
+        24 => "Loopback".to_string(),
+        131 => "Tunnel".to_string(),
+        243 | 244 => "Mobile Broadband".to_string(),
+        0 => String::new(),
+        other => format!("IfType {other}"),
+    }
+}
@@ -0,0 +1,175 @@
+/*
+User Access Logging (UAL / SUM) parser for Windows\System32\LogFiles\Sum\*.mdb.
+Reads the CLIENTS and DNS tables of Current.mdb and the yearly {GUID}.mdb databases,
+resolving the role names from SystemIdentity.mdb. The databases are read as-is, so
+they don't need repairing with esentutl first.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+
+use super::common;
+use super::ese::{EseDb, EseValue};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+
+const SYSTEM_IDENTITY: &str = "SystemIdentity.mdb";
+const CLIENTS_HEADER: [&str; 13] = [
+    "SourceFile", "Year", "RoleGuid", "RoleName", "ProductName", "TenantId", "AuthenticatedUserName",
+    "ClientIp", "TotalAccesses", "FirstAccess", "LastAccess", "ActiveDays", "DailyCounts",
+];
+
+/// A role from the ROLE_IDS table of SystemIdentity.mdb
+struct Role {
+    name: String,
+    product: String,
+}
+
+/// run the builtin sum wisker, with the input as the Sum folder or a single .mdb file
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let input = Path::new(&args.input);
+    let databases = find_databases(input)?;
+    let identity = databases
+        .iter()
+        .find(|p| file_name(p).eq_ignore_ascii_case(SYSTEM_IDENTITY))
+        .and_then(|p| EseDb::open(p).ok());
+    let roles = identity.as_ref().map(read_roles).unwrap_or_default();
+    let years = identity.as_ref().map(read_chained_years).unwrap_or_default();
+
+    let mut clients = common::csv_writer(&args.outfolder.join("sum_clients.csv"))?;
+    clients.write_record(CLIENTS_HEADER)?;
+    let mut dns = common::csv_writer(&args.outfolder.join("sum_dns.csv"))?;
+    dns.write_record(["SourceFile", "LastSeen", "Address", "HostName"])?;
+
+    let (mut client_count, mut dns_count) = (0, 0);
+    for path in databases.iter().filter(|p| !file_name(p).eq_ignore_ascii_case(SYSTEM_IDENTITY)) {
+        let db = match EseDb::open(path) {
+            Ok(db) => db,
+            Err(e) => {
+                file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to read UAL database: {:#}", e));
+                continue;
+            }
+        };
+        let source = file_name(path);
+        let year = years.get(&source.to_lowercase()).copied();
+        for row in client_rows(&db, &source, year, &roles) {
+            clients.write_record(&row)?;
+            client_count += 1;
+        }
+        for record in db.table_records("DNS").unwrap_or_default() {
+            let value = |col: &str| record.get(col).map(|v| v.to_string()).unwrap_or_default();
+            dns.write_record([source.clone(), value("LastSeen"), value("Address"), value("HostName")])?;
+            dns_count += 1;
+        }
+    }
+    clients.flush()?;
+    dns.flush()?;
+    Ok(format!("UAL records written - clients: {client_count}, dns: {dns_count}"))
+}
+
+/// get the .mdb files in the Sum folder, or the input itself if it is a file
+fn find_databases(input: &Path) -> Result<Vec<PathBuf>> {
+    if input.is_file() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+    if !input.is_dir() {
+        bail!("UAL path {} does not exist", input.display());
+    }
+    let mut databases: Vec<PathBuf> = fs::read_dir(input)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("mdb")))
+        .collect();
+    databases.sort();
+    Ok(databases)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// map the role GUID to the role and product name from ROLE_IDS
+fn read_roles(identity: &EseDb) -> HashMap<String, Role> {
+    identity.table_records("ROLE_IDS")
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|r| {
+            let guid = r.get("RoleGuid")?.to_string();
+            let text = |col: &str| r.get(col).map(|v| v.to_string()).unwrap_or_default();
+            Some((guid, Role { name: text("RoleName"), product: text("ProductName") }))
+        })
+        .collect()
+}
+
+/// map the lowercase database file name to its year from CHAINED_DATABASES
+fn read_chained_years(identity: &EseDb) -> HashMap<String, i32> {
+    identity.table_records("CHAINED_DATABASES")
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|r| {
+            let year = r.get("Year")?.as_i64()? as i32;
+            let name = r.get("FileName")?.to_string();
+            let name = name.rsplit(['\\', '/']).next().unwrap_or(&name).to_lowercase();
+            Some((name, year))
+        })
+        .collect()
+}
+
+/// build the CSV rows of the CLIENTS table
+fn client_rows(db: &EseDb, source: &str, year: Option<i32>, roles: &HashMap<String, Role>) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for record in db.table_records("CLIENTS").unwrap_or_default() {
+        let value = |col: &str| record.get(col).map(|v| v.to_string()).unwrap_or_default();
+        let first = record.get("InsertDate").and_then(|v| v.as_datetime());
+        let last = record.get("LastAccess").and_then(|v| v.as_datetime());
+        let year = year.or(last.map(|d| d.year())).or(first.map(|d| d.year()));
+
+        // the Day1 to Day366 columns hold the number of accesses on each day of the year
+        let mut daily = Vec::new();
+        for (column, count) in &record.values {
+            let Some(day) = column.strip_prefix("Day").and_then(|d| d.parse::<i64>().ok()) else { continue };
+            let Some(count) = count.as_i64().filter(|c| *c > 0) else { continue };
+            let date = year
+                .and_then(|y| NaiveDate::from_ymd_opt(y, 1, 1))
+                .map(|d| (d + Duration::days(day - 1)).to_string())
+                .unwrap_or(format!("Day{day}"));
+            daily.push((day, format!("{date}:{count}")));
+        }
+        daily.sort_by_key(|(day, _)| *day);
+
+        let role_guid = value("RoleGuid");
+        let role = roles.get(&role_guid);
+        rows.push(vec![
+            source.to_string(),
+            year.map(|y| y.to_string()).unwrap_or_default(),
+            role_guid.clone(),
+            role.map(|r| r.name.clone()).unwrap_or_default(),
+            role.map(|r| r.product.clone()).unwrap_or_default(),
+            value("TenantId"),
+            value("AuthenticatedUserName"),
+            record.get("Address").map(client_ip).unwrap_or_default(),
+            value("TotalAccesses"),
+            common::fmt_dt(first),
+            common::fmt_dt(last),
+            daily.len().to_string(),
+            daily.into_iter().map(|(_, d)| d).collect::<Vec<String>>().join(";"),
+        ]);
+    }
+    rows
+}
+
+/// the client address is stored as 4 bytes for IPv4 or 16 bytes for IPv6
+fn client_ip(value: &EseValue) -> String {
+    match value {
+        EseValue::Binary(b) if b.len() == 4 => IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3])).to_string(),
+        EseValue::Binary(b) if b.len() == 16 => {
+            let octets: [u8; 16] = b.as_slice().try_into().unwrap();
+            IpAddr::V6(Ipv6Addr::from(octets)).to_string()
+        }
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+
+use super::common::{le_u16, le_u32};
+
+/// decompress data using the LZXPRESS plain LZ77 algorithm, as described in MS-XCA 2.4.
+/// This is used by ESE databases for compressed long values.
+///
+/// Args:
+/// * `input` - the compressed bytes
+/// * `max_output` - the expected uncompressed size, used as a limit on the output
+pub fn lz77_decompress(input: &[u8], max_output: usize) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::with_capacity(max_output);
+    let mut in_pos = 0;
+    let mut flags = 0u32;
+    let mut flag_count = 0;
+    let mut last_length_half_byte = 0;
+
+    while in_pos < input.len() && output.len() < max_output {
+        if flag_count == 0 {
+            flags = match le_u32(input, in_pos) {
+                Some(f) => f,
+                None => break,
+            };
+            in_pos += 4;
+            flag_count = 32;
+        }
+        flag_count -= 1;
+
+        if flags & (1 << flag_count) == 0 {
+            // literal byte
+            match input.get(in_pos) {
+                Some(b) => output.push(*b),
+                None => break,
+            }
+            in_pos += 1;
+            continue;
+        }
+
+        // match: 13 bits of offset, 3 bits of length with extended lengths following
+        let match_bytes = match le_u16(input, in_pos) {
+            Some(m) => m as usize,
+            None => break,
+        };
+        in_pos += 2;
+        let mut match_len = match_bytes % 8;
+        let match_offset = (match_bytes / 8) + 1;
+        if match_len == 7 {
+            if last_length_half_byte == 0 {
+                match_len = (*input.get(in_pos).unwrap_or(&0) % 16) as usize;
+                last_length_half_byte = in_pos;
+                in_pos += 1;
+            } else {
+                match_len = (input[last_length_half_byte] / 16) as usize;
+                last_length_half_byte = 0;
+            }
+            if match_len == 15 {
+                match_len = *input.get(in_pos).unwrap_or(&0) as usize;
+                in_pos += 1;
+                if match_len == 255 {
+                    match_len = le_u16(input, in_pos).unwrap_or(0) as usize;
+                    in_pos += 2;
+                    if match_len == 0 {
+                        match_len = le_u32(input, in_pos).unwrap_or(0) as usize;
+                        in_pos += 4;
+                    }
+                    if match_len < 15 + 7 {
+                        bail!("Invalid LZXPRESS match length at offset {in_pos}");
+                    }
+                    match_len -= 15 + 7;
+                }
+                match_len += 15;
+            }
+            match_len += 7;
+        }
+        match_len += 3;
+
+        if match_offset > output.len() {
+            bail!("Invalid LZXPRESS match offset {match_offset} at output position {}", output.len());
+        }
+        // copy byte by byte, as the match can overlap the bytes being written
+        let start = output.len() - match_offset;
+        for i in 0..match_len {
+            if output.len() >= max_output {
+                break;
+            }
+            let b = output[start + i];
+            output.push(b);
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::iocs::allowlist::{self, Allowlist};
+    use crate::ops::builtin_ops;
+    use crate::parsers::hashes;
+
+    /// Test the path globs, IOC and path pairs and known good hashes of an allowlist
+    #[test]
+    fn test_allowlist() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = hashes::hash_file(Path::new("Cargo.toml")).unwrap();
+        fs::write(temp_dir.path().join("known_good.txt"), format!("{}  Cargo.toml\nnot a hash\n", good.sha1.to_uppercase())).unwrap();
+        let file = temp_dir.path().join("allowlist.yaml");
+        fs::write(&file, r#"
+paths:
+  - '*\Windows\WinSxS\*'
+iocs:
+  - ioc: mimi*
+    paths: ['*/System32/*.dll', '*\Tools\?.exe']
+  - ioc: psexec
+hashes: ['"D41D8CD98F00B204E9800998ECF8427E"']
+hash_files: [known_good.txt]
+"#).unwrap();
+        let allowlist = Allowlist::open(&file).unwrap();
+        assert!(!allowlist.is_empty() && allowlist.has_hashes());
+        assert!(allowlist.allows_path(r"C:\Windows\winsxs\amd64\cmd.exe"));
+        assert!(allowlist.allows_path("/mnt/c/Windows/WinSxS/x"));
+        assert!(!allowlist.allows_path(r"C:\Windows\System32\cmd.exe"));
+        assert!(allowlist.allows_hit("mimikatz", r"C:\Windows\System32\mimilib.dll"));
+        assert!(allowlist.allows_hit("MIMI", r"D:\Tools\m.exe"));
+        assert!(!allowlist.allows_hit("mimikatz", r"D:\Tools\mk.exe"));
+        assert!(!allowlist.allows_hit("mimikatz", r"C:\Users\a\mimikatz.exe"));
+        assert!(allowlist.allows_hit("PsExec", r"C:\anywhere.csv"));
+        assert!(allowlist.allows_hash("d41d8cd98f00b204e9800998ecf8427e"));
+        assert!(allowlist.allows_hashes(&good));
+
+        assert!(allowlist::glob_regex("a[b").unwrap().is_match("A[B"));
+        assert!(Allowlist::default().is_empty());
+        fs::write(&file, "hash_files: [missing.txt]\n").unwrap();
+        assert!(Allowlist::open(&file).is_err());
+    }
+
+    /// Test the IOC and hash enrichers suppress the hits of the allowlist, counting them in
+    /// the summary, and skip the wiskess outputs in the data source
+    #[test]
+    fn test_enrichers_with_allowlist() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let input = temp_dir.path().join("collection");
+        let old_output = input.join("HOST1-Wiskess");
+        fs::create_dir_all(input.join("Windows").join("System32")).unwrap();
+        fs::create_dir_all(input.join("Users")).unwrap();
+        fs::create_dir_all(old_output.join("IOC_Findings")).unwrap();
+        fs::create_dir_all(&out_path).unwrap();
+        fs::write(input.join("Windows").join("System32").join("lsasrv.dll"), "mimikatz string in a system dll").unwrap();
+        fs::write(input.join("Users").join("notes.txt"), "ran mimikatz and psexec").unwrap();
+        fs::write(input.join("Users").join("tool.exe"), "known good tool").unwrap();
+        fs::write(input.join("Users").join("bad.exe"), "bad tool").unwrap();
+        fs::write(old_output.join("wiskess_2024-01-01T00_00_00.log"), "Starting wiskess").unwrap();
+        fs::write(old_output.join("IOC_Findings").join("ioc_findings.csv"), "mimikatz,string\n").unwrap();
+        let tool = hashes::hash_file(&input.join("Users").join("tool.exe")).unwrap();
+        let bad = hashes::hash_file(&input.join("Users").join("bad.exe")).unwrap();
+        let ioc_file = temp_dir.path().join("iocs.txt");
+        fs::write(&ioc_file, format!("mimikatz\npsexec\n{}\n{}\n", tool.sha256, bad.md5)).unwrap();
+        let allowlist = temp_dir.path().join("allowlist.yaml");
+        fs::write(&allowlist, format!(
+            "iocs:\n  - ioc: mimikatz\n    paths: ['*\\Windows\\System32\\*']\n  - ioc: {}\n    paths: ['*bad.exe']\nhashes: [{}]\n",
+            bad.md5, tool.sha1
+        )).unwrap();
+
+        let main_args = MainArgs { ioc_file: ioc_file.to_string_lossy().to_string(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+        let wisker: Wiskers = serde_yaml::from_str(&format!(
+            "name: iocs\nbinary: 'builtin:iocs'\nargs: '--input-only --allowlist {}'\noutfolder: IOC_Findings\noutfile: ioc_findings.csv\ninput: base\n",
+            allowlist.display()
+        )).unwrap();
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "IOCs matched: 2 of 4, with 2 hits in 4 files scanned (0 skipped) and 3 hits suppressed by the allowlist");
+        let summary = fs::read_to_string(out_path.join("IOC_Findings").join("ioc_findings_summary.csv")).unwrap();
+        let rows: Vec<Vec<&str>> = summary.lines().skip(1).map(|l| l.split(',').collect()).collect();
+        let mut counts: Vec<(&str, &str, &str)> = rows.iter().map(|r| (r[0], r[3], r[7])).collect();
+        counts.sort();
+        let mut expected = vec![
+            ("mimikatz", "1", "1"),
+            ("psexec", "1", "0"),
+            (bad.md5.as_str(), "0", "1"),
+            (tool.sha256.as_str(), "0", "1"),
+        ];
+        expected.sort();
+        assert_eq!(counts, expected);
+        let findings = fs::read_to_string(out_path.join("IOC_Findings").join("ioc_findings.csv")).unwrap();
+        assert!(!findings.contains("Wiskess") && !findings.contains("System32"), "{findings}");
+
+        let wisker: Wiskers = serde_yaml::from_str(&format!(
+            "name: iocs_hashes\nbinary: 'builtin:hash_match'\nargs: '--allowlist {}'\noutfolder: IOC_Findings\noutfile: hash_matches.csv\ninput: base\n",
+            allowlist.display()
+        )).unwrap();
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.starts_with("Hash IOC matches: 0 from 2 hash IOCs, with 3 files hashed"), "{msg}");
+        assert!(msg.ends_with("and 2 matches suppressed by the allowlist"), "{msg}");
+    }
+}
\ No newline at end of file
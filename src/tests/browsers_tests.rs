@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::browsers::{self, BrowserKind};
+
+    /// 2023-03-15T12:00:00Z as WebKit microseconds since 1601
+    const WEBKIT_TIME: i64 = 13_323_355_200_000_000;
+    /// 2023-03-15T12:00:00Z as Firefox PRTime microseconds since 1970
+    const PR_TIME: i64 = 1_678_881_600_000_000;
+
+    fn chrome_history(profile: &Path) -> Connection {
+        fs::create_dir_all(profile).unwrap();
+        let conn = Connection::open(profile.join("History")).unwrap();
+        conn.execute_batch(&format!(
+            "PRAGMA journal_mode=WAL;
+             PRAGMA wal_autocheckpoint=0;
+             CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER, last_visit_time INTEGER);
+             CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER, from_visit INTEGER, transition INTEGER);
+             CREATE TABLE downloads (id INTEGER PRIMARY KEY, target_path TEXT, start_time INTEGER, end_time INTEGER,
+                 total_bytes INTEGER, state INTEGER, danger_type INTEGER, referrer TEXT, tab_url TEXT,
+                 tab_referrer_url TEXT, mime_type TEXT);
+             CREATE TABLE downloads_url_chains (id INTEGER, chain_index INTEGER, url TEXT);
+             CREATE TABLE keyword_search_terms (keyword_id INTEGER, url_id INTEGER, term TEXT);
+             INSERT INTO urls VALUES (1, 'https://mail.example.com/', 'Mail', 1, {WEBKIT_TIME});
+             INSERT INTO urls VALUES (2, 'https://www.google.com/search?q=rclone', 'rclone - Search', 1, {WEBKIT_TIME});
+             INSERT INTO visits VALUES (1, 1, {WEBKIT_TIME}, 0, 805306369);
+             INSERT INTO keyword_search_terms VALUES (2, 2, 'rclone');
+             INSERT INTO downloads VALUES (1, 'C:\\Users\\alice\\Downloads\\invoice.exe', {WEBKIT_TIME}, {WEBKIT_TIME},
+                 2048, 1, 0, 'https://mail.example.com/', 'https://mail.example.com/', '', 'application/octet-stream');
+             INSERT INTO downloads_url_chains VALUES (1, 0, 'https://bit.ly/abc');
+             INSERT INTO downloads_url_chains VALUES (1, 1, 'https://evil.example.net/invoice.exe');"
+        )).unwrap();
+        conn
+    }
+
+    fn firefox_places(profile: &Path) {
+        fs::create_dir_all(profile).unwrap();
+        let conn = Connection::open(profile.join("places.sqlite")).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER);
+             CREATE TABLE moz_historyvisits (id INTEGER PRIMARY KEY, from_visit INTEGER, place_id INTEGER, visit_date INTEGER, visit_type INTEGER);
+             CREATE TABLE moz_anno_attributes (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE moz_annos (id INTEGER PRIMARY KEY, place_id INTEGER, anno_attribute_id INTEGER, content TEXT, dateAdded INTEGER);
+             INSERT INTO moz_places VALUES (1, 'https://files.example.org/', 'Files', 1);
+             INSERT INTO moz_places VALUES (2, 'https://files.example.org/tool.zip', NULL, 0);
+             INSERT INTO moz_historyvisits VALUES (1, 0, 1, {PR_TIME}, 1);
+             INSERT INTO moz_historyvisits VALUES (2, 1, 2, {PR_TIME}, 7);
+             INSERT INTO moz_anno_attributes VALUES (1, 'downloads/destinationFileURI');
+             INSERT INTO moz_anno_attributes VALUES (2, 'downloads/metaData');
+             INSERT INTO moz_annos VALUES (1, 2, 1, 'file:///C:/Users/bob/Downloads/my%20tool.zip', {PR_TIME});
+             INSERT INTO moz_annos VALUES (2, 2, 2, '{{\"state\":1,\"endTime\":1678881660000,\"fileSize\":4096}}', {PR_TIME});"
+        )).unwrap();
+    }
+
+    fn read_csv(path: &PathBuf) -> Vec<HashMap<String, String>> {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        reader.records()
+            .map(|r| headers.iter().zip(r.unwrap().iter()).map(|(h, v)| (h.to_string(), v.to_string())).collect())
+            .collect()
+    }
+
+    /// Test Chromium and Firefox profiles are found in each user's profile folder
+    #[test]
+    fn test_find_browser_profiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let users = temp_dir.path().join("Users");
+        let _conn = chrome_history(&users.join("alice/AppData/Local/Google/Chrome/User Data/Profile 1"));
+        firefox_places(&users.join("bob/AppData/Roaming/Mozilla/Firefox/Profiles/abc.default-release"));
+        fs::create_dir_all(users.join("carol/AppData/Local/Microsoft/Edge/User Data/Default")).unwrap();
+
+        let profiles = browsers::find_profiles(&users);
+        assert_eq!(profiles.len(), 2);
+        assert_eq!((profiles[0].user.as_str(), profiles[0].browser.as_str(), profiles[0].name.as_str()), ("alice", "Chrome", "Profile 1"));
+        assert_eq!(profiles[0].kind, BrowserKind::Chromium);
+        assert_eq!((profiles[1].user.as_str(), profiles[1].browser.as_str()), ("bob", "Firefox"));
+    }
+
+    /// Test visits, downloads and searches are written, including records still in the WAL
+    /// and the host URL from the Zone.Identifier of the downloaded file
+    #[test]
+    fn test_browsers_builtin_writes_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let users = temp_dir.path().join("Users");
+        // keep the connection open so the records stay in the write-ahead log
+        let _conn = chrome_history(&users.join("alice/AppData/Local/Google/Chrome/User Data/Default"));
+        firefox_places(&users.join("bob/AppData/Roaming/Mozilla/Firefox/Profiles/abc.default-release"));
+        let downloads = users.join("alice/Downloads");
+        fs::create_dir_all(&downloads).unwrap();
+        fs::write(downloads.join("invoice.exe%3AZone.Identifier"),
+            "[ZoneTransfer]\r\nZoneId=3\r\nReferrerUrl=https://mail.example.com/\r\nHostUrl=https://evil.example.net/invoice.exe\r\n").unwrap();
+
+        let out_path = temp_dir.path().join("out");
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2023-01-01", "2023-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: browsers\nbinary: 'builtin:browsers'\nargs: ''\noutfolder: Browsers\noutfile: 'browser_*.csv'\ninput: user_dir\n"
+        ).unwrap();
+        let data_paths = HashMap::from([("user_dir".to_string(), users.to_string_lossy().to_string())]);
+
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("from 2 profiles - visits: 3, downloads: 2, searches: 1"), "{msg}");
+
+        let out = out_path.join("Browsers");
+        let visits = read_csv(&out.join("browser_visits.csv"));
+        assert_eq!(visits[0]["User"], "alice");
+        assert_eq!(visits[0]["VisitTime"], "2023-03-15T12:00:00.000Z");
+        assert_eq!(visits[0]["VisitType"], "TYPED");
+        assert_eq!(visits[2]["FromUrl"], "https://files.example.org/");
+        assert_eq!(visits[2]["VisitType"], "DOWNLOAD");
+
+        let downloads = read_csv(&out.join("browser_downloads.csv"));
+        assert_eq!(downloads.len(), 2);
+        assert_eq!(downloads[0]["Url"], "https://evil.example.net/invoice.exe");
+        assert_eq!(downloads[0]["UrlChain"], "https://bit.ly/abc -> https://evil.example.net/invoice.exe");
+        assert_eq!(downloads[0]["Referrer"], "https://mail.example.com/");
+        assert_eq!(downloads[0]["State"], "COMPLETE");
+        assert_eq!(downloads[0]["ZoneId"], "3");
+        assert_eq!(downloads[0]["ZoneHostUrl"], "https://evil.example.net/invoice.exe");
+        assert_eq!(downloads[1]["User"], "bob");
+        assert_eq!(downloads[1]["TargetPath"], "C:\\Users\\bob\\Downloads\\my tool.zip");
+        assert_eq!(downloads[1]["Referrer"], "https://files.example.org/");
+        assert_eq!(downloads[1]["EndTime"], "2023-03-15T12:01:00.000Z");
+        assert_eq!(downloads[1]["TotalBytes"], "4096");
+
+        let searches = read_csv(&out.join("browser_searches.csv"));
+        assert_eq!(searches[0]["Term"], "rclone");
+        assert!(out.join("browser_logins.csv").exists());
+    }
+
+    /// Test a missing Zone.Identifier returns None
+    #[test]
+    fn test_zone_identifier_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(browsers::zone_identifier(temp_dir.path(), "C:\\Users\\nobody\\file.exe").is_none());
+    }
+}
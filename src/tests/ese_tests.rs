@@ -0,0 +1,379 @@
+/// Builds small synthetic ESE databases with 4096 byte pages, for testing the parsers
+/// that read ESE databases, i.e. SRUDB.dat and the UAL Current.mdb
+#[cfg(test)]
+pub(crate) mod builder {
+    const PAGE_SIZE: usize = 4096;
+    const PAGE_LEAF: u32 = 0x2;
+    const PAGE_ROOT: u32 = 0x1;
+    const PAGE_PARENT: u32 = 0x4;
+    const PAGE_LONG_VALUE: u32 = 0x80;
+
+    /// The value of a column in a record
+    pub(crate) enum Cell {
+        Null,
+        Bytes(Vec<u8>),
+        /// a tagged value stored in the long value tree by its identifier
+        LongValue(u32),
+        /// a tagged value with the compressed flag, the bytes include the compression header
+        Compressed(Vec<u8>),
+    }
+
+    pub(crate) struct ColumnDef {
+        pub id: u32,
+        pub name: String,
+        pub coltyp: u32,
+        pub size: u32,
+        pub codepage: u32,
+    }
+
+    pub(crate) struct TableDef {
+        pub name: String,
+        pub columns: Vec<ColumnDef>,
+        pub rows: Vec<Vec<Cell>>,
+        pub long_values: Vec<(u32, Vec<u8>)>,
+        /// split the rows over leaf pages under a branch root page
+        pub rows_per_page: Option<usize>,
+    }
+
+    impl TableDef {
+        pub(crate) fn new(name: &str) -> Self {
+            TableDef {
+                name: name.to_string(),
+                columns: Vec::new(),
+                rows: Vec::new(),
+                long_values: Vec::new(),
+                rows_per_page: None,
+            }
+        }
+
+        /// add a column, the size is used for fixed columns (id <= 127)
+        pub(crate) fn column(mut self, id: u32, name: &str, coltyp: u32, size: u32, codepage: u32) -> Self {
+            self.columns.push(ColumnDef { id, name: name.to_string(), coltyp, size, codepage });
+            self
+        }
+
+        /// add a row, with a cell for each column in the order they were added
+        pub(crate) fn row(mut self, cells: Vec<Cell>) -> Self {
+            self.rows.push(cells);
+            self
+        }
+    }
+
+    pub(crate) fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    pub(crate) fn int32(v: i32) -> Cell {
+        Cell::Bytes(v.to_le_bytes().to_vec())
+    }
+
+    pub(crate) fn int64(v: i64) -> Cell {
+        Cell::Bytes(v.to_le_bytes().to_vec())
+    }
+
+    pub(crate) fn float64(v: f64) -> Cell {
+        Cell::Bytes(v.to_le_bytes().to_vec())
+    }
+
+    pub(crate) fn text(s: &str) -> Cell {
+        Cell::Bytes(utf16(s))
+    }
+
+    /// build the record data of fixed, variable and tagged columns
+    pub(crate) fn record(columns: &[ColumnDef], cells: &[Cell]) -> Vec<u8> {
+        let mut fixed = Vec::new();
+        let mut last_fixed = 0;
+        let mut var_ends: Vec<u16> = Vec::new();
+        let mut var_data: Vec<u8> = Vec::new();
+        let mut tagged: Vec<(u16, u8, Vec<u8>)> = Vec::new();
+        for (column, cell) in columns.iter().zip(cells) {
+            if column.id <= 127 {
+                let mut bytes = match cell {
+                    Cell::Bytes(b) => b.clone(),
+                    _ => Vec::new(),
+                };
+                bytes.resize(column.size as usize, 0);
+                fixed.extend(bytes);
+                last_fixed = column.id;
+            } else if column.id <= 255 {
+                while var_ends.len() < (column.id - 128) as usize {
+                    var_ends.push(var_data.len() as u16 | 0x8000);
+                }
+                match cell {
+                    Cell::Bytes(b) => {
+                        var_data.extend(b);
+                        var_ends.push(var_data.len() as u16);
+                    }
+                    _ => var_ends.push(var_data.len() as u16 | 0x8000),
+                }
+            } else {
+                match cell {
+                    Cell::Bytes(b) => tagged.push((column.id as u16, 0, b.clone())),
+                    Cell::LongValue(lid) => tagged.push((column.id as u16, 0x4, lid.to_le_bytes().to_vec())),
+                    Cell::Compressed(b) => tagged.push((column.id as u16, 0x2, b.clone())),
+                    Cell::Null => (),
+                }
+            }
+        }
+        let var_offset = 4 + fixed.len();
+        let mut data = vec![last_fixed as u8, (127 + var_ends.len()) as u8];
+        data.extend((var_offset as u16).to_le_bytes());
+        data.extend(fixed);
+        for end in &var_ends {
+            data.extend(end.to_le_bytes());
+        }
+        data.extend(var_data);
+        let mut offset = tagged.len() * 4;
+        let mut tagged_data = Vec::new();
+        for (id, flags, bytes) in &tagged {
+            data.extend(id.to_le_bytes());
+            let flag_bit = if *flags != 0 { 0x4000 } else { 0 };
+            data.extend((offset as u16 | flag_bit).to_le_bytes());
+            if *flags != 0 {
+                tagged_data.push(*flags);
+                offset += 1;
+            }
+            tagged_data.extend(bytes);
+            offset += bytes.len();
+        }
+        data.extend(tagged_data);
+        data
+    }
+
+    /// build a page with the first tag as the key prefix and an entry for each key and data
+    fn page(flags: u32, entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut page = vec![0u8; PAGE_SIZE];
+        page[34..36].copy_from_slice(&((entries.len() + 1) as u16).to_le_bytes());
+        page[36..40].copy_from_slice(&flags.to_le_bytes());
+        let mut offset = 0usize;
+        let mut tags = vec![(0usize, 0usize)];
+        for (key, data) in entries {
+            let mut value = (key.len() as u16).to_le_bytes().to_vec();
+            value.extend(key);
+            value.extend(data);
+            page[40 + offset..40 + offset + value.len()].copy_from_slice(&value);
+            tags.push((value.len(), offset));
+            offset += value.len();
+        }
+        for (i, (size, off)) in tags.iter().enumerate() {
+            let pos = PAGE_SIZE - 4 * (i + 1);
+            page[pos..pos + 2].copy_from_slice(&(*size as u16).to_le_bytes());
+            page[pos + 2..pos + 4].copy_from_slice(&(*off as u16).to_le_bytes());
+        }
+        page
+    }
+
+    /// add the entries as a B+ tree from the next free page, returning the root page number
+    fn add_tree(pages: &mut Vec<Vec<u8>>, extra_flags: u32, entries: Vec<(Vec<u8>, Vec<u8>)>, per_page: Option<usize>) -> u32 {
+        let root = pages.len() as u32;
+        match per_page {
+            None => pages.push(page(PAGE_ROOT | PAGE_LEAF | extra_flags, &entries)),
+            Some(per_page) => {
+                pages.push(Vec::new());
+                let mut branch = Vec::new();
+                for chunk in entries.chunks(per_page.max(1)) {
+                    let child = pages.len() as u32;
+                    pages.push(page(PAGE_LEAF | extra_flags, chunk));
+                    branch.push((chunk.last().unwrap().0.clone(), child.to_le_bytes().to_vec()));
+                }
+                pages[root as usize] = page(PAGE_ROOT | PAGE_PARENT | extra_flags, &branch);
+            }
+        }
+        root
+    }
+
+    fn catalog_row(objid: u32, obj_type: u16, id: u32, coltyp: u32, space: u32, codepage: u32, name: &str) -> Vec<u8> {
+        let mut data = vec![11u8, 128u8];
+        let mut fixed = Vec::new();
+        fixed.extend(objid.to_le_bytes());
+        fixed.extend(obj_type.to_le_bytes());
+        fixed.extend(id.to_le_bytes());
+        fixed.extend(coltyp.to_le_bytes());
+        fixed.extend(space.to_le_bytes());
+        fixed.extend(0u32.to_le_bytes());
+        fixed.extend(codepage.to_le_bytes());
+        fixed.push(0);
+        fixed.extend(0u16.to_le_bytes());
+        fixed.extend(0u32.to_le_bytes());
+        fixed.extend(0u16.to_le_bytes());
+        data.extend(((4 + fixed.len()) as u16).to_le_bytes());
+        data.extend(fixed);
+        data.extend((name.len() as u16).to_le_bytes());
+        data.extend(name.as_bytes());
+        data
+    }
+
+    /// build the database file with the catalog at page 4 and the tables after it
+    pub(crate) fn build(tables: &[TableDef]) -> Vec<u8> {
+        // pages 0 to 3 are unused by the reader, page 4 is the catalog
+        let mut pages: Vec<Vec<u8>> = vec![vec![0u8; PAGE_SIZE]; 5];
+        let mut catalog = Vec::new();
+        for (i, table) in tables.iter().enumerate() {
+            let objid = 100 + i as u32;
+            let rows: Vec<(Vec<u8>, Vec<u8>)> = table.rows
+                .iter()
+                .enumerate()
+                .map(|(n, cells)| ((n as u32).to_be_bytes().to_vec(), record(&table.columns, cells)))
+                .collect();
+            let fdp = add_tree(&mut pages, 0, rows, table.rows_per_page);
+            catalog.push(catalog_row(objid, 1, objid, fdp, 0, 0, &table.name));
+            for column in &table.columns {
+                catalog.push(catalog_row(objid, 2, column.id, column.coltyp, column.size, column.codepage, &column.name));
+            }
+            if !table.long_values.is_empty() {
+                let mut lv_entries = Vec::new();
+                for (lid, data) in &table.long_values {
+                    let mut root_data = 1u32.to_le_bytes().to_vec();
+                    root_data.extend((data.len() as u32).to_le_bytes());
+                    lv_entries.push((lid.to_be_bytes().to_vec(), root_data));
+                    // store in two segments to test they are joined in order
+                    let half = data.len() / 2;
+                    for (offset, part) in [(0, &data[..half]), (half, &data[half..])] {
+                        let mut key = lid.to_be_bytes().to_vec();
+                        key.extend((offset as u32).to_be_bytes());
+                        lv_entries.push((key, part.to_vec()));
+                    }
+                }
+                let lv_fdp = add_tree(&mut pages, PAGE_LONG_VALUE, lv_entries, None);
+                catalog.push(catalog_row(objid, 4, objid, lv_fdp, 0, 0, &format!("LV{}", table.name)));
+            }
+        }
+        let keyed: Vec<(Vec<u8>, Vec<u8>)> = catalog
+            .into_iter()
+            .enumerate()
+            .map(|(n, data)| ((n as u32).to_be_bytes().to_vec(), data))
+            .collect();
+        pages[4] = page(PAGE_ROOT | PAGE_LEAF, &keyed);
+
+        let mut header = vec![0u8; PAGE_SIZE];
+        header[4..8].copy_from_slice(&0x89AB_CDEFu32.to_le_bytes());
+        header[8..12].copy_from_slice(&0x620u32.to_le_bytes());
+        header[232..236].copy_from_slice(&0x14u32.to_le_bytes());
+        header[236..240].copy_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+        let mut file = header;
+        for page in pages {
+            file.extend(page);
+        }
+        file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::builder::{self, Cell, TableDef};
+    use crate::parsers::ese::{coltyp, EseDb, EseValue, CODEPAGE_UNICODE};
+
+    fn sample_table() -> TableDef {
+        TableDef::new("Sample")
+            .column(1, "AutoIncId", coltyp::LONG, 4, 0)
+            .column(2, "TimeStamp", coltyp::DATE_TIME, 8, 0)
+            .column(3, "Count", coltyp::LONG_LONG, 8, 0)
+            .column(128, "Label", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .column(129, "Blob", coltyp::BINARY, 0, 0)
+            .column(256, "Note", coltyp::LONG_TEXT, 0, CODEPAGE_UNICODE)
+            .row(vec![
+                builder::int32(1),
+                builder::float64(45000.5),
+                builder::int64(1234),
+                builder::text("first"),
+                Cell::Bytes(vec![0xde, 0xad]),
+                builder::text("tagged"),
+            ])
+            .row(vec![
+                builder::int32(2),
+                builder::float64(0.0),
+                builder::int64(-5),
+                Cell::Null,
+                Cell::Bytes(vec![0x01]),
+                Cell::Null,
+            ])
+    }
+
+    /// Test the catalog is read with the tables and their columns
+    #[test]
+    fn test_ese_reads_catalog() {
+        let db = EseDb::from_bytes(builder::build(&[sample_table(), TableDef::new("Other")])).unwrap();
+        let names: Vec<&str> = db.tables().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["Sample", "Other"]);
+        let table = db.table("sample").unwrap();
+        let columns: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(columns, vec!["AutoIncId", "TimeStamp", "Count", "Label", "Blob", "Note"]);
+    }
+
+    /// Test fixed, variable and tagged column values are decoded by type
+    #[test]
+    fn test_ese_decodes_record_values() {
+        let db = EseDb::from_bytes(builder::build(&[sample_table()])).unwrap();
+        let records = db.table_records("Sample").unwrap();
+        assert_eq!(records.len(), 2);
+
+        let first = &records[0];
+        assert_eq!(first.get("AutoIncId"), Some(&EseValue::Int(1)));
+        assert_eq!(first.get("Count"), Some(&EseValue::Int(1234)));
+        assert_eq!(first.get("Label"), Some(&EseValue::Text("first".to_string())));
+        assert_eq!(first.get("Blob").unwrap().to_string(), "dead");
+        assert_eq!(first.get("Note").unwrap().as_str(), Some("tagged"));
+        assert_eq!(first.get("TimeStamp").unwrap().to_string(), "2023-03-15T12:00:00.000Z");
+
+        let second = &records[1];
+        assert_eq!(second.get("Count"), Some(&EseValue::Int(-5)));
+        assert!(second.get("Label").unwrap().is_null());
+        assert!(second.get("Note").unwrap().is_null());
+        assert_eq!(second.get("TimeStamp").unwrap().to_string(), "");
+    }
+
+    /// Test tagged values stored in the long value tree are joined from their segments
+    #[test]
+    fn test_ese_reads_long_values() {
+        let long_text = "a long value that is stored outside of the record";
+        let mut table = TableDef::new("Long")
+            .column(1, "Id", coltyp::LONG, 4, 0)
+            .column(256, "Data", coltyp::LONG_TEXT, 0, CODEPAGE_UNICODE)
+            .row(vec![builder::int32(7), Cell::LongValue(0x42)]);
+        table.long_values.push((0x42, builder::utf16(long_text)));
+
+        let db = EseDb::from_bytes(builder::build(&[table])).unwrap();
+        let records = db.table_records("Long").unwrap();
+        assert_eq!(records[0].get("Data").unwrap().as_str(), Some(long_text));
+    }
+
+    /// Test 7-bit compressed text in a tagged column is decompressed
+    #[test]
+    fn test_ese_decompresses_seven_bit_text() {
+        // pack "Hi" as 7-bit ASCII after the header byte (type 1 << 3)
+        let packed = vec![0x08, b'H' | ((b'i' & 0x01) << 7), b'i' >> 1];
+        let table = TableDef::new("Packed")
+            .column(256, "Name", coltyp::LONG_TEXT, 0, CODEPAGE_UNICODE)
+            .row(vec![Cell::Compressed(packed)]);
+
+        let db = EseDb::from_bytes(builder::build(&[table])).unwrap();
+        let records = db.table_records("Packed").unwrap();
+        assert_eq!(records[0].get("Name").unwrap().as_str(), Some("Hi"));
+    }
+
+    /// Test records are read from every leaf page under a branch page, in key order
+    #[test]
+    fn test_ese_walks_branch_pages() {
+        let mut table = TableDef::new("Many").column(1, "Id", coltyp::LONG, 4, 0);
+        for i in 0..10 {
+            table = table.row(vec![builder::int32(i)]);
+        }
+        table.rows_per_page = Some(3);
+
+        let db = EseDb::from_bytes(builder::build(&[table])).unwrap();
+        let ids: Vec<i64> = db.table_records("Many")
+            .unwrap()
+            .iter()
+            .filter_map(|r| r.get("Id").and_then(|v| v.as_i64()))
+            .collect();
+        assert_eq!(ids, (0..10).collect::<Vec<i64>>());
+    }
+
+    /// Test a file that isn't an ESE database is rejected
+    #[test]
+    fn test_ese_rejects_invalid_signature() {
+        assert!(EseDb::from_bytes(vec![0u8; 8192]).is_err());
+        let db = EseDb::from_bytes(builder::build(&[])).unwrap();
+        assert!(db.table_records("Missing").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::iocs::hash_match::{self, HashFilter};
+    use crate::ops::builtin_ops;
+    use crate::parsers::hashes;
+
+    /// write the file, returning its hashes
+    fn write(path: &Path, data: &str) -> hashes::FileHashes {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+        hashes::hash_file(path).unwrap()
+    }
+
+    /// Test the filter of the files hashed is read from the args
+    #[test]
+    fn test_hash_filter() {
+        let filter = HashFilter::from_args("--max-size 5 --exclude WinSxS,\\Installer\\ --include windows").unwrap();
+        assert_eq!(filter.max_size, 5 * 1024 * 1024);
+        assert!(filter.path_allowed(Path::new(r"C:\Windows\System32\cmd.exe")));
+        assert!(!filter.path_allowed(Path::new(r"C:\Windows\WinSxS\cmd.exe")));
+        assert!(!filter.path_allowed(Path::new(r"C:\Users\a\cmd.exe")));
+        assert!(filter.extension_allowed(Path::new("evil.PS1")));
+        assert!(!filter.extension_allowed(Path::new("notes.txt")));
+        assert!(HashFilter::from_args("--all-files").unwrap().extension_allowed(Path::new("notes.txt")));
+        assert!(HashFilter::from_args("--extensions .txt").unwrap().extension_allowed(Path::new("notes.txt")));
+        assert!(HashFilter::from_args("--max-size big").is_err());
+
+        assert!(hash_match::is_recycled(Path::new("/c/$Recycle.Bin/S-1-5-21-1/$RAB12CD/tool.dll")));
+        assert!(!hash_match::is_recycled(Path::new("/c/$Recycle.Bin/S-1-5-21-1/$IAB12CD.exe")));
+    }
+
+    /// Test the executables of the data source, recycled files, collected artefacts and
+    /// Amcache entries are matched with the hash IOCs
+    #[test]
+    fn test_run_builtin_hash_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("collection");
+        let out_path = temp_dir.path().join("out");
+        let exe = write(&input.join("Windows").join("Temp").join("svc.exe"), "payload");
+        let notes = write(&input.join("Users").join("a").join("notes.txt"), "notes");
+        let excluded = write(&input.join("Windows").join("WinSxS").join("old.dll"), "old");
+        let recycled = write(&input.join("$Recycle.Bin").join("S-1-5-21-1").join("$RAB12CD.docx"), "document");
+        let collected = write(&out_path.join("Artefacts").join("dump.bin"), "collected");
+        let amcache = out_path.join("FileExecution").join("20240101_Amcache_UnassociatedFileEntries.csv");
+        fs::create_dir_all(amcache.parent().unwrap()).unwrap();
+        fs::write(&amcache, format!(
+            "ProgramName,FullPath,SHA1,Size\nUnassociated,c:\\users\\a\\rclone.exe,0000{},1234\nUnassociated,c:\\windows\\notepad.exe,,10\n",
+            "a".repeat(40)
+        )).unwrap();
+
+        let ioc_file = temp_dir.path().join("iocs.csv");
+        fs::write(&ioc_file, format!(
+            "value,type,list,description\n{},sha256,intel,loader\n{},,intel,\n{},md5,intel,\n{},,intel,\n{},,intel,\n{},sha1,amcache,rclone\nevil.com,,intel,\n",
+            exe.sha256, notes.md5, excluded.md5, recycled.sha1, collected.md5, "A".repeat(40)
+        )).unwrap();
+
+        let main_args = MainArgs { ioc_file: ioc_file.to_string_lossy().to_string(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: iocs_hashes\nbinary: 'builtin:hash_match'\nargs: '--exclude winsxs'\noutfolder: IOC_Findings\noutfile: hash_matches.csv\ninput: base\n"
+        ).unwrap();
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("Hash IOC matches: 4 from 6 hash IOCs, with 3 files hashed and 1 Amcache entries checked"), "{msg}");
+
+        let matches = fs::read_to_string(out_path.join("IOC_Findings").join("hash_matches.csv")).unwrap();
+        let rows: Vec<Vec<&str>> = matches.lines().map(|l| l.split(',').collect()).collect();
+        assert_eq!(rows[0], vec!["Path", "Source", "Size", "HashType", "Ioc", "List", "Description", "Event", "Tags", "Confidence"]);
+        let found: Vec<(&str, &str, &str)> = rows[1..].iter().map(|r| (r[1], r[3], r[4])).collect();
+        let mut expected = vec![
+            ("data_source", "sha256", exe.sha256.as_str()),
+            ("recycle_bin", "sha1", recycled.sha1.as_str()),
+            ("artefacts", "md5", collected.md5.as_str()),
+        ];
+        let amcache_sha1 = "a".repeat(40);
+        expected.push(("amcache", "sha1", amcache_sha1.as_str()));
+        expected.sort_by(|a, b| a.2.cmp(b.2));
+        assert_eq!(found, expected);
+        let amcache_row = rows.iter().find(|r| r[1] == "amcache").unwrap();
+        assert_eq!((amcache_row[0], amcache_row[2], amcache_row[5], amcache_row[6]), ("c:\\users\\a\\rclone.exe", "1234", "amcache", "rclone"));
+    }
+
+    /// Test a list without hashes writes the header of the matches
+    #[test]
+    fn test_hash_match_without_hash_iocs() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let ioc_file = temp_dir.path().join("iocs.txt");
+        fs::write(&ioc_file, "psexesvc\n").unwrap();
+        let main_args = MainArgs { ioc_file: ioc_file.to_string_lossy().to_string(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: iocs_hashes\nbinary: 'builtin:hash_match'\nargs: ''\noutfolder: IOC_Findings\noutfile: hash_matches.csv\ninput: none\n"
+        ).unwrap();
+        let data_paths = HashMap::from([("none".to_string(), "".to_string())]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "No hash IOCs to match");
+        let matches = fs::read_to_string(out_path.join("IOC_Findings").join("hash_matches.csv")).unwrap();
+        assert_eq!(matches, "Path,Source,Size,HashType,Ioc,List,Description,Event,Tags,Confidence\n");
+    }
+}
\ No newline at end of file
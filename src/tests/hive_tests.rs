@@ -0,0 +1,175 @@
+/// Builds small synthetic registry hives, for testing the parsers that read
+/// the SYSTEM, SOFTWARE and SAM hives
+#[cfg(test)]
+pub(crate) mod builder {
+    use crate::parsers::hive::{REG_DWORD, REG_SZ};
+
+    pub(crate) struct HiveBuilder {
+        bin: Vec<u8>,
+    }
+
+    impl HiveBuilder {
+        pub(crate) fn new() -> Self {
+            let mut bin = b"hbin".to_vec();
+            bin.resize(32, 0);
+            HiveBuilder { bin }
+        }
+
+        /// add an allocated cell, returning its offset from the start of the hive bins
+        fn cell(&mut self, data: &[u8]) -> u32 {
+            let offset = self.bin.len() as u32;
+            let size = (data.len() + 4).div_ceil(8) * 8;
+            self.bin.extend((-(size as i32)).to_le_bytes());
+            self.bin.extend(data);
+            self.bin.resize(offset as usize + size, 0);
+            offset
+        }
+
+        /// add a value, stored inline, in a cell or in big data segments by its size
+        pub(crate) fn value(&mut self, name: &str, data_type: u32, data: &[u8]) -> u32 {
+            let (size, data_offset) = if data.len() <= 4 {
+                let mut inline = data.to_vec();
+                inline.resize(4, 0);
+                (data.len() as u32 | 0x8000_0000, u32::from_le_bytes(inline.try_into().unwrap()))
+            } else if data.len() > 16344 {
+                let segments: Vec<u32> = data.chunks(16344).map(|c| self.cell(c)).collect();
+                let list: Vec<u8> = segments.iter().flat_map(|s| s.to_le_bytes()).collect();
+                let list = self.cell(&list);
+                let mut db = b"db".to_vec();
+                db.extend((segments.len() as u16).to_le_bytes());
+                db.extend(list.to_le_bytes());
+                (data.len() as u32, self.cell(&db))
+            } else {
+                (data.len() as u32, self.cell(data))
+            };
+            let mut vk = b"vk".to_vec();
+            vk.extend((name.len() as u16).to_le_bytes());
+            vk.extend(size.to_le_bytes());
+            vk.extend(data_offset.to_le_bytes());
+            vk.extend(data_type.to_le_bytes());
+            vk.extend(1u16.to_le_bytes());
+            vk.extend(0u16.to_le_bytes());
+            vk.extend(name.as_bytes());
+            self.cell(&vk)
+        }
+
+        pub(crate) fn string(&mut self, name: &str, data: &str) -> u32 {
+            let mut bytes: Vec<u8> = data.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+            bytes.extend([0, 0]);
+            self.value(name, REG_SZ, &bytes)
+        }
+
+        pub(crate) fn dword(&mut self, name: &str, data: u32) -> u32 {
+            self.value(name, REG_DWORD, &data.to_le_bytes())
+        }
+
+        /// add a key with its subkeys and values, returning its offset
+        pub(crate) fn key(&mut self, name: &str, last_written: u64, subkeys: &[u32], values: &[u32]) -> u32 {
+            let mut subkey_list = 0xffff_ffffu32;
+            if !subkeys.is_empty() {
+                let mut lf = b"lf".to_vec();
+                lf.extend((subkeys.len() as u16).to_le_bytes());
+                for subkey in subkeys {
+                    lf.extend(subkey.to_le_bytes());
+                    lf.extend(0u32.to_le_bytes());
+                }
+                subkey_list = self.cell(&lf);
+            }
+            let mut value_list = 0xffff_ffffu32;
+            if !values.is_empty() {
+                let list: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+                value_list = self.cell(&list);
+            }
+            let mut nk = vec![0u8; 76];
+            nk[0..2].copy_from_slice(b"nk");
+            nk[2..4].copy_from_slice(&0x20u16.to_le_bytes());
+            nk[4..12].copy_from_slice(&last_written.to_le_bytes());
+            nk[20..24].copy_from_slice(&(subkeys.len() as u32).to_le_bytes());
+            nk[28..32].copy_from_slice(&subkey_list.to_le_bytes());
+            nk[36..40].copy_from_slice(&(values.len() as u32).to_le_bytes());
+            nk[40..44].copy_from_slice(&value_list.to_le_bytes());
+            nk[72..74].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            nk.extend(name.as_bytes());
+            self.cell(&nk)
+        }
+
+        /// add a chain of keys, i.e. `Microsoft\Windows NT`, with the last holding the
+        /// given subkeys and values. Returns the offset of the first key in the chain
+        pub(crate) fn path(&mut self, path: &str, subkeys: &[u32], values: &[u32]) -> u32 {
+            let names: Vec<&str> = path.split('\\').collect();
+            let mut child = self.key(names[names.len() - 1], 0, subkeys, values);
+            for name in names[..names.len() - 1].iter().rev() {
+                child = self.key(name, 0, &[child], &[]);
+            }
+            child
+        }
+
+        /// build the hive file with the root key
+        pub(crate) fn build(mut self, root: u32) -> Vec<u8> {
+            let size = self.bin.len().div_ceil(4096) * 4096;
+            self.bin.resize(size, 0);
+            self.bin[8..12].copy_from_slice(&(size as u32).to_le_bytes());
+            let mut file = vec![0u8; 4096];
+            file[0..4].copy_from_slice(b"regf");
+            file[0x24..0x28].copy_from_slice(&root.to_le_bytes());
+            file[0x28..0x2c].copy_from_slice(&(size as u32).to_le_bytes());
+            file.extend(self.bin);
+            file
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::builder::HiveBuilder;
+    use crate::parsers::hive::{Hive, REG_BINARY, REG_MULTI_SZ};
+
+    /// 2024-01-02T03:04:05Z as a FILETIME
+    const WRITTEN: u64 = 133_486_382_450_000_000;
+
+    fn sample_hive() -> Hive {
+        let mut hb = HiveBuilder::new();
+        let name = hb.string("ComputerName", "WKS01");
+        let count = hb.dword("Count", 42);
+        let multi: Vec<u8> = "a\0bc\0\0".encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        let multi = hb.value("Multi", REG_MULTI_SZ, &multi);
+        let big = hb.value("Big", REG_BINARY, &vec![0xab; 20000]);
+        let leaf = hb.key("ComputerName", WRITTEN, &[], &[name, count, multi, big]);
+        let control = hb.path(r"ControlSet001\Control", &[leaf], &[]);
+        let root = hb.key("ROOT", 0, &[control], &[]);
+        Hive::from_bytes(hb.build(root)).unwrap()
+    }
+
+    /// Test keys are found by a case-insensitive path, with their last written time
+    #[test]
+    fn test_hive_finds_key_by_path() {
+        let hive = sample_hive();
+        let key = hive.key(r"controlset001\CONTROL\ComputerName").unwrap();
+        assert_eq!(key.name(), "ComputerName");
+        assert_eq!(key.last_written().unwrap().to_rfc3339(), "2024-01-02T03:04:05+00:00");
+        assert!(hive.key(r"ControlSet001\Missing").is_none());
+        let subkeys: Vec<String> = hive.key("ControlSet001").unwrap().subkeys().iter().map(|k| k.name()).collect();
+        assert_eq!(subkeys, vec!["Control"]);
+    }
+
+    /// Test values are read as strings, numbers, multi strings and big data
+    #[test]
+    fn test_hive_reads_values() {
+        let hive = sample_hive();
+        let key = hive.key(r"ControlSet001\Control\ComputerName").unwrap();
+        assert_eq!(key.values().len(), 4);
+        assert_eq!(key.value("computername").unwrap().as_string(), "WKS01");
+        assert_eq!(key.value("Count").unwrap().as_u32(), Some(42));
+        assert_eq!(key.value("Count").unwrap().as_string(), "42");
+        assert_eq!(key.value("Multi").unwrap().as_multi_string(), vec!["a", "bc"]);
+        let big = key.value("Big").unwrap();
+        assert_eq!(big.data.len(), 20000);
+        assert!(big.data.iter().all(|b| *b == 0xab));
+    }
+
+    /// Test a file that isn't a registry hive is rejected
+    #[test]
+    fn test_hive_rejects_invalid_signature() {
+        assert!(Hive::from_bytes(vec![0u8; 8192]).is_err());
+    }
+}
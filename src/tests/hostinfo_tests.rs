@@ -0,0 +1,265 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::hive::{Hive, REG_BINARY, REG_MULTI_SZ};
+    use crate::parsers::hostinfo::{self, HostInfo, HOSTINFO_JSON, HOSTINFO_MARKDOWN};
+    use crate::tests::hive_tests::builder::HiveBuilder;
+
+    /// 2024-01-02T03:04:05Z as a FILETIME
+    const WRITTEN: u64 = 133_486_382_450_000_000;
+    const MACHINE_SID: &str = "S-1-5-21-100-200-300";
+    const USER_SID: &str = "S-1-5-21-1-2-3-1001";
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    fn system_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let current = hb.dword("Current", 1);
+        let select = hb.key("Select", 0, &[], &[current]);
+        let name = hb.string("ComputerName", "WKS01");
+        let computer_name = hb.path(r"ComputerName\ComputerName", &[], &[name]);
+        let tz_name = hb.string("TimeZoneKeyName", "GMT Standard Time");
+        let bias = hb.dword("Bias", 0);
+        let active_bias = hb.dword("ActiveTimeBias", (-60i32) as u32);
+        let tz = hb.key("TimeZoneInformation", 0, &[], &[tz_name, bias, active_bias]);
+        let shutdown = hb.value("ShutdownTime", REG_BINARY, &WRITTEN.to_le_bytes());
+        let windows = hb.key("Windows", 0, &[], &[shutdown]);
+        let connection_name = hb.string("Name", "Ethernet0");
+        let connection = hb.key("Connection", 0, &[], &[connection_name]);
+        let adapter_name = hb.key("{AAAA}", 0, &[connection], &[]);
+        let network = hb.path(r"Network\{4D36E972-E325-11CE-BFC1-08002BE10318}", &[adapter_name], &[]);
+        let control = hb.key("Control", 0, &[computer_name, tz, windows, network], &[]);
+
+        let dhcp = hb.dword("EnableDHCP", 1);
+        let ip = hb.string("DhcpIPAddress", "10.0.0.5");
+        let mask = hb.string("DhcpSubnetMask", "255.255.255.0");
+        let gateway = hb.value("DhcpDefaultGateway", REG_MULTI_SZ, &utf16("10.0.0.1\0\0"));
+        let dns = hb.string("DhcpNameServer", "10.0.0.2 10.0.0.3");
+        let lease = hb.dword("LeaseObtainedTime", 1_704_164_645);
+        let adapter = hb.key("{AAAA}", 0, &[], &[dhcp, ip, mask, gateway, dns, lease]);
+        let unused_dhcp = hb.dword("EnableDHCP", 0);
+        let unused_ip = hb.string("IPAddress", "0.0.0.0");
+        let unused = hb.key("{BBBB}", 0, &[], &[unused_dhcp, unused_ip]);
+        let interfaces = hb.key("Interfaces", 0, &[adapter, unused], &[]);
+        let domain = hb.string("Domain", "corp.local");
+        let tcpip = hb.path(r"Tcpip\Parameters", &[interfaces], &[domain]);
+        let services = hb.key("Services", 0, &[tcpip], &[]);
+        let control_set = hb.key("ControlSet001", 0, &[control, services], &[]);
+        let root = hb.key("ROOT", 0, &[select, control_set], &[]);
+        hb.build(root)
+    }
+
+    fn software_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let product = hb.string("ProductName", "Windows 10 Pro");
+        let edition = hb.string("EditionID", "Professional");
+        let display = hb.string("DisplayVersion", "23H2");
+        let build = hb.string("CurrentBuildNumber", "22631");
+        let ubr = hb.dword("UBR", 3007);
+        let install = hb.dword("InstallDate", 1_704_164_645);
+        let owner = hb.string("RegisteredOwner", "IT");
+        let path = hb.string("ProfileImagePath", r"C:\Users\jsmith");
+        let high = hb.dword("LocalProfileLoadTimeHigh", (WRITTEN >> 32) as u32);
+        let low = hb.dword("LocalProfileLoadTimeLow", WRITTEN as u32);
+        let profile = hb.key(USER_SID, 0, &[], &[path, high, low]);
+        let profiles = hb.key("ProfileList", 0, &[profile], &[]);
+        let current_nt = hb.key("CurrentVersion", 0, &[profiles], &[product, edition, display, build, ubr, install, owner]);
+        let windows_nt = hb.key("Windows NT", 0, &[current_nt], &[]);
+
+        let app_name = hb.string("DisplayName", "7-Zip 23.01");
+        let app_version = hb.string("DisplayVersion", "23.01");
+        let app_date = hb.string("InstallDate", "20240102");
+        let app = hb.key("7-Zip", WRITTEN, &[], &[app_name, app_version, app_date]);
+        let hidden = hb.key("KB5034441", WRITTEN, &[], &[]);
+        let uninstall = hb.key("Uninstall", 0, &[app, hidden], &[]);
+        let last_user = hb.string("LastLoggedOnUser", r"CORP\jsmith");
+        let logon_ui = hb.path(r"Authentication\LogonUI", &[], &[last_user]);
+        let current = hb.key("CurrentVersion", 0, &[uninstall, logon_ui], &[]);
+        let windows = hb.key("Windows", 0, &[current], &[]);
+        let microsoft = hb.key("Microsoft", 0, &[windows, windows_nt], &[]);
+        let root = hb.key("ROOT", 0, &[microsoft], &[]);
+        hb.build(root)
+    }
+
+    /// a SAM V or C value, with the strings at the header offsets and the extra data after them
+    fn sam_value(data_start: usize, strings: &[(usize, &str)], extra: &[u8]) -> (Vec<u8>, u32) {
+        let mut data = vec![0u8; data_start];
+        let mut tail = Vec::new();
+        for (header, s) in strings {
+            let bytes = utf16(s);
+            data[*header..header + 4].copy_from_slice(&(tail.len() as u32).to_le_bytes());
+            data[header + 4..header + 8].copy_from_slice(&(bytes.len() as u32).to_le_bytes());
+            tail.extend(bytes);
+        }
+        let extra_offset = tail.len() as u32;
+        tail.extend(extra);
+        data.extend(tail);
+        (data, extra_offset)
+    }
+
+    fn sam_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let mut account_v = vec![0u8; 8];
+        for n in [100u32, 200, 300] {
+            account_v.extend(n.to_le_bytes());
+        }
+        let account_v = hb.value("V", REG_BINARY, &account_v);
+
+        let mut f = vec![0u8; 0x50];
+        f[0x08..0x10].copy_from_slice(&WRITTEN.to_le_bytes());
+        f[0x18..0x20].copy_from_slice(&WRITTEN.to_le_bytes());
+        f[0x38..0x3A].copy_from_slice(&0x0011u16.to_le_bytes());
+        f[0x42..0x44].copy_from_slice(&7u16.to_le_bytes());
+        let f = hb.value("F", REG_BINARY, &f);
+        let (v, _) = sam_value(0xCC, &[(0x0C, "admin"), (0x18, "Local Admin"), (0x24, "Built-in")], &[]);
+        let v = hb.value("V", REG_BINARY, &v);
+        let user = hb.key("000001F4", 0, &[], &[f, v]);
+        let rid = hb.value("", 0x1F4, &[]);
+        let name = hb.key("admin", WRITTEN, &[], &[rid]);
+        let names = hb.key("Names", 0, &[name], &[]);
+        let users = hb.key("Users", 0, &[user, names], &[]);
+        let account = hb.key("Account", 0, &[users], &[account_v]);
+
+        let mut members = Vec::new();
+        for sid in [(5u64, vec![21, 100, 200, 300, 500]), (5, vec![21, 1, 2, 3, 1001])] {
+            members.extend([1u8, sid.1.len() as u8]);
+            members.extend(&sid.0.to_be_bytes()[2..]);
+            for sub in sid.1 {
+                members.extend((sub as u32).to_le_bytes());
+            }
+        }
+        let (mut c, offset) = sam_value(0x34, &[(0x10, "Administrators"), (0x1C, "Admins")], &members);
+        c[0x28..0x2C].copy_from_slice(&offset.to_le_bytes());
+        c[0x30..0x34].copy_from_slice(&2u32.to_le_bytes());
+        let c = hb.value("C", REG_BINARY, &c);
+        let alias = hb.key("00000220", 0, &[], &[c]);
+        let aliases = hb.key("Aliases", 0, &[alias], &[]);
+        let builtin = hb.key("Builtin", 0, &[aliases], &[]);
+        let domains = hb.key("Domains", 0, &[account, builtin], &[]);
+        let sam = hb.key("SAM", 0, &[domains], &[]);
+        let root = hb.key("ROOT", 0, &[sam], &[]);
+        hb.build(root)
+    }
+
+    fn info() -> HostInfo {
+        let system = Hive::from_bytes(system_hive()).unwrap();
+        let software = Hive::from_bytes(software_hive()).unwrap();
+        let sam = Hive::from_bytes(sam_hive()).unwrap();
+        hostinfo::host_info(Some(&system), Some(&software), Some(&sam))
+    }
+
+    /// Test the host, OS and timezone facts are read, with Windows 11 known by its build
+    #[test]
+    fn test_host_info_system_and_software() {
+        let info = info();
+        assert_eq!(info.hostname, "WKS01");
+        assert_eq!(info.domain, "corp.local");
+        assert!(info.domain_joined);
+        assert_eq!(info.os.product_name, "Windows 11 Pro");
+        assert_eq!(info.os.build, "22631.3007");
+        assert_eq!(info.os.display_version, "23H2");
+        assert_eq!(info.os.install_date, "2024-01-02T03:04:05.000Z");
+        assert_eq!(info.timezone.name, "GMT Standard Time");
+        assert_eq!(info.timezone.active_bias, Some(-60));
+        assert_eq!(info.last_shutdown, "2024-01-02T03:04:05.000Z");
+        assert_eq!(info.last_logged_on_user, r"CORP\jsmith");
+        assert_eq!(info.last_logons.len(), 1);
+        assert_eq!(info.last_logons[0].user, "jsmith");
+        assert_eq!(info.last_logons[0].last_logon, "2024-01-02T03:04:05.000Z");
+        assert_eq!(info.software.len(), 1);
+        assert_eq!(info.software[0].name, "7-Zip 23.01");
+        assert_eq!(info.software[0].install_date, "2024-01-02");
+    }
+
+    /// Test the DHCP interface is read with its name, and the unconfigured one is skipped
+    #[test]
+    fn test_host_info_interfaces() {
+        let info = info();
+        assert_eq!(info.interfaces.len(), 1);
+        let interface = &info.interfaces[0];
+        assert_eq!(interface.name, "Ethernet0");
+        assert_eq!(interface.guid, "{AAAA}");
+        assert!(interface.dhcp);
+        assert_eq!(interface.ip_addresses, vec!["10.0.0.5"]);
+        assert_eq!(interface.gateways, vec!["10.0.0.1"]);
+        assert_eq!(interface.dns_servers, vec!["10.0.0.2", "10.0.0.3"]);
+        assert_eq!(interface.lease_obtained, "2024-01-02T03:04:05.000Z");
+    }
+
+    /// Test the SAM users are read from the F and V values and the group members resolved
+    #[test]
+    fn test_host_info_sam_users_and_groups() {
+        let info = info();
+        assert_eq!(info.machine_sid, MACHINE_SID);
+        assert_eq!(info.users.len(), 1);
+        let user = &info.users[0];
+        assert_eq!(user.name, "admin");
+        assert_eq!(user.rid, 500);
+        assert_eq!(user.sid, format!("{MACHINE_SID}-500"));
+        assert_eq!(user.full_name, "Local Admin");
+        assert_eq!(user.comment, "Built-in");
+        assert_eq!(user.last_logon, "2024-01-02T03:04:05.000Z");
+        assert_eq!(user.last_failed_logon, "");
+        assert_eq!(user.logon_count, 7);
+        assert!(user.disabled);
+
+        assert_eq!(info.groups.len(), 1);
+        assert_eq!(info.groups[0].name, "Administrators");
+        assert_eq!(info.groups[0].rid, 0x220);
+        assert_eq!(info.groups[0].members, vec![
+            format!("admin ({MACHINE_SID}-500)"),
+            format!("jsmith ({USER_SID})"),
+        ]);
+    }
+
+    /// Test the builtin writes the JSON and Markdown, with the event log facts from EvtxECmd
+    #[test]
+    fn test_hostinfo_builtin_writes_reports() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = temp_dir.path().join("config");
+        fs::create_dir_all(&config).unwrap();
+        fs::write(config.join("SYSTEM"), system_hive()).unwrap();
+        fs::write(config.join("SOFTWARE"), software_hive()).unwrap();
+        fs::write(config.join("SAM"), sam_hive()).unwrap();
+        let out_path = temp_dir.path().join("out");
+        let event_logs = out_path.join("EventLogs");
+        fs::create_dir_all(&event_logs).unwrap();
+        fs::write(event_logs.join("EvtxECmd-All.csv"), "\
+TimeCreated,EventId,Channel,Computer
+2024-01-05 10:00:00.0000000,4624,Security,WKS01.corp.local
+2024-01-01 09:00:00.0000000,4672,Security,WKS01.corp.local
+2023-12-01 09:00:00.0000000,7045,System,WKS01.corp.local
+").unwrap();
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-01-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: hostinfo\nbinary: 'builtin:hostinfo'\nargs: ''\noutfolder: Timeline\noutfile: Host_Information.md\ninput: system\ninput_other: software\n"
+        ).unwrap();
+        let data_paths = HashMap::from([
+            ("system".to_string(), config.join("SYSTEM").to_string_lossy().to_string()),
+            ("software".to_string(), config.join("SOFTWARE").to_string_lossy().to_string()),
+            ("sam".to_string(), config.join("SAM").to_string_lossy().to_string()),
+        ]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("WKS01: 1 interfaces, 1 users, 1 groups, 1 software"), "{msg}");
+
+        let timeline = out_path.join("Timeline");
+        let info: HostInfo = serde_json::from_str(&fs::read_to_string(timeline.join(HOSTINFO_JSON)).unwrap()).unwrap();
+        assert_eq!(info.hostname, "WKS01");
+        assert_eq!(info.event_logs.hostname, "WKS01");
+        assert_eq!(info.event_logs.security_first, "2024-01-01T09:00:00.000Z");
+        assert_eq!(info.event_logs.security_last, "2024-01-05T10:00:00.000Z");
+
+        let md = fs::read_to_string(timeline.join(HOSTINFO_MARKDOWN)).unwrap();
+        assert!(md.contains("| Windows Version (Product Name) | Windows 11 Pro |"), "{md}");
+        assert!(md.contains("| Security Log Retention | From: 2024-01-01T09:00:00.000Z to: 2024-01-05T10:00:00.000Z |"), "{md}");
+        assert!(md.contains("| Administrators |"), "{md}");
+    }
+}
\ No newline at end of file
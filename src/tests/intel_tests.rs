@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::iocs::loader::{self, IocType};
+    use crate::ops::builtin_ops;
+
+    const MISP_EVENT: &str = r#"{
+        "Event": {
+            "id": "1234",
+            "info": "Rclone exfiltration campaign",
+            "Tag": [{"name": "tlp:amber"}, {"name": "misp:confidence-level=\"usually-confident\""}],
+            "Attribute": [
+                {"type": "ip-dst|port", "value": "203.0.113[.]7|443", "to_ids": true, "comment": "C2 server"},
+                {"type": "domain", "value": "exfil.example", "to_ids": true, "Tag": [{"name": "kill-chain:exfiltration"}]},
+                {"type": "url", "value": "https://benign.example/", "to_ids": false},
+                {"type": "port", "value": "8443", "to_ids": true},
+                {"type": "filename|sha256", "value": "C:\\ProgramData\\rc.exe|E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855", "to_ids": true}
+            ],
+            "Object": [
+                {"name": "file", "Attribute": [{"type": "filename", "value": "rclone.conf", "to_ids": true}]},
+                {"name": "file", "deleted": true, "Attribute": [{"type": "filename", "value": "deleted.exe", "to_ids": true}]}
+            ]
+        }
+    }"#;
+
+    const STIX_BUNDLE: &str = r#"{
+        "type": "bundle",
+        "id": "bundle--1",
+        "objects": [
+            {
+                "type": "indicator", "id": "indicator--1", "name": "Loader hash", "confidence": 90,
+                "labels": ["malicious-activity"], "indicator_types": ["malicious-activity", "attribution"],
+                "pattern_type": "stix",
+                "pattern": "[file:hashes.'SHA-256' = 'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855'] OR [file:name = 'loader.dll']"
+            },
+            {
+                "type": "indicator", "id": "indicator--2", "description": "Staging share",
+                "pattern_type": "stix",
+                "pattern": "[windows-registry-key:key = 'HKEY_LOCAL_MACHINE\\\\Software\\\\Rclone'] AND [url:value LIKE 'http://stage.example/%']"
+            },
+            {"type": "indicator", "id": "indicator--3", "revoked": true, "pattern_type": "stix", "pattern": "[domain-name:value = 'revoked.example']"},
+            {"type": "indicator", "id": "indicator--4", "pattern_type": "sigma", "pattern": "title: not a stix pattern"},
+            {"type": "report", "id": "report--1", "name": "Operation Cloud Copy", "object_refs": ["indicator--1", "indicator--2"]}
+        ]
+    }"#;
+
+    /// Test the indicators of MISP events and STIX bundles are loaded with their intel,
+    /// with an IOC in more than one list kept once with the intel of each
+    #[test]
+    fn test_load_intel() {
+        let temp_dir = TempDir::new().unwrap();
+        let lists = temp_dir.path().join("intel");
+        fs::create_dir_all(&lists).unwrap();
+        fs::write(lists.join("misp_event.json"), MISP_EVENT).unwrap();
+        fs::write(lists.join("stix_bundle.json"), STIX_BUNDLE).unwrap();
+        fs::write(lists.join("analyst.txt"), "exfil.example\n").unwrap();
+
+        let iocs = loader::load_iocs(&lists, Path::new("")).unwrap();
+        let loaded: Vec<(&str, IocType, &str)> = iocs.iter().map(|i| (i.value.as_str(), i.ioc_type, i.list.as_str())).collect();
+        assert_eq!(loaded, vec![
+            ("exfil.example", IocType::Domain, "analyst"),
+            ("203.0.113.7", IocType::Ipv4, "misp_event"),
+            (r"C:\ProgramData\rc.exe", IocType::FilePath, "misp_event"),
+            ("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855", IocType::Sha256, "misp_event"),
+            ("rclone.conf", IocType::Filename, "misp_event"),
+            ("loader.dll", IocType::Filename, "stix_bundle"),
+            (r"HKEY_LOCAL_MACHINE\Software\Rclone", IocType::RegistryKey, "stix_bundle"),
+            (r"(?i)http://stage\.example/.*", IocType::Regex, "stix_bundle"),
+        ]);
+
+        let ip = &iocs[1];
+        assert_eq!(ip.event, "1234: Rclone exfiltration campaign");
+        assert_eq!(ip.description, "C2 server");
+        assert_eq!(ip.tags, vec!["tlp:amber".to_string(), "misp:confidence-level=\"usually-confident\"".to_string()]);
+        assert_eq!(ip.confidence, Some(75));
+        let domain = &iocs[0];
+        assert_eq!(domain.event, "1234: Rclone exfiltration campaign");
+        assert_eq!(domain.tags.last().map(String::as_str), Some("kill-chain:exfiltration"));
+        // the hash is in both, keeping the higher confidence and the tags of each
+        let hash = &iocs[3];
+        assert_eq!(hash.confidence, Some(90));
+        assert!(hash.tags.contains(&"tlp:amber".to_string()) && hash.tags.contains(&"attribution".to_string()), "{:?}", hash.tags);
+        let dll = &iocs[5];
+        assert_eq!((dll.event.as_str(), dll.description.as_str(), dll.confidence), ("Operation Cloud Copy", "Loader hash", Some(90)));
+        assert_eq!(dll.tags, vec!["malicious-activity".to_string(), "attribution".to_string()]);
+        assert_eq!((iocs[6].description.as_str(), iocs[6].confidence), ("Staging share", None));
+
+        fs::write(lists.join("unknown.json"), r#"{"indicators": []}"#).unwrap();
+        assert!(loader::load_iocs(&lists, Path::new("")).is_err());
+    }
+
+    /// Test the IOC findings and hash matches are written with the intel of their IOCs
+    #[test]
+    fn test_findings_with_intel() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let input = temp_dir.path().join("collection");
+        fs::create_dir_all(out_path.join("Network")).unwrap();
+        fs::create_dir_all(input.join("ProgramData")).unwrap();
+        fs::write(out_path.join("Network").join("connections.csv"), "Remote\n203.0.113.7:443\n").unwrap();
+        fs::write(input.join("ProgramData").join("rc.exe"), "").unwrap();
+        let ioc_file = temp_dir.path().join("misp_event.json");
+        fs::write(&ioc_file, MISP_EVENT).unwrap();
+        let main_args = MainArgs { ioc_file: ioc_file.to_string_lossy().to_string(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: iocs\nbinary: 'builtin:iocs'\nargs: '--output-only'\noutfolder: IOC_Findings\noutfile: ioc_findings.csv\ninput: base\n"
+        ).unwrap();
+        builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        let mut reader = csv::Reader::from_path(out_path.join("IOC_Findings").join("ioc_findings.csv")).unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(&rows[0][0], "203.0.113.7");
+        assert_eq!(&rows[0][8], "1234: Rclone exfiltration campaign");
+        assert_eq!(&rows[0][9], "tlp:amber; misp:confidence-level=\"usually-confident\"");
+        assert_eq!(&rows[0][10], "75");
+
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: iocs_hashes\nbinary: 'builtin:hash_match'\nargs: ''\noutfolder: IOC_Findings\noutfile: hash_matches.csv\ninput: base\n"
+        ).unwrap();
+        builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        let mut reader = csv::Reader::from_path(out_path.join("IOC_Findings").join("hash_matches.csv")).unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0][0].ends_with("rc.exe"), "{:?}", rows[0]);
+        assert_eq!((&rows[0][5], &rows[0][7], &rows[0][9]), ("misp_event", "1234: Rclone exfiltration campaign", "75"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use crate::iocs::report::{self, HostIocs};
+    use crate::timeline::builder::TimelineEvent;
+
+    const FINDINGS_HEADER: &str = "Ioc,Type,List,Scope,Path,Offset,Encoding,Context,Event,Tags,Confidence\n";
+    const MATCHES_HEADER: &str = "Path,Source,Size,HashType,Ioc,List,Description,Event,Tags,Confidence\n";
+
+    /// write the IOC findings and timeline of a host to its wiskess output folder
+    fn write_output(input: &Path, folder: &str, findings: &str, matches: &str, events: &[(&str, &str)]) {
+        let output = input.join(folder);
+        fs::create_dir_all(output.join("IOC_Findings")).unwrap();
+        fs::create_dir_all(output.join("Timeline")).unwrap();
+        fs::write(output.join("IOC_Findings").join("ioc_findings.csv"), format!("{FINDINGS_HEADER}{findings}")).unwrap();
+        fs::write(output.join("IOC_Findings").join("ioc_findings_summary.csv"), "Ioc,Type,List,Hits\nmimikatz,string,iocs,9\n").unwrap();
+        fs::write(output.join("IOC_Findings").join("hash_matches.csv"), format!("{MATCHES_HEADER}{matches}")).unwrap();
+        let lines: Vec<String> = events.iter()
+            .map(|(datetime, message)| serde_json::to_string(&TimelineEvent {
+                datetime: datetime.to_string(),
+                message: message.to_string(),
+                ..Default::default()
+            }).unwrap())
+            .collect();
+        fs::write(output.join("Timeline").join("timeline.json"), lines.join("\n")).unwrap();
+    }
+
+    /// Test the IOC findings, hash matches and YARA matches of each host are aggregated by
+    /// IOC, with the hits of the IOC scanner's summary rather than its rows, which are capped
+    /// by `--max-hits`, and the times of the timeline events containing them, and written
+    /// as CSV and HTML
+    #[test]
+    fn test_case_ioc_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("storage");
+        let hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        write_output(&input, "WKS01-Wiskess",
+            "mimikatz,string,iocs,output,/out/WKS01-Wiskess/FileExecution/prefetch.csv,10,ascii,MIMIKATZ.EXE-1A2B.pf,,,\n\
+             mimikatz,string,iocs,data_source,/mnt/c/Users/a/notes.txt,4,utf16le,ran mimikatz <here>,,,\n\
+             evil.example,domain,misp_event,output,/out/WKS01-Wiskess/Network/dns.csv,0,ascii,lookup evil.example,1: Campaign,tlp:amber,75\n",
+            &format!("/mnt/c/Users/a/rc.exe,data_source,0,sha256,{hash},misp_event,,1: Campaign,,75\n"),
+            &[
+                ("2024-03-01T10:00:00Z", "Process MIMIKATZ.EXE ran"),
+                ("2024-03-02T10:00:00Z", "rc.exe resolved evil.example"),
+                ("2024-03-05T10:00:00Z", "mimikatz deleted"),
+                ("2024-03-06T10:00:00Z", "unrelated"),
+            ],
+        );
+        write_output(&input, "WKS02-Wiskess",
+            "mimikatz,string,iocs,output,/out/WKS02-Wiskess/Registry/amcache.csv,0,ascii,c:\\temp\\mimikatz.exe,,,\n",
+            &format!("Amcache.hve,amcache,0,sha256,{},misp_event,,1: Campaign,,75\n", hash.to_uppercase()),
+            &[("2024-02-01T09:00:00Z", "amcache entry c:\\temp\\mimikatz.exe")],
+        );
+        fs::write(input.join("WKS02-Wiskess").join("IOC_Findings").join("yara_matches.jsonl"),
+            r#"{"rule":"Rclone_Config","namespace":"exfil","tags":[],"meta":{},"path":"/mnt/c/Users/b/rclone.conf","source":"data_source","strings":[{"identifier":"$s","offset":0,"length":6,"data":"[s3]\\x0a"}]}"#
+        ).unwrap();
+        // the output of an earlier run without IOC findings isn't reported
+        fs::create_dir_all(input.join("WKS03-Wiskess").join("Timeline")).unwrap();
+
+        let outputs = report::find_outputs(&input);
+        assert_eq!(outputs.len(), 2);
+        let out_dir = input.join(report::CASE_IOC_FOLDER);
+        let report = report::write_report(&outputs, &out_dir).unwrap();
+        let iocs: Vec<(&str, &str, usize, usize, &str, &str)> = report.iocs.iter()
+            .map(|i| (i.ioc.as_str(), i.ioc_type.as_str(), i.hosts.len(), i.hits, i.first_seen.as_str(), i.last_seen.as_str()))
+            .collect();
+        assert_eq!(iocs, vec![
+            ("mimikatz", "string", 2, 18, "2024-02-01T09:00:00Z", "2024-03-05T10:00:00Z"),
+            (hash, "sha256", 2, 2, "", ""),
+            ("Rclone_Config", "yara", 1, 1, "", ""),
+            ("evil.example", "domain", 1, 1, "2024-03-02T10:00:00Z", "2024-03-02T10:00:00Z"),
+        ]);
+        let sources: Vec<&str> = report.iocs[0].sources.iter().map(String::as_str).collect();
+        assert_eq!(sources, vec!["amcache", "notes.txt", "prefetch"]);
+        assert_eq!(report.iocs[1].sources.iter().cloned().collect::<Vec<_>>(), vec!["amcache".to_string(), "rc.exe".to_string()]);
+        assert_eq!(report.iocs[2].examples[0].line, r"$s [s3]\x0a");
+        assert_eq!(report.iocs[3].event, "1: Campaign");
+        assert_eq!(report.hosts, vec![
+            HostIocs { host: "WKS01".to_string(), iocs: 3, hits: 11, first_seen: "2024-03-01T10:00:00Z".to_string(), last_seen: "2024-03-05T10:00:00Z".to_string() },
+            HostIocs { host: "WKS02".to_string(), iocs: 3, hits: 11, first_seen: "2024-02-01T09:00:00Z".to_string(), last_seen: "2024-02-01T09:00:00Z".to_string() },
+        ]);
+
+        let csv = fs::read_to_string(out_dir.join(report::CASE_IOC_CSV)).unwrap();
+        assert!(csv.starts_with("Ioc,Type,List,Event,Hosts,HostNames,Hits,FirstSeen,LastSeen,Sources,Examples\n"), "{csv}");
+        assert!(csv.contains("mimikatz,string,iocs,,2,WKS01; WKS02,18,"), "{csv}");
+        let hosts = fs::read_to_string(out_dir.join(report::CASE_IOC_HOSTS_CSV)).unwrap();
+        assert!(hosts.starts_with("Host,Iocs,Hits,FirstSeen,LastSeen\nWKS01,3,11,"), "{hosts}");
+        let html = fs::read_to_string(out_dir.join(report::CASE_IOC_HTML)).unwrap();
+        assert!(html.contains("ran mimikatz &lt;here&gt;") && !html.contains("<here>"));
+        assert!(html.contains("4 IOCs found with 22 hits on 2 hosts, from 2 wiskess outputs."));
+        assert!(report.table().contains("WKS02"));
+
+        assert!(report::write_report(&[], &out_dir).is_err());
+    }
+}
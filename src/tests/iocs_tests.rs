@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::iocs::loader::{self, Ioc, IocType, IpNetwork};
+    use crate::iocs::scanner::{Encoding, IocScanner, CHUNK_SIZE};
+    use crate::ops::builtin_ops;
+    use crate::parsers::hashes;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    /// the IOCs with their types recognised from their values
+    fn iocs(values: &[&str]) -> Vec<Ioc> {
+        values.iter().map(|v| Ioc::new(v, None, "test", "").unwrap()).collect()
+    }
+
+    /// scan the data, returning the IOC, offset, encoding and context of each hit
+    fn scan(scanner: &IocScanner, data: Vec<u8>) -> Vec<(String, u64, Encoding, String)> {
+        let mut hits = Vec::new();
+        scanner.scan_reader(Cursor::new(data), |pattern, offset, encoding, context| {
+            hits.push((scanner.iocs[pattern].value.clone(), offset, encoding, context));
+        }).unwrap();
+        hits.sort_by_key(|h| h.1);
+        hits
+    }
+
+    /// Test the types of IOCs are recognised from their values, with defanged values refanged
+    #[test]
+    fn test_detect_ioc_types() {
+        let typed = |value: &str| {
+            let ioc = Ioc::new(value, None, "test", "").unwrap();
+            (ioc.value, ioc.ioc_type)
+        };
+        assert_eq!(typed("D41D8CD98F00B204E9800998ECF8427E"), ("d41d8cd98f00b204e9800998ecf8427e".to_string(), IocType::Md5));
+        assert_eq!(typed("da39a3ee5e6b4b0d3255bfef95601890afd80709").1, IocType::Sha1);
+        assert_eq!(typed(&"ab".repeat(32)).1, IocType::Sha256);
+        assert_eq!(typed("10.1.2[.]3"), ("10.1.2.3".to_string(), IocType::Ipv4));
+        assert_eq!(typed("FE80:0:0::1"), ("fe80::1".to_string(), IocType::Ipv6));
+        assert_eq!(typed("192.168.0.0/16").1, IocType::Cidr);
+        assert_eq!(typed("evil[.]com"), ("evil.com".to_string(), IocType::Domain));
+        assert_eq!(typed("hxxps://evil[.]com/payload"), ("https://evil.com/payload".to_string(), IocType::Url));
+        assert_eq!(typed(r"C:\ProgramData\svc.exe").1, IocType::FilePath);
+        assert_eq!(typed(r"HKLM\Software\Microsoft\Windows\CurrentVersion\Run").1, IocType::RegistryKey);
+        assert_eq!(typed("rclone.exe").1, IocType::Filename);
+        assert_eq!(typed("psexesvc").1, IocType::String);
+        assert!(Ioc::new("not a hash", Some(IocType::Sha1), "test", "").is_err());
+        assert!(Ioc::new("10.0.0.0/33", Some(IocType::Cidr), "test", "").is_err());
+
+        let network = IpNetwork::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains(&"10.200.3.4".parse().unwrap()));
+        assert!(!network.contains(&"11.0.0.1".parse().unwrap()));
+        assert!(IpNetwork::parse("2001:db8::/32").unwrap().contains(&"2001:db8:1::5".parse().unwrap()));
+    }
+
+    /// Test the lists are read as text, CSV and YAML, with the list of each IOC
+    #[test]
+    fn test_load_ioc_lists() {
+        let temp_dir = TempDir::new().unwrap();
+        let lists = temp_dir.path().join("lists");
+        fs::create_dir_all(&lists).unwrap();
+        fs::write(lists.join("iocs.txt"), "# case iocs\nrclone.exe\n\n  10.1.2.3  \nRCLONE.EXE\ndomain:evil.zip\nregex:mimi[a-z]+\n").unwrap();
+        fs::write(lists.join("intel.csv"), "Indicator,Type,Source,Description\nhxxp://bad[.]org/a,url,,dropper\n10.0.0.0/8,cidr,vpn,internal\n").unwrap();
+        fs::write(lists.join("feeds.yaml"), "apt1:\n  - bad.net\n  - value: evil.ps1\n    type: filename\n    description: loader\n").unwrap();
+        fs::write(lists.join("notes.md"), "not a list").unwrap();
+
+        let iocs = loader::load_iocs(&lists, Path::new("")).unwrap();
+        let loaded: Vec<(&str, IocType, &str)> = iocs.iter().map(|i| (i.value.as_str(), i.ioc_type, i.list.as_str())).collect();
+        assert_eq!(loaded, vec![
+            ("bad.net", IocType::Domain, "apt1"),
+            ("evil.ps1", IocType::Filename, "apt1"),
+            ("http://bad.org/a", IocType::Url, "intel"),
+            ("10.0.0.0/8", IocType::Cidr, "vpn"),
+            ("rclone.exe", IocType::Filename, "iocs"),
+            ("10.1.2.3", IocType::Ipv4, "iocs"),
+            ("evil.zip", IocType::Domain, "iocs"),
+            ("mimi[a-z]+", IocType::Regex, "iocs"),
+        ]);
+        assert_eq!(iocs[1].description, "loader");
+        assert!(IocScanner::new(Vec::new(), false).is_err());
+
+        fs::write(lists.join("bad.csv"), "Name\nrclone.exe\n").unwrap();
+        assert!(loader::load_iocs(&lists.join("bad.csv"), Path::new("")).is_err());
+    }
+
+    /// Test the lines of a list that aren't valid are logged with their line number and
+    /// skipped, with the rest of the list still read
+    #[test]
+    fn test_load_iocs_skips_bad_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_log = temp_dir.path().join("test.log");
+        let text = temp_dir.path().join("iocs.txt");
+        fs::write(&text, "evil.com\nsha1:not a hash\ncidr:10.0.0.0/33\nrclone.exe\n").unwrap();
+        let iocs = loader::load_iocs(&text, &out_log).unwrap();
+        assert_eq!(iocs.iter().map(|i| i.value.as_str()).collect::<Vec<_>>(), vec!["evil.com", "rclone.exe"]);
+
+        let csv = temp_dir.path().join("intel.csv");
+        fs::write(&csv, "value,type\nbad.net,domain\nabc,sha256\nx.exe,unknown\n10.1.2.3,ipv4\n").unwrap();
+        let iocs = loader::load_iocs(&csv, &out_log).unwrap();
+        assert_eq!(iocs.iter().map(|i| i.value.as_str()).collect::<Vec<_>>(), vec!["bad.net", "10.1.2.3"]);
+
+        let log = fs::read_to_string(&out_log).unwrap();
+        assert!(log.contains(&format!("Skipped line 2 of the IOC file {}", text.display())), "{log}");
+        assert!(log.contains(&format!("Skipped line 3 of the IOC file {}", text.display())), "{log}");
+        assert!(log.contains(&format!("Skipped line 3 of the IOC file {}", csv.display())), "{log}");
+        assert!(log.contains(&format!("Skipped line 4 of the IOC file {}: Unknown IOC type unknown", csv.display())), "{log}");
+    }
+
+    /// Test each type is matched as it suits: domains with their subdomains, addresses
+    /// in CIDR ranges, filenames after a path, registry keys without their root and regexes
+    #[test]
+    fn test_scan_typed_iocs() {
+        let mut typed = iocs(&[
+            "evil.com", "10.0.0.0/8", "svc.exe", r"HKLM\Software\Microsoft\Windows\CurrentVersion\Run\updater",
+            r"C:\ProgramData\drop",
+        ]);
+        typed.push(Ioc::new("mimi[a-z]+", Some(IocType::Regex), "test", "").unwrap());
+        let scanner = IocScanner::new(typed, false).unwrap();
+        let found = |text: &str| -> Vec<String> {
+            scan(&scanner, text.as_bytes().to_vec()).into_iter().map(|h| h.0).collect()
+        };
+        assert_eq!(found("GET cdn.evil.com"), vec!["evil.com"]);
+        assert!(found("notevil.com evil.com.au evil-com").is_empty());
+        assert_eq!(found("from 10.20.30.40:443"), vec!["10.0.0.0/8"]);
+        assert!(found("110.1.2.3 10.1.2.3.4 11.0.0.1").is_empty());
+        assert_eq!(found(r"C:\Windows\svc.exe ran"), vec!["svc.exe"]);
+        assert!(found("mysvc.exe svc.exe.bak").is_empty());
+        assert_eq!(found(r#"{"Key":"ROOT\\Software\\Microsoft\\Windows\\CurrentVersion\\Run\\updater"}"#), vec![
+            r"HKLM\Software\Microsoft\Windows\CurrentVersion\Run\updater",
+        ]);
+        assert_eq!(found("/mnt/c/ProgramData/drop/a.txt"), vec![r"C:\ProgramData\drop"]);
+        assert_eq!(found("ran MimiKatz"), vec!["mimi[a-z]+"]);
+    }
+
+    /// Test IOCs are matched ignoring case, as ASCII and UTF-16LE, with their context
+    #[test]
+    fn test_scan_ascii_and_utf16() {
+        let scanner = IocScanner::new(iocs(&["rclone.exe", "evil.com"]), false).unwrap();
+        let mut data = b"ran C:\\Temp\\RClone.EXE copy\n".to_vec();
+        data.extend([0; 200]);
+        let utf16_at = data.len() as u64;
+        data.extend(utf16("https://EVIL.com/x"));
+        data.extend([0, 0, 0xff]);
+
+        let hits = scan(&scanner, data);
+        assert_eq!(hits, vec![
+            ("rclone.exe".to_string(), 12, Encoding::Ascii, "ran C:\\Temp\\RClone.EXE copy".to_string()),
+            ("evil.com".to_string(), utf16_at + 16, Encoding::Utf16le, "https://EVIL.com/x".to_string()),
+        ]);
+    }
+
+    /// Test a hit across the end of a chunk is found once, at its offset in the file
+    #[test]
+    fn test_scan_across_chunks() {
+        let scanner = IocScanner::new(iocs(&["mimikatz"]), false).unwrap();
+        let mut data = vec![b' '; CHUNK_SIZE - 4];
+        data.extend(b"mimikatz ");
+        data.extend(vec![b' '; 100]);
+        data.extend(b"MIMIKATZ");
+        let hits = scan(&scanner, data);
+        let offsets: Vec<u64> = hits.iter().map(|h| h.1).collect();
+        assert_eq!(offsets, vec![(CHUNK_SIZE - 4) as u64, (CHUNK_SIZE + 105) as u64]);
+        assert!(hits.iter().all(|h| h.3.eq_ignore_ascii_case("mimikatz")));
+    }
+
+    /// Test whole word matching skips hits inside a longer word
+    #[test]
+    fn test_scan_whole_words() {
+        let data = || b"evil devil evil.exe evils".to_vec();
+        assert_eq!(scan(&IocScanner::new(iocs(&["evil"]), false).unwrap(), data()).len(), 4);
+        let offsets: Vec<u64> = scan(&IocScanner::new(iocs(&["evil"]), true).unwrap(), data()).iter().map(|h| h.1).collect();
+        assert_eq!(offsets, vec![0, 11]);
+    }
+
+    /// Test the builtin scans the output and data source, skipping its own outfolder, and
+    /// writes the hits with the summary as CSV and JSON
+    #[test]
+    fn test_run_builtin_iocs() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let input = temp_dir.path().join("collection");
+        fs::create_dir_all(out_path.join("FileExecution")).unwrap();
+        fs::create_dir_all(out_path.join("IOC_Findings")).unwrap();
+        fs::create_dir_all(input.join("Windows")).unwrap();
+        fs::write(out_path.join("FileExecution").join("prefetch.csv"), "Name\nRCLONE.EXE\nrclone.exe\n").unwrap();
+        fs::write(out_path.join("IOC_Findings").join("old.txt"), "rclone.exe").unwrap();
+        let mut pagefile = vec![0u8; 64];
+        pagefile.extend(utf16("10.1.2.3:443"));
+        fs::write(input.join("Windows").join("pagefile.sys"), pagefile).unwrap();
+        fs::write(input.join("Windows").join("svc.bin"), "payload").unwrap();
+        let sha256 = hashes::hash_file(&input.join("Windows").join("svc.bin")).unwrap().sha256;
+        let ioc_file = temp_dir.path().join("iocs.txt");
+        fs::write(&ioc_file, format!("rclone.exe\n10.1.2.3\nnot-found.com\n{}\n", sha256.to_uppercase())).unwrap();
+
+        let main_args = MainArgs { ioc_file: ioc_file.to_string_lossy().to_string(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: iocs\nbinary: 'builtin:iocs'\nargs: ''\noutfolder: IOC_Findings\noutfile: ioc_findings.csv\ninput: base\n"
+        ).unwrap();
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("IOCs matched: 3 of 4, with 4 hits in 3 files scanned"), "{msg}");
+
+        let findings = fs::read_to_string(out_path.join("IOC_Findings").join("ioc_findings.csv")).unwrap();
+        let lines: Vec<&str> = findings.lines().collect();
+        assert_eq!(lines[0], "Ioc,Type,List,Scope,Path,Offset,Encoding,Context,Event,Tags,Confidence");
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("10.1.2.3,ipv4,iocs,data_source,") && lines[1].ends_with(",64,utf16le,10.1.2.3:443,,,"), "{}", lines[1]);
+        assert!(lines[2].starts_with(&format!("{sha256},sha256,iocs,data_source,")), "{}", lines[2]);
+        assert!(lines[2].ends_with("svc.bin,0,file_hash,sha256 of the file,,,"), "{}", lines[2]);
+        assert!(lines[3].starts_with("rclone.exe,filename,iocs,output,") && lines[3].contains("prefetch.csv,5,ascii,"), "{}", lines[3]);
+
+        let summary = fs::read_to_string(out_path.join("IOC_Findings").join("ioc_findings_summary.csv")).unwrap();
+        assert_eq!(summary.lines().collect::<Vec<_>>(), vec![
+            "Ioc,Type,List,Hits,Files,OutputHits,DataSourceHits,Suppressed,Event,Tags,Confidence".to_string(),
+            "rclone.exe,filename,iocs,2,1,2,0,0,,,".to_string(),
+            "10.1.2.3,ipv4,iocs,1,1,0,1,0,,,".to_string(),
+            format!("{sha256},sha256,iocs,1,1,0,1,0,,,"),
+            "not-found.com,domain,iocs,0,0,0,0,0,,,".to_string(),
+        ]);
+        let report: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(out_path.join("IOC_Findings").join("ioc_findings_summary.json")).unwrap()
+        ).unwrap();
+        assert_eq!(report["files_scanned"], 3);
+        assert_eq!(report["summary"][0]["ioc"], "rclone.exe");
+        assert_eq!(report["summary"][0]["ioc_type"], "filename");
+        assert_eq!(report["summary"][0]["contexts"][0], "Name RCLONE.EXE rclone.exe");
+    }
+}
\ No newline at end of file
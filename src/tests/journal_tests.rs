@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::Cursor;
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::linux::journal::{self, JournalFile};
+    use crate::ops::builtin_ops;
+
+    const BOOT_ID: [u8; 16] = [0xab; 16];
+    /// an entry's realtime and fields, with the compression flag of each field
+    type Entry = (u64, Vec<(&'static [u8], u8)>);
+
+    /// compress a field as journald does, by the flag of the data object
+    fn compress(flag: u8, field: &[u8]) -> Vec<u8> {
+        match flag {
+            1 => {
+                let mut data = Vec::new();
+                lzma_rs::xz_compress(&mut Cursor::new(field), &mut data).unwrap();
+                data
+            }
+            2 => [(field.len() as u64).to_le_bytes().to_vec(), lz4_flex::block::compress(field)].concat(),
+            4 => zstd::stream::encode_all(field, 3).unwrap(),
+            _ => field.to_vec(),
+        }
+    }
+
+    fn object(data: &mut Vec<u8>, kind: u8, flags: u8, body: &[u8]) -> u64 {
+        let offset = data.len() as u64;
+        data.extend([kind, flags, 0, 0, 0, 0, 0, 0]);
+        data.extend((16 + body.len() as u64).to_le_bytes());
+        data.extend(body);
+        data.resize(data.len().next_multiple_of(8), 0);
+        offset
+    }
+
+    /// a journal of the entries, each being its realtime and fields with their compression,
+    /// with the entries split over two entry arrays
+    fn journal(compact: bool, entries: &[Entry]) -> Vec<u8> {
+        let mut data = vec![0u8; 256];
+        data[..8].copy_from_slice(b"LPKSHHRH");
+        let flags: u32 = if compact { 16 | 8 | 2 | 1 } else { 8 | 2 | 1 };
+        data[12..16].copy_from_slice(&flags.to_le_bytes());
+        data[88..96].copy_from_slice(&256u64.to_le_bytes());
+        data[152..160].copy_from_slice(&(entries.len() as u64).to_le_bytes());
+
+        let mut entry_offsets = Vec::new();
+        for (seqnum, (realtime, fields)) in entries.iter().enumerate() {
+            let items: Vec<u64> = fields.iter().map(|(field, flag)| {
+                let mut body = vec![0u8; if compact { 56 } else { 48 }];
+                body.extend(compress(*flag, field));
+                object(&mut data, 1, *flag, &body)
+            }).collect();
+            let mut body = Vec::new();
+            body.extend((seqnum as u64 + 1).to_le_bytes());
+            body.extend(realtime.to_le_bytes());
+            body.extend(5_000_000u64.to_le_bytes());
+            body.extend(BOOT_ID);
+            body.extend(0u64.to_le_bytes());
+            for item in items {
+                match compact {
+                    true => body.extend((item as u32).to_le_bytes()),
+                    false => body.extend([item.to_le_bytes(), 0u64.to_le_bytes()].concat()),
+                }
+            }
+            entry_offsets.push(object(&mut data, 3, 0, &body));
+        }
+        let array = |data: &mut Vec<u8>, next: u64, offsets: &[u64]| {
+            let mut body = next.to_le_bytes().to_vec();
+            for offset in offsets {
+                match compact {
+                    true => body.extend((*offset as u32).to_le_bytes()),
+                    false => body.extend(offset.to_le_bytes()),
+                }
+            }
+            // arrays are allocated with free slots
+            body.extend([0u8; 8]);
+            object(data, 6, 0, &body)
+        };
+        let split = entry_offsets.len() / 2;
+        let second = array(&mut data, 0, &entry_offsets[split..]);
+        let first = array(&mut data, second, &entry_offsets[..split]);
+        data[176..184].copy_from_slice(&first.to_le_bytes());
+        data
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            (1_709_280_000_123_456, vec![
+                (b"_HOSTNAME=web01", 0), (b"_SYSTEMD_UNIT=ssh.service", 0), (b"SYSLOG_IDENTIFIER=sshd", 0),
+                (b"_PID=812", 0), (b"_UID=0", 0), (b"MESSAGE=Accepted publickey for deploy from 198.51.100.4", 4),
+            ]),
+            (1_709_280_001_000_000, vec![(b"_HOSTNAME=web01", 0), (b"MESSAGE=xz compressed", 1), (b"TAG=a", 0), (b"TAG=b", 2)]),
+            (1_709_280_002_000_000, vec![(b"_HOSTNAME=web01", 0), (b"MESSAGE=\xff\xfebinary", 0)]),
+        ]
+    }
+
+    /// Test the size an LZ4 object claims isn't allocated when it's more than the block can
+    /// hold or over journald's limit
+    #[test]
+    fn test_decompress_rejects_lz4_sizes() {
+        let data = b"MESSAGE=lz4 compressed".repeat(4);
+        let lz4 = |size: u64| [size.to_le_bytes().to_vec(), lz4_flex::block::compress(&data)].concat();
+        assert_eq!(journal::decompress(2, &lz4(data.len() as u64)).unwrap(), data);
+        for size in [u64::MAX, 1024 * 1024 * 1024, 100_000] {
+            let err = journal::decompress(2, &lz4(size)).unwrap_err();
+            assert!(format!("{err}").contains("the block can hold"), "{err}");
+        }
+    }
+
+    /// Test the entries of regular and compact journals are read through the entry arrays,
+    /// with the XZ, LZ4 and ZSTD fields decompressed
+    #[test]
+    fn test_read_journal() {
+        for compact in [false, true] {
+            let mut journal = JournalFile::from_bytes(journal(compact, &entries())).unwrap();
+            assert_eq!(journal.header.compact(), compact);
+            let offsets = journal.entry_offsets();
+            assert_eq!(offsets.len(), 3);
+            let read: Vec<_> = offsets.iter().map(|o| journal.entry(*o).unwrap()).collect();
+            assert_eq!(read[0].seqnum, 1);
+            assert_eq!(read[0].boot_id, "ab".repeat(16));
+            assert_eq!(read[0].timestamp().unwrap().to_rfc3339(), "2024-03-01T08:00:00.123456+00:00");
+            assert_eq!(read[0].field("MESSAGE").unwrap(), "Accepted publickey for deploy from 198.51.100.4");
+            assert_eq!(read[1].field("MESSAGE").unwrap(), "xz compressed");
+
+            let line = read[1].to_json("system.journal");
+            assert_eq!(line["fields"]["TAG"], json!(["a", "b"]));
+            let line = read[2].to_json("system.journal");
+            assert_eq!(line["message"], "\u{fffd}\u{fffd}binary");
+            assert_eq!(line["fields"]["MESSAGE"], json!([255, 254, 98, 105, 110, 97, 114, 121]));
+            assert_eq!(journal.bad_data, 0);
+        }
+        assert!(JournalFile::from_bytes(b"not a journal file at all".repeat(10)).is_err());
+    }
+
+    /// Test a damaged journal, as when a host crashes mid write, keeps the entries and fields
+    /// that can be read
+    #[test]
+    fn test_damaged_journal() {
+        let mut data = journal(false, &entries());
+        let offsets = JournalFile::from_bytes(data.clone()).unwrap().entry_offsets();
+        // the first data object of the last entry, and the entry before it
+        let field = u64::from_le_bytes(data[offsets[2] as usize + 64..][..8].try_into().unwrap()) as usize;
+        data[field..field + 16].fill(0);
+        data[offsets[1] as usize] = 0;
+        let mut journal = JournalFile::from_bytes(data).unwrap();
+        assert!(journal.entry(offsets[0]).is_some());
+        assert!(journal.entry(offsets[1]).is_none());
+        let last = journal.entry(offsets[2]).unwrap();
+        assert_eq!(last.fields.len(), 1);
+        assert_eq!(journal.bad_data, 1);
+    }
+
+    /// Test the builtin writes the entries of the journals of a Linux root as JSON lines
+    #[test]
+    fn test_linux_journal() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let root = temp_dir.path().join("root");
+        let machine = root.join("var/log/journal/3d1219c7c4c5404aaa1f6d2a48adfda4");
+        fs::create_dir_all(&machine).unwrap();
+        fs::write(machine.join("system.journal"), journal(true, &entries())).unwrap();
+        fs::write(machine.join("user-1000@0006-0001.journal~"), journal(false, &entries()[..1])).unwrap();
+        fs::write(machine.join("corrupt.journal"), b"LPKSHH").unwrap();
+        fs::write(machine.join("notes.txt"), b"not a journal").unwrap();
+        assert_eq!(journal::find_journals(&root).len(), 3);
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31");
+        let data_paths = HashMap::from([("base".to_string(), root.to_string_lossy().to_string())]);
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: linux_journal\nbinary: 'builtin:linux_journal'\nargs: ''\noutfolder: Logs\noutfile: linux_journal.jsonl\ninput: base\n"
+        ).unwrap();
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "Linux journal: 4 entries from 2 journals (0 entries and 0 fields unreadable)");
+        let log = fs::read_to_string(main_args.out_log).unwrap();
+        assert!(log.contains("Unable to read the journal") && log.contains("corrupt.journal"), "{log}");
+
+        let lines: Vec<Value> = fs::read_to_string(out_path.join("Logs/linux_journal.jsonl")).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        let first = &lines[0];
+        assert_eq!(first["timestamp"], "2024-03-01T08:00:00.123456Z");
+        assert_eq!(first["realtime"], 1709280000123456u64);
+        assert_eq!((&first["unit"], &first["identifier"], &first["pid"], &first["uid"]), (&json!("ssh.service"), &json!("sshd"), &json!("812"), &json!("0")));
+        assert_eq!(first["fields"]["_HOSTNAME"], "web01");
+        assert!(first["journal"].as_str().unwrap().ends_with("system.journal"));
+        assert!(lines[3]["journal"].as_str().unwrap().ends_with(".journal~"));
+    }
+}
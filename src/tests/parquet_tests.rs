@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs::{self, File};
+    use std::path::Path;
+    use arrow_array::{Array, RecordBatch, StringArray, TimestampMillisecondArray};
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use tempfile::TempDir;
+    use crate::configs::config::{MainArgs, OutFormat, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::ops::{builtin_ops, parquet_ops};
+    use crate::timeline::builder::TimelineEvent;
+    use crate::timeline::parquet;
+
+    fn read_batches(file: &Path) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(File::open(file).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|b| b.unwrap())
+            .collect()
+    }
+
+    fn strings(batch: &RecordBatch, column: &str) -> Vec<Option<String>> {
+        let array = batch.column_by_name(column).unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        (0..array.len()).map(|i| array.is_valid(i).then(|| array.value(i).to_string())).collect()
+    }
+
+    /// Test a CSV is converted with its columns as strings, empty values as null and
+    /// repeated column names made unique
+    #[test]
+    fn test_csv_to_parquet() {
+        let temp_dir = TempDir::new().unwrap();
+        let csv_file = temp_dir.path().join("persistence.csv");
+        fs::write(&csv_file, "\u{feff}Name,Command,Name\nRun,calc.exe,a\nTask,,b\n").unwrap();
+        let parquet_file = temp_dir.path().join("persistence.parquet");
+        assert_eq!(parquet_ops::csv_to_parquet(&csv_file, &parquet_file).unwrap(), 2);
+
+        let batches = read_batches(&parquet_file);
+        assert_eq!(batches.len(), 1);
+        let names: Vec<String> = batches[0].schema().fields().iter().map(|f| f.name().clone()).collect();
+        assert_eq!(names, vec!["Name", "Command", "Name_2"]);
+        assert_eq!(strings(&batches[0], "Command"), vec![Some("calc.exe".to_string()), None]);
+        assert_eq!(strings(&batches[0], "Name_2"), vec![Some("a".to_string()), Some("b".to_string())]);
+    }
+
+    /// Test the outputs of a builtin are found by its outfile, and the CSVs are removed
+    /// once they have been converted
+    #[test]
+    fn test_convert_builtin_outputs_and_remove() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let network = out_path.join("Network");
+        fs::create_dir_all(&network).unwrap();
+        fs::write(network.join("sum_clients.csv"), "A\n1\n").unwrap();
+        fs::write(network.join("sum_dns.csv"), "B\n2\n").unwrap();
+        fs::write(network.join("other.csv"), "C\n3\n").unwrap();
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: sum\nbinary: 'builtin:sum'\nargs: ''\noutfolder: Network\noutfile: 'sum_*.csv'\ninput: sum\n"
+        ).unwrap();
+
+        let main_args = MainArgs { out_format: OutFormat::Parquet, ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-01-31") };
+        let mut converted = parquet_ops::convert_builtin_outputs(&wisker, &main_args).unwrap();
+        converted.sort();
+        assert_eq!(converted, vec![network.join("sum_clients.parquet"), network.join("sum_dns.parquet")]);
+
+        assert_eq!(parquet_ops::remove_converted(&out_path).unwrap(), 2);
+        assert!(!network.join("sum_clients.csv").exists());
+        assert!(network.join("sum_clients.parquet").is_file());
+        assert!(network.join("other.csv").is_file());
+    }
+
+    /// Test the timeline's Parquet is partitioned by source and day, with the datetime as a
+    /// timestamp, and that the JSON lines are kept for the reporters run after it
+    #[test]
+    fn test_timeline_parquet_partitions() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let file_system = out_path.join("FileSystem");
+        fs::create_dir_all(&file_system).unwrap();
+        fs::write(file_system.join("recycle_bin.csv"), "\
+DeletedOn,Sid,UserName,OriginalPath,FileSize,DeletedFileExists,SHA256
+2024-01-20T10:00:00.000Z,S-1-5-21-1,jsmith,C:\\Tools\\rclone.exe,10,true,abc
+2024-01-20T11:00:00.000Z,S-1-5-21-1,jsmith,C:\\Tools\\7z.exe,10,true,abc
+2024-01-21T10:00:00.000Z,S-1-5-21-1,,C:\\Tools\\psexec.exe,10,true,abc
+").unwrap();
+
+        let main_args = MainArgs { out_format: OutFormat::Parquet, ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-01-31") };
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: timeline\nbinary: 'builtin:timeline'\nargs: ''\noutfolder: Timeline\noutfile: timeline.json\ninput: none\n"
+        ).unwrap();
+        let data_paths = HashMap::from([("none".to_string(), "".to_string())]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("Parquet partitions written: 2"), "{msg}");
+
+        let timeline = out_path.join("Timeline");
+        assert!(timeline.join("timeline.json").is_file());
+        let partition = timeline.join(parquet::PARQUET_FOLDER).join("source=recycle_bin").join("day=2024-01-20").join("part-000.parquet");
+        let batches = read_batches(&partition);
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+        let datetime = batches[0].column_by_name("datetime").unwrap()
+            .as_any().downcast_ref::<TimestampMillisecondArray>().unwrap();
+        assert_eq!(datetime.value(0), 1705744800000);
+        assert_eq!(strings(&batches[0], "path")[1].as_deref(), Some(r"C:\Tools\7z.exe"));
+
+        let next_day = timeline.join(parquet::PARQUET_FOLDER).join("source=recycle_bin").join("day=2024-01-21").join("part-000.parquet");
+        assert_eq!(strings(&read_batches(&next_day)[0], "path"), vec![Some(r"C:\Tools\psexec.exe".to_string())]);
+    }
+
+    /// Test sigma reads the timeline written with only Parquet output, and that the JSON
+    /// lines are removed in the cleanup at the end of the run
+    #[test]
+    fn test_timeline_parquet_then_sigma() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let file_system = out_path.join("FileSystem");
+        fs::create_dir_all(&file_system).unwrap();
+        fs::write(file_system.join("recycle_bin.csv"), "\
+DeletedOn,Sid,UserName,OriginalPath,FileSize,DeletedFileExists,SHA256
+2024-01-20T10:00:00.000Z,S-1-5-21-1,jsmith,C:\\Tools\\rclone.exe,10,true,abc
+2024-01-20T11:00:00.000Z,S-1-5-21-1,jsmith,C:\\Tools\\7z.exe,10,true,abc
+").unwrap();
+        let rules = temp_dir.path().join("rules");
+        fs::create_dir_all(&rules).unwrap();
+        fs::write(rules.join("deleted_tool.yml"), r#"
+title: Deleted Exfiltration Tool
+level: high
+logsource:
+    category: file_delete
+    product: windows
+detection:
+    selection:
+        TargetFilename|endswith: '\rclone.exe'
+    condition: selection
+"#).unwrap();
+
+        let main_args = MainArgs { out_format: OutFormat::Parquet, ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-01-31") };
+        let data_paths = HashMap::from([("none".to_string(), "".to_string())]);
+        let timeline: Wiskers = serde_yaml::from_str(
+            "name: timeline\nbinary: 'builtin:timeline'\nargs: ''\noutfolder: Timeline\noutfile: timeline.json\ninput: none\n"
+        ).unwrap();
+        builtin_ops::run_builtin(&timeline, &data_paths, &main_args).unwrap();
+        let sigma: Wiskers = serde_yaml::from_str(&format!(
+            "name: sigma\nbinary: 'builtin:sigma'\nargs: '--rules {}'\noutfolder: Detections\noutfile: sigma.jsonl\ninput: none\npara: false\n",
+            rules.display()
+        )).unwrap();
+        let msg = builtin_ops::run_builtin(&sigma, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "Sigma detections: 1 from 1 of 1 rules in 1 rule files (0 skipped), over 2 records. Parquet files written: 0");
+
+        let timeline_dir = out_path.join("Timeline");
+        assert!(parquet_ops::remove_timeline(&out_path).unwrap());
+        assert!(!timeline_dir.join("timeline.json").exists());
+        assert!(timeline_dir.join(parquet::PARQUET_FOLDER).join("source=recycle_bin").is_dir());
+        assert!(!parquet_ops::remove_timeline(&out_path).unwrap());
+    }
+
+    /// Test events are kept when Parquet is written beside the timeline
+    #[test]
+    fn test_timeline_parquet_export_keeps_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = temp_dir.path().join("timeline.json");
+        let events: Vec<String> = (0..3).map(|i| serde_json::to_string(&TimelineEvent {
+            datetime: format!("2024-01-02T0{i}:00:00.000Z"),
+            timestamp_desc: "desc".to_string(),
+            source: if i == 1 { "mft".to_string() } else { "evtx".to_string() },
+            message: format!("event {i}"),
+            host: "WKS01".to_string(),
+            ..Default::default()
+        }).unwrap()).collect();
+        fs::write(&timeline_file, events.join("\n")).unwrap();
+        assert_eq!(parquet::export(&timeline_file, temp_dir.path()).unwrap(), 2);
+        let evtx = temp_dir.path().join(parquet::PARQUET_FOLDER).join("source=evtx").join("day=2024-01-02").join("part-000.parquet");
+        let batches = read_batches(&evtx);
+        assert_eq!(strings(&batches[0], "message"), vec![Some("event 0".to_string()), Some("event 2".to_string())]);
+        assert_eq!(strings(&batches[0], "user"), vec![None, None]);
+        assert!(timeline_file.is_file());
+    }
+}
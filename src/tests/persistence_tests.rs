@@ -0,0 +1,298 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::common;
+    use crate::parsers::hive::Hive;
+    use crate::parsers::persistence;
+    use crate::tests::ese_tests::builder;
+    use crate::tests::hive_tests::builder::HiveBuilder;
+    use crate::timeline::timezone::SystemTimezone;
+
+    const USER_SID: &str = "S-1-5-21-1-2-3-1001";
+    /// 2024-01-02T03:04:05Z as a FILETIME
+    const WRITTEN: u64 = 133_486_382_450_000_000;
+
+    const TASK_XML: &str = r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Date>2024-01-02T03:04:05Z</Date>
+    <Author>CORP\jsmith</Author>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+      <UserId>S-1-5-18</UserId>
+    </LogonTrigger>
+    <CalendarTrigger>
+      <StartBoundary>2024-01-02T03:00:00</StartBoundary>
+    </CalendarTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <UserId>S-1-5-21-1-2-3-1001</UserId>
+    </Principal>
+  </Principals>
+  <Settings>
+    <Enabled>false</Enabled>
+    <Hidden>true</Hidden>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>C:\ProgramData\updater.exe</Command>
+      <Arguments>-k &quot;run&quot;</Arguments>
+    </Exec>
+  </Actions>
+</Task>"#;
+
+    fn utf16_bom(s: &str) -> Vec<u8> {
+        let mut data = vec![0xff, 0xfe];
+        data.extend(builder::utf16(s));
+        data
+    }
+
+    /// a .job file, last run at 2024-01-02T03:04:05
+    fn job(application: &str, parameters: &str, author: &str) -> Vec<u8> {
+        let mut data = vec![0u8; 68];
+        data[48..52].copy_from_slice(&0x4u32.to_le_bytes());
+        for (i, v) in [2024u16, 1, 2, 2, 3, 4, 5, 0].iter().enumerate() {
+            data[52 + i * 2..54 + i * 2].copy_from_slice(&v.to_le_bytes());
+        }
+        data.extend(1u16.to_le_bytes());
+        for s in [application, parameters, r"C:\Windows", author, ""] {
+            data.extend((s.len() as u16 + 1).to_le_bytes());
+            data.extend(builder::utf16(s));
+            data.extend([0, 0]);
+        }
+        data
+    }
+
+    fn system_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let current = hb.dword("Current", 2);
+        let select = hb.key("Select", 0, &[], &[current]);
+        let image = hb.string("ImagePath", r"%SystemRoot%\System32\svchost.exe -k netsvcs");
+        let start = hb.dword("Start", 2);
+        let service_type = hb.dword("Type", 0x20);
+        let account = hb.string("ObjectName", "LocalSystem");
+        let dll = hb.string("ServiceDll", r"C:\Windows\evil.dll");
+        let parameters = hb.key("Parameters", 0, &[], &[dll]);
+        let service = hb.key("EvilSvc", WRITTEN, &[parameters], &[image, start, service_type, account]);
+        let driver_image = hb.string("ImagePath", r"\SystemRoot\System32\drivers\rootkit.sys");
+        let driver_type = hb.dword("Type", 0x1);
+        let driver = hb.key("rootkit", WRITTEN, &[], &[driver_image, driver_type]);
+        let no_image = hb.key("NoImage", WRITTEN, &[], &[]);
+        let services = hb.path(r"ControlSet002\Services", &[service, driver, no_image], &[]);
+        let root = hb.key("ROOT", 0, &[select, services], &[]);
+        hb.build(root)
+    }
+
+    fn software_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let run_value = hb.string("Updater", r"C:\ProgramData\updater.exe");
+        let run = hb.key("Run", WRITTEN, &[], &[run_value]);
+        let current = hb.key("CurrentVersion", 0, &[run], &[]);
+        let windows = hb.key("Windows", 0, &[current], &[]);
+        let debugger = hb.string("Debugger", r"C:\Windows\System32\cmd.exe");
+        let sethc = hb.key("sethc.exe", WRITTEN, &[], &[debugger]);
+        let ifeo = hb.key("Image File Execution Options", 0, &[sethc], &[]);
+        let shell = hb.string("Shell", "explorer.exe");
+        let userinit = hb.string("Userinit", r"C:\Windows\system32\userinit.exe,C:\evil.exe");
+        let other = hb.string("DefaultUserName", "jsmith");
+        let winlogon = hb.key("Winlogon", WRITTEN, &[], &[shell, userinit, other]);
+        let path = hb.string("ProfileImagePath", r"C:\Users\jsmith");
+        let profile = hb.key(USER_SID, 0, &[], &[path]);
+        let profiles = hb.key("ProfileList", 0, &[profile], &[]);
+        let current_nt = hb.key("CurrentVersion", 0, &[ifeo, winlogon, profiles], &[]);
+        let windows_nt = hb.key("Windows NT", 0, &[current_nt], &[]);
+        let microsoft = hb.key("Microsoft", 0, &[windows, windows_nt], &[]);
+        let root = hb.key("ROOT", 0, &[microsoft], &[]);
+        hb.build(root)
+    }
+
+    fn ntuser_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let value = hb.string("OneDriveSync", r"C:\Users\jsmith\AppData\Local\Temp\sync.exe");
+        let run_once = hb.path(r"Software\Microsoft\Windows\CurrentVersion\RunOnce", &[], &[value]);
+        let root = hb.key("ROOT", 0, &[run_once], &[]);
+        hb.build(root)
+    }
+
+    /// carved OBJECTS.DATA content with a consumer, filter and their binding, separated by binary
+    fn objects_data() -> Vec<u8> {
+        let mut data = vec![0x01, 0x02];
+        for s in [
+            "EvilConsumer", "cmd.exe /c powershell -enc SQBFAFgA", "C:\\Windows",
+            "EvilFilter", "root\\cimv2", "WQL", "SELECT * FROM __InstanceModificationEvent WITHIN 60",
+            "__FilterToConsumerBinding",
+            r#"\\.\root\subscription:CommandLineEventConsumer.Name="EvilConsumer""#,
+            r#"\\.\root\subscription:__EventFilter.Name="EvilFilter""#,
+        ] {
+            data.extend(s.as_bytes());
+            data.extend([0x00, 0x19, 0x00]);
+        }
+        data
+    }
+
+    fn read_csv(path: &PathBuf) -> Vec<HashMap<String, String>> {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        reader.records()
+            .map(|r| headers.iter().zip(r.unwrap().iter()).map(|(h, v)| (h.to_string(), v.to_string())).collect())
+            .collect()
+    }
+
+    /// Test a UTF-16 task is parsed with the principal's user, not a trigger's, and the settings
+    #[test]
+    fn test_task_entries_reads_xml() {
+        let temp_dir = TempDir::new().unwrap();
+        let folder = temp_dir.path().join("Microsoft").join("Windows");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("Updater"), utf16_bom(TASK_XML)).unwrap();
+        fs::write(temp_dir.path().join("notatask.txt"), b"hello").unwrap();
+        let users = HashMap::from([(USER_SID.to_string(), "jsmith".to_string())]);
+
+        let entries = persistence::task_entries(temp_dir.path(), &users, None);
+        assert_eq!(entries.len(), 1);
+        let task = &entries[0];
+        assert_eq!(task.name, "Updater");
+        assert_eq!(task.location, r"\Microsoft\Windows");
+        assert_eq!(task.command, r#"C:\ProgramData\updater.exe -k "run""#);
+        assert_eq!(task.user, "jsmith");
+        assert_eq!(common::fmt_dt(task.timestamp), "2024-01-02T03:04:05.000Z");
+        assert_eq!(task.details, r"Enabled=false; Hidden=true; Triggers=LogonTrigger,CalendarTrigger; Author=CORP\jsmith");
+    }
+
+    /// Test task dates without an offset are local to the host, converted with its timezone
+    #[test]
+    fn test_parse_task_date_timezone() {
+        // Eastern Standard Time, without daylight saving
+        let eastern = SystemTimezone {
+            name: "Eastern Standard Time".to_string(),
+            bias: 300,
+            standard_bias: 0,
+            daylight_bias: -60,
+            standard_start: None,
+            daylight_start: None,
+        };
+        let utc = |date: &str, timezone: Option<&SystemTimezone>| common::fmt_dt(persistence::parse_task_date(date, timezone));
+        assert_eq!(utc("2024-01-02T03:04:05", Some(&eastern)), "2024-01-02T08:04:05.000Z");
+        assert_eq!(utc("2024-01-02T03:04:05.5", Some(&eastern)), "2024-01-02T08:04:05.500Z");
+        assert_eq!(utc("2024-01-02T03:04:05", None), "2024-01-02T03:04:05.000Z");
+        assert_eq!(utc("2024-01-02T03:04:05+01:00", Some(&eastern)), "2024-01-02T02:04:05.000Z");
+        assert_eq!(utc("not a date", Some(&eastern)), "");
+    }
+
+    /// Test a .job file is parsed with its command, author, disabled flag and last run time
+    #[test]
+    fn test_parse_job() {
+        let entry = persistence::parse_job(&job(r"C:\temp\nc.exe", "-e cmd.exe 10.0.0.1 443", "jsmith")).unwrap();
+        assert_eq!(entry.command, r"C:\temp\nc.exe -e cmd.exe 10.0.0.1 443");
+        assert_eq!(entry.user, "jsmith");
+        assert_eq!(common::fmt_dt(entry.timestamp), "2024-01-02T03:04:05.000Z");
+        assert!(entry.details.starts_with("Enabled=false;"), "{}", entry.details);
+        assert!(persistence::parse_job(&[0u8; 10]).is_none());
+    }
+
+    /// Test services are read from the current control set, with drivers and the ServiceDll
+    #[test]
+    fn test_service_entries() {
+        let system = Hive::from_bytes(system_hive()).unwrap();
+        let entries = persistence::service_entries(&system, "SYSTEM");
+        assert_eq!(entries.len(), 2);
+        let service = entries.iter().find(|e| e.name == "EvilSvc").unwrap();
+        assert_eq!(service.mechanism, "Service");
+        assert_eq!(service.location, r"HKLM\SYSTEM\ControlSet002\Services");
+        assert_eq!(service.user, "LocalSystem");
+        assert!(service.details.contains("Start=Automatic"), "{}", service.details);
+        assert!(service.details.contains(r"ServiceDll=C:\Windows\evil.dll"), "{}", service.details);
+        assert_eq!(common::fmt_dt(service.timestamp), "2024-01-02T03:04:05.000Z");
+        let driver = entries.iter().find(|e| e.name == "rootkit").unwrap();
+        assert_eq!(driver.mechanism, "Driver");
+    }
+
+    /// Test the Run, Winlogon and IFEO debugger values are read from SOFTWARE
+    #[test]
+    fn test_software_entries() {
+        let software = Hive::from_bytes(software_hive()).unwrap();
+        let entries = persistence::software_entries(&software, "SOFTWARE");
+        let mechanisms: Vec<(&str, &str)> = entries.iter().map(|e| (e.mechanism.as_str(), e.name.as_str())).collect();
+        assert_eq!(mechanisms, vec![("Run", "Updater"), ("Winlogon", "Shell"), ("Winlogon", "Userinit"), ("IFEO", "Debugger")]);
+        let ifeo = entries.iter().find(|e| e.mechanism == "IFEO").unwrap();
+        assert_eq!(ifeo.command, r"C:\Windows\System32\cmd.exe");
+        assert!(ifeo.location.ends_with(r"Image File Execution Options\sethc.exe"));
+    }
+
+    /// Test the binding of a consumer to a filter is carved with the command and query
+    #[test]
+    fn test_carve_wmi_bindings() {
+        let bindings = persistence::carve_wmi_bindings(&objects_data());
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].consumer_type, "CommandLineEventConsumer");
+        assert_eq!(bindings[0].consumer, "EvilConsumer");
+        assert_eq!(bindings[0].filter, "EvilFilter");
+        assert_eq!(bindings[0].command, "cmd.exe /c powershell -enc SQBFAFgA");
+        assert_eq!(bindings[0].query, "SELECT * FROM __InstanceModificationEvent WITHIN 60");
+    }
+
+    /// Test the builtin gathers each mechanism from the volume root into persistence.csv
+    #[test]
+    fn test_persistence_builtin_writes_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("C");
+        let tasks = root.join("Windows").join("System32").join("Tasks");
+        fs::create_dir_all(&tasks).unwrap();
+        fs::write(tasks.join("Updater"), utf16_bom(TASK_XML)).unwrap();
+        let jobs = root.join("Windows").join("Tasks");
+        fs::create_dir_all(&jobs).unwrap();
+        fs::write(jobs.join("At1.job"), job(r"C:\temp\nc.exe", "", "jsmith")).unwrap();
+        let config = root.join("Windows").join("System32").join("config");
+        fs::create_dir_all(&config).unwrap();
+        fs::write(config.join("SYSTEM"), system_hive()).unwrap();
+        fs::write(config.join("SOFTWARE"), software_hive()).unwrap();
+        let wbem = root.join("Windows").join("System32").join("wbem").join("Repository");
+        fs::create_dir_all(&wbem).unwrap();
+        fs::write(wbem.join("OBJECTS.DATA"), objects_data()).unwrap();
+        let profile = root.join("Users").join("jsmith");
+        let startup = profile.join(r"AppData\Roaming\Microsoft\Windows\Start Menu\Programs\Startup".replace('\\', "/"));
+        fs::create_dir_all(&startup).unwrap();
+        fs::write(startup.join("evil.lnk"), b"lnk").unwrap();
+        fs::write(startup.join("desktop.ini"), b"ini").unwrap();
+        fs::write(profile.join("NTUSER.DAT"), ntuser_hive()).unwrap();
+
+        let out_path = temp_dir.path().join("out");
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2023-01-01", "2023-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: persistence\nbinary: 'builtin:persistence'\nargs: ''\noutfolder: Persistence\noutfile: persistence.csv\ninput: base\n"
+        ).unwrap();
+        let data_paths = HashMap::from([("base".to_string(), root.to_string_lossy().to_string())]);
+
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("written: 11"), "{msg}");
+
+        let rows = read_csv(&out_path.join("Persistence").join("persistence.csv"));
+        let count = |mechanism: &str| rows.iter().filter(|r| r["Mechanism"] == mechanism).count();
+        assert_eq!(count("ScheduledTask"), 1);
+        assert_eq!(count("ScheduledTaskJob"), 1);
+        assert_eq!(count("Service"), 1);
+        assert_eq!(count("Driver"), 1);
+        assert_eq!(count("Run"), 1);
+        assert_eq!(count("Winlogon"), 2);
+        assert_eq!(count("IFEO"), 1);
+        assert_eq!(count("WmiEventConsumer"), 1);
+
+        let task = rows.iter().find(|r| r["Mechanism"] == "ScheduledTask").unwrap();
+        assert_eq!(task["User"], "jsmith");
+        let run_once = rows.iter().find(|r| r["Mechanism"] == "RunOnce").unwrap();
+        assert_eq!(run_once["User"], "jsmith");
+        assert_eq!(run_once["Location"], r"HKU\jsmith\Software\Microsoft\Windows\CurrentVersion\RunOnce");
+        let startup_row = rows.iter().find(|r| r["Mechanism"] == "StartupFolder").unwrap();
+        assert_eq!(startup_row["Name"], "evil.lnk");
+        assert_eq!(startup_row["TimestampType"], "Modified");
+    }
+}
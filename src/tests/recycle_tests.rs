@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::hashes;
+    use crate::parsers::recycle;
+    use crate::tests::ese_tests::builder;
+    use crate::tests::hive_tests::builder::HiveBuilder;
+
+    const USER_SID: &str = "S-1-5-21-1-2-3-1001";
+    /// 2024-01-02T03:04:05Z as a FILETIME
+    const FILETIME: u64 = 133_486_382_450_000_000;
+
+    fn index_v1(path: &str, size: u64) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(1u64.to_le_bytes());
+        data.extend(size.to_le_bytes());
+        data.extend(FILETIME.to_le_bytes());
+        let mut name = builder::utf16(path);
+        name.resize(520, 0);
+        data.extend(name);
+        data
+    }
+
+    fn index_v2(path: &str, size: u64) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(2u64.to_le_bytes());
+        data.extend(size.to_le_bytes());
+        data.extend(FILETIME.to_le_bytes());
+        data.extend((path.len() as u32 + 1).to_le_bytes());
+        data.extend(builder::utf16(path));
+        data.extend([0, 0]);
+        data
+    }
+
+    /// an INFO2 file with a record for each path, on drive C
+    fn info2(paths: &[&str]) -> Vec<u8> {
+        let mut data = vec![0u8; 20];
+        data[0] = 5;
+        data[12..16].copy_from_slice(&800u32.to_le_bytes());
+        for (i, path) in paths.iter().enumerate() {
+            let mut record = vec![0u8; 800];
+            record[..path.len()].copy_from_slice(path.as_bytes());
+            record[260..264].copy_from_slice(&(i as u32 + 1).to_le_bytes());
+            record[264..268].copy_from_slice(&2u32.to_le_bytes());
+            record[268..276].copy_from_slice(&FILETIME.to_le_bytes());
+            record[276..280].copy_from_slice(&4096u32.to_le_bytes());
+            let name = builder::utf16(path);
+            record[280..280 + name.len()].copy_from_slice(&name);
+            data.extend(record);
+        }
+        data
+    }
+
+    fn software_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let path = hb.string("ProfileImagePath", r"C:\Users\jsmith");
+        let profile = hb.key(USER_SID, 0, &[], &[path]);
+        let microsoft = hb.path(r"Microsoft\Windows NT\CurrentVersion\ProfileList", &[profile], &[]);
+        let root = hb.key("ROOT", 0, &[microsoft], &[]);
+        hb.build(root)
+    }
+
+    fn read_csv(path: &PathBuf) -> Vec<HashMap<String, String>> {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        reader.records()
+            .map(|r| headers.iter().zip(r.unwrap().iter()).map(|(h, v)| (h.to_string(), v.to_string())).collect())
+            .collect()
+    }
+
+    /// Test version 1 and version 2 $I files are parsed with the name of the $R file
+    #[test]
+    fn test_parse_index_versions() {
+        let v1 = recycle::parse_index(&index_v1(r"C:\Users\jsmith\Desktop\notes.txt", 12), Path::new("$IABC123.txt")).unwrap();
+        assert_eq!(v1.original_path, r"C:\Users\jsmith\Desktop\notes.txt");
+        assert_eq!(v1.file_size, 12);
+        assert_eq!(v1.format, "$I v1");
+        assert_eq!(v1.deleted_name, "$RABC123.txt");
+        assert_eq!(crate::parsers::common::fmt_dt(v1.deleted_on), "2024-01-02T03:04:05.000Z");
+
+        let v2 = recycle::parse_index(&index_v2(r"C:\Tools\rclone.exe", 2048), Path::new("$IXYZ.exe")).unwrap();
+        assert_eq!(v2.original_path, r"C:\Tools\rclone.exe");
+        assert_eq!(v2.format, "$I v2");
+        assert_eq!(v2.deleted_name, "$RXYZ.exe");
+
+        assert!(recycle::parse_index(&[9u8; 8], Path::new("$Ibad")).is_none());
+    }
+
+    /// Test the records of an XP INFO2 file are parsed with the Dc name of the deleted file
+    #[test]
+    fn test_parse_info2() {
+        let records = recycle::parse_info2(&info2(&[r"C:\tools\nc.exe", r"C:\temp\readme"]));
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].original_path, r"C:\tools\nc.exe");
+        assert_eq!(records[0].deleted_name, "Dc1.exe");
+        assert_eq!(records[0].file_size, 4096);
+        assert_eq!(records[1].deleted_name, "Dc2");
+    }
+
+    /// Test the builtin writes $I and INFO2 records, resolves the user and hashes the $R file
+    #[test]
+    fn test_recycle_builtin_writes_records() {
+        let temp_dir = TempDir::new().unwrap();
+        let recycle_bin = temp_dir.path().join("$Recycle.Bin");
+        let user_bin = recycle_bin.join(USER_SID);
+        fs::create_dir_all(&user_bin).unwrap();
+        fs::write(user_bin.join("$IABC123.exe"), index_v2(r"C:\Tools\rclone.exe", 3)).unwrap();
+        fs::write(user_bin.join("$RABC123.exe"), b"abc").unwrap();
+        fs::write(user_bin.join("$IGONE.txt"), index_v1(r"C:\Users\jsmith\gone.txt", 10)).unwrap();
+        let system_bin = recycle_bin.join("S-1-5-18");
+        fs::create_dir_all(&system_bin).unwrap();
+        fs::write(system_bin.join("INFO2"), info2(&[r"C:\tools\nc.exe"])).unwrap();
+        let software = temp_dir.path().join("SOFTWARE");
+        fs::write(&software, software_hive()).unwrap();
+
+        let out_path = temp_dir.path().join("out");
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2023-01-01", "2023-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: recycle_bin\nbinary: 'builtin:recycle_bin'\nargs: '--hash'\noutfolder: FileSystem\noutfile: recycle_bin.csv\ninput: recycle_bin\ninput_other: software\n"
+        ).unwrap();
+        let data_paths = HashMap::from([
+            ("recycle_bin".to_string(), recycle_bin.to_string_lossy().to_string()),
+            ("software".to_string(), software.to_string_lossy().to_string()),
+        ]);
+
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("written: 3"), "{msg}");
+
+        let rows = read_csv(&out_path.join("FileSystem").join("recycle_bin.csv"));
+        let rclone = rows.iter().find(|r| r["OriginalPath"] == r"C:\Tools\rclone.exe").unwrap();
+        assert_eq!(rclone["UserName"], "jsmith");
+        assert_eq!(rclone["DeletedOn"], "2024-01-02T03:04:05.000Z");
+        assert_eq!(rclone["DeletedFileExists"], "true");
+        assert_eq!(rclone["MD5"], "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(rclone["SHA256"], "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let gone = rows.iter().find(|r| r["OriginalPath"] == r"C:\Users\jsmith\gone.txt").unwrap();
+        assert_eq!(gone["DeletedFileExists"], "false");
+        assert_eq!(gone["MD5"], "");
+
+        let nc = rows.iter().find(|r| r["Format"] == "INFO2").unwrap();
+        assert_eq!(nc["Sid"], "S-1-5-18");
+        assert_eq!(nc["UserName"], "SYSTEM");
+        assert!(nc["DeletedFile"].ends_with("Dc1.exe"));
+    }
+
+    /// Test a file is hashed with MD5, SHA1 and SHA256
+    #[test]
+    fn test_hash_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("abc.bin");
+        fs::write(&file, b"abc").unwrap();
+        let hashes = hashes::hash_file(&file).unwrap();
+        assert_eq!(hashes.sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert!(hashes::hash_file(&temp_dir.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::detections::sigma::{self, LogSourceMapping, SigmaMapping};
+    use crate::detections::sigma_rules::{self, Level, LogSource, SigmaRecord};
+    use crate::ops::builtin_ops;
+    use crate::timeline::builder::TimelineEvent;
+
+    struct TestRecord(Vec<(&'static str, &'static str)>);
+
+    impl SigmaRecord for TestRecord {
+        fn field(&self, name: &str) -> Option<&str> {
+            self.0.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| *v)
+        }
+
+        fn values(&self) -> Vec<&str> {
+            self.0.iter().map(|(_, v)| *v).collect()
+        }
+    }
+
+    /// Test the rules are parsed with their modifiers and conditions, and those that
+    /// aren't supported are skipped
+    #[test]
+    fn test_sigma_rules() {
+        let source = r#"
+title: Encoded PowerShell
+id: 11111111-aaaa-bbbb-cccc-000000000001
+status: test
+level: high
+tags: [attack.execution, attack.t1059.001]
+logsource:
+    category: process_creation
+    product: Windows
+detection:
+    image:
+        Image|endswith:
+            - '\powershell.exe'
+            - '\pwsh.exe'
+    encoded:
+        CommandLine|windash|contains: ' -enc '
+    download:
+        CommandLine|contains|all:
+            - 'Net.WebClient'
+            - 'Download'
+    payload:
+        CommandLine|base64offset|contains: 'IEX'
+    filter_admin:
+        User|startswith: 'ADMIN'
+        ParentImage|re: '(?i)\\ccmexec\.exe$'
+    condition: image and (encoded or 1 of download* or payload) and not filter_admin
+---
+title: Keyword
+logsource:
+    service: security
+detection:
+    keywords:
+        - 'mimi*'
+    network:
+        IpAddress|cidr: 10.0.0.0/8
+        LogonType|gte: 3
+    condition: keywords or network
+---
+title: Counted
+logsource:
+    product: windows
+detection:
+    selection:
+        EventID: 4625
+    condition: selection | count() > 5
+---
+title: Modified
+logsource:
+    product: windows
+detection:
+    selection:
+        Image|expand: '%System%'
+    condition: selection
+"#;
+        let (rules, errors) = sigma_rules::parse_rules(source, "test.yml");
+        assert_eq!(rules.len(), 2);
+        assert_eq!(errors, vec![
+            "test.yml: Aggregations aren't supported".to_string(),
+            "test.yml: In search selection: The expand modifier of Image isn't supported".to_string(),
+        ]);
+        let rule = &rules[0];
+        assert_eq!((rule.level, rule.status.as_str(), rule.tags.len()), (Level::High, "test", 2));
+        assert_eq!(rule.logsource, LogSource { category: Some("process_creation".to_string()), product: Some("windows".to_string()), service: None });
+        assert_eq!(rule.fields(), vec!["CommandLine", "Image", "ParentImage", "User"]);
+
+        let matches = |fields: Vec<(&'static str, &'static str)>| rule.matches(&TestRecord(fields));
+        assert!(matches(vec![("Image", r"C:\Windows\System32\WindowsPowerShell\v1.0\POWERSHELL.EXE"), ("CommandLine", "powershell /enc SQBFAFgA")]));
+        assert!(matches(vec![("Image", r"C:\pwsh.exe"), ("CommandLine", "(New-Object Net.WebClient).DownloadString('x')")]));
+        assert!(!matches(vec![("Image", r"C:\pwsh.exe"), ("CommandLine", "(New-Object Net.WebClient)")]));
+        assert!(!matches(vec![("Image", r"C:\cmd.exe"), ("CommandLine", "cmd -enc x")]));
+        // "IEX (" base64 encoded at each offset
+        for encoded in ["SUVYICg", "lFWCAo", "JRVggK"] {
+            let line: &'static str = Box::leak(format!("powershell -e {encoded}AAA").into_boxed_str());
+            assert!(matches(vec![("Image", r"C:\pwsh.exe"), ("CommandLine", line)]), "{encoded}");
+        }
+        assert!(!matches(vec![("Image", r"C:\pwsh.exe"), ("CommandLine", "x -enc y"), ("User", "admin1"), ("ParentImage", r"C:\CCM\CcmExec.exe")]));
+        assert!(matches(vec![("Image", r"C:\pwsh.exe"), ("CommandLine", "x -enc y"), ("User", "admin1"), ("ParentImage", r"C:\ccmexec.exe.bak")]));
+
+        let keyword = &rules[1];
+        assert!(keyword.matches(&TestRecord(vec![("Payload", "MimiKatz ran")])));
+        assert!(keyword.matches(&TestRecord(vec![("IpAddress", "10.1.2.3"), ("LogonType", "10")])));
+        assert!(!keyword.matches(&TestRecord(vec![("IpAddress", "10.1.2.3"), ("LogonType", "2")])));
+        assert!(!keyword.matches(&TestRecord(vec![("IpAddress", "192.168.1.1"), ("LogonType", "3")])));
+
+        let condition = sigma_rules::parse_condition("all of them and not (sel1 or sel2", &rule.searches);
+        assert!(condition.is_err());
+    }
+
+    /// Test regexes are case-sensitive as Sigma's are, unless `|i` is given
+    #[test]
+    fn test_sigma_regex_case() {
+        let source = r#"
+title: Invoke Cmdlet
+logsource:
+    product: windows
+detection:
+    selection:
+        CommandLine|re: 'Invoke-[A-Z][a-z]+'
+    condition: selection
+---
+title: Insensitive Cmdlet
+logsource:
+    product: windows
+detection:
+    selection:
+        CommandLine|re|i: 'INVOKE-WEBREQUEST'
+    condition: selection
+"#;
+        let (rules, errors) = sigma_rules::parse_rules(source, "test.yml");
+        assert!(errors.is_empty(), "{errors:?}");
+        let matches = |rule: usize, line: &'static str| rules[rule].matches(&TestRecord(vec![("CommandLine", line)]));
+        assert!(matches(0, "powershell Invoke-Mimikatz"));
+        assert!(!matches(0, "powershell invoke-mimikatz"));
+        assert!(matches(1, "powershell invoke-webrequest http://x"));
+        assert!(matches(1, "powershell Invoke-WebRequest http://x"));
+    }
+
+    /// Test the logsources of the mapping apply to the rules with the same category,
+    /// product and service
+    #[test]
+    fn test_sigma_mapping() {
+        let mapping = SigmaMapping::default_mapping();
+        assert!(!mapping.logsources.is_empty());
+        let entry = LogSourceMapping { category: "process_creation".to_string(), product: "windows".to_string(), ..Default::default() };
+        let logsource = |category: Option<&str>, product: Option<&str>, service: Option<&str>| LogSource {
+            category: category.map(String::from),
+            product: product.map(String::from),
+            service: service.map(String::from),
+        };
+        assert!(entry.applies(&logsource(Some("process_creation"), Some("windows"), None)));
+        assert!(entry.applies(&logsource(Some("process_creation"), None, None)));
+        assert!(!entry.applies(&logsource(Some("process_creation"), Some("linux"), None)));
+        assert!(!entry.applies(&logsource(Some("process_creation"), Some("windows"), Some("sysmon"))));
+        assert!(!entry.applies(&logsource(None, None, None)));
+
+        let fields = sigma::payload_fields(r##"{"EventData":{"Data":[{"@Name":"Image","#text":"C:\\a.exe"},{"@Name":"Empty"},{"@Name":"ProcessId","#text":42}],"Binary":"00"}}"##);
+        assert_eq!(fields, vec![
+            ("Binary".to_string(), "00".to_string()),
+            ("Image".to_string(), r"C:\a.exe".to_string()),
+            ("ProcessId".to_string(), "42".to_string()),
+        ]);
+    }
+
+    /// Test the rules are evaluated over the timeline and the event logs, with the
+    /// detections written of each rule capped and all of them summarised
+    #[test]
+    fn test_run_builtin_sigma() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let rules = temp_dir.path().join("rules");
+        fs::create_dir_all(rules.join("windows")).unwrap();
+        fs::write(rules.join("windows").join("proc_susp_tool.yml"), r#"
+title: Suspicious Tool Execution
+id: 22222222-0000-0000-0000-000000000001
+status: experimental
+level: high
+tags: [attack.exfiltration]
+logsource:
+    category: process_creation
+    product: windows
+detection:
+    selection:
+        Image|endswith: '\rclone.exe'
+    condition: selection
+"#).unwrap();
+        fs::write(rules.join("windows").join("reg_run_key.yaml"), r#"
+title: Run Key Persistence
+id: 22222222-0000-0000-0000-000000000002
+status: test
+level: medium
+logsource:
+    category: registry_set
+    product: windows
+detection:
+    selection:
+        TargetObject|contains: '\CurrentVersion\Run\'
+    condition: selection
+"#).unwrap();
+        fs::write(rules.join("windows").join("low.yml"), r#"
+title: Any Prefetch
+level: low
+logsource:
+    category: process_creation
+    product: windows
+detection:
+    selection:
+        Image|contains: '.exe'
+    condition: selection
+"#).unwrap();
+        fs::write(rules.join("windows").join("old.yml"), r#"
+title: Deprecated Rule
+status: deprecated
+level: critical
+logsource:
+    category: process_creation
+    product: windows
+detection:
+    selection:
+        Image|endswith: '.exe'
+    condition: selection
+"#).unwrap();
+        fs::write(rules.join("linux.yml"), "title: Linux\nlevel: high\nlogsource:\n    product: linux\n    category: process_creation\ndetection:\n    sel:\n        Image: /bin/sh\n    condition: sel\n").unwrap();
+        fs::write(rules.join("notes.txt"), "not a rule").unwrap();
+
+        fs::create_dir_all(out_path.join("Timeline")).unwrap();
+        let event = |datetime: &str, source: &str, message: &str, path: &str| serde_json::to_string(&TimelineEvent {
+            datetime: datetime.to_string(),
+            timestamp_desc: "Event Time".to_string(),
+            source: source.to_string(),
+            message: message.to_string(),
+            host: "HOST1".to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        }).unwrap();
+        let timeline = [
+            event("2024-03-01T10:00:00.000Z", "prefetch", "RCLONE.EXE run 3 times", r"C:\Users\a\rclone.exe"),
+            event("2024-03-01T11:00:00.000Z", "prefetch", "RCLONE.EXE run 4 times", r"C:\Users\a\rclone.exe"),
+            event("2024-03-01T09:00:00.000Z", "registry", r"HKLM\Software\Microsoft\Windows\CurrentVersion\Run\updater", r"C:\Windows\System32\config\SOFTWARE"),
+            event("2024-03-01T09:30:00.000Z", "mft", "rclone.exe created", r"C:\Users\a\rclone.exe"),
+        ];
+        fs::write(out_path.join("Timeline").join("timeline.json"), timeline.join("\n") + "\n").unwrap();
+
+        fs::create_dir_all(out_path.join("EventLogs")).unwrap();
+        let mut writer = csv::Writer::from_path(out_path.join("EventLogs").join("EvtxECmd-All.csv")).unwrap();
+        writer.write_record(["TimeCreated", "EventId", "Channel", "Computer", "Payload"]).unwrap();
+        let sysmon = |time: &str, image: &str| [
+            time.to_string(), "1".to_string(), "Microsoft-Windows-Sysmon/Operational".to_string(), "HOST1".to_string(),
+            format!(r##"{{"EventData":{{"Data":[{{"@Name":"Image","#text":"{}"}}]}}}}"##, image.replace('\\', "\\\\")),
+        ];
+        writer.write_record(sysmon("2024-03-01 08:00:00.0000000", r"C:\Temp\rclone.exe")).unwrap();
+        writer.write_record(sysmon("2023-03-01 08:00:00.0000000", r"C:\Temp\rclone.exe")).unwrap();
+        writer.write_record(sysmon("2024-03-01 08:30:00.0000000", r"C:\Windows\notepad.exe")).unwrap();
+        writer.write_record(sysmon("2024-03-02 08:00:00.0000000", r"C:\Temp\rclone.exe")).unwrap();
+        writer.flush().unwrap();
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(&format!(
+            "name: sigma\nbinary: 'builtin:sigma'\nargs: '--rules {} --min-level medium --max-per-rule 3'\noutfolder: Detections\noutfile: sigma.jsonl\ninput: none\npara: false\n",
+            rules.display()
+        )).unwrap();
+        let data_paths = HashMap::from([("none".to_string(), String::new())]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "Sigma detections: 5 from 2 of 2 rules in 5 rule files (1 skipped), over 7 records");
+
+        let out_folder = out_path.join("Detections");
+        let detections: Vec<serde_json::Value> = fs::read_to_string(out_folder.join("sigma.jsonl")).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let titles: Vec<(&str, &str)> = detections.iter()
+            .map(|d| (d["datetime"].as_str().unwrap(), d["title"].as_str().unwrap()))
+            .collect();
+        assert_eq!(titles, vec![
+            ("2024-03-01T08:00:00.000Z", "Suspicious Tool Execution"),
+            ("2024-03-01T09:00:00.000Z", "Run Key Persistence"),
+            ("2024-03-01T10:00:00.000Z", "Suspicious Tool Execution"),
+            ("2024-03-01T11:00:00.000Z", "Suspicious Tool Execution"),
+        ]);
+        let sysmon = &detections[0];
+        assert_eq!(sysmon["source"], "Microsoft-Windows-Sysmon/Operational");
+        assert_eq!(sysmon["level"], "high");
+        assert_eq!(sysmon["rule_id"], "22222222-0000-0000-0000-000000000001");
+        assert_eq!(sysmon["rule_file"], "proc_susp_tool.yml");
+        assert_eq!(sysmon["tags"], serde_json::json!(["attack.exfiltration"]));
+        assert_eq!(sysmon["record"]["Image"], r"C:\Temp\rclone.exe");
+        assert_eq!(detections[1]["record"]["path"], r"C:\Windows\System32\config\SOFTWARE");
+
+        let summary = fs::read_to_string(out_folder.join("sigma_summary.csv")).unwrap();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines, vec![
+            "Level,RuleId,Title,Detections,FirstSeen,LastSeen,RuleFile",
+            "high,22222222-0000-0000-0000-000000000001,Suspicious Tool Execution,4,2024-03-01T08:00:00.000Z,2024-03-02T08:00:00.000Z,proc_susp_tool.yml",
+            "medium,22222222-0000-0000-0000-000000000002,Run Key Persistence,1,2024-03-01T09:00:00.000Z,2024-03-01T09:00:00.000Z,reg_run_key.yaml",
+        ]);
+        let errors = fs::read_to_string(out_folder.join("sigma_rule_errors.txt")).unwrap();
+        assert_eq!(errors, "linux.yml: Linux has a logsource that isn't mapped\n");
+    }
+
+    /// Test a missing timeline is logged and in the message, not skipped silently
+    #[test]
+    fn test_run_builtin_sigma_missing_timeline() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let rules = temp_dir.path().join("rules");
+        fs::create_dir_all(&rules).unwrap();
+        fs::write(rules.join("tool.yml"), "title: Tool\nlevel: high\nlogsource:\n    category: process_creation\n    product: windows\ndetection:\n    sel:\n        Image|endswith: '\\rclone.exe'\n    condition: sel\n").unwrap();
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(&format!(
+            "name: sigma\nbinary: 'builtin:sigma'\nargs: '--rules {}'\noutfolder: Detections\noutfile: sigma.jsonl\ninput: none\npara: false\n",
+            rules.display()
+        )).unwrap();
+        let data_paths = HashMap::from([("none".to_string(), String::new())]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        let timeline_file = out_path.join("Timeline").join("timeline.json");
+        assert_eq!(msg, format!(
+            "Sigma detections: 0 from 0 of 1 rules in 1 rule files (0 skipped), over 0 records, without the timeline as {} wasn't found",
+            timeline_file.display()
+        ));
+        let log = fs::read_to_string(&main_args.out_log).unwrap();
+        assert!(log.contains(&format!("[!] Sigma rules aren't run over the timeline, as {} wasn't found", timeline_file.display())), "{log}");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,265 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::TempDir;
+    use crate::art::paths;
+    use crate::configs::config::MainArgs;
+    use crate::tests::create_main_args;
+    use crate::ops::source_ops::{self, DataSourceKind, Detection};
+
+    fn write(path: &Path, data: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    fn dirs(root: &Path, folders: &[&str]) {
+        for folder in folders {
+            fs::create_dir_all(root.join(folder)).unwrap();
+        }
+    }
+
+    /// the kind, root relative to the data source, profile and evidence detected
+    fn detected(path: &Path) -> (DataSourceKind, PathBuf, Option<&'static str>, String) {
+        let Detection { kind, root, profile, evidence, .. } = source_ops::detect(path);
+        (kind, root.strip_prefix(path).map(Path::to_path_buf).unwrap_or(root), profile, evidence)
+    }
+
+    /// a stored zip of empty files with the names
+    fn zip(names: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut central = Vec::new();
+        for name in names {
+            let offset = data.len() as u32;
+            data.extend(0x0403_4b50u32.to_le_bytes());
+            data.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            data.extend([0u8; 12]);
+            data.extend((name.len() as u16).to_le_bytes());
+            data.extend(0u16.to_le_bytes());
+            data.extend(name.as_bytes());
+            central.extend(0x0201_4b50u32.to_le_bytes());
+            central.extend([20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            central.extend([0u8; 12]);
+            central.extend((name.len() as u16).to_le_bytes());
+            central.extend([0u8; 12]);
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+        let cd_offset = data.len() as u32;
+        data.extend(&central);
+        data.extend(0x0605_4b50u32.to_le_bytes());
+        data.extend([0u8; 4]);
+        data.extend((names.len() as u16).to_le_bytes());
+        data.extend((names.len() as u16).to_le_bytes());
+        data.extend((central.len() as u32).to_le_bytes());
+        data.extend(cd_offset.to_le_bytes());
+        data.extend(0u16.to_le_bytes());
+        data
+    }
+
+    /// a gzipped tar of the files and their contents, with the names longer than a
+    /// header written as GNU long names
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let header = |name: &str, kind: u8, size: usize| {
+            let mut header = [0u8; 512];
+            header[..name.len().min(100)].copy_from_slice(&name.as_bytes()[..name.len().min(100)]);
+            header[124..135].copy_from_slice(format!("{size:011o}").as_bytes());
+            header[156] = kind;
+            header[257..263].copy_from_slice(b"ustar\0");
+            header
+        };
+        let padded = |data: &[u8]| [data.to_vec(), vec![0u8; data.len().next_multiple_of(512) - data.len()]].concat();
+        let mut tar = Vec::new();
+        for (name, data) in files {
+            if name.len() > 100 {
+                tar.extend(header("././@LongLink", b'L', name.len() + 1));
+                tar.extend(padded(format!("{name}\0").as_bytes()));
+            }
+            tar.extend(header(name, b'0', data.len()));
+            tar.extend(padded(data));
+        }
+        tar.extend([0u8; 1024]);
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&tar).unwrap();
+        gz.finish().unwrap()
+    }
+
+    /// Test mounted volumes of Windows, Linux and macOS are told apart, and the OS of the
+    /// `[root]` of a UAC collection chooses its profile
+    #[test]
+    fn test_detect_volumes() {
+        let temp_dir = TempDir::new().unwrap();
+        let windows = temp_dir.path().join("windows");
+        dirs(&windows, &["Windows/System32", "Users"]);
+        write(&windows.join("$MFT"), b"FILE0");
+        assert_eq!(detected(&windows), (DataSourceKind::WindowsVolume, PathBuf::new(), None, "Windows, $MFT and Users".to_string()));
+
+        let linux = temp_dir.path().join("linux");
+        dirs(&linux, &["etc", "var/log"]);
+        assert_eq!(detected(&linux), (DataSourceKind::LinuxRoot, PathBuf::new(), Some("linux"), "etc and var/log".to_string()));
+        let macos = temp_dir.path().join("macos");
+        dirs(&macos, &["etc", "var/log", "private/var", "Library"]);
+        assert_eq!(detected(&macos), (DataSourceKind::MacosRoot, PathBuf::new(), Some("macos"), "private/var and Library".to_string()));
+
+        let uac = temp_dir.path().join("uac-web01-linux-20240301120000");
+        dirs(&uac, &["[root]/etc", "[root]/var/log", "live_response/process"]);
+        write(&uac.join("uac.log"), b"");
+        assert_eq!(detected(&uac), (DataSourceKind::Uac, PathBuf::from("[root]"), Some("linux"), "etc and var/log in [root] and uac.log".to_string()));
+        let unknown_os = temp_dir.path().join("uac-esxi");
+        dirs(&unknown_os, &["[root]/scratch"]);
+        assert_eq!(detected(&unknown_os), (DataSourceKind::Uac, PathBuf::from("[root]"), None, "[root]".to_string()));
+    }
+
+    /// Test the collections of triage tools are found from their layouts, with the drive
+    /// having Windows as their root
+    #[test]
+    fn test_detect_collections() {
+        let temp_dir = TempDir::new().unwrap();
+        let kape = temp_dir.path().join("kape");
+        dirs(&kape, &["C/Windows", "C/Users"]);
+        write(&kape.join("2024-03-01T10_00_00_1234567_CopyLog.csv"), b"");
+        assert_eq!(detected(&kape), (DataSourceKind::KapeTarget, PathBuf::from("C"), None, "2024-03-01T10_00_00_1234567_CopyLog.csv, with Windows in C".to_string()));
+
+        let velociraptor = temp_dir.path().join("velociraptor");
+        dirs(&velociraptor, &["uploads/auto/D%3A/Data", "uploads/auto/C%3A/Windows", "uploads/ntfs/%5C%5C.%5CC%3A/Windows"]);
+        write(&velociraptor.join("collection_context.json"), b"{}");
+        assert_eq!(detected(&velociraptor), (DataSourceKind::Velociraptor, PathBuf::from("uploads/auto/C%3A"), None, "uploads, with Windows on C:".to_string()));
+        dirs(&velociraptor, &["uploads/files/Windows"]);
+        assert_eq!(detected(&velociraptor).1, PathBuf::from("uploads/files"));
+
+        let cylr = temp_dir.path().join("cylr");
+        dirs(&cylr, &["c/Windows/System32/config"]);
+        assert_eq!(detected(&cylr), (DataSourceKind::Cylr, PathBuf::from("c"), None, "the drive folder c having Windows".to_string()));
+        let renamed = temp_dir.path().join("renamed");
+        dirs(&renamed, &["C/Users", "OS_Drive/Windows"]);
+        assert_eq!(detected(&renamed), (DataSourceKind::WindowsVolume, PathBuf::from("OS_Drive"), None, "Windows in OS_Drive".to_string()));
+
+        let artefacts = temp_dir.path().join("artefacts");
+        for name in ["Security.evtx", "System.evtx", "CMD.EXE-4A81B364.pf", "SYSTEM", "NTUSER.DAT", "notes.txt"] {
+            write(&artefacts.join(name), b"");
+        }
+        assert_eq!(detected(&artefacts), (DataSourceKind::Artefacts, PathBuf::new(), Some("artefacts"), "2 event logs, 1 prefetch files and 2 registry hives".to_string()));
+        let empty = temp_dir.path().join("empty");
+        dirs(&empty, &["C"]);
+        assert_eq!(detected(&empty).0, DataSourceKind::Unknown);
+        assert_eq!(detected(&temp_dir.path().join("missing")).0, DataSourceKind::Unknown);
+    }
+
+    /// Test disk images are found by their signatures, or their extension when they have none
+    #[test]
+    fn test_detect_images() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = |name: &str, data: &[u8]| {
+            let path = temp_dir.path().join(name);
+            write(&path, data);
+            let detection = source_ops::detect(&path);
+            (detection.kind, detection.evidence)
+        };
+        let sector = |offset: usize, magic: &[u8]| {
+            let mut data = vec![0u8; 1024];
+            data[offset..offset + magic.len()].copy_from_slice(magic);
+            data
+        };
+        assert_eq!(image("host.E01", b"EVF\x09\x0d\x0a\xff\x00\x01"), (DataSourceKind::DiskImage, "the EWF signature of an E01".to_string()));
+        assert_eq!(image("evidence", &sector(0, b"vhdxfile")).1, "the signature of a VHDX");
+        assert_eq!(image("fixed.bin", &[vec![0u8; 4096], b"conectix".to_vec(), vec![0u8; 504]].concat()).1, "the signature of a VHD");
+        assert_eq!(image("disk.bin", &sector(512, b"EFI PART")).1, "a GPT partition table");
+        assert_eq!(image("volume.bin", &sector(3, b"NTFS    ")).1, "an NTFS boot sector");
+        let mut mbr = sector(510, &[0x55, 0xaa]);
+        assert_eq!(image("boot.bin", &mbr).0, DataSourceKind::Unknown);
+        mbr[446] = 0x80;
+        mbr[450] = 0x07;
+        assert_eq!(image("disk.001", &mbr).1, "an MBR partition table");
+        assert_eq!(image("host-flat.vmdk", &[0u8; 2048]), (DataSourceKind::DiskImage, "the .vmdk extension".to_string()));
+        assert_eq!(image("notes.txt", b"not an image").0, DataSourceKind::Unknown);
+    }
+
+    /// Test the collection in a zip or tarball is found from the names of its files
+    #[test]
+    fn test_detect_archives() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = |name: &str, data: &[u8]| {
+            let path = temp_dir.path().join(name);
+            write(&path, data);
+            let detection = source_ops::detect(&path);
+            assert!(detection.archived && detection.root == path && detection.profile.is_none());
+            (detection.kind, detection.evidence)
+        };
+        let velociraptor = zip(&["uploads/auto/C%3A/Windows/System32/config/SYSTEM", "collection_context.json"]);
+        assert_eq!(archive("Collection-HOST-2024.zip", &velociraptor), (DataSourceKind::Velociraptor, "uploads in the zip".to_string()));
+        let cylr = zip(&["C/$MFT", "C/Windows/System32/config/SAM"]);
+        assert_eq!(archive("HOST.zip", &cylr), (DataSourceKind::Cylr, "C/Windows in the zip".to_string()));
+        let kape = zip(&["2024-03-01T100000_CopyLog.csv", "C/Users/alice/NTUSER.DAT"]);
+        assert_eq!(archive("kape.zip", &kape).0, DataSourceKind::KapeTarget);
+        assert_eq!(archive("other.zip", &zip(&["report.pdf"])), (DataSourceKind::Archive, "a zip of 1 files, with its contents found once extracted".to_string()));
+        assert_eq!(archive("backup.7z", &[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c, 0, 4]).0, DataSourceKind::Archive);
+
+        let long_name = format!("live_response/{}/output.txt", "process".repeat(20));
+        let uac = tar_gz(&[(long_name.as_str(), b"ps output".as_slice()), ("[root]/etc/passwd", b"root:x:0:0::/root:/bin/bash\n"), ("uac.log", b"")]);
+        let path = temp_dir.path().join("collection.tar.gz");
+        write(&path, &uac);
+        assert_eq!(source_ops::tar_names(&path, 100).unwrap(), vec![long_name.as_str(), "[root]/etc/passwd", "uac.log"]);
+        assert_eq!(source_ops::tar_names(&path, 1).unwrap().len(), 1);
+        assert!(source_ops::is_tarball(&path));
+        assert_eq!(archive("collection.tar.gz", &uac), (DataSourceKind::Uac, "[root] in the tarball".to_string()));
+        assert_eq!(archive("uac-web01-linux-20240301120000.tar.gz", &tar_gz(&[])), (DataSourceKind::Uac, "the name uac-web01-linux-20240301120000.tar.gz of a UAC archive".to_string()));
+        assert_eq!(archive("logs.tar.gz", &tar_gz(&[("var/log/syslog", b"")])).0, DataSourceKind::Archive);
+    }
+
+    /// Test a long name larger than the cap is skipped rather than read, so the size a
+    /// header claims isn't allocated
+    #[test]
+    fn test_tar_names_long_name_cap() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.tar.gz");
+        let huge_name = format!("live_response/{}", "a".repeat(9000));
+        write(&path, &tar_gz(&[(huge_name.as_str(), b"".as_slice()), ("[root]/etc/passwd", b"")]));
+        assert_eq!(source_ops::tar_names(&path, 100).unwrap(), vec![&huge_name[..100], "[root]/etc/passwd"]);
+
+        // a GNU long name header claiming 8 GB, with no data after it
+        let mut header = [0u8; 512];
+        header[..13].copy_from_slice(b"././@LongLink");
+        header[124..135].copy_from_slice(b"77777777777");
+        header[156] = b'L';
+        let path = temp_dir.path().join("bogus.tar");
+        write(&path, &header);
+        assert_eq!(source_ops::tar_names(&path, 100), None);
+    }
+
+    /// Test the root and configs of each kind are selected, with the configs given kept
+    #[test]
+    fn test_select_by_kind() {
+        let temp_dir = TempDir::new().unwrap();
+        let main_args = MainArgs { tool_path: temp_dir.path().join("wiskess").join("tools"), ..create_main_args(temp_dir.path(), &temp_dir.path().join("out"), "2024-01-01", "2024-12-31") };
+        let config_dir = temp_dir.path().join("wiskess").join("config");
+        let (config, artefacts) = (config_dir.join("windows/main.yaml"), config_dir.join("windows/artefacts.yaml"));
+        write(&config_dir.join("profiles/artefacts/artefacts.yaml"), b"");
+        let select = |config: &Path, data_source: &Path| {
+            let (config, artefacts, data_source) = paths::select_profile(config, &artefacts, &data_source.to_string_lossy().to_string(), &main_args);
+            (config, artefacts, PathBuf::from(data_source))
+        };
+
+        let kape = temp_dir.path().join("kape");
+        dirs(&kape, &["C/Windows"]);
+        write(&kape.join("2024-03-01T100000_CopyLog.csv"), b"");
+        assert_eq!(select(&config, &kape), (config.clone(), artefacts.clone(), kape.join("C")));
+        let custom = temp_dir.path().join("custom.yaml");
+        assert_eq!(select(&custom, &kape), (custom.clone(), artefacts.clone(), kape.clone()));
+
+        let folder = temp_dir.path().join("evtx");
+        write(&folder.join("Security.evtx"), b"");
+        assert_eq!(select(&config, &folder), (config.clone(), config_dir.join("profiles/artefacts/artefacts.yaml"), folder.clone()));
+
+        let image = temp_dir.path().join("host.E01");
+        write(&image, b"EVF\x09\x0d\x0a\xff\x00");
+        assert_eq!(select(&config, &image), (config.clone(), artefacts.clone(), image.clone()));
+        let log = fs::read_to_string(&main_args.out_log).unwrap();
+        assert!(log.contains("[+] KAPE target output found at") && log.contains("having 2024-03-01T100000_CopyLog.csv, with Windows in C"), "{log}");
+        assert!(log.contains("[-] Keeping the configs given, processing the KAPE target output from"), "{log}");
+        assert!(log.contains("[+] Processing with the artefacts profile"), "{log}");
+        assert!(log.contains("having the EWF signature of an E01. Mount it to process its volume"), "{log}");
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::ese::{coltyp, CODEPAGE_UNICODE};
+    use crate::tests::ese_tests::builder::{self, Cell, TableDef};
+    use crate::tests::hive_tests::builder::HiveBuilder;
+
+    const USER_SID: &str = "S-1-5-21-1-2-3-1001";
+
+    fn sid_bytes() -> Vec<u8> {
+        let mut sid = vec![1u8, 5, 0, 0, 0, 0, 0, 5];
+        for sub in [21u32, 1, 2, 3, 1001] {
+            sid.extend(sub.to_le_bytes());
+        }
+        sid
+    }
+
+    /// a SRUDB.dat with the id map and a network usage record
+    fn srum_db() -> Vec<u8> {
+        let id_map = TableDef::new("SruDbIdMapTable")
+            .column(1, "IdType", coltyp::UNSIGNED_BYTE, 1, 0)
+            .column(2, "IdIndex", coltyp::LONG, 4, 0)
+            .column(256, "IdBlob", coltyp::LONG_BINARY, 0, 0)
+            .row(vec![Cell::Bytes(vec![0]), builder::int32(10), Cell::Bytes(builder::utf16("\\device\\harddiskvolume2\\tools\\rclone.exe"))])
+            .row(vec![Cell::Bytes(vec![3]), builder::int32(11), Cell::Bytes(sid_bytes())]);
+        let network = TableDef::new("{973F5D5C-1D90-4944-BE8E-24B94231A174}")
+            .column(1, "AutoIncId", coltyp::LONG, 4, 0)
+            .column(2, "TimeStamp", coltyp::DATE_TIME, 8, 0)
+            .column(3, "AppId", coltyp::LONG, 4, 0)
+            .column(4, "UserId", coltyp::LONG, 4, 0)
+            .column(5, "InterfaceLuid", coltyp::LONG_LONG, 8, 0)
+            .column(6, "BytesSent", coltyp::LONG_LONG, 8, 0)
+            .column(7, "BytesRecvd", coltyp::LONG_LONG, 8, 0)
+            .row(vec![
+                builder::int32(1),
+                builder::float64(45000.5),
+                builder::int32(10),
+                builder::int32(11),
+                builder::int64(71 << 48),
+                builder::int64(987654321),
+                builder::int64(1024),
+            ]);
+        let connectivity = TableDef::new("{DD6636C4-8929-4683-974E-22C046A43763}")
+            .column(1, "AutoIncId", coltyp::LONG, 4, 0)
+            .column(2, "ConnectStartTime", coltyp::LONG_LONG, 8, 0)
+            .column(128, "Note", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .row(vec![builder::int32(1), builder::int64(133_486_382_450_000_000), builder::text("x")]);
+        builder::build(&[id_map, network, connectivity])
+    }
+
+    /// a SOFTWARE hive with the profile of the user
+    fn software_hive() -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let path = hb.string("ProfileImagePath", r"C:\Users\jsmith");
+        let profile = hb.key(USER_SID, 0, &[], &[path]);
+        let microsoft = hb.path(r"Microsoft\Windows NT\CurrentVersion\ProfileList", &[profile], &[]);
+        let root = hb.key("ROOT", 0, &[microsoft], &[]);
+        hb.build(root)
+    }
+
+    fn read_csv(path: &PathBuf) -> Vec<HashMap<String, String>> {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        reader.records()
+            .map(|r| headers.iter().zip(r.unwrap().iter()).map(|(h, v)| (h.to_string(), v.to_string())).collect())
+            .collect()
+    }
+
+    /// Test the SRUM tables are written with the app, user and interface resolved
+    #[test]
+    fn test_srum_builtin_writes_tables() {
+        let temp_dir = TempDir::new().unwrap();
+        let srudb = temp_dir.path().join("SRUDB.dat");
+        let software = temp_dir.path().join("SOFTWARE");
+        fs::write(&srudb, srum_db()).unwrap();
+        fs::write(&software, software_hive()).unwrap();
+
+        let out_path = temp_dir.path().join("out");
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2023-01-01", "2023-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: srum\nbinary: 'builtin:srum'\nargs: ''\noutfolder: Network\noutfile: 'srum_*.csv'\ninput: srum\ninput_other: software\n"
+        ).unwrap();
+        let data_paths = HashMap::from([
+            ("srum".to_string(), srudb.to_string_lossy().to_string()),
+            ("software".to_string(), software.to_string_lossy().to_string()),
+        ]);
+
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("network_usage: 1"));
+
+        let rows = read_csv(&out_path.join("Network").join("srum_network_usage.csv"));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["TimeStamp"], "2023-03-15T12:00:00.000Z");
+        assert_eq!(rows[0]["AppName"], "\\device\\harddiskvolume2\\tools\\rclone.exe");
+        assert_eq!(rows[0]["UserSid"], USER_SID);
+        assert_eq!(rows[0]["UserName"], "jsmith");
+        assert_eq!(rows[0]["InterfaceType"], "Wireless");
+        assert_eq!(rows[0]["BytesSent"], "987654321");
+
+        let rows = read_csv(&out_path.join("Network").join("srum_network_connectivity.csv"));
+        assert_eq!(rows[0]["ConnectStartTime"], "2024-01-02T03:04:05.000Z");
+        assert!(!out_path.join("Network").join("srum_energy_usage.csv").exists());
+    }
+
+    /// Test usernames are resolved from the ProfileList of the SOFTWARE hive
+    #[test]
+    fn test_srum_profile_users() {
+        let hive = crate::parsers::hive::Hive::from_bytes(software_hive()).unwrap();
+        let users = crate::parsers::hive::profile_users(&hive);
+        assert_eq!(users.get(USER_SID).map(String::as_str), Some("jsmith"));
+    }
+
+    /// Test builtin binaries are told apart from external tools
+    #[test]
+    fn test_is_builtin() {
+        assert!(builtin_ops::is_builtin("builtin:srum"));
+        assert!(!builtin_ops::is_builtin("{tool_path}\\chainsaw\\chainsaw.exe"));
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::ese::{coltyp, CODEPAGE_UNICODE};
+    use crate::tests::ese_tests::builder::{self, Cell, TableDef};
+
+    const ROLE_GUID: [u8; 16] = [0x8c, 0x5b, 0x0f, 0x10, 0x4f, 0x1f, 0x2c, 0x4e, 0x92, 0x77, 0x41, 0x9c, 0x65, 0x8b, 0x9d, 0x1a];
+
+    fn short(v: i16) -> Cell {
+        Cell::Bytes(v.to_le_bytes().to_vec())
+    }
+
+    fn system_identity() -> Vec<u8> {
+        let roles = TableDef::new("ROLE_IDS")
+            .column(1, "RoleGuid", coltyp::GUID, 16, 0)
+            .column(128, "ProductName", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .column(129, "RoleName", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .row(vec![Cell::Bytes(ROLE_GUID.to_vec()), builder::text("Windows Server"), builder::text("File Server")]);
+        let chained = TableDef::new("CHAINED_DATABASES")
+            .column(1, "Year", coltyp::LONG, 4, 0)
+            .column(128, "FileName", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .row(vec![builder::int32(2023), builder::text("{5B3A3E4C-0000-0000-0000-000000000000}.mdb")]);
+        builder::build(&[roles, chained])
+    }
+
+    fn current() -> Vec<u8> {
+        let clients = TableDef::new("CLIENTS")
+            .column(1, "RoleGuid", coltyp::GUID, 16, 0)
+            .column(2, "TotalAccesses", coltyp::LONG, 4, 0)
+            .column(3, "InsertDate", coltyp::DATE_TIME, 8, 0)
+            .column(4, "LastAccess", coltyp::DATE_TIME, 8, 0)
+            .column(128, "Address", coltyp::BINARY, 0, 0)
+            .column(129, "AuthenticatedUserName", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .column(256, "Day1", coltyp::SHORT, 0, 0)
+            .column(257, "Day74", coltyp::SHORT, 0, 0)
+            .column(258, "Day75", coltyp::SHORT, 0, 0)
+            .row(vec![
+                Cell::Bytes(ROLE_GUID.to_vec()),
+                builder::int32(7),
+                builder::float64(44927.25),
+                builder::float64(45000.5),
+                Cell::Bytes(vec![10, 0, 0, 5]),
+                builder::text("corp\\admin"),
+                short(4),
+                Cell::Null,
+                short(3),
+            ]);
+        let dns = TableDef::new("DNS")
+            .column(1, "LastSeen", coltyp::DATE_TIME, 8, 0)
+            .column(128, "Address", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .column(129, "HostName", coltyp::TEXT, 0, CODEPAGE_UNICODE)
+            .row(vec![builder::float64(45000.5), builder::text("10.0.0.5"), builder::text("wks01.corp.local")]);
+        builder::build(&[clients, dns])
+    }
+
+    /// Test the UAL clients and DNS tables are written with the role, IP and daily counts
+    #[test]
+    fn test_sum_builtin_writes_clients() {
+        let temp_dir = TempDir::new().unwrap();
+        let sum_dir = temp_dir.path().join("Sum");
+        fs::create_dir(&sum_dir).unwrap();
+        fs::write(sum_dir.join("SystemIdentity.mdb"), system_identity()).unwrap();
+        fs::write(sum_dir.join("Current.mdb"), current()).unwrap();
+        fs::write(sum_dir.join("Broken.mdb"), b"not a database").unwrap();
+
+        let out_path = temp_dir.path().join("out");
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2023-01-01", "2023-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: sum\nbinary: 'builtin:sum'\nargs: ''\noutfolder: Network\noutfile: 'sum_*.csv'\ninput: sum\n"
+        ).unwrap();
+        let data_paths = HashMap::from([("sum".to_string(), sum_dir.to_string_lossy().to_string())]);
+
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "UAL records written - clients: 1, dns: 1");
+
+        let mut reader = csv::Reader::from_path(out_path.join("Network").join("sum_clients.csv")).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let rows: Vec<HashMap<String, String>> = reader.records()
+            .map(|r| headers.iter().zip(r.unwrap().iter()).map(|(h, v)| (h.to_string(), v.to_string())).collect())
+            .collect();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row["SourceFile"], "Current.mdb");
+        assert_eq!(row["Year"], "2023");
+        assert_eq!(row["RoleName"], "File Server");
+        assert_eq!(row["ProductName"], "Windows Server");
+        assert_eq!(row["AuthenticatedUserName"], "corp\\admin");
+        assert_eq!(row["ClientIp"], "10.0.0.5");
+        assert_eq!(row["TotalAccesses"], "7");
+        assert_eq!(row["FirstAccess"], "2023-01-01T06:00:00.000Z");
+        assert_eq!(row["LastAccess"], "2023-03-15T12:00:00.000Z");
+        assert_eq!(row["ActiveDays"], "2");
+        assert_eq!(row["DailyCounts"], "2023-01-01:4;2023-03-16:3");
+
+        let dns = fs::read_to_string(out_path.join("Network").join("sum_dns.csv")).unwrap();
+        assert!(dns.contains("Current.mdb,2023-03-15T12:00:00.000Z,10.0.0.5,wks01.corp.local"));
+        let log = fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        assert!(log.contains("Broken.mdb"));
+    }
+}
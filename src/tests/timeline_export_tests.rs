@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use crate::timeline::builder::TimelineEvent;
+    use crate::timeline::elastic::{self, ElasticOptions};
+    use crate::timeline::sender::{self, Sender};
+    use crate::timeline::splunk::{self, SplunkOptions};
+
+    /// A request received by the mock server
+    struct MockRequest {
+        request_line: String,
+        headers: Vec<String>,
+        body: String,
+    }
+
+    /// start a mock HTTP server that answers each request with the next of the responses,
+    /// returning its url and a channel of the requests it received
+    fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, mpsc::Receiver<MockRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let Ok((stream, _)) = listener.accept() else { return };
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    headers.push(line.trim().to_string());
+                }
+                let length = headers.iter()
+                    .find_map(|h| h.to_lowercase().strip_prefix("content-length:").map(|l| l.trim().parse().unwrap()))
+                    .unwrap_or(0);
+                let mut request_body = vec![0; length];
+                reader.read_exact(&mut request_body).unwrap();
+                let mut stream = reader.into_inner();
+                write!(stream, "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).unwrap();
+                stream.flush().unwrap();
+                tx.send(MockRequest {
+                    request_line: request_line.trim().to_string(),
+                    headers,
+                    body: String::from_utf8(request_body).unwrap(),
+                }).unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    fn sender(retries: u32, auth: Option<&str>) -> Sender {
+        Sender { retries, backoff: Duration::from_millis(10), auth: auth.map(String::from) }
+    }
+
+    fn write_timeline(dir: &Path) -> std::path::PathBuf {
+        let events = [
+            TimelineEvent {
+                datetime: "2024-01-02T03:04:05.123Z".to_string(),
+                timestamp_desc: "mft - MFTECmd.csv: Created0x10".to_string(),
+                source: "mft".to_string(),
+                message: "created".to_string(),
+                host: "WKS01".to_string(),
+                path: r"C:\Tools\rclone.exe".to_string(),
+                ..Default::default()
+            },
+            TimelineEvent {
+                datetime: "2024-01-03T00:00:00.000Z".to_string(),
+                timestamp_desc: "recycle_bin - recycle_bin.csv: DeletedOn".to_string(),
+                source: "recycle_bin".to_string(),
+                message: "deleted".to_string(),
+                host: "WKS01".to_string(),
+                user: "jsmith".to_string(),
+                ..Default::default()
+            },
+        ];
+        let timeline_file = dir.join("timeline.json");
+        let lines: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        fs::write(&timeline_file, lines.join("\n")).unwrap();
+        timeline_file
+    }
+
+    /// Test the index is made from the case and host, and cleaned to a valid name
+    #[test]
+    fn test_elastic_options_index_name() {
+        assert_eq!(ElasticOptions::from_args("--timesketch", "Case", "WKS01").unwrap(), None);
+        let options = ElasticOptions::from_args("--elastic", "Case 42", "WKS01").unwrap().unwrap();
+        assert_eq!(options.index, "wiskess-case_42-wks01");
+        assert_eq!(options.url, None);
+        let options = ElasticOptions::from_args(
+            "--elastic-url http://localhost:9200 --elastic-index _My:Index", "Case", "WKS01"
+        ).unwrap().unwrap();
+        assert_eq!(options.index, "my_index");
+        assert_eq!(options.url.as_deref(), Some("http://localhost:9200"));
+    }
+
+    /// Test the bulk files pair each action with an ECS document
+    #[test]
+    fn test_elastic_export_writes_bulk_ecs() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = write_timeline(temp_dir.path());
+        let options = ElasticOptions::from_args("--elastic", "case42", "WKS01").unwrap().unwrap();
+        let files = elastic::export(&timeline_file, temp_dir.path(), "WKS01", &options).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].events, 2);
+
+        let data = fs::read_to_string(temp_dir.path().join(elastic::ELASTIC_FOLDER).join(&files[0].file)).unwrap();
+        let lines: Vec<serde_json::Value> = data.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["index"]["_index"], "wiskess-case42-wks01");
+        assert_eq!(lines[0]["index"]["_id"].as_str().unwrap().len(), 32);
+        assert_ne!(lines[0]["index"]["_id"], lines[2]["index"]["_id"]);
+        let document = &lines[1];
+        assert_eq!(document["@timestamp"], "2024-01-02T03:04:05.123Z");
+        assert_eq!(document["message"], "created");
+        assert_eq!(document["event"]["action"], "mft - MFTECmd.csv: Created0x10");
+        assert_eq!(document["event"]["provider"], "mft");
+        assert_eq!(document["host"]["name"], "WKS01");
+        assert_eq!(document["file"]["path"], r"C:\Tools\rclone.exe");
+        assert_eq!(document["file"]["name"], "rclone.exe");
+        assert_eq!(document["labels"]["case"], "case42");
+        assert!(document.get("user").is_none());
+        assert_eq!(lines[3]["user"]["name"], "jsmith");
+    }
+
+    /// Test the HEC events have the epoch time, host, source and index
+    #[test]
+    fn test_splunk_export_writes_hec_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = write_timeline(temp_dir.path());
+        let options = SplunkOptions::from_args("--splunk --splunk-index dfir").unwrap().unwrap();
+        let files = splunk::export(&timeline_file, temp_dir.path(), "WKS01", &options).unwrap();
+        let data = fs::read_to_string(temp_dir.path().join(splunk::SPLUNK_FOLDER).join(&files[0].file)).unwrap();
+        let events: Vec<serde_json::Value> = data.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["time"], 1704164645.123);
+        assert_eq!(events[0]["host"], "WKS01");
+        assert_eq!(events[0]["source"], "wiskess:mft");
+        assert_eq!(events[0]["sourcetype"], splunk::DEFAULT_SOURCETYPE);
+        assert_eq!(events[0]["index"], "dfir");
+        assert_eq!(events[0]["event"]["message"], "created");
+        assert_eq!(SplunkOptions::from_args("--elastic").unwrap(), None);
+    }
+
+    /// Test the sender retries a busy server, then posts the bulk file with the auth header
+    #[test]
+    fn test_elastic_send_retries_busy_server() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = write_timeline(temp_dir.path());
+        let options = ElasticOptions::from_args("--elastic", "case42", "WKS01").unwrap().unwrap();
+        let files = elastic::export(&timeline_file, temp_dir.path(), "WKS01", &options).unwrap();
+        let (url, requests) = mock_server(vec![
+            (503, "{}"),
+            (200, r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":400,"error":{"type":"mapper_parsing_exception"}}}]}"#),
+        ]);
+
+        let report = elastic::send(temp_dir.path(), &files, &url, &sender(2, Some("ApiKey abc"))).unwrap();
+        assert_eq!(report, elastic::BulkReport { files: 1, items: 2, errors: 1 });
+        let retried = requests.recv().unwrap();
+        let sent = requests.recv().unwrap();
+        assert_eq!(retried.body, sent.body);
+        assert_eq!(sent.request_line, "POST /_bulk HTTP/1.1");
+        assert!(sent.headers.iter().any(|h| h.eq_ignore_ascii_case("authorization: ApiKey abc")));
+        assert!(sent.headers.iter().any(|h| h.eq_ignore_ascii_case("content-type: application/x-ndjson")));
+        assert_eq!(sent.body.lines().count(), 4);
+    }
+
+    /// Test the sender gives up after the retries and doesn't retry a rejected request
+    #[test]
+    fn test_sender_gives_up() {
+        let (url, requests) = mock_server(vec![(500, "{}"), (500, "{}")]);
+        let err = sender(1, None).post(&url, "application/json", b"{}").unwrap_err();
+        assert!(format!("{err}").contains("after 2 attempts"), "{err}");
+        assert_eq!(requests.iter().count(), 2);
+
+        let (url, requests) = mock_server(vec![(403, r#"{"text":"Invalid token"}"#)]);
+        let err = sender(3, None).post(&url, "application/json", b"{}").unwrap_err();
+        assert!(format!("{err}").contains("403"), "{err}");
+        assert_eq!(requests.iter().count(), 1);
+    }
+
+    /// Test the retries are capped when parsed and the backoff doesn't overflow
+    #[test]
+    fn test_sender_retries_and_backoff_capped() {
+        assert_eq!(Sender::from_args("--send-retries 10", None).unwrap().retries, 10);
+        let err = Sender::from_args("--send-retries 40", None).unwrap_err();
+        assert!(format!("{err}").contains("the most is 10"), "{err}");
+
+        let sender = Sender { retries: 40, backoff: Duration::from_secs(2), auth: None };
+        assert_eq!(sender.backoff_after(0), Duration::from_secs(2));
+        assert_eq!(sender.backoff_after(3), Duration::from_secs(16));
+        assert_eq!(sender.backoff_after(40), Duration::from_secs(300));
+    }
+
+    /// Test the HEC files are posted to the event endpoint with the Splunk token
+    #[test]
+    fn test_splunk_send_posts_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = write_timeline(temp_dir.path());
+        let options = SplunkOptions::from_args("--splunk").unwrap().unwrap();
+        let files = splunk::export(&timeline_file, temp_dir.path(), "WKS01", &options).unwrap();
+        let (url, requests) = mock_server(vec![(200, r#"{"text":"Success","code":0}"#)]);
+        let sent = splunk::send(temp_dir.path(), &files, &format!("{url}/"), &sender(0, Some("Splunk token"))).unwrap();
+        assert_eq!(sent, 2);
+        let request = requests.recv().unwrap();
+        assert_eq!(request.request_line, "POST /services/collector/event HTTP/1.1");
+        assert!(request.headers.iter().any(|h| h.eq_ignore_ascii_case("authorization: Splunk token")));
+        assert_eq!(sender::endpoint_url("https://splunk:8088/services/collector/event", "/services/collector/event"),
+            "https://splunk:8088/services/collector/event");
+    }
+}
@@ -0,0 +1,262 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+    use regex::Regex;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::common;
+    use crate::tests::hive_tests::builder::HiveBuilder;
+    use crate::timeline::builder::{DateRange, TimelineEvent};
+    use crate::timeline::mapping::{self, TimelineMapping};
+    use crate::timeline::sorter::LineSorter;
+    use crate::timeline::timesketch::{self, ExportFormat, TimesketchOptions};
+
+    fn system_hive(hostname: &str) -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let current = hb.dword("Current", 1);
+        let select = hb.key("Select", 0, &[], &[current]);
+        let name = hb.string("ComputerName", hostname);
+        let computer_name = hb.key("ComputerName", 0, &[], &[name]);
+        let control = hb.path(r"ControlSet001\Control\ComputerName", &[computer_name], &[]);
+        let root = hb.key("ROOT", 0, &[select, control], &[]);
+        hb.build(root)
+    }
+
+    fn read_events(path: &Path) -> Vec<TimelineEvent> {
+        fs::read_to_string(path).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    /// Test the timestamps of the common tool formats are parsed to UTC
+    #[test]
+    fn test_parse_time_formats() {
+        let parse = |value: &str, format: &str| common::fmt_dt(mapping::parse_time(value, format));
+        assert_eq!(parse("2024-01-02 03:04:05.1234567", ""), "2024-01-02T03:04:05.123Z");
+        assert_eq!(parse("2024-01-02 03:04:05", ""), "2024-01-02T03:04:05.000Z");
+        assert_eq!(parse("2024-01-02T03:04:05.123Z", ""), "2024-01-02T03:04:05.123Z");
+        assert_eq!(parse("2024-01-02 04:04:05.000 +01:00", ""), "2024-01-02T03:04:05.000Z");
+        assert_eq!(parse("1704164645", "%s"), "2024-01-02T03:04:05.000Z");
+        assert_eq!(parse("02/01/2024 03:04:05", "%d/%m/%Y %H:%M:%S"), "2024-01-02T03:04:05.000Z");
+        assert_eq!(parse("1/2/2024 3:04:05 PM", "%m/%d/%Y %I:%M:%S %p"), "2024-01-02T15:04:05.000Z");
+        assert_eq!(parse("not a time", ""), "");
+        assert_eq!(parse("", ""), "");
+    }
+
+    /// Test the builtin mapping parses and each source's file regex is valid
+    #[test]
+    fn test_default_mapping_is_valid() {
+        let mapping = TimelineMapping::default_mapping();
+        assert!(mapping.sources.len() > 20);
+        for source in &mapping.sources {
+            assert!(Regex::new(&source.file).is_ok(), "invalid file regex for {}", source.name);
+            assert!(!source.times.is_empty(), "no times for {}", source.name);
+        }
+    }
+
+    /// Test the end date of the range includes the whole day
+    #[test]
+    fn test_date_range_includes_end_day() {
+        let range = DateRange::new("2024-01-01", "2024-01-31");
+        let dt = |s: &str| mapping::parse_time(s, "").unwrap();
+        assert!(range.contains(&dt("2024-01-31 23:59:59")));
+        assert!(!range.contains(&dt("2024-02-01 00:00:00")));
+        assert!(!range.contains(&dt("2023-12-31 23:59:59")));
+        assert!(DateRange::new("", "").contains(&dt("1999-01-01 00:00:00")));
+    }
+
+    /// Test lines spilled across several chunks are merged in order
+    #[test]
+    fn test_line_sorter_merges_chunks() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut sorter = LineSorter::new(3);
+        for n in [5, 3, 9, 1, 7, 2, 8, 6, 4, 0] {
+            sorter.push(format!("line {n}")).unwrap();
+        }
+        let out_file = temp_dir.path().join("sorted.json");
+        assert_eq!(sorter.finish(&out_file).unwrap(), 10);
+        let lines: Vec<String> = fs::read_to_string(&out_file).unwrap().lines().map(String::from).collect();
+        let expected: Vec<String> = (0..10).map(|n| format!("line {n}")).collect();
+        assert_eq!(lines, expected);
+    }
+
+    /// Test the builtin maps CSV, headerless PSV and JSON lines outputs into a sorted timeline
+    /// in the date range, with the hostname from the SYSTEM hive and the PowerShell history
+    #[test]
+    fn test_timeline_builtin_writes_sorted_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let file_system = out_path.join("FileSystem");
+        fs::create_dir_all(&file_system).unwrap();
+        fs::write(file_system.join("recycle_bin.csv"), "\
+DeletedOn,Sid,UserName,OriginalPath,FileSize,DeletedFileExists,SHA256
+2024-01-20T10:00:00.000Z,S-1-5-21-1,jsmith,C:\\Tools\\rclone.exe,10,true,abc
+2023-06-01T10:00:00.000Z,S-1-5-21-1,jsmith,C:\\old.txt,10,false,
+").unwrap();
+        fs::write(file_system.join("MFTECmd.csv"), "\
+ParentPath,FileName,Created0x10,LastModified0x10,LastAccess0x10
+.\\Users\\jsmith\\AppData\\Roaming\\Microsoft\\Windows\\PowerShell\\PSReadLine,ConsoleHost_history.txt,2024-01-03 00:00:00,2024-01-04 00:00:00,2024-01-05 00:00:00
+").unwrap();
+        let regripper = out_path.join("Registry").join("regripper_tln");
+        fs::create_dir_all(&regripper).unwrap();
+        fs::write(regripper.join("regripper_system.psv"), "\
+Launching plugin
+1704164645|REG|WKS01|jsmith|Run key written
+").unwrap();
+        let network = out_path.join("Network");
+        fs::create_dir_all(&network).unwrap();
+        fs::write(network.join("hindsight.jsonl"), "\
+{\"datetime\": \"2024-01-10T08:00:00.000000\", \"url\": \"https://example.com\", \"message\": \"visit\"}
+").unwrap();
+        let history = out_path.join("PSReadLine");
+        fs::create_dir_all(&history).unwrap();
+        fs::write(history.join("jsmith_ConsoleHost_history.txt"), "whoami\nnet user\n").unwrap();
+        let system = temp_dir.path().join("SYSTEM");
+        fs::write(&system, system_hive("WKS01")).unwrap();
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-01-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: timeline\nbinary: 'builtin:timeline'\nargs: ''\noutfolder: Timeline\noutfile: timeline.json\ninput: none\ninput_other: system\n"
+        ).unwrap();
+        let data_paths = HashMap::from([
+            ("none".to_string(), "".to_string()),
+            ("system".to_string(), system.to_string_lossy().to_string()),
+        ]);
+
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("host WKS01: 9"), "{msg}");
+
+        let events = read_events(&out_path.join("Timeline").join("timeline.json"));
+        assert!(events.windows(2).all(|w| w[0].datetime <= w[1].datetime));
+        assert_eq!(events.iter().filter(|e| e.source == "mft").count(), 3);
+        let events: Vec<TimelineEvent> = events.into_iter().filter(|e| e.source != "mft").collect();
+        let times: Vec<&str> = events.iter().map(|e| e.datetime.as_str()).collect();
+        assert_eq!(times, vec![
+            "2024-01-02T03:04:05.000Z", "2024-01-03T00:00:00.000Z", "2024-01-04T00:00:00.000Z",
+            "2024-01-05T00:00:00.000Z", "2024-01-10T08:00:00.000Z", "2024-01-20T10:00:00.000Z",
+        ]);
+        assert!(events.iter().all(|e| e.host == "WKS01"));
+
+        let regripper = &events[0];
+        assert_eq!(regripper.source, "regripper_reg");
+        assert_eq!(regripper.message, "REG; WKS01; jsmith; Run key written");
+        assert_eq!(regripper.user, "jsmith");
+
+        let history = &events[1];
+        assert_eq!(history.source, "powershell_history");
+        assert_eq!(history.message, "whoami");
+        assert_eq!(history.timestamp_desc, "powershell_history - jsmith_ConsoleHost_history.txt: Created0x10");
+        assert_eq!(history.user, "jsmith");
+        assert!(history.path.ends_with(r"PowerShell\PSReadLine"), "{}", history.path);
+        assert_eq!(events[2].message, "net user");
+        assert_eq!(events[3].message, "whoami; net user");
+
+        let recycle = &events[5];
+        assert_eq!(recycle.source, "recycle_bin");
+        assert_eq!(recycle.timestamp_desc, "recycle_bin - recycle_bin.csv: DeletedOn");
+        assert_eq!(recycle.message, r"C:\Tools\rclone.exe; 10; true; abc");
+        assert_eq!(recycle.user, "jsmith");
+        assert_eq!(recycle.path, r"C:\Tools\rclone.exe");
+    }
+
+    fn timeline_event(datetime: &str, source: &str, message: &str) -> TimelineEvent {
+        TimelineEvent {
+            datetime: datetime.to_string(),
+            timestamp_desc: format!("{source} - test.csv: Time"),
+            source: source.to_string(),
+            message: message.to_string(),
+            host: "WKS01".to_string(),
+            path: r"C:\Tools\rclone.exe".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Test the Timesketch options are read from the args, with quoted sketch names
+    #[test]
+    fn test_timesketch_options_from_args() {
+        assert_eq!(TimesketchOptions::from_args("--mapping timeline.yaml").unwrap(), None);
+        let options = TimesketchOptions::from_args("--timesketch").unwrap().unwrap();
+        assert_eq!(options, TimesketchOptions::default());
+        let options = TimesketchOptions::from_args(
+            "--timesketch-sketch-name \"Case 42\" --timesketch-format csv --timesketch-max-mb 5"
+        ).unwrap().unwrap();
+        assert_eq!(options.sketch_name.as_deref(), Some("Case 42"));
+        assert_eq!(options.format, ExportFormat::Csv);
+        assert_eq!(options.max_bytes, 5 * 1024 * 1024);
+        assert!(TimesketchOptions::from_args("--timesketch --timesketch-format xml").is_err());
+    }
+
+    /// Test the export has the required fields, is split under the size limit and
+    /// writes the summary and import manifest
+    #[test]
+    fn test_timesketch_export_splits_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = temp_dir.path().join("timeline.json");
+        let events = [
+            timeline_event("2024-01-02T03:04:05.000Z", "mft", "created"),
+            timeline_event("2024-01-02T05:00:00.000Z", "recycle_bin", ""),
+            timeline_event("2024-01-03T00:00:00.000Z", "mft", "modified"),
+        ];
+        let mut lines: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        lines.push("not json".to_string());
+        fs::write(&timeline_file, lines.join("\n")).unwrap();
+
+        let options = TimesketchOptions { max_bytes: 300, sketch_name: Some("Case 42".to_string()), ..Default::default() };
+        let summary = timesketch::export(&timeline_file, temp_dir.path(), "WKS01", &options).unwrap();
+        assert_eq!((summary.events, summary.skipped), (3, 1));
+        assert_eq!(summary.per_source["mft"], 2);
+        assert_eq!(summary.per_day["2024-01-02"], 2);
+        assert_eq!(summary.per_day["2024-01-03"], 1);
+        assert_eq!(summary.first, "2024-01-02T03:04:05.000Z");
+        assert_eq!(summary.last, "2024-01-03T00:00:00.000Z");
+        assert!(summary.files.len() > 1);
+        assert_eq!(summary.files.iter().map(|f| f.events).sum::<usize>(), 3);
+
+        let out_dir = temp_dir.path().join(timesketch::TIMESKETCH_FOLDER);
+        let mut exported = Vec::new();
+        for file in &summary.files {
+            let data = fs::read_to_string(out_dir.join(&file.file)).unwrap();
+            assert!(file.file.starts_with("WKS01_timeline_"));
+            assert!(file.events == 1 || data.len() as u64 <= options.max_bytes);
+            exported.extend(data.lines().map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()));
+        }
+        for event in &exported {
+            for field in ["message", "datetime", "timestamp_desc"] {
+                assert!(!event[field].as_str().unwrap().is_empty(), "{field} missing");
+            }
+        }
+        assert_eq!(exported[0]["timestamp"], 1704164645000000_i64);
+        assert_eq!(exported[1]["message"], r"recycle_bin: C:\Tools\rclone.exe");
+
+        let manifest: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(out_dir.join(timesketch::MANIFEST_FILE)).unwrap()
+        ).unwrap();
+        assert_eq!(manifest["sketch_name"], "Case 42");
+        assert_eq!(manifest["timelines"].as_array().unwrap().len(), summary.files.len());
+        assert!(manifest["timelines"][0]["command"].as_str().unwrap().contains("--sketch_name \"Case 42\""));
+        assert!(out_dir.join(timesketch::SUMMARY_FILE).is_file());
+    }
+
+    /// Test the CSV export has Timesketch's header in each file
+    #[test]
+    fn test_timesketch_export_csv() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = temp_dir.path().join("timeline.json");
+        let event = timeline_event("2024-01-02T03:04:05.000Z", "mft", "created, with comma");
+        fs::write(&timeline_file, serde_json::to_string(&event).unwrap()).unwrap();
+        let options = TimesketchOptions { format: ExportFormat::Csv, ..Default::default() };
+        let summary = timesketch::export(&timeline_file, temp_dir.path(), "WKS01", &options).unwrap();
+        assert_eq!(summary.files.len(), 1);
+        let out_dir = temp_dir.path().join(timesketch::TIMESKETCH_FOLDER);
+        let mut reader = csv::Reader::from_path(out_dir.join(&summary.files[0].file)).unwrap();
+        assert_eq!(&reader.headers().unwrap().iter().take(3).collect::<Vec<_>>(), &["message", "datetime", "timestamp_desc"]);
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(&record[0], "created, with comma");
+        assert!(!out_dir.join(timesketch::MANIFEST_FILE).exists());
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use chrono::NaiveDateTime;
+    use tempfile::TempDir;
+    use crate::configs::config::Wiskers;
+    use crate::tests::create_main_args;
+    use crate::ops::builtin_ops;
+    use crate::parsers::common;
+    use crate::parsers::hive::{Hive, REG_BINARY};
+    use crate::tests::hive_tests::builder::HiveBuilder;
+    use crate::timeline::builder::TimelineEvent;
+    use crate::timeline::mapping::{self, SourceMapping};
+    use crate::timeline::timezone::{DisplayTimezone, SystemTimezone};
+
+    /// a SYSTEMTIME of a daylight saving transition
+    fn systemtime(month: u16, day_of_week: u16, week: u16, hour: u16) -> Vec<u8> {
+        [0, month, day_of_week, week, hour, 0, 0, 0].iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// a SYSTEM hive with the timezone's key name, bias and transitions
+    fn system_hive(name: &str, bias: i32, daylight_start: Vec<u8>, standard_start: Vec<u8>) -> Vec<u8> {
+        let mut hb = HiveBuilder::new();
+        let current = hb.dword("Current", 1);
+        let select = hb.key("Select", 0, &[], &[current]);
+        let computer = hb.string("ComputerName", "WKS01");
+        let computer_name = hb.path(r"ComputerName\ComputerName", &[], &[computer]);
+        let values = [
+            hb.string("TimeZoneKeyName", name),
+            hb.dword("Bias", bias as u32),
+            hb.dword("StandardBias", 0),
+            hb.dword("DaylightBias", (-60i32) as u32),
+            hb.value("DaylightStart", REG_BINARY, &daylight_start),
+            hb.value("StandardStart", REG_BINARY, &standard_start),
+        ];
+        let tz = hb.key("TimeZoneInformation", 0, &[], &values);
+        let control = hb.path(r"ControlSet001\Control", &[computer_name, tz], &[]);
+        let root = hb.key("ROOT", 0, &[select, control], &[]);
+        hb.build(root)
+    }
+
+    /// GMT Standard Time, with daylight saving from the last Sunday of March at 01:00 to
+    /// the last Sunday of October at 02:00
+    fn london() -> SystemTimezone {
+        let hive = Hive::from_bytes(system_hive("GMT Standard Time", 0, systemtime(3, 0, 5, 1), systemtime(10, 0, 5, 2))).unwrap();
+        SystemTimezone::from_system(&hive).unwrap()
+    }
+
+    fn local(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    /// Test local times are converted to UTC with the daylight saving of the SYSTEM hive
+    #[test]
+    fn test_system_timezone_to_utc() {
+        let tz = london();
+        assert_eq!(tz.name, "GMT Standard Time");
+        assert_eq!(tz.describe(), "GMT Standard Time (UTC+00:00)");
+        let utc = |value: &str| common::fmt_dt(Some(tz.to_utc(&local(value))));
+        assert_eq!(utc("2024-01-15 12:00:00"), "2024-01-15T12:00:00.000Z");
+        assert_eq!(utc("2024-07-15 12:00:00"), "2024-07-15T11:00:00.000Z");
+        // 2024's transitions are the 31st of March and the 27th of October
+        assert_eq!(utc("2024-03-31 00:59:00"), "2024-03-31T00:59:00.000Z");
+        assert_eq!(utc("2024-03-31 02:00:00"), "2024-03-31T01:00:00.000Z");
+        assert_eq!(utc("2024-10-27 02:30:00"), "2024-10-27T02:30:00.000Z");
+
+        let hive = Hive::from_bytes(system_hive(
+            "AUS Eastern Standard Time", -600, systemtime(10, 0, 1, 2), systemtime(4, 0, 1, 3)
+        )).unwrap();
+        let sydney = SystemTimezone::from_system(&hive).unwrap();
+        assert_eq!(sydney.describe(), "AUS Eastern Standard Time (UTC+10:00)");
+        assert_eq!(common::fmt_dt(Some(sydney.to_utc(&local("2024-01-15 12:00:00")))), "2024-01-15T01:00:00.000Z");
+        assert_eq!(common::fmt_dt(Some(sydney.to_utc(&local("2024-07-15 12:00:00")))), "2024-07-15T02:00:00.000Z");
+    }
+
+    /// Test a source's format is detected from its sampled times, with ambiguous dates day
+    /// first unless a day over 12 shows they are month first
+    #[test]
+    fn test_detect_time_format() {
+        let detect = |values: &[&str]| mapping::detect_time_format(&values.iter().map(|v| v.to_string()).collect::<Vec<_>>());
+        assert_eq!(detect(&["2024-01-02 03:04:05.1234567", "2024-01-03 03:04:05"]).as_deref(), Some("%Y-%m-%d %H:%M:%S%.f"));
+        assert_eq!(detect(&["02/01/2024 03:04:05", "03/01/2024 03:04:05"]).as_deref(), Some("%d/%m/%Y %H:%M:%S%.f"));
+        assert_eq!(detect(&["01/02/2024 03:04:05", "01/13/2024 03:04:05"]).as_deref(), Some("%m/%d/%Y %H:%M:%S%.f"));
+        assert_eq!(detect(&["1/13/2024 3:04:05 PM", "1/2/2024 9:00:00 AM"]).as_deref(), Some("%m/%d/%Y %I:%M:%S %p"));
+        assert_eq!(detect(&["1704164645", "1704164700"]).as_deref(), Some("%s"));
+        assert_eq!(detect(&["2024-01-02T03:04:05Z", ""]), None);
+        assert_eq!(detect(&["not a time"]), None);
+    }
+
+    /// Test the display timezone is read as an IANA name, an offset or the system's
+    #[test]
+    fn test_display_timezone() {
+        let dt = mapping::parse_time("2024-07-15T11:00:00Z", "").unwrap();
+        let format = |zone: &str| DisplayTimezone::parse(zone, Some(&london())).unwrap().format(&dt);
+        assert_eq!(format("Europe/London"), "2024-07-15T12:00:00.000+01:00");
+        assert_eq!(format("America/New_York"), "2024-07-15T07:00:00.000-04:00");
+        assert_eq!(format("+05:30"), "2024-07-15T16:30:00.000+05:30");
+        assert_eq!(format("UTC-03:00"), "2024-07-15T08:00:00.000-03:00");
+        assert_eq!(format("UTC"), "2024-07-15T11:00:00.000+00:00");
+        assert_eq!(format("system"), "2024-07-15T12:00:00.000+01:00");
+        assert!(DisplayTimezone::parse("Mars/Olympus", None).is_err());
+        assert!(DisplayTimezone::parse("system", None).is_err());
+    }
+
+    /// Test the times of a local time source are detected, corrected to UTC by the system's
+    /// timezone and shown in the display timezone
+    #[test]
+    fn test_timeline_local_times_and_display() {
+        let temp_dir = TempDir::new().unwrap();
+        let out_path = temp_dir.path().join("out");
+        let network = out_path.join("Network");
+        fs::create_dir_all(&network).unwrap();
+        fs::write(network.join("BrowsingHistory.csv"), "\
+URL,Title,Visit Time,Visited From,Visit Type,Web Browser,User Profile
+https://example.com/a,A,07/15/2024 12:00:00,,Link,Chrome,jsmith
+https://example.com/b,B,01/13/2024 12:00:00,,Link,Chrome,jsmith
+").unwrap();
+        let system = temp_dir.path().join("SYSTEM");
+        fs::write(&system, system_hive("GMT Standard Time", 0, systemtime(3, 0, 5, 1), systemtime(10, 0, 5, 2))).unwrap();
+
+        let main_args = create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31");
+        let wisker: Wiskers = serde_yaml::from_str(
+            "name: timeline\nbinary: 'builtin:timeline'\nargs: '--display-timezone Asia/Tokyo'\noutfolder: Timeline\noutfile: timeline.json\ninput: none\ninput_other: system\n"
+        ).unwrap();
+        let data_paths = HashMap::from([
+            ("none".to_string(), "".to_string()),
+            ("system".to_string(), system.to_string_lossy().to_string()),
+        ]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("browser-hist: 2"), "{msg}");
+        assert!(msg.contains("System timezone: GMT Standard Time (UTC+00:00)"), "{msg}");
+
+        let events: Vec<TimelineEvent> = fs::read_to_string(out_path.join("Timeline").join("timeline.json")).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let times: Vec<(&str, &str)> = events.iter().map(|e| (e.datetime.as_str(), e.datetime_display.as_str())).collect();
+        assert_eq!(times, vec![
+            ("2024-01-13T12:00:00.000Z", "2024-01-13T21:00:00.000+09:00"),
+            ("2024-07-15T11:00:00.000Z", "2024-07-15T20:00:00.000+09:00"),
+        ]);
+    }
+
+    /// Test an unknown source timezone is an error, and local times are UTC without a SYSTEM hive
+    #[test]
+    fn test_time_parser_timezone() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("times.csv");
+        fs::write(&file, "Time\n2024-07-15 12:00:00\n").unwrap();
+        let source = SourceMapping { name: "times".to_string(), times: vec!["Time".to_string()], timezone: "mars".to_string(), ..Default::default() };
+        assert!(source.time_parser(&file, None).is_err());
+
+        let source = SourceMapping { timezone: "local".to_string(), ..source };
+        let parser = source.time_parser(&file, None).unwrap();
+        assert_eq!(common::fmt_dt(parser.parse("2024-07-15 12:00:00")), "2024-07-15T12:00:00.000Z");
+        let parser = source.time_parser(&file, Some(&london())).unwrap();
+        assert_eq!(parser.format, "%Y-%m-%d %H:%M:%S%.f");
+        assert_eq!(common::fmt_dt(parser.parse("2024-07-15 12:00:00")), "2024-07-15T11:00:00.000Z");
+        assert_eq!(common::fmt_dt(parser.parse("2024-07-15T12:00:00+02:00")), "2024-07-15T10:00:00.000Z");
+    }
+}
\ No newline at end of file
@@ -16,6 +16,9 @@ mod tests {
             ioc_file: "test_iocs.txt".to_string(),
             silent: true,
             collect: false,
+            out_format: Default::default(),
+            yara_rules: String::new(),
+            keep_timeline: false,
             out_log: PathBuf::from("/tmp/test.log"),
             multi_pb: MultiProgress::new()
         }
@@ -0,0 +1,242 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+    use crate::configs::config::{MainArgs, Wiskers};
+    use crate::tests::create_main_args;
+    use crate::iocs::yara::{self, YaraScanner, YaraSource};
+    use crate::iocs::yara_rules::{self, Expr, MetaValue, Pattern};
+    use crate::ops::builtin_ops;
+
+    /// the names of the rules matching the data
+    fn matching(rules: &str, data: &[u8]) -> Vec<String> {
+        let (rules, errors) = yara_rules::parse_rules(rules, "test");
+        assert!(errors.is_empty(), "{errors:?}");
+        let (scanner, errors) = YaraScanner::new(rules).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sample.bin");
+        fs::write(&path, data).unwrap();
+        scanner.scan_file(&path, YaraSource::DataSource).unwrap().into_iter().map(|m| m.rule).collect()
+    }
+
+    /// Test rules are parsed with their tags, meta and strings, and those that aren't
+    /// supported are skipped, keeping the rest of the file
+    #[test]
+    fn test_parse_rules() {
+        let source = r#"
+            import "pe"
+            // a comment
+            rule Loader : apt loader {
+                meta:
+                    author = "analyst"
+                    score = -5
+                    active = true
+                strings:
+                    $a = "cmd.exe /c" nocase wide ascii
+                    $b = { 4D 5A ?? [2-4] ( 90 | 9? ) }
+                    $c = /https?:\/\/[a-z]+\.top/i
+                condition:
+                    uint16(0) == 0x5A4D and filesize < 2MB and 2 of ($a, $b*) or #c > 1
+            }
+            rule UsesPe { condition: pe.is_dll() }
+            /* a block
+               comment */
+            private rule Xored { strings: $x = "key" xor condition: $x }
+            rule Child { condition: Loader and not UsesPe }
+        "#;
+        let (rules, errors) = yara_rules::parse_rules(source, "apt");
+        let names: Vec<&str> = rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Loader"]);
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(errors[0].starts_with("UsesPe: The module pe isn't supported"), "{errors:?}");
+        assert!(errors[1].starts_with("Xored: The xor modifier"), "{errors:?}");
+        assert_eq!(errors[2], "Child: unknown rule or identifier UsesPe");
+
+        let loader = &rules[0];
+        assert_eq!((loader.namespace.as_str(), loader.tags.clone()), ("apt", vec!["apt".to_string(), "loader".to_string()]));
+        assert_eq!(loader.meta, vec![
+            ("author".to_string(), MetaValue::Text("analyst".to_string())),
+            ("score".to_string(), MetaValue::Int(-5)),
+            ("active".to_string(), MetaValue::Bool(true)),
+        ]);
+        let a = &loader.strings[0];
+        assert_eq!((a.nocase, a.wide, a.ascii, a.pattern.clone()), (true, true, true, Pattern::Literal(b"cmd.exe /c".to_vec())));
+        assert_eq!(loader.strings[1].pattern, Pattern::Regex(yara_rules::hex_to_regex(" 4D 5A ?? [2-4] ( 90 | 9? ) ").unwrap()));
+        assert_eq!(loader.strings[2].pattern, Pattern::Regex(r"(?-ui)https?://[a-z]+\.top".to_string()));
+        assert!(matches!(loader.condition, Expr::Or(_)));
+
+        assert!(yara_rules::hex_to_regex("4D 5A [4-2]").is_err());
+        assert!(yara_rules::hex_to_regex("4D ( 5A").is_err());
+        assert!(yara_rules::hex_to_regex("4G").is_err());
+        let (_, errors) = yara_rules::parse_rules("rule A { strings: $a = \"x\" condition: $b }", "test");
+        assert_eq!(errors, vec!["A: Rule A has no string $b".to_string()]);
+    }
+
+    /// Test each rule using what the parser doesn't support is reported as skipped, rather
+    /// than parsed to a condition YARA wouldn't have, and a size too large is an error
+    #[test]
+    fn test_parse_rules_reports_unsupported() {
+        let unsupported = [
+            "rule Math { condition: math.entropy(0, filesize) > 7 }",
+            "rule Loop { strings: $a = \"a\" condition: for all i in (1..#a) : (@a[i] < 100) }",
+            "rule Entry { condition: entrypoint == 0 }",
+            "rule B64 { strings: $a = \"cmd\" base64 condition: $a }",
+            "rule Wide64 { strings: $a = \"cmd\" base64wide condition: $a }",
+            "rule Contains { condition: \"abc\" contains \"b\" }",
+            "rule IContains { condition: \"abc\" icontains \"B\" }",
+            "rule Matches { condition: \"abc\" matches /b/ }",
+            "rule Huge { condition: filesize < 99999999999999MB }",
+        ];
+        for rule in unsupported {
+            let (rules, errors) = yara_rules::parse_rules(rule, "test");
+            assert!(rules.is_empty() && errors.len() == 1, "{rule}: {errors:?}");
+        }
+        let (_, errors) = yara_rules::parse_rules(unsupported[8], "test");
+        assert_eq!(errors, vec!["Huge: Number 99999999999999MB is too large on line 1".to_string()]);
+    }
+
+    /// Test a rule that can't be lexed or a stray token between the rules is skipped on its
+    /// own, with the rules before and after it kept
+    #[test]
+    fn test_parse_rules_skips_bad_rules() {
+        let source = r#"
+            import "pe"
+            import "math"
+            rule First { strings: $a = "first" condition: $a }
+            rule BadRegex { strings: $r = /unclosed
+                condition: $r }
+            rule BadChar { condition: filesize ? 10 }
+            stray ;
+            rule Second { strings: $b = "second" condition: $b }
+            rule BadString { strings: $s = "no end
+                condition: $s }
+            rule Last { condition: First or Second }
+        "#;
+        let (rules, errors) = yara_rules::parse_rules(source, "mixed");
+        let names: Vec<&str> = rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["First", "Second", "Last"]);
+        assert_eq!(errors, vec![
+            "BadRegex: Unclosed regex on line 5".to_string(),
+            "BadChar: Unexpected character ? on line 7".to_string(),
+            "Unexpected Ident(\"stray\") on line 8".to_string(),
+            "BadString: Invalid string on line 10".to_string(),
+        ]);
+    }
+
+    /// Test the strings and conditions match as YARA does
+    #[test]
+    fn test_yara_conditions() {
+        let mut data = b"MZ\x90\x00".to_vec();
+        data.extend(b"xx powershell -enc xx ");
+        data.extend("Invoke-Mimikatz".encode_utf16().flat_map(|c| c.to_le_bytes()));
+        data.extend(b" MIMIKATZ evil.top/a evilxtop bad.top ");
+        let rules = r#"
+            rule Mz { condition: uint16(0) == 0x5A4D and uint32be(0) == 0x4D5A9000 and int8(2) == -112 }
+            rule Size { condition: filesize > 10 and filesize < 1KB }
+            rule Hex { strings: $h = { 4D [1] ( 91 | 90 ) 0? } condition: $h at 0 }
+            rule HexMiss { strings: $h = { 4D ~5A 90 } condition: $h }
+            rule Wide { strings: $w = "invoke-mimikatz" nocase wide condition: $w }
+            rule CaseSensitive { strings: $w = "invoke-mimikatz" wide condition: $w }
+            rule Counts {
+                strings: $m = "mimikatz" nocase $r = /[a-z]+\.top/
+                condition: #m == 1 and #r == 7 and @r[2] > @r[1] and !r[1] == 8 and !r[2] == 7 and $r in (20..filesize)
+            }
+            rule Fullword { strings: $f = "evil" fullword condition: #f == 1 }
+            rule Percent { strings: $a = "powershell" $b = "-enc" $c = "absent" condition: 60% of them and not all of them }
+            rule NoneOf { strings: $a = "absent" $b = "missing" condition: none of them }
+            private rule Base { strings: $p = "powershell" private condition: $p }
+            rule Derived { condition: Base and Mz }
+            rule Arithmetic { condition: (filesize \ 2) * 2 + filesize % 2 == filesize and 1 << 4 == 16 and -(3) + ~0 == -4 }
+            rule Undefined { condition: not (uint32(filesize) == 0) or 1 \ 0 == 0 }
+        "#;
+        let mut found = matching(rules, &data);
+        found.sort();
+        let mut expected = vec!["Arithmetic", "Counts", "Derived", "Fullword", "Hex", "Mz", "NoneOf", "Percent", "Size", "Wide"];
+        expected.sort();
+        assert_eq!(found, expected);
+
+        // regex and hex strings match at every offset they can, overlapping as in YARA
+        let overlapping = r#"
+            rule Overlap { strings: $r = /aa/ $h = { 61 61 } condition: #r == 3 and #h == 3 and @r[2] == 1 }
+        "#;
+        assert_eq!(matching(overlapping, b"aaaa"), vec!["Overlap"]);
+
+        let global = "global rule Small { condition: filesize < 10 } rule Any { condition: true }";
+        assert!(matching(global, &data).is_empty());
+        assert_eq!(matching(global, b"MZ"), vec!["Small", "Any"]);
+    }
+
+    /// Test the data source and Artefacts are scanned with the rules of the config and the
+    /// command line, with the parsed rules cached between runs
+    #[test]
+    fn test_run_builtin_yara() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("collection");
+        let out_path = temp_dir.path().join("out");
+        let config_rules = temp_dir.path().join("rules");
+        let cli_rules = temp_dir.path().join("cli_rules.yar");
+        fs::create_dir_all(config_rules.join("common")).unwrap();
+        fs::write(config_rules.join("webshells.yar"), r#"
+            include "common/strings.yar"
+            rule Webshell : webshell { meta: description = "eval of a request" strings: $e = "eval($_POST" condition: $e }
+        "#).unwrap();
+        fs::write(config_rules.join("common").join("strings.yar"), "rule Tiny { condition: filesize < 3 }\n").unwrap();
+        fs::write(config_rules.join("broken.yar"), "rule Broken { condition: pe.is_dll() }\n").unwrap();
+        fs::write(config_rules.join("readme.txt"), "not rules").unwrap();
+        fs::write(&cli_rules, "rule Tool { strings: $t = \"rclone\" condition: $t }\n").unwrap();
+
+        fs::create_dir_all(input.join("inetpub").join("wwwroot")).unwrap();
+        fs::create_dir_all(input.join("excluded")).unwrap();
+        fs::create_dir_all(out_path.join("Artefacts")).unwrap();
+        fs::write(input.join("inetpub").join("wwwroot").join("shell.php"), "<?php eval($_POST['x']); ?>").unwrap();
+        fs::write(input.join("excluded").join("shell.php"), "<?php eval($_POST['x']); ?>").unwrap();
+        fs::write(input.join("clean.txt"), "nothing to see").unwrap();
+        fs::write(out_path.join("Artefacts").join("copied.bin"), "ran rclone copy").unwrap();
+
+        let main_args = MainArgs { yara_rules: cli_rules.to_string_lossy().to_string(), ..create_main_args(temp_dir.path(), &out_path, "2024-01-01", "2024-12-31") };
+        let wisker: Wiskers = serde_yaml::from_str(&format!(
+            "name: yara\nbinary: 'builtin:yara'\nargs: '--rules {} --exclude excluded'\noutfolder: IOC_Findings\noutfile: yara_matches.jsonl\ninput: base\n",
+            config_rules.display()
+        )).unwrap();
+        let data_paths = HashMap::from([("base".to_string(), input.to_string_lossy().to_string())]);
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert_eq!(msg, "YARA matches: 2 from 4 rules of 4 rule files (0 cached, 1 rules skipped), with 3 files scanned and 0 matches suppressed by the allowlist");
+        let msg = builtin_ops::run_builtin(&wisker, &data_paths, &main_args).unwrap();
+        assert!(msg.contains("of 4 rule files (4 cached, 1 rules skipped)"), "{msg}");
+        let cache = temp_dir.path().join("tools").join("yara_cache");
+        assert_eq!(fs::read_dir(cache).unwrap().count(), 4);
+
+        let out_folder = out_path.join("IOC_Findings");
+        let errors = fs::read_to_string(out_folder.join("yara_matches_rule_errors.txt")).unwrap();
+        assert!(errors.contains("broken.yar: Broken: The module pe isn't supported"), "{errors}");
+        let matches: Vec<serde_json::Value> = fs::read_to_string(out_folder.join("yara_matches.jsonl")).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(matches.len(), 2);
+        let shell = matches.iter().find(|m| m["rule"] == "Webshell").unwrap();
+        assert_eq!(shell["namespace"], "webshells");
+        assert_eq!(shell["source"], "data_source");
+        assert_eq!(shell["tags"], serde_json::json!(["webshell"]));
+        assert_eq!(shell["meta"]["description"], "eval of a request");
+        assert!(shell["path"].as_str().unwrap().contains("wwwroot"));
+        assert_eq!(shell["strings"], serde_json::json!([{"identifier": "$e", "offset": 6, "length": 11, "data": "eval($_POST"}]));
+        let tool = matches.iter().find(|m| m["rule"] == "Tool").unwrap();
+        assert_eq!((tool["source"].as_str(), tool["namespace"].as_str()), (Some("artefacts"), Some("cli_rules")));
+
+        assert_eq!(yara::rule_files(&[PathBuf::from(&config_rules)]).len(), 3);
+        let log = fs::read_to_string(&main_args.out_log).unwrap();
+        assert!(log.contains("[!] YARA rule skipped, ") && log.contains("Broken: The module pe isn't supported"), "{log}");
+        assert!(log.contains("[!] 1 YARA rules were skipped"), "{log}");
+
+        let strict: Wiskers = serde_yaml::from_str(&format!(
+            "name: yara\nbinary: 'builtin:yara'\nargs: '--rules {} --strict'\noutfolder: IOC_Findings\noutfile: yara_matches.jsonl\ninput: base\n",
+            config_rules.display()
+        )).unwrap();
+        let err = builtin_ops::run_builtin(&strict, &data_paths, &main_args).unwrap_err();
+        assert!(format!("{err:#}").contains("1 YARA rules were skipped"), "{err:#}");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+pub mod builder;
+pub mod elastic;
+pub mod mapping;
+pub mod merge;
+pub mod parquet;
+pub mod sender;
+pub mod sorter;
+pub mod split;
+pub mod splunk;
+pub mod timesketch;
+pub mod timezone;
@@ -0,0 +1,329 @@
+/*
+Timeline builder, replacing polars_tln.py.
+Walks the wiskess output folders for each source in the timeline mapping, making an
+event for each time column of each row in the case's date range. The events of all
+the sources are sorted by time and written as JSON lines to Timeline\timeline.json.
+All times are written in UTC, with local times converted by the system's timezone from
+the SYSTEM hive, and may be shown in a second zone with `--display-timezone <zone>`.
+*/
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::elastic::{self, ElasticOptions};
+use super::mapping::{SourceMapping, SourceRow, TimeParser, TimelineMapping};
+use super::parquet;
+use super::sender::{self, Sender};
+use super::sorter::{LineSorter, CHUNK_EVENTS};
+use super::splunk::{self, SplunkOptions};
+use super::timesketch::{self, TimesketchOptions};
+use super::timezone::{DisplayTimezone, SystemTimezone};
+use crate::ops::builtin_ops::BuiltinArgs;
+use crate::ops::file_ops;
+use crate::parsers::common;
+use crate::parsers::hive::{self, Hive};
+
+pub const TIMELINE_FILE: &str = "timeline.json";
+pub const UNKNOWN_HOST: &str = "Unknown";
+
+/// An event of the timeline, in the common schema of all sources. The datetime must be
+/// the first field, as the events are sorted by their JSON line. The datetime_display is
+/// only written with a display timezone
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub datetime: String,
+    pub timestamp_desc: String,
+    pub source: String,
+    pub message: String,
+    pub host: String,
+    pub user: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub datetime_display: String,
+}
+
+/// The timezones of the timeline, the system's to correct local times and the zone to
+/// display the events in
+#[derive(Debug, Clone, Default)]
+pub struct TimelineZones {
+    pub system: Option<SystemTimezone>,
+    pub display: Option<DisplayTimezone>,
+}
+
+impl TimelineZones {
+    /// the time in the display timezone, if one was chosen
+    pub fn display(&self, dt: &DateTime<Utc>) -> String {
+        self.display.as_ref().map(|tz| tz.format(dt)).unwrap_or_default()
+    }
+}
+
+/// The case's date range, with the end date including the whole day
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DateRange {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl DateRange {
+    /// the range from dates formatted as yyyy-mm-dd, dates that can't be parsed are unbounded
+    pub fn new(start_date: &str, end_date: &str) -> Self {
+        let day = |date: &str| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|d| d.and_utc());
+        DateRange {
+            start: day(start_date),
+            end: day(end_date).map(|d| d + Duration::days(1)),
+        }
+    }
+
+    pub fn contains(&self, dt: &DateTime<Utc>) -> bool {
+        self.start.is_none_or(|s| *dt >= s) && self.end.is_none_or(|e| *dt < e)
+    }
+}
+
+/// run the builtin timeline reporter, with the input_other as the SYSTEM hive to get the
+/// hostname and timezone. `--mapping <file>` in the args sets the timeline mapping, otherwise
+/// config/timeline.yaml beside the tools folder or the builtin mapping is used.
+/// Parquet is written when set by the run's out_format. The Timesketch, Elasticsearch
+/// and Splunk exports are made when enabled in the args, see `TimesketchOptions`,
+/// `ElasticOptions` and `SplunkOptions`. `--display-timezone <zone>` adds the
+/// datetime_display of each event, see `DisplayTimezone::parse`
+pub fn run(args: &BuiltinArgs) -> Result<String> {
+    let mapping = load_mapping(args)?;
+    let timesketch = TimesketchOptions::from_args(&args.args)?;
+    let system = match args.has_input_other() {
+        true => Hive::open(Path::new(&args.input_other)).ok(),
+        false => None,
+    };
+    let host = system.as_ref().and_then(hive::computer_name).unwrap_or_else(|| UNKNOWN_HOST.to_string());
+    let mut zones = TimelineZones { system: system.as_ref().and_then(SystemTimezone::from_system), display: None };
+    if let Some(zone) = arg_value(&args.args, "--display-timezone") {
+        zones.display = Some(DisplayTimezone::parse(&zone, zones.system.as_ref())?);
+    }
+    let system_zone = match &zones.system {
+        Some(tz) => tz.describe(),
+        None => "not found, local times are read as UTC".to_string(),
+    };
+    file_ops::log_msg(&args.main_args.out_log, format!("[ ] Timeline system timezone: {system_zone}"));
+    let elastic = ElasticOptions::from_args(&args.args, &case_name(args), &host)?;
+    let splunk = SplunkOptions::from_args(&args.args)?;
+    let range = DateRange::new(&args.main_args.start_date, &args.main_args.end_date);
+    let out_path = Path::new(&args.main_args.out_path);
+
+    let mut sorter = LineSorter::new(CHUNK_EVENTS);
+    let mut summary = Vec::new();
+    for source in &mapping.sources {
+        if source.times_from.is_some() {
+            match add_joined(source, out_path, &host, &range, &zones, &mut sorter) {
+                Ok(0) => (),
+                Ok(count) => summary.push(format!("{}: {count}", source.name)),
+                Err(e) => file_ops::log_msg(&args.main_args.out_log, format!(
+                    "[!] Unable to timeline {} with its joined times: {:#}", source.name, e
+                )),
+            }
+            continue;
+        }
+        for file in source.find_files(out_path) {
+            match add_file(source, &file, &host, &range, &zones, &mut sorter) {
+                Ok(0) => (),
+                Ok(count) => summary.push(format!("{}: {count}", source.name)),
+                Err(e) => file_ops::log_msg(&args.main_args.out_log, format!(
+                    "[!] Unable to timeline {} from {}: {:#}", source.name, file.display(), e
+                )),
+            }
+        }
+    }
+    let timeline_file = args.outfolder.join(TIMELINE_FILE);
+    let count = sorter.finish(&timeline_file)?;
+    let mut msg = format!(
+        "Timeline events written for host {host}: {count} ({}). System timezone: {system_zone}", summary.join(", ")
+    );
+    if args.main_args.out_format.parquet() {
+        let partitions = parquet::export(&timeline_file, &args.outfolder)?;
+        msg.push_str(&format!(". Parquet partitions written: {partitions}"));
+    }
+    if let Some(options) = timesketch {
+        let export = timesketch::export(&timeline_file, &args.outfolder, &host, &options)?;
+        msg.push_str(&format!(". Timesketch events exported: {} in {} files", export.events, export.files.len()));
+    }
+    if let Some(options) = elastic {
+        let files = elastic::export(&timeline_file, &args.outfolder, &host, &options)?;
+        msg.push_str(&format!(". Elasticsearch bulk files for index {}: {}", options.index, files.len()));
+        if let Some(url) = &options.url {
+            let sender = Sender::from_args(&args.args, std::env::var(sender::ELASTIC_AUTH_ENV).ok())?;
+            match elastic::send(&args.outfolder, &files, url, &sender) {
+                Ok(report) => msg.push_str(&format!(", sent {} documents with {} errors", report.items, report.errors)),
+                Err(e) => {
+                    file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to send the timeline to Elasticsearch: {:#}", e));
+                    msg.push_str(", sending failed");
+                }
+            }
+        }
+    }
+    if let Some(options) = splunk {
+        let files = splunk::export(&timeline_file, &args.outfolder, &host, &options)?;
+        msg.push_str(&format!(". Splunk HEC files: {}", files.len()));
+        if let Some(url) = &options.url {
+            let token = std::env::var(sender::SPLUNK_TOKEN_ENV).ok().map(|t| format!("Splunk {t}"));
+            let sender = Sender::from_args(&args.args, token)?;
+            match splunk::send(&args.outfolder, &files, url, &sender) {
+                Ok(events) => msg.push_str(&format!(", sent {events} events")),
+                Err(e) => {
+                    file_ops::log_msg(&args.main_args.out_log, format!("[!] Unable to send the timeline to Splunk: {:#}", e));
+                    msg.push_str(", sending failed");
+                }
+            }
+        }
+    }
+    Ok(msg)
+}
+
+/// read the events of a timeline file, calling `each` for every event and returning the
+/// number of lines that weren't events
+pub fn read_events(timeline_file: &Path, mut each: impl FnMut(TimelineEvent) -> Result<()>) -> Result<usize> {
+    let reader = BufReader::new(File::open(timeline_file)
+        .with_context(|| format!("Unable to open {}", timeline_file.display()))?);
+    let mut skipped = 0;
+    for line in reader.lines() {
+        match serde_json::from_str(&line?) {
+            Ok(event) => each(event)?,
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok(skipped)
+}
+
+/// split the wisker's args into words, keeping double quoted values together
+pub fn split_args(args: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in args.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// get the value following a flag in the wisker's args, i.e. `--mapping <file>`
+pub fn arg_value(args: &str, flag: &str) -> Option<String> {
+    let words = split_args(args);
+    let index = words.iter().position(|w| w == flag)?;
+    words.get(index + 1).cloned()
+}
+
+fn load_mapping(args: &BuiltinArgs) -> Result<TimelineMapping> {
+    if let Some(path) = arg_value(&args.args, "--mapping") {
+        return TimelineMapping::open(Path::new(&path));
+    }
+    let config = args.main_args.tool_path
+        .parent()
+        .map(|p| p.join("config").join("timeline.yaml"))
+        .unwrap_or_else(|| PathBuf::from("timeline.yaml"));
+    match config.is_file() {
+        true => TimelineMapping::open(&config),
+        false => Ok(TimelineMapping::default_mapping()),
+    }
+}
+
+/// get the case name from `--case <name>` in the args, otherwise the name of the out_path
+fn case_name(args: &BuiltinArgs) -> String {
+    arg_value(&args.args, "--case").unwrap_or_else(|| {
+        Path::new(&args.main_args.out_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "case".to_string())
+    })
+}
+
+/// add the events of an output file to the timeline, returning how many were added
+pub fn add_file(source: &SourceMapping, file: &Path, host: &str, range: &DateRange, zones: &TimelineZones, sorter: &mut LineSorter) -> Result<usize> {
+    let parser = source.time_parser(file, zones.system.as_ref())?;
+    let mut events = EventWriter { source, host, range, zones, sorter, count: 0, error: None };
+    source.read_rows(file, |row| events.push(file, row, &parser))?;
+    events.finish()
+}
+
+/// add the events of a source with `times_from`, with the times of each of its rows from
+/// the row of the joined output that matches it
+pub fn add_joined(source: &SourceMapping, out_path: &Path, host: &str, range: &DateRange, zones: &TimelineZones, sorter: &mut LineSorter) -> Result<usize> {
+    let Some(times_from) = &source.times_from else { return Ok(0) };
+    let files = source.find_files(out_path);
+    let joined = times_from.mapping(source);
+    let Some(join_file) = joined.find_files(out_path).into_iter().next() else { return Ok(0) };
+    if files.is_empty() {
+        return Ok(0);
+    }
+    let parser = joined.time_parser(&join_file, zones.system.as_ref())?;
+    let mut events = EventWriter { source, host, range, zones, sorter, count: 0, error: None };
+    source.read_joined(&join_file, &files, |file, row| events.push(file, row, &parser))?;
+    events.finish()
+}
+
+/// Writes the events of the rows of a source to the sorter, keeping the first error
+struct EventWriter<'a> {
+    source: &'a SourceMapping,
+    host: &'a str,
+    range: &'a DateRange,
+    zones: &'a TimelineZones,
+    sorter: &'a mut LineSorter,
+    count: usize,
+    error: Option<anyhow::Error>,
+}
+
+impl EventWriter<'_> {
+    /// push an event for each time column of the row in the date range
+    fn push(&mut self, file: &Path, row: &SourceRow, parser: &TimeParser) {
+        let source = self.source;
+        let file_name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        for time_column in &source.times {
+            let Some(dt) = row.get(time_column).and_then(|v| parser.parse(v)) else { continue };
+            if !self.range.contains(&dt) {
+                continue;
+            }
+            let columns = source.time_message.get(time_column).unwrap_or(&source.message);
+            let message: Vec<&str> = columns.iter().filter_map(|c| row.get(c)).collect();
+            let event = TimelineEvent {
+                datetime: common::fmt_dt(Some(dt)),
+                timestamp_desc: format!("{} - {file_name}: {time_column}", source.name),
+                source: source.name.clone(),
+                message: message.join("; "),
+                host: self.host.to_string(),
+                user: row.first(&source.user),
+                path: row.first(&source.path),
+                datetime_display: self.zones.display(&dt),
+            };
+            let pushed = serde_json::to_string(&event)
+                .map_err(anyhow::Error::from)
+                .and_then(|line| self.sorter.push(line));
+            match pushed {
+                Ok(_) => self.count += 1,
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> Result<usize> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.count),
+        }
+    }
+}
+
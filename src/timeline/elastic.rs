@@ -0,0 +1,144 @@
+/*
+Elasticsearch export of the timeline, replacing tools/timeline-wiskess.conf so Logstash
+isn't needed. Events are written as `_bulk` NDJSON with ECS field names, split into
+files that are each small enough for one bulk request. The document id is a hash of
+the event, as the Logstash fingerprint was, so posting the files again won't duplicate.
+*/
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use md5::{Digest, Md5};
+use serde_json::{json, Map, Value};
+
+use super::builder::{self, TimelineEvent};
+use super::sender::{self, Sender};
+use super::split::{ExportFile, SplitWriter};
+
+pub const ELASTIC_FOLDER: &str = "elastic";
+/// The default size limit of each bulk file, in MB
+const DEFAULT_MAX_MB: u64 = 10;
+
+/// The Elasticsearch export options, set in the timeline wisker's args
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElasticOptions {
+    pub index: String,
+    pub case: String,
+    /// the size limit of each file in bytes
+    pub max_bytes: u64,
+    /// the url of Elasticsearch to post the files to
+    pub url: Option<String>,
+}
+
+impl ElasticOptions {
+    /// get the options from the args, returning None if the export isn't enabled. The export
+    /// is enabled by `--elastic` or `--elastic-url <url>`, with `--elastic-index <name>` to
+    /// set the index, otherwise made from the case and host, and `--elastic-max-mb <size>`
+    pub fn from_args(args: &str, case: &str, host: &str) -> Result<Option<Self>> {
+        let url = builder::arg_value(args, "--elastic-url");
+        if url.is_none() && !builder::split_args(args).iter().any(|w| w == "--elastic") {
+            return Ok(None);
+        }
+        let index = builder::arg_value(args, "--elastic-index")
+            .unwrap_or_else(|| format!("wiskess-{case}-{host}"));
+        let max_mb = match builder::arg_value(args, "--elastic-max-mb") {
+            Some(max_mb) => max_mb.parse().with_context(|| format!("Invalid --elastic-max-mb: {max_mb}"))?,
+            None => DEFAULT_MAX_MB,
+        };
+        Ok(Some(ElasticOptions {
+            index: index_name(&index),
+            case: case.to_string(),
+            max_bytes: u64::max(max_mb, 1) * 1024 * 1024,
+            url,
+        }))
+    }
+}
+
+/// make a valid index name, which is lowercase without the characters Elasticsearch rejects
+pub fn index_name(name: &str) -> String {
+    let name: String = name.to_lowercase()
+        .chars()
+        .map(|c| if r#"\/*?"<>| ,#:"#.contains(c) { '_' } else { c })
+        .collect();
+    name.trim_start_matches(['-', '_', '+', '.']).to_string()
+}
+
+/// the id of the event's document, an MD5 of its time, description, message and host
+pub fn document_id(event: &TimelineEvent) -> String {
+    let mut hasher = Md5::new();
+    for field in [&event.datetime, &event.timestamp_desc, &event.message, &event.host] {
+        hasher.update(field.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// the event as an ECS document, leaving out the fields without a value
+pub fn ecs_document(event: &TimelineEvent, case: &str) -> Value {
+    let mut document = json!({
+        "@timestamp": event.datetime,
+        "message": event.message,
+        "event": {
+            "kind": "event",
+            "action": event.timestamp_desc,
+            "provider": event.source,
+            "dataset": format!("wiskess.{}", event.source),
+        },
+        "host": { "name": event.host },
+        "labels": { "case": case },
+        "tags": ["wiskess"],
+    });
+    let Some(fields) = document.as_object_mut() else { unreachable!() };
+    if !event.user.is_empty() {
+        fields.insert("user".to_string(), json!({ "name": event.user }));
+    }
+    if !event.path.is_empty() {
+        let mut file = Map::new();
+        file.insert("path".to_string(), json!(event.path));
+        if let Some(name) = event.path.rsplit(['\\', '/']).next().filter(|n| !n.is_empty()) {
+            file.insert("name".to_string(), json!(name));
+        }
+        fields.insert("file".to_string(), Value::Object(file));
+    }
+    document
+}
+
+/// export the timeline as bulk files to the elastic folder of the out_dir
+pub fn export(timeline_file: &Path, out_dir: &Path, host: &str, options: &ElasticOptions) -> Result<Vec<ExportFile>> {
+    let mut writer = SplitWriter::new(out_dir.join(ELASTIC_FOLDER), &format!("{host}_bulk"), "ndjson", options.max_bytes);
+    builder::read_events(timeline_file, |event| {
+        let action = json!({ "index": { "_index": options.index, "_id": document_id(&event) } });
+        let mut data = serde_json::to_vec(&action)?;
+        data.push(b'\n');
+        data.extend(serde_json::to_vec(&ecs_document(&event, &options.case))?);
+        data.push(b'\n');
+        writer.write(&data)
+    })?;
+    writer.finish()
+}
+
+/// The result of posting the bulk files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BulkReport {
+    pub files: usize,
+    pub items: usize,
+    /// the documents that Elasticsearch rejected
+    pub errors: usize,
+}
+
+/// post the bulk files to the `_bulk` endpoint of the url, counting the rejected documents
+pub fn send(out_dir: &Path, files: &[ExportFile], url: &str, sender: &Sender) -> Result<BulkReport> {
+    let url = sender::endpoint_url(url, "/_bulk");
+    let mut report = BulkReport::default();
+    for file in files {
+        let body = std::fs::read(out_dir.join(ELASTIC_FOLDER).join(&file.file))?;
+        let response = sender.post(&url, "application/x-ndjson", &body)
+            .with_context(|| format!("Unable to send {}", file.file))?;
+        let response: Value = serde_json::from_str(&response).unwrap_or_default();
+        let items = response["items"].as_array().map(Vec::as_slice).unwrap_or_default();
+        report.files += 1;
+        report.items += items.len();
+        report.errors += items.iter()
+            .filter(|i| i.as_object().and_then(|i| i.values().next()).is_some_and(|r| r.get("error").is_some()))
+            .count();
+    }
+    Ok(report)
+}
@@ -0,0 +1,480 @@
+/*
+Declarative mapping of the processed output files to the timeline's schema.
+The mapping is read from config/timeline.yaml, with a source for each output file
+that names its time columns and the columns used for the message, user and path.
+Sources without a time format have it detected from the first rows of each file, and
+sources in the host's local time are converted to UTC with the system's timezone.
+Outputs without timestamps of their own, such as the PowerShell history, take their
+times from a row of another output joined to each of their rows.
+*/
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use super::timezone::SystemTimezone;
+use crate::parsers::common;
+
+/// The default mapping, used when config/timeline.yaml isn't found beside the tools folder
+pub const DEFAULT_MAPPING: &str = include_str!("../../config/timeline.yaml");
+
+/// Time formats tried in order when a source has no time_format
+const AUTO_TIME_FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"];
+/// Time formats a source's format is detected from, in order of preference when the
+/// sampled times match more than one, so day first is preferred for ambiguous dates
+const DETECT_TIME_FORMATS: [&str; 12] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%d/%m/%Y %H:%M:%S%.f",
+    "%m/%d/%Y %H:%M:%S%.f",
+    "%d/%m/%Y %I:%M:%S %p",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%d/%m/%Y %H:%M",
+    "%m/%d/%Y %H:%M",
+    "%d.%m.%Y %H:%M:%S%.f",
+    "%d-%m-%Y %H:%M:%S%.f",
+];
+/// The number of rows of a file sampled to detect its time format
+const SAMPLE_ROWS: usize = 1000;
+/// Time formats with an offset, i.e. `2024-01-02 03:04:05.123 +01:00` from hayabusa
+const AUTO_OFFSET_FORMATS: [&str; 2] = ["%Y-%m-%d %H:%M:%S%.f %:z", "%Y-%m-%d %H:%M:%S%.f%:z"];
+
+/// The timeline mapping file, with a source for each processed output
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimelineMapping {
+    pub sources: Vec<SourceMapping>,
+}
+
+/// How the columns of an output file map to the timeline
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SourceMapping {
+    pub name: String,
+    /// the folder in the wiskess output, split by `/` or `\`
+    pub folder: String,
+    /// a regex of the file names in the folder, matched case-insensitive
+    pub file: String,
+    /// csv, psv, jsonl or text, if empty this is from the file extension. Each text file
+    /// is a single row of the columns in `TEXT_COLUMNS`
+    #[serde(default)]
+    pub format: String,
+    /// the column names, for files without a header
+    #[serde(default)]
+    pub columns: Vec<String>,
+    /// a regex of the lines to keep, others are skipped
+    #[serde(default)]
+    pub line_filter: String,
+    pub times: Vec<String>,
+    #[serde(default)]
+    pub time_format: String,
+    /// utc, or local for times in the host's timezone. Times with an offset keep it
+    #[serde(default)]
+    pub timezone: String,
+    #[serde(default)]
+    pub message: Vec<String>,
+    /// the message columns of a time column, used instead of `message` for its events
+    #[serde(default)]
+    pub time_message: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub user: Vec<String>,
+    #[serde(default)]
+    pub path: Vec<String>,
+    /// the output the times are joined from, for outputs without times of their own
+    #[serde(default)]
+    pub times_from: Option<TimesFrom>,
+}
+
+/// The output a source's times are joined from. Each row of the source is joined to the
+/// first row of the output that matches the filters and contains the row's values
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TimesFrom {
+    /// the folder in the wiskess output, split by `/` or `\`
+    pub folder: String,
+    /// a regex of the file names in the folder, the first match is read
+    pub file: String,
+    /// regexes of the column values the joined rows must match, case-insensitive
+    #[serde(default)]
+    pub filter: BTreeMap<String, String>,
+    /// the columns of the joined rows that must contain the value of a column of the
+    /// source's row, case-insensitive
+    #[serde(default)]
+    pub contains: BTreeMap<String, String>,
+}
+
+impl TimelineMapping {
+    /// read the mapping from a YAML file
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Unable to open timeline mapping {}", path.display()))?;
+        serde_yaml::from_reader(file)
+            .with_context(|| format!("Unable to parse timeline mapping {}", path.display()))
+    }
+
+    /// the mapping that is built into wiskess
+    pub fn default_mapping() -> Self {
+        serde_yaml::from_str(DEFAULT_MAPPING).expect("The builtin timeline mapping is valid")
+    }
+}
+
+/// The columns of a row of a text file: the file name, the first capture group of the
+/// source's file regex, and the first, last and all non-empty lines joined with `; `
+pub const TEXT_COLUMNS: [&str; 5] = ["File", "Name", "FirstLine", "LastLine", "Lines"];
+
+/// A record of an output file, with its values by column name
+#[derive(Clone)]
+pub struct SourceRow {
+    headers: Rc<Vec<String>>,
+    values: Vec<String>,
+}
+
+impl SourceRow {
+    /// get the value of a column, case-insensitive. Empty values and `-` are None
+    pub fn get(&self, column: &str) -> Option<&str> {
+        let index = self.headers.iter().position(|h| h.eq_ignore_ascii_case(column))?;
+        self.values.get(index)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && *v != "-")
+    }
+
+    /// get the value of the first of the columns that has one
+    pub fn first(&self, columns: &[String]) -> String {
+        columns.iter().find_map(|c| self.get(c)).unwrap_or_default().to_string()
+    }
+
+    /// the row with the columns of another row after its own
+    fn join(&self, other: &SourceRow) -> SourceRow {
+        SourceRow {
+            headers: Rc::new(self.headers.iter().chain(other.headers.iter()).cloned().collect()),
+            values: self.values.iter().chain(other.values.iter()).cloned().collect(),
+        }
+    }
+}
+
+impl TimesFrom {
+    /// the mapping of the joined output, with the time columns of the source
+    pub fn mapping(&self, source: &SourceMapping) -> SourceMapping {
+        SourceMapping {
+            name: source.name.clone(),
+            folder: self.folder.clone(),
+            file: self.file.clone(),
+            times: source.times.clone(),
+            time_format: source.time_format.clone(),
+            timezone: source.timezone.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, filters: &[(&String, Regex)], joined: &SourceRow, row: &SourceRow) -> bool {
+        filters.iter().all(|(column, re)| joined.get(column).is_some_and(|v| re.is_match(v)))
+            && self.contains.iter().all(|(column, value)| match (joined.get(column), row.get(value)) {
+                (Some(joined), Some(value)) => joined.to_lowercase().contains(&value.to_lowercase()),
+                _ => false,
+            })
+    }
+}
+
+impl SourceMapping {
+    /// find the files in the output folder that match this source
+    pub fn find_files(&self, out_path: &Path) -> Vec<PathBuf> {
+        let Some(folder) = common::join_case_insensitive(out_path, &self.folder) else { return Vec::new() };
+        let Ok(re) = RegexBuilder::new(&self.file).case_insensitive(true).build() else { return Vec::new() };
+        let Ok(entries) = std::fs::read_dir(folder) else { return Vec::new() };
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .filter(|p| p.file_name().is_some_and(|n| re.is_match(&n.to_string_lossy())))
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn file_format(&self, file: &Path) -> String {
+        if !self.format.is_empty() {
+            return self.format.to_lowercase();
+        }
+        let ext = file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        match ext.as_str() {
+            "psv" => "psv".to_string(),
+            "json" | "jsonl" => "jsonl".to_string(),
+            "txt" => "text".to_string(),
+            _ => "csv".to_string(),
+        }
+    }
+
+    /// read the rows of an output file, calling `each` for every row. Ragged rows are read
+    /// as they are, as tool output often is, and JSON lines that aren't objects are skipped
+    pub fn read_rows(&self, file: &Path, each: impl FnMut(&SourceRow)) -> Result<()> {
+        self.read_lines(file, None, each)
+    }
+
+    /// read the rows of the files of a source with `times_from`, each joined to the first
+    /// matching row of the joined output in a single read of it. The joined rows are given
+    /// with the file they are from, rows without a match are skipped
+    pub fn read_joined(&self, join_file: &Path, files: &[PathBuf], mut each: impl FnMut(&Path, &SourceRow)) -> Result<()> {
+        let Some(times_from) = &self.times_from else { bail!("The source {} has no times_from", self.name) };
+        let mut rows = Vec::new();
+        for (i, file) in files.iter().enumerate() {
+            self.read_rows(file, |row| rows.push((i, row.clone())))?;
+        }
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let filters = times_from.filter.iter()
+            .map(|(column, filter)| Ok((column, RegexBuilder::new(filter).case_insensitive(true).build()?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut joined: Vec<Option<SourceRow>> = vec![None; rows.len()];
+        times_from.mapping(self).read_rows(join_file, |other| {
+            for ((_, row), found) in rows.iter().zip(joined.iter_mut()) {
+                if found.is_none() && times_from.matches(&filters, other, row) {
+                    *found = Some(row.join(other));
+                }
+            }
+        })?;
+        for ((i, _), found) in rows.iter().zip(joined) {
+            if let Some(row) = found {
+                each(&files[*i], &row);
+            }
+        }
+        Ok(())
+    }
+
+    /// read the rows of the first lines of an output file, or of all its lines. Files with
+    /// a line filter are streamed through it
+    fn read_lines(&self, file: &Path, max_lines: Option<usize>, mut each: impl FnMut(&SourceRow)) -> Result<()> {
+        let format = self.file_format(file);
+        if format == "text" {
+            return self.read_text(file, each);
+        }
+        let reader = BufReader::new(File::open(file)
+            .with_context(|| format!("Unable to open {}", file.display()))?);
+        let line_filter = match self.line_filter.as_str() {
+            "" => None,
+            filter => Some(Regex::new(filter)?),
+        };
+        let reader: Box<dyn Read> = match (line_filter, max_lines) {
+            (None, None) => Box::new(reader),
+            (filter, max_lines) => Box::new(FilteredLines {
+                reader,
+                filter,
+                remaining: max_lines.unwrap_or(usize::MAX),
+                line: Vec::new(),
+                pos: 0,
+            }),
+        };
+        match format.as_str() {
+            "jsonl" => read_jsonl(reader, each),
+            format => {
+                let mut csv_reader = csv::ReaderBuilder::new()
+                    .delimiter(if format == "psv" { b'|' } else { b',' })
+                    .has_headers(self.columns.is_empty())
+                    .flexible(true)
+                    .from_reader(reader);
+                let headers = match self.columns.is_empty() {
+                    true => csv_reader.byte_headers()?.iter().map(|h| lossy(h).trim_start_matches('\u{feff}').to_string()).collect(),
+                    false => self.columns.clone(),
+                };
+                let headers = Rc::new(headers);
+                for record in csv_reader.byte_records().map_while(|r| r.ok()) {
+                    each(&SourceRow { headers: headers.clone(), values: record.iter().map(lossy).collect() });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// read a text file as a single row of the `TEXT_COLUMNS`. Files without a non-empty
+    /// line have no row
+    fn read_text(&self, file: &Path, mut each: impl FnMut(&SourceRow)) -> Result<()> {
+        let data = std::fs::read(file).with_context(|| format!("Unable to open {}", file.display()))?;
+        let text = String::from_utf8_lossy(&data);
+        let lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        let (Some(first), Some(last)) = (lines.first(), lines.last()) else { return Ok(()) };
+        let file_name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let name = RegexBuilder::new(&self.file).case_insensitive(true).build().ok()
+            .and_then(|re| re.captures(&file_name)?.get(1).map(|m| m.as_str().to_string()))
+            .unwrap_or_default();
+        let values = vec![file_name, name, first.to_string(), last.to_string(), lines.join("; ")];
+        each(&SourceRow { headers: Rc::new(TEXT_COLUMNS.map(String::from).to_vec()), values });
+        Ok(())
+    }
+
+    /// parse a value of a time column to UTC
+    pub fn parse_time(&self, value: &str) -> Option<DateTime<Utc>> {
+        parse_time(value, &self.time_format)
+    }
+
+    /// make the parser of the times of an output file, detecting their format from its
+    /// first rows when the source has none. Local times are converted with the system's
+    /// timezone, or read as UTC if it isn't known
+    pub fn time_parser(&self, file: &Path, system: Option<&SystemTimezone>) -> Result<TimeParser> {
+        let format = match self.time_format.is_empty() {
+            true => {
+                let mut values = Vec::new();
+                self.read_lines(file, Some(SAMPLE_ROWS + 1), |row| {
+                    values.extend(self.times.iter().filter_map(|c| row.get(c)).map(String::from));
+                })?;
+                detect_time_format(&values).unwrap_or_default()
+            }
+            false => self.time_format.clone(),
+        };
+        let local = match self.timezone.to_lowercase().as_str() {
+            "" | "utc" => None,
+            "local" => system.cloned(),
+            other => bail!("Unknown timezone {other} of source {}, use utc or local", self.name),
+        };
+        Ok(TimeParser { format, local })
+    }
+}
+
+/// Parses the times of an output file, in the format detected or set by its source
+#[derive(Debug, Clone, Default)]
+pub struct TimeParser {
+    pub format: String,
+    pub local: Option<SystemTimezone>,
+}
+
+impl TimeParser {
+    /// parse a time to UTC. Times with an offset keep it, others are in the local timezone
+    /// if the source has one, otherwise UTC. Times not in the format are read as `parse_time`
+    /// would without a format
+    pub fn parse(&self, value: &str) -> Option<DateTime<Utc>> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        if self.format == "%s" {
+            return common::unix_to_dt(value.parse().ok()?);
+        }
+        if let Some(dt) = parse_offset_time(value) {
+            return Some(dt);
+        }
+        let naive = match self.format.is_empty() {
+            true => None,
+            false => NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), &self.format).ok(),
+        };
+        let naive = naive.or_else(|| parse_naive_time(value))?;
+        match &self.local {
+            Some(tz) => Some(tz.to_utc(&naive)),
+            None => Some(naive.and_utc()),
+        }
+    }
+}
+
+/// detect the format of the sampled times, as the format that parses the most of them.
+/// Times with an offset need no format and unix seconds are `%s`
+pub fn detect_time_format(values: &[String]) -> Option<String> {
+    let values: Vec<&str> = values.iter()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && parse_offset_time(v).is_none())
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    if values.iter().all(|v| (9..=11).contains(&v.len()) && v.bytes().all(|b| b.is_ascii_digit())) {
+        return Some("%s".to_string());
+    }
+    let mut best: Option<(&str, usize)> = None;
+    for format in DETECT_TIME_FORMATS {
+        let parsed = values.iter()
+            .filter(|v| NaiveDateTime::parse_from_str(v.trim_end_matches('Z'), format).is_ok())
+            .count();
+        if parsed > 0 && best.is_none_or(|(_, most)| parsed > most) {
+            best = Some((format, parsed));
+        }
+    }
+    best.map(|(format, _)| format.to_string())
+}
+
+/// Reads the lines of a file that match a filter, up to a number of lines, so filtered
+/// files are streamed rather than held in memory
+struct FilteredLines<R> {
+    reader: R,
+    filter: Option<Regex>,
+    remaining: usize,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> Read for FilteredLines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.line.len() {
+            self.line.clear();
+            self.pos = 0;
+            if self.remaining == 0 || self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(0);
+            }
+            if self.line.last() == Some(&b'\n') {
+                self.line.pop();
+            }
+            if self.filter.as_ref().is_some_and(|re| !re.is_match(&String::from_utf8_lossy(&self.line))) {
+                self.line.clear();
+                continue;
+            }
+            self.line.push(b'\n');
+            self.remaining -= 1;
+        }
+        let n = buf.len().min(self.line.len() - self.pos);
+        buf[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn lossy(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).to_string()
+}
+
+fn read_jsonl(reader: Box<dyn Read>, mut each: impl FnMut(&SourceRow)) -> Result<()> {
+    for line in BufReader::new(reader).lines().map_while(|l| l.ok()) {
+        let Ok(serde_json::Value::Object(object)) = serde_json::from_str(&line) else { continue };
+        let (headers, values): (Vec<String>, Vec<String>) = object
+            .into_iter()
+            .map(|(k, v)| match v {
+                serde_json::Value::String(s) => (k, s),
+                serde_json::Value::Null => (k, String::new()),
+                other => (k, other.to_string()),
+            })
+            .unzip();
+        each(&SourceRow { headers: Rc::new(headers), values });
+    }
+    Ok(())
+}
+
+/// parse a timestamp with the strftime format, `%s` for unix seconds. Without a format,
+/// RFC 3339 and the formats used by most tools are tried. Times without an offset are UTC
+pub fn parse_time(value: &str, format: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if format == "%s" {
+        return common::unix_to_dt(value.parse().ok()?);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    if !format.is_empty() {
+        return NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), format).ok().map(|dt| dt.and_utc());
+    }
+    parse_offset_time(value).or_else(|| parse_naive_time(value).map(|dt| dt.and_utc()))
+}
+
+/// parse a time with an offset, as RFC 3339 or the offset formats of the tools
+fn parse_offset_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).ok()
+        .or_else(|| AUTO_OFFSET_FORMATS.iter().find_map(|f| DateTime::parse_from_str(value, f).ok()))
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// parse a time without an offset, in the unambiguous formats used by most tools
+fn parse_naive_time(value: &str) -> Option<NaiveDateTime> {
+    AUTO_TIME_FORMATS.iter().find_map(|f| NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), f).ok())
+}
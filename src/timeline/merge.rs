@@ -0,0 +1,132 @@
+/*
+Merge of the per-host timelines of a case. Whipped processes each data item into its
+own `<name>-Wiskess` folder, so the timeline.json of each host is merged into one case
+timeline, sorted by time with repeated events removed, and each event tagged with its
+host. A summary of each host's event counts and earliest and latest activity is written
+beside it.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use super::builder::{self, TIMELINE_FILE, UNKNOWN_HOST};
+use super::sorter::{LineSorter, CHUNK_EVENTS};
+use crate::parsers::common;
+
+pub const CASE_TIMELINE_FOLDER: &str = "Case-Timeline";
+pub const CASE_TIMELINE_FILE: &str = "case_timeline.json";
+pub const CASE_SUMMARY_FILE: &str = "case_hosts_summary.csv";
+/// The suffix of the wiskess output folders made by whipped, i.e. `WKS01-Wiskess_1`
+const WISKESS_SUFFIX: &str = "-Wiskess";
+
+/// The events of a host in the case timeline
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HostSummary {
+    pub host: String,
+    pub events: usize,
+    pub earliest: String,
+    pub latest: String,
+    pub sources: usize,
+}
+
+/// The result of merging the timelines
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    pub timelines: usize,
+    pub events: usize,
+    pub duplicates: usize,
+    pub hosts: Vec<HostSummary>,
+}
+
+impl MergeReport {
+    /// format the summary of the hosts as a table for the terminal
+    pub fn table(&self) -> String {
+        let width = self.hosts.iter().map(|h| h.host.len()).max().unwrap_or(0).max(4);
+        let mut table = format!("{:<width$}  {:>10}  {:<24}  {:<24}\n", "Host", "Events", "Earliest", "Latest");
+        for host in &self.hosts {
+            table.push_str(&format!("{:<width$}  {:>10}  {:<24}  {:<24}\n", host.host, host.events, host.earliest, host.latest));
+        }
+        table
+    }
+}
+
+/// find the timeline of each host's wiskess output in the folder, such as the local
+/// storage of whipped, or in a single wiskess output folder
+pub fn find_timelines(input: &Path) -> Vec<PathBuf> {
+    let mut timelines: Vec<PathBuf> = WalkDir::new(input)
+        .max_depth(3)
+        .into_iter()
+        .flatten()
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file() && p.file_name().is_some_and(|n| n == TIMELINE_FILE))
+        .filter(|p| p.parent().and_then(|p| p.file_name()).is_some_and(|n| n.eq_ignore_ascii_case("Timeline")))
+        .collect();
+    timelines.sort();
+    timelines
+}
+
+/// the host of a timeline from its wiskess output folder, for events of an unknown host
+fn folder_host(timeline_file: &Path) -> String {
+    timeline_file.parent()
+        .and_then(|p| p.parent())
+        .map(output_host)
+        .unwrap_or_default()
+}
+
+/// the host of a wiskess output folder from its name, i.e. `WKS01` of `WKS01-Wiskess_1`
+pub fn output_host(output: &Path) -> String {
+    let folder = output.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match folder.rfind(WISKESS_SUFFIX) {
+        Some(i) => folder[..i].to_string(),
+        None => folder,
+    }
+}
+
+/// merge the timelines into the case timeline in the out_dir, writing the summary of each
+/// host beside it
+pub fn merge(timelines: &[PathBuf], out_dir: &Path) -> Result<MergeReport> {
+    if timelines.is_empty() {
+        bail!("No timelines were found to merge, run the timeline reporter for each host first");
+    }
+    let mut sorter = LineSorter::new(CHUNK_EVENTS);
+    for timeline in timelines {
+        let host = folder_host(timeline);
+        builder::read_events(timeline, |mut event| {
+            if event.host.is_empty() || event.host == UNKNOWN_HOST {
+                event.host = host.clone();
+            }
+            sorter.push(serde_json::to_string(&event)?)
+        })?;
+    }
+    let pushed = sorter.count;
+    let case_timeline = out_dir.join(CASE_TIMELINE_FILE);
+    let events = sorter.finish_unique(&case_timeline)?;
+
+    // the merged timeline is sorted, so a host's first event is its earliest
+    let mut hosts: BTreeMap<String, (HostSummary, BTreeSet<String>)> = BTreeMap::new();
+    builder::read_events(&case_timeline, |event| {
+        let (summary, sources) = hosts.entry(event.host.clone()).or_insert_with(|| (
+            HostSummary { host: event.host.clone(), earliest: event.datetime.clone(), ..Default::default() },
+            BTreeSet::new(),
+        ));
+        summary.events += 1;
+        summary.latest = event.datetime;
+        sources.insert(event.source);
+        Ok(())
+    })?;
+    let hosts: Vec<HostSummary> = hosts.into_values()
+        .map(|(summary, sources)| HostSummary { sources: sources.len(), ..summary })
+        .collect();
+    let mut writer = common::csv_writer(&out_dir.join(CASE_SUMMARY_FILE))?;
+    for host in &hosts {
+        writer.serialize(host)?;
+    }
+    writer.flush()?;
+    Ok(MergeReport { timelines: timelines.len(), events, duplicates: pushed - events, hosts })
+}
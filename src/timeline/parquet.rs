@@ -0,0 +1,115 @@
+/*
+Parquet output of the timeline, partitioned by source and day in the hive layout of
+`source=<name>/day=<yyyy-mm-dd>/part-000.parquet`, so DuckDB and polars can read only
+the partitions of a query. As the timeline is sorted, each day is finished before the
+next starts and a single file is written for each partition.
+*/
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use anyhow::Result;
+use arrow_array::{ArrayRef, RecordBatch, StringArray, TimestampMillisecondArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+
+use super::builder::{self, TimelineEvent};
+use super::mapping;
+use super::split::file_name_safe;
+use crate::ops::parquet_ops::{self, BATCH_ROWS};
+
+pub const PARQUET_FOLDER: &str = "parquet";
+
+/// the schema of the timeline files, the source and day are in the partition's folders
+pub fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("datetime", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+        Field::new("timestamp_desc", DataType::Utf8, false),
+        Field::new("message", DataType::Utf8, false),
+        Field::new("host", DataType::Utf8, false),
+        Field::new("user", DataType::Utf8, true),
+        Field::new("path", DataType::Utf8, true),
+        Field::new("datetime_display", DataType::Utf8, true),
+    ]))
+}
+
+/// The file of a partition, with the events buffered for the next batch
+struct Partition {
+    writer: ArrowWriter<File>,
+    events: Vec<(i64, TimelineEvent)>,
+}
+
+impl Partition {
+    fn flush(&mut self, schema: &SchemaRef) -> Result<()> {
+        if self.events.is_empty() {
+            return Ok(());
+        }
+        let events = std::mem::take(&mut self.events);
+        let text = |f: fn(&TimelineEvent) -> &String| -> ArrayRef {
+            Arc::new(StringArray::from_iter_values(events.iter().map(|(_, e)| f(e))))
+        };
+        let optional = |f: fn(&TimelineEvent) -> &String| -> ArrayRef {
+            Arc::new(StringArray::from_iter(events.iter().map(|(_, e)| Some(f(e)).filter(|v| !v.is_empty()))))
+        };
+        let datetime = TimestampMillisecondArray::from_iter_values(events.iter().map(|(ms, _)| *ms)).with_timezone("UTC");
+        let arrays = vec![
+            Arc::new(datetime) as ArrayRef,
+            text(|e| &e.timestamp_desc),
+            text(|e| &e.message),
+            text(|e| &e.host),
+            optional(|e| &e.user),
+            optional(|e| &e.path),
+            optional(|e| &e.datetime_display),
+        ];
+        self.writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+        Ok(())
+    }
+}
+
+/// write the sorted timeline as Parquet to the parquet folder of the out_dir, returning
+/// the number of partitions written
+pub fn export(timeline_file: &Path, out_dir: &Path) -> Result<usize> {
+    let out_dir = out_dir.join(PARQUET_FOLDER);
+    if out_dir.is_dir() {
+        std::fs::remove_dir_all(&out_dir)?;
+    }
+    let schema = schema();
+    let mut day = String::new();
+    let mut partitions: BTreeMap<String, Partition> = BTreeMap::new();
+    let mut count = 0;
+    builder::read_events(timeline_file, |event| {
+        let Some(dt) = mapping::parse_time(&event.datetime, "") else { return Ok(()) };
+        let event_day = dt.format("%Y-%m-%d").to_string();
+        if event_day != day {
+            count += close(&mut partitions, &schema)?;
+            day = event_day;
+        }
+        let source = file_name_safe(&event.source);
+        let partition = match partitions.get_mut(&source) {
+            Some(partition) => partition,
+            None => {
+                let file = out_dir.join(format!("source={source}")).join(format!("day={day}")).join("part-000.parquet");
+                let partition = Partition { writer: parquet_ops::writer(&file, schema.clone())?, events: Vec::new() };
+                partitions.entry(source).or_insert(partition)
+            }
+        };
+        partition.events.push((dt.timestamp_millis(), event));
+        if partition.events.len() >= BATCH_ROWS {
+            partition.flush(&schema)?;
+        }
+        Ok(())
+    })?;
+    count += close(&mut partitions, &schema)?;
+    Ok(count)
+}
+
+/// flush and close the files of the partitions, returning how many were closed
+fn close(partitions: &mut BTreeMap<String, Partition>, schema: &SchemaRef) -> Result<usize> {
+    let count = partitions.len();
+    for (_, mut partition) in std::mem::take(partitions) {
+        partition.flush(schema)?;
+        partition.writer.close()?;
+    }
+    Ok(count)
+}
@@ -0,0 +1,90 @@
+/*
+Posts the timeline exports to Elasticsearch or Splunk, retrying when the server is
+busy or can't be reached. The credentials are read from environment variables so
+they aren't kept in the config or the wiskess log.
+*/
+
+use std::thread;
+use std::time::Duration;
+use anyhow::{bail, Result};
+
+use super::builder;
+
+/// The Authorization header for Elasticsearch, i.e. `ApiKey <key>` or `Basic <base64>`
+pub const ELASTIC_AUTH_ENV: &str = "WISKESS_ELASTIC_AUTH";
+/// The HEC token for Splunk
+pub const SPLUNK_TOKEN_ENV: &str = "WISKESS_SPLUNK_TOKEN";
+const DEFAULT_RETRIES: u32 = 3;
+/// The most retries of `--send-retries`, as the backoff doubles after each one
+pub const MAX_RETRIES: u32 = 10;
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(2);
+/// The longest wait between attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How the files are posted, with the backoff doubled after each failed attempt
+#[derive(Debug, Clone)]
+pub struct Sender {
+    pub retries: u32,
+    pub backoff: Duration,
+    /// the Authorization header, if one is needed
+    pub auth: Option<String>,
+}
+
+impl Sender {
+    /// make the sender with `--send-retries <n>` from the args and the auth header, with
+    /// up to `MAX_RETRIES` retries
+    pub fn from_args(args: &str, auth: Option<String>) -> Result<Self> {
+        let retries = match builder::arg_value(args, "--send-retries") {
+            Some(retries) => match retries.parse() {
+                Ok(retries) if retries <= MAX_RETRIES => retries,
+                Ok(_) => bail!("Invalid --send-retries: {retries}, the most is {MAX_RETRIES}"),
+                Err(_) => bail!("Invalid --send-retries: {retries}"),
+            },
+            None => DEFAULT_RETRIES,
+        };
+        Ok(Sender { retries, backoff: DEFAULT_BACKOFF, auth })
+    }
+
+    /// post the body to the url, returning the response body. Connection errors, 429 and
+    /// 5xx responses are retried, other errors are returned straight away
+    pub fn post(&self, url: &str, content_type: &str, body: &[u8]) -> Result<String> {
+        let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+        let mut attempt = 0;
+        loop {
+            let mut request = agent.post(url).set("Content-Type", content_type);
+            if let Some(auth) = &self.auth {
+                request = request.set("Authorization", auth);
+            }
+            let error = match request.send_bytes(body) {
+                Ok(response) => return Ok(response.into_string()?),
+                Err(ureq::Error::Status(code, response)) if code != 429 && code < 500 => {
+                    let text = response.into_string().unwrap_or_default();
+                    bail!("{url} responded {code}: {}", text.chars().take(500).collect::<String>());
+                }
+                Err(ureq::Error::Status(code, _)) => format!("{url} responded {code}"),
+                Err(e) => format!("{e}"),
+            };
+            if attempt >= self.retries {
+                bail!("Unable to post to {url} after {} attempts: {error}", attempt + 1);
+            }
+            thread::sleep(self.backoff_after(attempt));
+            attempt += 1;
+        }
+    }
+
+    /// the wait after a failed attempt, doubling each time up to `MAX_BACKOFF`
+    pub fn backoff_after(&self, attempt: u32) -> Duration {
+        let factor = 2u32.checked_pow(attempt).unwrap_or(u32::MAX);
+        self.backoff.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+}
+
+/// join the endpoint onto the url, unless the url already has it
+pub fn endpoint_url(url: &str, endpoint: &str) -> String {
+    let url = url.trim_end_matches('/');
+    match url.ends_with(endpoint) {
+        true => url.to_string(),
+        false => format!("{url}{endpoint}"),
+    }
+}
@@ -0,0 +1,119 @@
+/*
+External sort of timeline events, so a timeline can be larger than memory.
+Events are kept as JSON lines that start with the datetime, so sorting the lines
+sorts them by time. When a chunk is full it is sorted and spilled to a temporary
+file, and the chunks are merged into the output at the end.
+*/
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+use anyhow::Result;
+use tempfile::NamedTempFile;
+
+use crate::ops::file_ops;
+
+/// The number of events held in memory before they are spilled to a temporary file
+pub const CHUNK_EVENTS: usize = 500_000;
+
+/// Sorts JSON lines, spilling sorted chunks to temporary files
+pub struct LineSorter {
+    chunk: Vec<String>,
+    chunk_size: usize,
+    spills: Vec<NamedTempFile>,
+    pub count: usize,
+}
+
+impl LineSorter {
+    pub fn new(chunk_size: usize) -> Self {
+        LineSorter { chunk: Vec::new(), chunk_size: chunk_size.max(1), spills: Vec::new(), count: 0 }
+    }
+
+    pub fn push(&mut self, line: String) -> Result<()> {
+        self.chunk.push(line);
+        self.count += 1;
+        if self.chunk.len() >= self.chunk_size {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        self.chunk.sort_unstable();
+        let mut spill = NamedTempFile::new()?;
+        {
+            let mut writer = BufWriter::new(spill.as_file_mut());
+            for line in self.chunk.drain(..) {
+                writeln!(writer, "{line}")?;
+            }
+            writer.flush()?;
+        }
+        self.spills.push(spill);
+        Ok(())
+    }
+
+    /// write all the lines in order to the output file, returning how many were written
+    pub fn finish(self, out_file: &Path) -> Result<usize> {
+        let count = self.count;
+        self.write_sorted(out_file, |writer, line| writeln!(writer, "{line}"))?;
+        Ok(count)
+    }
+
+    /// write the lines in order to the output file without repeating identical lines,
+    /// returning how many were written
+    pub fn finish_unique(self, out_file: &Path) -> Result<usize> {
+        let mut last: Option<String> = None;
+        let mut written = 0;
+        self.write_sorted(out_file, |writer, line| {
+            if last.as_deref() == Some(line) {
+                return Ok(());
+            }
+            writeln!(writer, "{line}")?;
+            last = Some(line.to_string());
+            written += 1;
+            Ok(())
+        })?;
+        Ok(written)
+    }
+
+    fn write_sorted(
+        mut self,
+        out_file: &Path,
+        mut write: impl FnMut(&mut BufWriter<File>, &str) -> std::io::Result<()>,
+    ) -> Result<()> {
+        if let Some(parent) = out_file.parent() {
+            file_ops::make_folders(parent);
+        }
+        let mut writer = BufWriter::new(File::create(out_file)?);
+        if self.spills.is_empty() {
+            self.chunk.sort_unstable();
+            for line in &self.chunk {
+                write(&mut writer, line)?;
+            }
+        } else {
+            if !self.chunk.is_empty() {
+                self.spill()?;
+            }
+            let mut readers: Vec<Lines<BufReader<File>>> = self.spills
+                .iter()
+                .map(|s| s.reopen().map(|f| BufReader::new(f).lines()))
+                .collect::<std::io::Result<_>>()?;
+            let mut heap = BinaryHeap::new();
+            for (i, reader) in readers.iter_mut().enumerate() {
+                if let Some(line) = reader.next() {
+                    heap.push(Reverse((line?, i)));
+                }
+            }
+            while let Some(Reverse((line, i))) = heap.pop() {
+                write(&mut writer, &line)?;
+                if let Some(next) = readers[i].next() {
+                    heap.push(Reverse((next?, i)));
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+/*
+Writes the exports of the timeline to numbered files, starting a new file when the
+size limit is reached so each file can be uploaded or posted on its own.
+*/
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::ops::file_ops;
+
+/// An export file, with the number of events in it
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportFile {
+    pub file: String,
+    pub events: usize,
+    pub bytes: u64,
+}
+
+/// Writes the events to files named `{prefix}_001.{extension}` and so on
+pub struct SplitWriter {
+    out_dir: PathBuf,
+    prefix: String,
+    extension: String,
+    /// written at the start of each file, i.e. the CSV header
+    header: Vec<u8>,
+    max_bytes: u64,
+    writer: Option<BufWriter<File>>,
+    files: Vec<ExportFile>,
+}
+
+impl SplitWriter {
+    pub fn new(out_dir: PathBuf, prefix: &str, extension: &str, max_bytes: u64) -> Self {
+        file_ops::make_folders(&out_dir);
+        SplitWriter {
+            out_dir,
+            prefix: file_name_safe(prefix),
+            extension: extension.to_string(),
+            header: Vec::new(),
+            max_bytes,
+            writer: None,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn with_header(mut self, header: &[u8]) -> Self {
+        self.header = header.to_vec();
+        self
+    }
+
+    /// write an event, which is kept whole in a file even if it's over the size limit
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        let full = self.files.last().is_some_and(|f| f.events > 0 && f.bytes + data.len() as u64 > self.max_bytes);
+        if self.writer.is_none() || full {
+            self.next_file()?;
+        }
+        let (Some(writer), Some(file)) = (self.writer.as_mut(), self.files.last_mut()) else { unreachable!() };
+        writer.write_all(data)?;
+        file.events += 1;
+        file.bytes += data.len() as u64;
+        Ok(())
+    }
+
+    fn next_file(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        let name = format!("{}_{:03}.{}", self.prefix, self.files.len() + 1, self.extension);
+        let mut writer = BufWriter::new(File::create(self.out_dir.join(&name))?);
+        writer.write_all(&self.header)?;
+        self.writer = Some(writer);
+        self.files.push(ExportFile { file: name, events: 0, bytes: self.header.len() as u64 });
+        Ok(())
+    }
+
+    /// flush the last file, returning the files written
+    pub fn finish(mut self) -> Result<Vec<ExportFile>> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        Ok(self.files)
+    }
+}
+
+/// replace the characters that can't be in a file name
+pub fn file_name_safe(name: &str) -> String {
+    name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
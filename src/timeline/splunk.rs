@@ -0,0 +1,101 @@
+/*
+Splunk HTTP Event Collector (HEC) export of the timeline.
+Events are written in the HEC JSON event format, with the epoch time, host and source
+set on each, and split into files that are each posted as one batch of events.
+*/
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use super::builder::{self, TimelineEvent};
+use super::mapping;
+use super::sender::{self, Sender};
+use super::split::{ExportFile, SplitWriter};
+
+pub const SPLUNK_FOLDER: &str = "splunk";
+pub const DEFAULT_SOURCETYPE: &str = "wiskess:timeline";
+/// The default size limit of each file, in MB
+const DEFAULT_MAX_MB: u64 = 10;
+
+/// The Splunk export options, set in the timeline wisker's args
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplunkOptions {
+    /// the index of the events, otherwise the token's default index is used
+    pub index: Option<String>,
+    pub sourcetype: String,
+    /// the size limit of each file in bytes
+    pub max_bytes: u64,
+    /// the url of the HEC to post the files to
+    pub url: Option<String>,
+}
+
+impl SplunkOptions {
+    /// get the options from the args, returning None if the export isn't enabled. The export
+    /// is enabled by `--splunk` or `--splunk-url <url>`, with `--splunk-index <index>`,
+    /// `--splunk-sourcetype <sourcetype>` and `--splunk-max-mb <size>`
+    pub fn from_args(args: &str) -> Result<Option<Self>> {
+        let url = builder::arg_value(args, "--splunk-url");
+        if url.is_none() && !builder::split_args(args).iter().any(|w| w == "--splunk") {
+            return Ok(None);
+        }
+        let max_mb = match builder::arg_value(args, "--splunk-max-mb") {
+            Some(max_mb) => max_mb.parse().with_context(|| format!("Invalid --splunk-max-mb: {max_mb}"))?,
+            None => DEFAULT_MAX_MB,
+        };
+        Ok(Some(SplunkOptions {
+            index: builder::arg_value(args, "--splunk-index"),
+            sourcetype: builder::arg_value(args, "--splunk-sourcetype").unwrap_or_else(|| DEFAULT_SOURCETYPE.to_string()),
+            max_bytes: u64::max(max_mb, 1) * 1024 * 1024,
+            url,
+        }))
+    }
+}
+
+/// the event in the HEC format, returning None if the datetime isn't valid
+pub fn hec_event(event: &TimelineEvent, options: &SplunkOptions) -> Option<Value> {
+    let dt = mapping::parse_time(&event.datetime, "")?;
+    let mut hec = json!({
+        "time": dt.timestamp_millis() as f64 / 1000.0,
+        "host": event.host,
+        "source": format!("wiskess:{}", event.source),
+        "sourcetype": options.sourcetype,
+        "event": {
+            "datetime": event.datetime,
+            "timestamp_desc": event.timestamp_desc,
+            "message": event.message,
+            "source": event.source,
+            "user": event.user,
+            "path": event.path,
+        },
+    });
+    if let Some(index) = &options.index {
+        hec["index"] = json!(index);
+    }
+    Some(hec)
+}
+
+/// export the timeline as HEC event files to the splunk folder of the out_dir
+pub fn export(timeline_file: &Path, out_dir: &Path, host: &str, options: &SplunkOptions) -> Result<Vec<ExportFile>> {
+    let mut writer = SplitWriter::new(out_dir.join(SPLUNK_FOLDER), &format!("{host}_hec"), "json", options.max_bytes);
+    builder::read_events(timeline_file, |event| {
+        let Some(hec) = hec_event(&event, options) else { return Ok(()) };
+        let mut data = serde_json::to_vec(&hec)?;
+        data.push(b'\n');
+        writer.write(&data)
+    })?;
+    writer.finish()
+}
+
+/// post the HEC files to the event endpoint of the url, returning the number of events sent
+pub fn send(out_dir: &Path, files: &[ExportFile], url: &str, sender: &Sender) -> Result<usize> {
+    let url = sender::endpoint_url(url, "/services/collector/event");
+    let mut events = 0;
+    for file in files {
+        let body = std::fs::read(out_dir.join(SPLUNK_FOLDER).join(&file.file))?;
+        sender.post(&url, "application/json", &body)
+            .with_context(|| format!("Unable to send {}", file.file))?;
+        events += file.events;
+    }
+    Ok(events)
+}
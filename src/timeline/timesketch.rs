@@ -0,0 +1,228 @@
+/*
+Timesketch export of the timeline.
+Each event is written with the fields Timesketch requires, message, datetime and
+timestamp_desc, to JSONL or CSV files that are split under a size limit for upload.
+A summary of the event counts per source and per day is written beside them, and an
+import manifest when the sketch is named.
+*/
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use super::builder::{self, TimelineEvent};
+use super::mapping;
+use super::split::{ExportFile, SplitWriter};
+
+pub const TIMESKETCH_FOLDER: &str = "timesketch";
+pub const SUMMARY_FILE: &str = "timesketch_summary.json";
+pub const MANIFEST_FILE: &str = "timesketch_manifest.json";
+/// The default size limit of each file, in MB
+const DEFAULT_MAX_MB: u64 = 200;
+const CSV_HEADER: [&str; 8] = ["message", "datetime", "timestamp_desc", "timestamp", "source", "host", "user", "path"];
+
+/// The format of the export files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// The Timesketch export options, set in the timeline wisker's args
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimesketchOptions {
+    pub format: ExportFormat,
+    /// the size limit of each file in bytes
+    pub max_bytes: u64,
+    pub sketch_name: Option<String>,
+}
+
+impl Default for TimesketchOptions {
+    fn default() -> Self {
+        TimesketchOptions { format: ExportFormat::Jsonl, max_bytes: DEFAULT_MAX_MB * 1024 * 1024, sketch_name: None }
+    }
+}
+
+impl TimesketchOptions {
+    /// get the options from the args, returning None if the export isn't enabled. The export
+    /// is enabled by `--timesketch` or `--timesketch-sketch-name <name>`, with
+    /// `--timesketch-format <jsonl|csv>` and `--timesketch-max-mb <size>` to change the files
+    pub fn from_args(args: &str) -> Result<Option<Self>> {
+        let sketch_name = builder::arg_value(args, "--timesketch-sketch-name");
+        if sketch_name.is_none() && !builder::split_args(args).iter().any(|w| w == "--timesketch") {
+            return Ok(None);
+        }
+        let mut options = TimesketchOptions { sketch_name, ..Default::default() };
+        if let Some(format) = builder::arg_value(args, "--timesketch-format") {
+            options.format = match format.to_lowercase().as_str() {
+                "jsonl" | "json" => ExportFormat::Jsonl,
+                "csv" => ExportFormat::Csv,
+                other => bail!("Unknown Timesketch format: {other}, use jsonl or csv"),
+            };
+        }
+        if let Some(max_mb) = builder::arg_value(args, "--timesketch-max-mb") {
+            let max_mb: u64 = max_mb.parse().with_context(|| format!("Invalid --timesketch-max-mb: {max_mb}"))?;
+            options.max_bytes = max_mb.max(1) * 1024 * 1024;
+        }
+        Ok(Some(options))
+    }
+}
+
+/// An event as imported by Timesketch, with the timestamp in microseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct TimesketchEvent {
+    pub message: String,
+    pub datetime: String,
+    pub timestamp_desc: String,
+    pub timestamp: i64,
+    pub source: String,
+    pub host: String,
+    pub user: String,
+    pub path: String,
+}
+
+impl TimesketchEvent {
+    /// make the Timesketch event, returning None if the datetime isn't valid. An empty
+    /// message is filled from the source and path, as Timesketch requires one
+    pub fn from_event(event: TimelineEvent) -> Option<Self> {
+        let dt = mapping::parse_time(&event.datetime, "")?;
+        let message = match event.message.trim().is_empty() {
+            true => [event.source.as_str(), event.path.as_str()]
+                .iter()
+                .filter(|v| !v.is_empty())
+                .copied()
+                .collect::<Vec<_>>()
+                .join(": "),
+            false => event.message,
+        };
+        let timestamp_desc = match event.timestamp_desc.trim().is_empty() {
+            true => event.source.clone(),
+            false => event.timestamp_desc,
+        };
+        Some(TimesketchEvent {
+            message,
+            datetime: event.datetime,
+            timestamp_desc,
+            timestamp: dt.timestamp_micros(),
+            source: event.source,
+            host: event.host,
+            user: event.user,
+            path: event.path,
+        })
+    }
+
+    fn to_csv(&self) -> Result<Vec<u8>> {
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+        writer.write_record([
+            self.message.as_str(), &self.datetime, &self.timestamp_desc, &self.timestamp.to_string(),
+            &self.source, &self.host, &self.user, &self.path,
+        ])?;
+        Ok(writer.into_inner()?)
+    }
+}
+
+/// The summary of the export, written beside the files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportSummary {
+    pub host: String,
+    pub events: usize,
+    /// events skipped as their datetime wasn't valid
+    pub skipped: usize,
+    pub first: String,
+    pub last: String,
+    pub files: Vec<ExportFile>,
+    pub per_source: BTreeMap<String, usize>,
+    pub per_day: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ManifestTimeline {
+    timeline_name: String,
+    file: String,
+    events: usize,
+    command: String,
+}
+
+/// The import manifest, with a timeline for each file and the command to upload it
+#[derive(Debug, Clone, Serialize)]
+struct ImportManifest {
+    sketch_name: String,
+    host: String,
+    format: String,
+    timelines: Vec<ManifestTimeline>,
+}
+
+/// export the sorted timeline to the timesketch folder of the out_dir, returning the summary
+pub fn export(timeline_file: &Path, out_dir: &Path, host: &str, options: &TimesketchOptions) -> Result<ExportSummary> {
+    let out_dir = out_dir.join(TIMESKETCH_FOLDER);
+    let mut writer = SplitWriter::new(out_dir.clone(), &format!("{host}_timeline"), options.format.extension(), options.max_bytes);
+    if options.format == ExportFormat::Csv {
+        writer = writer.with_header(format!("{}\n", CSV_HEADER.join(",")).as_bytes());
+    }
+    let mut summary = ExportSummary { host: host.to_string(), ..Default::default() };
+    let mut invalid = 0;
+    let skipped = builder::read_events(timeline_file, |event| {
+        let Some(event) = TimesketchEvent::from_event(event) else {
+            invalid += 1;
+            return Ok(());
+        };
+        let data = match options.format {
+            ExportFormat::Jsonl => {
+                let mut data = serde_json::to_vec(&event)?;
+                data.push(b'\n');
+                data
+            }
+            ExportFormat::Csv => event.to_csv()?,
+        };
+        writer.write(&data)?;
+        summary.events += 1;
+        *summary.per_source.entry(event.source.clone()).or_default() += 1;
+        *summary.per_day.entry(event.datetime.get(..10).unwrap_or_default().to_string()).or_default() += 1;
+        if summary.first.is_empty() {
+            summary.first = event.datetime.clone();
+        }
+        summary.last = event.datetime;
+        Ok(())
+    })?;
+    summary.skipped = skipped + invalid;
+    summary.files = writer.finish()?;
+    serde_json::to_writer_pretty(File::create(out_dir.join(SUMMARY_FILE))?, &summary)?;
+    if let Some(sketch_name) = &options.sketch_name {
+        write_manifest(&out_dir, sketch_name, options, &summary)?;
+    }
+    Ok(summary)
+}
+
+/// write the import manifest, with a timesketch_importer command for each file
+fn write_manifest(out_dir: &Path, sketch_name: &str, options: &TimesketchOptions, summary: &ExportSummary) -> Result<()> {
+    let timelines = summary.files.iter().map(|f| {
+        let timeline_name = f.file.rsplit_once('.').map(|(n, _)| n).unwrap_or(&f.file).to_string();
+        ManifestTimeline {
+            command: format!(
+                "timesketch_importer --sketch_name \"{sketch_name}\" --timeline_name \"{timeline_name}\" \"{}\"", f.file
+            ),
+            timeline_name,
+            file: f.file.clone(),
+            events: f.events,
+        }
+    }).collect();
+    let manifest = ImportManifest {
+        sketch_name: sketch_name.to_string(),
+        host: summary.host.clone(),
+        format: options.format.extension().to_string(),
+        timelines,
+    };
+    serde_json::to_writer_pretty(File::create(out_dir.join(MANIFEST_FILE))?, &manifest)?;
+    Ok(())
+}
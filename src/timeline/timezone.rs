@@ -0,0 +1,163 @@
+/*
+Timezones of the timeline. The system's configured timezone is read from the SYSTEM
+hive's TimeZoneInformation key, with its daylight saving rules, so sources written in
+the host's local time can be converted to UTC as Windows itself would. The display
+timezone renders a second column of each event in the analyst's chosen zone.
+*/
+
+use std::str::FromStr;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, Utc, Weekday};
+use serde::Serialize;
+
+use crate::parsers::common::le_u16;
+use crate::parsers::hive::{self, Hive};
+
+/// When daylight saving starts or ends, from the SYSTEMTIME of the TimeZoneInformation
+/// key. The week is the occurrence of the day of the week in the month, 5 being the last
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Transition {
+    pub month: u32,
+    pub day_of_week: u32,
+    pub week: u32,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Transition {
+    /// read the transition from a SYSTEMTIME, returning None when it isn't set
+    pub fn from_systemtime(data: &[u8]) -> Option<Self> {
+        let field = |i: usize| le_u16(data, i * 2).map(u32::from);
+        let transition = Transition {
+            month: field(1)?,
+            day_of_week: field(2)?,
+            week: field(3)?,
+            hour: field(4)?,
+            minute: field(5)?,
+        };
+        (1..=12).contains(&transition.month).then_some(transition)
+    }
+
+    /// the local time of the transition in the year
+    fn in_year(&self, year: i32) -> Option<NaiveDateTime> {
+        let weekday = Weekday::try_from(((self.day_of_week + 6) % 7) as u8).ok()?;
+        // the fifth week is the last, which some months don't have
+        let day = NaiveDate::from_weekday_of_month_opt(year, self.month, weekday, self.week.clamp(1, 5) as u8)
+            .or_else(|| NaiveDate::from_weekday_of_month_opt(year, self.month, weekday, 4))?;
+        day.and_hms_opt(self.hour, self.minute, 0)
+    }
+}
+
+/// The timezone configured on the host, with the biases in minutes as Windows stores them,
+/// so UTC = local time + bias
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SystemTimezone {
+    pub name: String,
+    pub bias: i32,
+    pub standard_bias: i32,
+    pub daylight_bias: i32,
+    pub standard_start: Option<Transition>,
+    pub daylight_start: Option<Transition>,
+}
+
+impl SystemTimezone {
+    /// read the timezone from the current control set of the SYSTEM hive
+    pub fn from_system(system: &Hive) -> Option<Self> {
+        let key = system.key(&format!(r"{}\Control\TimeZoneInformation", hive::current_control_set(system)))?;
+        let bias = |name: &str| key.value(name).and_then(|v| v.as_u32()).map(|b| b as i32).unwrap_or(0);
+        let transition = |name: &str| key.value(name).and_then(|v| Transition::from_systemtime(&v.data));
+        let dynamic_disabled = key.value("DynamicDaylightTimeDisabled").and_then(|v| v.as_u32()) == Some(1);
+        let name = key.value("TimeZoneKeyName")
+            .or_else(|| key.value("StandardName"))
+            .map(|v| v.as_string().trim().to_string())
+            .unwrap_or_default();
+        Some(SystemTimezone {
+            name,
+            bias: bias("Bias"),
+            standard_bias: bias("StandardBias"),
+            daylight_bias: bias("DaylightBias"),
+            standard_start: transition("StandardStart").filter(|_| !dynamic_disabled),
+            daylight_start: transition("DaylightStart").filter(|_| !dynamic_disabled),
+        })
+    }
+
+    /// whether daylight saving applies to the local time
+    fn is_daylight(&self, local: &NaiveDateTime) -> bool {
+        let (Some(daylight), Some(standard)) = (self.daylight_start, self.standard_start) else { return false };
+        let (Some(starts), Some(ends)) = (daylight.in_year(local.year()), standard.in_year(local.year())) else { return false };
+        match starts < ends {
+            true => *local >= starts && *local < ends,
+            // in the southern hemisphere daylight saving spans the new year
+            false => *local >= starts || *local < ends,
+        }
+    }
+
+    /// the bias in minutes of the local time, with daylight saving when it applies
+    fn bias_at(&self, local: &NaiveDateTime) -> i32 {
+        match self.is_daylight(local) {
+            true => self.bias + self.daylight_bias,
+            false => self.bias + self.standard_bias,
+        }
+    }
+
+    /// convert a local time of the host to UTC
+    pub fn to_utc(&self, local: &NaiveDateTime) -> DateTime<Utc> {
+        (*local + Duration::minutes(self.bias_at(local) as i64)).and_utc()
+    }
+
+    /// the offset from UTC of the host at the UTC time
+    pub fn offset_at(&self, dt: &DateTime<Utc>) -> FixedOffset {
+        let standard = dt.naive_utc() - Duration::minutes((self.bias + self.standard_bias) as i64);
+        let bias = self.bias_at(&standard);
+        FixedOffset::west_opt(bias * 60).unwrap_or(FixedOffset::east_opt(0).expect("UTC is a valid offset"))
+    }
+
+    /// describe the timezone with its standard offset, i.e. `GMT Standard Time (UTC+00:00)`
+    pub fn describe(&self) -> String {
+        let minutes = -(self.bias + self.standard_bias);
+        let sign = if minutes < 0 { '-' } else { '+' };
+        format!("{} (UTC{sign}{:02}:{:02})", self.name, minutes.abs() / 60, minutes.abs() % 60)
+    }
+}
+
+/// The timezone the analyst chose to display the events in, with `--display-timezone`
+#[derive(Debug, Clone)]
+pub enum DisplayTimezone {
+    Fixed(FixedOffset),
+    Named(chrono_tz::Tz),
+    System(SystemTimezone),
+}
+
+impl DisplayTimezone {
+    /// read the zone as `system` for the host's timezone, an offset such as `+05:30` or
+    /// `UTC-05:00`, or an IANA name such as `Europe/London`
+    pub fn parse(zone: &str, system: Option<&SystemTimezone>) -> Result<Self> {
+        let zone = zone.trim();
+        if zone.eq_ignore_ascii_case("system") || zone.eq_ignore_ascii_case("local") {
+            return match system {
+                Some(system) => Ok(DisplayTimezone::System(system.clone())),
+                None => bail!("The display timezone is the system's, but the SYSTEM hive's timezone wasn't found"),
+            };
+        }
+        let offset = zone.strip_prefix("UTC").or_else(|| zone.strip_prefix("GMT")).unwrap_or(zone);
+        if offset.is_empty() {
+            return Ok(DisplayTimezone::Named(chrono_tz::UTC));
+        }
+        if let Ok(offset) = FixedOffset::from_str(offset) {
+            return Ok(DisplayTimezone::Fixed(offset));
+        }
+        match chrono_tz::Tz::from_str(zone) {
+            Ok(tz) => Ok(DisplayTimezone::Named(tz)),
+            Err(_) => bail!("Unknown display timezone {zone}, use an IANA name such as Europe/London, an offset such as +05:30 or system"),
+        }
+    }
+
+    /// format the time in the zone, as ISO 8601 with its offset
+    pub fn format(&self, dt: &DateTime<Utc>) -> String {
+        match self {
+            DisplayTimezone::Fixed(offset) => dt.with_timezone(offset).to_rfc3339_opts(SecondsFormat::Millis, false),
+            DisplayTimezone::Named(tz) => dt.with_timezone(tz).to_rfc3339_opts(SecondsFormat::Millis, false),
+            DisplayTimezone::System(system) => dt.with_timezone(&system.offset_at(dt)).to_rfc3339_opts(SecondsFormat::Millis, false),
+        }
+    }
+}
@@ -193,6 +193,9 @@ pub mod web {
             ioc_file: ioc_filename,
             silent,
             collect: true,
+            out_format: Default::default(),
+            yara_rules: String::new(),
+            keep_timeline: false,
             out_log: PathBuf::new(),
             multi_pb: MultiProgress::new()
         };
@@ -260,6 +263,10 @@ pub mod web {
             out_link: params.out_link.to_string(),
             update,
             keep_evidence,
+            out_format: Default::default(),
+            yara_rules: String::new(),
+            merge_timelines: false,
+            ioc_report: false,
         };
 
         let items = struct_to_vec_whip(&args);
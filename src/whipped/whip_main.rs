@@ -1,8 +1,12 @@
 use crate::configs::config::{self, MainArgs, WhippedArgs};
 use crate::init::scripts;
+use crate::iocs::report;
 use crate::ops::exe_ops::{run_wisker, run_posh};
 use crate::ops::file_ops::make_folders;
 use crate::ops::{file_ops, wiskess};
+use crate::ops::source_ops::{self, DataSourceKind};
+pub use crate::ops::source_ops::{extract_drive_letter, find_os_drive_letters, has_windows_child};
+use crate::timeline::merge;
 
 use super::whip_s3;
 use super::whip_az;
@@ -100,7 +104,15 @@ fn pre_process_data(file_path: &Path, log_name: &Path, data_folder: &PathBuf) ->
     
     // get the type of data downloaded, i.e. image, folder or archive
     let mut process_vector: Vec<PathBuf> = Vec::new();
-    if file_path.is_dir() {
+    let detection = source_ops::detect(file_path);
+    print_log(
+        format!("[ ] {} found at {}, having {}", detection.kind, detection.root.display(), detection.evidence).as_str(),
+        log_name,
+        true
+    );
+    if detection.archived {
+        pre_process_zip(&file_path.to_path_buf(), data_folder, log_name, &mut process_vector);
+    } else if file_path.is_dir() && detection.kind == DataSourceKind::Unknown {
         // if folder, loop through the folder at one level down, adding archives and images to process_vector
         let entries: Vec<PathBuf> = WalkDir::new(file_path)
             .min_depth(1)
@@ -112,21 +124,17 @@ fn pre_process_data(file_path: &Path, log_name: &Path, data_folder: &PathBuf) ->
             .collect();
     
         entries.iter().for_each(|entry| {
-            if let Some(ext) = entry.extension() {
-                match ext.to_str().unwrap_or("") {
-                    "vmdk"|"vhdx"|"vhd"|"e01"|"vdi"|"ex01"|"raw" => {
-                        process_vector.push(entry.to_path_buf())
-                    },
-                    "zip"|"7z" => {
-                        pre_process_zip(entry, data_folder, log_name, &mut process_vector);
-                    }
-                    _ => print_log("[ ] File in folder is not a valid artefact type", log_name, true)
-                }
+            let detection = source_ops::detect(entry);
+            if detection.kind == DataSourceKind::DiskImage {
+                process_vector.push(entry.to_path_buf())
+            } else if detection.archived {
+                pre_process_zip(entry, data_folder, log_name, &mut process_vector);
+            } else {
+                print_log("[ ] File in folder is not a valid artefact type", log_name, true)
             }
         });
-    } else if file_path.extension().unwrap() == "zip" || file_path.extension().unwrap() == "7z" {
-        pre_process_zip(&file_path.to_path_buf(), data_folder, log_name, &mut process_vector);
     } else {
+        // an image, or a folder of a volume or collection that wiskess processes from its root
         process_vector.push(file_path.to_path_buf());
     }
     Ok(process_vector)
@@ -173,153 +181,103 @@ fn move_files_para(files_entries: &[std::path::PathBuf], files_dir: &std::path::
     }
 }
 
-/// Extracts the drive letter (uppercased) from a Velociraptor percent-encoded
-/// path component, matching both forms Velociraptor produces: `<LETTER>%3A`
-/// (e.g. `F%3A` for `F:`) and `%5C%5C.%5C<LETTER>%3A` (e.g. `%5C%5C.%5CF%3A`
-/// for `\\.\F:`). Case-insensitive. Returns `None` if it matches neither.
-pub fn extract_drive_letter(component_str: &str) -> Option<char> {
-    let re = regex::Regex::new(r"(?i)^(?:%5C%5C\.%5C)?([A-Za-z])%3A$").unwrap();
-    re.captures(component_str)
-        .and_then(|caps| caps.get(1))
-        .and_then(|m| m.as_str().chars().next())
-        .map(|c| c.to_ascii_uppercase())
-}
-
-/// Returns true if `dir` has an immediate child directory named `Windows`
-/// (case-insensitive), so this works the same on Windows and Linux.
-pub fn has_windows_child(dir: &Path) -> bool {
-    WalkDir::new(dir)
-        .min_depth(1)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .any(|entry| {
-            entry.file_type().is_dir()
-                && entry.file_name().to_str()
-                    .map_or(false, |name| name.eq_ignore_ascii_case("windows"))
-        })
-}
-
-/// Walks a Velociraptor `uploads` folder's `auto`/`ntfs` subtrees at depth 2
-/// (i.e. `uploads/<auto|ntfs>/<drive-component>`) and returns the distinct,
-/// sorted drive letters that have an immediate `Windows` child - i.e. are
-/// confirmed to be the OS drive. Either encoded form of the same letter, in
-/// either subtree, confirms that letter.
-pub fn find_os_drive_letters(uploads_dir: &Path) -> Vec<char> {
-    let mut confirmed: Vec<char> = WalkDir::new(uploads_dir)
-        .min_depth(2)
-        .max_depth(2)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|entry| entry.file_type().is_dir())
-        .filter_map(|entry| {
-            let comp_str = entry.file_name().to_str()?;
-            let letter = extract_drive_letter(comp_str)?;
-            has_windows_child(entry.path()).then_some(letter)
-        })
-        .collect();
-    confirmed.sort_unstable();
-    confirmed.dedup();
-    confirmed
-}
-
-/// pre-process an achive of type zip or 7z, extract using 7zip then add artefacts to a vector. If the contents
-/// has a folder in the root called uploads we treat it as a velociraptor collection and move the data into one folder.
+/// pre-process an archive of type zip, 7z or tar, extract it using 7zip or tar then add the data to process to a vector.
+/// The kind of data extracted is detected, and if it is a velociraptor collection the data is moved into one folder.
 /// # Arguments
 /// `data_file` - the archive file path
-/// `data_folder` - the folder where the archive is stored
+/// `data_folder` - the folder where the archive is extracted to
 /// `log_name` - the name of the log file
 /// `process_vector` - the vector of files that need processing, is updated in this function
 fn pre_process_zip(data_file: &PathBuf, data_folder: &PathBuf, log_name: &Path, process_vector: &mut Vec<PathBuf>) {
     // if data is an archive, extract it to the extracted folder 
     let data_str = format!("'{}'", data_file.clone().display());
-    let extract_flag = format!("-o'{}'", data_folder.display());
-    let unzip_cmd = ["x", "-aos", data_str.as_str(), extract_flag.as_str()].to_vec();
-                        
-    let bin_path = Path::new("7z.exe").to_path_buf();
+    let folder_str = format!("'{}'", data_folder.display());
+    let extract_flag = format!("-o{folder_str}");
+    let (bin_path, unzip_cmd) = match source_ops::is_tarball(data_file) {
+        true => {
+            // tar needs the folder to extract to, and reads gzip compressed tarballs as they are
+            make_folders(data_folder);
+            (Path::new("tar").to_path_buf(), ["-xf", data_str.as_str(), "-C", folder_str.as_str()].to_vec())
+        },
+        false => (Path::new("7z.exe").to_path_buf(), ["x", "-aos", data_str.as_str(), extract_flag.as_str()].to_vec()),
+    };
     print_log(format!("Extracting the archive {data_str}...").as_str(), log_name, true);
     let _json_data = run_cmd(bin_path, unzip_cmd, log_name, true).unwrap();
     // TODO: check for archives at one level deep, adding paths to the process_vector
-    // loop through the extracted archive at one level down, adding relevant data to process_vector
-    let entries: Vec<PathBuf> = WalkDir::new(data_folder)
+    // loop through the extracted archive at one level down, adding disk images to process_vector
+    WalkDir::new(data_folder)
         .min_depth(1)
         .max_depth(1)
         .into_iter()
         .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && source_ops::detect(e.path()).kind == DataSourceKind::DiskImage)
+        .for_each(|e| process_vector.push(e.into_path()));
+
+    // detect the collection or volume extracted, adding the folder of its evidence to process_vector
+    let detection = source_ops::detect(data_folder);
+    print_log(
+        format!("[ ] {} extracted to {}, having {}", detection.kind, detection.root.display(), detection.evidence).as_str(),
+        log_name,
+        true
+    );
+    match detection.kind {
+        DataSourceKind::Velociraptor => {
+            let files_dir = move_velociraptor_files(&data_folder.join(source_ops::VELOCIRAPTOR_UPLOADS), log_name);
+            process_vector.push(files_dir)
+        },
+        DataSourceKind::Unknown | DataSourceKind::Archive | DataSourceKind::DiskImage => (),
+        _ => process_vector.push(detection.root),
+    }
+}
+
+/// move the files of a velociraptor collection, found in the folders `auto` and `ntfs` of the drives
+/// having a Windows folder, into the folder `files` of `uploads`, returning the folder `files`
+/// # Arguments
+/// `uploads` - the folder `uploads` of the collection
+/// `log_name` - the name of the log file
+fn move_velociraptor_files(uploads: &Path, log_name: &Path) -> PathBuf {
+    let files_dir = uploads.join("files");
+    if files_dir.is_dir() {
+        print_log("[ ] files folder already created, so not moving over files", log_name, true);
+        return files_dir
+    }
+    // find which drive letter(s) actually contain a Windows folder,
+    // rather than assuming the OS drive is always C
+    let confirmed_drives = find_os_drive_letters(uploads);
+    let no_drive_confirmed = confirmed_drives.is_empty();
+    if no_drive_confirmed {
+        print_log(
+            "[!] No drive with a Windows folder was found in the Velociraptor collection; processing all drives found.",
+            log_name,
+            true
+        );
+    }
+    let files_entries: Vec<PathBuf> = WalkDir::new(uploads)
+        .min_depth(3)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| {
+            no_drive_confirmed || entry.path().components().any(|component| {
+                component.as_os_str().to_str()
+                    .and_then(extract_drive_letter)
+                    .map_or(false, |letter| confirmed_drives.contains(&letter))
+            })
+        })
         .map(|e| e.into_path())
         .collect();
-
-    entries.iter().for_each(|data_file| {
-        if data_file.is_file() {
-            if let Some(ext) = data_file.extension() {
-                match ext.to_str().unwrap_or("") {
-                    "vmdk"|"vhdx"|"vhd"|"e01"|"vdi"|"ex01"|"raw" => {
-                        process_vector.push(data_file.to_path_buf())
-                    },
-                    _ => ()
-                }
-            }
-        } else if data_file.is_dir() {
-            if let Some(file_path) = data_file.file_name() {
-                match file_path.to_str().unwrap_or("") {
-                    "uploads" => {
-                        // this is a velociraptor collection, find data in folders `auto` and `ntfs`
-                        let files_dir = data_file.join("files");
-                        if files_dir.is_dir() {
-                            print_log("[ ] files folder already created, so not moving over files", log_name, true);
-                            // add `files` folder to process_vector
-                            return process_vector.push(files_dir)
-                        }
-                        // find which drive letter(s) actually contain a Windows folder,
-                        // rather than assuming the OS drive is always C
-                        let confirmed_drives = find_os_drive_letters(data_file);
-                        let no_drive_confirmed = confirmed_drives.is_empty();
-                        if no_drive_confirmed {
-                            print_log(
-                                "[!] No drive with a Windows folder was found in the Velociraptor collection; processing all drives found.",
-                                log_name,
-                                true
-                            );
-                        }
-                        let files_entries: Vec<PathBuf> = WalkDir::new(data_file)
-                            .min_depth(3)
-                            .max_depth(3)
-                            .into_iter()
-                            .filter_map(|e| e.ok())
-                            .filter(|entry| {
-                                no_drive_confirmed || entry.path().components().any(|component| {
-                                    component.as_os_str().to_str()
-                                        .and_then(extract_drive_letter)
-                                        .map_or(false, |letter| confirmed_drives.contains(&letter))
-                                })
-                            })
-                            .map(|e| e.into_path())
-                            .collect();
-                        // create folder `files`
-                        _ = create(&files_dir, false);
-                        // move the data into `files`
-                        match move_files_para(&files_entries, &files_dir, &log_name) {
-                            Ok(()) => print_log("[ ] All files moved.", log_name, true),
-                            Err(error) => print_log(
-                                    format!("[!] Errors occurred during file moving: {}", error).as_str(),
-                                    log_name,
-                                    true
-                                )
-                        }
-                        // add `files` folder to process_vector
-                        process_vector.push(files_dir)
-                    },
-                    _ => {
-                        // Cylr-like collection: any top-level folder that itself contains
-                        // an immediate Windows child is the OS drive root, whatever it's named
-                        if has_windows_child(data_file) {
-                            process_vector.push(data_file.to_path_buf())
-                        }
-                    }
-                }
-            }
-        }
-    });
+    // create folder `files`
+    _ = create(&files_dir, false);
+    // move the data into `files`
+    match move_files_para(&files_entries, &files_dir, &log_name) {
+        Ok(()) => print_log("[ ] All files moved.", log_name, true),
+        Err(error) => print_log(
+                format!("[!] Errors occurred during file moving: {}", error).as_str(),
+                log_name,
+                true
+            )
+    }
+    files_dir
 }
 
 /// run_cmd runs a binary with a command, in windows it uses powershell, 
@@ -567,13 +525,13 @@ fn process_data(data_source: &PathBuf, log_name: &Path, args: MainArgs, config:
     let config = file_ops::check_path(config);
     let artefacts_config = file_ops::check_path(artefacts_config);
 
-    match data_source.extension().unwrap_or_default().to_str().unwrap_or("") {
-        "vmdk"|"vhdx"|"vhd"|"e01"|"vdi"|"ex01"|"raw" => {
-            // if extension or file type is image, send to process_image
+    match source_ops::detect(data_source).kind {
+        DataSourceKind::DiskImage => {
+            // if the signature or extension is of an image, send to process_image
             process_image(data_source, &log_name, args, config, artefacts_config);
         },
-        "" => {
-            // if there's no extension, it is likely a collection of files, send to process_collection
+        _ if data_source.is_dir() => {
+            // if it is a folder, it is a collection of files, send to start_wiskess to detect its root and profile
             print_log(
                 format!("[ ] Running wiskess for collection path: {}, to output folder: {}", data_source.display(), args.out_path).as_str(),
                 log_name,
@@ -722,6 +680,9 @@ pub async fn whip_main(args: WhippedArgs, tool_path: &PathBuf) -> Result<()> {
                 ioc_file: args.ioc_file.clone(),
                 silent: true,
                 collect: false,
+                out_format: args.out_format,
+                yara_rules: args.yara_rules.clone(),
+                keep_timeline: args.merge_timelines || args.ioc_report,
                 out_log: PathBuf::new(),
                 multi_pb: MultiProgress::new()
             };
@@ -759,5 +720,72 @@ pub async fn whip_main(args: WhippedArgs, tool_path: &PathBuf) -> Result<()> {
         // assert_eq!(is_processed, false);
     }
 
+    if args.merge_timelines {
+        merge_case_timelines(Path::new(&args.local_storage), &args.out_link, tool_path, log_name).await;
+    }
+    if args.ioc_report {
+        report_case_iocs(Path::new(&args.local_storage), &args.out_link, tool_path, log_name).await;
+    }
+    // the hosts' timelines were kept for the case steps, remove those exported to Parquet
+    if args.out_format == config::OutFormat::Parquet && (args.merge_timelines || args.ioc_report) {
+        for timeline in merge::find_timelines(Path::new(&args.local_storage)) {
+            if let Some(host_folder) = timeline.parent().and_then(|p| p.parent()) {
+                wiskess::remove_timeline(host_folder, log_name);
+            }
+        }
+    }
+
     Ok(())
 }
+
+/// Merge the timelines of the hosts processed to the local storage into one case timeline,
+/// then upload it beside the hosts' wiskess output
+/// # Arguments
+/// * `local_storage` - the folder where the wiskess output of each host is stored locally
+/// * `out_link` - the URL to the S3 bucket or Azure Blob container
+async fn merge_case_timelines(local_storage: &Path, out_link: &String, tool_path: &Path, log_name: &Path) {
+    let out_folder = local_storage.join(merge::CASE_TIMELINE_FOLDER);
+    let timelines = merge::find_timelines(local_storage);
+    print_log(
+        format!("[ ] Merging {} host timelines into {}", timelines.len(), out_folder.display()).as_str(),
+        log_name,
+        true
+    );
+    match merge::merge(&timelines, &out_folder) {
+        Ok(report) => {
+            print_log(
+                format!("[+] Case timeline written with {} events, {} duplicates removed:\n{}", report.events, report.duplicates, report.table()).as_str(),
+                log_name,
+                true
+            );
+            upload_file(&out_folder, out_link, tool_path, log_name).await;
+        },
+        Err(e) => print_log(format!("[!] Unable to merge the timelines. Error: {e}").as_str(), log_name, true),
+    }
+}
+
+/// Report the IOC findings of the hosts processed to the local storage in one case IOC
+/// report, then upload it beside the hosts' wiskess output
+/// # Arguments
+/// * `local_storage` - the folder where the wiskess output of each host is stored locally
+/// * `out_link` - the URL to the S3 bucket or Azure Blob container
+async fn report_case_iocs(local_storage: &Path, out_link: &String, tool_path: &Path, log_name: &Path) {
+    let out_folder = local_storage.join(report::CASE_IOC_FOLDER);
+    let outputs = report::find_outputs(local_storage);
+    print_log(
+        format!("[ ] Reporting the IOC findings of {} hosts to {}", outputs.len(), out_folder.display()).as_str(),
+        log_name,
+        true
+    );
+    match report::write_report(&outputs, &out_folder) {
+        Ok(report) => {
+            print_log(
+                format!("[+] Case IOC report written with {} IOCs:\n{}", report.iocs.len(), report.table()).as_str(),
+                log_name,
+                true
+            );
+            upload_file(&out_folder, out_link, tool_path, log_name).await;
+        },
+        Err(e) => print_log(format!("[!] Unable to report the IOC findings. Error: {e}").as_str(), log_name, true),
+    }
+}